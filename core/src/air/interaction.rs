@@ -1,6 +1,7 @@
 use crate::lookup::InteractionKind;
 
 /// An interaction is a cross-table lookup.
+#[derive(Debug, Clone)]
 pub struct AirInteraction<E> {
     pub values: Vec<E>,
     pub multiplicity: E,
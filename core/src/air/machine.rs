@@ -2,7 +2,7 @@ use p3_air::BaseAir;
 use p3_field::Field;
 use p3_matrix::dense::RowMajorMatrix;
 
-use crate::{runtime::Program, stark::MachineRecord};
+use crate::stark::MachineRecord;
 
 pub use sp1_derive::MachineAir;
 
@@ -20,18 +20,33 @@ pub trait MachineAir<F: Field>: BaseAir<F> {
     ///    the record such as byte lookup requests.
     fn generate_trace(&self, input: &Self::Record, output: &mut Self::Record) -> RowMajorMatrix<F>;
 
+    /// The number of rows of the trace generated for `input`, before it is padded.
+    fn num_rows(&self, input: &Self::Record) -> usize;
+
     /// Generate the dependencies for a given execution record.
     fn generate_dependencies(&self, input: &Self::Record, output: &mut Self::Record) {
         self.generate_trace(input, output);
     }
 
+    /// The phase in which the dependencies of this AIR are generated.
+    ///
+    /// The events of an AIR may be emitted by the dependencies of the AIRs of earlier phases, so
+    /// the phases run in order, while the AIRs of a phase generate their dependencies in parallel.
+    fn dependency_phase(&self) -> usize {
+        0
+    }
+
     /// The number of preprocessed columns in the trace.
     fn preprocessed_width(&self) -> usize {
         0
     }
 
+    /// Generate the preprocessed trace for a given program, which the verifying key commits to.
     #[allow(unused_variables)]
-    fn generate_preprocessed_trace(&self, program: &Program) -> Option<RowMajorMatrix<F>> {
+    fn generate_preprocessed_trace(
+        &self,
+        program: &<Self::Record as MachineRecord>::Program,
+    ) -> Option<RowMajorMatrix<F>> {
         None
     }
 
@@ -47,6 +47,11 @@ impl<F: PrimeField> MachineAir<F> for AddChip {
         "Add".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The ALU events are emitted by the CPU and the DivRem chip.
+        2
+    }
+
     #[instrument(name = "generate add trace", level = "debug", skip_all)]
     fn generate_trace(
         &self,
@@ -92,6 +97,10 @@ impl<F: PrimeField> MachineAir<F> for AddChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.add_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.add_events.is_empty()
     }
@@ -11,7 +11,7 @@ use crate::air::MachineAir;
 use crate::air::{SP1AirBuilder, Word};
 use crate::bytes::{ByteLookupEvent, ByteOpcode};
 use crate::runtime::{ExecutionRecord, Opcode};
-use crate::utils::pad_to_power_of_two;
+use crate::utils::{pad_to_power_of_two, par_generate_rows};
 
 /// The number of main trace columns for `BitwiseChip`.
 pub const NUM_BITWISE_COLS: usize = size_of::<BitwiseCols<u8>>();
@@ -49,6 +49,11 @@ impl<F: PrimeField> MachineAir<F> for BitwiseChip {
         "Bitwise".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The ALU events are emitted by the CPU and the DivRem chip.
+        2
+    }
+
     #[instrument(name = "generate bitwise trace", level = "debug", skip_all)]
     fn generate_trace(
         &self,
@@ -56,38 +61,34 @@ impl<F: PrimeField> MachineAir<F> for BitwiseChip {
         output: &mut ExecutionRecord,
     ) -> RowMajorMatrix<F> {
         // Generate the trace rows for each event.
-        let rows = input
-            .bitwise_events
-            .iter()
-            .map(|event| {
-                let mut row = [F::zero(); NUM_BITWISE_COLS];
-                let cols: &mut BitwiseCols<F> = row.as_mut_slice().borrow_mut();
-                let a = event.a.to_le_bytes();
-                let b = event.b.to_le_bytes();
-                let c = event.c.to_le_bytes();
-
-                cols.a = Word::from(event.a);
-                cols.b = Word::from(event.b);
-                cols.c = Word::from(event.c);
-
-                cols.is_xor = F::from_bool(event.opcode == Opcode::XOR);
-                cols.is_or = F::from_bool(event.opcode == Opcode::OR);
-                cols.is_and = F::from_bool(event.opcode == Opcode::AND);
-
-                for ((b_a, b_b), b_c) in a.into_iter().zip(b).zip(c) {
-                    let byte_event = ByteLookupEvent {
-                        opcode: ByteOpcode::from(event.opcode),
-                        a1: b_a as u32,
-                        a2: 0,
-                        b: b_b as u32,
-                        c: b_c as u32,
-                    };
-                    output.add_byte_lookup_event(byte_event);
-                }
-
-                row
-            })
-            .collect::<Vec<_>>();
+        let rows = par_generate_rows(&input.bitwise_events, output, |event, output| {
+            let mut row = [F::zero(); NUM_BITWISE_COLS];
+            let cols: &mut BitwiseCols<F> = row.as_mut_slice().borrow_mut();
+            let a = event.a.to_le_bytes();
+            let b = event.b.to_le_bytes();
+            let c = event.c.to_le_bytes();
+
+            cols.a = Word::from(event.a);
+            cols.b = Word::from(event.b);
+            cols.c = Word::from(event.c);
+
+            cols.is_xor = F::from_bool(event.opcode == Opcode::XOR);
+            cols.is_or = F::from_bool(event.opcode == Opcode::OR);
+            cols.is_and = F::from_bool(event.opcode == Opcode::AND);
+
+            for ((b_a, b_b), b_c) in a.into_iter().zip(b).zip(c) {
+                let byte_event = ByteLookupEvent {
+                    opcode: ByteOpcode::from(event.opcode),
+                    a1: b_a as u32,
+                    a2: 0,
+                    b: b_b as u32,
+                    c: b_c as u32,
+                };
+                output.add_byte_lookup_event(byte_event);
+            }
+
+            row
+        });
 
         // Convert the trace to a row major matrix.
         let mut trace = RowMajorMatrix::new(
@@ -101,6 +102,10 @@ impl<F: PrimeField> MachineAir<F> for BitwiseChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.bitwise_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.bitwise_events.is_empty()
     }
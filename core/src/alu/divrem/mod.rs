@@ -81,7 +81,7 @@ use crate::bytes::{ByteLookupEvent, ByteOpcode};
 use crate::disassembler::WORD_SIZE;
 use crate::operations::{IsEqualWordOperation, IsZeroWordOperation};
 use crate::runtime::{ExecutionRecord, Opcode};
-use crate::utils::pad_to_power_of_two;
+use crate::utils::{pad_to_power_of_two, par_generate_rows};
 
 /// The number of main trace columns for `DivRemChip`.
 pub const NUM_DIVREM_COLS: usize = size_of::<DivRemCols<u8>>();
@@ -188,6 +188,11 @@ impl<F: PrimeField> MachineAir<F> for DivRemChip {
         "DivRem".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The DIV and REM events are emitted by the CPU.
+        1
+    }
+
     #[instrument(name = "generate divrem trace", level = "debug", skip_all)]
     fn generate_trace(
         &self,
@@ -195,9 +200,7 @@ impl<F: PrimeField> MachineAir<F> for DivRemChip {
         output: &mut ExecutionRecord,
     ) -> RowMajorMatrix<F> {
         // Generate the trace rows for each event.
-        let mut rows: Vec<[F; NUM_DIVREM_COLS]> = vec![];
-        let divrem_events = input.divrem_events.clone();
-        for event in divrem_events.iter() {
+        let rows = par_generate_rows(&input.divrem_events, output, |event, output| {
             assert!(
                 event.opcode == Opcode::DIVU
                     || event.opcode == Opcode::REMU
@@ -364,8 +367,8 @@ impl<F: PrimeField> MachineAir<F> for DivRemChip {
                 }
             }
 
-            rows.push(row);
-        }
+            row
+        });
 
         // Convert the trace to a row major matrix.
         let mut trace = RowMajorMatrix::new(
@@ -399,6 +402,10 @@ impl<F: PrimeField> MachineAir<F> for DivRemChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.divrem_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.divrem_events.is_empty()
     }
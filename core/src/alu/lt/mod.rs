@@ -80,6 +80,11 @@ impl<F: PrimeField> MachineAir<F> for LtChip {
         "Lt".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The ALU events are emitted by the CPU and the DivRem chip.
+        2
+    }
+
     fn generate_dependencies(&self, _input: &ExecutionRecord, _output: &mut ExecutionRecord) {}
 
     #[instrument(name = "generate lt trace", level = "debug", skip_all)]
@@ -172,6 +177,10 @@ impl<F: PrimeField> MachineAir<F> for LtChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.lt_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.lt_events.is_empty()
     }
@@ -117,6 +117,11 @@ impl<F: PrimeField> MachineAir<F> for MulChip {
         "Mul".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The ALU events are emitted by the CPU and the DivRem chip.
+        2
+    }
+
     #[instrument(name = "generate mul trace", level = "debug", skip_all)]
     fn generate_trace(
         &self,
@@ -252,6 +257,10 @@ impl<F: PrimeField> MachineAir<F> for MulChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.mul_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.mul_events.is_empty()
     }
@@ -44,7 +44,7 @@ use crate::air::MachineAir;
 use crate::air::{SP1AirBuilder, Word};
 use crate::disassembler::WORD_SIZE;
 use crate::runtime::{ExecutionRecord, Opcode};
-use crate::utils::pad_to_power_of_two;
+use crate::utils::{pad_to_power_of_two, par_generate_rows};
 
 /// The number of main trace columns for `ShiftLeft`.
 pub const NUM_SHIFT_LEFT_COLS: usize = size_of::<ShiftLeftCols<u8>>();
@@ -97,6 +97,11 @@ impl<F: PrimeField> MachineAir<F> for ShiftLeft {
         "ShiftLeft".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The ALU events are emitted by the CPU and the DivRem chip.
+        2
+    }
+
     #[instrument(name = "generate sll trace", level = "debug", skip_all)]
     fn generate_trace(
         &self,
@@ -104,9 +109,7 @@ impl<F: PrimeField> MachineAir<F> for ShiftLeft {
         output: &mut ExecutionRecord,
     ) -> RowMajorMatrix<F> {
         // Generate the trace rows for each event.
-        let mut rows: Vec<[F; NUM_SHIFT_LEFT_COLS]> = vec![];
-        let shift_left_events = input.shift_left_events.clone();
-        for event in shift_left_events.iter() {
+        let rows = par_generate_rows(&input.shift_left_events, output, |event, output| {
             let mut row = [F::zero(); NUM_SHIFT_LEFT_COLS];
             let cols: &mut ShiftLeftCols<F> = row.as_mut_slice().borrow_mut();
             let a = event.a.to_le_bytes();
@@ -162,8 +165,8 @@ impl<F: PrimeField> MachineAir<F> for ShiftLeft {
                 );
             }
 
-            rows.push(row);
-        }
+            row
+        });
 
         // Convert the trace to a row major matrix.
         let mut trace = RowMajorMatrix::new(
@@ -192,6 +195,10 @@ impl<F: PrimeField> MachineAir<F> for ShiftLeft {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.shift_left_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.shift_left_events.is_empty()
     }
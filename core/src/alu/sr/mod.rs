@@ -60,7 +60,7 @@ use crate::bytes::utils::shr_carry;
 use crate::bytes::{ByteLookupEvent, ByteOpcode};
 use crate::disassembler::WORD_SIZE;
 use crate::runtime::{ExecutionRecord, Opcode};
-use crate::utils::pad_to_power_of_two;
+use crate::utils::{pad_to_power_of_two, par_generate_rows};
 
 /// The number of main trace columns for `ShiftRightChip`.
 pub const NUM_SHIFT_RIGHT_COLS: usize = size_of::<ShiftRightCols<u8>>();
@@ -129,6 +129,11 @@ impl<F: PrimeField> MachineAir<F> for ShiftRightChip {
         "ShiftRight".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The ALU events are emitted by the CPU and the DivRem chip.
+        2
+    }
+
     #[instrument(name = "generate sr trace", level = "debug", skip_all)]
     fn generate_trace(
         &self,
@@ -136,9 +141,7 @@ impl<F: PrimeField> MachineAir<F> for ShiftRightChip {
         output: &mut ExecutionRecord,
     ) -> RowMajorMatrix<F> {
         // Generate the trace rows for each event.
-        let mut rows: Vec<[F; NUM_SHIFT_RIGHT_COLS]> = Vec::new();
-        let sr_events = input.shift_right_events.clone();
-        for event in sr_events.iter() {
+        let rows = par_generate_rows(&input.shift_right_events, output, |event, output| {
             assert!(event.opcode == Opcode::SRL || event.opcode == Opcode::SRA);
             let mut row = [F::zero(); NUM_SHIFT_RIGHT_COLS];
             let cols: &mut ShiftRightCols<F> = row.as_mut_slice().borrow_mut();
@@ -238,8 +241,8 @@ impl<F: PrimeField> MachineAir<F> for ShiftRightChip {
                 output.add_u8_range_checks(&shr_carry_output_shifted_byte);
             }
 
-            rows.push(row);
-        }
+            row
+        });
 
         // Convert the trace to a row major matrix.
         let mut trace = RowMajorMatrix::new(
@@ -269,6 +272,10 @@ impl<F: PrimeField> MachineAir<F> for ShiftRightChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.shift_right_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.shift_right_events.is_empty()
     }
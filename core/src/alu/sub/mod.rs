@@ -12,7 +12,7 @@ use tracing::instrument;
 use crate::air::MachineAir;
 use crate::air::{SP1AirBuilder, Word};
 use crate::runtime::{ExecutionRecord, Opcode};
-use crate::utils::pad_to_power_of_two;
+use crate::utils::{pad_to_power_of_two, par_generate_rows};
 
 /// The number of main trace columns for `SubChip`.
 pub const NUM_SUB_COLS: usize = size_of::<SubCols<u8>>();
@@ -47,6 +47,11 @@ impl<F: PrimeField> MachineAir<F> for SubChip {
         "Sub".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The ALU events are emitted by the CPU and the DivRem chip.
+        2
+    }
+
     #[instrument(name = "generate sub trace", level = "debug", skip_all)]
     fn generate_trace(
         &self,
@@ -54,8 +59,7 @@ impl<F: PrimeField> MachineAir<F> for SubChip {
         output: &mut ExecutionRecord,
     ) -> RowMajorMatrix<F> {
         // Generate the trace rows for each event.
-        let mut rows: Vec<[F; NUM_SUB_COLS]> = vec![];
-        for event in input.sub_events.iter() {
+        let rows = par_generate_rows(&input.sub_events, output, |event, output| {
             let mut row = [F::zero(); NUM_SUB_COLS];
             let cols: &mut SubCols<F> = row.as_mut_slice().borrow_mut();
             let a = event.a.to_le_bytes();
@@ -89,8 +93,8 @@ impl<F: PrimeField> MachineAir<F> for SubChip {
                 output.add_u8_range_checks(&b);
                 output.add_u8_range_checks(&c);
             }
-            rows.push(row);
-        }
+            row
+        });
 
         // Convert the trace to a row major matrix.
         let mut trace =
@@ -102,6 +106,10 @@ impl<F: PrimeField> MachineAir<F> for SubChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.sub_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.sub_events.is_empty()
     }
@@ -0,0 +1,42 @@
+//! A worker of a [`DistributedProver`](sp1_core::stark::DistributedProver), proving the shards of
+//! a program with the BabyBear Blake3 configuration.
+//!
+//! Once it listens, the worker prints its address to the standard output, e.g. to find the port
+//! it was given when bound to port 0.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use clap::Parser;
+use sp1_core::runtime::Program;
+use sp1_core::stark::{serve_worker, RiscvAir, WorkerAddress};
+use sp1_core::utils::{setup_logger, BabyBearBlake3};
+
+#[derive(Parser)]
+#[command(about = "Prove the shards sent by the coordinator of a distributed prover.")]
+struct WorkerArgs {
+    /// The address to listen on, as `host:port` or `unix:path`.
+    #[arg(long)]
+    listen: WorkerAddress,
+
+    /// The ELF of the program proven by the coordinator.
+    #[arg(long)]
+    elf: PathBuf,
+}
+
+fn main() -> io::Result<()> {
+    setup_logger();
+    let args = WorkerArgs::parse();
+    let program = Program::from(fs::read(&args.elf)?.as_slice());
+
+    let machine = RiscvAir::machine(BabyBearBlake3::new());
+    let (pk, _) = machine.setup(&program);
+
+    let listener = args.listen.bind()?;
+    let mut stdout = io::stdout();
+    writeln!(stdout, "{}", listener.local_addr()?)?;
+    stdout.flush()?;
+
+    serve_worker(&machine, &pk, &listener)
+}
@@ -13,6 +13,11 @@ impl<F: Field> MachineAir<F> for ByteChip<F> {
         "Byte".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The byte lookups are emitted by every other chip.
+        3
+    }
+
     fn generate_trace(
         &self,
         input: &ExecutionRecord,
@@ -32,6 +37,10 @@ impl<F: Field> MachineAir<F> for ByteChip<F> {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        NUM_ROWS
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.byte_lookups.is_empty()
     }
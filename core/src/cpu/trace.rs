@@ -9,13 +9,11 @@ use crate::field::event::FieldEvent;
 use crate::memory::MemoryCols;
 use crate::runtime::MemoryRecordEnum;
 use crate::runtime::{ExecutionRecord, Opcode};
-use hashbrown::HashMap;
+use crate::utils::par_generate_rows;
 use p3_field::PrimeField;
 use p3_matrix::dense::RowMajorMatrix;
-use p3_maybe_rayon::prelude::IntoParallelRefIterator;
-use p3_maybe_rayon::prelude::ParallelIterator;
-use p3_maybe_rayon::prelude::ParallelSlice;
 use std::borrow::BorrowMut;
+use std::collections::BTreeMap;
 use tracing::instrument;
 
 impl<F: PrimeField> MachineAir<F> for CpuChip {
@@ -31,40 +29,14 @@ impl<F: PrimeField> MachineAir<F> for CpuChip {
         input: &ExecutionRecord,
         output: &mut ExecutionRecord,
     ) -> RowMajorMatrix<F> {
-        let mut new_alu_events = HashMap::new();
-        let mut new_blu_events = Vec::new();
-        let mut new_field_events: Vec<FieldEvent> = Vec::new();
-
         // Generate the trace rows for each event.
-        let rows_with_events = input
-            .cpu_events
-            .par_iter()
-            .map(|op: &CpuEvent| self.event_to_row::<F>(*op))
-            .collect::<Vec<_>>();
-
-        let mut rows = Vec::<F>::new();
-        rows_with_events.into_iter().for_each(|row_with_events| {
-            let (row, alu_events, blu_events, field_events) = row_with_events;
-            rows.extend(row);
-            for (key, value) in alu_events {
-                new_alu_events
-                    .entry(key)
-                    .and_modify(|op_new_events: &mut Vec<AluEvent>| {
-                        op_new_events.extend(value.clone())
-                    })
-                    .or_insert(value);
-            }
-            new_blu_events.extend(blu_events);
-            new_field_events.extend(field_events);
+        let rows = par_generate_rows(&input.cpu_events, output, |event, output| {
+            self.event_to_row_with_dependencies::<F>(event, output)
         });
 
-        // Add the dependency events to the shard.
-        output.add_alu_events(new_alu_events);
-        output.add_byte_lookup_events(new_blu_events);
-        output.add_field_events(&new_field_events);
-
         // Convert the trace to a row major matrix.
-        let mut trace = RowMajorMatrix::new(rows, NUM_CPU_COLS);
+        let mut trace =
+            RowMajorMatrix::new(rows.into_iter().flatten().collect::<Vec<_>>(), NUM_CPU_COLS);
 
         // Pad the trace to a power of two.
         Self::pad_to_power_of_two::<F>(&mut trace.values);
@@ -72,37 +44,15 @@ impl<F: PrimeField> MachineAir<F> for CpuChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.cpu_events.len()
+    }
+
     #[instrument(name = "generate cpu dependencies", level = "debug", skip_all)]
     fn generate_dependencies(&self, input: &ExecutionRecord, output: &mut ExecutionRecord) {
-        // Generate the trace rows for each event.
-        let chunk_size = std::cmp::max(input.cpu_events.len() / num_cpus::get(), 1);
-        let events = input
-            .cpu_events
-            .par_chunks(chunk_size)
-            .map(|ops: &[CpuEvent]| {
-                let mut alu = HashMap::new();
-                let mut blu: Vec<_> = Vec::default();
-                let mut field: Vec<_> = Vec::default();
-                ops.iter().for_each(|op| {
-                    let (_, alu_events, blu_events, field_events) = self.event_to_row::<F>(*op);
-                    alu_events.into_iter().for_each(|(key, value)| {
-                        alu.entry(key).or_insert(Vec::default()).extend(value);
-                    });
-                    blu.extend(blu_events);
-                    field.extend(field_events);
-                });
-                (alu, blu, field)
-            })
-            .collect::<Vec<_>>();
-
-        events
-            .into_iter()
-            .for_each(|(alu_events, blu_events, field_events)| {
-                // Add the dependency events to the shard.
-                output.add_alu_events(alu_events);
-                output.add_byte_lookup_events(blu_events);
-                output.add_field_events(&field_events);
-            });
+        par_generate_rows(&input.cpu_events, output, |event, output| {
+            self.event_to_row_with_dependencies::<F>(event, output);
+        });
     }
 
     fn included(&self, _: &Self::Record) -> bool {
@@ -111,17 +61,30 @@ impl<F: PrimeField> MachineAir<F> for CpuChip {
 }
 
 impl CpuChip {
+    /// Create a row from an event, adding the events it emits for other chips to `output`.
+    fn event_to_row_with_dependencies<F: PrimeField>(
+        &self,
+        event: &CpuEvent,
+        output: &mut ExecutionRecord,
+    ) -> [F; NUM_CPU_COLS] {
+        let (row, alu_events, blu_events, field_events) = self.event_to_row::<F>(*event);
+        output.add_alu_events(alu_events);
+        output.add_byte_lookup_events(blu_events);
+        output.add_field_events(&field_events);
+        row
+    }
+
     /// Create a row from an event.
     fn event_to_row<F: PrimeField>(
         &self,
         event: CpuEvent,
     ) -> (
         [F; NUM_CPU_COLS],
-        HashMap<Opcode, Vec<alu::AluEvent>>,
+        BTreeMap<Opcode, Vec<alu::AluEvent>>,
         Vec<ByteLookupEvent>,
         Vec<FieldEvent>,
     ) {
-        let mut new_alu_events = HashMap::new();
+        let mut new_alu_events = BTreeMap::new();
         let mut new_blu_events = Vec::new();
         let mut new_field_events = Vec::new();
 
@@ -175,7 +138,7 @@ impl CpuChip {
         &self,
         cols: &mut CpuCols<F>,
         event: CpuEvent,
-        new_alu_events: &mut HashMap<Opcode, Vec<alu::AluEvent>>,
+        new_alu_events: &mut BTreeMap<Opcode, Vec<alu::AluEvent>>,
         new_blu_events: &mut Vec<ByteLookupEvent>,
     ) {
         if !matches!(
@@ -297,7 +260,7 @@ impl CpuChip {
         &self,
         cols: &mut CpuCols<F>,
         event: CpuEvent,
-        alu_events: &mut HashMap<Opcode, Vec<alu::AluEvent>>,
+        alu_events: &mut BTreeMap<Opcode, Vec<alu::AluEvent>>,
     ) {
         if event.instruction.is_branch_instruction() {
             let branch_columns = cols.opcode_specific_columns.branch_mut();
@@ -392,7 +355,7 @@ impl CpuChip {
         &self,
         cols: &mut CpuCols<F>,
         event: CpuEvent,
-        alu_events: &mut HashMap<Opcode, Vec<alu::AluEvent>>,
+        alu_events: &mut BTreeMap<Opcode, Vec<alu::AluEvent>>,
     ) {
         if event.instruction.is_jump_instruction() {
             let jump_columns = cols.opcode_specific_columns.jump_mut();
@@ -443,7 +406,7 @@ impl CpuChip {
         &self,
         cols: &mut CpuCols<F>,
         event: CpuEvent,
-        alu_events: &mut HashMap<Opcode, Vec<alu::AluEvent>>,
+        alu_events: &mut BTreeMap<Opcode, Vec<alu::AluEvent>>,
     ) {
         if matches!(event.instruction.opcode, Opcode::AUIPC) {
             let auipc_columns = cols.opcode_specific_columns.auipc_mut();
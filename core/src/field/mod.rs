@@ -54,6 +54,11 @@ impl<F: PrimeField> MachineAir<F> for FieldLtuChip {
         "FieldLTU".to_string()
     }
 
+    fn dependency_phase(&self) -> usize {
+        // The field events are emitted by the CPU and the precompiles.
+        3
+    }
+
     fn generate_dependencies(&self, _input: &ExecutionRecord, _output: &mut ExecutionRecord) {}
 
     #[instrument(name = "generate field ltu trace", level = "debug", skip_all)]
@@ -94,6 +99,10 @@ impl<F: PrimeField> MachineAir<F> for FieldLtuChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.field_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.field_events.is_empty()
     }
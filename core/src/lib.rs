@@ -41,7 +41,10 @@ use serde::{Deserialize, Serialize};
 use stark::StarkGenericConfig;
 use stark::{OpeningProof, ProgramVerificationError, Proof, ShardMainData};
 use std::fs;
-use utils::{prove_core, BabyBearBlake3, StarkUtils};
+use utils::{
+    prove_core, prove_core_with_monitor, BabyBearBlake3, ProgressMonitor, ProverOptions,
+    RuntimeOptions, StarkUtils,
+};
 
 /// A prover that can prove RISCV ELFs.
 pub struct SP1Prover;
@@ -62,23 +65,49 @@ impl SP1Prover {
     /// Executes the elf with the given inputs and returns the output.
     pub fn execute(elf: &[u8], stdin: SP1Stdin) -> Result<SP1Stdout> {
         let program = Program::from(elf);
-        let mut runtime = Runtime::new(program);
+        let mut runtime = Runtime::with_options(program, RuntimeOptions::from_env());
         runtime.write_stdin_slice(&stdin.buffer.data);
         runtime.run();
         Ok(SP1Stdout::from(&runtime.state.output_stream))
     }
 
     /// Generate a proof for the execution of the ELF with the given public inputs.
+    ///
+    /// The options are read from the environment, see [`ProverOptions::from_env`].
     pub fn prove(elf: &[u8], stdin: SP1Stdin) -> Result<SP1ProofWithIO<BabyBearBlake3>> {
+        Self::prove_with_options(elf, stdin, &ProverOptions::from_env())
+    }
+
+    /// Generate a proof for the execution of the ELF with the given public inputs and options.
+    pub fn prove_with_options(
+        elf: &[u8],
+        stdin: SP1Stdin,
+        options: &ProverOptions,
+    ) -> Result<SP1ProofWithIO<BabyBearBlake3>> {
+        Self::prove_with_monitor(elf, stdin, options, &ProgressMonitor::default())
+    }
+
+    /// Proves the execution of a program, reporting the progress of each phase to `monitor`.
+    ///
+    /// Once the cancellation of `monitor` is requested, the proof stops at the next shard or chip
+    /// and a [`ProverError::Cancelled`](stark::ProverError::Cancelled) error is returned.
+    pub fn prove_with_monitor(
+        elf: &[u8],
+        stdin: SP1Stdin,
+        options: &ProverOptions,
+        monitor: &ProgressMonitor,
+    ) -> Result<SP1ProofWithIO<BabyBearBlake3>> {
         let program = Program::from(elf);
-        let mut runtime = Runtime::new(program);
+        let mut runtime = Runtime::with_options(program, options.runtime_options());
+        runtime.monitor = monitor.clone();
         runtime.write_stdin_slice(&stdin.buffer.data);
         tracing::info_span!("execute").in_scope(|| {
             runtime.run();
         });
+        monitor.check()?;
         let config = BabyBearBlake3::new();
         let stdout = SP1Stdout::from(&runtime.state.output_stream);
-        let proof = prove_core(config, runtime);
+        let proof = prove_core_with_monitor(config, runtime, options, monitor)?;
         Ok(SP1ProofWithIO {
             proof,
             stdin,
@@ -101,12 +130,13 @@ impl SP1Prover {
         ShardMainData<SC>: Serialize + DeserializeOwned,
         <SC as StarkGenericConfig>::Val: p3_field::PrimeField32,
     {
+        let options = ProverOptions::from_env();
         let program = Program::from(elf);
-        let mut runtime = Runtime::new(program);
+        let mut runtime = Runtime::with_options(program, options.runtime_options());
         runtime.write_stdin_slice(&stdin.buffer.data);
         runtime.run();
         let stdout = SP1Stdout::from(&runtime.state.output_stream);
-        let proof = prove_core(config, runtime);
+        let proof = prove_core(config, runtime, &options);
         Ok(SP1ProofWithIO {
             proof,
             stdin,
@@ -1,34 +1,39 @@
 use crate::air::{AirInteraction, MessageBuilder};
-use p3_air::{AirBuilder, PairCol, VirtualPairCol};
+use p3_air::{AirBuilder, PairBuilder, PairCol, VirtualPairCol};
 use p3_field::Field;
-use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
 use p3_uni_stark::{SymbolicExpression, SymbolicVariable};
 
 use super::Interaction;
 
-/// A builder for the lookup table interactions.
+/// A builder for the lookup table interactions, which also records the degree of the constraints.
 pub struct InteractionBuilder<F: Field> {
+    preprocessed: RowMajorMatrix<SymbolicVariable<F>>,
     main: RowMajorMatrix<SymbolicVariable<F>>,
     sends: Vec<Interaction<F>>,
     receives: Vec<Interaction<F>>,
+    max_constraint_degree: usize,
 }
 
 impl<F: Field> InteractionBuilder<F> {
-    /// Creates a new `InteractionBuilder` with the given width.
-    pub fn new(width: usize) -> Self {
-        let values = [false, true]
-            .into_iter()
-            .flat_map(|is_next| {
-                (0..width).map(move |column| SymbolicVariable::new(is_next, column))
-            })
-            .collect();
+    /// Creates a new `InteractionBuilder` with the given preprocessed and main widths.
+    ///
+    /// The preprocessed columns are numbered after the main columns.
+    pub fn new(preprocessed_width: usize, width: usize) -> Self {
         Self {
-            main: RowMajorMatrix::new(values, width),
+            preprocessed: symbolic_columns(width, preprocessed_width),
+            main: symbolic_columns(0, width),
             sends: vec![],
             receives: vec![],
+            max_constraint_degree: 0,
         }
     }
 
+    /// Returns the maximum degree of the constraints asserted so far.
+    pub const fn max_constraint_degree(&self) -> usize {
+        self.max_constraint_degree
+    }
+
     /// Returns the sends and receives.
     pub fn interactions(self) -> (Vec<Interaction<F>>, Vec<Interaction<F>>) {
         (self.sends, self.receives)
@@ -61,7 +66,16 @@ impl<F: Field> AirBuilder for InteractionBuilder<F> {
         }
     }
 
-    fn assert_zero<I: Into<Self::Expr>>(&mut self, _x: I) {}
+    fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
+        let degree = x.into().degree_multiple();
+        self.max_constraint_degree = self.max_constraint_degree.max(degree);
+    }
+}
+
+impl<F: Field> PairBuilder for InteractionBuilder<F> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed.clone()
+    }
 }
 
 impl<F: Field> MessageBuilder<AirInteraction<SymbolicExpression<F>>> for InteractionBuilder<F> {
@@ -69,10 +83,10 @@ impl<F: Field> MessageBuilder<AirInteraction<SymbolicExpression<F>>> for Interac
         let values = message
             .values
             .into_iter()
-            .map(|v| symbolic_to_virtual_pair(&v))
+            .map(|v| symbolic_to_virtual_pair(&v, self.main.width()))
             .collect::<Vec<_>>();
 
-        let multiplicity = symbolic_to_virtual_pair(&message.multiplicity);
+        let multiplicity = symbolic_to_virtual_pair(&message.multiplicity, self.main.width());
 
         self.sends
             .push(Interaction::new(values, multiplicity, message.kind));
@@ -82,22 +96,41 @@ impl<F: Field> MessageBuilder<AirInteraction<SymbolicExpression<F>>> for Interac
         let values = message
             .values
             .into_iter()
-            .map(|v| symbolic_to_virtual_pair(&v))
+            .map(|v| symbolic_to_virtual_pair(&v, self.main.width()))
             .collect::<Vec<_>>();
 
-        let multiplicity = symbolic_to_virtual_pair(&message.multiplicity);
+        let multiplicity = symbolic_to_virtual_pair(&message.multiplicity, self.main.width());
 
         self.receives
             .push(Interaction::new(values, multiplicity, message.kind));
     }
 }
 
-fn symbolic_to_virtual_pair<F: Field>(expression: &SymbolicExpression<F>) -> VirtualPairCol<F> {
+/// The symbolic variables of the local and next rows of `width` columns, numbered from `offset`.
+pub(crate) fn symbolic_columns<F: Field>(
+    offset: usize,
+    width: usize,
+) -> RowMajorMatrix<SymbolicVariable<F>> {
+    let values = [false, true]
+        .into_iter()
+        .flat_map(|is_next| {
+            (offset..offset + width).map(move |column| SymbolicVariable::new(is_next, column))
+        })
+        .collect();
+    RowMajorMatrix::new(values, width)
+}
+
+/// Converts an affine expression over the local row into a [`VirtualPairCol`], where the columns
+/// from `width` on are the preprocessed columns.
+fn symbolic_to_virtual_pair<F: Field>(
+    expression: &SymbolicExpression<F>,
+    width: usize,
+) -> VirtualPairCol<F> {
     if expression.degree_multiple() > 1 {
         panic!("degree multiple is too high");
     }
 
-    let (column_weights, constant) = eval_symbolic_to_virtual_pair(expression);
+    let (column_weights, constant) = eval_symbolic_to_virtual_pair(expression, width);
 
     let column_weights = column_weights.into_iter().collect();
 
@@ -106,30 +139,35 @@ fn symbolic_to_virtual_pair<F: Field>(expression: &SymbolicExpression<F>) -> Vir
 
 fn eval_symbolic_to_virtual_pair<F: Field>(
     expression: &SymbolicExpression<F>,
+    width: usize,
 ) -> (Vec<(PairCol, F)>, F) {
     match expression {
         SymbolicExpression::Constant(c) => (vec![], *c),
-        SymbolicExpression::Variable(v) if !v.is_next => {
+        SymbolicExpression::Variable(v) if !v.is_next && v.column < width => {
             (vec![(PairCol::Main(v.column), F::one())], F::zero())
         }
+        SymbolicExpression::Variable(v) if !v.is_next => (
+            vec![(PairCol::Preprocessed(v.column - width), F::one())],
+            F::zero(),
+        ),
         SymbolicExpression::Add { x, y, .. } => {
-            let (v_l, c_l) = eval_symbolic_to_virtual_pair(x);
-            let (v_r, c_r) = eval_symbolic_to_virtual_pair(y);
+            let (v_l, c_l) = eval_symbolic_to_virtual_pair(x, width);
+            let (v_r, c_r) = eval_symbolic_to_virtual_pair(y, width);
             ([v_l, v_r].concat(), c_l + c_r)
         }
         SymbolicExpression::Sub { x, y, .. } => {
-            let (v_l, c_l) = eval_symbolic_to_virtual_pair(x);
-            let (v_r, c_r) = eval_symbolic_to_virtual_pair(y);
+            let (v_l, c_l) = eval_symbolic_to_virtual_pair(x, width);
+            let (v_r, c_r) = eval_symbolic_to_virtual_pair(y, width);
             let neg_v_r = v_r.iter().map(|(c, w)| (*c, -*w)).collect();
             ([v_l, neg_v_r].concat(), c_l - c_r)
         }
         SymbolicExpression::Neg { x, .. } => {
-            let (v, c) = eval_symbolic_to_virtual_pair(x);
+            let (v, c) = eval_symbolic_to_virtual_pair(x, width);
             (v.iter().map(|(c, w)| (*c, -*w)).collect(), -c)
         }
         SymbolicExpression::Mul { x, y, .. } => {
-            let (v_l, c_l) = eval_symbolic_to_virtual_pair(x);
-            let (v_r, c_r) = eval_symbolic_to_virtual_pair(y);
+            let (v_l, c_l) = eval_symbolic_to_virtual_pair(x, width);
+            let (v_r, c_r) = eval_symbolic_to_virtual_pair(y, width);
 
             let mut v = vec![];
             v.extend(v_l.iter().map(|(c, w)| (*c, *w * c_r)));
@@ -180,7 +218,7 @@ mod tests {
 
         let z = x + y;
 
-        let (column_weights, constant) = super::eval_symbolic_to_virtual_pair(&z);
+        let (column_weights, constant) = super::eval_symbolic_to_virtual_pair(&z, 2);
         println!("column_weights: {:?}", column_weights);
         println!("constant: {:?}", constant);
 
@@ -235,7 +273,7 @@ mod tests {
     fn test_lookup_interactions() {
         let air = LookupTestAir {};
 
-        let mut builder = InteractionBuilder::<BabyBear>::new(NUM_COLS);
+        let mut builder = InteractionBuilder::<BabyBear>::new(0, NUM_COLS);
 
         air.eval(&mut builder);
 
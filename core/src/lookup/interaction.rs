@@ -33,6 +33,18 @@ pub enum InteractionKind {
 
     /// Interaction with the field op table for field operations.
     Field = 7,
+
+    /// Passing the chaining value of a BLAKE3 tree node to its parent.
+    Blake3ChainingValue = 8,
+
+    /// Requesting a Poseidon2 permutation from the recursion VM's Poseidon2 table.
+    Poseidon2 = 9,
+
+    /// Requesting a bit decomposition from the recursion VM's NUM2BITS table.
+    Num2Bits = 10,
+
+    /// Exposing a public value at a given index to the verifier, which receives it.
+    PublicValues = 11,
 }
 
 impl InteractionKind {
@@ -45,6 +57,10 @@ impl InteractionKind {
             InteractionKind::Byte,
             InteractionKind::Range,
             InteractionKind::Field,
+            InteractionKind::Blake3ChainingValue,
+            InteractionKind::Poseidon2,
+            InteractionKind::Num2Bits,
+            InteractionKind::PublicValues,
         ]
     }
 }
@@ -88,6 +104,10 @@ impl Display for InteractionKind {
             InteractionKind::Byte => write!(f, "Byte"),
             InteractionKind::Range => write!(f, "Range"),
             InteractionKind::Field => write!(f, "Field"),
+            InteractionKind::Blake3ChainingValue => write!(f, "Blake3ChainingValue"),
+            InteractionKind::Poseidon2 => write!(f, "Poseidon2"),
+            InteractionKind::Num2Bits => write!(f, "Num2Bits"),
+            InteractionKind::PublicValues => write!(f, "PublicValues"),
         }
     }
 }
@@ -2,6 +2,7 @@ mod builder;
 mod debug;
 mod interaction;
 
+pub(crate) use builder::symbolic_columns;
 pub use builder::InteractionBuilder;
 pub use debug::*;
 pub use interaction::*;
@@ -3,6 +3,7 @@ use crate::air::{AirInteraction, SP1AirBuilder, Word};
 use crate::utils::pad_to_power_of_two;
 use p3_field::PrimeField;
 use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 
 use crate::runtime::ExecutionRecord;
 use core::borrow::{Borrow, BorrowMut};
@@ -58,9 +59,9 @@ impl<F: PrimeField> MachineAir<F> for MemoryGlobalChip {
             MemoryChipKind::Finalize => &input.last_memory_record,
             MemoryChipKind::Program => &input.program_memory_record,
         };
-        let rows: Vec<[F; 8]> = (0..memory_record.len()) // TODO: change this back to par_iter
-            .map(|i| {
-                let (addr, record, multiplicity) = memory_record[i];
+        let rows: Vec<[F; 8]> = memory_record
+            .par_iter()
+            .map(|&(addr, record, multiplicity)| {
                 let mut row = [F::zero(); NUM_MEMORY_INIT_COLS];
                 let cols: &mut MemoryInitCols<F> = row.as_mut_slice().borrow_mut();
                 cols.addr = F::from_canonical_u32(addr);
@@ -82,6 +83,14 @@ impl<F: PrimeField> MachineAir<F> for MemoryGlobalChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        match self.kind {
+            MemoryChipKind::Init => input.first_memory_record.len(),
+            MemoryChipKind::Finalize => input.last_memory_record.len(),
+            MemoryChipKind::Program => input.program_memory_record.len(),
+        }
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         match self.kind {
             MemoryChipKind::Init => !shard.first_memory_record.is_empty(),
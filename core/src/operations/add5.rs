@@ -9,8 +9,6 @@ use crate::runtime::ExecutionRecord;
 use p3_field::AbstractField;
 
 /// A set of columns needed to compute the sum of five words.
-///
-/// TODO: This is currently not in use, and thus not tested thoroughly yet.
 #[derive(AlignedBorrow, Default, Debug, Clone, Copy)]
 #[repr(C)]
 pub struct Add5Operation<T> {
@@ -156,7 +154,6 @@ impl<F: Field> Add5Operation<F> {
                 }
                 overflow -= cols.value[i].into();
 
-                overflow -= cols.carry[i] * base;
                 if i > 0 {
                     overflow += cols.carry[i - 1].into();
                 }
@@ -0,0 +1,107 @@
+use p3_air::AirBuilder;
+use p3_field::Field;
+use sp1_derive::AlignedBorrow;
+
+use crate::air::SP1AirBuilder;
+use crate::air::Word;
+use crate::disassembler::WORD_SIZE;
+use crate::runtime::ExecutionRecord;
+use p3_field::AbstractField;
+
+/// The number of bytes in a 64-bit word.
+pub const U64_SIZE: usize = 2 * WORD_SIZE;
+
+/// Splits a 64-bit value into its low and high words.
+pub fn u64_to_words<F: Field>(value: u64) -> [Word<F>; 2] {
+    [Word::from(value as u32), Word::from((value >> 32) as u32)]
+}
+
+/// Joins the low and high words of a 64-bit value.
+pub fn words_to_u64<F: Field>(words: &[Word<F>; 2]) -> u64 {
+    (words[0].to_u32() as u64) | ((words[1].to_u32() as u64) << 32)
+}
+
+/// A set of columns needed to compute the add of two 64-bit words.
+///
+/// A 64-bit word is represented by its low and high 32-bit words.
+#[derive(AlignedBorrow, Default, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct AddU64Operation<T> {
+    /// The result of `a + b`.
+    pub value: [Word<T>; 2],
+
+    /// Trace.
+    pub carry: [T; U64_SIZE - 1],
+}
+
+impl<F: Field> AddU64Operation<F> {
+    pub fn populate(&mut self, record: &mut ExecutionRecord, a_u64: u64, b_u64: u64) -> u64 {
+        let expected = a_u64.wrapping_add(b_u64);
+        self.value = u64_to_words(expected);
+        let a = a_u64.to_le_bytes();
+        let b = b_u64.to_le_bytes();
+
+        let mut carry = 0u32;
+        for i in 0..U64_SIZE - 1 {
+            carry = ((a[i] as u32) + (b[i] as u32) + carry) >> 8;
+            self.carry[i] = F::from_canonical_u32(carry);
+        }
+
+        // Range check
+        {
+            record.add_u8_range_checks(&a);
+            record.add_u8_range_checks(&b);
+            record.add_u8_range_checks(&expected.to_le_bytes());
+        }
+        expected
+    }
+
+    pub fn eval<AB: SP1AirBuilder>(
+        builder: &mut AB,
+        a: [Word<AB::Var>; 2],
+        b: [Word<AB::Var>; 2],
+        cols: AddU64Operation<AB::Var>,
+        is_real: AB::Var,
+    ) {
+        let one = AB::Expr::one();
+        let base = AB::F::from_canonical_u32(256);
+
+        let a_bytes: [AB::Var; U64_SIZE] =
+            core::array::from_fn(|i| a[i / WORD_SIZE][i % WORD_SIZE]);
+        let b_bytes: [AB::Var; U64_SIZE] =
+            core::array::from_fn(|i| b[i / WORD_SIZE][i % WORD_SIZE]);
+        let value_bytes: [AB::Var; U64_SIZE] =
+            core::array::from_fn(|i| cols.value[i / WORD_SIZE][i % WORD_SIZE]);
+
+        let mut builder_is_real = builder.when(is_real);
+
+        for i in 0..U64_SIZE {
+            // Assert that difference between the carried result and the non-carried result is
+            // either zero or the base.
+            let mut overflow: AB::Expr = a_bytes[i] + b_bytes[i] - value_bytes[i];
+            if i > 0 {
+                overflow += cols.carry[i - 1].into();
+            }
+            builder_is_real.assert_zero(overflow.clone() * (overflow.clone() - base));
+
+            if i < U64_SIZE - 1 {
+                // If the carry is one, then the overflow must be the base.
+                builder_is_real.assert_zero(cols.carry[i] * (overflow.clone() - base));
+
+                // If the carry is not one, then the overflow must be zero.
+                builder_is_real.assert_zero((cols.carry[i] - one.clone()) * overflow);
+
+                // Assert that the carry is either zero or one.
+                builder_is_real.assert_bool(cols.carry[i]);
+            }
+        }
+        builder_is_real.assert_bool(is_real);
+
+        // Range check each byte.
+        {
+            builder.slice_range_check_u8(&a_bytes, is_real);
+            builder.slice_range_check_u8(&b_bytes, is_real);
+            builder.slice_range_check_u8(&value_bytes, is_real);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use p3_field::AbstractField;
+use p3_field::Field;
+use sp1_derive::AlignedBorrow;
+
+use crate::air::SP1AirBuilder;
+use crate::air::Word;
+use crate::bytes::ByteLookupEvent;
+use crate::bytes::ByteOpcode;
+use crate::disassembler::WORD_SIZE;
+use crate::runtime::ExecutionRecord;
+
+use super::{u64_to_words, U64_SIZE};
+
+/// A set of columns needed to compute the and of two 64-bit words.
+#[derive(AlignedBorrow, Default, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct AndU64Operation<T> {
+    /// The result of `x & y`.
+    pub value: [Word<T>; 2],
+}
+
+impl<F: Field> AndU64Operation<F> {
+    pub fn populate(&mut self, record: &mut ExecutionRecord, x: u64, y: u64) -> u64 {
+        let expected = x & y;
+        let x_bytes = x.to_le_bytes();
+        let y_bytes = y.to_le_bytes();
+        for i in 0..U64_SIZE {
+            let and = x_bytes[i] & y_bytes[i];
+
+            let byte_event = ByteLookupEvent {
+                opcode: ByteOpcode::AND,
+                a1: and as u32,
+                a2: 0,
+                b: x_bytes[i] as u32,
+                c: y_bytes[i] as u32,
+            };
+            record.add_byte_lookup_event(byte_event);
+        }
+        self.value = u64_to_words(expected);
+        expected
+    }
+
+    pub fn eval<AB: SP1AirBuilder>(
+        builder: &mut AB,
+        a: [Word<AB::Var>; 2],
+        b: [Word<AB::Var>; 2],
+        cols: AndU64Operation<AB::Var>,
+        is_real: AB::Var,
+    ) {
+        for i in 0..U64_SIZE {
+            builder.send_byte(
+                AB::F::from_canonical_u32(ByteOpcode::AND as u32),
+                cols.value[i / WORD_SIZE][i % WORD_SIZE],
+                a[i / WORD_SIZE][i % WORD_SIZE],
+                b[i / WORD_SIZE][i % WORD_SIZE],
+                is_real,
+            );
+        }
+    }
+}
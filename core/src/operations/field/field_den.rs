@@ -222,6 +222,10 @@ mod tests {
             )
         }
 
+        fn num_rows(&self, _: &ExecutionRecord) -> usize {
+            1 << 8
+        }
+
         fn included(&self, _: &Self::Record) -> bool {
             true
         }
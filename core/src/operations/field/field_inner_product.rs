@@ -207,6 +207,10 @@ mod tests {
             trace
         }
 
+        fn num_rows(&self, _: &ExecutionRecord) -> usize {
+            1 << 8
+        }
+
         fn included(&self, _: &Self::Record) -> bool {
             true
         }
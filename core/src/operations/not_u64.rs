@@ -0,0 +1,55 @@
+use p3_air::AirBuilder;
+use p3_field::Field;
+use sp1_derive::AlignedBorrow;
+
+use crate::air::SP1AirBuilder;
+use crate::air::Word;
+use crate::bytes::ByteOpcode;
+use crate::disassembler::WORD_SIZE;
+use crate::runtime::ExecutionRecord;
+use p3_field::AbstractField;
+
+use super::{u64_to_words, U64_SIZE};
+
+/// A set of columns needed to compute the not of a 64-bit word.
+#[derive(AlignedBorrow, Default, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct NotU64Operation<T> {
+    /// The result of `!x`.
+    pub value: [Word<T>; 2],
+}
+
+impl<F: Field> NotU64Operation<F> {
+    pub fn populate(&mut self, record: &mut ExecutionRecord, x: u64) -> u64 {
+        let expected = !x;
+        self.value = u64_to_words(expected);
+        record.add_u8_range_checks(&x.to_le_bytes());
+        expected
+    }
+
+    pub fn eval<AB: SP1AirBuilder>(
+        builder: &mut AB,
+        a: [Word<AB::Var>; 2],
+        cols: NotU64Operation<AB::Var>,
+        is_real: AB::Var,
+    ) {
+        for i in (0..U64_SIZE).step_by(2) {
+            builder.send_byte_pair(
+                AB::F::from_canonical_u32(ByteOpcode::U8Range as u32),
+                AB::F::zero(),
+                AB::F::zero(),
+                a[i / WORD_SIZE][i % WORD_SIZE],
+                a[(i + 1) / WORD_SIZE][(i + 1) % WORD_SIZE],
+                is_real,
+            );
+        }
+
+        // For any byte b, b + !b = 0xFF.
+        for i in 0..U64_SIZE {
+            builder.when(is_real).assert_eq(
+                cols.value[i / WORD_SIZE][i % WORD_SIZE] + a[i / WORD_SIZE][i % WORD_SIZE],
+                AB::F::from_canonical_u8(u8::MAX),
+            );
+        }
+    }
+}
@@ -4,7 +4,7 @@ use p3_air::{Air, BaseAir};
 use p3_field::PrimeField;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::MatrixRowSlices;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use sp1_derive::AlignedBorrow;
 
@@ -53,7 +53,7 @@ impl<F: PrimeField> MachineAir<F> for ProgramChip {
 
         // Collect the number of times each instruction is called from the cpu events.
         // Store it as a map of PC -> count.
-        let mut instruction_counts = HashMap::new();
+        let mut instruction_counts = BTreeMap::new();
         input.cpu_events.iter().for_each(|event| {
             let pc = event.pc;
             instruction_counts
@@ -93,6 +93,10 @@ impl<F: PrimeField> MachineAir<F> for ProgramChip {
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.program.instructions.len()
+    }
+
     fn included(&self, _: &Self::Record) -> bool {
         true
     }
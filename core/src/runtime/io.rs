@@ -41,7 +41,7 @@ pub mod tests {
     use super::*;
     use crate::runtime::Program;
     use crate::utils::tests::IO_ELF;
-    use crate::utils::{self, prove_core, BabyBearBlake3};
+    use crate::utils::{self, prove_core, BabyBearBlake3, ProverOptions};
     use serde::Deserialize;
 
     #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -96,6 +96,6 @@ pub mod tests {
         runtime.write_stdin(&points.1);
         runtime.run();
         let config = BabyBearBlake3::new();
-        prove_core(config, runtime);
+        prove_core(config, runtime, &ProverOptions::default());
     }
 }
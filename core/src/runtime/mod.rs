@@ -21,12 +21,12 @@ pub use syscall::*;
 pub use utils::*;
 
 use self::state::ExecutionState;
-use crate::utils::env;
+use crate::utils::{ProgressMonitor, ProvePhase, RuntimeOptions};
 use crate::{alu::AluEvent, cpu::CpuEvent};
 
 use hashbrown::hash_map::Entry;
 use hashbrown::HashMap;
-use nohash_hasher::BuildNoHashHasher;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::BufWriter;
 use std::io::Write;
@@ -76,11 +76,19 @@ pub struct Runtime {
     pub(crate) unconstrained_state: ForkState,
 
     pub syscall_map: HashMap<SyscallCode, Rc<dyn Syscall>>,
+
+    /// Reports the shards executed, and stops the execution once cancelled.
+    pub monitor: ProgressMonitor,
 }
 
 impl Runtime {
-    // Create a new runtime from a program.
+    // Create a new runtime from a program, with the default options.
     pub fn new(program: Program) -> Self {
+        Self::with_options(program, RuntimeOptions::default())
+    }
+
+    /// Create a new runtime from a program, with the given options.
+    pub fn with_options(program: Program, options: RuntimeOptions) -> Self {
         // Create a shared reference to the program.
         let program = Arc::new(program);
 
@@ -103,7 +111,7 @@ impl Runtime {
             state: ExecutionState::new(program.pc_start),
             program,
             memory_accesses: MemoryAccessRecord::default(),
-            shard_size: env::shard_size() as u32 * 4,
+            shard_size: options.shard_size as u32 * 4,
             cycle_tracker: HashMap::new(),
             io_buf: HashMap::new(),
             trace_buf,
@@ -111,6 +119,7 @@ impl Runtime {
             unconstrained: false,
             unconstrained_state: ForkState::default(),
             syscall_map: default_syscall_map(),
+            monitor: ProgressMonitor::default(),
         }
     }
 
@@ -752,6 +761,9 @@ impl Runtime {
     }
 
     /// Execute the program.
+    ///
+    /// If the cancellation of the monitor is requested, the execution stops at the end of the
+    /// current shard, leaving the record incomplete.
     pub fn run(&mut self) {
         let max_syscall_cycles = self.max_syscall_cycles();
         self.state.clk = 1;
@@ -769,6 +781,7 @@ impl Runtime {
         }
 
         tracing::info!("starting execution");
+        let progress = self.monitor.start(ProvePhase::Execute, None);
         while self.state.pc.wrapping_sub(self.program.pc_base)
             < (self.program.instructions.len() * 4) as u32
         {
@@ -790,6 +803,11 @@ impl Runtime {
             if !self.unconstrained && max_syscall_cycles + self.state.clk >= self.shard_size * 4 {
                 self.state.current_shard += 1;
                 self.state.clk = 0;
+                progress.advance();
+                if self.monitor.cancellation().is_cancelled() {
+                    tracing::info!("execution cancelled at shard {}", self.state.current_shard);
+                    return;
+                }
             }
         }
 
@@ -823,7 +841,7 @@ impl Runtime {
     }
 
     fn postprocess(&mut self) {
-        let mut program_memory_used = HashMap::with_hasher(BuildNoHashHasher::<u32>::default());
+        let mut program_memory_used = BTreeMap::new();
         for (key, value) in &self.program.memory_image {
             // By default we assume that the program_memory is used.
             program_memory_used.insert(*key, (*value, 1));
@@ -832,7 +850,10 @@ impl Runtime {
         let mut first_memory_record = Vec::new();
         let mut last_memory_record = Vec::new();
 
-        let memory_keys = self.state.memory.keys().cloned().collect::<Vec<u32>>();
+        // The memory is visited in the order of the addresses rather than of the hash map, so that
+        // the memory records, and thus the proofs, do not depend on the layout of the map.
+        let mut memory_keys = self.state.memory.keys().cloned().collect::<Vec<u32>>();
+        memory_keys.sort_unstable();
         for addr in memory_keys {
             let record = *self.state.memory.get(&addr).unwrap();
             if record.shard == 0 && record.timestamp == 0 {
@@ -868,7 +889,7 @@ impl Runtime {
             ));
         }
 
-        let mut program_memory_record = program_memory_used
+        let program_memory_record = program_memory_used
             .iter()
             .map(|(&addr, &(value, used))| {
                 (
@@ -882,7 +903,6 @@ impl Runtime {
                 )
             })
             .collect::<Vec<(u32, MemoryRecord, u32)>>();
-        program_memory_record.sort_by_key(|&(addr, _, _)| addr);
 
         self.record.first_memory_record = first_memory_record;
         self.record.last_memory_record = last_memory_record;
@@ -1,4 +1,3 @@
-use hashbrown::HashMap;
 use std::collections::BTreeMap;
 use std::mem::take;
 use std::sync::Arc;
@@ -11,14 +10,17 @@ use crate::cpu::CpuEvent;
 use crate::field::event::FieldEvent;
 use crate::runtime::MemoryRecord;
 use crate::runtime::MemoryRecordEnum;
-use crate::stark::MachineRecord;
-use crate::syscall::precompiles::blake3::Blake3CompressInnerEvent;
-use crate::syscall::precompiles::edwards::EdDecompressEvent;
+use crate::stark::{MachineRecord, ShardPacking};
+use crate::syscall::precompiles::blake3::{Blake3CompressInnerEvent, Blake3HashEvent};
+use crate::syscall::precompiles::edwards::{EdDecompressEvent, EdDoubleScalarMulEvent};
 use crate::syscall::precompiles::k256::K256DecompressEvent;
-use crate::syscall::precompiles::keccak256::KeccakPermuteEvent;
-use crate::syscall::precompiles::sha256::{ShaCompressEvent, ShaExtendEvent};
+use crate::syscall::precompiles::keccak256::{Keccak256Event, KeccakPermuteEvent};
+use crate::syscall::precompiles::sha256::{
+    ShaCompressBlocksEvent, ShaCompressEvent, ShaExtendEvent,
+};
+use crate::syscall::precompiles::sha512::{Sha512CompressEvent, Sha512ExtendEvent};
 use crate::syscall::precompiles::{ECAddEvent, ECDoubleEvent};
-use crate::utils::env;
+use crate::utils::DEFAULT_SHARD_SIZE;
 use serde::{Deserialize, Serialize};
 
 /// A record of the execution of a program. Contains event data for everything that happened during
@@ -68,12 +70,22 @@ pub struct ExecutionRecord {
 
     pub sha_compress_events: Vec<ShaCompressEvent>,
 
+    pub sha_compress_blocks_events: Vec<ShaCompressBlocksEvent>,
+
+    pub sha512_extend_events: Vec<Sha512ExtendEvent>,
+
+    pub sha512_compress_events: Vec<Sha512CompressEvent>,
+
     pub keccak_permute_events: Vec<KeccakPermuteEvent>,
 
+    pub keccak256_events: Vec<Keccak256Event>,
+
     pub ed_add_events: Vec<ECAddEvent>,
 
     pub ed_decompress_events: Vec<EdDecompressEvent>,
 
+    pub ed_double_scalar_mul_events: Vec<EdDoubleScalarMulEvent>,
+
     pub weierstrass_add_events: Vec<ECAddEvent>,
 
     pub weierstrass_double_events: Vec<ECDoubleEvent>,
@@ -82,6 +94,8 @@ pub struct ExecutionRecord {
 
     pub blake3_compress_inner_events: Vec<Blake3CompressInnerEvent>,
 
+    pub blake3_hash_events: Vec<Blake3HashEvent>,
+
     /// Information needed for global chips. This shouldn't really be here but for legacy reasons,
     /// we keep this information in this struct for now.
     pub first_memory_record: Vec<(u32, MemoryRecord, u32)>,
@@ -103,17 +117,18 @@ pub struct ShardingConfig {
     pub keccak_len: usize,
     pub weierstrass_add_len: usize,
     pub weierstrass_double_len: usize,
+    /// The packing of the events under a target number of cells per shard, in place of the
+    /// static lengths above.
+    pub packing: Option<ShardPacking>,
 }
 
 impl ShardingConfig {
-    pub const fn shard_size(&self) -> usize {
-        self.shard_size
-    }
-}
-
-impl Default for ShardingConfig {
-    fn default() -> Self {
-        let shard_size = env::shard_size();
+    /// Creates a configuration with `shard_size` rows for each chip, which must be a power of two.
+    pub fn new(shard_size: usize) -> Self {
+        assert!(
+            shard_size.is_power_of_two(),
+            "the shard size must be a power of two"
+        );
         Self {
             shard_size,
             add_len: shard_size,
@@ -128,12 +143,29 @@ impl Default for ShardingConfig {
             keccak_len: shard_size,
             weierstrass_add_len: shard_size,
             weierstrass_double_len: shard_size,
+            packing: None,
         }
     }
+
+    pub fn with_packing(mut self, packing: ShardPacking) -> Self {
+        self.packing = Some(packing);
+        self
+    }
+
+    pub const fn shard_size(&self) -> usize {
+        self.shard_size
+    }
+}
+
+impl Default for ShardingConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARD_SIZE)
+    }
 }
 
 impl MachineRecord for ExecutionRecord {
     type Config = ShardingConfig;
+    type Program = Program;
 
     fn index(&self) -> u32 {
         self.index
@@ -143,8 +175,8 @@ impl MachineRecord for ExecutionRecord {
         self.index = index;
     }
 
-    fn stats(&self) -> HashMap<String, usize> {
-        let mut stats = HashMap::new();
+    fn stats(&self) -> BTreeMap<String, usize> {
+        let mut stats = BTreeMap::new();
         stats.insert("cpu_events".to_string(), self.cpu_events.len());
         stats.insert("add_events".to_string(), self.add_events.len());
         stats.insert("mul_events".to_string(), self.mul_events.len());
@@ -169,15 +201,32 @@ impl MachineRecord for ExecutionRecord {
             "sha_compress_events".to_string(),
             self.sha_compress_events.len(),
         );
+        stats.insert(
+            "sha_compress_blocks_events".to_string(),
+            self.sha_compress_blocks_events.len(),
+        );
+        stats.insert(
+            "sha512_extend_events".to_string(),
+            self.sha512_extend_events.len(),
+        );
+        stats.insert(
+            "sha512_compress_events".to_string(),
+            self.sha512_compress_events.len(),
+        );
         stats.insert(
             "keccak_permute_events".to_string(),
             self.keccak_permute_events.len(),
         );
+        stats.insert("keccak256_events".to_string(), self.keccak256_events.len());
         stats.insert("ed_add_events".to_string(), self.ed_add_events.len());
         stats.insert(
             "ed_decompress_events".to_string(),
             self.ed_decompress_events.len(),
         );
+        stats.insert(
+            "ed_double_scalar_mul_events".to_string(),
+            self.ed_double_scalar_mul_events.len(),
+        );
         stats.insert(
             "weierstrass_add_events".to_string(),
             self.weierstrass_add_events.len(),
@@ -194,6 +243,10 @@ impl MachineRecord for ExecutionRecord {
             "blake3_compress_inner_events".to_string(),
             self.blake3_compress_inner_events.len(),
         );
+        stats.insert(
+            "blake3_hash_events".to_string(),
+            self.blake3_hash_events.len(),
+        );
         stats
     }
 
@@ -214,11 +267,20 @@ impl MachineRecord for ExecutionRecord {
         self.sha_extend_events.append(&mut other.sha_extend_events);
         self.sha_compress_events
             .append(&mut other.sha_compress_events);
+        self.sha_compress_blocks_events
+            .append(&mut other.sha_compress_blocks_events);
+        self.sha512_extend_events
+            .append(&mut other.sha512_extend_events);
+        self.sha512_compress_events
+            .append(&mut other.sha512_compress_events);
         self.keccak_permute_events
             .append(&mut other.keccak_permute_events);
+        self.keccak256_events.append(&mut other.keccak256_events);
         self.ed_add_events.append(&mut other.ed_add_events);
         self.ed_decompress_events
             .append(&mut other.ed_decompress_events);
+        self.ed_double_scalar_mul_events
+            .append(&mut other.ed_double_scalar_mul_events);
         self.weierstrass_add_events
             .append(&mut other.weierstrass_add_events);
         self.weierstrass_double_events
@@ -227,6 +289,8 @@ impl MachineRecord for ExecutionRecord {
             .append(&mut other.k256_decompress_events);
         self.blake3_compress_inner_events
             .append(&mut other.blake3_compress_inner_events);
+        self.blake3_hash_events
+            .append(&mut other.blake3_hash_events);
 
         for (event, mult) in other.byte_lookups.iter_mut() {
             self.byte_lookups
@@ -244,6 +308,10 @@ impl MachineRecord for ExecutionRecord {
     }
 
     fn shard(mut self, config: &ShardingConfig) -> Vec<Self> {
+        if let Some(packing) = &config.packing {
+            return packing.shard(self, config);
+        }
+
         // Make the shard vector by splitting CPU and program events.
         let num_shards = (self.cpu_events.len() + config.shard_size - 1) / config.shard_size;
         let mut shards = (0..num_shards)
@@ -373,18 +441,36 @@ impl MachineRecord for ExecutionRecord {
         // SHA-256 compress events.
         first.sha_compress_events = std::mem::take(&mut self.sha_compress_events);
 
+        // SHA-256 compress blocks events.
+        first.sha_compress_blocks_events = std::mem::take(&mut self.sha_compress_blocks_events);
+
+        // SHA-512 extend events.
+        first.sha512_extend_events = std::mem::take(&mut self.sha512_extend_events);
+
+        // SHA-512 compress events.
+        first.sha512_compress_events = std::mem::take(&mut self.sha512_compress_events);
+
+        // Keccak-256 events.
+        first.keccak256_events = std::mem::take(&mut self.keccak256_events);
+
         // Edwards curve add events.
         first.ed_add_events = std::mem::take(&mut self.ed_add_events);
 
         // Edwards curve decompress events.
         first.ed_decompress_events = std::mem::take(&mut self.ed_decompress_events);
 
+        // Edwards curve double scalar multiplication events.
+        first.ed_double_scalar_mul_events = std::mem::take(&mut self.ed_double_scalar_mul_events);
+
         // K256 curve decompress events.
         first.k256_decompress_events = std::mem::take(&mut self.k256_decompress_events);
 
         // Blake3 compress events .
         first.blake3_compress_inner_events = std::mem::take(&mut self.blake3_compress_inner_events);
 
+        // Blake3 hash events.
+        first.blake3_hash_events = std::mem::take(&mut self.blake3_hash_events);
+
         // Put all byte lookups in the first shard (as the table size is fixed)
         first.byte_lookups = std::mem::take(&mut self.byte_lookups);
 
@@ -437,7 +523,7 @@ impl ExecutionRecord {
             .or_insert(1);
     }
 
-    pub fn add_alu_events(&mut self, alu_events: HashMap<Opcode, Vec<AluEvent>>) {
+    pub fn add_alu_events(&mut self, alu_events: BTreeMap<Opcode, Vec<AluEvent>>) {
         for opcode in alu_events.keys() {
             match opcode {
                 Opcode::ADD => {
@@ -2,12 +2,14 @@ use hashbrown::HashMap;
 use std::rc::Rc;
 
 use crate::runtime::{Register, Runtime};
-use crate::syscall::precompiles::blake3::Blake3CompressInnerChip;
+use crate::syscall::precompiles::blake3::{Blake3CompressInnerChip, Blake3HashChip};
 use crate::syscall::precompiles::edwards::EdAddAssignChip;
 use crate::syscall::precompiles::edwards::EdDecompressChip;
+use crate::syscall::precompiles::edwards::EdDoubleScalarMulChip;
 use crate::syscall::precompiles::k256::K256DecompressChip;
-use crate::syscall::precompiles::keccak256::KeccakPermuteChip;
-use crate::syscall::precompiles::sha256::{ShaCompressChip, ShaExtendChip};
+use crate::syscall::precompiles::keccak256::{Keccak256Chip, KeccakPermuteChip};
+use crate::syscall::precompiles::sha256::{ShaCompressBlocksChip, ShaCompressChip, ShaExtendChip};
+use crate::syscall::precompiles::sha512::{Sha512CompressChip, Sha512ExtendChip};
 use crate::syscall::precompiles::weierstrass::WeierstrassAddAssignChip;
 use crate::syscall::precompiles::weierstrass::WeierstrassDoubleAssignChip;
 use crate::syscall::{
@@ -60,6 +62,24 @@ pub enum SyscallCode {
     /// Executes the `BLAKE3_COMPRESS_INNER` precompile.
     BLAKE3_COMPRESS_INNER = 112,
 
+    /// Executes the `SHA_COMPRESS_BLOCKS` precompile.
+    SHA_COMPRESS_BLOCKS = 113,
+
+    /// Executes the `SHA512_EXTEND` precompile.
+    SHA512_EXTEND = 114,
+
+    /// Executes the `SHA512_COMPRESS` precompile.
+    SHA512_COMPRESS = 115,
+
+    /// Executes the `KECCAK256` precompile.
+    KECCAK256 = 116,
+
+    /// Executes the `BLAKE3_HASH` precompile.
+    BLAKE3_HASH = 117,
+
+    /// Executes the `ED_DOUBLE_SCALAR_MUL` precompile.
+    ED_DOUBLE_SCALAR_MUL = 118,
+
     WRITE = 999,
 }
 
@@ -80,6 +100,12 @@ impl SyscallCode {
             110 => SyscallCode::ENTER_UNCONSTRAINED,
             111 => SyscallCode::EXIT_UNCONSTRAINED,
             112 => SyscallCode::BLAKE3_COMPRESS_INNER,
+            113 => SyscallCode::SHA_COMPRESS_BLOCKS,
+            114 => SyscallCode::SHA512_EXTEND,
+            115 => SyscallCode::SHA512_COMPRESS,
+            116 => SyscallCode::KECCAK256,
+            117 => SyscallCode::BLAKE3_HASH,
+            118 => SyscallCode::ED_DOUBLE_SCALAR_MUL,
             999 => SyscallCode::WRITE,
             _ => panic!("invalid syscall number: {}", value),
         }
@@ -196,6 +222,10 @@ pub fn default_syscall_map() -> HashMap<SyscallCode, Rc<dyn Syscall>> {
         SyscallCode::ED_DECOMPRESS,
         Rc::new(EdDecompressChip::<Ed25519Parameters>::new()),
     );
+    syscall_map.insert(
+        SyscallCode::ED_DOUBLE_SCALAR_MUL,
+        Rc::new(EdDoubleScalarMulChip::<Ed25519>::new()),
+    );
     syscall_map.insert(
         SyscallCode::KECCAK_PERMUTE,
         Rc::new(KeccakPermuteChip::new()),
@@ -217,6 +247,17 @@ pub fn default_syscall_map() -> HashMap<SyscallCode, Rc<dyn Syscall>> {
         SyscallCode::BLAKE3_COMPRESS_INNER,
         Rc::new(Blake3CompressInnerChip::new()),
     );
+    syscall_map.insert(SyscallCode::BLAKE3_HASH, Rc::new(Blake3HashChip::new()));
+    syscall_map.insert(
+        SyscallCode::SHA_COMPRESS_BLOCKS,
+        Rc::new(ShaCompressBlocksChip::new()),
+    );
+    syscall_map.insert(SyscallCode::SHA512_EXTEND, Rc::new(Sha512ExtendChip::new()));
+    syscall_map.insert(
+        SyscallCode::SHA512_COMPRESS,
+        Rc::new(Sha512CompressChip::new()),
+    );
+    syscall_map.insert(SyscallCode::KECCAK256, Rc::new(Keccak256Chip::new()));
     syscall_map.insert(
         SyscallCode::ENTER_UNCONSTRAINED,
         Rc::new(SyscallEnterUnconstrained::new()),
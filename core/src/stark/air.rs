@@ -24,12 +24,18 @@ pub(crate) mod riscv_chips {
     pub use crate::memory::MemoryGlobalChip;
     pub use crate::program::ProgramChip;
     pub use crate::syscall::precompiles::blake3::Blake3CompressInnerChip;
+    pub use crate::syscall::precompiles::blake3::Blake3HashChip;
     pub use crate::syscall::precompiles::edwards::EdAddAssignChip;
     pub use crate::syscall::precompiles::edwards::EdDecompressChip;
+    pub use crate::syscall::precompiles::edwards::EdDoubleScalarMulChip;
     pub use crate::syscall::precompiles::k256::K256DecompressChip;
+    pub use crate::syscall::precompiles::keccak256::Keccak256Chip;
     pub use crate::syscall::precompiles::keccak256::KeccakPermuteChip;
+    pub use crate::syscall::precompiles::sha256::ShaCompressBlocksChip;
     pub use crate::syscall::precompiles::sha256::ShaCompressChip;
     pub use crate::syscall::precompiles::sha256::ShaExtendChip;
+    pub use crate::syscall::precompiles::sha512::Sha512CompressChip;
+    pub use crate::syscall::precompiles::sha512::Sha512ExtendChip;
     pub use crate::syscall::precompiles::weierstrass::WeierstrassAddAssignChip;
     pub use crate::syscall::precompiles::weierstrass::WeierstrassDoubleAssignChip;
     pub use crate::utils::ec::edwards::ed25519::Ed25519Parameters;
@@ -79,10 +85,18 @@ pub enum RiscvAir<F: PrimeField32> {
     Sha256Extend(ShaExtendChip),
     /// A precompile for sha256 compress.
     Sha256Compress(ShaCompressChip),
+    /// A precompile for compressing several sha256 blocks at once.
+    Sha256CompressBlocks(ShaCompressBlocksChip),
+    /// A precompile for sha512 extend.
+    Sha512Extend(Sha512ExtendChip),
+    /// A precompile for sha512 compress.
+    Sha512Compress(Sha512CompressChip),
     /// A precompile for addition on the Elliptic curve ed25519.
     Ed25519Add(EdAddAssignChip<EdwardsCurve<Ed25519Parameters>>),
     /// A precompile for decompressing a point on the Edwards curve ed25519.
     Ed25519Decompress(EdDecompressChip<Ed25519Parameters>),
+    /// A precompile for computing `a * G + b * P` on the Ed25519 curve.
+    Ed25519DoubleScalarMul(EdDoubleScalarMulChip<EdwardsCurve<Ed25519Parameters>>),
     /// A precompile for decompressing a point on the K256 curve.
     K256Decompress(K256DecompressChip),
     /// A precompile for addition on the Elliptic curve secp256k1.
@@ -91,8 +105,12 @@ pub enum RiscvAir<F: PrimeField32> {
     Secp256k1Double(WeierstrassDoubleAssignChip<SwCurve<Secp256k1Parameters>>),
     /// A precompile for the Keccak permutation.
     KeccakP(KeccakPermuteChip),
+    /// A precompile for hashing a buffer with Keccak-256.
+    Keccak256(Keccak256Chip),
     /// A precompile for the Blake3 compression function.
     Blake3Compress(Blake3CompressInnerChip),
+    /// A precompile for hashing a buffer with Blake3.
+    Blake3Hash(Blake3HashChip),
 }
 
 impl<F: PrimeField32> RiscvAir<F> {
@@ -117,10 +135,18 @@ impl<F: PrimeField32> RiscvAir<F> {
         chips.push(RiscvAir::Sha256Extend(sha_extend));
         let sha_compress = ShaCompressChip::default();
         chips.push(RiscvAir::Sha256Compress(sha_compress));
+        let sha_compress_blocks = ShaCompressBlocksChip::default();
+        chips.push(RiscvAir::Sha256CompressBlocks(sha_compress_blocks));
+        let sha512_extend = Sha512ExtendChip::default();
+        chips.push(RiscvAir::Sha512Extend(sha512_extend));
+        let sha512_compress = Sha512CompressChip::default();
+        chips.push(RiscvAir::Sha512Compress(sha512_compress));
         let ed_add_assign = EdAddAssignChip::<EdwardsCurve<Ed25519Parameters>>::new();
         chips.push(RiscvAir::Ed25519Add(ed_add_assign));
         let ed_decompress = EdDecompressChip::<Ed25519Parameters>::default();
         chips.push(RiscvAir::Ed25519Decompress(ed_decompress));
+        let ed_double_scalar_mul = EdDoubleScalarMulChip::<EdwardsCurve<Ed25519Parameters>>::new();
+        chips.push(RiscvAir::Ed25519DoubleScalarMul(ed_double_scalar_mul));
         let k256_decompress = K256DecompressChip::default();
         chips.push(RiscvAir::K256Decompress(k256_decompress));
         let weierstrass_add_assign =
@@ -131,8 +157,12 @@ impl<F: PrimeField32> RiscvAir<F> {
         chips.push(RiscvAir::Secp256k1Double(weierstrass_double_assign));
         let keccak_permute = KeccakPermuteChip::new();
         chips.push(RiscvAir::KeccakP(keccak_permute));
+        let keccak256 = Keccak256Chip::new();
+        chips.push(RiscvAir::Keccak256(keccak256));
         let blake3_compress_inner = Blake3CompressInnerChip::new();
         chips.push(RiscvAir::Blake3Compress(blake3_compress_inner));
+        let blake3_hash = Blake3HashChip::new();
+        chips.push(RiscvAir::Blake3Hash(blake3_hash));
         let add = AddChip::default();
         chips.push(RiscvAir::Add(add));
         let sub = SubChip::default();
@@ -8,12 +8,12 @@ use p3_util::log2_ceil_usize;
 use crate::{
     air::{MachineAir, MultiTableAirBuilder, SP1AirBuilder},
     lookup::{Interaction, InteractionBuilder},
-    runtime::Program,
 };
 
 use super::{
-    eval_permutation_constraints, generate_permutation_trace, DebugConstraintBuilder,
-    ProverConstraintFolder, StarkGenericConfig, VerifierConstraintFolder,
+    eval_permutation_constraints, generate_permutation_trace, permutation_constraint_degree,
+    DebugConstraintBuilder, MachineRecord, ProverConstraintFolder, StarkGenericConfig,
+    SymbolicConstraintBuilder, SymbolicConstraints, VerifierConstraintFolder,
 };
 
 /// An Air that encodes lookups based on interactions.
@@ -24,11 +24,18 @@ pub struct Chip<F: Field, A> {
     sends: Vec<Interaction<F>>,
     /// The interactions that the chip receives.
     receives: Vec<Interaction<F>>,
+    /// The maximum degree of the constraints of the chip, including the permutation constraints.
+    max_constraint_degree: usize,
     /// The relative log degree of the quotient polynomial, i.e. `log2(max_constraint_degree - 1)`.
     log_quotient_degree: usize,
 }
 
 impl<F: Field, A> Chip<F, A> {
+    /// The underlying AIR of the chip.
+    pub fn air(&self) -> &A {
+        &self.air
+    }
+
     /// The send interactions of the chip.
     pub fn sends(&self) -> &[Interaction<F>] {
         &self.sends
@@ -39,6 +46,11 @@ impl<F: Field, A> Chip<F, A> {
         &self.receives
     }
 
+    /// The maximum degree of the constraints of the chip, including the permutation constraints.
+    pub const fn max_constraint_degree(&self) -> usize {
+        self.max_constraint_degree
+    }
+
     /// The relative log degree of the quotient polynomial, i.e. `log2(max_constraint_degree - 1)`.
     pub const fn log_quotient_degree(&self) -> usize {
         self.log_quotient_degree
@@ -80,33 +92,47 @@ where
     F: Field,
 {
     /// Records the interactions and constraint degree from the air and crates a new chip.
+    ///
+    /// The degree is the maximum degree of the constraints of the air and of the permutation
+    /// argument, at least 2, so that the quotient polynomial is never smaller than the trace.
     pub fn new(air: A) -> Self
     where
-        A: Air<InteractionBuilder<F>>,
+        A: MachineAir<F> + Air<InteractionBuilder<F>>,
     {
-        let mut builder = InteractionBuilder::new(air.width());
+        let mut builder = InteractionBuilder::new(air.preprocessed_width(), air.width());
         air.eval(&mut builder);
+        let air_degree = builder.max_constraint_degree();
         let (sends, receives) = builder.interactions();
 
-        // TODO: count constraints from the air.
-        let max_constraint_degree = 3;
+        let permutation_degree =
+            permutation_constraint_degree(&sends, &receives, air.preprocessed_width(), air.width());
+        let max_constraint_degree = air_degree.max(permutation_degree).max(2);
         let log_quotient_degree = log2_ceil_usize(max_constraint_degree - 1);
 
         Self {
             air,
             sends,
             receives,
+            max_constraint_degree,
             log_quotient_degree,
         }
     }
 
+    /// Records the constraints and interactions of the chip's AIR symbolically.
+    pub fn symbolic_constraints(&self) -> SymbolicConstraints<F>
+    where
+        A: MachineAir<F> + Air<SymbolicConstraintBuilder<F>>,
+    {
+        SymbolicConstraints::from_air(&self.air)
+    }
+
     pub fn num_interactions(&self) -> usize {
         self.sends.len() + self.receives.len()
     }
 
     pub fn generate_permutation_trace<EF: ExtensionField<F>>(
         &self,
-        preprocessed: &Option<RowMajorMatrix<F>>,
+        preprocessed: Option<&RowMajorMatrix<F>>,
         main: &RowMajorMatrix<F>,
         random_elements: &[EF],
     ) -> RowMajorMatrix<EF>
@@ -147,7 +173,11 @@ where
     fn name(&self) -> String {
         self.air.name()
     }
-    fn generate_preprocessed_trace(&self, program: &Program) -> Option<RowMajorMatrix<F>> {
+
+    fn generate_preprocessed_trace(
+        &self,
+        program: &<A::Record as MachineRecord>::Program,
+    ) -> Option<RowMajorMatrix<F>> {
         <A as MachineAir<F>>::generate_preprocessed_trace(&self.air, program)
     }
 
@@ -159,10 +189,18 @@ where
         self.air.generate_trace(input, output)
     }
 
+    fn num_rows(&self, input: &A::Record) -> usize {
+        self.air.num_rows(input)
+    }
+
     fn generate_dependencies(&self, input: &A::Record, output: &mut A::Record) {
         self.air.generate_dependencies(input, output)
     }
 
+    fn dependency_phase(&self) -> usize {
+        self.air.dependency_phase()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         self.air.included(shard)
     }
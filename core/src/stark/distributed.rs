@@ -0,0 +1,580 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Condvar, Mutex};
+
+use p3_air::Air;
+use p3_challenger::{CanObserve, FieldChallenger};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::types::*;
+use super::{
+    DebugConstraintBuilder, LocalProver, MachineRecord, MachineStark, ProverConstraintFolder,
+    ProverError, ProvingKey, StarkGenericConfig, VerifierConstraintFolder,
+};
+use crate::air::MachineAir;
+use crate::lookup::InteractionBuilder;
+use crate::stark::Prover;
+use crate::utils::{CancellationToken, ProgressMonitor, ProvePhase, StarkUtils};
+
+/// The address of a worker process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkerAddress {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// A connection to a worker.
+trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+impl WorkerAddress {
+    fn connect(&self) -> io::Result<Box<dyn Stream>> {
+        match self {
+            WorkerAddress::Tcp(address) => Ok(Box::new(TcpStream::connect(address)?)),
+            #[cfg(unix)]
+            WorkerAddress::Unix(path) => Ok(Box::new(UnixStream::connect(path)?)),
+        }
+    }
+
+    /// Listens on the address, to serve a worker with [`serve_worker`].
+    pub fn bind(&self) -> io::Result<WorkerListener> {
+        match self {
+            WorkerAddress::Tcp(address) => Ok(WorkerListener::Tcp(TcpListener::bind(address)?)),
+            #[cfg(unix)]
+            WorkerAddress::Unix(path) => Ok(WorkerListener::Unix(UnixListener::bind(path)?)),
+        }
+    }
+}
+
+/// Formats a TCP address as `host:port` and a Unix socket as `unix:path`, as parsed by
+/// [`WorkerAddress::from_str`].
+impl Display for WorkerAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkerAddress::Tcp(address) => write!(f, "{}", address),
+            #[cfg(unix)]
+            WorkerAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for WorkerAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(WorkerAddress::Unix(PathBuf::from(path)));
+        }
+        s.parse()
+            .map(WorkerAddress::Tcp)
+            .map_err(|error| format!("invalid worker address `{}`: {}", s, error))
+    }
+}
+
+/// A listener accepting the connections of a coordinator.
+pub enum WorkerListener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl WorkerListener {
+    fn accept(&self) -> io::Result<Box<dyn Stream>> {
+        match self {
+            WorkerListener::Tcp(listener) => Ok(Box::new(listener.accept()?.0)),
+            #[cfg(unix)]
+            WorkerListener::Unix(listener) => Ok(Box::new(listener.accept()?.0)),
+        }
+    }
+
+    /// The address the listener is bound to, e.g. to find the port of a TCP listener bound to
+    /// port 0.
+    pub fn local_addr(&self) -> io::Result<WorkerAddress> {
+        match self {
+            WorkerListener::Tcp(listener) => Ok(WorkerAddress::Tcp(listener.local_addr()?)),
+            #[cfg(unix)]
+            WorkerListener::Unix(listener) => {
+                let address = listener.local_addr()?;
+                let path = address
+                    .as_pathname()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "unnamed unix socket"))?;
+                Ok(WorkerAddress::Unix(path.to_path_buf()))
+            }
+        }
+    }
+}
+
+/// The requests sent by the coordinator. Each connection carries a single request: its kind, the
+/// index and the record of the shard, and for openings the commitments observed by the challenger
+/// of the coordinator.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+enum Request {
+    Commit,
+    Open,
+}
+
+/// Writes a value to a stream, prefixed with its length.
+fn send<T: Serialize>(stream: &mut dyn Stream, value: &T) -> io::Result<()> {
+    let bytes = bincode::serialize(value)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    stream.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()
+}
+
+/// Reads a value written by [`send`].
+fn receive<T: DeserializeOwned>(stream: &mut dyn Stream) -> io::Result<T> {
+    let mut len = [0; 8];
+    stream.read_exact(&mut len)?;
+    let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+    stream.read_exact(&mut bytes)?;
+    bincode::deserialize(&bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Runs `f`, turning a panic into an error message.
+fn catch<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|error| {
+        error
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| error.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "worker panicked".to_string())
+    })
+}
+
+/// A prover which distributes the shards to worker processes, which run [`serve_worker`].
+///
+/// The workers commit to the shards first, then the coordinator observes the commitments and the
+/// workers open the shards. A worker opening a shard regenerates its traces, so a shard may be
+/// committed and opened by different workers. The shards which fail are retried, on any worker,
+/// up to `max_attempts` times.
+///
+/// The workers rebuild the challenger of the coordinator by observing, in a fresh challenger, the
+/// commitments given to [`Self::with_observed`], the proving key, the public values of the shards
+/// and then the commitments of the shards. The challenger given to [`Prover::prove_shards`] must
+/// thus have observed these values only.
+pub struct DistributedProver<SC: StarkGenericConfig, A> {
+    workers: Vec<WorkerAddress>,
+    max_attempts: usize,
+    observed: Vec<Com<SC>>,
+    _marker: PhantomData<A>,
+}
+
+impl<SC: StarkGenericConfig, A> DistributedProver<SC, A> {
+    pub fn new(workers: Vec<WorkerAddress>) -> Self {
+        assert!(!workers.is_empty(), "the distributed prover needs a worker");
+        Self {
+            workers,
+            max_attempts: 3,
+            observed: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the number of times a shard is tried before the proof is aborted.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        assert!(max_attempts > 0);
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the commitments observed by the challenger given to [`Prover::prove_shards`] before
+    /// the proving key, in order.
+    pub fn with_observed(mut self, observed: Vec<Com<SC>>) -> Self {
+        self.observed = observed;
+        self
+    }
+
+    /// Runs a job for each index on the workers, retrying the failed ones, and returns their
+    /// results in order.
+    ///
+    /// Each worker takes a job from a shared queue once it is done with the previous one, and
+    /// waits for the jobs of the other workers to be requeued when the queue is empty. A worker
+    /// which cannot be reached is not used anymore. No job is sent once the proof is cancelled,
+    /// but the jobs already sent run to completion on the workers.
+    fn dispatch<T: Send>(
+        &self,
+        phase: ProvePhase,
+        monitor: &ProgressMonitor,
+        num_jobs: usize,
+        job: impl Fn(&mut dyn Stream, usize) -> io::Result<Result<T, String>> + Sync,
+    ) -> Result<Vec<T>, ProverError> {
+        let progress = monitor.start(phase, Some(num_jobs));
+        let state = Mutex::new(DispatchState {
+            queue: (0..num_jobs).map(|i| (i, 0)).collect(),
+            results: (0..num_jobs).map(|_| None).collect(),
+            remaining: num_jobs,
+            failure: None,
+        });
+        // Notified whenever a job is requeued, or a worker stops.
+        let changed = Condvar::new();
+
+        std::thread::scope(|scope| {
+            for worker in self.workers.iter() {
+                let (state, changed, job, progress) = (&state, &changed, &job, &progress);
+                scope.spawn(move || loop {
+                    let (index, attempts) = {
+                        let mut state = state.lock().unwrap();
+                        loop {
+                            if state.is_over() || monitor.cancellation().is_cancelled() {
+                                // Wake the workers waiting for a job, which may stop too.
+                                changed.notify_all();
+                                return;
+                            }
+                            match state.queue.pop_front() {
+                                Some(job) => break job,
+                                // The other workers hold the remaining jobs, which may be
+                                // requeued.
+                                None => state = changed.wait(state).unwrap(),
+                            }
+                        }
+                    };
+
+                    let mut stream = match worker.connect() {
+                        Ok(stream) => stream,
+                        Err(error) => {
+                            log::warn!("worker {:?} is unreachable: {}", worker, error);
+                            state.lock().unwrap().queue.push_front((index, attempts));
+                            changed.notify_one();
+                            return;
+                        }
+                    };
+                    let error = match job(stream.as_mut(), index) {
+                        Ok(Ok(result)) => {
+                            let mut state = state.lock().unwrap();
+                            state.results[index] = Some(result);
+                            state.remaining -= 1;
+                            if state.remaining == 0 {
+                                changed.notify_all();
+                            }
+                            drop(state);
+                            progress.advance();
+                            continue;
+                        }
+                        Ok(Err(error)) => error,
+                        Err(error) => error.to_string(),
+                    };
+
+                    log::warn!(
+                        "{} shard {} failed on worker {:?}: {}",
+                        phase,
+                        index,
+                        worker,
+                        error
+                    );
+                    let mut state = state.lock().unwrap();
+                    if attempts + 1 >= self.max_attempts {
+                        state.failure = Some(format!(
+                            "{} shard {} failed {} times, last with: {}",
+                            phase,
+                            index,
+                            attempts + 1,
+                            error
+                        ));
+                        changed.notify_all();
+                        return;
+                    }
+                    state.queue.push_back((index, attempts + 1));
+                    changed.notify_one();
+                });
+            }
+        });
+
+        monitor.check()?;
+        let state = state.into_inner().unwrap();
+        if let Some(failure) = state.failure {
+            return Err(ProverError::Failed(failure));
+        }
+        if state.remaining > 0 {
+            return Err(ProverError::Failed(format!(
+                "no worker could be reached to {} the shards",
+                phase
+            )));
+        }
+        Ok(state.results.into_iter().map(Option::unwrap).collect())
+    }
+}
+
+/// The jobs of [`DistributedProver::dispatch`], shared by the threads of the workers.
+struct DispatchState<T> {
+    /// The jobs to run, with the number of times they were tried.
+    queue: VecDeque<(usize, usize)>,
+    results: Vec<Option<T>>,
+    remaining: usize,
+    failure: Option<String>,
+}
+
+impl<T> DispatchState<T> {
+    /// Whether all the jobs are done, or one of them failed too many times.
+    fn is_over(&self) -> bool {
+        self.remaining == 0 || self.failure.is_some()
+    }
+}
+
+impl<SC, A> Prover<SC, A> for DistributedProver<SC, A>
+where
+    SC: StarkUtils + Send + Sync,
+    SC::Challenger: Clone,
+    A: MachineAir<SC::Val> + Sync,
+    A::Record: Serialize + DeserializeOwned + Sync,
+    Com<SC>: Serialize + DeserializeOwned + Send + Sync,
+    ShardProof<SC>: Serialize + DeserializeOwned + Send,
+{
+    fn prove_shards(
+        &self,
+        machine: &MachineStark<SC, A>,
+        pk: &ProvingKey<SC>,
+        shards: Vec<A::Record>,
+        challenger: &mut SC::Challenger,
+        monitor: &ProgressMonitor,
+    ) -> Result<Vec<ShardProof<SC>>, ProverError>
+    where
+        A: for<'a> Air<ProverConstraintFolder<'a, SC>>
+            + Air<InteractionBuilder<SC::Val>>
+            + for<'a> Air<VerifierConstraintFolder<'a, SC>>
+            + for<'a> Air<DebugConstraintBuilder<'a, SC::Val, SC::Challenge>>,
+    {
+        log::info!("commit shards on {} workers", self.workers.len());
+        let commitments = self.dispatch(
+            ProvePhase::Commit,
+            monitor,
+            shards.len(),
+            |stream, index| {
+                send(stream, &Request::Commit)?;
+                send(stream, &index)?;
+                send(stream, &shards[index])?;
+                receive::<Result<Com<SC>, String>>(stream)
+            },
+        )?;
+
+        // The workers replay the observations of the challenger: the ones made before the proof,
+        // the proving key and the public values, followed by the commitments of the shards.
+        let public_values = shards
+            .iter()
+            .flat_map(MachineRecord::public_values::<SC::Val>)
+            .collect::<Vec<_>>();
+        let replayed = replay_challenger(
+            machine.config(),
+            pk,
+            &self.observed,
+            &public_values,
+            &commitments,
+        );
+        for commitment in commitments.iter() {
+            challenger.observe(commitment.clone());
+        }
+        if challenger.clone().sample_ext_element::<SC::Challenge>()
+            != replayed.clone().sample_ext_element::<SC::Challenge>()
+        {
+            return Err(ProverError::Failed(
+                "the challenger observed other values than the ones the workers replay".to_string(),
+            ));
+        }
+
+        log::info!("open shards on {} workers", self.workers.len());
+        self.dispatch(ProvePhase::Open, monitor, shards.len(), |stream, index| {
+            send(stream, &Request::Open)?;
+            send(stream, &index)?;
+            send(stream, &shards[index])?;
+            send(stream, &self.observed)?;
+            send(stream, &public_values)?;
+            send(stream, &commitments)?;
+            receive::<Result<ShardProof<SC>, String>>(stream)
+        })
+    }
+}
+
+/// The challenger of a proof after it observed the commitments observed before the proof, the
+/// proving key, the public values and the commitments of the shards, in this order.
+fn replay_challenger<SC: StarkUtils>(
+    config: &SC,
+    pk: &ProvingKey<SC>,
+    observed: &[Com<SC>],
+    public_values: &[SC::Val],
+    commitments: &[Com<SC>],
+) -> SC::Challenger {
+    let mut challenger = config.challenger();
+    for commitment in observed {
+        challenger.observe(commitment.clone());
+    }
+    pk.observe_into(&mut challenger);
+    challenger.observe_slice(public_values);
+    for commitment in commitments {
+        challenger.observe(commitment.clone());
+    }
+    challenger
+}
+
+/// Serves the requests of a [`DistributedProver`] on a worker, one connection at a time, until
+/// the listener fails.
+///
+/// The machine and the proving key must be the ones of the coordinator.
+pub fn serve_worker<SC, A>(
+    machine: &MachineStark<SC, A>,
+    pk: &ProvingKey<SC>,
+    listener: &WorkerListener,
+) -> io::Result<()>
+where
+    SC: StarkUtils + Send + Sync,
+    SC::Challenger: Clone,
+    A: MachineAir<SC::Val>
+        + for<'a> Air<ProverConstraintFolder<'a, SC>>
+        + Air<InteractionBuilder<SC::Val>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>
+        + for<'a> Air<DebugConstraintBuilder<'a, SC::Val, SC::Challenge>>,
+    A::Record: Serialize + DeserializeOwned,
+    Com<SC>: Serialize + DeserializeOwned + Send + Sync,
+    PcsProverData<SC>: Send + Sync,
+    ShardMainData<SC>: Serialize + DeserializeOwned,
+    ShardProof<SC>: Serialize + DeserializeOwned,
+{
+    loop {
+        let mut stream = listener.accept()?;
+        // A failed connection only fails its request, which the coordinator retries.
+        if let Err(error) = serve_request(machine, pk, stream.as_mut()) {
+            log::warn!("failed to serve a request: {}", error);
+        }
+    }
+}
+
+fn serve_request<SC, A>(
+    machine: &MachineStark<SC, A>,
+    pk: &ProvingKey<SC>,
+    stream: &mut dyn Stream,
+) -> io::Result<()>
+where
+    SC: StarkUtils + Send + Sync,
+    SC::Challenger: Clone,
+    A: MachineAir<SC::Val>
+        + for<'a> Air<ProverConstraintFolder<'a, SC>>
+        + Air<InteractionBuilder<SC::Val>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>
+        + for<'a> Air<DebugConstraintBuilder<'a, SC::Val, SC::Challenge>>,
+    A::Record: Serialize + DeserializeOwned,
+    Com<SC>: Serialize + DeserializeOwned + Send + Sync,
+    PcsProverData<SC>: Send + Sync,
+    ShardMainData<SC>: Serialize + DeserializeOwned,
+    ShardProof<SC>: Serialize + DeserializeOwned,
+{
+    let config = machine.config();
+    // The coordinator cancels a proof by not sending the remaining shards.
+    let cancellation = CancellationToken::new();
+    let request: Request = receive(stream)?;
+    let index: usize = receive(stream)?;
+    let shard: A::Record = receive(stream)?;
+    match request {
+        Request::Commit => {
+            let commitment = catch(|| {
+                LocalProver::<SC, A>::commit_main(config, machine, &shard, index, &cancellation)
+                    .map(|data| data.main_commit)
+            })
+            .and_then(|result| result.map_err(|error| error.to_string()));
+            send(stream, &commitment)
+        }
+        Request::Open => {
+            let observed: Vec<Com<SC>> = receive(stream)?;
+            let public_values: Vec<SC::Val> = receive(stream)?;
+            let commitments: Vec<Com<SC>> = receive(stream)?;
+            let proof = catch(|| {
+                let data = LocalProver::<SC, A>::commit_main(
+                    config,
+                    machine,
+                    &shard,
+                    index,
+                    &cancellation,
+                )?;
+                let chips = machine.shard_chips(&shard).collect::<Vec<_>>();
+                let mut challenger =
+                    replay_challenger(config, pk, &observed, &public_values, &commitments);
+                LocalProver::<SC, A>::prove_shard(
+                    config,
+                    pk,
+                    &chips,
+                    data,
+                    &mut challenger,
+                    &cancellation,
+                )
+            })
+            .and_then(|result| result.map_err(|error| error.to_string()));
+            send(stream, &proof)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{Program, Runtime};
+    use crate::stark::RiscvAir;
+    use crate::utils::tests::FIBONACCI_ELF;
+    use crate::utils::{setup_logger, BabyBearBlake3, ProverOptions};
+
+    /// Starts a worker on a thread, standing for a separate process.
+    fn spawn_worker() -> WorkerAddress {
+        let listener = WorkerAddress::Tcp("127.0.0.1:0".parse().unwrap())
+            .bind()
+            .unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let machine = RiscvAir::machine(BabyBearBlake3::new());
+            let (pk, _) = machine.setup(&Program::from(FIBONACCI_ELF));
+            serve_worker(&machine, &pk, &listener)
+        });
+        address
+    }
+
+    /// Starts a worker which drops every connection.
+    fn spawn_failing_worker() -> WorkerAddress {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = WorkerAddress::Tcp(listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                drop(stream);
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn test_distributed_prover() {
+        setup_logger();
+        let options = ProverOptions::default().with_shard_size(1 << 12);
+        let program = Program::from(FIBONACCI_ELF);
+        let mut runtime = Runtime::with_options(program, options.runtime_options());
+        runtime.run();
+
+        let workers = vec![spawn_worker(), spawn_failing_worker(), spawn_worker()];
+        let prover = DistributedProver::new(workers).with_max_attempts(10);
+
+        let machine = RiscvAir::machine(BabyBearBlake3::new());
+        let (pk, vk) = machine.setup(runtime.program.as_ref());
+        let mut challenger = machine.config().challenger();
+        let proof = machine
+            .prove_with_options(
+                &prover,
+                &pk,
+                runtime.record,
+                &mut challenger,
+                &options.sharding_config(),
+                &ProgressMonitor::default(),
+            )
+            .unwrap();
+        assert!(proof.shard_proofs.len() > 1);
+
+        let mut challenger = machine.config().challenger();
+        machine.verify(&vk, &proof, &mut challenger).unwrap();
+    }
+}
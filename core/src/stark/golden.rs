@@ -0,0 +1,93 @@
+//! Golden-proof tests: the proofs of a few programs must be byte-identical from one run to the
+//! next, and match the digests recorded in `golden/`, so that an accidental change to the layout
+//! of the traces or to the order of the transcript is caught.
+//!
+//! A missing or different digest fails the test. The digests are only written when the tests are
+//! run with `SP1_UPDATE_GOLDEN=1`. To record them, e.g. after an intended change to the proofs, run
+//!
+//! ```text
+//! SP1_UPDATE_GOLDEN=1 cargo test --release -p sp1-core golden
+//! ```
+//!
+//! then check that the tests pass without the variable, and commit the files written to
+//! `core/src/stark/golden/` with the change.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::runtime::{Program, Runtime};
+use crate::stark::{LocalProver, RiscvAir};
+use crate::utils::tests::{ED_ADD_ELF, FIBONACCI_ELF, KECCAK_PERMUTE_ELF};
+use crate::utils::{BabyBearBlake3, ProgressMonitor, ProverOptions, StarkUtils};
+
+/// Proves the program with fixed options, so that the proof does not depend on the environment,
+/// and returns the serialized proof.
+fn prove(elf: &[u8]) -> Vec<u8> {
+    let options = ProverOptions::default().with_shard_size(1 << 12);
+    let mut runtime = Runtime::with_options(Program::from(elf), options.runtime_options());
+    runtime.run();
+
+    let machine = RiscvAir::machine(BabyBearBlake3::new());
+    let (pk, vk) = machine.setup(runtime.program.as_ref());
+    let proof = machine
+        .prove_with_options(
+            &LocalProver::with_options(options.clone()),
+            &pk,
+            runtime.record,
+            &mut machine.config().challenger(),
+            &options.sharding_config(),
+            &ProgressMonitor::new(),
+        )
+        .unwrap();
+    machine
+        .verify(&vk, &proof, &mut machine.config().challenger())
+        .unwrap();
+    bincode::serialize(&proof).unwrap()
+}
+
+/// Checks the digest of the proof of the program against the one recorded for `name`.
+fn check_golden(name: &str, elf: &[u8]) {
+    let proof = prove(elf);
+    assert_eq!(proof, prove(elf), "the proofs of {} differ", name);
+    let digest = blake3::hash(&proof).to_hex().to_string();
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/stark/golden")
+        .join(format!("{}.blake3", name));
+    if std::env::var("SP1_UPDATE_GOLDEN").map_or(false, |v| v == "1") {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("{}\n", digest)).unwrap();
+        log::warn!("recorded the golden proof digest of {}", name);
+        return;
+    }
+    let golden = fs::read_to_string(&path).unwrap_or_else(|error| {
+        panic!(
+            "no golden proof digest for {} at {}: {}, run the tests with SP1_UPDATE_GOLDEN=1 to \
+            record it",
+            name,
+            path.display(),
+            error
+        )
+    });
+    assert_eq!(
+        digest,
+        golden.trim(),
+        "the proof of {} changed, run the tests with SP1_UPDATE_GOLDEN=1 if it is intended",
+        name
+    );
+}
+
+#[test]
+fn test_golden_fibonacci() {
+    check_golden("fibonacci", FIBONACCI_ELF);
+}
+
+#[test]
+fn test_golden_keccak_permute() {
+    check_golden("keccak-permute", KECCAK_PERMUTE_ELF);
+}
+
+#[test]
+fn test_golden_ed_add() {
+    check_golden("ed-add", ED_ADD_ELF);
+}
@@ -1,19 +1,31 @@
-use std::marker::PhantomData;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
 
 use crate::air::MachineAir;
-use crate::lookup::InteractionBuilder;
+use crate::lookup::{InteractionBuilder, InteractionKind};
 use crate::stark::record::MachineRecord;
 use crate::stark::DebugConstraintBuilder;
 use crate::stark::ProverConstraintFolder;
 use crate::stark::VerifierConstraintFolder;
+use crate::utils::ProgressMonitor;
 use p3_air::Air;
-use p3_challenger::CanObserve;
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_commit::{Pcs, UnivariatePcsWithLde};
 use p3_field::AbstractField;
 use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_maybe_rayon::prelude::*;
+use p3_util::log2_strict_usize;
+use serde::{Deserialize, Serialize};
 
 use super::Chip;
+use super::Com;
+use super::PcsProverData;
 use super::Proof;
 use super::Prover;
+use super::ProverError;
+use super::ShardProof;
 use super::StarkGenericConfig;
 use super::VerificationError;
 use super::Verifier;
@@ -28,22 +40,85 @@ pub struct MachineStark<SC: StarkGenericConfig, A> {
     chips: Vec<Chip<SC::Val, A>>,
 }
 
-impl<SC: StarkGenericConfig, A> MachineStark<SC, A> {
+impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> MachineStark<SC, A> {
+    /// Creates a new machine.
+    ///
+    /// Panics if the quotient of a chip does not fit in the blowup of the configuration, i.e. if
+    /// `max_constraint_degree - 1` exceeds `2^log_blowup`.
     pub fn new(config: SC, chips: Vec<Chip<SC::Val, A>>) -> Self {
+        let log_blowup = config.pcs().log_blowup();
+        for chip in chips.iter() {
+            assert!(
+                chip.log_quotient_degree() <= log_blowup,
+                "chip {} has constraints of degree {}, but the blowup of 2^{} supports at most {}",
+                chip.name(),
+                chip.max_constraint_degree(),
+                log_blowup,
+                (1 << log_blowup) + 1
+            );
+        }
         Self { config, chips }
     }
 }
 
-#[derive(Debug, Clone)]
+/// The proving key of a program: the preprocessed traces of the chips which have one, and the
+/// commitment to them.
 pub struct ProvingKey<SC: StarkGenericConfig> {
-    //TODO
-    marker: std::marker::PhantomData<SC>,
+    /// The commitment to the preprocessed traces, if there are any.
+    pub commit: Option<Com<SC>>,
+    /// The names of the chips with a preprocessed trace, in the order of the machine.
+    pub chip_ids: Vec<String>,
+    /// The preprocessed traces, in the order of `chip_ids`.
+    pub traces: Vec<RowMajorMatrix<SC::Val>>,
+    /// The prover data of the commitment to the preprocessed traces.
+    pub data: Option<PcsProverData<SC>>,
 }
 
-#[derive(Debug, Clone)]
+/// The verifying key of a program: the commitment to the preprocessed traces, and the chips they
+/// belong to.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct VerifyingKey<SC: StarkGenericConfig> {
-    // TODO:
-    marker: std::marker::PhantomData<SC>,
+    /// The commitment to the preprocessed traces, if there are any.
+    pub commit: Option<Com<SC>>,
+    /// The name and the log degree of each chip with a preprocessed trace, in the order of the
+    /// machine.
+    pub chip_information: Vec<(String, usize)>,
+}
+
+impl<SC: StarkGenericConfig> ProvingKey<SC> {
+    /// Observes the commitment to the preprocessed traces, if there is one.
+    pub fn observe_into(&self, challenger: &mut SC::Challenger) {
+        if let Some(commit) = &self.commit {
+            challenger.observe(commit.clone());
+        }
+    }
+
+    /// The preprocessed trace of the chip named `name`, if it has one.
+    pub fn trace(&self, name: &str) -> Option<&RowMajorMatrix<SC::Val>> {
+        self.chip_ids
+            .iter()
+            .position(|id| id == name)
+            .map(|index| &self.traces[index])
+    }
+}
+
+impl<SC: StarkGenericConfig> VerifyingKey<SC> {
+    /// Observes the commitment to the preprocessed traces, if there is one.
+    pub fn observe_into(&self, challenger: &mut SC::Challenger) {
+        if let Some(commit) = &self.commit {
+            challenger.observe(commit.clone());
+        }
+    }
+}
+
+impl<SC: StarkGenericConfig> Clone for VerifyingKey<SC> {
+    fn clone(&self) -> Self {
+        Self {
+            commit: self.commit.clone(),
+            chip_information: self.chip_information.clone(),
+        }
+    }
 }
 
 impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> MachineStark<SC, A> {
@@ -65,33 +140,86 @@ impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> MachineStark<SC, A> {
     /// The setup preprocessing phase.
     ///
     /// Given a program, this function generates the proving and verifying keys. The keys correspond
-    /// to the program code and other preprocessed colunms such as lookup tables.
-    pub fn setup<P>(&self, _program: &P) -> (ProvingKey<SC>, VerifyingKey<SC>) {
+    /// to the program code and other preprocessed colunms such as lookup tables: the preprocessed
+    /// traces of the chips which have one are committed to, in the order of the machine.
+    pub fn setup(
+        &self,
+        program: &<A::Record as MachineRecord>::Program,
+    ) -> (ProvingKey<SC>, VerifyingKey<SC>) {
+        let (chip_ids, traces): (Vec<_>, Vec<_>) = self
+            .chips
+            .iter()
+            .filter_map(|chip| {
+                chip.generate_preprocessed_trace(program)
+                    .map(|trace| (chip.name(), trace))
+            })
+            .unzip();
+        let chip_information = chip_ids
+            .iter()
+            .zip(traces.iter())
+            .map(|(name, trace)| (name.clone(), log2_strict_usize(trace.height())))
+            .collect();
+
+        let (commit, data) = if traces.is_empty() {
+            (None, None)
+        } else {
+            let (commit, data) = self.config.pcs().commit_batches(traces.clone());
+            (Some(commit), Some(data))
+        };
+
         (
             ProvingKey {
-                marker: PhantomData,
+                commit: commit.clone(),
+                chip_ids,
+                traces,
+                data,
             },
             VerifyingKey {
-                marker: PhantomData,
+                commit,
+                chip_information,
             },
         )
     }
 
+    /// Generates the dependencies of every chip, i.e. the events which the chips emit for other
+    /// chips, and adds them to `record`.
+    ///
+    /// The chips of a [dependency phase](MachineAir::dependency_phase) run in parallel, and their
+    /// events are appended in the order of the chips, so the record does not depend on the
+    /// scheduling of the threads.
+    pub fn generate_dependencies(&self, record: &mut A::Record) {
+        let mut phases = BTreeMap::<usize, Vec<&MachineChip<SC, A>>>::new();
+        for chip in self.chips() {
+            phases
+                .entry(chip.dependency_phase())
+                .or_default()
+                .push(chip);
+        }
+
+        for chips in phases.into_values() {
+            let input = &*record;
+            let outputs = chips
+                .par_iter()
+                .map(|chip| {
+                    let mut output = A::Record::default();
+                    output.set_index(input.index());
+                    chip.generate_dependencies(input, &mut output);
+                    output
+                })
+                .collect::<Vec<_>>();
+            for mut output in outputs {
+                record.append(&mut output);
+            }
+        }
+    }
+
     pub fn shard(
         &self,
         mut record: A::Record,
         config: &<A::Record as MachineRecord>::Config,
     ) -> Vec<A::Record> {
-        // Get the local and global chips.
-        let chips = self.chips();
-
-        // Generate the trace for each chip to collect events emitted from chips with dependencies.
-        chips.iter().for_each(|chip| {
-            let mut output = A::Record::default();
-            output.set_index(record.index());
-            chip.generate_dependencies(&record, &mut output);
-            record.append(&mut output);
-        });
+        // Collect the events emitted from chips with dependencies.
+        self.generate_dependencies(&mut record);
 
         // Display some statistics about the workload.
         let stats = record.stats();
@@ -106,13 +234,44 @@ impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> MachineStark<SC, A> {
     /// Prove the execution record is valid.
     ///
     /// Given a proving key `pk` and a matching execution record `record`, this function generates
-    /// a STARK proof that the execution record is valid.
-    pub fn prove<P: Prover<SC, A>>(
+    /// a STARK proof that the execution record is valid, with the default options.
+    pub fn prove<P: Prover<SC, A> + Default>(
         &self,
         pk: &ProvingKey<SC>,
         record: A::Record,
         challenger: &mut SC::Challenger,
     ) -> Proof<SC>
+    where
+        A: for<'a> Air<ProverConstraintFolder<'a, SC>>
+            + Air<InteractionBuilder<SC::Val>>
+            + for<'a> Air<VerifierConstraintFolder<'a, SC>>
+            + for<'a> Air<DebugConstraintBuilder<'a, SC::Val, SC::Challenge>>,
+    {
+        self.prove_with_options(
+            &P::default(),
+            pk,
+            record,
+            challenger,
+            &<A::Record as MachineRecord>::Config::default(),
+            &ProgressMonitor::default(),
+        )
+        .expect("a proof without a cancellation token cannot be cancelled")
+    }
+
+    /// Prove the execution record is valid with `prover`, splitting it into shards with
+    /// `sharding_config`.
+    ///
+    /// The progress is reported to `monitor`, and [`ProverError::Cancelled`] is returned if the
+    /// cancellation of the proof is requested before it is done.
+    pub fn prove_with_options<P: Prover<SC, A>>(
+        &self,
+        prover: &P,
+        pk: &ProvingKey<SC>,
+        record: A::Record,
+        challenger: &mut SC::Challenger,
+        sharding_config: &<A::Record as MachineRecord>::Config,
+        monitor: &ProgressMonitor,
+    ) -> Result<Proof<SC>, ProverError>
     where
         A: for<'a> Air<ProverConstraintFolder<'a, SC>>
             + Air<InteractionBuilder<SC::Val>>
@@ -120,19 +279,37 @@ impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> MachineStark<SC, A> {
             + for<'a> Air<DebugConstraintBuilder<'a, SC::Val, SC::Challenge>>,
     {
         tracing::debug!("sharding the execution record");
-        let shards = self.shard(record, &<A::Record as MachineRecord>::Config::default());
+        let shards = self.shard(record, sharding_config);
+
+        // The proof is bound to the program by the preprocessed traces, and to the public values.
+        let public_values = shards
+            .iter()
+            .flat_map(|shard| shard.public_values::<SC::Val>())
+            .collect::<Vec<_>>();
+        pk.observe_into(challenger);
+        challenger.observe_slice(&public_values);
 
         tracing::debug!("generating the shard proofs");
-        P::prove_shards(self, pk, shards, challenger)
+        let shard_proofs = prover.prove_shards(self, pk, shards, challenger, monitor)?;
+        Ok(Proof {
+            shard_proofs,
+            public_values,
+        })
     }
 
     pub const fn config(&self) -> &SC {
         &self.config
     }
 
+    /// Verifies the proof of a program with the verifying key `vk` of the program.
+    ///
+    /// The challenger observes the verifying key, the public values and the main commitments of
+    /// all the shards, and the cumulative sums of the shards must balance the public values, which
+    /// the program sends as [`InteractionKind::PublicValues`](crate::lookup::InteractionKind)
+    /// interactions.
     pub fn verify(
         &self,
-        _vk: &VerifyingKey<SC>,
+        vk: &VerifyingKey<SC>,
         proof: &Proof<SC>,
         challenger: &mut SC::Challenger,
     ) -> Result<(), ProgramVerificationError>
@@ -140,68 +317,256 @@ impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> MachineStark<SC, A> {
         SC::Challenger: Clone,
         A: for<'a> Air<VerifierConstraintFolder<'a, SC>>,
     {
+        vk.observe_into(challenger);
+        challenger.observe_slice(&proof.public_values);
+
         // TODO: Observe the challenges in a tree-like structure for easily verifiable reconstruction
         // in a map-reduce recursion setting.
         #[cfg(feature = "perf")]
         tracing::debug_span!("observe challenges for all shards").in_scope(|| {
             proof.shard_proofs.iter().for_each(|proof| {
-                challenger.observe(proof.commitment.main_commit.clone());
+                challenger.observe(proof.main_commit().clone());
             });
         });
 
         // Verify the segment proofs.
         tracing::info!("verifying shard proofs");
-        for (i, proof) in proof.shard_proofs.iter().enumerate() {
-            tracing::debug_span!("verifying shard", segment = i).in_scope(|| {
-                let chips = self
-                    .chips()
-                    .iter()
-                    .filter(|chip| proof.chip_ids.contains(&chip.name()))
-                    .collect::<Vec<_>>();
-                Verifier::verify_shard(&self.config, &chips, &mut challenger.clone(), proof)
-                    .map_err(ProgramVerificationError::InvalidSegmentProof)
-            })?;
-        }
+        let sum =
+            self.verify_shard_proofs(vk, proof.shard_proofs.iter().enumerate(), challenger)?;
         tracing::info!("success");
 
-        // Verify the cumulative sum is 0.
-        let mut sum = SC::Challenge::zero();
+        // Verify the cumulative sum balances the public values.
         #[cfg(feature = "perf")]
-        {
-            for proof in proof.shard_proofs.iter() {
+        let expected = public_values_sum::<SC>(&proof.public_values, challenger);
+        #[cfg(not(feature = "perf"))]
+        let expected = SC::Challenge::zero();
+        match sum == expected {
+            true => Ok(()),
+            false => Err(ProgramVerificationError::NonZeroCumulativeSum),
+        }
+    }
+
+    /// Verifies some of the shard proofs of a proof, given as `(shard, proof)` pairs, against the
+    /// main commitments of all the shards of the proof.
+    ///
+    /// The challenger must be in the state the whole proof is verified from, after observing the
+    /// verifying key and the public values and before observing the main commitments. Returns the
+    /// sum of the cumulative sums of the shards, which over all the shards of a valid proof is the
+    /// [sum of its public values](public_values_sum), so that partial proofs verified independently
+    /// can be checked together.
+    pub fn verify_shards<'a>(
+        &self,
+        vk: &VerifyingKey<SC>,
+        shard_proofs: impl IntoIterator<Item = (usize, &'a ShardProof<SC>)>,
+        main_commits: &[Com<SC>],
+        challenger: &mut SC::Challenger,
+    ) -> Result<SC::Challenge, ProgramVerificationError>
+    where
+        SC: 'a,
+        SC::Challenger: Clone,
+        Com<SC>: PartialEq,
+        A: for<'b> Air<VerifierConstraintFolder<'b, SC>>,
+    {
+        let shard_proofs = shard_proofs.into_iter().collect::<Vec<_>>();
+        for (shard, proof) in shard_proofs.iter() {
+            let main_commit =
+                main_commits
+                    .get(*shard)
+                    .ok_or(ProgramVerificationError::InvalidShardIndex {
+                        shard: *shard,
+                        num_shards: main_commits.len(),
+                    })?;
+            if main_commit != proof.main_commit() {
+                return Err(ProgramVerificationError::InvalidShardProof {
+                    shard: *shard,
+                    error: VerificationError::MainCommitmentMismatch,
+                });
+            }
+        }
+
+        #[cfg(feature = "perf")]
+        main_commits.iter().for_each(|commit| {
+            challenger.observe(commit.clone());
+        });
+
+        self.verify_shard_proofs(vk, shard_proofs, challenger)
+    }
+
+    /// Verifies the proof of the shard at index `shard` against the main commitments of all the
+    /// shards of the proof, and returns its cumulative sum. See [`Self::verify_shards`].
+    pub fn verify_shard(
+        &self,
+        vk: &VerifyingKey<SC>,
+        shard: usize,
+        proof: &ShardProof<SC>,
+        main_commits: &[Com<SC>],
+        challenger: &mut SC::Challenger,
+    ) -> Result<SC::Challenge, ProgramVerificationError>
+    where
+        SC::Challenger: Clone,
+        Com<SC>: PartialEq,
+        A: for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+    {
+        self.verify_shards(vk, [(shard, proof)], main_commits, challenger)
+    }
+
+    /// Verifies the shard proofs with copies of a challenger which observed all the main
+    /// commitments, and returns the sum of their cumulative sums.
+    fn verify_shard_proofs<'a>(
+        &self,
+        vk: &VerifyingKey<SC>,
+        shard_proofs: impl IntoIterator<Item = (usize, &'a ShardProof<SC>)>,
+        challenger: &SC::Challenger,
+    ) -> Result<SC::Challenge, ProgramVerificationError>
+    where
+        SC: 'a,
+        SC::Challenger: Clone,
+        A: for<'b> Air<VerifierConstraintFolder<'b, SC>>,
+    {
+        let mut sum = SC::Challenge::zero();
+        for (shard, proof) in shard_proofs {
+            tracing::debug_span!("verifying shard", segment = shard)
+                .in_scope(|| {
+                    let chips = self.shard_proof_chips(proof)?;
+                    Verifier::verify_shard(&self.config, vk, &chips, &mut challenger.clone(), proof)
+                })
+                .map_err(|error| ProgramVerificationError::InvalidShardProof { shard, error })?;
+
+            #[cfg(feature = "perf")]
+            {
                 sum += proof.cumulative_sum();
             }
         }
+        Ok(sum)
+    }
 
-        match sum.is_zero() {
-            true => Ok(()),
-            false => Err(ProgramVerificationError::NonZeroCumulativeSum),
+    /// The chips of a shard proof, which must be chips of the machine in the order of the machine.
+    fn shard_proof_chips(
+        &self,
+        proof: &ShardProof<SC>,
+    ) -> Result<Vec<&MachineChip<SC, A>>, VerificationError> {
+        let mut chips = Vec::with_capacity(proof.chip_ids.len());
+        let mut next = 0;
+        for name in proof.chip_ids.iter() {
+            let Some(index) = self.chips.iter().position(|chip| &chip.name() == name) else {
+                return Err(VerificationError::InvalidChipSet(format!(
+                    "unknown chip {}",
+                    name
+                )));
+            };
+            if index < next {
+                return Err(VerificationError::InvalidChipSet(format!(
+                    "chip {} is duplicated or out of order",
+                    name
+                )));
+            }
+            chips.push(&self.chips[index]);
+            next = index + 1;
         }
+        Ok(chips)
     }
 }
 
+/// The sum the cumulative sums of the shards of a proof add up to: the public values are the
+/// receives of the [`InteractionKind::PublicValues`](crate::lookup::InteractionKind) interactions
+/// sending `[i, public_values[i]]`, which no chip receives.
+///
+/// The challenger must have observed the main commitments of all the shards, so that it samples
+/// the permutation challenges of the shards.
+pub fn public_values_sum<SC: StarkGenericConfig>(
+    public_values: &[SC::Val],
+    challenger: &SC::Challenger,
+) -> SC::Challenge
+where
+    SC::Challenger: Clone,
+{
+    let mut challenger = challenger.clone();
+    let alpha = challenger.sample_ext_element::<SC::Challenge>();
+    let beta = challenger.sample_ext_element::<SC::Challenge>();
+    let rlc = alpha.exp_u64(InteractionKind::PublicValues as u64 + 1);
+    public_values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| (rlc + SC::Challenge::from_canonical_usize(i) + beta * *value).inverse())
+        .sum()
+}
+
 #[derive(Debug)]
 pub enum ProgramVerificationError {
-    InvalidSegmentProof(VerificationError),
+    /// The proof of a shard is invalid.
+    InvalidShardProof {
+        shard: usize,
+        error: VerificationError,
+    },
     InvalidGlobalProof(VerificationError),
+    /// A shard proof was given for a shard the proof does not have.
+    InvalidShardIndex {
+        shard: usize,
+        num_shards: usize,
+    },
+    /// The cumulative sums of the shards do not add up to the sum of the public values.
     NonZeroCumulativeSum,
     DebugInteractionsFailed,
 }
 
+impl Display for ProgramVerificationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramVerificationError::InvalidShardProof { shard, error } => {
+                write!(f, "Invalid proof of shard {}: {}", shard, error)
+            }
+            ProgramVerificationError::InvalidGlobalProof(error) => {
+                write!(f, "Invalid global proof: {}", error)
+            }
+            ProgramVerificationError::InvalidShardIndex { shard, num_shards } => {
+                write!(
+                    f,
+                    "Invalid shard {} of a proof of {} shards",
+                    shard, num_shards
+                )
+            }
+            ProgramVerificationError::NonZeroCumulativeSum => {
+                write!(
+                    f,
+                    "The cumulative sums of the shards do not balance the public values"
+                )
+            }
+            ProgramVerificationError::DebugInteractionsFailed => {
+                write!(f, "The interactions of the chips do not balance")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProgramVerificationError {}
+
 #[cfg(test)]
 #[allow(non_snake_case)]
 pub mod tests {
 
+    use crate::air::MachineAir;
     use crate::runtime::tests::ecall_lwa_program;
     use crate::runtime::tests::fibonacci_program;
     use crate::runtime::tests::simple_memory_program;
     use crate::runtime::tests::simple_program;
+    use crate::runtime::ExecutionRecord;
     use crate::runtime::Instruction;
     use crate::runtime::Opcode;
     use crate::runtime::Program;
+    use crate::runtime::Runtime;
+    use crate::stark::{
+        LocalProver, MachineRecord, ProgramVerificationError, ProverError, RiscvAir, ShardProof,
+        StarkGenericConfig, VerificationError,
+    };
     use crate::utils;
     use crate::utils::run_test;
     use crate::utils::setup_logger;
+    use crate::utils::{
+        BabyBearBlake3, CancellationToken, MemoryBudget, ProgressMonitor, ProvePhase,
+        ProverOptions, StarkUtils,
+    };
+    use p3_field::{AbstractField, Field};
+    use std::sync::mpsc::channel;
 
     #[test]
     fn test_simple_prove() {
@@ -349,4 +714,204 @@ pub mod tests {
         let program = simple_memory_program();
         run_test(program).unwrap();
     }
+
+    #[test]
+    fn test_generate_dependencies_in_parallel() {
+        let mut runtime = Runtime::new(fibonacci_program());
+        runtime.run();
+        let machine = RiscvAir::machine(BabyBearBlake3::new());
+
+        // Generating the dependencies chip by chip gives the same record as the phases.
+        let mut sequential = runtime.record.clone();
+        for chip in machine.chips() {
+            let mut output = ExecutionRecord::default();
+            output.set_index(sequential.index());
+            chip.generate_dependencies(&sequential, &mut output);
+            sequential.append(&mut output);
+        }
+        let mut parallel = runtime.record;
+        machine.generate_dependencies(&mut parallel);
+        assert!(!parallel.byte_lookups.is_empty());
+        assert_eq!(
+            bincode::serialize(&parallel).unwrap(),
+            bincode::serialize(&sequential).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_prove_cancelled() {
+        let options = ProverOptions::default().with_shard_size(1 << 10);
+        let mut runtime = Runtime::with_options(fibonacci_program(), options.runtime_options());
+        runtime.run();
+
+        // Cancel the proof once the first shard is committed.
+        let token = CancellationToken::new();
+        let (sender, receiver) = channel();
+        let monitor = ProgressMonitor::new()
+            .with_cancellation(token.clone())
+            .with_callback(move |progress| {
+                if progress.phase == ProvePhase::Commit && progress.done == 1 {
+                    token.cancel();
+                }
+                sender.send(progress.phase).unwrap();
+            });
+
+        let machine = RiscvAir::machine(BabyBearBlake3::new());
+        let (pk, _) = machine.setup(runtime.program.as_ref());
+        let mut challenger = machine.config().challenger();
+        let result = machine.prove_with_options(
+            &LocalProver::with_options(options.clone()),
+            &pk,
+            runtime.record,
+            &mut challenger,
+            &options.sharding_config(),
+            &monitor,
+        );
+        assert!(matches!(result, Err(ProverError::Cancelled)));
+        assert!(receiver.try_iter().all(|phase| phase == ProvePhase::Commit));
+    }
+
+    #[test]
+    fn test_prove_with_memory_budget() {
+        setup_logger();
+        let spill_dir = tempfile::tempdir().unwrap();
+        let budgets = [
+            MemoryBudget::new(0).with_spill_dir(spill_dir.path()),
+            MemoryBudget::new(0),
+            MemoryBudget::new(usize::MAX),
+        ];
+        for budget in budgets {
+            let options = ProverOptions::default()
+                .with_shard_size(1 << 12)
+                .with_memory_budget(budget.clone());
+            let mut runtime = Runtime::with_options(fibonacci_program(), options.runtime_options());
+            runtime.run();
+
+            let machine = RiscvAir::machine(BabyBearBlake3::new());
+            let (pk, vk) = machine.setup(runtime.program.as_ref());
+            let monitor = ProgressMonitor::new();
+            let mut challenger = machine.config().challenger();
+            let proof = machine
+                .prove_with_options(
+                    &LocalProver::with_options(options.clone()),
+                    &pk,
+                    runtime.record,
+                    &mut challenger,
+                    &options.sharding_config(),
+                    &monitor,
+                )
+                .unwrap();
+            let mut challenger = machine.config().challenger();
+            machine.verify(&vk, &proof, &mut challenger).unwrap();
+
+            let usage = monitor.memory_usage();
+            let num_shards = proof.shard_proofs.len();
+            assert_eq!(usage.current_bytes, 0);
+            assert!(usage.peak_bytes > 0);
+            if budget.limit == usize::MAX {
+                assert_eq!(usage.shards_kept, num_shards);
+            } else if budget.spill_dir.is_some() {
+                assert_eq!(usage.shards_spilled, num_shards);
+                assert_eq!(usage.bytes_read, usage.bytes_written);
+            } else {
+                assert_eq!(usage.shards_recomputed, num_shards);
+                assert_eq!(usage.bytes_written, 0);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "perf")]
+    fn test_verify_shards() {
+        type Challenge = <BabyBearBlake3 as StarkGenericConfig>::Challenge;
+
+        let options = ProverOptions::default().with_shard_size(1 << 12);
+        let mut runtime = Runtime::with_options(fibonacci_program(), options.runtime_options());
+        runtime.run();
+
+        let machine = RiscvAir::machine(BabyBearBlake3::new());
+        let (pk, vk) = machine.setup(runtime.program.as_ref());
+        let proof = machine
+            .prove_with_options(
+                &LocalProver::new(),
+                &pk,
+                runtime.record,
+                &mut machine.config().challenger(),
+                &options.sharding_config(),
+                &ProgressMonitor::new(),
+            )
+            .unwrap();
+        let main_commits = proof
+            .shard_proofs
+            .iter()
+            .map(|proof| proof.main_commit().clone())
+            .collect::<Vec<_>>();
+        assert!(main_commits.len() > 1);
+        let challenger = machine.config().challenger();
+
+        // The partial proofs verify independently, and their cumulative sums add up to zero.
+        let (first, rest) = proof.shard_proofs.split_at(1);
+        let first_sum = machine
+            .verify_shard(&vk, 0, &first[0], &main_commits, &mut challenger.clone())
+            .unwrap();
+        let rest_sum = machine
+            .verify_shards(
+                &vk,
+                (1..).zip(rest.iter()),
+                &main_commits,
+                &mut challenger.clone(),
+            )
+            .unwrap();
+        assert!((first_sum + rest_sum).is_zero());
+
+        // A shard proof only verifies at its index.
+        assert!(matches!(
+            machine.verify_shard(&vk, 1, &first[0], &main_commits, &mut challenger.clone()),
+            Err(ProgramVerificationError::InvalidShardProof {
+                shard: 1,
+                error: VerificationError::MainCommitmentMismatch,
+            })
+        ));
+        assert!(matches!(
+            machine.verify_shard(&vk, 9, &first[0], &main_commits, &mut challenger.clone()),
+            Err(ProgramVerificationError::InvalidShardIndex {
+                shard: 9,
+                num_shards,
+            }) if num_shards == main_commits.len()
+        ));
+
+        // The errors of a tampered shard proof name the chip and the failing check.
+        let tampered = |tamper: &dyn Fn(&mut ShardProof<BabyBearBlake3>)| {
+            let bytes = bincode::serialize(&proof.shard_proofs[0]).unwrap();
+            let mut shard_proof: ShardProof<BabyBearBlake3> = bincode::deserialize(&bytes).unwrap();
+            tamper(&mut shard_proof);
+            match machine.verify_shard(&vk, 0, &shard_proof, &main_commits, &mut challenger.clone())
+            {
+                Err(ProgramVerificationError::InvalidShardProof { shard: 0, error }) => error,
+                result => panic!("unexpected result: {:?}", result.map(|_| ())),
+            }
+        };
+        let chip = &first[0].chip_ids[0];
+
+        let error = tampered(&|proof| proof.chip_ids.reverse());
+        assert!(matches!(error, VerificationError::InvalidChipSet(_)));
+        let error = tampered(&|proof| proof.chip_ids[0] = "Unknown".to_string());
+        assert!(matches!(error, VerificationError::InvalidChipSet(_)));
+        let error = tampered(&|proof| {
+            proof.opened_values.chips.pop();
+        });
+        assert!(matches!(error, VerificationError::InvalidChipSet(_)));
+        let error = tampered(&|proof| {
+            proof.opened_values.chips[0].main.local.pop();
+        });
+        assert!(matches!(error, VerificationError::InvalidShape(name) if &name == chip));
+        let error = tampered(&|proof| {
+            proof.opened_values.chips[0].main.local[0] += Challenge::one();
+        });
+        assert!(matches!(error, VerificationError::InvalidopeningArgument));
+        let error = tampered(&|proof| {
+            proof.opened_values.chips[0].cumulative_sum += Challenge::one();
+        });
+        assert!(matches!(error, VerificationError::OodEvaluationMismatch(name) if &name == chip));
+    }
 }
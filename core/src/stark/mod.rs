@@ -2,12 +2,17 @@ mod air;
 mod chip;
 mod config;
 mod debug;
+mod distributed;
 mod folder;
+#[cfg(all(test, feature = "perf"))]
+mod golden;
 mod machine;
+mod packing;
 mod permutation;
 mod prover;
 mod quotient;
 mod record;
+mod symbolic;
 mod types;
 mod util;
 mod verifier;
@@ -17,12 +22,15 @@ pub use air::*;
 pub use chip::*;
 pub use config::*;
 pub use debug::*;
+pub use distributed::*;
 pub use folder::*;
 pub use machine::*;
+pub use packing::*;
 pub use permutation::*;
 pub use prover::*;
 pub use quotient::*;
 pub use record::*;
+pub use symbolic::*;
 pub use types::*;
 pub use verifier::*;
 
@@ -0,0 +1,817 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::mem::take;
+use std::sync::Arc;
+
+use p3_air::BaseAir;
+use p3_field::{AbstractExtensionField, PrimeField32};
+
+use super::{MachineStark, RiscvAir, StarkGenericConfig};
+use crate::air::MachineAir;
+use crate::cpu::CpuEvent;
+use crate::runtime::{ExecutionRecord, ShardingConfig};
+use crate::syscall::precompiles::blake3::{Blake3HashChip, OPERATION_COUNT, ROUND_COUNT};
+use crate::syscall::precompiles::edwards::NUM_SCALAR_BITS;
+use crate::syscall::precompiles::keccak256::Keccak256Chip;
+use crate::syscall::precompiles::sha256::ShaCompressBlocksChip;
+
+/// The smallest height of a trace, as padded by `pad_to_power_of_two`.
+const MIN_HEIGHT: usize = 8;
+
+/// The height of the trace of a chip with `rows` rows, or zero if the chip is not included.
+const fn padded_height(rows: usize) -> usize {
+    if rows == 0 {
+        0
+    } else if rows < MIN_HEIGHT {
+        MIN_HEIGHT
+    } else {
+        rows.next_power_of_two()
+    }
+}
+
+/// The largest power of two which is at most `n`, which must be positive.
+const fn prev_power_of_two(n: usize) -> usize {
+    1 << (usize::BITS - 1 - n.leading_zeros())
+}
+
+macro_rules! mobile_events {
+    ($($kind:ident => $field:ident, $rows_per_event:expr;)*) => {
+        /// The kinds of events which can be proven in any shard, as each of them takes a fixed
+        /// number of rows of a single chip.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum MobileEvents {
+            $($kind,)*
+        }
+
+        impl MobileEvents {
+            pub const ALL: &'static [MobileEvents] = &[$(MobileEvents::$kind,)*];
+
+            /// The number of events of this kind in `record`.
+            pub fn len(self, record: &ExecutionRecord) -> usize {
+                match self {
+                    $(MobileEvents::$kind => record.$field.len(),)*
+                }
+            }
+
+            /// The number of rows taken by each event of this kind.
+            pub const fn rows_per_event(self) -> usize {
+                match self {
+                    $(MobileEvents::$kind => $rows_per_event,)*
+                }
+            }
+
+            /// Moves the last `n` events of this kind from `from` to `to`, which has none.
+            fn move_last(self, from: &mut ExecutionRecord, to: &mut ExecutionRecord, n: usize) {
+                match self {
+                    $(MobileEvents::$kind => {
+                        let at = from.$field.len() - n;
+                        to.$field = from.$field.split_off(at);
+                    })*
+                }
+            }
+        }
+    };
+}
+
+mobile_events! {
+    Add => add_events, 1;
+    Sub => sub_events, 1;
+    Mul => mul_events, 1;
+    Bitwise => bitwise_events, 1;
+    ShiftLeft => shift_left_events, 1;
+    ShiftRight => shift_right_events, 1;
+    DivRem => divrem_events, 1;
+    Lt => lt_events, 1;
+    FieldLtu => field_events, 1;
+    ShaExtend => sha_extend_events, 48;
+    ShaCompress => sha_compress_events, 80;
+    Sha512Extend => sha512_extend_events, 64;
+    Sha512Compress => sha512_compress_events, 96;
+    KeccakPermute => keccak_permute_events, p3_keccak_air::NUM_ROUNDS;
+    EdAdd => ed_add_events, 1;
+    EdDecompress => ed_decompress_events, 1;
+    EdDoubleScalarMul => ed_double_scalar_mul_events, NUM_SCALAR_BITS;
+    K256Decompress => k256_decompress_events, 1;
+    WeierstrassAdd => weierstrass_add_events, 1;
+    WeierstrassDouble => weierstrass_double_events, 1;
+    Blake3CompressInner => blake3_compress_inner_events, ROUND_COUNT * OPERATION_COUNT;
+}
+
+macro_rules! whole_events {
+    ($($kind:ident => $field:ident, $chip:ident;)*) => {
+        /// The kinds of events which take a variable number of rows of a single chip, so that
+        /// each of them is proven whole in any shard.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum WholeEvents {
+            $($kind,)*
+        }
+
+        impl WholeEvents {
+            pub const ALL: &'static [WholeEvents] = &[$(WholeEvents::$kind,)*];
+
+            /// The number of events of this kind in `record`.
+            pub fn len(self, record: &ExecutionRecord) -> usize {
+                match self {
+                    $(WholeEvents::$kind => record.$field.len(),)*
+                }
+            }
+
+            /// The number of rows taken by each event of this kind in `record`.
+            pub fn event_rows(self, record: &ExecutionRecord) -> Vec<usize> {
+                match self {
+                    $(WholeEvents::$kind => record.$field.iter().map($chip::event_rows).collect(),)*
+                }
+            }
+
+            /// Moves the events of this kind from `from` to the shards, the i-th event to the
+            /// shard `assignment[i]`.
+            fn distribute(
+                self,
+                from: &mut ExecutionRecord,
+                shards: &mut [ExecutionRecord],
+                assignment: &[usize],
+            ) {
+                match self {
+                    $(WholeEvents::$kind => {
+                        for (event, &s) in take(&mut from.$field).into_iter().zip(assignment) {
+                            shards[s].$field.push(event);
+                        }
+                    })*
+                }
+            }
+        }
+    };
+}
+
+whole_events! {
+    ShaCompressBlocks => sha_compress_blocks_events, ShaCompressBlocksChip;
+    Keccak256 => keccak256_events, Keccak256Chip;
+    Blake3Hash => blake3_hash_events, Blake3HashChip;
+}
+
+/// Where the events of a chip are proven.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChipPlacement {
+    /// The chip proves the CPU events of the shard.
+    Cpu,
+    /// The chip has the same rows in every shard.
+    EveryShard,
+    /// The events of the chip are all proven in the first shard.
+    First,
+    /// The events of the chip are all proven in the last shard.
+    Last,
+    /// The events of the chip can be proven in any shard.
+    Mobile(MobileEvents),
+    /// Each event of the chip can be proven in any shard, but is not split between shards.
+    Whole(WholeEvents),
+}
+
+/// The cost of a chip of [`RiscvAir`] in a shard.
+#[derive(Clone)]
+pub struct ChipCost {
+    pub name: String,
+    /// The number of base field cells committed for each row of the trace: the columns of the
+    /// main trace and those of the permutation trace, which is over the extension field.
+    pub cells_per_row: usize,
+    pub placement: ChipPlacement,
+    rows: Arc<dyn Fn(&ExecutionRecord) -> usize + Send + Sync>,
+}
+
+impl ChipCost {
+    fn new<F: PrimeField32>(air: RiscvAir<F>, cells_per_row: usize) -> Self {
+        let placement = match &air {
+            RiscvAir::Cpu(_) => ChipPlacement::Cpu,
+            RiscvAir::Program(_) => ChipPlacement::EveryShard,
+            RiscvAir::ByteLookup(_) => ChipPlacement::First,
+            RiscvAir::MemoryInit(_) | RiscvAir::MemoryFinal(_) | RiscvAir::ProgramMemory(_) => {
+                ChipPlacement::Last
+            }
+            RiscvAir::Sha256CompressBlocks(_) => {
+                ChipPlacement::Whole(WholeEvents::ShaCompressBlocks)
+            }
+            RiscvAir::Keccak256(_) => ChipPlacement::Whole(WholeEvents::Keccak256),
+            RiscvAir::Blake3Hash(_) => ChipPlacement::Whole(WholeEvents::Blake3Hash),
+            RiscvAir::Add(_) => ChipPlacement::Mobile(MobileEvents::Add),
+            RiscvAir::Sub(_) => ChipPlacement::Mobile(MobileEvents::Sub),
+            RiscvAir::Mul(_) => ChipPlacement::Mobile(MobileEvents::Mul),
+            RiscvAir::Bitwise(_) => ChipPlacement::Mobile(MobileEvents::Bitwise),
+            RiscvAir::ShiftLeft(_) => ChipPlacement::Mobile(MobileEvents::ShiftLeft),
+            RiscvAir::ShiftRight(_) => ChipPlacement::Mobile(MobileEvents::ShiftRight),
+            RiscvAir::DivRem(_) => ChipPlacement::Mobile(MobileEvents::DivRem),
+            RiscvAir::Lt(_) => ChipPlacement::Mobile(MobileEvents::Lt),
+            RiscvAir::FieldLTU(_) => ChipPlacement::Mobile(MobileEvents::FieldLtu),
+            RiscvAir::Sha256Extend(_) => ChipPlacement::Mobile(MobileEvents::ShaExtend),
+            RiscvAir::Sha256Compress(_) => ChipPlacement::Mobile(MobileEvents::ShaCompress),
+            RiscvAir::Sha512Extend(_) => ChipPlacement::Mobile(MobileEvents::Sha512Extend),
+            RiscvAir::Sha512Compress(_) => ChipPlacement::Mobile(MobileEvents::Sha512Compress),
+            RiscvAir::KeccakP(_) => ChipPlacement::Mobile(MobileEvents::KeccakPermute),
+            RiscvAir::Ed25519Add(_) => ChipPlacement::Mobile(MobileEvents::EdAdd),
+            RiscvAir::Ed25519Decompress(_) => ChipPlacement::Mobile(MobileEvents::EdDecompress),
+            RiscvAir::Ed25519DoubleScalarMul(_) => {
+                ChipPlacement::Mobile(MobileEvents::EdDoubleScalarMul)
+            }
+            RiscvAir::K256Decompress(_) => ChipPlacement::Mobile(MobileEvents::K256Decompress),
+            RiscvAir::Secp256k1Add(_) => ChipPlacement::Mobile(MobileEvents::WeierstrassAdd),
+            RiscvAir::Secp256k1Double(_) => ChipPlacement::Mobile(MobileEvents::WeierstrassDouble),
+            RiscvAir::Blake3Compress(_) => ChipPlacement::Mobile(MobileEvents::Blake3CompressInner),
+        };
+        Self {
+            name: air.name(),
+            cells_per_row,
+            placement,
+            rows: Arc::new(move |record| {
+                if air.included(record) {
+                    air.num_rows(record)
+                } else {
+                    0
+                }
+            }),
+        }
+    }
+
+    /// The number of rows of the chip in `record`, before padding.
+    pub fn rows(&self, record: &ExecutionRecord) -> usize {
+        (self.rows)(record)
+    }
+
+    /// The number of cells committed by the chip for `record`, after padding.
+    pub fn cells(&self, record: &ExecutionRecord) -> usize {
+        padded_height(self.rows(record)) * self.cells_per_row
+    }
+}
+
+impl Debug for ChipCost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChipCost")
+            .field("name", &self.name)
+            .field("cells_per_row", &self.cells_per_row)
+            .field("placement", &self.placement)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Estimates the number of cells committed for a shard from the padded height and the width of
+/// every chip of [`RiscvAir`].
+#[derive(Debug, Clone)]
+pub struct ShardCostModel {
+    chips: Vec<ChipCost>,
+}
+
+impl ShardCostModel {
+    pub fn new<SC: StarkGenericConfig>(machine: &MachineStark<SC, RiscvAir<SC::Val>>) -> Self {
+        let d = <SC::Challenge as AbstractExtensionField<SC::Val>>::D;
+        // The machine is built from the AIRs of `get_all`, in the same order.
+        let chips = machine
+            .chips()
+            .iter()
+            .zip(RiscvAir::<SC::Val>::get_all())
+            .map(|(chip, air)| {
+                debug_assert_eq!(chip.name(), air.name());
+                let cells_per_row = chip.width() + (chip.num_interactions() + 1) * d;
+                ChipCost::new(air, cells_per_row)
+            })
+            .collect();
+        Self { chips }
+    }
+
+    pub fn chips(&self) -> &[ChipCost] {
+        &self.chips
+    }
+
+    /// The number of cells committed for `shard`.
+    pub fn shard_cells(&self, shard: &ExecutionRecord) -> usize {
+        self.chips.iter().map(|chip| chip.cells(shard)).sum()
+    }
+
+    /// The number of cells committed for a shard whose chips have the given rows.
+    fn cells_of_rows(&self, rows: &[usize]) -> usize {
+        self.chips
+            .iter()
+            .zip(rows)
+            .map(|(chip, &rows)| padded_height(rows) * chip.cells_per_row)
+            .sum()
+    }
+}
+
+/// A sharding strategy packing the events of an execution record so that the number of cells
+/// committed for each shard, according to a [`ShardCostModel`], stays under a target.
+///
+/// The CPU events are first split at the shard boundaries of the runtime and every `shard_size`
+/// events, as in the static sharding. The events of a variable number of rows are then assigned
+/// one by one, the largest first, each to the shard with the most room where it fits. The events
+/// of a fixed number of rows are assigned to the shards greedily, the most expensive kinds first,
+/// each shard taking as many events as fit in its remaining cells. While some events do not fit,
+/// the largest CPU chunks are split to make room in more shards.
+#[derive(Debug, Clone)]
+pub struct ShardPacking {
+    cost_model: ShardCostModel,
+    max_cells: usize,
+}
+
+/// The assignment of the events to the shards.
+struct Plan {
+    /// The number of mobile events of each shard, in the order of [`MobileEvents::ALL`].
+    counts: Vec<Vec<usize>>,
+    /// The shard of each event of a variable number of rows, in the order of [`WholeEvents::ALL`].
+    whole: Vec<Vec<usize>>,
+    /// The number of cells of the events which did not fit under the target.
+    overflow: usize,
+}
+
+impl ShardPacking {
+    pub fn new(cost_model: ShardCostModel, max_cells: usize) -> Self {
+        Self {
+            cost_model,
+            max_cells,
+        }
+    }
+
+    pub fn cost_model(&self) -> &ShardCostModel {
+        &self.cost_model
+    }
+
+    /// The target number of cells committed for each shard.
+    pub const fn max_cells(&self) -> usize {
+        self.max_cells
+    }
+
+    /// Splits `record` into shards, as [`MachineRecord::shard`](super::MachineRecord::shard).
+    pub fn shard(
+        &self,
+        mut record: ExecutionRecord,
+        config: &ShardingConfig,
+    ) -> Vec<ExecutionRecord> {
+        let mut chunks = cpu_chunks(&record.cpu_events, config.shard_size);
+        let plan = loop {
+            let plan = self.plan(&record, &chunks);
+            if plan.overflow == 0 {
+                break plan;
+            }
+            let num_splits = plan.overflow.div_ceil(self.max_cells);
+            if !split_largest(&mut chunks, num_splits) {
+                log::warn!(
+                    "{} cells do not fit in shards of {} cells",
+                    plan.overflow,
+                    self.max_cells
+                );
+                break plan;
+            }
+        };
+
+        let mut shards = (0..chunks.len())
+            .map(|i| ExecutionRecord::new(i as u32 + 1, record.program.clone()))
+            .collect::<Vec<_>>();
+
+        // Iterate from the end so that each shard takes its events off the end of the record.
+        for (shard, &len) in shards.iter_mut().zip(chunks.iter()).rev() {
+            let at = record.cpu_events.len() - len;
+            shard.cpu_events = record.cpu_events.split_off(at);
+        }
+        for (i, kind) in MobileEvents::ALL.iter().enumerate() {
+            for (shard, counts) in shards.iter_mut().zip(plan.counts.iter()).rev() {
+                kind.move_last(&mut record, shard, counts[i]);
+            }
+        }
+
+        for (kind, assignment) in WholeEvents::ALL.iter().zip(plan.whole.iter()) {
+            kind.distribute(&mut record, &mut shards, assignment);
+        }
+
+        // Put the byte lookups in the first shard.
+        shards[0].byte_lookups = take(&mut record.byte_lookups);
+
+        // Put the memory records in the last shard.
+        let last = shards.last_mut().unwrap();
+        last.first_memory_record = take(&mut record.first_memory_record);
+        last.last_memory_record = take(&mut record.last_memory_record);
+        last.program_memory_record = take(&mut record.program_memory_record);
+
+        shards
+    }
+
+    /// Assigns the events of `record` which may go to any shard to the shards of the given CPU
+    /// chunks.
+    fn plan(&self, record: &ExecutionRecord, chunks: &[usize]) -> Plan {
+        let chips = self.cost_model.chips();
+        let num_shards = chunks.len();
+        let mut rows = vec![vec![0; chips.len()]; num_shards];
+        // The rows of a shard of a single CPU event, which no split of the chunks can go under.
+        let mut min_rows = vec![0; chips.len()];
+        let mut mobile = Vec::new();
+        let mut whole = Vec::new();
+        for (c, chip) in chips.iter().enumerate() {
+            match chip.placement {
+                ChipPlacement::Cpu => {
+                    for (rows, &len) in rows.iter_mut().zip(chunks) {
+                        rows[c] = len;
+                    }
+                    min_rows[c] = 1;
+                }
+                ChipPlacement::EveryShard => {
+                    let n = chip.rows(record);
+                    rows.iter_mut().for_each(|rows| rows[c] = n);
+                    min_rows[c] = n;
+                }
+                ChipPlacement::First => rows[0][c] = chip.rows(record),
+                ChipPlacement::Last => rows[num_shards - 1][c] = chip.rows(record),
+                ChipPlacement::Mobile(kind) => mobile.push((c, kind)),
+                ChipPlacement::Whole(kind) => whole.push((c, kind)),
+            }
+        }
+        let mut cells = rows
+            .iter()
+            .map(|rows| self.cost_model.cells_of_rows(rows))
+            .collect::<Vec<_>>();
+        let min_cells = self.cost_model.cells_of_rows(&min_rows);
+        let mut overflow = 0;
+
+        // Assign the largest events of a variable number of rows first, each to the shard with
+        // the most room where it fits, or else to the shard where it adds the fewest cells.
+        let mut assignment = WholeEvents::ALL
+            .iter()
+            .map(|kind| vec![0; kind.len(record)])
+            .collect::<Vec<_>>();
+        let mut events = whole
+            .iter()
+            .flat_map(|&(c, kind)| {
+                let index = WholeEvents::ALL.iter().position(|k| *k == kind).unwrap();
+                let cells_per_row = chips[c].cells_per_row;
+                kind.event_rows(record)
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(i, n)| (c, index, i, n, n * cells_per_row))
+            })
+            .collect::<Vec<_>>();
+        events.sort_by_key(|&(.., event_cells)| std::cmp::Reverse(event_cells));
+        for (c, index, i, n, event_cells) in events {
+            let cells_per_row = chips[c].cells_per_row;
+            let after = |s: usize| {
+                cells[s]
+                    + (padded_height(rows[s][c] + n) - padded_height(rows[s][c])) * cells_per_row
+            };
+            let s = match (0..num_shards)
+                .filter(|&s| after(s) <= self.max_cells)
+                .min_by_key(|&s| cells[s])
+            {
+                Some(s) => s,
+                None => {
+                    // Splitting the chunks does not help an event which fits in no shard.
+                    if min_cells + padded_height(n) * cells_per_row <= self.max_cells {
+                        overflow += event_cells;
+                    }
+                    (0..num_shards).min_by_key(|&s| after(s)).unwrap()
+                }
+            };
+            add_rows(&mut rows[s][c], &mut cells[s], n, cells_per_row);
+            assignment[index][i] = s;
+        }
+
+        // Assign the most expensive events first, while the shards have the most room.
+        mobile.sort_by_key(|&(c, kind)| {
+            std::cmp::Reverse(kind.rows_per_event() * chips[c].cells_per_row)
+        });
+
+        let mut counts = vec![vec![0; MobileEvents::ALL.len()]; num_shards];
+        for (c, kind) in mobile {
+            let index = MobileEvents::ALL.iter().position(|k| *k == kind).unwrap();
+            let rows_per_event = kind.rows_per_event();
+            let cells_per_row = chips[c].cells_per_row;
+
+            let mut remaining = kind.len(record);
+            for s in 0..num_shards {
+                if remaining == 0 {
+                    break;
+                }
+                let current = padded_height(rows[s][c]) * cells_per_row;
+                let available = self.max_cells.saturating_sub(cells[s] - current) / cells_per_row;
+                if available < MIN_HEIGHT {
+                    continue;
+                }
+                let height = prev_power_of_two(available);
+                let n = (height.saturating_sub(rows[s][c]) / rows_per_event).min(remaining);
+                if n > 0 {
+                    add_rows(
+                        &mut rows[s][c],
+                        &mut cells[s],
+                        n * rows_per_event,
+                        cells_per_row,
+                    );
+                    counts[s][index] += n;
+                    remaining -= n;
+                }
+            }
+
+            // The events which do not fit go to the shard where they add the fewest cells.
+            if remaining > 0 {
+                let s = (0..num_shards)
+                    .min_by_key(|&s| {
+                        let after = padded_height(rows[s][c] + remaining * rows_per_event);
+                        cells[s] + (after - padded_height(rows[s][c])) * cells_per_row
+                    })
+                    .unwrap();
+                add_rows(
+                    &mut rows[s][c],
+                    &mut cells[s],
+                    remaining * rows_per_event,
+                    cells_per_row,
+                );
+                counts[s][index] += remaining;
+                overflow += remaining * rows_per_event * cells_per_row;
+            }
+        }
+
+        Plan {
+            counts,
+            whole: assignment,
+            overflow,
+        }
+    }
+}
+
+/// Adds `extra` rows to a chip of a shard, updating the cells of the shard.
+fn add_rows(rows: &mut usize, cells: &mut usize, extra: usize, cells_per_row: usize) {
+    *cells -= padded_height(*rows) * cells_per_row;
+    *rows += extra;
+    *cells += padded_height(*rows) * cells_per_row;
+}
+
+/// Splits the CPU events at the shard boundaries of the runtime and every `max_len` events, and
+/// returns the length of each chunk.
+fn cpu_chunks(events: &[CpuEvent], max_len: usize) -> Vec<usize> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for end in 1..=events.len() {
+        if end == events.len() || events[end].shard != events[start].shard {
+            let len = end - start;
+            chunks.extend(std::iter::repeat(max_len).take(len / max_len));
+            if len % max_len != 0 {
+                chunks.push(len % max_len);
+            }
+            start = end;
+        }
+    }
+    if chunks.is_empty() {
+        chunks.push(0);
+    }
+    chunks
+}
+
+/// Splits up to `num_splits` of the largest chunks in two, and returns whether any was split.
+///
+/// A chunk is split after a power of two of events, so that every chunk but the last of a runtime
+/// shard fills its padded trace: the transition constraints of the CPU must not apply between the
+/// last event of a chunk and the padding rows after it.
+fn split_largest(chunks: &mut Vec<usize>, num_splits: usize) -> bool {
+    let mut split = false;
+    for _ in 0..num_splits {
+        let (i, &len) = chunks
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, &len)| len)
+            .unwrap();
+        if len < 2 {
+            break;
+        }
+        let first = prev_power_of_two(len - 1);
+        chunks[i] = first;
+        chunks.insert(i + 1, len - first);
+        split = true;
+    }
+    split
+}
+
+/// The number of cells committed for each shard with the static sharding and with a packing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackingReport {
+    pub max_cells: usize,
+    pub static_cells: Vec<usize>,
+    pub packed_cells: Vec<usize>,
+}
+
+impl PackingReport {
+    /// Shards `record` with the static sharding of `shard_size` events and with `packing`, and
+    /// compares the cells committed for the shards.
+    pub fn new<SC: StarkGenericConfig>(
+        machine: &MachineStark<SC, RiscvAir<SC::Val>>,
+        record: ExecutionRecord,
+        shard_size: usize,
+        packing: &ShardPacking,
+    ) -> Self {
+        let static_config = ShardingConfig::new(shard_size);
+        let packed_config = ShardingConfig::new(shard_size).with_packing(packing.clone());
+        let cells = |shards: Vec<ExecutionRecord>| {
+            shards
+                .iter()
+                .map(|shard| packing.cost_model().shard_cells(shard))
+                .collect::<Vec<_>>()
+        };
+        Self {
+            max_cells: packing.max_cells(),
+            static_cells: cells(machine.shard(record.clone(), &static_config)),
+            packed_cells: cells(machine.shard(record, &packed_config)),
+        }
+    }
+}
+
+impl Display for PackingReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<8} {:>8} {:>16} {:>16} {:>12}",
+            "strategy", "shards", "total cells", "max shard cells", "over target"
+        )?;
+        for (name, cells) in [
+            ("static", &self.static_cells),
+            ("packed", &self.packed_cells),
+        ] {
+            writeln!(
+                f,
+                "{:<8} {:>8} {:>16} {:>16} {:>12}",
+                name,
+                cells.len(),
+                cells.iter().sum::<usize>(),
+                cells.iter().max().copied().unwrap_or(0),
+                cells
+                    .iter()
+                    .filter(|&&cells| cells > self.max_cells)
+                    .count()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{Program, Runtime};
+    use crate::stark::{LocalProver, MachineRecord};
+    use crate::syscall::precompiles::blake3::hash_tests::blake3_hash_program;
+    use crate::syscall::precompiles::keccak256::sponge_tests::keccak256_program;
+    use crate::syscall::precompiles::sha256::compress_blocks_tests::sha_compress_blocks_program;
+    use crate::utils::tests::{FIBONACCI_ELF, KECCAK_PERMUTE_ELF};
+    use crate::utils::{setup_logger, BabyBearBlake3, ProgressMonitor, ProverOptions, StarkUtils};
+
+    #[test]
+    fn test_split_largest() {
+        let mut chunks = vec![16, 5, 12];
+        assert!(split_largest(&mut chunks, 2));
+        assert_eq!(chunks, vec![8, 8, 5, 8, 4]);
+        let mut chunks = vec![1];
+        assert!(!split_largest(&mut chunks, 1));
+    }
+
+    #[test]
+    fn test_packing() {
+        setup_logger();
+        let machine = RiscvAir::machine(BabyBearBlake3::new());
+        let options = ProverOptions::default().with_shard_size(1 << 12);
+        for elf in [FIBONACCI_ELF, KECCAK_PERMUTE_ELF] {
+            let mut runtime = Runtime::with_options(Program::from(elf), options.runtime_options());
+            runtime.run();
+
+            // Pack the shards under the largest shard of the static sharding.
+            let model = ShardCostModel::new(&machine);
+            let static_shards = machine.shard(runtime.record.clone(), &options.sharding_config());
+            let max_cells = static_shards
+                .iter()
+                .map(|shard| model.shard_cells(shard))
+                .max()
+                .unwrap();
+            let packing = ShardPacking::new(model, max_cells);
+            let report = PackingReport::new(
+                &machine,
+                runtime.record.clone(),
+                options.shard_size,
+                &packing,
+            );
+            log::info!("\n{}", report);
+            assert!(report.packed_cells.iter().all(|&cells| cells <= max_cells));
+
+            // The packed shards keep every event.
+            let config = options.sharding_config().with_packing(packing);
+            let packed_shards = machine.shard(runtime.record.clone(), &config);
+            let stats = |shards: Vec<ExecutionRecord>| {
+                let mut total = ExecutionRecord::default();
+                for mut shard in shards {
+                    assert!(!shard.cpu_events.is_empty());
+                    shard.index = 0;
+                    total.append(&mut shard);
+                }
+                total.stats()
+            };
+            assert_eq!(stats(packed_shards), stats(static_shards));
+
+            let (pk, vk) = machine.setup(runtime.program.as_ref());
+            let mut challenger = machine.config().challenger();
+            let proof = machine
+                .prove_with_options(
+                    &LocalProver::with_options(options.clone()),
+                    &pk,
+                    runtime.record,
+                    &mut challenger,
+                    &config,
+                    &ProgressMonitor::new(),
+                )
+                .unwrap();
+            let mut challenger = machine.config().challenger();
+            machine.verify(&vk, &proof, &mut challenger).unwrap();
+        }
+    }
+    /// A program of hashes of increasing lengths with the precompiles whose events take a
+    /// variable number of rows.
+    fn precompile_heavy_program() -> Program {
+        let mut instructions = Vec::new();
+        for i in 1..=8 {
+            instructions.extend(sha_compress_blocks_program(i).instructions);
+            instructions.extend(keccak256_program(&vec![i as u8; 200 * i as usize]).instructions);
+            instructions.extend(blake3_hash_program(&vec![i as u8; 300 * i as usize]).instructions);
+        }
+        Program::new(instructions, 0, 0)
+    }
+
+    #[test]
+    fn test_packing_precompile_events() {
+        setup_logger();
+        let machine = RiscvAir::machine(BabyBearBlake3::new());
+        let options = ProverOptions::default().with_shard_size(1 << 12);
+        let mut runtime =
+            Runtime::with_options(precompile_heavy_program(), options.runtime_options());
+        runtime.run();
+
+        // Leave room in each shard for its share of the events of the static sharding, and for
+        // the largest event of every chip with a variable number of rows.
+        let model = ShardCostModel::new(&machine);
+        let static_shards = machine.shard(runtime.record.clone(), &options.sharding_config());
+        let is_whole = |chip: &ChipCost| matches!(chip.placement, ChipPlacement::Whole(_));
+        let max_rest = static_shards
+            .iter()
+            .map(|shard| {
+                model
+                    .chips()
+                    .iter()
+                    .filter(|chip| !is_whole(chip))
+                    .map(|chip| chip.cells(shard))
+                    .sum::<usize>()
+            })
+            .max()
+            .unwrap();
+        let largest_events = model
+            .chips()
+            .iter()
+            .filter_map(|chip| match chip.placement {
+                ChipPlacement::Whole(kind) => kind
+                    .event_rows(&runtime.record)
+                    .into_iter()
+                    .max()
+                    .map(|rows| padded_height(rows) * chip.cells_per_row),
+                _ => None,
+            })
+            .sum::<usize>();
+        let max_cells = max_rest + largest_events;
+
+        let packing = ShardPacking::new(model, max_cells);
+        let report = PackingReport::new(
+            &machine,
+            runtime.record.clone(),
+            options.shard_size,
+            &packing,
+        );
+        log::info!("\n{}", report);
+        assert!(report.packed_cells.iter().all(|&cells| cells <= max_cells));
+
+        // The events are spread over the shards, each of them whole.
+        let config = options.sharding_config().with_packing(packing);
+        let packed_shards = machine.shard(runtime.record.clone(), &config);
+        let with_events = |events: fn(&ExecutionRecord) -> usize| {
+            packed_shards
+                .iter()
+                .filter(|shard| events(shard) > 0)
+                .count()
+        };
+        assert!(with_events(|shard| shard.sha_compress_blocks_events.len()) > 1);
+        assert!(with_events(|shard| shard.keccak256_events.len()) > 1);
+        assert!(with_events(|shard| shard.blake3_hash_events.len()) > 1);
+        for kind in WholeEvents::ALL {
+            let total = packed_shards
+                .iter()
+                .map(|shard| kind.len(shard))
+                .sum::<usize>();
+            assert_eq!(total, kind.len(&runtime.record));
+        }
+
+        let (pk, vk) = machine.setup(runtime.program.as_ref());
+        let mut challenger = machine.config().challenger();
+        let proof = machine
+            .prove_with_options(
+                &LocalProver::with_options(options.clone()),
+                &pk,
+                runtime.record,
+                &mut challenger,
+                &config,
+                &ProgressMonitor::new(),
+            )
+            .unwrap();
+        let mut challenger = machine.config().challenger();
+        machine.verify(&vk, &proof, &mut challenger).unwrap();
+    }
+}
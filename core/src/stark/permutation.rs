@@ -2,6 +2,7 @@ use p3_air::{ExtensionBuilder, PairBuilder};
 use p3_field::{AbstractExtensionField, AbstractField, ExtensionField, Field, Powers, PrimeField};
 use p3_matrix::{dense::RowMajorMatrix, Matrix, MatrixRowSlices};
 use p3_maybe_rayon::prelude::*;
+use p3_uni_stark::{SymbolicExpression, SymbolicVariable};
 
 use super::util::batch_multiplicative_inverse_inplace;
 use crate::{air::MultiTableAirBuilder, lookup::Interaction};
@@ -31,7 +32,7 @@ pub fn generate_interaction_rlc_elements<F: Field, EF: AbstractExtensionField<F>
 pub(crate) fn generate_permutation_trace<F: PrimeField, EF: ExtensionField<F>>(
     sends: &[Interaction<F>],
     receives: &[Interaction<F>],
-    preprocessed: &Option<RowMajorMatrix<F>>,
+    preprocessed: Option<&RowMajorMatrix<F>>,
     main: &RowMajorMatrix<F>,
     random_elements: &[EF],
 ) -> RowMajorMatrix<EF> {
@@ -41,9 +42,6 @@ pub(crate) fn generate_permutation_trace<F: PrimeField, EF: ExtensionField<F>>(
     // Generate the RLC elements to uniquely identify each item in the looked up tuple.
     let betas = random_elements[1].powers();
 
-    // TODO: Get the preprocessed trace and handle it properly.
-    // let preprocessed = chip.preprocessed_trace();
-
     // Iterate over the rows of the main trace to compute the permutation trace values. In
     // particular, for each row i, interaction j, and columns c_0, ..., c_{k-1} we compute the sum:
     //
@@ -57,7 +55,26 @@ pub(crate) fn generate_permutation_trace<F: PrimeField, EF: ExtensionField<F>>(
         // Compute the permutation trace values in parallel.
 
         let mut parallel = match preprocessed {
-            Some(_) => unimplemented!(),
+            Some(preprocessed) => preprocessed
+                .par_row_chunks(chunk_rate)
+                .zip(main.par_row_chunks(chunk_rate))
+                .flat_map(|(preprocessed_rows_chunk, main_rows_chunk)| {
+                    preprocessed_rows_chunk
+                        .rows()
+                        .zip(main_rows_chunk.rows())
+                        .flat_map(|(preprocessed_row, main_row)| {
+                            compute_permutation_row(
+                                main_row,
+                                preprocessed_row,
+                                sends,
+                                receives,
+                                &alphas,
+                                betas.clone(),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>(),
             None => main
                 .par_row_chunks(chunk_rate)
                 .flat_map(|main_rows_chunk| {
@@ -81,9 +98,10 @@ pub(crate) fn generate_permutation_trace<F: PrimeField, EF: ExtensionField<F>>(
         // Compute the permutation trace values for the remainder.
         let remainder = main.height() % chunk_rate;
         for i in 0..remainder {
+            let row = main.height() - remainder + i;
             let perm_row = compute_permutation_row(
-                main.row_slice(main.height() - remainder + i),
-                &[],
+                main.row_slice(row),
+                preprocessed_row(preprocessed, row),
                 sends,
                 receives,
                 &alphas,
@@ -113,14 +131,15 @@ pub(crate) fn generate_permutation_trace<F: PrimeField, EF: ExtensionField<F>>(
         if i > 0 {
             phi[i] = phi[i - 1];
         }
+        let preprocessed_row = preprocessed_row(preprocessed, i);
         // All all sends
         for (j, send) in sends.iter().enumerate() {
-            let mult = send.multiplicity.apply::<F, F>(&[], main_row);
+            let mult = send.multiplicity.apply::<F, F>(preprocessed_row, main_row);
             phi[i] += EF::from_base(mult) * permutation_row[j];
         }
         // Subtract all receives
         for (j, rec) in receives.iter().enumerate() {
-            let mult = rec.multiplicity.apply::<F, F>(&[], main_row);
+            let mult = rec.multiplicity.apply::<F, F>(preprocessed_row, main_row);
             phi[i] -= EF::from_base(mult) * permutation_row[nb_sends + j];
         }
         *permutation_row.last_mut().unwrap() = phi[i];
@@ -129,6 +148,14 @@ pub(crate) fn generate_permutation_trace<F: PrimeField, EF: ExtensionField<F>>(
     permutation_trace
 }
 
+/// The row of the preprocessed trace, or no values if the chip has no preprocessed trace.
+fn preprocessed_row<F: Field>(preprocessed: Option<&RowMajorMatrix<F>>, row: usize) -> &[F] {
+    match preprocessed {
+        Some(preprocessed) => preprocessed.row_slice(row),
+        None => &[],
+    }
+}
+
 /// Evaluates the permutation constraints for the given chip.
 ///
 /// In particular, the constraints checked here are:
@@ -210,6 +237,72 @@ pub fn eval_permutation_constraints<F, AB>(
         .assert_eq_ext(*perm_local.last().unwrap(), cumulative_sum);
 }
 
+/// Returns the maximum degree of the constraints of [`eval_permutation_constraints`] for the given
+/// interactions, computed symbolically over preprocessed and main traces of the given widths.
+pub fn permutation_constraint_degree<F: Field>(
+    sends: &[Interaction<F>],
+    receives: &[Interaction<F>],
+    preprocessed_width: usize,
+    width: usize,
+) -> usize {
+    let columns = |is_next, offset, width| {
+        (offset..offset + width)
+            .map(|column| SymbolicVariable::new(is_next, column))
+            .collect::<Vec<_>>()
+    };
+    let (main_local, main_next) = (columns(false, 0, width), columns(true, 0, width));
+
+    // The permutation and preprocessed columns only matter for their degree, so they are numbered
+    // after the main columns. The challenges are constants, and the signs of the terms do not
+    // change the degree.
+    let num_interactions = sends.len() + receives.len();
+    let perm = |is_next| {
+        (0..=num_interactions)
+            .map(|column| SymbolicExpression::from(SymbolicVariable::new(is_next, width + column)))
+            .collect::<Vec<_>>()
+    };
+    let (perm_local, perm_next) = (perm(false), perm(true));
+    let preprocessed_offset = width + num_interactions + 1;
+    let preprocessed_local = columns(false, preprocessed_offset, preprocessed_width);
+    let preprocessed_next = columns(true, preprocessed_offset, preprocessed_width);
+    let phi_local = perm_local[num_interactions].clone();
+    let phi_next = perm_next[num_interactions].clone();
+
+    let mut constraints = Vec::with_capacity(num_interactions + 3);
+    let mut rhs = SymbolicExpression::zero();
+    let mut phi_0 = SymbolicExpression::zero();
+    for (m, interaction) in sends.iter().chain(receives.iter()).enumerate() {
+        let rlc = interaction
+            .values
+            .iter()
+            .fold(SymbolicExpression::one(), |rlc, value| {
+                rlc + value.apply::<SymbolicExpression<F>, SymbolicVariable<F>>(
+                    &preprocessed_local,
+                    &main_local,
+                )
+            });
+        constraints.push(rlc * perm_local[m].clone() - SymbolicExpression::one());
+
+        let mult_local = interaction
+            .multiplicity
+            .apply::<SymbolicExpression<F>, SymbolicVariable<F>>(&preprocessed_local, &main_local);
+        let mult_next = interaction
+            .multiplicity
+            .apply::<SymbolicExpression<F>, SymbolicVariable<F>>(&preprocessed_next, &main_next);
+        phi_0 = phi_0 + perm_local[m].clone() * mult_local;
+        rhs = rhs + perm_next[m].clone() * mult_next;
+    }
+    constraints.push(SymbolicExpression::IsTransition * (phi_next - phi_local.clone() - rhs));
+    constraints.push(SymbolicExpression::IsFirstRow * (phi_local.clone() - phi_0));
+    constraints.push(SymbolicExpression::IsLastRow * phi_local);
+
+    constraints
+        .iter()
+        .map(SymbolicExpression::degree_multiple)
+        .max()
+        .unwrap_or(0)
+}
+
 /// Computes the permutation fingerprint of a row.
 pub fn compute_permutation_row<F: PrimeField, EF: ExtensionField<F>>(
     main_row: &[F],
@@ -15,39 +15,66 @@ use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::MatrixRows;
 use p3_matrix::{Matrix, MatrixRowSlices};
 use p3_maybe_rayon::prelude::*;
-use p3_util::log2_ceil_usize;
 use p3_util::log2_strict_usize;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::marker::PhantomData;
-use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::Instant;
 
 use super::util::decompose_and_flatten;
 use super::{types::*, StarkGenericConfig};
 use crate::air::MachineAir;
-use crate::utils::env;
+use crate::utils::{
+    CancellationToken, Cancelled, MemoryBudget, MemoryTracker, ProgressMonitor, ProvePhase,
+    ProverOptions,
+};
 
 #[cfg(not(feature = "perf"))]
 use crate::stark::debug_constraints;
 
-fn chunk_vec<T>(mut vec: Vec<T>, chunk_size: usize) -> Vec<Vec<T>> {
-    let mut result = Vec::new();
-    while !vec.is_empty() {
-        let current_chunk_size = std::cmp::min(chunk_size, vec.len());
-        let current_chunk = vec.drain(..current_chunk_size).collect::<Vec<T>>();
-        result.push(current_chunk);
+/// The error returned when a [`Prover`] does not produce a proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProverError {
+    /// The proof was cancelled through its [`CancellationToken`].
+    Cancelled,
+    /// The proof failed, e.g. when the workers of a [`DistributedProver`](super::DistributedProver)
+    /// could not prove a shard.
+    Failed(String),
+}
+
+impl From<Cancelled> for ProverError {
+    fn from(_: Cancelled) -> Self {
+        ProverError::Cancelled
     }
-    result
 }
 
+impl std::fmt::Display for ProverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProverError::Cancelled => write!(f, "{}", Cancelled),
+            ProverError::Failed(error) => write!(f, "the proof failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ProverError {}
+
+/// Proves the shards of an execution record: all the shards are committed to first, and then each
+/// shard is opened with a copy of the challenger which observed all the commitments.
+///
+/// The challenger has already observed the proving key and the public values of the proof.
+///
+/// The progress is reported to `monitor`, and the proof stops with [`ProverError::Cancelled`] once
+/// its cancellation is requested.
 pub trait Prover<SC: StarkGenericConfig, A: MachineAir<SC::Val>> {
     fn prove_shards(
+        &self,
         machine: &MachineStark<SC, A>,
         pk: &ProvingKey<SC>,
         shards: Vec<A::Record>,
         challenger: &mut SC::Challenger,
-    ) -> Proof<SC>
+        monitor: &ProgressMonitor,
+    ) -> Result<Vec<ShardProof<SC>>, ProverError>
     where
         A: for<'a> Air<ProverConstraintFolder<'a, SC>>
             + Air<InteractionBuilder<SC::Val>>
@@ -67,11 +94,13 @@ where
     A: MachineAir<SC::Val>,
 {
     fn prove_shards(
+        &self,
         machine: &MachineStark<SC, A>,
         pk: &ProvingKey<SC>,
         shards: Vec<A::Record>,
         challenger: &mut SC::Challenger,
-    ) -> Proof<SC>
+        monitor: &ProgressMonitor,
+    ) -> Result<Vec<ShardProof<SC>>, ProverError>
     where
         A: for<'a> Air<ProverConstraintFolder<'a, SC>>
             + Air<InteractionBuilder<SC::Val>>
@@ -79,7 +108,9 @@ where
             + for<'a> Air<DebugConstraintBuilder<'a, SC::Val, SC::Challenge>>,
     {
         // Generate and commit the traces for each segment.
-        let (shard_commits, shard_data) = Self::commit_shards(machine, &shards);
+        let (shard_commits, shard_data) =
+            Self::commit_shards(machine, &shards, &self.options, monitor)?;
+        monitor.check()?;
 
         // Observe the challenges for each segment.
         tracing::debug_span!("observing all challenges").in_scope(|| {
@@ -88,65 +119,145 @@ where
             });
         });
 
-        let finished = AtomicU32::new(0);
-        let total = shards.len() as u32;
+        let total = shards.len();
+        let progress = monitor.start(ProvePhase::Open, Some(total));
 
         // Generate a proof for each segment. Note that we clone the challenger so we can observe
         // identical global challenges across the segments.
-        let chunk_size = std::cmp::max(shards.len() / num_cpus::get(), 1);
+        //
+        // The shards kept in memory are opened first. The shards spilled to disk or dropped are
+        // then read back or recomputed in waves whose estimated size fits in the memory budget,
+        // so that only the shards which fit in it are materialized at once.
         let config = machine.config();
-        let reconstruct_commitments = env::reconstruct_commitments();
-        let shard_data_chunks = chunk_vec(shard_data, chunk_size);
-        let shard_chunks = chunk_vec(shards, chunk_size);
+        let memory = monitor.memory();
+        let memory_limit = self
+            .options
+            .memory_budget
+            .as_ref()
+            .map(|budget| budget.limit);
+        let (kept, dropped): (Vec<_>, Vec<_>) = shard_data
+            .into_iter()
+            .zip(shards)
+            .enumerate()
+            .partition(|(_, ((data, _), _))| matches!(data, ShardMainDataWrapper::InMemory(_)));
+        let mut waves = vec![kept, Vec::new()];
+        let mut wave_size = 0;
+        for (idx, ((data, estimated_size), shard)) in dropped {
+            let wave = waves.last_mut().unwrap();
+            if !wave.is_empty()
+                && memory_limit.map_or(false, |limit| wave_size + estimated_size > limit)
+            {
+                waves.push(Vec::new());
+                wave_size = 0;
+            }
+            wave_size += estimated_size;
+            waves
+                .last_mut()
+                .unwrap()
+                .push((idx, ((data, estimated_size), shard)));
+        }
+
         log::info!("open shards");
-        let shard_proofs = tracing::debug_span!("open shards").in_scope(|| {
-            shard_data_chunks
-                .into_par_iter()
-                .zip(shard_chunks.into_par_iter())
-                .enumerate()
-                .map(|(i, (datas, shards))| {
-                    datas
-                        .into_iter()
-                        .zip(shards)
-                        .enumerate()
-                        .map(|(j, (data, shard))| {
-                            let start = Instant::now();
-                            let idx = i * chunk_size + j;
-                            let data = if reconstruct_commitments {
-                                Self::commit_main(config, machine, &shard, idx)
-                            } else {
-                                data.materialize()
+        let mut shard_proofs = Vec::with_capacity(total);
+        for wave in waves {
+            let proofs = tracing::debug_span!("open shards").in_scope(|| {
+                wave.into_par_iter()
+                    .map(|(idx, ((data, estimated_size), shard))| {
+                        monitor.check()?;
+                        let start = Instant::now();
+                        let data = match data {
+                            ShardMainDataWrapper::InMemory(data) => data,
+                            ShardMainDataWrapper::TempFile(file, bytes_written) => {
+                                memory.allocate(estimated_size);
+                                memory.read(bytes_written);
+                                ShardMainDataWrapper::TempFile(file, bytes_written)
+                                    .materialize()
                                     .expect("failed to materialize shard main data")
-                            };
-                            let chips = machine.shard_chips(&shard).collect::<Vec<_>>();
-                            let proof = Self::prove_shard(
-                                config,
-                                pk,
-                                &chips,
-                                data,
-                                &mut challenger.clone(),
-                            );
-                            finished.fetch_add(1, Ordering::Relaxed);
-                            log::info!(
-                                "> open shards ({}/{}): shard = {}, time = {:.2} secs",
-                                finished.load(Ordering::Relaxed),
-                                total,
-                                idx,
-                                start.elapsed().as_secs_f64()
-                            );
-                            proof
-                        })
-                        .collect::<Vec<_>>()
-                })
-                .flatten()
-                .collect::<Vec<_>>()
-        });
+                            }
+                            ShardMainDataWrapper::Empty() => {
+                                memory.allocate(estimated_size);
+                                memory.recompute();
+                                Self::commit_main(
+                                    config,
+                                    machine,
+                                    &shard,
+                                    idx,
+                                    monitor.cancellation(),
+                                )?
+                            }
+                        };
+                        let chips = machine.shard_chips(&shard).collect::<Vec<_>>();
+                        let proof = Self::prove_shard(
+                            config,
+                            pk,
+                            &chips,
+                            data,
+                            &mut challenger.clone(),
+                            monitor.cancellation(),
+                        )?;
+                        memory.free(estimated_size);
+                        log::info!(
+                            "> open shards ({}/{}): shard = {}, time = {:.2} secs",
+                            progress.advance(),
+                            total,
+                            idx,
+                            start.elapsed().as_secs_f64()
+                        );
+                        Ok((idx, proof))
+                    })
+                    .collect::<Result<Vec<_>, Cancelled>>()
+            })?;
+            shard_proofs.extend(proofs);
+        }
+        shard_proofs.sort_by_key(|(idx, _)| *idx);
+        let shard_proofs = shard_proofs
+            .into_iter()
+            .map(|(_, proof)| proof)
+            .collect::<Vec<_>>();
+
+        let usage = memory.usage();
+        log::info!(
+            "shard data: peak memory = {}, written to disk = {}, read from disk = {}, \
+            kept = {}, spilled = {}, recomputed = {}",
+            size::Size::from_bytes(usage.peak_bytes),
+            size::Size::from_bytes(usage.bytes_written),
+            size::Size::from_bytes(usage.bytes_read),
+            usage.shards_kept,
+            usage.shards_spilled,
+            usage.shards_recomputed
+        );
+
+        Ok(shard_proofs)
+    }
+}
+
+/// A prover running on the local machine, which proves the shards in parallel.
+pub struct LocalProver<SC, A> {
+    options: ProverOptions,
+    _marker: PhantomData<(SC, A)>,
+}
 
-        Proof { shard_proofs }
+impl<SC, A> LocalProver<SC, A> {
+    /// A prover with the default options.
+    pub fn new() -> Self {
+        Self::with_options(ProverOptions::default())
+    }
+
+    /// A prover keeping, spilling or recomputing the shard data between the commit and the open
+    /// phases as decided by `options`.
+    pub fn with_options(options: ProverOptions) -> Self {
+        Self {
+            options,
+            _marker: PhantomData,
+        }
     }
 }
 
-pub struct LocalProver<SC, A>(PhantomData<SC>, PhantomData<A>);
+impl<SC, A> Default for LocalProver<SC, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl<SC, A> LocalProver<SC, A>
 where
@@ -163,7 +274,22 @@ where
         machine: &MachineStark<SC, A>,
         shard: &A::Record,
         index: usize,
-    ) -> ShardMainData<SC>
+        cancellation: &CancellationToken,
+    ) -> Result<ShardMainData<SC>, Cancelled>
+    where
+        SC::Val: PrimeField32,
+    {
+        let (chip_ids, traces) = Self::generate_traces(machine, shard, cancellation)?;
+        Ok(Self::commit_traces(config, chip_ids, traces, index))
+    }
+
+    /// Generates the main traces of the chips used by the shard, along with the names of these
+    /// chips.
+    pub fn generate_traces(
+        machine: &MachineStark<SC, A>,
+        shard: &A::Record,
+        cancellation: &CancellationToken,
+    ) -> Result<(Vec<String>, Vec<RowMajorMatrix<SC::Val>>), Cancelled>
     where
         SC::Val: PrimeField32,
     {
@@ -173,11 +299,11 @@ where
         // For each chip, generate the trace.
         let traces = filtered_chips
             .par_iter()
-            .map(|chip| chip.generate_trace(shard, &mut A::Record::default()))
-            .collect::<Vec<_>>();
-
-        // Commit to the batch of traces.
-        let (main_commit, main_data) = config.pcs().commit_batches(traces.to_vec());
+            .map(|chip| {
+                cancellation.check()?;
+                Ok(chip.generate_trace(shard, &mut A::Record::default()))
+            })
+            .collect::<Result<Vec<_>, Cancelled>>()?;
 
         // Get the filtered chip ids.
         let chip_ids = filtered_chips
@@ -185,6 +311,19 @@ where
             .map(|chip| chip.name())
             .collect::<Vec<_>>();
 
+        Ok((chip_ids, traces))
+    }
+
+    /// Commits to the main traces of a shard, generated by [`Self::generate_traces`].
+    pub fn commit_traces(
+        config: &SC,
+        chip_ids: Vec<String>,
+        traces: Vec<RowMajorMatrix<SC::Val>>,
+        index: usize,
+    ) -> ShardMainData<SC> {
+        // Commit to the batch of traces.
+        let (main_commit, main_data) = config.pcs().commit_batches(traces.to_vec());
+
         ShardMainData {
             traces,
             main_commit,
@@ -195,13 +334,16 @@ where
     }
 
     /// Prove the program for the given shard and given a commitment to the main data.
+    ///
+    /// The cancellation is checked between the stages of the proof.
     pub fn prove_shard(
         config: &SC,
-        _pk: &ProvingKey<SC>,
+        pk: &ProvingKey<SC>,
         chips: &[&MachineChip<SC, A>],
         shard_data: ShardMainData<SC>,
         challenger: &mut SC::Challenger,
-    ) -> ShardProof<SC>
+        cancellation: &CancellationToken,
+    ) -> Result<ShardProof<SC>, Cancelled>
     where
         SC::Val: PrimeField32,
         SC: Send + Sync,
@@ -211,16 +353,33 @@ where
             + for<'a> Air<VerifierConstraintFolder<'a, SC>>
             + for<'a> Air<DebugConstraintBuilder<'a, SC::Val, SC::Challenge>>,
     {
-        // Get the traces.
+        cancellation.check()?;
+
+        // Get the traces, and the preprocessed traces of the chips which have one.
         let traces = &shard_data.traces;
+        let preprocessed_traces = chips
+            .iter()
+            .map(|chip| pk.trace(&chip.name()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chips
+                .iter()
+                .zip(preprocessed_traces.iter())
+                .filter(|(_, trace)| trace.is_some())
+                .map(|(chip, _)| chip.name())
+                .collect::<Vec<_>>(),
+            pk.chip_ids,
+            "every chip with a preprocessed trace must be in every shard"
+        );
 
         let log_degrees = traces
             .iter()
             .map(|trace| log2_strict_usize(trace.height()))
             .collect::<Vec<_>>();
-        // TODO: read dynamically from Chip.
-        let max_constraint_degree = 3;
-        let log_quotient_degree = log2_ceil_usize(max_constraint_degree - 1);
+        let log_quotient_degrees = chips
+            .iter()
+            .map(|chip| chip.log_quotient_degree())
+            .collect::<Vec<_>>();
         let g_subgroups = log_degrees
             .iter()
             .map(|log_deg| SC::Val::two_adic_generator(*log_deg))
@@ -238,10 +397,14 @@ where
         tracing::debug_span!("generate permutation traces").in_scope(|| {
             chips
                 .par_iter()
+                .zip(preprocessed_traces.par_iter())
                 .zip(traces.par_iter())
-                .map(|(chip, main_trace)| {
-                    let perm_trace =
-                        chip.generate_permutation_trace(&None, main_trace, &permutation_challenges);
+                .map(|((chip, preprocessed_trace), main_trace)| {
+                    let perm_trace = chip.generate_permutation_trace(
+                        *preprocessed_trace,
+                        main_trace,
+                        &permutation_challenges,
+                    );
                     let cumulative_sum = perm_trace
                         .row_slice(main_trace.height() - 1)
                         .last()
@@ -269,6 +432,7 @@ where
         }
 
         // Commit to the permutation traces.
+        cancellation.check()?;
         let flattened_permutation_traces = tracing::debug_span!("flatten permutation traces")
             .in_scope(|| {
                 permutation_traces
@@ -281,14 +445,19 @@ where
                 .in_scope(|| config.pcs().commit_batches(flattened_permutation_traces));
         challenger.observe(permutation_commit.clone());
 
-        // For each chip, compute the quotient polynomial.
-        let log_stride_for_quotient = config.pcs().log_blowup() - log_quotient_degree;
+        // For each chip, compute the quotient polynomial, on a coset as large as its degree needs.
+        let log_blowup = config.pcs().log_blowup();
+        let log_strides_for_quotient = log_quotient_degrees
+            .iter()
+            .map(|log_quotient_degree| log_blowup - log_quotient_degree)
+            .collect::<Vec<_>>();
         let main_ldes = tracing::debug_span!("get main ldes").in_scope(|| {
             config
                 .pcs()
                 .get_ldes(&shard_data.main_data)
                 .into_iter()
-                .map(|lde| lde.vertically_strided(1 << log_stride_for_quotient, 0))
+                .zip(log_strides_for_quotient.iter())
+                .map(|(lde, log_stride)| lde.vertically_strided(1 << log_stride, 0))
                 .collect::<Vec<_>>()
         });
         let permutation_ldes = tracing::debug_span!("get perm ldes").in_scope(|| {
@@ -296,7 +465,23 @@ where
                 .pcs()
                 .get_ldes(&permutation_data)
                 .into_iter()
-                .map(|lde| lde.vertically_strided(1 << log_stride_for_quotient, 0))
+                .zip(log_strides_for_quotient.iter())
+                .map(|(lde, log_stride)| lde.vertically_strided(1 << log_stride, 0))
+                .collect::<Vec<_>>()
+        });
+        let preprocessed_ldes = tracing::debug_span!("get preprocessed ldes").in_scope(|| {
+            let mut ldes = pk
+                .data
+                .as_ref()
+                .map(|data| config.pcs().get_ldes(data))
+                .unwrap_or_default()
+                .into_iter();
+            preprocessed_traces
+                .iter()
+                .zip(log_strides_for_quotient.iter())
+                .map(|(trace, log_stride)| {
+                    trace.map(|_| ldes.next().unwrap().vertically_strided(1 << log_stride, 0))
+                })
                 .collect::<Vec<_>>()
         });
         let alpha: SC::Challenge = challenger.sample_ext_element::<SC::Challenge>();
@@ -306,29 +491,32 @@ where
             (0..chips.len())
                 .into_par_iter()
                 .map(|i| {
-                    quotient_values(
+                    cancellation.check()?;
+                    Ok(quotient_values(
                         config,
                         chips[i],
                         cumulative_sums[i],
                         log_degrees[i],
+                        preprocessed_ldes[i].as_ref(),
                         &main_ldes[i],
                         &permutation_ldes[i],
                         &permutation_challenges,
                         alpha,
-                    )
+                    ))
                 })
-                .collect::<Vec<_>>()
-        });
+                .collect::<Result<Vec<_>, Cancelled>>()
+        })?;
 
         // Compute the quotient chunks.
         let quotient_chunks = tracing::debug_span!("decompose and flatten").in_scope(|| {
             quotient_values
                 .into_iter()
-                .map(|values| {
+                .zip(log_quotient_degrees.iter())
+                .map(|(values, log_quotient_degree)| {
                     decompose_and_flatten::<SC>(
                         values,
                         SC::Challenge::from_base(config.pcs().coset_shift()),
-                        log_quotient_degree,
+                        *log_quotient_degree,
                     )
                 })
                 .collect::<Vec<_>>()
@@ -337,17 +525,20 @@ where
         // Check the shapes of the quotient chunks.
         #[cfg(not(feature = "perf"))]
         for (i, mat) in quotient_chunks.iter().enumerate() {
-            assert_eq!(mat.width(), SC::Challenge::D << log_quotient_degree);
+            assert_eq!(mat.width(), SC::Challenge::D << log_quotient_degrees[i]);
             assert_eq!(mat.height(), traces[i].height());
         }
 
-        let num_quotient_chunks = quotient_chunks.len();
         let coset_shifts = tracing::debug_span!("coset shift").in_scope(|| {
-            let shift = config
-                .pcs()
-                .coset_shift()
-                .exp_power_of_2(log_quotient_degree);
-            vec![shift; chips.len()]
+            log_quotient_degrees
+                .iter()
+                .map(|log_quotient_degree| {
+                    config
+                        .pcs()
+                        .coset_shift()
+                        .exp_power_of_2(*log_quotient_degree)
+                })
+                .collect::<Vec<_>>()
         });
         let (quotient_commit, quotient_data) = tracing::debug_span!("commit shifted batches")
             .in_scope(|| {
@@ -370,25 +561,51 @@ where
                     .collect::<Vec<_>>()
             });
 
-        let zeta_quot_pow = zeta.exp_power_of_2(log_quotient_degree);
-        let quotient_opening_points = (0..num_quotient_chunks)
-            .map(|_| vec![zeta_quot_pow])
+        let quotient_opening_points = log_quotient_degrees
+            .iter()
+            .map(|log_quotient_degree| vec![zeta.exp_power_of_2(*log_quotient_degree)])
             .collect::<Vec<_>>();
 
-        let (openings, opening_proof) = tracing::debug_span!("open multi batches").in_scope(|| {
-            config.pcs().open_multi_batches(
-                &[
-                    (&shard_data.main_data, &trace_opening_points),
-                    (&permutation_data, &trace_opening_points),
-                    (&quotient_data, &quotient_opening_points),
-                ],
-                challenger,
-            )
-        });
+        // The preprocessed traces are opened first, as a batch of their own.
+        let preprocessed_opening_points = preprocessed_traces
+            .iter()
+            .zip(trace_opening_points.iter())
+            .filter(|(trace, _)| trace.is_some())
+            .map(|(_, points)| points.clone())
+            .collect::<Vec<_>>();
+        let mut batches: Vec<(&PcsProverData<SC>, &[Vec<SC::Challenge>])> = vec![];
+        if let Some(preprocessed_data) = &pk.data {
+            batches.push((preprocessed_data, &preprocessed_opening_points));
+        }
+        batches.push((&shard_data.main_data, &trace_opening_points));
+        batches.push((&permutation_data, &trace_opening_points));
+        batches.push((&quotient_data, &quotient_opening_points));
+
+        let (openings, opening_proof) = tracing::debug_span!("open multi batches")
+            .in_scope(|| config.pcs().open_multi_batches(&batches, challenger));
 
         #[cfg(feature = "perf")]
         {
             // Collect the opened values for each chip.
+            let mut openings = openings;
+            let mut preprocessed_values = match pk.data {
+                Some(_) => openings.remove(0),
+                None => vec![],
+            }
+            .into_iter();
+            let preprocessed_opened_values = preprocessed_traces
+                .iter()
+                .map(|trace| match trace {
+                    Some(_) => {
+                        let [local, next] = preprocessed_values.next().unwrap().try_into().unwrap();
+                        AirOpenedValues { local, next }
+                    }
+                    None => AirOpenedValues {
+                        local: vec![],
+                        next: vec![],
+                    },
+                })
+                .collect::<Vec<_>>();
             let [main_values, permutation_values, quotient_values] = openings.try_into().unwrap();
             let main_opened_values = main_values
                 .into_iter()
@@ -410,6 +627,7 @@ where
                 .collect::<Vec<_>>();
 
             let opened_values = izip!(
+                preprocessed_opened_values,
                 main_opened_values,
                 permutation_opened_values,
                 quotient_opened_values,
@@ -417,21 +635,20 @@ where
                 log_degrees
             )
             .map(
-                |(main, permutation, quotient, cumulative_sum, log_degree)| ChipOpenedValues {
-                    preprocessed: AirOpenedValues {
-                        local: vec![],
-                        next: vec![],
-                    },
-                    main,
-                    permutation,
-                    quotient,
-                    cumulative_sum,
-                    log_degree,
+                |(preprocessed, main, permutation, quotient, cumulative_sum, log_degree)| {
+                    ChipOpenedValues {
+                        preprocessed,
+                        main,
+                        permutation,
+                        quotient,
+                        cumulative_sum,
+                        log_degree,
+                    }
                 },
             )
             .collect::<Vec<_>>();
 
-            ShardProof::<SC> {
+            Ok(ShardProof::<SC> {
                 index: shard_data.index,
                 commitment: ShardCommitment {
                     main_commit: shard_data.main_commit.clone(),
@@ -443,7 +660,7 @@ where
                 },
                 opening_proof,
                 chip_ids: chips.iter().map(|chip| chip.name()).collect::<Vec<_>>(),
-            }
+            })
         }
 
         // Check that the table-specific constraints are correct for each chip.
@@ -452,7 +669,7 @@ where
             for i in 0..chips.len() {
                 debug_constraints::<SC, A>(
                     &chips[i],
-                    None,
+                    preprocessed_traces[i],
                     &traces[i],
                     &permutation_traces[i],
                     &permutation_challenges,
@@ -461,21 +678,26 @@ where
         });
 
         #[cfg(not(feature = "perf"))]
-        return ShardProof {
+        return Ok(ShardProof {
             main_commit: shard_data.main_commit.clone(),
             traces: traces.to_vec(),
             permutation_traces,
             chip_ids: chips.iter().map(|chip| chip.name()).collect::<Vec<_>>(),
-        };
+        });
     }
 
     fn commit_shards<F, EF>(
         machine: &MachineStark<SC, A>,
         shards: &[A::Record],
-    ) -> (
-        Vec<<SC::Pcs as Pcs<SC::Val, RowMajorMatrix<SC::Val>>>::Commitment>,
-        Vec<ShardMainDataWrapper<SC>>,
-    )
+        options: &ProverOptions,
+        monitor: &ProgressMonitor,
+    ) -> Result<
+        (
+            Vec<<SC::Pcs as Pcs<SC::Val, RowMajorMatrix<SC::Val>>>::Commitment>,
+            Vec<(ShardMainDataWrapper<SC>, usize)>,
+        ),
+        Cancelled,
+    >
     where
         F: PrimeField + TwoAdicField + PrimeField32,
         EF: ExtensionField<F>,
@@ -489,12 +711,9 @@ where
         let num_shards = shards.len();
         log::info!("commit shards");
 
-        // Get the number of shards that is the threshold for saving shards to disk instead of
-        // keeping all the shards in memory.
-        let save_disk_threshold = env::save_disk_threshold();
-        let reconstruct_commitments = env::reconstruct_commitments();
-        let finished = AtomicU32::new(0);
-        let total = shards.len() as u32;
+        let log_blowup = config.pcs().log_blowup();
+        let total = shards.len();
+        let progress = monitor.start(ProvePhase::Commit, Some(total));
         let (commitments, shard_main_data): (Vec<_>, Vec<_>) =
             tracing::debug_span!("commit shards").in_scope(|| {
                 let chunk_size = std::cmp::max(shards.len() / num_cpus::get(), 1);
@@ -506,43 +725,46 @@ where
                             .iter()
                             .enumerate()
                             .map(|(j, shard)| {
+                                monitor.check()?;
                                 let index = i * chunk_size + j;
                                 let start = Instant::now();
-                                let data = Self::commit_main(config, machine, shard, index);
-                                finished.fetch_add(1, Ordering::Relaxed);
+                                let data = Self::commit_main(
+                                    config,
+                                    machine,
+                                    shard,
+                                    index,
+                                    monitor.cancellation(),
+                                )?;
                                 log::info!(
                                     "> commit shards ({}/{}): shard = {}, time = {:.2} secs",
-                                    finished.load(Ordering::Relaxed),
+                                    progress.advance(),
                                     total,
                                     index,
                                     start.elapsed().as_secs_f64()
                                 );
                                 let commitment = data.main_commit.clone();
-                                let file = tempfile::tempfile().unwrap();
-                                let data = if reconstruct_commitments {
-                                    ShardMainDataWrapper::Empty()
-                                } else if num_shards > save_disk_threshold {
-                                    tracing::info_span!("saving trace to disk").in_scope(|| {
-                                        data.save(file).expect("failed to save shard main data")
-                                    })
-                                } else {
-                                    data.to_in_memory()
-                                };
-                                (commitment, data)
+                                let estimated_size = data.estimated_size(log_blowup);
+                                let data = Self::store_shard_data(
+                                    data,
+                                    estimated_size,
+                                    num_shards,
+                                    options,
+                                    monitor.memory(),
+                                );
+                                Ok((commitment, (data, estimated_size)))
                             })
                             .collect::<Vec<_>>()
                     })
                     .flatten()
-                    .collect::<Vec<_>>()
-                    .into_iter()
-                    .unzip()
-            });
+                    .collect::<Result<Vec<_>, Cancelled>>()
+                    .map(|results| results.into_iter().unzip())
+            })?;
 
         #[cfg(not(feature = "perf"))]
         {
             let bytes_written = shard_main_data
                 .iter()
-                .map(|data| match data {
+                .map(|(data, _)| match data {
                     ShardMainDataWrapper::InMemory(_) => 0,
                     ShardMainDataWrapper::TempFile(_, bytes_written) => *bytes_written,
                     ShardMainDataWrapper::Empty() => 0,
@@ -556,6 +778,54 @@ where
             }
         }
 
-        (commitments, shard_main_data)
+        Ok((commitments, shard_main_data))
+    }
+
+    /// Stores the data of a committed shard until the shard is opened.
+    ///
+    /// With a memory budget, the data is kept in memory if it fits in the budget, and otherwise
+    /// spilled to the spill directory or dropped to be recomputed. Without one, the data is
+    /// dropped if the commitments are reconstructed, and otherwise spilled to a temporary file if
+    /// there are more than `save_disk_threshold` shards.
+    fn store_shard_data(
+        data: ShardMainData<SC>,
+        estimated_size: usize,
+        num_shards: usize,
+        options: &ProverOptions,
+        memory: &MemoryTracker,
+    ) -> ShardMainDataWrapper<SC> {
+        memory.allocate(estimated_size);
+        let file = match &options.memory_budget {
+            Some(budget) if memory.fits(budget.limit) => {
+                memory.keep();
+                return data.to_in_memory();
+            }
+            Some(MemoryBudget {
+                spill_dir: Some(spill_dir),
+                ..
+            }) => tempfile::tempfile_in(spill_dir),
+            Some(_) => {
+                memory.free(estimated_size);
+                return ShardMainDataWrapper::Empty();
+            }
+            None if options.reconstruct_commitments => {
+                memory.free(estimated_size);
+                return ShardMainDataWrapper::Empty();
+            }
+            None if num_shards > options.save_disk_threshold => tempfile::tempfile(),
+            None => {
+                memory.keep();
+                return data.to_in_memory();
+            }
+        };
+
+        let file = file.expect("failed to create a file to spill the shard main data");
+        let data = tracing::info_span!("saving trace to disk")
+            .in_scope(|| data.save(file).expect("failed to save shard main data"));
+        memory.free(estimated_size);
+        if let ShardMainDataWrapper::TempFile(_, bytes_written) = &data {
+            memory.spill(*bytes_written);
+        }
+        data
     }
 }
@@ -16,11 +16,12 @@ use p3_maybe_rayon::prelude::*;
 use super::{zerofier_coset::ZerofierOnCoset, StarkGenericConfig};
 
 #[allow(clippy::too_many_arguments)]
-pub fn quotient_values<SC, A, MainLde, PermLde>(
+pub fn quotient_values<SC, A, PreprocessedLde, MainLde, PermLde>(
     config: &SC,
     chip: &Chip<SC::Val, A>,
     cumulative_sum: SC::Challenge,
     degree_bits: usize,
+    preprocessed_lde: Option<&PreprocessedLde>,
     main_lde: &MainLde,
     permutation_lde: &PermLde,
     perm_challenges: &[SC::Challenge],
@@ -30,6 +31,7 @@ where
     A: StarkAir<SC>,
     SC: StarkGenericConfig,
     SC::Val: TwoAdicField,
+    PreprocessedLde: MatrixGet<SC::Val> + Sync,
     MainLde: MatrixGet<SC::Val> + Sync,
     PermLde: MatrixGet<SC::Val> + Sync,
 {
@@ -84,6 +86,18 @@ where
                 })
                 .collect();
 
+            let preprocessed_rows = |start: usize| -> Vec<_> {
+                preprocessed_lde.map_or(vec![], |lde| {
+                    (0..lde.width())
+                        .map(|col| {
+                            PackedVal::<SC>::from_fn(|offset| lde.get(wrap(start + offset), col))
+                        })
+                        .collect()
+                })
+            };
+            let preprocessed_local = preprocessed_rows(i_local_start);
+            let preprocessed_next = preprocessed_rows(i_next_start);
+
             let perm_local: Vec<_> = (0..permutation_lde.width())
                 .step_by(ext_degree)
                 .map(|col| {
@@ -111,8 +125,8 @@ where
             let accumulator = PackedChallenge::<SC>::zero();
             let mut folder = ProverConstraintFolder {
                 preprocessed: TwoRowMatrixView {
-                    local: &[],
-                    next: &[],
+                    local: &preprocessed_local,
+                    next: &preprocessed_next,
                 },
                 main: TwoRowMatrixView {
                     local: &local,
@@ -1,15 +1,28 @@
-use hashbrown::HashMap;
+use std::collections::BTreeMap;
+
+use p3_field::AbstractField;
 
 pub trait MachineRecord: Default + Sized + Send + Sync {
     type Config: Default;
 
+    /// The program the records are executions of, from which the preprocessed traces are
+    /// generated.
+    type Program;
+
     fn index(&self) -> u32;
 
     fn set_index(&mut self, index: u32);
 
-    fn stats(&self) -> HashMap<String, usize>;
+    fn stats(&self) -> BTreeMap<String, usize>;
 
     fn append(&mut self, other: &mut Self);
 
     fn shard(self, config: &Self::Config) -> Vec<Self>;
+
+    /// The values the execution exposes to the verifier of its proof, in order.
+    ///
+    /// The public values of a proof are those of its shards, in the order of the shards.
+    fn public_values<F: AbstractField>(&self) -> Vec<F> {
+        Vec::new()
+    }
 }
@@ -0,0 +1,214 @@
+use p3_air::{Air, AirBuilder, BaseAir, PairBuilder};
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::{SymbolicExpression, SymbolicVariable};
+
+use crate::air::{AirInteraction, MachineAir, MessageBuilder};
+use crate::lookup::symbolic_columns;
+
+/// The constraints and interactions of an AIR, recorded as symbolic expressions over its columns.
+#[derive(Debug, Clone)]
+pub struct SymbolicConstraints<F: Field> {
+    /// The constraints, in the order in which the AIR asserts them.
+    pub constraints: Vec<SymbolicExpression<F>>,
+    pub sends: Vec<AirInteraction<SymbolicExpression<F>>>,
+    pub receives: Vec<AirInteraction<SymbolicExpression<F>>>,
+}
+
+impl<F: Field> SymbolicConstraints<F> {
+    /// Records the constraints and interactions of `air`.
+    pub fn from_air<A>(air: &A) -> Self
+    where
+        A: MachineAir<F> + Air<SymbolicConstraintBuilder<F>>,
+    {
+        let mut builder = SymbolicConstraintBuilder::new(air.preprocessed_width(), air.width());
+        air.eval(&mut builder);
+        builder.constraints()
+    }
+}
+
+/// A builder that records the constraints and interactions of an AIR symbolically, so that they
+/// can be evaluated later over any field.
+pub struct SymbolicConstraintBuilder<F: Field> {
+    preprocessed: RowMajorMatrix<SymbolicVariable<F>>,
+    main: RowMajorMatrix<SymbolicVariable<F>>,
+    constraints: SymbolicConstraints<F>,
+}
+
+impl<F: Field> SymbolicConstraintBuilder<F> {
+    /// Creates a new `SymbolicConstraintBuilder` with the given preprocessed and main widths.
+    ///
+    /// The preprocessed columns are numbered after the main columns.
+    pub fn new(preprocessed_width: usize, width: usize) -> Self {
+        Self {
+            preprocessed: symbolic_columns(width, preprocessed_width),
+            main: symbolic_columns(0, width),
+            constraints: SymbolicConstraints {
+                constraints: vec![],
+                sends: vec![],
+                receives: vec![],
+            },
+        }
+    }
+
+    /// Returns the recorded constraints and interactions.
+    pub fn constraints(self) -> SymbolicConstraints<F> {
+        self.constraints
+    }
+}
+
+impl<F: Field> AirBuilder for SymbolicConstraintBuilder<F> {
+    type F = F;
+    type Expr = SymbolicExpression<F>;
+    type Var = SymbolicVariable<F>;
+    type M = RowMajorMatrix<Self::Var>;
+
+    fn main(&self) -> Self::M {
+        self.main.clone()
+    }
+
+    fn is_first_row(&self) -> Self::Expr {
+        SymbolicExpression::IsFirstRow
+    }
+
+    fn is_last_row(&self) -> Self::Expr {
+        SymbolicExpression::IsLastRow
+    }
+
+    fn is_transition_window(&self, size: usize) -> Self::Expr {
+        if size == 2 {
+            SymbolicExpression::IsTransition
+        } else {
+            panic!("uni-stark only supports a window size of 2")
+        }
+    }
+
+    fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
+        self.constraints.constraints.push(x.into());
+    }
+}
+
+impl<F: Field> PairBuilder for SymbolicConstraintBuilder<F> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed.clone()
+    }
+}
+
+impl<F: Field> MessageBuilder<AirInteraction<SymbolicExpression<F>>>
+    for SymbolicConstraintBuilder<F>
+{
+    fn send(&mut self, message: AirInteraction<SymbolicExpression<F>>) {
+        self.constraints.sends.push(message);
+    }
+
+    fn receive(&mut self, message: AirInteraction<SymbolicExpression<F>>) {
+        self.constraints.receives.push(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_air::{Air, AirBuilder, BaseAir};
+    use p3_baby_bear::BabyBear;
+    use p3_field::{AbstractField, Field};
+    use p3_matrix::MatrixRowSlices;
+    use p3_uni_stark::SymbolicExpression;
+
+    use p3_matrix::dense::RowMajorMatrix;
+
+    use super::SymbolicConstraints;
+    use crate::air::{AirInteraction, MachineAir, SP1AirBuilder};
+    use crate::lookup::InteractionKind;
+    use crate::runtime::ExecutionRecord;
+    use crate::stark::{permutation_constraint_degree, Chip};
+
+    const NUM_COLS: usize = 2;
+
+    /// An AIR computing a fibonacci sequence, which sends its values to the ALU table.
+    struct FibonacciTestAir;
+
+    impl<F: Field> BaseAir<F> for FibonacciTestAir {
+        fn width(&self) -> usize {
+            NUM_COLS
+        }
+    }
+
+    impl<F: Field> MachineAir<F> for FibonacciTestAir {
+        type Record = ExecutionRecord;
+
+        fn name(&self) -> String {
+            "FibonacciTest".to_string()
+        }
+
+        fn generate_trace(
+            &self,
+            _: &ExecutionRecord,
+            _: &mut ExecutionRecord,
+        ) -> RowMajorMatrix<F> {
+            unimplemented!()
+        }
+
+        fn num_rows(&self, _: &ExecutionRecord) -> usize {
+            unimplemented!()
+        }
+
+        fn included(&self, _: &ExecutionRecord) -> bool {
+            true
+        }
+    }
+
+    impl<AB: SP1AirBuilder> Air<AB> for FibonacciTestAir {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            let next = main.row_slice(1);
+
+            builder.when_first_row().assert_zero(local[0]);
+            builder.when_first_row().assert_one(local[1]);
+            builder.when_transition().assert_eq(next[0], local[1]);
+            builder
+                .when_transition()
+                .assert_eq(next[1], local[0] + local[1]);
+
+            builder.send(AirInteraction::new(
+                vec![local[0].into(), local[1].into()],
+                AB::F::one().into(),
+                InteractionKind::Alu,
+            ));
+        }
+    }
+
+    #[test]
+    fn test_symbolic_constraints() {
+        let constraints = SymbolicConstraints::<BabyBear>::from_air(&FibonacciTestAir);
+
+        assert_eq!(constraints.constraints.len(), 4);
+        assert_eq!(constraints.sends.len(), 1);
+        assert!(constraints.receives.is_empty());
+
+        let max_degree = constraints
+            .constraints
+            .iter()
+            .map(|constraint| constraint.degree_multiple())
+            .max();
+        assert_eq!(max_degree, Some(2));
+        assert!(matches!(
+            &constraints.sends[0].values[0],
+            SymbolicExpression::Variable(v) if !v.is_next && v.column == 0
+        ));
+    }
+
+    #[test]
+    fn test_chip_constraint_degree() {
+        // The air has constraints of degree 2, but the first row of the running sum of its
+        // interaction is constrained with degree 3.
+        let chip = Chip::<BabyBear, _>::new(FibonacciTestAir);
+        assert_eq!(chip.max_constraint_degree(), 3);
+        assert_eq!(chip.log_quotient_degree(), 1);
+
+        assert_eq!(
+            permutation_constraint_degree::<BabyBear>(&[], &[], 0, NUM_COLS),
+            2
+        );
+    }
+}
@@ -61,6 +61,18 @@ impl<SC: StarkGenericConfig> ShardMainData<SC> {
         }
     }
 
+    /// An estimate of the number of bytes of the data in memory: the traces, and their
+    /// low-degree extensions by `2^log_blowup` in the prover data.
+    pub fn estimated_size(&self, log_blowup: usize) -> usize {
+        let trace_size = self
+            .traces
+            .iter()
+            .map(|trace| trace.values.len())
+            .sum::<usize>()
+            * std::mem::size_of::<Val<SC>>();
+        trace_size * (1 + (1 << log_blowup))
+    }
+
     pub fn save(&self, file: File) -> Result<ShardMainDataWrapper<SC>, Error>
     where
         ShardMainData<SC>: Serialize,
@@ -154,7 +166,10 @@ pub struct ShardProof<SC: StarkGenericConfig> {
 }
 
 impl<T: Serialize> ShardOpenedValues<T> {
+    /// The opened values in the batches of the commitments, with a batch of the preprocessed
+    /// traces first if some chips have one.
     pub fn into_values(self) -> OpenedValues<T> {
+        let mut preprocessed_vals = vec![];
         let mut main_vals = vec![];
         let mut permutation_vals = vec![];
         let mut quotient_vals = vec![];
@@ -162,18 +177,31 @@ impl<T: Serialize> ShardOpenedValues<T> {
         let to_values = |values: AirOpenedValues<T>| vec![values.local, values.next];
         for chip_values in self.chips {
             let ChipOpenedValues {
+                preprocessed,
                 main,
                 permutation,
                 quotient,
                 ..
             } = chip_values;
 
+            if !preprocessed.local.is_empty() {
+                preprocessed_vals.push(to_values(preprocessed));
+            }
             main_vals.push(to_values(main));
             permutation_vals.push(to_values(permutation));
             quotient_vals.push(vec![quotient]);
         }
 
-        vec![main_vals, permutation_vals, quotient_vals]
+        if preprocessed_vals.is_empty() {
+            vec![main_vals, permutation_vals, quotient_vals]
+        } else {
+            vec![
+                preprocessed_vals,
+                main_vals,
+                permutation_vals,
+                quotient_vals,
+            ]
+        }
     }
 }
 
@@ -184,6 +212,40 @@ impl<T> AirOpenedValues<T> {
     }
 }
 
+impl<SC: StarkGenericConfig> ShardProof<SC> {
+    /// The commitment to the main traces of the shard.
+    pub fn main_commit(&self) -> &Com<SC> {
+        #[cfg(feature = "perf")]
+        {
+            &self.commitment.main_commit
+        }
+        #[cfg(not(feature = "perf"))]
+        {
+            &self.main_commit
+        }
+    }
+
+    /// The width and the height of the main trace of each chip of the shard, in the order of
+    /// `chip_ids`.
+    pub fn main_dimensions(&self) -> Vec<(usize, usize)> {
+        #[cfg(feature = "perf")]
+        {
+            self.opened_values
+                .chips
+                .iter()
+                .map(|chip| (chip.main.local.len(), 1 << chip.log_degree))
+                .collect()
+        }
+        #[cfg(not(feature = "perf"))]
+        {
+            self.traces
+                .iter()
+                .map(|trace| (trace.width, trace.values.len() / trace.width))
+                .collect()
+        }
+    }
+}
+
 #[cfg(feature = "perf")]
 impl<SC: StarkGenericConfig> ShardProof<SC> {
     pub fn cumulative_sum(&self) -> Challenge<SC> {
@@ -198,4 +260,6 @@ impl<SC: StarkGenericConfig> ShardProof<SC> {
 #[derive(Serialize, Deserialize)]
 pub struct Proof<SC: StarkGenericConfig> {
     pub shard_proofs: Vec<ShardProof<SC>>,
+    /// The public values of the shards, in the order of the shards.
+    pub public_values: Vec<Val<SC>>,
 }
@@ -1,5 +1,6 @@
 use crate::air::MachineAir;
 use crate::stark::MachineChip;
+use crate::stark::VerifyingKey;
 use itertools::izip;
 use itertools::Itertools;
 use p3_air::Air;
@@ -26,10 +27,12 @@ use core::fmt::Display;
 pub struct Verifier<SC, A>(PhantomData<SC>, PhantomData<A>);
 
 impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> Verifier<SC, A> {
-    /// Verify a proof for a collection of air chips.
+    /// Verify a proof for a collection of air chips, whose preprocessed traces are committed to by
+    /// the verifying key `vk`.
     #[cfg(feature = "perf")]
     pub fn verify_shard(
         config: &SC,
+        vk: &VerifyingKey<SC>,
         chips: &[&MachineChip<SC, A>],
         challenger: &mut SC::Challenger,
         proof: &ShardProof<SC>,
@@ -44,6 +47,9 @@ impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> Verifier<SC, A> {
             ..
         } = proof;
 
+        Self::verify_shape(chips, opened_values)?;
+        Self::verify_preprocessed_chips(vk, chips, opened_values)?;
+
         let (main_dims, perm_dims, quot_dims): (Vec<_>, Vec<_>, Vec<_>) = chips
             .iter()
             .zip(opened_values.chips.iter())
@@ -64,8 +70,15 @@ impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> Verifier<SC, A> {
                 )
             })
             .multiunzip();
-
-        let dims = &[main_dims, perm_dims, quot_dims];
+        let preprocessed_dims = chips
+            .iter()
+            .zip(opened_values.chips.iter())
+            .filter(|(chip, _)| chip.preprocessed_width() > 0)
+            .map(|(chip, val)| Dimensions {
+                width: chip.preprocessed_width(),
+                height: 1 << val.log_degree,
+            })
+            .collect::<Vec<_>>();
 
         let g_subgroups = opened_values
             .chips
@@ -104,15 +117,29 @@ impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> Verifier<SC, A> {
             .map(|chip| vec![zeta.exp_power_of_2(chip.log_quotient_degree())])
             .collect::<Vec<_>>();
 
+        let preprocessed_opening_points = chips
+            .iter()
+            .zip(trace_opening_points.iter())
+            .filter(|(chip, _)| chip.preprocessed_width() > 0)
+            .map(|(_, points)| points.clone())
+            .collect::<Vec<_>>();
+
+        let mut batches: Vec<(Com<SC>, &[Vec<SC::Challenge>])> = vec![];
+        let mut dims = vec![];
+        if let Some(preprocessed_commit) = &vk.commit {
+            batches.push((preprocessed_commit.clone(), &preprocessed_opening_points));
+            dims.push(preprocessed_dims);
+        }
+        batches.push((main_commit.clone(), &trace_opening_points));
+        batches.push((permutation_commit.clone(), &trace_opening_points));
+        batches.push((quotient_commit.clone(), &quotient_opening_points));
+        dims.extend([main_dims, perm_dims, quot_dims]);
+
         config
             .pcs()
             .verify_multi_batches(
-                &[
-                    (main_commit.clone(), &trace_opening_points),
-                    (permutation_commit.clone(), &trace_opening_points),
-                    (quotient_commit.clone(), &quotient_opening_points),
-                ],
-                dims,
+                &batches,
+                &dims,
                 opened_values.clone().into_values(),
                 opening_proof,
                 challenger,
@@ -137,9 +164,66 @@ impl<SC: StarkGenericConfig, A: MachineAir<SC::Val>> Verifier<SC, A> {
         Ok(())
     }
 
+    /// Checks that there are opened values for each chip, with the dimensions of the chip, so that
+    /// a malformed proof is rejected rather than making the verifier panic.
+    #[cfg(feature = "perf")]
+    fn verify_shape(
+        chips: &[&MachineChip<SC, A>],
+        opened_values: &ShardOpenedValues<SC::Challenge>,
+    ) -> Result<(), VerificationError> {
+        if chips.len() != opened_values.chips.len() {
+            return Err(VerificationError::InvalidChipSet(format!(
+                "{} chips but {} opened values",
+                chips.len(),
+                opened_values.chips.len()
+            )));
+        }
+
+        for (chip, values) in chips.iter().zip(opened_values.chips.iter()) {
+            let permutation_width = (chip.sends().len() + chip.receives().len()) * SC::Challenge::D;
+            let valid = values.log_degree <= SC::Val::TWO_ADICITY
+                && values.preprocessed.local.len() == chip.preprocessed_width()
+                && values.preprocessed.next.len() == chip.preprocessed_width()
+                && values.main.local.len() == chip.width()
+                && values.main.next.len() == chip.width()
+                && values.permutation.local.len() == permutation_width
+                && values.permutation.next.len() == permutation_width
+                && values.quotient.len() == SC::Challenge::D << chip.log_quotient_degree();
+            if !valid {
+                return Err(VerificationError::InvalidShape(chip.name()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the chips with a preprocessed trace are those of the verifying key, with the
+    /// degrees of their preprocessed traces, since the preprocessed traces are opened as a batch.
+    #[cfg(feature = "perf")]
+    fn verify_preprocessed_chips(
+        vk: &VerifyingKey<SC>,
+        chips: &[&MachineChip<SC, A>],
+        opened_values: &ShardOpenedValues<SC::Challenge>,
+    ) -> Result<(), VerificationError> {
+        let preprocessed_chips = chips
+            .iter()
+            .zip(opened_values.chips.iter())
+            .filter(|(chip, _)| chip.preprocessed_width() > 0)
+            .map(|(chip, values)| (chip.name(), values.log_degree))
+            .collect::<Vec<_>>();
+        if preprocessed_chips != vk.chip_information {
+            return Err(VerificationError::InvalidChipSet(format!(
+                "the chips with a preprocessed trace are {:?}, but the verifying key has {:?}",
+                preprocessed_chips, vk.chip_information
+            )));
+        }
+        Ok(())
+    }
+
     #[cfg(not(feature = "perf"))]
     pub fn verify_shard(
         _config: &SC,
+        _vk: &VerifyingKey<SC>,
         _chips: &[&MachineChip<SC, A>],
         _challenger: &mut SC::Challenger,
         _proof: &ShardProof<SC>,
@@ -238,6 +322,13 @@ pub enum VerificationError {
     ///
     /// `constraints(zeta)` did not match `quotient(zeta) Z_H(zeta)`.
     OodEvaluationMismatch(String),
+    /// The chips of the shard proof are not chips of the machine, in the order of the machine, with
+    /// one opened value each.
+    InvalidChipSet(String),
+    /// The opened values of a chip do not have the dimensions of its traces.
+    InvalidShape(String),
+    /// The main commitment of the shard proof is not the one of the shard.
+    MainCommitmentMismatch,
 }
 
 impl Display for VerificationError {
@@ -249,6 +340,17 @@ impl Display for VerificationError {
             VerificationError::OodEvaluationMismatch(chip) => {
                 write!(f, "Out-of-domain evaluation mismatch on chip {}", chip)
             }
+            VerificationError::InvalidChipSet(reason) => {
+                write!(f, "Invalid chip set: {}", reason)
+            }
+            VerificationError::InvalidShape(chip) => {
+                write!(f, "Invalid shape of the opened values of chip {}", chip)
+            }
+            VerificationError::MainCommitmentMismatch => {
+                write!(f, "Main commitment mismatch")
+            }
         }
     }
 }
+
+impl std::error::Error for VerificationError {}
@@ -23,7 +23,7 @@
 mod air;
 mod columns;
 mod execute;
-mod g;
+pub(crate) mod g;
 mod trace;
 use crate::runtime::{MemoryReadRecord, MemoryWriteRecord};
 
@@ -116,6 +116,10 @@ impl<F: PrimeField> MachineAir<F> for Blake3CompressInnerChip {
         )
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.blake3_compress_inner_events.len() * ROUND_COUNT * OPERATION_COUNT
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.blake3_compress_inner_events.is_empty()
     }
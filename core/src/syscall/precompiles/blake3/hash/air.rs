@@ -0,0 +1,532 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::AbstractField;
+use p3_matrix::MatrixRowSlices;
+
+use super::super::compress::g::GOperation;
+use super::super::{G_INDEX, MSG_SIZE, OPERATION_COUNT, ROUND_COUNT};
+use super::columns::{Blake3HashCols, NUM_BLAKE3_HASH_COLS};
+use super::{
+    Blake3HashChip, BLAKE3_BLOCK_LEN, BLAKE3_CHUNK_BLOCKS, BLAKE3_CHUNK_LEN, BLAKE3_CV_WORDS,
+    BLAKE3_IV, BLAKE3_MAX_SPLIT_BITS, BLAKE3_MSG_PERMUTATION, CHUNK_END, CHUNK_START, PARENT, ROOT,
+};
+use crate::air::{AirInteraction, BaseAirBuilder, MessageBuilder, SP1AirBuilder, Word, WORD_SIZE};
+use crate::lookup::InteractionKind;
+use crate::memory::MemoryCols;
+use crate::operations::{IsZeroOperation, XorOperation};
+
+impl<F> BaseAir<F> for Blake3HashChip {
+    fn width(&self) -> usize {
+        NUM_BLAKE3_HASH_COLS
+    }
+}
+
+impl<AB> Air<AB> for Blake3HashChip
+where
+    AB: SP1AirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &Blake3HashCols<AB::Var> = main.row_slice(0).borrow();
+        let next: &Blake3HashCols<AB::Var> = main.row_slice(1).borrow();
+
+        self.constrain_control_flow_flags(builder, local, next);
+
+        self.constrain_node(builder, local, next);
+
+        self.constrain_block(builder, local);
+
+        self.constrain_compression(builder, local, next);
+
+        self.constrain_output(builder, local);
+    }
+}
+
+impl Blake3HashChip {
+    /// Constrains the order of the rows: every compression is made of `ROUND_COUNT *
+    /// OPERATION_COUNT` calls of `g` followed by a finalize row, and every event is a sequence of
+    /// compressions that ends with the root.
+    fn constrain_control_flow_flags<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Blake3HashCols<AB::Var>,
+        next: &Blake3HashCols<AB::Var>,
+    ) {
+        builder.assert_bool(local.is_real);
+        builder.assert_bool(local.is_finalize);
+        builder.assert_bool(local.is_event_start);
+        for i in 0..OPERATION_COUNT {
+            builder.assert_bool(local.is_operation_index_n[i]);
+        }
+        for i in 0..ROUND_COUNT {
+            builder.assert_bool(local.is_round_index_n[i]);
+        }
+        let num_operations = local
+            .is_operation_index_n
+            .iter()
+            .fold(AB::Expr::zero(), |acc, &flag| acc + flag);
+        let num_rounds = local
+            .is_round_index_n
+            .iter()
+            .fold(AB::Expr::zero(), |acc, &flag| acc + flag);
+        builder.assert_eq(local.is_g_row, num_operations);
+        builder.assert_eq(local.is_g_row, num_rounds);
+        builder.assert_eq(local.is_g_row + local.is_finalize, local.is_real);
+        builder.assert_eq(
+            local.is_compression_start,
+            local.is_operation_index_n[0] * local.is_round_index_n[0],
+        );
+
+        // If this is the i-th operation, then the next row should be the (i+1)-th operation of the
+        // same round, the first operation of the next round, or the finalize row.
+        for i in 0..OPERATION_COUNT - 1 {
+            builder
+                .when_transition()
+                .when(local.is_operation_index_n[i])
+                .assert_one(next.is_operation_index_n[i + 1]);
+            for round in 0..ROUND_COUNT {
+                builder
+                    .when_transition()
+                    .when(local.is_operation_index_n[i])
+                    .assert_eq(local.is_round_index_n[round], next.is_round_index_n[round]);
+            }
+        }
+        for round in 0..ROUND_COUNT {
+            let mut builder_round_end = builder.when_transition();
+            let mut builder_round_end = builder_round_end.when(
+                local.is_operation_index_n[OPERATION_COUNT - 1] * local.is_round_index_n[round],
+            );
+            if round + 1 < ROUND_COUNT {
+                builder_round_end.assert_one(next.is_operation_index_n[0]);
+                builder_round_end.assert_one(next.is_round_index_n[round + 1]);
+            } else {
+                builder_round_end.assert_one(next.is_finalize);
+            }
+        }
+
+        // A finalize row is followed by the start of the next compression, or by padding.
+        builder
+            .when_transition()
+            .when(local.is_finalize)
+            .assert_eq(next.is_operation_index_n[0], next.is_real);
+        builder
+            .when_transition()
+            .when(local.is_finalize)
+            .assert_eq(next.is_round_index_n[0], next.is_real);
+        builder
+            .when_first_row()
+            .assert_eq(local.is_operation_index_n[0], local.is_real);
+        builder
+            .when_first_row()
+            .assert_eq(local.is_round_index_n[0], local.is_real);
+
+        // An event starts with a compression and ends right after the root.
+        let is_event_end = local.is_finalize * local.is_root;
+        builder
+            .when(local.is_event_start)
+            .assert_one(local.is_compression_start);
+        builder
+            .when_first_row()
+            .assert_eq(local.is_event_start, local.is_real);
+        builder
+            .when_transition()
+            .when(is_event_end.clone())
+            .assert_eq(next.is_event_start, next.is_real);
+        builder
+            .when_transition()
+            .when(local.is_real - is_event_end)
+            .assert_one(next.is_real - next.is_event_start);
+        builder
+            .when_transition()
+            .when_not(local.is_real)
+            .assert_zero(next.is_real);
+
+        // The syscall arguments are the same for the whole event.
+        let mut builder_same_event = builder.when_transition();
+        let mut builder_same_event = builder_same_event.when(next.is_real - next.is_event_start);
+        builder_same_event.assert_eq(local.shard, next.shard);
+        builder_same_event.assert_eq(local.clk, next.clk);
+        builder_same_event.assert_eq(local.input_ptr, next.input_ptr);
+        builder_same_event.assert_eq(local.input_len, next.input_len);
+        builder_same_event.assert_eq(local.output_ptr, next.output_ptr);
+        builder_same_event.assert_eq(local.num_chunks, next.num_chunks);
+    }
+
+    /// Constrains the position of the node in the tree, and how the chaining values flow between
+    /// the nodes.
+    fn constrain_node<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Blake3HashCols<AB::Var>,
+        next: &Blake3HashCols<AB::Var>,
+    ) {
+        let is_parent = local.is_real - local.is_chunk;
+
+        builder.assert_bool(local.is_chunk);
+        builder.assert_bool(local.is_chunk_end);
+        builder.assert_bool(local.is_root);
+        builder.when(local.is_chunk).assert_one(local.is_real);
+        builder.when(local.is_chunk_end).assert_one(local.is_chunk);
+        for i in 0..BLAKE3_CHUNK_BLOCKS {
+            builder.assert_bool(local.is_block_index_n[i]);
+        }
+        let num_block_flags = local
+            .is_block_index_n
+            .iter()
+            .fold(AB::Expr::zero(), |acc, &flag| acc + flag);
+        builder.assert_eq(num_block_flags, local.is_chunk);
+        builder
+            .when(local.is_block_index_n[BLAKE3_CHUNK_BLOCKS - 1])
+            .assert_one(local.is_chunk_end);
+
+        // The node is the same for every row of the compression.
+        {
+            let mut builder_same_node = builder.when_transition();
+            let mut builder_same_node = builder_same_node.when(local.is_g_row);
+            builder_same_node.assert_eq(local.node_start, next.node_start);
+            builder_same_node.assert_eq(local.node_len, next.node_len);
+            builder_same_node.assert_eq(local.is_chunk, next.is_chunk);
+            builder_same_node.assert_eq(local.is_chunk_end, next.is_chunk_end);
+            builder_same_node.assert_eq(local.is_root, next.is_root);
+            builder_same_node.assert_eq(local.is_last_chunk.inverse, next.is_last_chunk.inverse);
+            builder_same_node.assert_eq(local.is_last_chunk.result, next.is_last_chunk.result);
+            for i in 0..BLAKE3_CHUNK_BLOCKS {
+                builder_same_node.assert_eq(local.is_block_index_n[i], next.is_block_index_n[i]);
+            }
+            for i in 0..BLAKE3_BLOCK_LEN {
+                builder_same_node.assert_eq(local.is_input_byte[i], next.is_input_byte[i]);
+            }
+            for i in 0..BLAKE3_MAX_SPLIT_BITS {
+                builder_same_node.assert_eq(local.is_split_bit_n[i], next.is_split_bit_n[i]);
+            }
+            for i in 0..BLAKE3_CV_WORDS {
+                builder_same_node.assert_word_eq(local.cv[i], next.cv[i]);
+            }
+        }
+
+        // A chunk covers a single chunk and the root covers the whole message.
+        builder.when(local.is_chunk).assert_one(local.node_len);
+        builder.when(local.is_root).assert_zero(local.node_start);
+        builder
+            .when(local.is_root)
+            .assert_eq(local.node_len, local.num_chunks);
+        builder
+            .when(local.is_root)
+            .assert_one(local.is_chunk_end + is_parent.clone());
+
+        // The first block of a chunk and parent nodes start from the key, and the other blocks of
+        // a chunk continue from the previous block, which is the previous compression.
+        for i in 0..BLAKE3_CV_WORDS {
+            let iv = Word::<AB::F>::from(BLAKE3_IV[i]);
+            for j in 0..WORD_SIZE {
+                builder
+                    .when(local.is_block_index_n[0] + is_parent.clone())
+                    .assert_eq(local.cv[i][j], iv[j]);
+            }
+        }
+        let block_idx = |cols: &Blake3HashCols<AB::Var>| {
+            (0..BLAKE3_CHUNK_BLOCKS).fold(AB::Expr::zero(), |acc, i| {
+                acc + cols.is_block_index_n[i] * AB::F::from_canonical_usize(i)
+            })
+        };
+        {
+            let mut builder_next_block = builder.when_transition();
+            let mut builder_next_block = builder_next_block
+                .when(local.is_finalize * (next.is_chunk - next.is_block_index_n[0]));
+            builder_next_block.assert_one(local.is_chunk - local.is_chunk_end);
+            builder_next_block.assert_eq(local.node_start, next.node_start);
+            builder_next_block.assert_eq(block_idx(local) + AB::Expr::one(), block_idx(next));
+            for i in 0..BLAKE3_CV_WORDS {
+                builder_next_block.assert_word_eq(next.cv[i], local.output[i].value);
+            }
+        }
+        builder
+            .when_transition()
+            .when(local.is_finalize * (local.is_chunk - local.is_chunk_end))
+            .assert_one(next.is_chunk - next.is_block_index_n[0]);
+
+        // The left child of a parent node covers the largest power of two number of chunks that
+        // leaves at least one chunk for the right child.
+        let mut left_len = AB::Expr::zero();
+        let mut num_split_bits = AB::Expr::zero();
+        for i in 0..BLAKE3_MAX_SPLIT_BITS {
+            builder.assert_bool(local.is_split_bit_n[i]);
+            left_len += local.is_split_bit_n[i] * AB::F::from_canonical_u32(1 << i);
+            num_split_bits += local.is_split_bit_n[i].into();
+        }
+        builder.assert_eq(num_split_bits, is_parent.clone());
+        let two_pow_16 = AB::F::from_canonical_u32(1 << 16);
+        builder.when(local.do_receive).assert_eq(
+            local.node_len - left_len.clone() - AB::Expr::one(),
+            local.split_range_lo[0] + local.split_range_hi[0] * two_pow_16,
+        );
+        builder.when(local.do_receive).assert_eq(
+            left_len.clone() * AB::F::two() - local.node_len,
+            local.split_range_lo[1] + local.split_range_hi[1] * two_pow_16,
+        );
+        builder.slice_range_check_u16(&local.split_range_lo, local.do_receive);
+        builder.slice_range_check_u8(&local.split_range_hi, local.do_receive);
+
+        // Receive the chaining values of the children as the message of a parent node.
+        builder.assert_eq(
+            local.do_receive,
+            local.is_compression_start * is_parent.clone(),
+        );
+        let children: [(AB::Expr, AB::Expr, usize); 2] = [
+            (local.node_start.into(), left_len.clone(), 0),
+            (
+                local.node_start + left_len.clone(),
+                local.node_len - left_len,
+                BLAKE3_CV_WORDS,
+            ),
+        ];
+        for (node_start, node_len, offset) in children {
+            let cv = local.msg[offset..offset + BLAKE3_CV_WORDS]
+                .try_into()
+                .unwrap();
+            builder.receive(AirInteraction::new(
+                chaining_value_message::<AB>(local.shard, local.clk, node_start, node_len, cv),
+                local.do_receive.into(),
+                InteractionKind::Blake3ChainingValue,
+            ));
+        }
+
+        // Send the chaining value of every node but the root to its parent.
+        builder.assert_eq(
+            local.do_send,
+            local.is_finalize * (local.is_chunk_end + is_parent - local.is_root),
+        );
+        let cv = core::array::from_fn(|i| local.output[i].value);
+        builder.send(AirInteraction::new(
+            chaining_value_message::<AB>(
+                local.shard,
+                local.clk,
+                local.node_start.into(),
+                local.node_len.into(),
+                cv,
+            ),
+            local.do_send.into(),
+            InteractionKind::Blake3ChainingValue,
+        ));
+    }
+
+    /// Constrains the block of a chunk: the message bytes are read from memory and padded with
+    /// zeros, every block but the last one of a chunk is full, and every chunk but the last one of
+    /// the message is full.
+    fn constrain_block<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Blake3HashCols<AB::Var>,
+    ) {
+        let mut block_len = AB::Expr::zero();
+        for i in 0..BLAKE3_BLOCK_LEN {
+            builder.assert_bool(local.is_input_byte[i]);
+            builder
+                .when_not(local.is_chunk)
+                .assert_zero(local.is_input_byte[i]);
+            builder
+                .when(local.is_chunk - local.is_chunk_end)
+                .assert_one(local.is_input_byte[i]);
+            if i > 0 {
+                builder
+                    .when_not(local.is_input_byte[i - 1])
+                    .assert_zero(local.is_input_byte[i]);
+            }
+            block_len += local.is_input_byte[i].into();
+        }
+
+        // Only the first block of an empty message is empty.
+        builder
+            .when(local.is_chunk_end - local.is_chunk_end * local.is_block_index_n[0])
+            .assert_one(local.is_input_byte[0]);
+
+        // The last chunk ends with the message, and the others are full.
+        IsZeroOperation::<AB::F>::eval(
+            builder,
+            local.num_chunks - local.node_start - AB::Expr::one(),
+            local.is_last_chunk,
+            local.is_chunk.into(),
+        );
+        let block_idx = (0..BLAKE3_CHUNK_BLOCKS).fold(AB::Expr::zero(), |acc, i| {
+            acc + local.is_block_index_n[i] * AB::F::from_canonical_usize(i)
+        });
+        builder
+            .when(local.is_chunk_end * local.is_last_chunk.result)
+            .assert_eq(
+                local.node_start * AB::F::from_canonical_usize(BLAKE3_CHUNK_LEN)
+                    + block_idx.clone() * AB::F::from_canonical_usize(BLAKE3_BLOCK_LEN)
+                    + block_len.clone(),
+                local.input_len,
+            );
+        {
+            let mut builder_full_chunk =
+                builder.when(local.is_chunk_end - local.is_chunk_end * local.is_last_chunk.result);
+            builder_full_chunk.assert_one(local.is_block_index_n[BLAKE3_CHUNK_BLOCKS - 1]);
+            builder_full_chunk.assert_eq(
+                block_len.clone(),
+                AB::F::from_canonical_usize(BLAKE3_BLOCK_LEN),
+            );
+        }
+
+        // Read the words covering the message bytes of the block.
+        builder.assert_eq(local.do_read, local.is_compression_start * local.is_chunk);
+        let block_ptr = local.input_ptr
+            + local.node_start * AB::F::from_canonical_usize(BLAKE3_CHUNK_LEN)
+            + block_idx * AB::F::from_canonical_usize(BLAKE3_BLOCK_LEN);
+        for i in 0..MSG_SIZE {
+            builder.assert_eq(
+                local.read_word[i],
+                local.do_read * local.is_input_byte[i * WORD_SIZE],
+            );
+            builder.constraint_memory_access(
+                local.shard,
+                local.clk,
+                block_ptr.clone() + AB::F::from_canonical_usize(i * WORD_SIZE),
+                &local.msg_mem[i],
+                local.read_word[i],
+            );
+            for j in 0..WORD_SIZE {
+                builder.when(local.do_read).assert_eq(
+                    local.msg[i][j],
+                    local.is_input_byte[i * WORD_SIZE + j] * local.msg_mem[i].value()[j],
+                );
+            }
+        }
+
+        // The initial state holds the counter, the block length and the flags.
+        let mut builder_start = builder.when(local.is_compression_start);
+        let counter = (0..WORD_SIZE).fold(AB::Expr::zero(), |acc, j| {
+            acc + local.state[12][j] * AB::F::from_canonical_u32(1 << (8 * j))
+        });
+        builder_start.assert_eq(counter, local.node_start * local.is_chunk);
+        builder_start.assert_eq(
+            local.state[14][0],
+            block_len * local.is_chunk
+                + (local.is_real - local.is_chunk) * AB::F::from_canonical_usize(BLAKE3_BLOCK_LEN),
+        );
+        builder_start.assert_eq(
+            local.state[15][0],
+            local.is_block_index_n[0] * AB::F::from_canonical_u32(CHUNK_START)
+                + local.is_chunk_end * AB::F::from_canonical_u32(CHUNK_END)
+                + (local.is_real - local.is_chunk) * AB::F::from_canonical_u32(PARENT)
+                + local.is_root * AB::F::from_canonical_u32(ROOT),
+        );
+        for j in 0..WORD_SIZE {
+            builder_start.assert_zero(local.state[13][j]);
+            if j > 0 {
+                builder_start.assert_zero(local.state[14][j]);
+                builder_start.assert_zero(local.state[15][j]);
+            }
+        }
+        for i in 0..BLAKE3_CV_WORDS {
+            builder_start.assert_word_eq(local.state[i], local.cv[i]);
+        }
+        for i in 0..4 {
+            let iv = Word::<AB::F>::from(BLAKE3_IV[i]);
+            for j in 0..WORD_SIZE {
+                builder_start.assert_eq(local.state[8 + i][j], iv[j]);
+            }
+        }
+    }
+
+    /// Constrains the calls of `g` and how they update the state and the message.
+    fn constrain_compression<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Blake3HashCols<AB::Var>,
+        next: &Blake3HashCols<AB::Var>,
+    ) {
+        // Select the input of `g`.
+        for j in 0..WORD_SIZE {
+            for i in 0..4 {
+                let selected = (0..OPERATION_COUNT).fold(AB::Expr::zero(), |acc, operation| {
+                    acc + local.is_operation_index_n[operation]
+                        * local.state[G_INDEX[operation][i]][j]
+                });
+                builder.assert_eq(local.g_input[i][j], selected);
+            }
+            for i in 0..2 {
+                let selected = (0..OPERATION_COUNT).fold(AB::Expr::zero(), |acc, operation| {
+                    acc + local.is_operation_index_n[operation] * local.msg[2 * operation + i][j]
+                });
+                builder.assert_eq(local.g_input[4 + i][j], selected);
+            }
+        }
+
+        GOperation::<AB::F>::eval(builder, local.g_input, local.g, local.is_g_row);
+
+        // Write the result of `g` to the state of the next row.
+        for operation in 0..OPERATION_COUNT {
+            let mut builder_operation = builder.when_transition();
+            let mut builder_operation =
+                builder_operation.when(local.is_operation_index_n[operation]);
+            for k in 0..16 {
+                match G_INDEX[operation].iter().position(|&index| index == k) {
+                    Some(i) => builder_operation.assert_word_eq(next.state[k], local.g.result[i]),
+                    None => builder_operation.assert_word_eq(next.state[k], local.state[k]),
+                }
+            }
+        }
+
+        // Permute the message after every round.
+        for i in 0..MSG_SIZE {
+            builder
+                .when_transition()
+                .when(local.is_g_row - local.is_operation_index_n[OPERATION_COUNT - 1])
+                .assert_word_eq(next.msg[i], local.msg[i]);
+            builder
+                .when_transition()
+                .when(local.is_operation_index_n[OPERATION_COUNT - 1])
+                .assert_word_eq(next.msg[i], local.msg[BLAKE3_MSG_PERMUTATION[i]]);
+        }
+    }
+
+    /// Constrains the output of the compression function and writes the digest.
+    fn constrain_output<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Blake3HashCols<AB::Var>,
+    ) {
+        for i in 0..BLAKE3_CV_WORDS {
+            XorOperation::<AB::F>::eval(
+                builder,
+                local.state[i],
+                local.state[i + 8],
+                local.output[i],
+                local.is_finalize,
+            );
+        }
+
+        builder.assert_eq(local.do_write, local.is_finalize * local.is_root);
+        for i in 0..BLAKE3_CV_WORDS {
+            builder.constraint_memory_access(
+                local.shard,
+                local.clk + AB::F::from_canonical_u32(4),
+                local.output_ptr + AB::F::from_canonical_usize(i * WORD_SIZE),
+                &local.digest_mem[i],
+                local.do_write,
+            );
+            builder
+                .when(local.do_write)
+                .assert_word_eq(*local.digest_mem[i].value(), local.output[i].value);
+        }
+    }
+}
+
+/// The message of the interaction carrying the chaining value of a node. The bytes of the chaining
+/// value are packed in 16-bit limbs.
+fn chaining_value_message<AB: SP1AirBuilder>(
+    shard: AB::Var,
+    clk: AB::Var,
+    node_start: AB::Expr,
+    node_len: AB::Expr,
+    cv: [Word<AB::Var>; BLAKE3_CV_WORDS],
+) -> Vec<AB::Expr> {
+    let mut values = vec![shard.into(), clk.into(), node_start, node_len];
+    for word in cv.iter() {
+        values.push(word[0] + word[1] * AB::F::from_canonical_u32(1 << 8));
+        values.push(word[2] + word[3] * AB::F::from_canonical_u32(1 << 8));
+    }
+    values
+}
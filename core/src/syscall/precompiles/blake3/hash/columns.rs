@@ -0,0 +1,97 @@
+use std::mem::size_of;
+
+use sp1_derive::AlignedBorrow;
+
+use crate::air::Word;
+use crate::memory::{MemoryReadCols, MemoryWriteCols};
+use crate::operations::{IsZeroOperation, XorOperation};
+
+use super::super::compress::g::GOperation;
+use super::super::{MSG_SIZE, OPERATION_COUNT, ROUND_COUNT};
+use super::{BLAKE3_BLOCK_LEN, BLAKE3_CHUNK_BLOCKS, BLAKE3_CV_WORDS, BLAKE3_MAX_SPLIT_BITS};
+
+pub const NUM_BLAKE3_HASH_COLS: usize = size_of::<Blake3HashCols<u8>>();
+
+#[derive(AlignedBorrow, Default, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Blake3HashCols<T> {
+    pub shard: T,
+    pub clk: T,
+    pub input_ptr: T,
+    pub input_len: T,
+    pub output_ptr: T,
+    pub num_chunks: T,
+
+    /// Set on the first row of an event.
+    pub is_event_start: T,
+
+    /// The first chunk and the number of chunks covered by the node.
+    pub node_start: T,
+    pub node_len: T,
+
+    /// Whether the node is a chunk. Otherwise, it is a parent node.
+    pub is_chunk: T,
+
+    /// Indicates which block of the chunk is compressed, all zero for a parent node.
+    pub is_block_index_n: [T; BLAKE3_CHUNK_BLOCKS],
+
+    pub is_chunk_end: T,
+    pub is_root: T,
+
+    /// Whether the chunk is the last chunk of the message.
+    pub is_last_chunk: IsZeroOperation<T>,
+
+    /// Which bytes of the block are message bytes, the rest are zero padding.
+    pub is_input_byte: [T; BLAKE3_BLOCK_LEN],
+
+    /// The size of the left subtree of a parent node, as a power of two.
+    pub is_split_bit_n: [T; BLAKE3_MAX_SPLIT_BITS],
+
+    /// Range checked limbs showing that the left subtree is the largest power of two smaller
+    /// than the node.
+    pub split_range_lo: [T; 2],
+    pub split_range_hi: [T; 2],
+
+    /// The input chaining value.
+    pub cv: [Word<T>; BLAKE3_CV_WORDS],
+
+    /// Indicates which call of `g` is being performed.
+    pub is_operation_index_n: [T; OPERATION_COUNT],
+
+    /// Indicates which call of `round` is being performed.
+    pub is_round_index_n: [T; ROUND_COUNT],
+
+    pub is_g_row: T,
+    pub is_finalize: T,
+    pub is_compression_start: T,
+
+    /// The state before this row.
+    pub state: [Word<T>; 16],
+
+    /// The message, permuted after every round.
+    pub msg: [Word<T>; MSG_SIZE],
+
+    /// Reads the block of a chunk.
+    pub msg_mem: [MemoryReadCols<T>; MSG_SIZE],
+    pub read_word: [T; MSG_SIZE],
+    pub do_read: T,
+
+    /// Receives the chaining values of the children of a parent node.
+    pub do_receive: T,
+
+    /// The input of `g`: four words of the state and two words of the message.
+    pub g_input: [Word<T>; 6],
+    pub g: GOperation<T>,
+
+    /// The output of the compression function.
+    pub output: [XorOperation<T>; BLAKE3_CV_WORDS],
+
+    /// Sends the chaining value of the node to its parent.
+    pub do_send: T,
+
+    /// Writes the digest.
+    pub digest_mem: [MemoryWriteCols<T>; BLAKE3_CV_WORDS],
+    pub do_write: T,
+
+    pub is_real: T,
+}
@@ -0,0 +1,57 @@
+use crate::runtime::Register;
+use crate::runtime::Syscall;
+use crate::syscall::precompiles::SyscallContext;
+
+use super::{blake3_hash, Blake3HashChip, Blake3HashEvent};
+
+impl Syscall for Blake3HashChip {
+    fn num_extra_cycles(&self) -> u32 {
+        8
+    }
+
+    fn execute(&self, rt: &mut SyscallContext) -> u32 {
+        // Read `input_ptr`, `input_len` and `output_ptr` from registers a0, a1 and a2.
+        let input_ptr = rt.register_unsafe(Register::X10);
+        let input_len = rt.register_unsafe(Register::X11);
+        let output_ptr = rt.register_unsafe(Register::X12);
+        if input_ptr % 4 != 0 || output_ptr % 4 != 0 {
+            panic!("blake3 hash: pointers must be word aligned");
+        }
+
+        let start_clk = rt.clk;
+
+        // Read the words covering the input.
+        let (input_read_records, input) = rt.mr_slice(input_ptr, input_len.div_ceil(4) as usize);
+        let bytes = input
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .take(input_len as usize)
+            .collect::<Vec<_>>();
+
+        let digest = blake3_hash(&bytes)
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect::<Vec<_>>();
+
+        // When we write the digest, we want the clk to be incremented.
+        rt.clk += 4;
+
+        let digest_write_records = rt.mw_slice(output_ptr, &digest);
+
+        rt.clk += 4;
+
+        let shard = rt.current_shard();
+        rt.record_mut().blake3_hash_events.push(Blake3HashEvent {
+            shard,
+            clk: start_clk,
+            input_ptr,
+            input_len,
+            output_ptr,
+            input,
+            input_read_records,
+            digest_write_records: digest_write_records.try_into().unwrap(),
+        });
+
+        output_ptr
+    }
+}
@@ -0,0 +1,387 @@
+//! This module contains the implementation of the `blake3_hash` precompile, which hashes a whole
+//! message with BLAKE3 in a single syscall.
+//!
+//! The message is split into chunks of 1024 bytes. Every chunk is compressed block by block, and
+//! the chaining values of the chunks are merged pairwise by parent nodes into a binary tree whose
+//! root output is the digest. Each call of the compression function takes 57 rows in the trace:
+//! one row per call of `g`, using the same gadget as [`super::Blake3CompressInnerChip`], followed
+//! by a row that computes the output. The chaining value of a node is passed to its parent through
+//! a dedicated interaction, so the tree never goes through memory.
+mod air;
+mod columns;
+mod execute;
+mod trace;
+
+use crate::runtime::{MemoryReadRecord, MemoryWriteRecord};
+
+use serde::{Deserialize, Serialize};
+
+use super::{g_func, G_INDEX, MSG_SIZE, OPERATION_COUNT, ROUND_COUNT};
+
+/// The number of bytes in a block.
+pub const BLAKE3_BLOCK_LEN: usize = 64;
+
+/// The number of blocks in a chunk.
+pub const BLAKE3_CHUNK_BLOCKS: usize = 16;
+
+/// The number of bytes in a chunk.
+pub const BLAKE3_CHUNK_LEN: usize = BLAKE3_BLOCK_LEN * BLAKE3_CHUNK_BLOCKS;
+
+/// The number of `Word`s in a chaining value and in the digest.
+pub const BLAKE3_CV_WORDS: usize = 8;
+
+/// The number of bits needed for the size of the left subtree of a node. The message length is a
+/// `u32`, so there are at most `2^22` chunks.
+pub const BLAKE3_MAX_SPLIT_BITS: usize = 22;
+
+/// The number of rows used by a call of the compression function.
+pub const BLAKE3_ROWS_PER_COMPRESSION: usize = ROUND_COUNT * OPERATION_COUNT + 1;
+
+/// The initial chaining value, which is also the key in the default hashing mode.
+pub const BLAKE3_IV: [u32; BLAKE3_CV_WORDS] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A, 0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
+];
+
+/// The permutation applied to the message words after every round.
+pub const BLAKE3_MSG_PERMUTATION: [usize; MSG_SIZE] =
+    [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+pub const CHUNK_START: u32 = 1 << 0;
+pub const CHUNK_END: u32 = 1 << 1;
+pub const PARENT: u32 = 1 << 2;
+pub const ROOT: u32 = 1 << 3;
+
+/// An event for the `BLAKE3_HASH` precompile.
+///
+/// The syscall reads `input_len` bytes starting at `input_ptr` and writes their 32-byte BLAKE3
+/// digest to `output_ptr`. The input is read as the `input_len.div_ceil(4)` words that cover it,
+/// and the bytes past `input_len` are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Blake3HashEvent {
+    pub shard: u32,
+    pub clk: u32,
+    pub input_ptr: u32,
+    pub input_len: u32,
+    pub output_ptr: u32,
+    pub input: Vec<u32>,
+    pub input_read_records: Vec<MemoryReadRecord>,
+    pub digest_write_records: [MemoryWriteRecord; BLAKE3_CV_WORDS],
+}
+
+/// A call of the compression function made while hashing a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blake3Compression {
+    /// The first chunk covered by the node.
+    pub node_start: u32,
+
+    /// The number of chunks covered by the node.
+    pub node_len: u32,
+
+    /// The index of the block in its chunk, or `None` for a parent node.
+    pub block_idx: Option<u32>,
+
+    /// The number of message bytes in the block.
+    pub block_len: u32,
+
+    /// Whether this is the last block of its chunk.
+    pub chunk_end: bool,
+
+    /// Whether this is the root node.
+    pub is_root: bool,
+
+    /// The number of chunks covered by the left child of a parent node.
+    pub left_len: u32,
+
+    pub cv: [u32; BLAKE3_CV_WORDS],
+    pub msg: [u32; MSG_SIZE],
+    pub output: [u32; BLAKE3_CV_WORDS],
+}
+
+impl Blake3Compression {
+    /// The block counter, which is the chunk index for chunk nodes and zero for parent nodes.
+    pub fn counter(&self) -> u32 {
+        match self.block_idx {
+            Some(_) => self.node_start,
+            None => 0,
+        }
+    }
+
+    /// The domain separation flags of the compression.
+    pub fn flags(&self) -> u32 {
+        let mut flags = match self.block_idx {
+            Some(0) => CHUNK_START,
+            Some(_) => 0,
+            None => PARENT,
+        };
+        if self.chunk_end {
+            flags |= CHUNK_END;
+        }
+        if self.is_root {
+            flags |= ROOT;
+        }
+        flags
+    }
+
+    /// The initial state of the compression function.
+    pub fn initial_state(&self) -> [u32; 16] {
+        let mut state = [0u32; 16];
+        state[..8].copy_from_slice(&self.cv);
+        state[8..12].copy_from_slice(&BLAKE3_IV[..4]);
+        state[12] = self.counter();
+        state[13] = 0;
+        state[14] = self.block_len;
+        state[15] = self.flags();
+        state
+    }
+}
+
+/// The BLAKE3 compression function, truncated to the chaining value.
+pub fn blake3_compress(
+    cv: &[u32; BLAKE3_CV_WORDS],
+    msg: &[u32; MSG_SIZE],
+    counter: u32,
+    block_len: u32,
+    flags: u32,
+) -> [u32; BLAKE3_CV_WORDS] {
+    let mut state = [0u32; 16];
+    state[..8].copy_from_slice(cv);
+    state[8..12].copy_from_slice(&BLAKE3_IV[..4]);
+    state[12] = counter;
+    state[14] = block_len;
+    state[15] = flags;
+
+    let mut msg = *msg;
+    for _ in 0..ROUND_COUNT {
+        for (operation, index) in G_INDEX.iter().enumerate() {
+            let result = g_func([
+                state[index[0]],
+                state[index[1]],
+                state[index[2]],
+                state[index[3]],
+                msg[2 * operation],
+                msg[2 * operation + 1],
+            ]);
+            for (i, value) in index.iter().zip(result) {
+                state[*i] = value;
+            }
+        }
+        msg = core::array::from_fn(|i| msg[BLAKE3_MSG_PERMUTATION[i]]);
+    }
+
+    core::array::from_fn(|i| state[i] ^ state[i + 8])
+}
+
+/// Returns the number of chunks of a message of `input_len` bytes. An empty message still has one
+/// (empty) chunk.
+pub fn blake3_num_chunks(input_len: usize) -> usize {
+    input_len.div_ceil(BLAKE3_CHUNK_LEN).max(1)
+}
+
+/// Returns all of the compressions made while hashing `input`, in the order they appear in the
+/// trace. The nodes of the tree are visited in post-order, so the root comes last and its output
+/// is the digest.
+pub fn blake3_compressions(input: &[u8]) -> Vec<Blake3Compression> {
+    let mut compressions = Vec::new();
+    let num_chunks = blake3_num_chunks(input.len()) as u32;
+    blake3_node(input, 0, num_chunks, true, &mut compressions);
+    compressions
+}
+
+/// Appends the compressions of the node covering `node_len` chunks from `node_start`, and returns
+/// its chaining value.
+fn blake3_node(
+    input: &[u8],
+    node_start: u32,
+    node_len: u32,
+    is_root: bool,
+    compressions: &mut Vec<Blake3Compression>,
+) -> [u32; BLAKE3_CV_WORDS] {
+    if node_len == 1 {
+        let chunk_start = node_start as usize * BLAKE3_CHUNK_LEN;
+        let chunk = &input[chunk_start..input.len().min(chunk_start + BLAKE3_CHUNK_LEN)];
+        let num_blocks = chunk.len().div_ceil(BLAKE3_BLOCK_LEN).max(1);
+
+        let mut cv = BLAKE3_IV;
+        for block_idx in 0..num_blocks {
+            let block = &chunk[(block_idx * BLAKE3_BLOCK_LEN).min(chunk.len())
+                ..chunk.len().min((block_idx + 1) * BLAKE3_BLOCK_LEN)];
+            let mut bytes = [0u8; BLAKE3_BLOCK_LEN];
+            bytes[..block.len()].copy_from_slice(block);
+            let msg = core::array::from_fn(|i| {
+                u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap())
+            });
+
+            let chunk_end = block_idx == num_blocks - 1;
+            let mut compression = Blake3Compression {
+                node_start,
+                node_len,
+                block_idx: Some(block_idx as u32),
+                block_len: block.len() as u32,
+                chunk_end,
+                is_root: is_root && chunk_end,
+                left_len: 0,
+                cv,
+                msg,
+                output: [0; BLAKE3_CV_WORDS],
+            };
+            compression.output = blake3_compress(
+                &cv,
+                &msg,
+                compression.counter(),
+                compression.block_len,
+                compression.flags(),
+            );
+            cv = compression.output;
+            compressions.push(compression);
+        }
+        cv
+    } else {
+        // The left subtree holds the largest power of two number of chunks that leaves at least
+        // one chunk for the right subtree.
+        let left_len = 1 << (31 - (node_len - 1).leading_zeros());
+        let left_cv = blake3_node(input, node_start, left_len, false, compressions);
+        let right_cv = blake3_node(
+            input,
+            node_start + left_len,
+            node_len - left_len,
+            false,
+            compressions,
+        );
+
+        let mut msg = [0u32; MSG_SIZE];
+        msg[..8].copy_from_slice(&left_cv);
+        msg[8..].copy_from_slice(&right_cv);
+        let mut compression = Blake3Compression {
+            node_start,
+            node_len,
+            block_idx: None,
+            block_len: BLAKE3_BLOCK_LEN as u32,
+            chunk_end: false,
+            is_root,
+            left_len,
+            cv: BLAKE3_IV,
+            msg,
+            output: [0; BLAKE3_CV_WORDS],
+        };
+        compression.output = blake3_compress(
+            &BLAKE3_IV,
+            &msg,
+            0,
+            compression.block_len,
+            compression.flags(),
+        );
+        compressions.push(compression);
+        compression.output
+    }
+}
+
+/// Hashes `input` with BLAKE3.
+pub fn blake3_hash(input: &[u8]) -> [u8; 32] {
+    let root = blake3_compressions(input).pop().unwrap();
+    let mut digest = [0u8; 32];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(root.output.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+/// A chip that hashes a whole message with BLAKE3.
+#[derive(Default)]
+pub struct Blake3HashChip;
+
+impl Blake3HashChip {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// The number of rows of the trace taken by `event`: one compression per block of the chunks,
+    /// and one per parent node of the tree.
+    pub fn event_rows(event: &Blake3HashEvent) -> usize {
+        let input_len = event.input_len as usize;
+        let num_blocks = input_len.div_ceil(BLAKE3_BLOCK_LEN).max(1);
+        let num_parents = blake3_num_chunks(input_len) - 1;
+        BLAKE3_ROWS_PER_COMPRESSION * (num_blocks + num_parents)
+    }
+}
+
+#[cfg(test)]
+pub mod hash_tests {
+    use crate::runtime::{Instruction, Opcode, Program, Runtime, SyscallCode};
+    use crate::utils::{run_test, setup_logger};
+
+    use super::blake3_hash;
+
+    /// A program that hashes `input` with the `BLAKE3_HASH` precompile.
+    pub fn blake3_hash_program(input: &[u8]) -> Program {
+        let input_ptr = 100;
+        let output_ptr = 10000;
+        let mut instructions = vec![];
+        for (i, chunk) in input.chunks(4).enumerate() {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            instructions.extend(vec![
+                Instruction::new(Opcode::ADD, 29, 0, u32::from_le_bytes(word), false, true),
+                Instruction::new(Opcode::ADD, 30, 0, input_ptr + i as u32 * 4, false, true),
+                Instruction::new(Opcode::SW, 29, 30, 0, false, true),
+            ]);
+        }
+        instructions.extend(vec![
+            Instruction::new(
+                Opcode::ADD,
+                5,
+                0,
+                SyscallCode::BLAKE3_HASH as u32,
+                false,
+                true,
+            ),
+            Instruction::new(Opcode::ADD, 10, 0, input_ptr, false, true),
+            Instruction::new(Opcode::ADD, 11, 0, input.len() as u32, false, true),
+            Instruction::new(Opcode::ADD, 12, 0, output_ptr, false, true),
+            Instruction::new(Opcode::ECALL, 10, 5, 0, false, true),
+        ]);
+        Program::new(instructions, 0, 0)
+    }
+
+    fn test_input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_blake3_hash_matches_reference() {
+        // Lengths around the block and chunk boundaries, and enough chunks for uneven trees.
+        for len in [0, 1, 63, 64, 65, 1023, 1024, 1025, 2048, 3073, 5 * 1024 + 7] {
+            let input = test_input(len);
+            assert_eq!(
+                blake3_hash(&input),
+                *blake3::hash(&input).as_bytes(),
+                "len = {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_blake3_hash_execute() {
+        for len in [0, 5, 64, 1500, 3073] {
+            let input = test_input(len);
+            let mut runtime = Runtime::new(blake3_hash_program(&input));
+            runtime.run();
+            let digest = (0..8)
+                .flat_map(|i| runtime.word(10000 + i * 4).to_le_bytes())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                digest,
+                blake3::hash(&input).as_bytes().to_vec(),
+                "len = {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn prove_babybear() {
+        setup_logger();
+        let input = test_input(2100);
+        run_test(blake3_hash_program(&input)).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+use std::borrow::BorrowMut;
+
+use p3_field::PrimeField;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::air::{MachineAir, Word};
+use crate::runtime::ExecutionRecord;
+use crate::utils::pad_rows;
+
+use super::super::{G_INDEX, MSG_SIZE, OPERATION_COUNT, ROUND_COUNT};
+use super::columns::{Blake3HashCols, NUM_BLAKE3_HASH_COLS};
+use super::{
+    blake3_compressions, blake3_num_chunks, Blake3HashChip, BLAKE3_BLOCK_LEN, BLAKE3_CHUNK_LEN,
+    BLAKE3_MSG_PERMUTATION,
+};
+
+impl<F: PrimeField> MachineAir<F> for Blake3HashChip {
+    type Record = ExecutionRecord;
+
+    fn name(&self) -> String {
+        "Blake3Hash".to_string()
+    }
+
+    fn generate_trace(
+        &self,
+        input: &ExecutionRecord,
+        output: &mut ExecutionRecord,
+    ) -> RowMajorMatrix<F> {
+        let mut rows = Vec::new();
+
+        let mut new_field_events = Vec::new();
+
+        for event in input.blake3_hash_events.iter() {
+            let bytes = event
+                .input
+                .iter()
+                .flat_map(|word| word.to_le_bytes())
+                .take(event.input_len as usize)
+                .collect::<Vec<_>>();
+            let num_chunks = blake3_num_chunks(bytes.len()) as u32;
+
+            for (compression_idx, compression) in blake3_compressions(&bytes).iter().enumerate() {
+                let is_chunk = compression.block_idx.is_some();
+                let block_idx = compression.block_idx.unwrap_or_default();
+
+                // Fill in the columns that are shared by every row of the compression.
+                let populate_common = |cols: &mut Blake3HashCols<F>| {
+                    cols.shard = F::from_canonical_u32(event.shard);
+                    cols.clk = F::from_canonical_u32(event.clk);
+                    cols.input_ptr = F::from_canonical_u32(event.input_ptr);
+                    cols.input_len = F::from_canonical_u32(event.input_len);
+                    cols.output_ptr = F::from_canonical_u32(event.output_ptr);
+                    cols.num_chunks = F::from_canonical_u32(num_chunks);
+
+                    cols.node_start = F::from_canonical_u32(compression.node_start);
+                    cols.node_len = F::from_canonical_u32(compression.node_len);
+                    cols.is_chunk = F::from_bool(is_chunk);
+                    cols.is_chunk_end = F::from_bool(compression.chunk_end);
+                    cols.is_root = F::from_bool(compression.is_root);
+                    cols.cv = compression.cv.map(Word::from);
+
+                    if is_chunk {
+                        cols.is_block_index_n[block_idx as usize] = F::one();
+                        cols.is_last_chunk
+                            .populate(num_chunks - 1 - compression.node_start);
+                        for i in 0..compression.block_len as usize {
+                            cols.is_input_byte[i] = F::one();
+                        }
+                    } else {
+                        let split_bit = compression.left_len.trailing_zeros() as usize;
+                        cols.is_split_bit_n[split_bit] = F::one();
+                        let ranges = [
+                            compression.node_len - compression.left_len - 1,
+                            2 * compression.left_len - compression.node_len,
+                        ];
+                        for (i, range) in ranges.iter().enumerate() {
+                            cols.split_range_lo[i] = F::from_canonical_u32(range & 0xffff);
+                            cols.split_range_hi[i] = F::from_canonical_u32(range >> 16);
+                        }
+                    }
+
+                    cols.is_real = F::one();
+                };
+
+                let mut state = compression.initial_state();
+                let mut msg = compression.msg;
+                for round in 0..ROUND_COUNT {
+                    for operation in 0..OPERATION_COUNT {
+                        let mut row = [F::zero(); NUM_BLAKE3_HASH_COLS];
+                        let cols: &mut Blake3HashCols<F> = row.as_mut_slice().borrow_mut();
+                        populate_common(cols);
+
+                        cols.is_event_start =
+                            F::from_bool(compression_idx == 0 && round == 0 && operation == 0);
+                        cols.is_round_index_n[round] = F::one();
+                        cols.is_operation_index_n[operation] = F::one();
+                        cols.is_g_row = F::one();
+                        cols.state = state.map(Word::from);
+                        cols.msg = msg.map(Word::from);
+
+                        // Read the block or receive the chaining values of the children.
+                        if round == 0 && operation == 0 {
+                            cols.is_compression_start = F::one();
+                            if is_chunk {
+                                cols.do_read = F::one();
+                                let block_start = compression.node_start as usize
+                                    * BLAKE3_CHUNK_LEN
+                                    + block_idx as usize * BLAKE3_BLOCK_LEN;
+                                for i in 0..MSG_SIZE {
+                                    let word_idx = block_start / 4 + i;
+                                    if block_start + i * 4 < bytes.len() {
+                                        cols.read_word[i] = F::one();
+                                        cols.msg_mem[i].populate(
+                                            event.input_read_records[word_idx],
+                                            &mut new_field_events,
+                                        );
+                                    }
+                                }
+                            } else {
+                                cols.do_receive = F::one();
+                            }
+                        }
+
+                        // Apply the `g` operation.
+                        let index = G_INDEX[operation];
+                        let input = [
+                            state[index[0]],
+                            state[index[1]],
+                            state[index[2]],
+                            state[index[3]],
+                            msg[2 * operation],
+                            msg[2 * operation + 1],
+                        ];
+                        cols.g_input = input.map(Word::from);
+                        let result = cols.g.populate(output, input);
+                        for (i, value) in index.iter().zip(result) {
+                            state[*i] = value;
+                        }
+
+                        rows.push(row);
+                    }
+                    msg = core::array::from_fn(|i| msg[BLAKE3_MSG_PERMUTATION[i]]);
+                }
+
+                // Compute the output and pass it on.
+                let mut row = [F::zero(); NUM_BLAKE3_HASH_COLS];
+                let cols: &mut Blake3HashCols<F> = row.as_mut_slice().borrow_mut();
+                populate_common(cols);
+
+                cols.is_finalize = F::one();
+                cols.state = state.map(Word::from);
+                cols.msg = msg.map(Word::from);
+                for i in 0..8 {
+                    cols.output[i].populate(output, state[i], state[i + 8]);
+                }
+
+                if compression.is_root {
+                    cols.do_write = F::one();
+                    for (i, record) in event.digest_write_records.iter().enumerate() {
+                        cols.digest_mem[i].populate(*record, &mut new_field_events);
+                    }
+                } else if compression.chunk_end || !is_chunk {
+                    cols.do_send = F::one();
+                }
+
+                rows.push(row);
+            }
+        }
+
+        output.add_field_events(&new_field_events);
+
+        pad_rows(&mut rows, || [F::zero(); NUM_BLAKE3_HASH_COLS]);
+
+        // Convert the trace to a row major matrix.
+        RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_BLAKE3_HASH_COLS,
+        )
+    }
+
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.blake3_hash_events.iter().map(Self::event_rows).sum()
+    }
+
+    fn included(&self, shard: &Self::Record) -> bool {
+        !shard.blake3_hash_events.is_empty()
+    }
+}
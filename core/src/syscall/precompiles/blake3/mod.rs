@@ -1,3 +1,5 @@
 mod compress;
+mod hash;
 
 pub use compress::*;
+pub use hash::*;
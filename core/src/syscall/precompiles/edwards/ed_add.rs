@@ -193,6 +193,10 @@ impl<F: PrimeField32, E: EllipticCurve + EdwardsParameters> MachineAir<F> for Ed
         )
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.ed_add_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.ed_add_events.is_empty()
     }
@@ -310,6 +310,10 @@ impl<F: PrimeField32, E: EdwardsParameters> MachineAir<F> for EdDecompressChip<E
         )
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.ed_decompress_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.ed_decompress_events.is_empty()
     }
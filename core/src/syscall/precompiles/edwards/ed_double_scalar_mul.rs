@@ -0,0 +1,845 @@
+use crate::air::MachineAir;
+use crate::air::SP1AirBuilder;
+use crate::air::{BaseAirBuilder, Word};
+use crate::field::event::FieldEvent;
+use crate::memory::MemoryCols;
+use crate::memory::MemoryReadCols;
+use crate::memory::MemoryWriteCols;
+use crate::operations::field::field_den::FieldDenCols;
+use crate::operations::field::field_inner_product::FieldInnerProductCols;
+use crate::operations::field::field_op::FieldOpCols;
+use crate::operations::field::field_op::FieldOperation;
+use crate::operations::field::params::Limbs;
+use crate::operations::field::params::NUM_LIMBS;
+use crate::runtime::ExecutionRecord;
+use crate::runtime::Syscall;
+use crate::runtime::{MemoryReadRecord, MemoryWriteRecord};
+use crate::syscall::precompiles::limbs_from_biguint;
+use crate::syscall::precompiles::SyscallContext;
+use crate::utils::ec::edwards::EdwardsParameters;
+use crate::utils::ec::field::FieldParameters;
+use crate::utils::ec::AffinePoint;
+use crate::utils::ec::EllipticCurve;
+use crate::utils::ec::NUM_BYTES_FIELD_ELEMENT;
+use crate::utils::ec::NUM_WORDS_EC_POINT;
+use crate::utils::ec::NUM_WORDS_FIELD_ELEMENT;
+use crate::utils::pad_rows;
+use crate::utils::{limbs_from_access, limbs_from_prev_access};
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::size_of;
+use num::BigUint;
+use num::Zero;
+use p3_air::AirBuilder;
+use p3_air::{Air, BaseAir};
+use p3_field::AbstractField;
+use p3_field::PrimeField32;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::MatrixRowSlices;
+use p3_maybe_rayon::prelude::IntoParallelRefIterator;
+use p3_maybe_rayon::prelude::ParallelIterator;
+use serde::{Deserialize, Serialize};
+use sp1_derive::AlignedBorrow;
+use std::marker::PhantomData;
+use tracing::instrument;
+
+/// The number of bits of each scalar, which is also the number of rows of an event.
+pub const NUM_SCALAR_BITS: usize = NUM_BYTES_FIELD_ELEMENT * 8;
+
+pub const NUM_ED_DOUBLE_SCALAR_MUL_COLS: usize = size_of::<EdDoubleScalarMulCols<u8>>();
+
+/// Edwards curve double scalar multiplication event.
+///
+/// The syscall reads the point `P` at `p_ptr` and the scalars `a` and `b` at `scalars_ptr`, and
+/// overwrites `P` with `a * G + b * P`, where `G` is the generator of the curve.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdDoubleScalarMulEvent {
+    pub shard: u32,
+    pub clk: u32,
+    pub p_ptr: u32,
+    pub p: [u32; NUM_WORDS_EC_POINT],
+    pub scalars_ptr: u32,
+    pub scalars: [u32; 2 * NUM_WORDS_FIELD_ELEMENT],
+    pub scalars_ptr_record: MemoryReadRecord,
+    pub p_memory_records: [MemoryWriteRecord; NUM_WORDS_EC_POINT],
+    pub scalars_memory_records: [MemoryReadRecord; 2 * NUM_WORDS_FIELD_ELEMENT],
+}
+
+/// Computes `a * G + b * P` with a double-and-add loop over the bits of the scalars, starting from
+/// the most significant one. This is the computation carried out by the rows of an event.
+pub fn ed_double_scalar_mul<E: EllipticCurve + EdwardsParameters>(
+    a: &[u32],
+    b: &[u32],
+    p: &AffinePoint<E>,
+) -> AffinePoint<E> {
+    let g = E::ec_generator();
+    let mut acc = E::ec_neutral().unwrap();
+    for bit in (0..NUM_SCALAR_BITS).rev() {
+        acc = &acc + &acc;
+        if (a[bit / 32] >> (bit % 32)) & 1 == 1 {
+            acc = &acc + &g;
+        }
+        if (b[bit / 32] >> (bit % 32)) & 1 == 1 {
+            acc = &acc + p;
+        }
+    }
+    acc
+}
+
+/// A set of columns to compute the sum of two points with the twisted Edwards addition law, which
+/// is complete so it also covers doubling and the neutral element.
+#[derive(Debug, Clone, AlignedBorrow)]
+#[repr(C)]
+pub struct EdAddCols<T> {
+    pub(crate) x3_numerator: FieldInnerProductCols<T>,
+    pub(crate) y3_numerator: FieldInnerProductCols<T>,
+    pub(crate) x1_mul_y1: FieldOpCols<T>,
+    pub(crate) x2_mul_y2: FieldOpCols<T>,
+    pub(crate) f: FieldOpCols<T>,
+    pub(crate) d_mul_f: FieldOpCols<T>,
+    pub(crate) x3_ins: FieldDenCols<T>,
+    pub(crate) y3_ins: FieldDenCols<T>,
+}
+
+impl<F: PrimeField32> EdAddCols<F> {
+    pub fn populate<E: EllipticCurve + EdwardsParameters>(
+        &mut self,
+        p: &AffinePoint<E>,
+        q: &AffinePoint<E>,
+    ) -> AffinePoint<E> {
+        let (p_x, p_y) = (&p.x, &p.y);
+        let (q_x, q_y) = (&q.x, &q.y);
+        let x3_numerator = self
+            .x3_numerator
+            .populate::<E::BaseField>(&[p_x.clone(), q_x.clone()], &[q_y.clone(), p_y.clone()]);
+        let y3_numerator = self
+            .y3_numerator
+            .populate::<E::BaseField>(&[p_y.clone(), p_x.clone()], &[q_y.clone(), q_x.clone()]);
+        let x1_mul_y1 = self
+            .x1_mul_y1
+            .populate::<E::BaseField>(p_x, p_y, FieldOperation::Mul);
+        let x2_mul_y2 = self
+            .x2_mul_y2
+            .populate::<E::BaseField>(q_x, q_y, FieldOperation::Mul);
+        let f = self
+            .f
+            .populate::<E::BaseField>(&x1_mul_y1, &x2_mul_y2, FieldOperation::Mul);
+
+        let d = E::d_biguint();
+        let d_mul_f = self
+            .d_mul_f
+            .populate::<E::BaseField>(&f, &d, FieldOperation::Mul);
+
+        let x3 = self
+            .x3_ins
+            .populate::<E::BaseField>(&x3_numerator, &d_mul_f, true);
+        let y3 = self
+            .y3_ins
+            .populate::<E::BaseField>(&y3_numerator, &d_mul_f, false);
+
+        AffinePoint::new(x3, y3)
+    }
+}
+
+impl<V: Copy> EdAddCols<V> {
+    pub fn eval<AB: SP1AirBuilder<Var = V>, E: EllipticCurve + EdwardsParameters>(
+        &self,
+        builder: &mut AB,
+        x1: &Limbs<V>,
+        y1: &Limbs<V>,
+        x2: &Limbs<V>,
+        y2: &Limbs<V>,
+    ) where
+        V: Into<AB::Expr>,
+    {
+        // x3_numerator = x1 * y2 + x2 * y1.
+        self.x3_numerator
+            .eval::<AB, E::BaseField>(builder, &[*x1, *x2], &[*y2, *y1]);
+
+        // y3_numerator = y1 * y2 + x1 * x2.
+        self.y3_numerator
+            .eval::<AB, E::BaseField>(builder, &[*y1, *x1], &[*y2, *x2]);
+
+        // f = x1 * x2 * y1 * y2.
+        self.x1_mul_y1
+            .eval::<AB, E::BaseField, _, _>(builder, x1, y1, FieldOperation::Mul);
+        self.x2_mul_y2
+            .eval::<AB, E::BaseField, _, _>(builder, x2, y2, FieldOperation::Mul);
+
+        let x1_mul_y1 = self.x1_mul_y1.result;
+        let x2_mul_y2 = self.x2_mul_y2.result;
+        self.f
+            .eval::<AB, E::BaseField, _, _>(builder, &x1_mul_y1, &x2_mul_y2, FieldOperation::Mul);
+
+        // d * f.
+        let f = self.f.result;
+        let d_const_expr = limbs_from_biguint::<AB, E::BaseField>(&E::d_biguint());
+        self.d_mul_f.eval::<AB, E::BaseField, _, _>(
+            builder,
+            &f,
+            &d_const_expr,
+            FieldOperation::Mul,
+        );
+
+        let d_mul_f = self.d_mul_f.result;
+
+        // x3 = x3_numerator / (1 + d * f).
+        self.x3_ins
+            .eval::<AB, E::BaseField>(builder, &self.x3_numerator.result, &d_mul_f, true);
+
+        // y3 = y3_numerator / (1 - d * f).
+        self.y3_ins
+            .eval::<AB, E::BaseField>(builder, &self.y3_numerator.result, &d_mul_f, false);
+    }
+
+    /// The coordinates of the sum.
+    pub fn result(&self) -> (Limbs<V>, Limbs<V>) {
+        (self.x3_ins.result, self.y3_ins.result)
+    }
+}
+
+/// A set of columns for one step of the double-and-add loop of `EdDoubleScalarMul`. An event
+/// takes `NUM_SCALAR_BITS` rows, the i-th one doubling the accumulator and adding the i-th most
+/// significant bits of the scalars times `G` and `P`.
+#[derive(Debug, Clone, AlignedBorrow)]
+#[repr(C)]
+pub struct EdDoubleScalarMulCols<T> {
+    pub is_real: T,
+    pub shard: T,
+    pub clk: T,
+    pub p_ptr: T,
+    pub scalars_ptr: T,
+
+    /// Which byte of the scalars is consumed by this row.
+    pub is_byte_index_n: [T; NUM_BYTES_FIELD_ELEMENT],
+
+    /// Which bit of the byte is consumed by this row, from the most significant one.
+    pub is_bit_index_n: [T; 8],
+
+    pub is_first_row: T,
+    pub is_last_row: T,
+
+    /// The scalars `a` and `b`, read on the first row of the event.
+    pub scalars: [Word<T>; 2 * NUM_WORDS_FIELD_ELEMENT],
+
+    /// The bits of the scalars consumed by this row.
+    pub a_bit: T,
+    pub b_bit: T,
+
+    /// The bits of the current byte of the scalars consumed so far.
+    pub a_prefix: T,
+    pub b_prefix: T,
+
+    /// The point `P`, read on the last row of the event as the previous value of the output.
+    pub p_x: Limbs<T>,
+    pub p_y: Limbs<T>,
+
+    /// The accumulator before this row.
+    pub acc_x: Limbs<T>,
+    pub acc_y: Limbs<T>,
+
+    pub double: EdAddCols<T>,
+
+    /// `G` if `a_bit` is set, the neutral element otherwise.
+    pub g_x: Limbs<T>,
+    pub g_y: Limbs<T>,
+    pub add_g: EdAddCols<T>,
+
+    /// `P` if `b_bit` is set, the neutral element otherwise.
+    pub q_x: Limbs<T>,
+    pub q_y: Limbs<T>,
+    pub add_p: EdAddCols<T>,
+
+    pub scalars_ptr_access: MemoryReadCols<T>,
+    pub scalars_access: [MemoryReadCols<T>; 2 * NUM_WORDS_FIELD_ELEMENT],
+    pub p_access: [MemoryWriteCols<T>; NUM_WORDS_EC_POINT],
+}
+
+#[derive(Default)]
+pub struct EdDoubleScalarMulChip<E> {
+    _marker: PhantomData<E>,
+}
+
+impl<E: EllipticCurve + EdwardsParameters> EdDoubleScalarMulChip<E> {
+    pub fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+
+    /// Populates one step of the loop and returns the next accumulator.
+    fn populate_step<F: PrimeField32>(
+        cols: &mut EdDoubleScalarMulCols<F>,
+        acc: &AffinePoint<E>,
+        p: &AffinePoint<E>,
+        a_bit: bool,
+        b_bit: bool,
+    ) -> AffinePoint<E> {
+        let neutral = E::ec_neutral().unwrap();
+        let g = if a_bit {
+            E::ec_generator()
+        } else {
+            neutral.clone()
+        };
+        let q = if b_bit { p.clone() } else { neutral };
+
+        cols.a_bit = F::from_bool(a_bit);
+        cols.b_bit = F::from_bool(b_bit);
+        cols.acc_x = E::BaseField::to_limbs_field::<F>(&acc.x);
+        cols.acc_y = E::BaseField::to_limbs_field::<F>(&acc.y);
+        cols.g_x = E::BaseField::to_limbs_field::<F>(&g.x);
+        cols.g_y = E::BaseField::to_limbs_field::<F>(&g.y);
+        cols.q_x = E::BaseField::to_limbs_field::<F>(&q.x);
+        cols.q_y = E::BaseField::to_limbs_field::<F>(&q.y);
+
+        let doubled = cols.double.populate(acc, acc);
+        let with_g = cols.add_g.populate(&doubled, &g);
+        cols.add_p.populate(&with_g, &q)
+    }
+}
+
+impl<E: EllipticCurve + EdwardsParameters> Syscall for EdDoubleScalarMulChip<E> {
+    fn num_extra_cycles(&self) -> u32 {
+        8
+    }
+
+    fn execute(&self, rt: &mut SyscallContext) -> u32 {
+        let a0 = crate::runtime::Register::X10;
+        let a1 = crate::runtime::Register::X11;
+
+        let start_clk = rt.clk;
+
+        let p_ptr = rt.register_unsafe(a0);
+        if p_ptr % 4 != 0 {
+            panic!();
+        }
+
+        let (scalars_ptr_record, scalars_ptr) = rt.mr(a1 as u32);
+        if scalars_ptr % 4 != 0 {
+            panic!();
+        }
+
+        let p: [u32; NUM_WORDS_EC_POINT] = rt
+            .slice_unsafe(p_ptr, NUM_WORDS_EC_POINT)
+            .try_into()
+            .unwrap();
+        let (scalars_memory_records, scalars) =
+            rt.mr_slice(scalars_ptr, 2 * NUM_WORDS_FIELD_ELEMENT);
+
+        // When we write to p, we want the clk to be incremented.
+        rt.clk += 4;
+
+        let p_affine = AffinePoint::<E>::from_words_le(&p);
+        let result_affine = ed_double_scalar_mul(
+            &scalars[..NUM_WORDS_FIELD_ELEMENT],
+            &scalars[NUM_WORDS_FIELD_ELEMENT..],
+            &p_affine,
+        );
+        let result_words = result_affine.to_words_le();
+
+        let p_memory_records = rt.mw_slice(p_ptr, &result_words).try_into().unwrap();
+
+        rt.clk += 4;
+
+        let shard = rt.current_shard();
+        rt.record_mut()
+            .ed_double_scalar_mul_events
+            .push(EdDoubleScalarMulEvent {
+                shard,
+                clk: start_clk,
+                p_ptr,
+                p,
+                scalars_ptr,
+                scalars: scalars.try_into().unwrap(),
+                scalars_ptr_record,
+                p_memory_records,
+                scalars_memory_records: scalars_memory_records.try_into().unwrap(),
+            });
+
+        p_ptr
+    }
+}
+
+impl<F: PrimeField32, E: EllipticCurve + EdwardsParameters> MachineAir<F>
+    for EdDoubleScalarMulChip<E>
+{
+    type Record = ExecutionRecord;
+
+    fn name(&self) -> String {
+        "EdDoubleScalarMul".to_string()
+    }
+
+    #[instrument(
+        name = "generate ed double scalar mul trace",
+        level = "debug",
+        skip_all
+    )]
+    fn generate_trace(
+        &self,
+        input: &ExecutionRecord,
+        output: &mut ExecutionRecord,
+    ) -> RowMajorMatrix<F> {
+        let (rows_list, new_field_events_list): (
+            Vec<Vec<[F; NUM_ED_DOUBLE_SCALAR_MUL_COLS]>>,
+            Vec<Vec<FieldEvent>>,
+        ) = input
+            .ed_double_scalar_mul_events
+            .par_iter()
+            .map(|event| {
+                let p = AffinePoint::<E>::from_words_le(&event.p);
+                let mut acc = E::ec_neutral().unwrap();
+
+                let mut rows = Vec::with_capacity(NUM_SCALAR_BITS);
+                let mut new_field_events = Vec::new();
+                for i in 0..NUM_SCALAR_BITS {
+                    let mut row = [F::zero(); NUM_ED_DOUBLE_SCALAR_MUL_COLS];
+                    let cols: &mut EdDoubleScalarMulCols<F> = row.as_mut_slice().borrow_mut();
+
+                    // Populate basic columns.
+                    cols.is_real = F::one();
+                    cols.shard = F::from_canonical_u32(event.shard);
+                    cols.clk = F::from_canonical_u32(event.clk);
+                    cols.p_ptr = F::from_canonical_u32(event.p_ptr);
+                    cols.scalars_ptr = F::from_canonical_u32(event.scalars_ptr);
+                    cols.scalars = event.scalars.map(Word::from);
+                    cols.p_x = E::BaseField::to_limbs_field::<F>(&p.x);
+                    cols.p_y = E::BaseField::to_limbs_field::<F>(&p.y);
+
+                    // The row consumes the bits of the scalars from the most significant one.
+                    let bit = NUM_SCALAR_BITS - 1 - i;
+                    let (byte_idx, bit_idx) = (bit / 8, i % 8);
+                    cols.is_byte_index_n[byte_idx] = F::one();
+                    cols.is_bit_index_n[bit_idx] = F::one();
+                    let a_byte = event.scalars[byte_idx / 4].to_le_bytes()[byte_idx % 4];
+                    let b_byte = event.scalars[NUM_WORDS_FIELD_ELEMENT + byte_idx / 4]
+                        .to_le_bytes()[byte_idx % 4];
+                    cols.a_prefix = F::from_canonical_u8(a_byte >> (7 - bit_idx));
+                    cols.b_prefix = F::from_canonical_u8(b_byte >> (7 - bit_idx));
+
+                    acc = Self::populate_step(
+                        cols,
+                        &acc,
+                        &p,
+                        (a_byte >> (bit % 8)) & 1 == 1,
+                        (b_byte >> (bit % 8)) & 1 == 1,
+                    );
+
+                    // Populate the memory access columns.
+                    if i == 0 {
+                        cols.is_first_row = F::one();
+                        cols.scalars_ptr_access
+                            .populate(event.scalars_ptr_record, &mut new_field_events);
+                        for j in 0..2 * NUM_WORDS_FIELD_ELEMENT {
+                            cols.scalars_access[j]
+                                .populate(event.scalars_memory_records[j], &mut new_field_events);
+                        }
+                    }
+                    if i == NUM_SCALAR_BITS - 1 {
+                        cols.is_last_row = F::one();
+                        for j in 0..NUM_WORDS_EC_POINT {
+                            cols.p_access[j]
+                                .populate(event.p_memory_records[j], &mut new_field_events);
+                        }
+                    }
+
+                    rows.push(row);
+                }
+
+                (rows, new_field_events)
+            })
+            .unzip();
+
+        let mut rows = rows_list.into_iter().flatten().collect::<Vec<_>>();
+        for new_field_events in new_field_events_list {
+            output.add_field_events(&new_field_events);
+        }
+
+        pad_rows(&mut rows, || {
+            let mut row = [F::zero(); NUM_ED_DOUBLE_SCALAR_MUL_COLS];
+            let cols: &mut EdDoubleScalarMulCols<F> = row.as_mut_slice().borrow_mut();
+            let zero = AffinePoint::<E>::new(BigUint::zero(), BigUint::zero());
+            Self::populate_step(cols, &zero, &zero, false, false);
+            row
+        });
+
+        // Convert the trace to a row major matrix.
+        RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_ED_DOUBLE_SCALAR_MUL_COLS,
+        )
+    }
+
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.ed_double_scalar_mul_events.len() * NUM_SCALAR_BITS
+    }
+
+    fn included(&self, shard: &Self::Record) -> bool {
+        !shard.ed_double_scalar_mul_events.is_empty()
+    }
+}
+
+impl<F, E: EllipticCurve + EdwardsParameters> BaseAir<F> for EdDoubleScalarMulChip<E> {
+    fn width(&self) -> usize {
+        NUM_ED_DOUBLE_SCALAR_MUL_COLS
+    }
+}
+
+impl<AB, E: EllipticCurve + EdwardsParameters> Air<AB> for EdDoubleScalarMulChip<E>
+where
+    AB: SP1AirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &EdDoubleScalarMulCols<AB::Var> = main.row_slice(0).borrow();
+        let next: &EdDoubleScalarMulCols<AB::Var> = main.row_slice(1).borrow();
+
+        // Every row consumes one bit of each scalar, starting from the most significant one.
+        builder.assert_bool(local.is_real);
+        let mut num_byte_flags = AB::Expr::zero();
+        for flag in local.is_byte_index_n.iter() {
+            builder.assert_bool(*flag);
+            num_byte_flags += (*flag).into();
+        }
+        let mut num_bit_flags = AB::Expr::zero();
+        for flag in local.is_bit_index_n.iter() {
+            builder.assert_bool(*flag);
+            num_bit_flags += (*flag).into();
+        }
+        builder.assert_eq(num_byte_flags, local.is_real);
+        builder.assert_eq(num_bit_flags, local.is_real);
+        builder.assert_eq(
+            local.is_first_row,
+            local.is_byte_index_n[NUM_BYTES_FIELD_ELEMENT - 1] * local.is_bit_index_n[0],
+        );
+        builder.assert_eq(
+            local.is_last_row,
+            local.is_byte_index_n[0] * local.is_bit_index_n[7],
+        );
+
+        builder
+            .when_first_row()
+            .assert_eq(local.is_first_row, local.is_real);
+        builder
+            .when_transition()
+            .when(local.is_last_row)
+            .assert_eq(next.is_first_row, next.is_real);
+        builder
+            .when_transition()
+            .when(local.is_real - local.is_last_row)
+            .assert_one(next.is_real);
+        builder
+            .when_transition()
+            .when_not(local.is_real)
+            .assert_zero(next.is_real);
+        for i in 0..7 {
+            builder
+                .when_transition()
+                .when(local.is_bit_index_n[i])
+                .assert_one(next.is_bit_index_n[i + 1]);
+            for j in 0..NUM_BYTES_FIELD_ELEMENT {
+                builder
+                    .when_transition()
+                    .when(local.is_bit_index_n[i])
+                    .assert_eq(local.is_byte_index_n[j], next.is_byte_index_n[j]);
+            }
+        }
+        for j in 1..NUM_BYTES_FIELD_ELEMENT {
+            let mut builder_next_byte = builder.when_transition();
+            let mut builder_next_byte =
+                builder_next_byte.when(local.is_bit_index_n[7] * local.is_byte_index_n[j]);
+            builder_next_byte.assert_one(next.is_bit_index_n[0]);
+            builder_next_byte.assert_one(next.is_byte_index_n[j - 1]);
+        }
+
+        // The inputs are the same for every row of the event.
+        {
+            let mut builder_same_event = builder.when_transition();
+            let mut builder_same_event = builder_same_event.when(local.is_real - local.is_last_row);
+            builder_same_event.assert_eq(local.shard, next.shard);
+            builder_same_event.assert_eq(local.clk, next.clk);
+            builder_same_event.assert_eq(local.p_ptr, next.p_ptr);
+            builder_same_event.assert_eq(local.scalars_ptr, next.scalars_ptr);
+            for i in 0..2 * NUM_WORDS_FIELD_ELEMENT {
+                builder_same_event.assert_word_eq(local.scalars[i], next.scalars[i]);
+            }
+            for i in 0..NUM_LIMBS {
+                builder_same_event.assert_eq(local.p_x[i], next.p_x[i]);
+                builder_same_event.assert_eq(local.p_y[i], next.p_y[i]);
+            }
+        }
+
+        // The bits consumed from each byte of the scalars add up to that byte.
+        builder.assert_bool(local.a_bit);
+        builder.assert_bool(local.b_bit);
+        builder
+            .when(local.is_bit_index_n[0])
+            .assert_eq(local.a_prefix, local.a_bit);
+        builder
+            .when(local.is_bit_index_n[0])
+            .assert_eq(local.b_prefix, local.b_bit);
+        builder
+            .when_transition()
+            .when(local.is_real - local.is_bit_index_n[7])
+            .assert_eq(next.a_prefix, local.a_prefix * AB::F::two() + next.a_bit);
+        builder
+            .when_transition()
+            .when(local.is_real - local.is_bit_index_n[7])
+            .assert_eq(next.b_prefix, local.b_prefix * AB::F::two() + next.b_bit);
+        let mut a_byte = AB::Expr::zero();
+        let mut b_byte = AB::Expr::zero();
+        for j in 0..NUM_BYTES_FIELD_ELEMENT {
+            a_byte += local.is_byte_index_n[j] * local.scalars[j / 4][j % 4];
+            b_byte +=
+                local.is_byte_index_n[j] * local.scalars[NUM_WORDS_FIELD_ELEMENT + j / 4][j % 4];
+        }
+        builder
+            .when(local.is_bit_index_n[7])
+            .assert_eq(local.a_prefix, a_byte);
+        builder
+            .when(local.is_bit_index_n[7])
+            .assert_eq(local.b_prefix, b_byte);
+
+        // The accumulator starts from the neutral element and is carried over to the next row.
+        let (neutral_x, neutral_y) = E::neutral();
+        let neutral_x = limbs_from_biguint::<AB, E::BaseField>(&neutral_x);
+        let neutral_y = limbs_from_biguint::<AB, E::BaseField>(&neutral_y);
+        let (result_x, result_y) = local.add_p.result();
+        for i in 0..NUM_LIMBS {
+            builder
+                .when(local.is_first_row)
+                .assert_eq(local.acc_x[i], neutral_x[i].clone());
+            builder
+                .when(local.is_first_row)
+                .assert_eq(local.acc_y[i], neutral_y[i].clone());
+            builder
+                .when_transition()
+                .when(local.is_real - local.is_last_row)
+                .assert_eq(next.acc_x[i], result_x[i]);
+            builder
+                .when_transition()
+                .when(local.is_real - local.is_last_row)
+                .assert_eq(next.acc_y[i], result_y[i]);
+        }
+
+        // acc = 2 * acc + a_bit * G + b_bit * P.
+        local.double.eval::<AB, E>(
+            builder,
+            &local.acc_x,
+            &local.acc_y,
+            &local.acc_x,
+            &local.acc_y,
+        );
+
+        let (generator_x, generator_y) = E::generator();
+        let generator_x = limbs_from_biguint::<AB, E::BaseField>(&generator_x);
+        let generator_y = limbs_from_biguint::<AB, E::BaseField>(&generator_y);
+        for i in 0..NUM_LIMBS {
+            builder.assert_eq(
+                local.g_x[i],
+                generator_x[i].clone() * local.a_bit
+                    + neutral_x[i].clone() * (AB::Expr::one() - local.a_bit),
+            );
+            builder.assert_eq(
+                local.g_y[i],
+                generator_y[i].clone() * local.a_bit
+                    + neutral_y[i].clone() * (AB::Expr::one() - local.a_bit),
+            );
+            builder.assert_eq(
+                local.q_x[i],
+                local.p_x[i] * local.b_bit + neutral_x[i].clone() * (AB::Expr::one() - local.b_bit),
+            );
+            builder.assert_eq(
+                local.q_y[i],
+                local.p_y[i] * local.b_bit + neutral_y[i].clone() * (AB::Expr::one() - local.b_bit),
+            );
+        }
+
+        let (doubled_x, doubled_y) = local.double.result();
+        local
+            .add_g
+            .eval::<AB, E>(builder, &doubled_x, &doubled_y, &local.g_x, &local.g_y);
+        let (with_g_x, with_g_y) = local.add_g.result();
+        local
+            .add_p
+            .eval::<AB, E>(builder, &with_g_x, &with_g_y, &local.q_x, &local.q_y);
+
+        // Read the scalars on the first row.
+        builder.constraint_memory_access(
+            local.shard,
+            local.clk, // clk + 0 -> C
+            AB::F::from_canonical_u32(11),
+            &local.scalars_ptr_access,
+            local.is_first_row,
+        );
+        builder.when(local.is_first_row).assert_eq(
+            local.scalars_ptr,
+            local.scalars_ptr_access.value().reduce::<AB>(),
+        );
+        for i in 0..2 * NUM_WORDS_FIELD_ELEMENT {
+            builder.constraint_memory_access(
+                local.shard,
+                local.clk, // clk + 0 -> Memory
+                local.scalars_ptr + AB::F::from_canonical_u32(i as u32 * 4),
+                &local.scalars_access[i],
+                local.is_first_row,
+            );
+            builder
+                .when(local.is_first_row)
+                .assert_word_eq(local.scalars[i], *local.scalars_access[i].value());
+        }
+
+        // Overwrite `P` with the result on the last row.
+        let prev_x = limbs_from_prev_access(&local.p_access[0..NUM_WORDS_FIELD_ELEMENT]);
+        let prev_y = limbs_from_prev_access(&local.p_access[NUM_WORDS_FIELD_ELEMENT..]);
+        let new_x = limbs_from_access(&local.p_access[0..NUM_WORDS_FIELD_ELEMENT]);
+        let new_y = limbs_from_access(&local.p_access[NUM_WORDS_FIELD_ELEMENT..]);
+        for i in 0..NUM_LIMBS {
+            builder
+                .when(local.is_last_row)
+                .assert_eq(prev_x[i], local.p_x[i]);
+            builder
+                .when(local.is_last_row)
+                .assert_eq(prev_y[i], local.p_y[i]);
+            builder
+                .when(local.is_last_row)
+                .assert_eq(new_x[i], result_x[i]);
+            builder
+                .when(local.is_last_row)
+                .assert_eq(new_y[i], result_y[i]);
+        }
+        for i in 0..NUM_WORDS_EC_POINT {
+            builder.constraint_memory_access(
+                local.shard,
+                local.clk + AB::F::from_canonical_u32(4), // clk + 4 -> Memory
+                local.p_ptr + AB::F::from_canonical_u32(i as u32 * 4),
+                &local.p_access[i],
+                local.is_last_row,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod ed_double_scalar_mul_tests {
+    use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+    use curve25519_dalek::edwards::EdwardsPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use num::BigUint;
+    use rand::Rng;
+
+    use super::{ed_double_scalar_mul, NUM_WORDS_EC_POINT};
+    use crate::runtime::{Instruction, Opcode, Program, Runtime, SyscallCode};
+    use crate::utils::ec::edwards::ed25519::{decompress, Ed25519};
+    use crate::utils::ec::{AffinePoint, EllipticCurve};
+    use crate::utils::{run_test, setup_logger};
+
+    fn to_affine(point: &EdwardsPoint) -> AffinePoint<Ed25519> {
+        decompress(&point.compress())
+    }
+
+    fn to_words(bytes: &[u8; 32]) -> Vec<u32> {
+        bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    /// A program that overwrites `p` with `a * G + b * p` using the `ED_DOUBLE_SCALAR_MUL`
+    /// precompile.
+    pub fn ed_double_scalar_mul_program(a: &[u32], b: &[u32], p: &[u32]) -> Program {
+        let p_ptr = 100;
+        let scalars_ptr = 1000;
+        let mut instructions = vec![];
+        let words = p
+            .iter()
+            .enumerate()
+            .map(|(i, word)| (p_ptr + i as u32 * 4, *word))
+            .chain(
+                a.iter()
+                    .chain(b.iter())
+                    .enumerate()
+                    .map(|(i, word)| (scalars_ptr + i as u32 * 4, *word)),
+            );
+        for (addr, word) in words {
+            instructions.extend(vec![
+                Instruction::new(Opcode::ADD, 29, 0, word, false, true),
+                Instruction::new(Opcode::ADD, 30, 0, addr, false, true),
+                Instruction::new(Opcode::SW, 29, 30, 0, false, true),
+            ]);
+        }
+        instructions.extend(vec![
+            Instruction::new(
+                Opcode::ADD,
+                5,
+                0,
+                SyscallCode::ED_DOUBLE_SCALAR_MUL as u32,
+                false,
+                true,
+            ),
+            Instruction::new(Opcode::ADD, 10, 0, p_ptr, false, true),
+            Instruction::new(Opcode::ADD, 11, 0, scalars_ptr, false, true),
+            Instruction::new(Opcode::ECALL, 10, 5, 0, false, true),
+        ]);
+        Program::new(instructions, 0, 0)
+    }
+
+    fn random_inputs() -> (Scalar, Scalar, EdwardsPoint) {
+        let mut rng = rand::thread_rng();
+        let a = Scalar::from_bytes_mod_order(rng.gen());
+        let b = Scalar::from_bytes_mod_order(rng.gen());
+        let c = Scalar::from_bytes_mod_order(rng.gen());
+        (a, b, ED25519_BASEPOINT_POINT * c)
+    }
+
+    #[test]
+    fn test_ed_double_scalar_mul_matches_dalek() {
+        for _ in 0..4 {
+            let (a, b, p) = random_inputs();
+            let expected = EdwardsPoint::vartime_double_scalar_mul_basepoint(&b, &p, &a);
+            let result = ed_double_scalar_mul(
+                &to_words(a.as_bytes()),
+                &to_words(b.as_bytes()),
+                &to_affine(&p),
+            );
+            assert_eq!(result, to_affine(&expected));
+        }
+    }
+
+    #[test]
+    fn test_ed_double_scalar_mul_full_width_scalars() {
+        // The scalars are not reduced, so all 256 bits are used.
+        let a = [u32::MAX; 8];
+        let b = [0x8000_0001u32; 8];
+        let p = Ed25519::ec_generator();
+        let expected = &(&p * BigUint::from_slice(&a)) + &(&p * BigUint::from_slice(&b));
+        assert_eq!(ed_double_scalar_mul(&a, &b, &p), expected);
+    }
+
+    #[test]
+    fn test_ed_double_scalar_mul_execute() {
+        let (a, b, p) = random_inputs();
+        let expected = EdwardsPoint::vartime_double_scalar_mul_basepoint(&b, &p, &a);
+        let p_words = to_affine(&p).to_words_le();
+        let mut runtime = Runtime::new(ed_double_scalar_mul_program(
+            &to_words(a.as_bytes()),
+            &to_words(b.as_bytes()),
+            &p_words,
+        ));
+        runtime.run();
+        let result = (0..NUM_WORDS_EC_POINT as u32)
+            .map(|i| runtime.word(100 + i * 4))
+            .collect::<Vec<_>>();
+        assert_eq!(result, to_affine(&expected).to_words_le().to_vec());
+    }
+
+    #[test]
+    fn prove_babybear() {
+        setup_logger();
+        let (a, b, p) = random_inputs();
+        let program = ed_double_scalar_mul_program(
+            &to_words(a.as_bytes()),
+            &to_words(b.as_bytes()),
+            &to_affine(&p).to_words_le(),
+        );
+        run_test(program).unwrap();
+    }
+}
@@ -1,5 +1,7 @@
 mod ed_add;
 mod ed_decompress;
+mod ed_double_scalar_mul;
 
 pub use ed_add::*;
 pub use ed_decompress::*;
+pub use ed_double_scalar_mul::*;
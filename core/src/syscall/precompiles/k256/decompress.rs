@@ -340,6 +340,10 @@ impl<F: PrimeField32> MachineAir<F> for K256DecompressChip {
         )
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.k256_decompress_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.k256_decompress_events.is_empty()
     }
@@ -5,7 +5,7 @@ use crate::{
 
 use p3_keccak_air::{NUM_ROUNDS, RC};
 
-use super::{KeccakPermuteChip, STATE_NUM_WORDS};
+use super::{KeccakPermuteChip, STATE_NUM_WORDS, STATE_SIZE};
 
 const RHO: [u32; 24] = [
     1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
@@ -39,54 +39,15 @@ impl Syscall for KeccakPermuteChip {
             state.push(least_sig as u64 + ((most_sig as u64) << 32));
         }
 
-        let saved_state = state.clone();
-
-        for i in 0..NUM_ROUNDS {
-            let mut array: [u64; 5 * 5] = [0; 5 * 5];
-
-            // Theta
-            for x in 0..5 {
-                for y_count in 0..5 {
-                    let y = y_count * 5;
-                    array[x] ^= state[x + y];
-                }
-            }
-
-            for x in 0..5 {
-                for y_count in 0..5 {
-                    let y = y_count * 5;
-                    state[y + x] ^= array[(x + 4) % 5] ^ array[(x + 1) % 5].rotate_left(1);
-                }
-            }
-
-            // Rho and pi
-            let mut last = state[1];
-            for x in 0..24 {
-                array[0] = state[PI[x]];
-                state[PI[x]] = last.rotate_left(RHO[x]);
-                last = array[0];
-            }
-
-            // Chi
-            for y_step in 0..5 {
-                let y = y_step * 5;
-
-                array[..5].copy_from_slice(&state[y..(5 + y)]);
-
-                for x in 0..5 {
-                    state[y + x] = array[x] ^ ((!array[(x + 1) % 5]) & (array[(x + 2) % 5]));
-                }
-            }
-
-            // Iota
-            state[0] ^= RC[i];
-        }
+        let pre_state: [u64; STATE_SIZE] = state.as_slice().try_into().unwrap();
+        let mut post_state = pre_state;
+        keccakf(&mut post_state);
 
         rt.clk += self.num_extra_cycles() - 4;
         let mut values_to_write = Vec::new();
-        for i in 0..25 {
-            let most_sig = ((state[i] >> 32) & 0xFFFFFFFF) as u32;
-            let least_sig = (state[i] & 0xFFFFFFFF) as u32;
+        for lane in post_state.iter() {
+            let most_sig = ((lane >> 32) & 0xFFFFFFFF) as u32;
+            let least_sig = (lane & 0xFFFFFFFF) as u32;
             values_to_write.push(least_sig);
             values_to_write.push(most_sig);
         }
@@ -103,8 +64,8 @@ impl Syscall for KeccakPermuteChip {
             .push(KeccakPermuteEvent {
                 shard,
                 clk: saved_clk,
-                pre_state: saved_state.as_slice().try_into().unwrap(),
-                post_state: state.as_slice().try_into().unwrap(),
+                pre_state,
+                post_state,
                 state_read_records,
                 state_write_records,
                 state_addr: state_ptr,
@@ -113,3 +74,47 @@ impl Syscall for KeccakPermuteChip {
         state_ptr
     }
 }
+
+/// Applies the Keccak-f[1600] permutation to `state` in place.
+pub fn keccakf(state: &mut [u64; STATE_SIZE]) {
+    for rc in RC.iter().take(NUM_ROUNDS) {
+        let mut array: [u64; 5 * 5] = [0; 5 * 5];
+
+        // Theta
+        for x in 0..5 {
+            for y_count in 0..5 {
+                let y = y_count * 5;
+                array[x] ^= state[x + y];
+            }
+        }
+
+        for x in 0..5 {
+            for y_count in 0..5 {
+                let y = y_count * 5;
+                state[y + x] ^= array[(x + 4) % 5] ^ array[(x + 1) % 5].rotate_left(1);
+            }
+        }
+
+        // Rho and pi
+        let mut last = state[1];
+        for x in 0..24 {
+            array[0] = state[PI[x]];
+            state[PI[x]] = last.rotate_left(RHO[x]);
+            last = array[0];
+        }
+
+        // Chi
+        for y_step in 0..5 {
+            let y = y_step * 5;
+
+            array[..5].copy_from_slice(&state[y..(5 + y)]);
+
+            for x in 0..5 {
+                state[y + x] = array[x] ^ ((!array[(x + 1) % 5]) & (array[(x + 2) % 5]));
+            }
+        }
+
+        // Iota
+        state[0] ^= rc;
+    }
+}
@@ -5,8 +5,12 @@ use serde::{Deserialize, Serialize};
 mod air;
 pub mod columns;
 mod execute;
+mod sponge;
 mod trace;
 
+pub use execute::keccakf;
+pub use sponge::*;
+
 const STATE_SIZE: usize = 25;
 
 // The permutation state is 25 u64's.  Our word size is 32 bits, so it is 50 words.
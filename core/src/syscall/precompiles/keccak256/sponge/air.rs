@@ -0,0 +1,246 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::AbstractField;
+use p3_keccak_air::{KeccakAir, KeccakCols, NUM_KECCAK_COLS, NUM_ROUNDS, U64_LIMBS};
+use p3_matrix::MatrixRowSlices;
+
+use crate::{
+    air::{BaseAirBuilder, SP1AirBuilder, SubAirBuilder, Word},
+    memory::MemoryCols,
+    operations::XorOperation,
+    syscall::precompiles::keccak256::STATE_SIZE,
+};
+
+use super::{
+    columns::{Keccak256Cols, NUM_KECCAK256_COLS},
+    Keccak256Chip, KECCAK256_DIGEST_WORDS, KECCAK256_RATE_BYTES, KECCAK256_RATE_LANES,
+    KECCAK256_RATE_WORDS,
+};
+
+impl<F> BaseAir<F> for Keccak256Chip {
+    fn width(&self) -> usize {
+        NUM_KECCAK_COLS + NUM_KECCAK256_COLS
+    }
+}
+
+/// Splits a lane given as two words of bytes into the four 16-bit limbs used by the keccak air.
+fn lane_limbs<AB: SP1AirBuilder>(lo: Word<AB::Var>, hi: Word<AB::Var>) -> [AB::Expr; U64_LIMBS] {
+    let base = AB::Expr::from_canonical_u32(1 << 8);
+    [
+        lo[0] + lo[1] * base.clone(),
+        lo[2] + lo[3] * base.clone(),
+        hi[0] + hi[1] * base.clone(),
+        hi[2] + hi[3] * base,
+    ]
+}
+
+impl<AB> Air<AB> for Keccak256Chip
+where
+    AB: SP1AirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+
+        let local_keccak: &KeccakCols<AB::Var> = main.row_slice(0)[..NUM_KECCAK_COLS].borrow();
+        let local: &Keccak256Cols<AB::Var> = main.row_slice(0)[NUM_KECCAK_COLS..].borrow();
+        let next: &Keccak256Cols<AB::Var> = main.row_slice(1)[NUM_KECCAK_COLS..].borrow();
+
+        let first_step = local_keccak.step_flags[0];
+        let last_step = local_keccak.step_flags[NUM_ROUNDS - 1];
+
+        builder.assert_bool(local.is_real);
+        builder.assert_bool(local.is_first_block);
+        builder.assert_bool(local.is_final_block);
+        builder.when(local.is_first_block).assert_one(local.is_real);
+        builder.when(local.is_final_block).assert_one(local.is_real);
+        builder.assert_eq(first_step * local.is_real, local.do_absorb);
+        builder.assert_eq(last_step * local.is_final_block, local.do_squeeze);
+
+        // Whether the next row continues the same event.
+        let next_continues = next.is_real - next.is_first_block;
+
+        // An event starts at the first block, and every other row belongs to the same permutation
+        // as the previous row or to the block right after it.
+        builder
+            .when_first_row()
+            .assert_zero(local.is_real - local.is_first_block);
+        builder
+            .when_transition()
+            .when_not(last_step)
+            .assert_eq(local.is_real, next.is_real);
+        builder
+            .when_transition()
+            .when(last_step * (AB::Expr::one() - local.is_real))
+            .assert_zero(next_continues.clone());
+        {
+            let mut builder_same_perm = builder.when_transition();
+            let mut builder_same_perm =
+                builder_same_perm.when((AB::Expr::one() - last_step) * local.is_real);
+            builder_same_perm.assert_eq(local.block_idx, next.block_idx);
+            builder_same_perm.assert_eq(local.is_first_block, next.is_first_block);
+            builder_same_perm.assert_eq(local.is_final_block, next.is_final_block);
+        }
+        {
+            let mut builder_next_block = builder.when_transition();
+            let mut builder_next_block =
+                builder_next_block.when(last_step * (local.is_real - local.is_final_block));
+            builder_next_block.assert_one(next_continues.clone());
+            builder_next_block.assert_eq(local.block_idx + AB::Expr::one(), next.block_idx);
+        }
+        builder
+            .when_transition()
+            .when(last_step * local.is_final_block)
+            .assert_zero(next_continues.clone());
+        builder
+            .when(local.is_first_block)
+            .assert_zero(local.block_idx);
+
+        // The syscall arguments are the same for the whole event.
+        {
+            let mut builder_same_event = builder.when_transition();
+            let mut builder_same_event = builder_same_event.when(next_continues.clone());
+            builder_same_event.assert_eq(local.shard, next.shard);
+            builder_same_event.assert_eq(local.clk, next.clk);
+            builder_same_event.assert_eq(local.input_ptr, next.input_ptr);
+            builder_same_event.assert_eq(local.input_len, next.input_len);
+            builder_same_event.assert_eq(local.output_ptr, next.output_ptr);
+        }
+
+        // The message bytes are a prefix of the message. Every block but the last is full, and the
+        // last one has room for at least one byte of padding.
+        for i in 0..KECCAK256_RATE_BYTES {
+            builder.assert_bool(local.is_input_byte[i]);
+            builder
+                .when_not(local.do_absorb)
+                .assert_zero(local.is_input_byte[i]);
+            builder
+                .when(local.do_absorb - local.do_absorb * local.is_final_block)
+                .assert_one(local.is_input_byte[i]);
+            if i > 0 {
+                builder
+                    .when_not(local.is_input_byte[i - 1])
+                    .assert_zero(local.is_input_byte[i]);
+            }
+        }
+        builder
+            .when(local.is_final_block)
+            .assert_zero(local.is_input_byte[KECCAK256_RATE_BYTES - 1]);
+        let num_input_bytes = local
+            .is_input_byte
+            .iter()
+            .fold(AB::Expr::zero(), |acc, &flag| acc + flag);
+        builder
+            .when(local.do_absorb * local.is_final_block)
+            .assert_eq(
+                local.block_idx * AB::Expr::from_canonical_usize(KECCAK256_RATE_BYTES)
+                    + num_input_bytes,
+                local.input_len,
+            );
+
+        // Read the words covering the message bytes of the block.
+        let block_ptr = local.input_ptr
+            + local.block_idx * AB::Expr::from_canonical_usize(KECCAK256_RATE_BYTES);
+        for i in 0..KECCAK256_RATE_WORDS {
+            builder.constraint_memory_access(
+                local.shard,
+                local.clk,
+                block_ptr.clone() + AB::Expr::from_canonical_usize(i * 4),
+                &local.block_mem[i],
+                local.is_input_byte[i * 4],
+            );
+        }
+
+        // Pad the block: the message bytes are followed by `0x01`, zeros and a final `0x80`.
+        for i in 0..KECCAK256_RATE_BYTES {
+            let is_input_byte = local.is_input_byte[i];
+            let prev_is_input_byte = if i == 0 {
+                AB::Expr::one()
+            } else {
+                local.is_input_byte[i - 1].into()
+            };
+            let mut padded_byte = is_input_byte * local.block_mem[i / 4].value()[i % 4]
+                + prev_is_input_byte
+                - is_input_byte;
+            if i == KECCAK256_RATE_BYTES - 1 {
+                padded_byte += local.is_final_block * AB::F::from_canonical_u32(0x80);
+            }
+            builder
+                .when(local.do_absorb)
+                .assert_eq(local.block[i / 4][i % 4], padded_byte);
+        }
+
+        // Absorb the block into the rate of the state.
+        for i in 0..KECCAK256_RATE_WORDS {
+            XorOperation::<AB::F>::eval(
+                builder,
+                local.prev_state[i],
+                local.block[i],
+                local.absorbed[i],
+                local.do_absorb,
+            );
+        }
+
+        // The first block is absorbed into the zero state.
+        for word in local.prev_state.iter() {
+            for byte in word.0.iter() {
+                builder
+                    .when(local.do_absorb * local.is_first_block)
+                    .assert_zero(*byte);
+            }
+        }
+
+        // The input of the permutation is the absorbed state.
+        for i in 0..STATE_SIZE {
+            let words = if i < KECCAK256_RATE_LANES {
+                (local.absorbed[2 * i].value, local.absorbed[2 * i + 1].value)
+            } else {
+                (local.prev_state[2 * i], local.prev_state[2 * i + 1])
+            };
+            let limbs = lane_limbs::<AB>(words.0, words.1);
+            let a_value_limbs = local_keccak.a[i / 5][i % 5];
+            for (limb, a_limb) in limbs.into_iter().zip(a_value_limbs) {
+                builder.when(local.do_absorb).assert_eq(limb, a_limb);
+            }
+        }
+
+        // The output of the permutation is carried over to the next block of the same event.
+        for i in 0..STATE_SIZE {
+            let limbs = lane_limbs::<AB>(next.prev_state[2 * i], next.prev_state[2 * i + 1]);
+            for (j, limb) in limbs.into_iter().enumerate() {
+                builder
+                    .when_transition()
+                    .when(last_step * next_continues.clone())
+                    .assert_eq(limb, local_keccak.a_prime_prime_prime(i % 5, i / 5, j));
+            }
+        }
+
+        // Write the first four lanes of the final state as the digest.
+        for i in 0..KECCAK256_DIGEST_WORDS {
+            builder.constraint_memory_access(
+                local.shard,
+                local.clk + AB::Expr::from_canonical_u32(4),
+                local.output_ptr + AB::Expr::from_canonical_usize(i * 4),
+                &local.digest_mem[i],
+                local.do_squeeze,
+            );
+        }
+        for i in 0..KECCAK256_DIGEST_WORDS / 2 {
+            let limbs = lane_limbs::<AB>(
+                *local.digest_mem[2 * i].value(),
+                *local.digest_mem[2 * i + 1].value(),
+            );
+            for (j, limb) in limbs.into_iter().enumerate() {
+                builder
+                    .when(local.do_squeeze)
+                    .assert_eq(limb, local_keccak.a_prime_prime_prime(i, 0, j));
+            }
+        }
+
+        let mut sub_builder =
+            SubAirBuilder::<AB, KeccakAir, AB::Var>::new(builder, 0..NUM_KECCAK_COLS);
+
+        // Eval the plonky3 keccak air
+        self.p3_keccak.eval(&mut sub_builder);
+    }
+}
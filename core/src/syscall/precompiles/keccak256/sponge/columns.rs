@@ -0,0 +1,51 @@
+use core::mem::size_of;
+
+use sp1_derive::AlignedBorrow;
+
+use crate::{
+    air::Word,
+    memory::{MemoryReadCols, MemoryWriteCols},
+    operations::XorOperation,
+    syscall::precompiles::keccak256::STATE_NUM_WORDS,
+};
+
+use super::{KECCAK256_DIGEST_WORDS, KECCAK256_RATE_BYTES, KECCAK256_RATE_WORDS};
+
+/// The columns of the sponge, laid out next to the plonky3 keccak columns.
+#[derive(AlignedBorrow)]
+#[repr(C)]
+pub(crate) struct Keccak256Cols<T> {
+    pub shard: T,
+    pub clk: T,
+    pub input_ptr: T,
+    pub input_len: T,
+    pub output_ptr: T,
+
+    /// The index of the block absorbed by the current permutation.
+    pub block_idx: T,
+    pub is_first_block: T,
+    pub is_final_block: T,
+
+    /// Set on the first row of a permutation, where the block is read and absorbed.
+    pub do_absorb: T,
+
+    /// Set on the last row of the final permutation, where the digest is written.
+    pub do_squeeze: T,
+
+    /// Which bytes of the block are message bytes, the rest are padding.
+    pub is_input_byte: [T; KECCAK256_RATE_BYTES],
+    pub block_mem: [MemoryReadCols<T>; KECCAK256_RATE_WORDS],
+
+    /// The padded block.
+    pub block: [Word<T>; KECCAK256_RATE_WORDS],
+
+    /// The state before absorbing the block, i.e. the output of the previous permutation.
+    pub prev_state: [Word<T>; STATE_NUM_WORDS],
+    pub absorbed: [XorOperation<T>; KECCAK256_RATE_WORDS],
+
+    pub digest_mem: [MemoryWriteCols<T>; KECCAK256_DIGEST_WORDS],
+
+    pub is_real: T,
+}
+
+pub const NUM_KECCAK256_COLS: usize = size_of::<Keccak256Cols<u8>>();
@@ -0,0 +1,63 @@
+use crate::{
+    runtime::{Register, Syscall},
+    syscall::precompiles::{keccak256::keccakf, SyscallContext},
+};
+
+use super::{
+    keccak256_absorb, keccak256_padded_blocks, Keccak256Chip, Keccak256Event,
+    KECCAK256_DIGEST_WORDS,
+};
+
+impl Syscall for Keccak256Chip {
+    fn num_extra_cycles(&self) -> u32 {
+        8
+    }
+
+    fn execute(&self, rt: &mut SyscallContext) -> u32 {
+        // Read `input_ptr`, `input_len` and `output_ptr` from registers a0, a1 and a2.
+        let input_ptr = rt.register_unsafe(Register::X10);
+        let input_len = rt.register_unsafe(Register::X11);
+        let output_ptr = rt.register_unsafe(Register::X12);
+        if input_ptr % 4 != 0 || output_ptr % 4 != 0 {
+            panic!("keccak256: pointers must be word aligned");
+        }
+
+        let start_clk = rt.clk;
+
+        // Read the words covering the input and absorb the padded message.
+        let (input_read_records, input) = rt.mr_slice(input_ptr, input_len.div_ceil(4) as usize);
+        let mut state = [0u64; 25];
+        for block in keccak256_padded_blocks(&input, input_len as usize) {
+            keccak256_absorb(&mut state, &block);
+            keccakf(&mut state);
+        }
+
+        // Squeeze the first four lanes of the state.
+        let digest = state[..KECCAK256_DIGEST_WORDS / 2]
+            .iter()
+            .flat_map(|lane| [*lane as u32, (lane >> 32) as u32])
+            .collect::<Vec<_>>();
+
+        // When we write the digest, we want the clk to be incremented.
+        rt.clk += 4;
+
+        let digest_write_records = rt.mw_slice(output_ptr, &digest);
+
+        rt.clk += 4;
+
+        // Push the Keccak-256 event.
+        let shard = rt.current_shard();
+        rt.record_mut().keccak256_events.push(Keccak256Event {
+            shard,
+            clk: start_clk,
+            input_ptr,
+            input_len,
+            output_ptr,
+            input,
+            input_read_records,
+            digest_write_records: digest_write_records.try_into().unwrap(),
+        });
+
+        output_ptr
+    }
+}
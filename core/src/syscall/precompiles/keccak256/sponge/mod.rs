@@ -0,0 +1,177 @@
+use crate::runtime::{MemoryReadRecord, MemoryWriteRecord};
+use p3_keccak_air::{KeccakAir, NUM_ROUNDS};
+use serde::{Deserialize, Serialize};
+
+use super::STATE_SIZE;
+
+mod air;
+mod columns;
+mod execute;
+mod trace;
+
+/// The rate of Keccak-256 in bytes.
+pub const KECCAK256_RATE_BYTES: usize = 136;
+
+/// The rate of Keccak-256 in 32-bit words.
+pub const KECCAK256_RATE_WORDS: usize = KECCAK256_RATE_BYTES / 4;
+
+/// The rate of Keccak-256 in 64-bit lanes.
+pub const KECCAK256_RATE_LANES: usize = KECCAK256_RATE_BYTES / 8;
+
+/// The number of 32-bit words in a Keccak-256 digest.
+pub const KECCAK256_DIGEST_WORDS: usize = 8;
+
+/// An event for the `KECCAK256` precompile.
+///
+/// The syscall reads `input_len` bytes starting at `input_ptr`, absorbs them into a Keccak sponge
+/// with the Keccak padding and writes the 32-byte digest to `output_ptr`. The input is read as the
+/// `input_len.div_ceil(4)` words that cover it, and the bytes past `input_len` are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keccak256Event {
+    pub shard: u32,
+    pub clk: u32,
+    pub input_ptr: u32,
+    pub input_len: u32,
+    pub output_ptr: u32,
+    pub input: Vec<u32>,
+    pub input_read_records: Vec<MemoryReadRecord>,
+    pub digest_write_records: [MemoryWriteRecord; KECCAK256_DIGEST_WORDS],
+}
+
+/// A chip that hashes a whole buffer with Keccak-256.
+///
+/// Every absorbed block takes one permutation (24 rows) of the plonky3 keccak air. The block is read
+/// from memory, padded and xor-ed into the state on the first row of the permutation, and the
+/// output of the permutation is carried over to the next block. The digest is written on the last
+/// row of the final permutation, so the state itself never goes through memory.
+pub struct Keccak256Chip {
+    p3_keccak: KeccakAir,
+}
+
+impl Keccak256Chip {
+    pub fn new() -> Self {
+        Self {
+            p3_keccak: KeccakAir {},
+        }
+    }
+
+    /// The number of rows of the trace taken by `event`: one permutation per absorbed block.
+    pub const fn event_rows(event: &Keccak256Event) -> usize {
+        NUM_ROUNDS * (event.input_len as usize / KECCAK256_RATE_BYTES + 1)
+    }
+}
+
+impl Default for Keccak256Chip {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the padded blocks absorbed when hashing the `input_len` bytes laid out in `input`.
+pub fn keccak256_padded_blocks(
+    input: &[u32],
+    input_len: usize,
+) -> Vec<[u32; KECCAK256_RATE_WORDS]> {
+    let mut bytes = input
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .take(input_len)
+        .collect::<Vec<_>>();
+    bytes.push(0x01);
+    bytes.resize(bytes.len().next_multiple_of(KECCAK256_RATE_BYTES), 0);
+    *bytes.last_mut().unwrap() |= 0x80;
+
+    bytes
+        .chunks_exact(KECCAK256_RATE_BYTES)
+        .map(|block| {
+            core::array::from_fn(|i| {
+                u32::from_le_bytes(block[i * 4..i * 4 + 4].try_into().unwrap())
+            })
+        })
+        .collect()
+}
+
+/// Xors a padded block into the rate of the sponge state.
+pub fn keccak256_absorb(state: &mut [u64; STATE_SIZE], block: &[u32; KECCAK256_RATE_WORDS]) {
+    for (lane, words) in state.iter_mut().zip(block.chunks_exact(2)) {
+        *lane ^= words[0] as u64 | ((words[1] as u64) << 32);
+    }
+}
+
+#[cfg(test)]
+pub mod sponge_tests {
+    use tiny_keccak::Hasher;
+
+    use crate::{
+        runtime::{Instruction, Opcode, Program, Runtime},
+        utils::{run_test, setup_logger},
+    };
+
+    /// A program that hashes `input` with the `KECCAK256` precompile.
+    pub fn keccak256_program(input: &[u8]) -> Program {
+        let input_ptr = 100;
+        let output_ptr = 4000;
+        let mut instructions = vec![];
+        for (i, chunk) in input.chunks(4).enumerate() {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            instructions.extend(vec![
+                Instruction::new(Opcode::ADD, 29, 0, u32::from_le_bytes(word), false, true),
+                Instruction::new(Opcode::ADD, 30, 0, input_ptr + i as u32 * 4, false, true),
+                Instruction::new(Opcode::SW, 29, 30, 0, false, true),
+            ]);
+        }
+        instructions.extend(vec![
+            Instruction::new(Opcode::ADD, 5, 0, 116, false, true),
+            Instruction::new(Opcode::ADD, 10, 0, input_ptr, false, true),
+            Instruction::new(Opcode::ADD, 11, 0, input.len() as u32, false, true),
+            Instruction::new(Opcode::ADD, 12, 0, output_ptr, false, true),
+            Instruction::new(Opcode::ECALL, 10, 5, 0, false, true),
+        ]);
+        Program::new(instructions, 0, 0)
+    }
+
+    fn digest(runtime: &Runtime) -> Vec<u8> {
+        (0..8)
+            .flat_map(|i| runtime.word(4000 + i * 4).to_le_bytes())
+            .collect()
+    }
+
+    fn expected_digest(input: &[u8]) -> Vec<u8> {
+        let mut keccak = tiny_keccak::Keccak::v256();
+        keccak.update(input);
+        let mut hash = [0u8; 32];
+        keccak.finalize(&mut hash);
+        hash.to_vec()
+    }
+
+    #[test]
+    fn test_keccak256_execute() {
+        // Lengths around the rate exercise the cases where the padding is a single `0x81` byte and
+        // where it takes a whole extra block.
+        for len in [0, 1, 3, 4, 135, 136, 137, 300] {
+            let input = (0..len).map(|i| (i * 7 + 3) as u8).collect::<Vec<_>>();
+            let mut runtime = Runtime::new(keccak256_program(&input));
+            runtime.run();
+            assert_eq!(digest(&runtime), expected_digest(&input), "len = {}", len);
+            assert_eq!(runtime.record.keccak256_events.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_keccak256_empty_digest() {
+        let mut runtime = Runtime::new(keccak256_program(&[]));
+        runtime.run();
+        assert_eq!(
+            hex::encode(digest(&runtime)),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+    }
+
+    #[test]
+    fn test_keccak256_prove_babybear() {
+        setup_logger();
+        let input = (0..200).map(|i| i as u8).collect::<Vec<_>>();
+        run_test(keccak256_program(&input)).unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+use std::borrow::BorrowMut;
+
+use p3_field::PrimeField32;
+use p3_keccak_air::{generate_trace_rows, NUM_KECCAK_COLS, NUM_ROUNDS};
+use p3_matrix::dense::RowMajorMatrix;
+use tracing::instrument;
+
+use crate::{
+    air::{MachineAir, Word},
+    runtime::ExecutionRecord,
+    syscall::precompiles::keccak256::{keccakf, STATE_SIZE},
+};
+
+use super::{
+    columns::{Keccak256Cols, NUM_KECCAK256_COLS},
+    keccak256_absorb, keccak256_padded_blocks, Keccak256Chip, KECCAK256_RATE_BYTES,
+    KECCAK256_RATE_WORDS,
+};
+
+impl<F: PrimeField32> MachineAir<F> for Keccak256Chip {
+    type Record = ExecutionRecord;
+
+    fn name(&self) -> String {
+        "Keccak256".to_string()
+    }
+
+    #[instrument(name = "generate keccak256 trace", level = "debug", skip_all)]
+    fn generate_trace(
+        &self,
+        input: &ExecutionRecord,
+        output: &mut ExecutionRecord,
+    ) -> RowMajorMatrix<F> {
+        let mut rows: Vec<[F; NUM_KECCAK_COLS + NUM_KECCAK256_COLS]> = Vec::new();
+
+        let mut new_field_events = Vec::new();
+        for event in input.keccak256_events.iter() {
+            let blocks = keccak256_padded_blocks(&event.input, event.input_len as usize);
+            let last_block_idx = blocks.len() - 1;
+
+            let mut state = [0u64; STATE_SIZE];
+            for (block_idx, block) in blocks.iter().enumerate() {
+                let prev_state = state;
+                keccak256_absorb(&mut state, block);
+
+                // First get the trace for the plonky3 keccak air.
+                let p3_keccak_trace = generate_trace_rows::<F>(vec![state]);
+
+                for (i, p3_keccak_row) in (0..NUM_ROUNDS).zip(p3_keccak_trace.rows()) {
+                    let mut row = [F::zero(); NUM_KECCAK_COLS + NUM_KECCAK256_COLS];
+                    row[..NUM_KECCAK_COLS].copy_from_slice(p3_keccak_row);
+
+                    let cols: &mut Keccak256Cols<F> = row[NUM_KECCAK_COLS..].borrow_mut();
+                    cols.shard = F::from_canonical_u32(event.shard);
+                    cols.clk = F::from_canonical_u32(event.clk);
+                    cols.input_ptr = F::from_canonical_u32(event.input_ptr);
+                    cols.input_len = F::from_canonical_u32(event.input_len);
+                    cols.output_ptr = F::from_canonical_u32(event.output_ptr);
+                    cols.block_idx = F::from_canonical_usize(block_idx);
+                    cols.is_first_block = F::from_bool(block_idx == 0);
+                    cols.is_final_block = F::from_bool(block_idx == last_block_idx);
+                    cols.is_real = F::one();
+
+                    // On the first row, read the block and absorb it into the state.
+                    if i == 0 {
+                        cols.do_absorb = F::one();
+
+                        let block_start = block_idx * KECCAK256_RATE_BYTES;
+                        for (j, is_input_byte) in cols.is_input_byte.iter_mut().enumerate() {
+                            *is_input_byte =
+                                F::from_bool(block_start + j < event.input_len as usize);
+                        }
+
+                        for j in 0..KECCAK256_RATE_WORDS {
+                            let word_idx = block_idx * KECCAK256_RATE_WORDS + j;
+                            if word_idx < event.input_read_records.len() {
+                                cols.block_mem[j].populate(
+                                    event.input_read_records[word_idx],
+                                    &mut new_field_events,
+                                );
+                            }
+
+                            let prev_word = if j % 2 == 0 {
+                                prev_state[j / 2] as u32
+                            } else {
+                                (prev_state[j / 2] >> 32) as u32
+                            };
+                            cols.block[j] = Word::from(block[j]);
+                            cols.absorbed[j].populate(output, prev_word, block[j]);
+                        }
+
+                        for (j, lane) in prev_state.iter().enumerate() {
+                            cols.prev_state[2 * j] = Word::from(*lane as u32);
+                            cols.prev_state[2 * j + 1] = Word::from((lane >> 32) as u32);
+                        }
+                    }
+
+                    // On the last row of the final block, write the digest.
+                    if i == NUM_ROUNDS - 1 && block_idx == last_block_idx {
+                        cols.do_squeeze = F::one();
+                        for (j, record) in event.digest_write_records.iter().enumerate() {
+                            cols.digest_mem[j].populate(*record, &mut new_field_events);
+                        }
+                    }
+
+                    rows.push(row);
+                }
+
+                keccakf(&mut state);
+            }
+        }
+
+        output.add_field_events(&new_field_events);
+
+        // Pad the trace with permutations of the zero state, which keeps the keccak air happy.
+        let nb_rows = rows.len();
+        let mut padded_nb_rows = nb_rows.next_power_of_two();
+        if padded_nb_rows < 4 {
+            padded_nb_rows = 4;
+        }
+
+        let dummy_trace = generate_trace_rows::<F>(vec![[0; STATE_SIZE]]);
+        let dummy_rows = (0..NUM_ROUNDS)
+            .zip(dummy_trace.rows())
+            .map(|(_, p3_keccak_row)| {
+                let mut row = [F::zero(); NUM_KECCAK_COLS + NUM_KECCAK256_COLS];
+                row[..NUM_KECCAK_COLS].copy_from_slice(p3_keccak_row);
+                row
+            })
+            .collect::<Vec<_>>();
+        rows.extend(dummy_rows.iter().cycle().take(padded_nb_rows - nb_rows));
+
+        // Convert the trace to a row major matrix.
+        RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_KECCAK_COLS + NUM_KECCAK256_COLS,
+        )
+    }
+
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.keccak256_events.iter().map(Self::event_rows).sum()
+    }
+
+    fn included(&self, shard: &Self::Record) -> bool {
+        !shard.keccak256_events.is_empty()
+    }
+}
@@ -161,6 +161,10 @@ impl<F: PrimeField32> MachineAir<F> for KeccakPermuteChip {
         )
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.keccak_permute_events.len() * NUM_ROUNDS
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.keccak_permute_events.is_empty()
     }
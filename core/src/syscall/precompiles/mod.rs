@@ -3,6 +3,7 @@ pub mod edwards;
 pub mod k256;
 pub mod keccak256;
 pub mod sha256;
+pub mod sha512;
 pub mod weierstrass;
 
 use num::BigUint;
@@ -259,6 +259,10 @@ impl<F: PrimeField> MachineAir<F> for ShaCompressChip {
         )
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.sha_compress_events.len() * 80
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.sha_compress_events.is_empty()
     }
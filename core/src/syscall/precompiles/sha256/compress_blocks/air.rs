@@ -0,0 +1,668 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::AbstractField;
+
+use super::columns::{ShaCompressBlocksCols, NUM_SHA_COMPRESS_BLOCKS_COLS};
+use super::{ShaCompressBlocksChip, SHA256_BLOCK_WORDS, SHA256_ROUNDS};
+use crate::air::{BaseAirBuilder, SP1AirBuilder, Word, WordAirBuilder};
+use crate::memory::MemoryCols;
+use crate::operations::{
+    Add4Operation, Add5Operation, AddOperation, AndOperation, FixedRotateRightOperation,
+    FixedShiftRightOperation, IsZeroOperation, NotOperation, XorOperation,
+};
+use crate::runtime::Register;
+use crate::syscall::precompiles::sha256::SHA_COMPRESS_K;
+use core::borrow::Borrow;
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for ShaCompressBlocksChip {
+    fn width(&self) -> usize {
+        NUM_SHA_COMPRESS_BLOCKS_COLS
+    }
+}
+
+impl<AB> Air<AB> for ShaCompressBlocksChip
+where
+    AB: SP1AirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &ShaCompressBlocksCols<AB::Var> = main.row_slice(0).borrow();
+        let next: &ShaCompressBlocksCols<AB::Var> = main.row_slice(1).borrow();
+
+        self.constrain_control_flow_flags(builder, local, next);
+
+        self.constrain_memory(builder, local);
+
+        self.constrain_message_schedule(builder, local, next);
+
+        self.constrain_compression_ops(builder, local, next);
+
+        self.constrain_finalize_ops(builder, local, next);
+    }
+}
+
+impl ShaCompressBlocksChip {
+    fn constrain_control_flow_flags<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &ShaCompressBlocksCols<AB::Var>,
+        next: &ShaCompressBlocksCols<AB::Var>,
+    ) {
+        // Verify that the phase flags are bool and that exactly one of them is set on real rows.
+        builder.assert_bool(local.is_initialize);
+        builder.assert_bool(local.is_compression);
+        builder.assert_bool(local.is_finalize);
+        builder.assert_bool(local.is_msg_round);
+        builder.assert_bool(local.is_schedule_round);
+        builder.assert_bool(local.is_real);
+        builder.assert_eq(
+            local.is_initialize + local.is_compression + local.is_finalize,
+            local.is_real,
+        );
+        builder.assert_eq(local.is_first_row, local.is_initialize * local.octet[0]);
+
+        //// Constrain octet columns
+        // Verify that exactly one octet is set in the initialize and finalize phases.
+        let mut octet_sum = AB::Expr::zero();
+        for i in 0..8 {
+            builder.assert_bool(local.octet[i]);
+            octet_sum += local.octet[i].into();
+        }
+        builder.assert_eq(octet_sum, local.is_initialize + local.is_finalize);
+
+        //// Constrain round columns
+        // Verify that exactly one round is set in the compression phase, and that the message and
+        // schedule flags match the round.
+        let mut msg_round_sum = AB::Expr::zero();
+        let mut schedule_round_sum = AB::Expr::zero();
+        for i in 0..SHA256_ROUNDS {
+            builder.assert_bool(local.round[i]);
+            if i < SHA256_BLOCK_WORDS {
+                msg_round_sum += local.round[i].into();
+            } else {
+                schedule_round_sum += local.round[i].into();
+            }
+        }
+        builder.assert_eq(msg_round_sum, local.is_msg_round);
+        builder.assert_eq(schedule_round_sum, local.is_schedule_round);
+        builder.assert_eq(
+            local.is_msg_round + local.is_schedule_round,
+            local.is_compression,
+        );
+
+        // Every event starts with the first initialize row, and real rows are contiguous.
+        builder
+            .when_first_row()
+            .when(local.is_real)
+            .assert_one(local.is_initialize);
+        builder
+            .when_first_row()
+            .when(local.is_real)
+            .assert_one(local.octet[0]);
+        builder
+            .when_transition()
+            .when_not(local.is_real)
+            .assert_zero(next.is_real);
+        builder
+            .when_transition()
+            .when(local.is_initialize + local.is_compression)
+            .assert_one(next.is_real);
+
+        // Verify that the last block flag is correct.
+        IsZeroOperation::<AB::F>::eval(
+            builder,
+            local.num_blocks - local.block_idx - AB::Expr::one(),
+            local.is_last_block,
+            local.is_real.into(),
+        );
+        let is_last_block = local.is_last_block.result;
+
+        // Initialize phase: octet `i` is followed by octet `i + 1`, and the last octet is followed
+        // by the first round of the first block.
+        builder
+            .when(local.is_initialize)
+            .assert_zero(local.block_idx);
+        for i in 0..7 {
+            builder
+                .when_transition()
+                .when(local.is_initialize)
+                .when(local.octet[i])
+                .assert_one(next.octet[i + 1]);
+            builder
+                .when_transition()
+                .when(local.is_initialize)
+                .when(local.octet[i])
+                .assert_one(next.is_initialize);
+        }
+        builder
+            .when_transition()
+            .when(local.is_initialize)
+            .when(local.octet[7])
+            .assert_one(next.round[0]);
+
+        // Compression phase: round `i` is followed by round `i + 1`. The last round is followed by
+        // the first round of the next block, or by the finalize phase if this is the last block.
+        for i in 0..SHA256_ROUNDS - 1 {
+            builder
+                .when_transition()
+                .when(local.round[i])
+                .assert_one(next.round[i + 1]);
+        }
+        builder
+            .when_transition()
+            .when(local.round[SHA256_ROUNDS - 1])
+            .when_not(is_last_block)
+            .assert_one(next.round[0]);
+        builder
+            .when_transition()
+            .when(local.round[SHA256_ROUNDS - 1])
+            .when(is_last_block)
+            .assert_one(next.is_finalize);
+        builder
+            .when_transition()
+            .when(local.round[SHA256_ROUNDS - 1])
+            .when(is_last_block)
+            .assert_one(next.octet[0]);
+
+        // Finalize phase: octet `i` is followed by octet `i + 1`, and the last octet is followed by
+        // either padding or the next event.
+        for i in 0..7 {
+            builder
+                .when_transition()
+                .when(local.is_finalize)
+                .when(local.octet[i])
+                .assert_one(next.octet[i + 1]);
+            builder
+                .when_transition()
+                .when(local.is_finalize)
+                .when(local.octet[i])
+                .assert_one(next.is_finalize);
+        }
+        builder
+            .when_transition()
+            .when(local.is_finalize)
+            .when(local.octet[7])
+            .assert_eq(next.is_initialize, next.is_real);
+        builder
+            .when_transition()
+            .when(local.is_finalize)
+            .when(local.octet[7])
+            .assert_eq(next.octet[0], next.is_real);
+
+        // The inputs stay the same for every row of an event.
+        let is_same_event = local.is_real - local.is_finalize * local.octet[7];
+        let mut builder_same_event = builder.when_transition();
+        let mut builder_same_event = builder_same_event.when(is_same_event);
+        builder_same_event.assert_eq(local.shard, next.shard);
+        builder_same_event.assert_eq(local.clk, next.clk);
+        builder_same_event.assert_eq(local.state_ptr, next.state_ptr);
+        builder_same_event.assert_eq(local.num_blocks, next.num_blocks);
+
+        // The block pointer and index only advance after the last round of a block that is not the
+        // last one.
+        let is_round_transition = local.is_compression - local.round[SHA256_ROUNDS - 1];
+        let mut builder_same_block = builder.when_transition();
+        let mut builder_same_block =
+            builder_same_block.when(local.is_initialize + is_round_transition);
+        builder_same_block.assert_eq(local.block_idx, next.block_idx);
+        builder_same_block.assert_eq(local.block_ptr, next.block_ptr);
+
+        let mut builder_next_block = builder.when_transition();
+        let mut builder_next_block = builder_next_block
+            .when(local.round[SHA256_ROUNDS - 1])
+            .when_not(is_last_block);
+        builder_next_block.assert_eq(local.block_idx + AB::Expr::one(), next.block_idx);
+        builder_next_block.assert_eq(
+            local.block_ptr + AB::Expr::from_canonical_u32(64),
+            next.block_ptr,
+        );
+    }
+
+    fn constrain_memory<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &ShaCompressBlocksCols<AB::Var>,
+    ) {
+        // The state is read at `clk` and written at `clk + 4`, the message is read at `clk`.
+        builder.constraint_memory_access(
+            local.shard,
+            local.clk + local.is_finalize * AB::Expr::from_canonical_u32(4),
+            local.mem_addr,
+            &local.mem,
+            local.is_initialize + local.is_msg_round + local.is_finalize,
+        );
+
+        // Reads don't modify memory.
+        builder
+            .when(local.is_initialize + local.is_msg_round)
+            .assert_word_eq(*local.mem.prev_value(), *local.mem.value());
+
+        // The first row of the event reads the arguments of the syscall from registers a0, a1 and
+        // a2, and they must match the pointers and the number of blocks used by the event. The
+        // block pointer of the first row is the one of the first block.
+        let args = [
+            (Register::X10, &local.state_ptr_access, local.state_ptr),
+            (Register::X11, &local.msg_ptr_access, local.block_ptr),
+            (Register::X12, &local.num_blocks_access, local.num_blocks),
+        ];
+        for (register, access, value) in args {
+            builder.constraint_memory_access(
+                local.shard,
+                local.clk,
+                AB::F::from_canonical_u32(register as u32),
+                access,
+                local.is_first_row,
+            );
+            builder
+                .when(local.is_first_row)
+                .assert_eq(value, access.value().reduce::<AB>());
+        }
+
+        // Calculate the current step of the initialize and finalize phases.
+        let mut octet_step = AB::Expr::zero();
+        for i in 0..8 {
+            octet_step += local.octet[i] * AB::Expr::from_canonical_usize(i);
+        }
+
+        // Calculate the current message word index of the compression phase.
+        let mut msg_step = AB::Expr::zero();
+        for i in 0..SHA256_BLOCK_WORDS {
+            msg_step += local.round[i] * AB::Expr::from_canonical_usize(i);
+        }
+
+        // Verify correct mem address for the initialize and finalize phases.
+        builder
+            .when(local.is_initialize + local.is_finalize)
+            .assert_eq(
+                local.mem_addr,
+                local.state_ptr + octet_step * AB::Expr::from_canonical_u32(4),
+            );
+
+        // Verify correct mem address for the message rounds.
+        builder.when(local.is_msg_round).assert_eq(
+            local.mem_addr,
+            local.block_ptr + msg_step * AB::Expr::from_canonical_u32(4),
+        );
+
+        // The state words that are read and written are the chaining value of the row.
+        for i in 0..8 {
+            builder
+                .when(local.is_initialize + local.is_finalize)
+                .when(local.octet[i])
+                .assert_word_eq(local.state[i], *local.mem.value());
+        }
+
+        // The message word is the big-endian interpretation of the bytes in memory.
+        let mem_value = local.mem.value();
+        builder.when(local.is_msg_round).assert_word_eq(
+            local.w,
+            Word([mem_value[3], mem_value[2], mem_value[1], mem_value[0]]),
+        );
+    }
+
+    fn constrain_message_schedule<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &ShaCompressBlocksCols<AB::Var>,
+        next: &ShaCompressBlocksCols<AB::Var>,
+    ) {
+        // Shift the window by one word between two rounds of the same block.
+        let is_round_transition = local.is_compression - local.round[SHA256_ROUNDS - 1];
+        for i in 0..SHA256_BLOCK_WORDS - 1 {
+            builder
+                .when_transition()
+                .when(is_round_transition.clone())
+                .assert_word_eq(next.w_window[i], local.w_window[i + 1]);
+        }
+        builder
+            .when_transition()
+            .when(is_round_transition)
+            .assert_word_eq(next.w_window[SHA256_BLOCK_WORDS - 1], local.w);
+
+        // Compute `s0` from `w[i - 15]`.
+        let w_i_minus_15 = local.w_window[1];
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            w_i_minus_15,
+            7,
+            local.w_i_minus_15_rr_7,
+            local.is_schedule_round,
+        );
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            w_i_minus_15,
+            18,
+            local.w_i_minus_15_rr_18,
+            local.is_schedule_round,
+        );
+        FixedShiftRightOperation::<AB::F>::eval(
+            builder,
+            w_i_minus_15,
+            3,
+            local.w_i_minus_15_rs_3,
+            local.is_schedule_round,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.w_i_minus_15_rr_7.value,
+            local.w_i_minus_15_rr_18.value,
+            local.sched_s0_intermediate,
+            local.is_schedule_round,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.sched_s0_intermediate.value,
+            local.w_i_minus_15_rs_3.value,
+            local.sched_s0,
+            local.is_schedule_round,
+        );
+
+        // Compute `s1` from `w[i - 2]`.
+        let w_i_minus_2 = local.w_window[14];
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            w_i_minus_2,
+            17,
+            local.w_i_minus_2_rr_17,
+            local.is_schedule_round,
+        );
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            w_i_minus_2,
+            19,
+            local.w_i_minus_2_rr_19,
+            local.is_schedule_round,
+        );
+        FixedShiftRightOperation::<AB::F>::eval(
+            builder,
+            w_i_minus_2,
+            10,
+            local.w_i_minus_2_rs_10,
+            local.is_schedule_round,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.w_i_minus_2_rr_17.value,
+            local.w_i_minus_2_rr_19.value,
+            local.sched_s1_intermediate,
+            local.is_schedule_round,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.sched_s1_intermediate.value,
+            local.w_i_minus_2_rs_10.value,
+            local.sched_s1,
+            local.is_schedule_round,
+        );
+
+        // Compute `w[i] = w[i - 16] + s0 + w[i - 7] + s1`.
+        Add4Operation::<AB::F>::eval(
+            builder,
+            local.w_window[0],
+            local.sched_s0.value,
+            local.w_window[9],
+            local.sched_s1.value,
+            local.is_schedule_round,
+            local.sched_w,
+        );
+        builder
+            .when(local.is_schedule_round)
+            .assert_word_eq(local.w, local.sched_w.value);
+    }
+
+    fn constrain_compression_ops<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &ShaCompressBlocksCols<AB::Var>,
+        next: &ShaCompressBlocksCols<AB::Var>,
+    ) {
+        // Verify that `k` is the round constant of the current round.
+        for i in 0..4 {
+            let mut k_byte = AB::Expr::zero();
+            for j in 0..SHA256_ROUNDS {
+                let byte = SHA_COMPRESS_K[j].to_le_bytes()[i];
+                k_byte += local.round[j] * AB::Expr::from_canonical_u8(byte);
+            }
+            builder.assert_eq(local.k[i], k_byte);
+        }
+
+        // The working variables start from the chaining value at the first round of every block.
+        let vars = [
+            local.a, local.b, local.c, local.d, local.e, local.f, local.g, local.h,
+        ];
+        for i in 0..8 {
+            builder
+                .when(local.round[0])
+                .assert_word_eq(vars[i], local.state[i]);
+        }
+
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            local.e,
+            6,
+            local.e_rr_6,
+            local.is_compression,
+        );
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            local.e,
+            11,
+            local.e_rr_11,
+            local.is_compression,
+        );
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            local.e,
+            25,
+            local.e_rr_25,
+            local.is_compression,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.e_rr_6.value,
+            local.e_rr_11.value,
+            local.s1_intermediate,
+            local.is_compression,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.s1_intermediate.value,
+            local.e_rr_25.value,
+            local.s1,
+            local.is_compression,
+        );
+
+        AndOperation::<AB::F>::eval(
+            builder,
+            local.e,
+            local.f,
+            local.e_and_f,
+            local.is_compression,
+        );
+        NotOperation::<AB::F>::eval(builder, local.e, local.e_not, local.is_compression);
+        AndOperation::<AB::F>::eval(
+            builder,
+            local.e_not.value,
+            local.g,
+            local.e_not_and_g,
+            local.is_compression,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.e_and_f.value,
+            local.e_not_and_g.value,
+            local.ch,
+            local.is_compression,
+        );
+
+        Add5Operation::<AB::F>::eval(
+            builder,
+            &[local.h, local.s1.value, local.ch.value, local.k, local.w],
+            local.is_compression,
+            local.temp1,
+        );
+
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            local.a,
+            2,
+            local.a_rr_2,
+            local.is_compression,
+        );
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            local.a,
+            13,
+            local.a_rr_13,
+            local.is_compression,
+        );
+        FixedRotateRightOperation::<AB::F>::eval(
+            builder,
+            local.a,
+            22,
+            local.a_rr_22,
+            local.is_compression,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.a_rr_2.value,
+            local.a_rr_13.value,
+            local.s0_intermediate,
+            local.is_compression,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.s0_intermediate.value,
+            local.a_rr_22.value,
+            local.s0,
+            local.is_compression,
+        );
+
+        AndOperation::<AB::F>::eval(
+            builder,
+            local.a,
+            local.b,
+            local.a_and_b,
+            local.is_compression,
+        );
+        AndOperation::<AB::F>::eval(
+            builder,
+            local.a,
+            local.c,
+            local.a_and_c,
+            local.is_compression,
+        );
+        AndOperation::<AB::F>::eval(
+            builder,
+            local.b,
+            local.c,
+            local.b_and_c,
+            local.is_compression,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.a_and_b.value,
+            local.a_and_c.value,
+            local.maj_intermediate,
+            local.is_compression,
+        );
+        XorOperation::<AB::F>::eval(
+            builder,
+            local.maj_intermediate.value,
+            local.b_and_c.value,
+            local.maj,
+            local.is_compression,
+        );
+
+        AddOperation::<AB::F>::eval(
+            builder,
+            local.s0.value,
+            local.maj.value,
+            local.temp2,
+            local.is_compression,
+        );
+
+        AddOperation::<AB::F>::eval(
+            builder,
+            local.d,
+            local.temp1.value,
+            local.d_add_temp1,
+            local.is_compression,
+        );
+
+        AddOperation::<AB::F>::eval(
+            builder,
+            local.temp1.value,
+            local.temp2.value,
+            local.temp1_add_temp2,
+            local.is_compression,
+        );
+
+        // Between two rounds of the same block, the working variables are rotated.
+        let is_round_transition = local.is_compression - local.round[SHA256_ROUNDS - 1];
+        let mut builder_round = builder.when_transition();
+        let mut builder_round = builder_round.when(is_round_transition);
+        builder_round.assert_word_eq(next.a, local.temp1_add_temp2.value);
+        builder_round.assert_word_eq(next.b, local.a);
+        builder_round.assert_word_eq(next.c, local.b);
+        builder_round.assert_word_eq(next.d, local.c);
+        builder_round.assert_word_eq(next.e, local.d_add_temp1.value);
+        builder_round.assert_word_eq(next.f, local.e);
+        builder_round.assert_word_eq(next.g, local.f);
+        builder_round.assert_word_eq(next.h, local.g);
+
+        // The chaining value stays the same within a block.
+        for i in 0..8 {
+            builder_round.assert_word_eq(next.state[i], local.state[i]);
+        }
+    }
+
+    fn constrain_finalize_ops<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &ShaCompressBlocksCols<AB::Var>,
+        next: &ShaCompressBlocksCols<AB::Var>,
+    ) {
+        let is_last_round = local.round[SHA256_ROUNDS - 1];
+
+        // In the last round of every block, add the updated working variables to the chaining
+        // value.
+        let add_operands = [
+            local.temp1_add_temp2.value,
+            local.a,
+            local.b,
+            local.c,
+            local.d_add_temp1.value,
+            local.e,
+            local.f,
+            local.g,
+        ];
+        for i in 0..8 {
+            AddOperation::<AB::F>::eval(
+                builder,
+                local.state[i],
+                add_operands[i],
+                local.finalize_add[i],
+                is_last_round,
+            );
+        }
+
+        // The result becomes the chaining value of the next block (or of the finalize phase).
+        for i in 0..8 {
+            builder
+                .when_transition()
+                .when(is_last_round)
+                .assert_word_eq(next.state[i], local.finalize_add[i].value);
+        }
+
+        // The chaining value stays the same in the initialize and finalize phases.
+        let is_phase_transition =
+            local.is_initialize + local.is_finalize - local.is_finalize * local.octet[7];
+        for i in 0..8 {
+            builder
+                .when_transition()
+                .when(is_phase_transition.clone())
+                .assert_word_eq(next.state[i], local.state[i]);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::mem::size_of;
+
+use sp1_derive::AlignedBorrow;
+
+use crate::air::Word;
+use crate::memory::MemoryReadCols;
+use crate::memory::MemoryReadWriteCols;
+use crate::operations::Add4Operation;
+use crate::operations::Add5Operation;
+use crate::operations::AddOperation;
+use crate::operations::AndOperation;
+use crate::operations::FixedRotateRightOperation;
+use crate::operations::FixedShiftRightOperation;
+use crate::operations::IsZeroOperation;
+use crate::operations::NotOperation;
+use crate::operations::XorOperation;
+
+use super::{SHA256_BLOCK_WORDS, SHA256_ROUNDS};
+
+pub const NUM_SHA_COMPRESS_BLOCKS_COLS: usize = size_of::<ShaCompressBlocksCols<u8>>();
+
+/// The columns of the fused SHA-256 chip.
+///
+/// Every event is laid out as 8 rows reading the state, 64 rows per message block performing one
+/// round each, and 8 rows writing the state back.
+#[derive(AlignedBorrow, Default, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ShaCompressBlocksCols<T> {
+    /// Inputs.
+    pub shard: T,
+    pub clk: T,
+    pub state_ptr: T,
+
+    /// The address of the block that is currently being compressed.
+    pub block_ptr: T,
+    pub num_blocks: T,
+    pub block_idx: T,
+
+    /// Whether `block_idx` is the last block, i.e. `num_blocks - block_idx - 1 == 0`.
+    pub is_last_block: IsZeroOperation<T>,
+
+    /// Selects the state word in the initialize and finalize phases.
+    pub octet: [T; 8],
+
+    /// Selects the round in the compression phase.
+    pub round: [T; SHA256_ROUNDS],
+
+    /// Phase flags.
+    pub is_initialize: T,
+    pub is_compression: T,
+    pub is_finalize: T,
+
+    /// Whether the round reads its message word from memory (rounds 0 to 15).
+    pub is_msg_round: T,
+
+    /// Whether the round computes its message word from the schedule (rounds 16 to 63).
+    pub is_schedule_round: T,
+
+    /// Whether this is the first row of the event, which reads the arguments of the syscall.
+    pub is_first_row: T,
+
+    /// The reads of `state_ptr`, `msg_ptr` and `num_blocks` from registers a0, a1 and a2.
+    pub state_ptr_access: MemoryReadCols<T>,
+    pub msg_ptr_access: MemoryReadCols<T>,
+    pub num_blocks_access: MemoryReadCols<T>,
+
+    pub mem: MemoryReadWriteCols<T>,
+    pub mem_addr: T,
+
+    /// The chaining value at the start of the current block.
+    pub state: [Word<T>; 8],
+
+    pub a: Word<T>,
+    pub b: Word<T>,
+    pub c: Word<T>,
+    pub d: Word<T>,
+    pub e: Word<T>,
+    pub f: Word<T>,
+    pub g: Word<T>,
+    pub h: Word<T>,
+
+    /// The message schedule words `w[i - 16], ..., w[i - 1]` of the current round.
+    pub w_window: [Word<T>; SHA256_BLOCK_WORDS],
+
+    /// The message schedule word `w[i]` of the current round.
+    pub w: Word<T>,
+
+    /// The round constant `k[i]` of the current round.
+    pub k: Word<T>,
+
+    /// Computing `s0` of the message schedule.
+    pub w_i_minus_15_rr_7: FixedRotateRightOperation<T>,
+    pub w_i_minus_15_rr_18: FixedRotateRightOperation<T>,
+    pub w_i_minus_15_rs_3: FixedShiftRightOperation<T>,
+    pub sched_s0_intermediate: XorOperation<T>,
+    pub sched_s0: XorOperation<T>,
+
+    /// Computing `s1` of the message schedule.
+    pub w_i_minus_2_rr_17: FixedRotateRightOperation<T>,
+    pub w_i_minus_2_rr_19: FixedRotateRightOperation<T>,
+    pub w_i_minus_2_rs_10: FixedShiftRightOperation<T>,
+    pub sched_s1_intermediate: XorOperation<T>,
+    pub sched_s1: XorOperation<T>,
+
+    /// Computing `w[i]` of the message schedule.
+    pub sched_w: Add4Operation<T>,
+
+    pub e_rr_6: FixedRotateRightOperation<T>,
+    pub e_rr_11: FixedRotateRightOperation<T>,
+    pub e_rr_25: FixedRotateRightOperation<T>,
+    pub s1_intermediate: XorOperation<T>,
+    pub s1: XorOperation<T>,
+
+    pub e_and_f: AndOperation<T>,
+    pub e_not: NotOperation<T>,
+    pub e_not_and_g: AndOperation<T>,
+    pub ch: XorOperation<T>,
+
+    pub temp1: Add5Operation<T>,
+
+    pub a_rr_2: FixedRotateRightOperation<T>,
+    pub a_rr_13: FixedRotateRightOperation<T>,
+    pub a_rr_22: FixedRotateRightOperation<T>,
+    pub s0_intermediate: XorOperation<T>,
+    pub s0: XorOperation<T>,
+
+    pub a_and_b: AndOperation<T>,
+    pub a_and_c: AndOperation<T>,
+    pub b_and_c: AndOperation<T>,
+    pub maj_intermediate: XorOperation<T>,
+    pub maj: XorOperation<T>,
+
+    pub temp2: AddOperation<T>,
+
+    pub d_add_temp1: AddOperation<T>,
+    pub temp1_add_temp2: AddOperation<T>,
+
+    /// The additions of the working variables to the chaining value in the last round of a block.
+    pub finalize_add: [AddOperation<T>; 8],
+
+    pub is_real: T,
+}
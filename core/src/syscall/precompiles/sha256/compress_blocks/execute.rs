@@ -0,0 +1,103 @@
+use crate::{
+    runtime::{Register, Syscall},
+    syscall::precompiles::{
+        sha256::{ShaCompressBlocksEvent, SHA_COMPRESS_K},
+        SyscallContext,
+    },
+};
+
+use super::{sha256_msg_word, sha256_schedule, ShaCompressBlocksChip, SHA256_BLOCK_WORDS};
+
+impl Syscall for ShaCompressBlocksChip {
+    fn num_extra_cycles(&self) -> u32 {
+        8
+    }
+
+    fn execute(&self, rt: &mut SyscallContext) -> u32 {
+        // An empty message leaves the state untouched, and no event is recorded.
+        if rt.register_unsafe(Register::X12) == 0 {
+            rt.clk += 8;
+            return rt.register_unsafe(Register::X10);
+        }
+
+        let start_clk = rt.clk;
+
+        // Read `state_ptr`, `msg_ptr` and `num_blocks` from registers a0, a1 and a2. The chip reads
+        // them through memory, which binds its pointers to the arguments of the syscall.
+        let (state_ptr_record, state_ptr) = rt.mr(Register::X10 as u32);
+        let (msg_ptr_record, msg_ptr) = rt.mr(Register::X11 as u32);
+        let (num_blocks_record, num_blocks) = rt.mr(Register::X12 as u32);
+        if state_ptr % 4 != 0 || msg_ptr % 4 != 0 {
+            panic!("sha256 compress blocks: pointers must be word aligned");
+        }
+
+        // Read the state and all of the message blocks.
+        let (h_read_records, h) = rt.mr_slice(state_ptr, 8);
+        let (msg_read_records, msg) =
+            rt.mr_slice(msg_ptr, num_blocks as usize * SHA256_BLOCK_WORDS);
+
+        // Compress every block.
+        let mut state: [u32; 8] = h.clone().try_into().unwrap();
+        for block in msg.chunks_exact(SHA256_BLOCK_WORDS) {
+            let block: [u32; SHA256_BLOCK_WORDS] =
+                core::array::from_fn(|i| sha256_msg_word(block[i]));
+            let w = sha256_schedule(&block);
+
+            let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+            for i in 0..64 {
+                let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+                let ch = (e & f) ^ (!e & g);
+                let temp1 = h
+                    .wrapping_add(s1)
+                    .wrapping_add(ch)
+                    .wrapping_add(SHA_COMPRESS_K[i])
+                    .wrapping_add(w[i]);
+                let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+                let maj = (a & b) ^ (a & c) ^ (b & c);
+                let temp2 = s0.wrapping_add(maj);
+
+                h = g;
+                g = f;
+                f = e;
+                e = d.wrapping_add(temp1);
+                d = c;
+                c = b;
+                b = a;
+                a = temp1.wrapping_add(temp2);
+            }
+
+            for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+                *s = s.wrapping_add(v);
+            }
+        }
+
+        // When we write to the state, we want the clk to be incremented.
+        rt.clk += 4;
+
+        let h_write_records = rt.mw_slice(state_ptr, &state);
+
+        rt.clk += 4;
+
+        // Push the SHA compress blocks event.
+        let shard = rt.current_shard();
+        rt.record_mut()
+            .sha_compress_blocks_events
+            .push(ShaCompressBlocksEvent {
+                shard,
+                clk: start_clk,
+                state_ptr,
+                msg_ptr,
+                num_blocks,
+                h: h.try_into().unwrap(),
+                msg,
+                state_ptr_record,
+                msg_ptr_record,
+                num_blocks_record,
+                h_read_records: h_read_records.try_into().unwrap(),
+                msg_read_records,
+                h_write_records: h_write_records.try_into().unwrap(),
+            });
+
+        state_ptr
+    }
+}
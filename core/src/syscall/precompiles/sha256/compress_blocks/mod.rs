@@ -0,0 +1,188 @@
+use crate::runtime::{MemoryReadRecord, MemoryWriteRecord};
+use serde::{Deserialize, Serialize};
+
+mod air;
+mod columns;
+mod execute;
+mod trace;
+
+pub use columns::*;
+
+/// The number of 32-bit words in a SHA-256 message block.
+pub const SHA256_BLOCK_WORDS: usize = 16;
+
+/// The number of rounds of the SHA-256 compression function.
+pub const SHA256_ROUNDS: usize = 64;
+
+/// An event for the `SHA_COMPRESS_BLOCKS` precompile.
+///
+/// The syscall reads `state_ptr`, `msg_ptr` and `num_blocks` from registers a0, a1 and a2, the
+/// eight-word state at `state_ptr` and `num_blocks` message blocks starting at `msg_ptr`, applies the compression function to every block and writes the new state back to
+/// `state_ptr`. The message words are read exactly as they are laid out in memory (i.e. the bytes
+/// of the message), and the big-endian conversion is done inside the chip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShaCompressBlocksEvent {
+    pub shard: u32,
+    pub clk: u32,
+    pub state_ptr: u32,
+    pub msg_ptr: u32,
+    pub num_blocks: u32,
+    pub h: [u32; 8],
+    pub msg: Vec<u32>,
+    pub state_ptr_record: MemoryReadRecord,
+    pub msg_ptr_record: MemoryReadRecord,
+    pub num_blocks_record: MemoryReadRecord,
+    pub h_read_records: [MemoryReadRecord; 8],
+    pub msg_read_records: Vec<MemoryReadRecord>,
+    pub h_write_records: [MemoryWriteRecord; 8],
+}
+
+/// A chip that fuses the SHA-256 message schedule and compression function over several blocks.
+///
+/// Unlike [`super::ShaExtendChip`] and [`super::ShaCompressChip`], the message schedule is kept in
+/// a sliding window of columns instead of going through memory, and the state is only read once at
+/// the start and written once at the end of the syscall.
+#[derive(Default)]
+pub struct ShaCompressBlocksChip;
+
+impl ShaCompressBlocksChip {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// The number of rows of the trace taken by `event`.
+    pub const fn event_rows(event: &ShaCompressBlocksEvent) -> usize {
+        16 + SHA256_ROUNDS * event.num_blocks as usize
+    }
+}
+
+/// Converts a word read from memory into the big-endian message word used by SHA-256.
+pub const fn sha256_msg_word(memory_word: u32) -> u32 {
+    memory_word.swap_bytes()
+}
+
+/// Computes the full message schedule of a single block.
+pub fn sha256_schedule(block: &[u32; SHA256_BLOCK_WORDS]) -> [u32; SHA256_ROUNDS] {
+    let mut w = [0u32; SHA256_ROUNDS];
+    w[..SHA256_BLOCK_WORDS].copy_from_slice(block);
+    for i in SHA256_BLOCK_WORDS..SHA256_ROUNDS {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+    w
+}
+
+#[cfg(test)]
+pub mod compress_blocks_tests {
+
+    use crate::{
+        runtime::{Instruction, Opcode, Program, Runtime},
+        utils::{run_test, setup_logger},
+    };
+
+    use sha2::digest::generic_array::GenericArray;
+
+    use crate::syscall::precompiles::sha256::SHA256_IV;
+
+    /// A program that hashes the padded message "abc" followed by an extra block of `0x01` bytes.
+    pub fn sha_compress_blocks_program(num_blocks: u32) -> Program {
+        let state_ptr = 100;
+        let msg_ptr = 200;
+        let mut instructions = vec![];
+        for (i, h) in SHA256_IV.iter().enumerate() {
+            instructions.extend(vec![
+                Instruction::new(Opcode::ADD, 29, 0, *h, false, true),
+                Instruction::new(Opcode::ADD, 30, 0, state_ptr + i as u32 * 4, false, true),
+                Instruction::new(Opcode::SW, 29, 30, 0, false, true),
+            ]);
+        }
+        for (i, word) in message_words(num_blocks).iter().enumerate() {
+            instructions.extend(vec![
+                Instruction::new(Opcode::ADD, 29, 0, *word, false, true),
+                Instruction::new(Opcode::ADD, 30, 0, msg_ptr + i as u32 * 4, false, true),
+                Instruction::new(Opcode::SW, 29, 30, 0, false, true),
+            ]);
+        }
+        instructions.extend(vec![
+            Instruction::new(Opcode::ADD, 5, 0, 113, false, true),
+            Instruction::new(Opcode::ADD, 10, 0, state_ptr, false, true),
+            Instruction::new(Opcode::ADD, 11, 0, msg_ptr, false, true),
+            Instruction::new(Opcode::ADD, 12, 0, num_blocks, false, true),
+            Instruction::new(Opcode::ECALL, 10, 5, 0, false, true),
+        ]);
+        Program::new(instructions, 0, 0)
+    }
+
+    /// The padded message "abc" followed by `num_blocks - 1` blocks of `0x01` bytes.
+    fn message_bytes(num_blocks: u32) -> Vec<u8> {
+        let mut bytes = vec![0u8; 64];
+        bytes[..3].copy_from_slice(b"abc");
+        bytes[3] = 0x80;
+        bytes[63] = 24;
+        for _ in 1..num_blocks {
+            bytes.extend_from_slice(&[1u8; 64]);
+        }
+        bytes
+    }
+
+    /// The message as it is laid out in memory (little-endian words of the message bytes).
+    fn message_words(num_blocks: u32) -> Vec<u32> {
+        message_bytes(num_blocks)
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    /// The state after compressing the message, computed by the `sha2` crate.
+    fn expected_state(num_blocks: u32) -> [u32; 8] {
+        let blocks = message_bytes(num_blocks)
+            .chunks_exact(64)
+            .map(GenericArray::clone_from_slice)
+            .collect::<Vec<_>>();
+        let mut state = SHA256_IV;
+        sha2::compress256(&mut state, &blocks);
+        state
+    }
+
+    #[test]
+    fn test_sha_compress_blocks_execute() {
+        let program = sha_compress_blocks_program(1);
+        let mut runtime = Runtime::new(program);
+        runtime.run();
+        let state = (0..8)
+            .map(|i| runtime.word(100 + i * 4))
+            .collect::<Vec<_>>();
+        // SHA-256("abc").
+        assert_eq!(
+            state,
+            vec![
+                0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+                0xf20015ad
+            ]
+        );
+        assert_eq!(state, expected_state(1).to_vec());
+    }
+
+    #[test]
+    fn test_sha_compress_blocks_multiple_execute() {
+        let program = sha_compress_blocks_program(3);
+        let mut runtime = Runtime::new(program);
+        runtime.run();
+        let state = (0..8)
+            .map(|i| runtime.word(100 + i * 4))
+            .collect::<Vec<_>>();
+        assert_eq!(state, expected_state(3).to_vec());
+        assert_eq!(runtime.record.sha_compress_blocks_events.len(), 1);
+    }
+
+    #[test]
+    fn prove_babybear() {
+        setup_logger();
+        let program = sha_compress_blocks_program(2);
+        run_test(program).unwrap();
+    }
+}
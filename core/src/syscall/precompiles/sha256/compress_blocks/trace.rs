@@ -0,0 +1,258 @@
+use std::borrow::BorrowMut;
+
+use p3_field::PrimeField;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::{
+    air::{MachineAir, Word},
+    runtime::ExecutionRecord,
+};
+
+use super::{
+    columns::{ShaCompressBlocksCols, NUM_SHA_COMPRESS_BLOCKS_COLS},
+    sha256_msg_word, ShaCompressBlocksChip, SHA256_BLOCK_WORDS, SHA256_ROUNDS,
+};
+use crate::syscall::precompiles::sha256::SHA_COMPRESS_K;
+
+impl<F: PrimeField> MachineAir<F> for ShaCompressBlocksChip {
+    type Record = ExecutionRecord;
+
+    fn name(&self) -> String {
+        "ShaCompressBlocks".to_string()
+    }
+
+    fn generate_trace(
+        &self,
+        input: &ExecutionRecord,
+        output: &mut ExecutionRecord,
+    ) -> RowMajorMatrix<F> {
+        let mut rows = Vec::new();
+
+        let mut new_field_events = Vec::new();
+        for event in input.sha_compress_blocks_events.iter() {
+            let num_blocks = event.num_blocks as usize;
+            let last_block_idx = event.num_blocks - 1;
+
+            // Fill in the columns that are shared by every row of the event.
+            let populate_common =
+                |cols: &mut ShaCompressBlocksCols<F>, block_idx: u32, state: &[u32; 8]| {
+                    cols.shard = F::from_canonical_u32(event.shard);
+                    cols.clk = F::from_canonical_u32(event.clk);
+                    cols.state_ptr = F::from_canonical_u32(event.state_ptr);
+                    cols.block_ptr = F::from_canonical_u32(event.msg_ptr + block_idx * 64);
+                    cols.num_blocks = F::from_canonical_u32(event.num_blocks);
+                    cols.block_idx = F::from_canonical_u32(block_idx);
+                    cols.is_last_block.populate(last_block_idx - block_idx);
+                    cols.state = state.map(Word::from);
+                    cols.is_real = F::one();
+                };
+
+            // Read the state.
+            for j in 0..8usize {
+                let mut row = [F::zero(); NUM_SHA_COMPRESS_BLOCKS_COLS];
+                let cols: &mut ShaCompressBlocksCols<F> = row.as_mut_slice().borrow_mut();
+                populate_common(cols, 0, &event.h);
+
+                cols.is_initialize = F::one();
+                cols.octet[j] = F::one();
+                if j == 0 {
+                    cols.is_first_row = F::one();
+                    cols.state_ptr_access
+                        .populate(event.state_ptr_record, &mut new_field_events);
+                    cols.msg_ptr_access
+                        .populate(event.msg_ptr_record, &mut new_field_events);
+                    cols.num_blocks_access
+                        .populate(event.num_blocks_record, &mut new_field_events);
+                }
+                cols.mem
+                    .populate_read(event.h_read_records[j], &mut new_field_events);
+                cols.mem_addr = F::from_canonical_u32(event.state_ptr + (j * 4) as u32);
+
+                rows.push(row);
+            }
+
+            // Compress every block, one round per row.
+            let mut state = event.h;
+            let mut w_window = [0u32; SHA256_BLOCK_WORDS];
+            for block_idx in 0..num_blocks {
+                let block = &event.msg[block_idx * SHA256_BLOCK_WORDS..][..SHA256_BLOCK_WORDS];
+                let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+                let mut next_state = state;
+
+                for j in 0..SHA256_ROUNDS {
+                    let mut row = [F::zero(); NUM_SHA_COMPRESS_BLOCKS_COLS];
+                    let cols: &mut ShaCompressBlocksCols<F> = row.as_mut_slice().borrow_mut();
+                    populate_common(cols, block_idx as u32, &state);
+
+                    cols.is_compression = F::one();
+                    cols.round[j] = F::one();
+
+                    cols.a = Word::from(a);
+                    cols.b = Word::from(b);
+                    cols.c = Word::from(c);
+                    cols.d = Word::from(d);
+                    cols.e = Word::from(e);
+                    cols.f = Word::from(f);
+                    cols.g = Word::from(g);
+                    cols.h = Word::from(h);
+                    cols.w_window = w_window.map(Word::from);
+
+                    // Get the message schedule word, either from memory or from the window.
+                    let w_i = if j < SHA256_BLOCK_WORDS {
+                        cols.is_msg_round = F::one();
+                        let record = event.msg_read_records[block_idx * SHA256_BLOCK_WORDS + j];
+                        cols.mem.populate_read(record, &mut new_field_events);
+                        cols.mem_addr =
+                            F::from_canonical_u32(event.msg_ptr + (block_idx * 64 + j * 4) as u32);
+                        sha256_msg_word(block[j])
+                    } else {
+                        cols.is_schedule_round = F::one();
+
+                        // Compute `s0`.
+                        let w_i_minus_15 = w_window[1];
+                        let w_i_minus_15_rr_7 =
+                            cols.w_i_minus_15_rr_7.populate(output, w_i_minus_15, 7);
+                        let w_i_minus_15_rr_18 =
+                            cols.w_i_minus_15_rr_18.populate(output, w_i_minus_15, 18);
+                        let w_i_minus_15_rs_3 =
+                            cols.w_i_minus_15_rs_3.populate(output, w_i_minus_15, 3);
+                        let s0_intermediate = cols.sched_s0_intermediate.populate(
+                            output,
+                            w_i_minus_15_rr_7,
+                            w_i_minus_15_rr_18,
+                        );
+                        let s0 = cols
+                            .sched_s0
+                            .populate(output, s0_intermediate, w_i_minus_15_rs_3);
+
+                        // Compute `s1`.
+                        let w_i_minus_2 = w_window[14];
+                        let w_i_minus_2_rr_17 =
+                            cols.w_i_minus_2_rr_17.populate(output, w_i_minus_2, 17);
+                        let w_i_minus_2_rr_19 =
+                            cols.w_i_minus_2_rr_19.populate(output, w_i_minus_2, 19);
+                        let w_i_minus_2_rs_10 =
+                            cols.w_i_minus_2_rs_10.populate(output, w_i_minus_2, 10);
+                        let s1_intermediate = cols.sched_s1_intermediate.populate(
+                            output,
+                            w_i_minus_2_rr_17,
+                            w_i_minus_2_rr_19,
+                        );
+                        let s1 = cols
+                            .sched_s1
+                            .populate(output, s1_intermediate, w_i_minus_2_rs_10);
+
+                        // Compute `w[i]`.
+                        cols.sched_w
+                            .populate(output, w_window[0], s0, w_window[9], s1)
+                    };
+                    cols.w = Word::from(w_i);
+                    cols.k = Word::from(SHA_COMPRESS_K[j]);
+
+                    let e_rr_6 = cols.e_rr_6.populate(output, e, 6);
+                    let e_rr_11 = cols.e_rr_11.populate(output, e, 11);
+                    let e_rr_25 = cols.e_rr_25.populate(output, e, 25);
+                    let s1_intermediate = cols.s1_intermediate.populate(output, e_rr_6, e_rr_11);
+                    let s1 = cols.s1.populate(output, s1_intermediate, e_rr_25);
+
+                    let e_and_f = cols.e_and_f.populate(output, e, f);
+                    let e_not = cols.e_not.populate(output, e);
+                    let e_not_and_g = cols.e_not_and_g.populate(output, e_not, g);
+                    let ch = cols.ch.populate(output, e_and_f, e_not_and_g);
+
+                    let temp1 = cols
+                        .temp1
+                        .populate(output, h, s1, ch, SHA_COMPRESS_K[j], w_i);
+
+                    let a_rr_2 = cols.a_rr_2.populate(output, a, 2);
+                    let a_rr_13 = cols.a_rr_13.populate(output, a, 13);
+                    let a_rr_22 = cols.a_rr_22.populate(output, a, 22);
+                    let s0_intermediate = cols.s0_intermediate.populate(output, a_rr_2, a_rr_13);
+                    let s0 = cols.s0.populate(output, s0_intermediate, a_rr_22);
+
+                    let a_and_b = cols.a_and_b.populate(output, a, b);
+                    let a_and_c = cols.a_and_c.populate(output, a, c);
+                    let b_and_c = cols.b_and_c.populate(output, b, c);
+                    let maj_intermediate = cols.maj_intermediate.populate(output, a_and_b, a_and_c);
+                    let maj = cols.maj.populate(output, maj_intermediate, b_and_c);
+
+                    let temp2 = cols.temp2.populate(output, s0, maj);
+
+                    let d_add_temp1 = cols.d_add_temp1.populate(output, d, temp1);
+                    let temp1_add_temp2 = cols.temp1_add_temp2.populate(output, temp1, temp2);
+
+                    h = g;
+                    g = f;
+                    f = e;
+                    e = d_add_temp1;
+                    d = c;
+                    c = b;
+                    b = a;
+                    a = temp1_add_temp2;
+
+                    // Add the working variables to the chaining value after the last round.
+                    if j == SHA256_ROUNDS - 1 {
+                        let v = [a, b, c, d, e, f, g, h];
+                        for k in 0..8 {
+                            next_state[k] = cols.finalize_add[k].populate(output, state[k], v[k]);
+                        }
+                    }
+
+                    // Shift the message schedule window.
+                    w_window.copy_within(1.., 0);
+                    w_window[SHA256_BLOCK_WORDS - 1] = w_i;
+
+                    rows.push(row);
+                }
+
+                state = next_state;
+            }
+
+            // Write the state.
+            for j in 0..8usize {
+                let mut row = [F::zero(); NUM_SHA_COMPRESS_BLOCKS_COLS];
+                let cols: &mut ShaCompressBlocksCols<F> = row.as_mut_slice().borrow_mut();
+                populate_common(cols, last_block_idx, &state);
+
+                cols.is_finalize = F::one();
+                cols.octet[j] = F::one();
+                cols.mem
+                    .populate_write(event.h_write_records[j], &mut new_field_events);
+                cols.mem_addr = F::from_canonical_u32(event.state_ptr + (j * 4) as u32);
+
+                rows.push(row);
+            }
+        }
+
+        output.add_field_events(&new_field_events);
+
+        let nb_rows = rows.len();
+        let mut padded_nb_rows = nb_rows.next_power_of_two();
+        if padded_nb_rows == 2 || padded_nb_rows == 1 {
+            padded_nb_rows = 4;
+        }
+
+        for _ in nb_rows..padded_nb_rows {
+            let row = [F::zero(); NUM_SHA_COMPRESS_BLOCKS_COLS];
+            rows.push(row);
+        }
+
+        // Convert the trace to a row major matrix.
+        RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_SHA_COMPRESS_BLOCKS_COLS,
+        )
+    }
+
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input
+            .sha_compress_blocks_events
+            .iter()
+            .map(Self::event_rows)
+            .sum()
+    }
+
+    fn included(&self, shard: &Self::Record) -> bool {
+        !shard.sha_compress_blocks_events.is_empty()
+    }
+}
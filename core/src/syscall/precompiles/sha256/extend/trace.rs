@@ -96,6 +96,10 @@ impl<F: PrimeField> MachineAir<F> for ShaExtendChip {
         )
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.sha_extend_events.len() * 48
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.sha_extend_events.is_empty()
     }
@@ -1,5 +1,7 @@
 mod compress;
+mod compress_blocks;
 mod extend;
 
 pub use compress::*;
+pub use compress_blocks::*;
 pub use extend::*;
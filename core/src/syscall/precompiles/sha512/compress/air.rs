@@ -0,0 +1,503 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::AbstractField;
+
+use super::columns::{Sha512CompressCols, NUM_SHA512_COMPRESS_COLS};
+use super::{Sha512CompressChip, SHA512_COMPRESS_K};
+use crate::air::{BaseAirBuilder, SP1AirBuilder, Word, WordAirBuilder, WORD_SIZE};
+use crate::memory::MemoryCols;
+use crate::operations::{
+    AddU64Operation, AndU64Operation, FixedRotateRightU64Operation, NotU64Operation,
+    XorU64Operation, U64_SIZE,
+};
+use core::borrow::Borrow;
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for Sha512CompressChip {
+    fn width(&self) -> usize {
+        NUM_SHA512_COMPRESS_COLS
+    }
+}
+
+impl<AB> Air<AB> for Sha512CompressChip
+where
+    AB: SP1AirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &Sha512CompressCols<AB::Var> = main.row_slice(0).borrow();
+        let next: &Sha512CompressCols<AB::Var> = main.row_slice(1).borrow();
+
+        self.constrain_control_flow_flags(builder, local, next);
+
+        self.constrain_memory(builder, local);
+
+        self.constrain_working_variables(builder, local, next);
+
+        self.constrain_compression_ops(builder, local);
+
+        self.constrain_finalize_ops(builder, local);
+    }
+}
+
+impl Sha512CompressChip {
+    fn constrain_control_flow_flags<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Sha512CompressCols<AB::Var>,
+        next: &Sha512CompressCols<AB::Var>,
+    ) {
+        builder.assert_bool(local.is_real);
+
+        //// Constrain octet columns
+        // Verify that all of the octet columns are bool.
+        for i in 0..8 {
+            builder.assert_bool(local.octet[i]);
+        }
+        // Verify that exactly one of the octet columns is true on real rows.
+        let mut octet_sum = AB::Expr::zero();
+        for i in 0..8 {
+            octet_sum += local.octet[i].into();
+        }
+        builder.assert_eq(octet_sum, local.is_real);
+
+        // Verify that the first row's octet value is correct.
+        builder
+            .when_first_row()
+            .when(local.is_real)
+            .assert_one(local.octet[0]);
+
+        // Verify correct transition for octet column.
+        for i in 0..8 {
+            builder
+                .when_transition()
+                .when(next.is_real)
+                .when(local.octet[i])
+                .assert_one(next.octet[(i + 1) % 8])
+        }
+
+        //// Constrain octet_num columns
+        // Verify that all of the octet_num columns are bool.
+        for i in 0..12 {
+            builder.assert_bool(local.octet_num[i]);
+        }
+
+        // Verify that exactly one of the octet_num columns is true on real rows.
+        let mut octet_num_sum = AB::Expr::zero();
+        for i in 0..12 {
+            octet_num_sum += local.octet_num[i].into();
+        }
+        builder.assert_eq(octet_num_sum, local.is_real);
+
+        // Verify that the first row's octet_num value is correct.
+        builder
+            .when_first_row()
+            .when(local.is_real)
+            .assert_one(local.octet_num[0]);
+
+        for i in 0..12 {
+            builder
+                .when_transition()
+                .when(next.is_real)
+                .when_not(local.octet[7])
+                .assert_eq(local.octet_num[i], next.octet_num[i]);
+        }
+
+        for i in 0..12 {
+            builder
+                .when_transition()
+                .when(next.is_real)
+                .when(local.octet[7])
+                .assert_eq(local.octet_num[i], next.octet_num[(i + 1) % 12]);
+        }
+
+        // Assert that the is_compression flag is correct.
+        let mut is_compression = AB::Expr::zero();
+        for i in 1..11 {
+            is_compression += local.octet_num[i].into();
+        }
+        builder.assert_eq(local.is_compression, is_compression);
+
+        // Every row of an event except for the last one is followed by a row of the same event.
+        let is_same_event_transition = local.is_real - local.octet_num[11] * local.octet[7];
+        let mut builder_transition = builder.when_transition();
+        let mut builder_same_event = builder_transition.when(is_same_event_transition);
+        builder_same_event.assert_one(next.is_real);
+        builder_same_event.assert_eq(local.shard, next.shard);
+        builder_same_event.assert_eq(local.clk + AB::F::from_canonical_u32(4), next.clk);
+        builder_same_event.assert_eq(local.w_ptr, next.w_ptr);
+        builder_same_event.assert_eq(local.h_ptr, next.h_ptr);
+    }
+
+    fn constrain_memory<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Sha512CompressCols<AB::Var>,
+    ) {
+        let is_initialize = local.octet_num[0];
+        let is_finalize = local.octet_num[11];
+        builder.constraint_memory_access_slice(
+            local.shard,
+            local.clk.into(),
+            local.mem_addr,
+            &local.mem,
+            local.is_real,
+        );
+
+        // The initialize and compression phases only read from memory.
+        for k in 0..2 {
+            builder
+                .when(is_initialize + local.is_compression)
+                .assert_word_eq(*local.mem[k].value(), *local.mem[k].prev_value());
+        }
+
+        // Calculate the current cycle_num.
+        let mut cycle_num = AB::Expr::zero();
+        for i in 0..12 {
+            cycle_num += local.octet_num[i] * AB::Expr::from_canonical_usize(i);
+        }
+
+        // Calculate the current step of the cycle 8.
+        let mut cycle_step = AB::Expr::zero();
+        for i in 0..8 {
+            cycle_step += local.octet[i] * AB::Expr::from_canonical_usize(i);
+        }
+
+        // Verify correct mem address for initialize phase.
+        builder.when(is_initialize).assert_eq(
+            local.mem_addr,
+            local.h_ptr + cycle_step.clone() * AB::Expr::from_canonical_u32(8),
+        );
+
+        // Verify correct mem address for compression phase.
+        builder.when(local.is_compression).assert_eq(
+            local.mem_addr,
+            local.w_ptr
+                + ((cycle_num - AB::Expr::one()) * AB::Expr::from_canonical_u32(8)
+                    + cycle_step.clone())
+                    * AB::Expr::from_canonical_u32(8),
+        );
+
+        // Verify correct mem address for finalize phase.
+        builder.when(is_finalize).assert_eq(
+            local.mem_addr,
+            local.h_ptr + cycle_step * AB::Expr::from_canonical_u32(8),
+        );
+
+        // In the initialize phase, verify that local.a, local.b, ... is correctly set to the
+        // memory value.
+        let vars = [
+            local.a, local.b, local.c, local.d, local.e, local.f, local.g, local.h,
+        ];
+        for i in 0..8 {
+            for k in 0..2 {
+                builder
+                    .when(is_initialize)
+                    .when(local.octet[i])
+                    .assert_word_eq(vars[i][k], *local.mem[k].value());
+            }
+        }
+    }
+
+    fn constrain_working_variables<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Sha512CompressCols<AB::Var>,
+        next: &Sha512CompressCols<AB::Var>,
+    ) {
+        let is_initialize = local.octet_num[0];
+        let is_finalize = local.octet_num[11];
+        let local_vars = [
+            local.a, local.b, local.c, local.d, local.e, local.f, local.g, local.h,
+        ];
+        let next_vars = [
+            next.a, next.b, next.c, next.d, next.e, next.f, next.g, next.h,
+        ];
+
+        // The working variables hold the whole state during the initialize phase and in the first
+        // round of the compression phase.
+        for i in 0..8 {
+            for k in 0..2 {
+                builder
+                    .when_transition()
+                    .when(is_initialize)
+                    .assert_word_eq(local_vars[i][k], next_vars[i][k]);
+            }
+        }
+
+        // Every round of the compression phase shifts the working variables.
+        let rotated_vars = [
+            local.temp1_add_temp2.value,
+            local.a,
+            local.b,
+            local.c,
+            local.d_add_temp1.value,
+            local.e,
+            local.f,
+            local.g,
+        ];
+        for i in 0..8 {
+            for k in 0..2 {
+                builder
+                    .when_transition()
+                    .when(local.is_compression)
+                    .assert_word_eq(rotated_vars[i][k], next_vars[i][k]);
+            }
+        }
+
+        // The working variables are kept constant during the finalize phase.
+        for i in 0..8 {
+            for k in 0..2 {
+                builder
+                    .when_transition()
+                    .when(is_finalize)
+                    .when_not(local.octet[7])
+                    .assert_word_eq(local_vars[i][k], next_vars[i][k]);
+            }
+        }
+    }
+
+    fn constrain_compression_ops<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Sha512CompressCols<AB::Var>,
+    ) {
+        // Select the round constant using the octet and octet_num columns.
+        let k_bytes = SHA512_COMPRESS_K.map(u64::to_le_bytes);
+        for i in 0..U64_SIZE {
+            let mut k_byte = AB::Expr::zero();
+            for round in 0..80 {
+                k_byte += local.octet_num[1 + round / 8]
+                    * local.octet[round % 8]
+                    * AB::F::from_canonical_u8(k_bytes[round][i]);
+            }
+            builder.assert_eq(local.k[i / WORD_SIZE][i % WORD_SIZE], k_byte);
+        }
+
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            local.e,
+            14,
+            local.e_rr_14,
+            local.is_compression,
+        );
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            local.e,
+            18,
+            local.e_rr_18,
+            local.is_compression,
+        );
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            local.e,
+            41,
+            local.e_rr_41,
+            local.is_compression,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.e_rr_14.value,
+            local.e_rr_18.value,
+            local.s1_intermediate,
+            local.is_compression,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.s1_intermediate.value,
+            local.e_rr_41.value,
+            local.s1,
+            local.is_compression,
+        );
+
+        AndU64Operation::<AB::F>::eval(
+            builder,
+            local.e,
+            local.f,
+            local.e_and_f,
+            local.is_compression,
+        );
+        NotU64Operation::<AB::F>::eval(builder, local.e, local.e_not, local.is_compression);
+        AndU64Operation::<AB::F>::eval(
+            builder,
+            local.e_not.value,
+            local.g,
+            local.e_not_and_g,
+            local.is_compression,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.e_and_f.value,
+            local.e_not_and_g.value,
+            local.ch,
+            local.is_compression,
+        );
+
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            local.h,
+            local.s1.value,
+            local.temp1_intermediate[0],
+            local.is_compression,
+        );
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            local.temp1_intermediate[0].value,
+            local.ch.value,
+            local.temp1_intermediate[1],
+            local.is_compression,
+        );
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            local.temp1_intermediate[1].value,
+            local.k,
+            local.temp1_intermediate[2],
+            local.is_compression,
+        );
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            local.temp1_intermediate[2].value,
+            [*local.mem[0].value(), *local.mem[1].value()],
+            local.temp1,
+            local.is_compression,
+        );
+
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            local.a,
+            28,
+            local.a_rr_28,
+            local.is_compression,
+        );
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            local.a,
+            34,
+            local.a_rr_34,
+            local.is_compression,
+        );
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            local.a,
+            39,
+            local.a_rr_39,
+            local.is_compression,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.a_rr_28.value,
+            local.a_rr_34.value,
+            local.s0_intermediate,
+            local.is_compression,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.s0_intermediate.value,
+            local.a_rr_39.value,
+            local.s0,
+            local.is_compression,
+        );
+
+        AndU64Operation::<AB::F>::eval(
+            builder,
+            local.a,
+            local.b,
+            local.a_and_b,
+            local.is_compression,
+        );
+        AndU64Operation::<AB::F>::eval(
+            builder,
+            local.a,
+            local.c,
+            local.a_and_c,
+            local.is_compression,
+        );
+        AndU64Operation::<AB::F>::eval(
+            builder,
+            local.b,
+            local.c,
+            local.b_and_c,
+            local.is_compression,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.a_and_b.value,
+            local.a_and_c.value,
+            local.maj_intermediate,
+            local.is_compression,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.maj_intermediate.value,
+            local.b_and_c.value,
+            local.maj,
+            local.is_compression,
+        );
+
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            local.s0.value,
+            local.maj.value,
+            local.temp2,
+            local.is_compression,
+        );
+
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            local.d,
+            local.temp1.value,
+            local.d_add_temp1,
+            local.is_compression,
+        );
+
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            local.temp1.value,
+            local.temp2.value,
+            local.temp1_add_temp2,
+            local.is_compression,
+        );
+    }
+
+    fn constrain_finalize_ops<AB: SP1AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Sha512CompressCols<AB::Var>,
+    ) {
+        let is_finalize = local.octet_num[11];
+        // In the finalize phase, need to execute h[0] + a, h[1] + b, ..., h[7] + h, for each of the
+        // phase's 8 rows.
+        // We can get the needed operand (a,b,c,...,h) by doing an inner product between octet and
+        // [a,b,c,...,h] which will act as a selector.
+        let add_operands = [
+            local.a, local.b, local.c, local.d, local.e, local.f, local.g, local.h,
+        ];
+        for k in 0..2 {
+            let zero = AB::Expr::zero();
+            let mut filtered_operand = Word([zero.clone(), zero.clone(), zero.clone(), zero]);
+            for (i, operand) in local.octet.iter().zip(add_operands.iter()) {
+                for j in 0..4 {
+                    filtered_operand.0[j] += *i * operand[k].0[j];
+                }
+            }
+
+            builder.when(is_finalize).assert_word_eq(
+                filtered_operand,
+                local.finalized_operand[k].map(|x| x.into()),
+            );
+        }
+
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            [*local.mem[0].prev_value(), *local.mem[1].prev_value()],
+            local.finalized_operand,
+            local.finalize_add,
+            is_finalize,
+        );
+
+        for k in 0..2 {
+            builder
+                .when(is_finalize)
+                .assert_word_eq(*local.mem[k].value(), local.finalize_add.value[k]);
+        }
+    }
+}
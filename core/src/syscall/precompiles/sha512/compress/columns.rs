@@ -0,0 +1,92 @@
+use std::mem::size_of;
+
+use sp1_derive::AlignedBorrow;
+
+use crate::air::Word;
+use crate::memory::MemoryReadWriteCols;
+use crate::operations::AddU64Operation;
+use crate::operations::AndU64Operation;
+use crate::operations::FixedRotateRightU64Operation;
+use crate::operations::NotU64Operation;
+use crate::operations::XorU64Operation;
+
+pub const NUM_SHA512_COMPRESS_COLS: usize = size_of::<Sha512CompressCols<u8>>();
+
+/// The columns of the SHA-512 compress chip.
+///
+/// Every 64-bit word is represented by its low and high 32-bit words.
+#[derive(AlignedBorrow, Default, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Sha512CompressCols<T> {
+    /// Inputs.
+    pub shard: T,
+    pub clk: T,
+    pub w_ptr: T,
+    pub h_ptr: T,
+
+    /// The bits for cycle 8.
+    pub octet: [T; 8],
+
+    // This will specify which octet we are currently processing.
+    // The first octet is for initialize.
+    // The next 10 octets are for compress.
+    // The last octet is for finalize.
+    pub octet_num: [T; 12],
+
+    /// The low and high words of the 64-bit word accessed in this row.
+    pub mem: [MemoryReadWriteCols<T>; 2],
+    pub mem_addr: T,
+
+    pub a: [Word<T>; 2],
+    pub b: [Word<T>; 2],
+    pub c: [Word<T>; 2],
+    pub d: [Word<T>; 2],
+    pub e: [Word<T>; 2],
+    pub f: [Word<T>; 2],
+    pub g: [Word<T>; 2],
+    pub h: [Word<T>; 2],
+
+    /// The round constant `k[i]` of the current round.
+    pub k: [Word<T>; 2],
+
+    pub e_rr_14: FixedRotateRightU64Operation<T>,
+    pub e_rr_18: FixedRotateRightU64Operation<T>,
+    pub e_rr_41: FixedRotateRightU64Operation<T>,
+    pub s1_intermediate: XorU64Operation<T>,
+    pub s1: XorU64Operation<T>,
+
+    pub e_and_f: AndU64Operation<T>,
+    pub e_not: NotU64Operation<T>,
+    pub e_not_and_g: AndU64Operation<T>,
+    pub ch: XorU64Operation<T>,
+
+    /// Computing `temp1 = h + s1 + ch + k[i] + w[i]`.
+    pub temp1_intermediate: [AddU64Operation<T>; 3],
+    pub temp1: AddU64Operation<T>,
+
+    pub a_rr_28: FixedRotateRightU64Operation<T>,
+    pub a_rr_34: FixedRotateRightU64Operation<T>,
+    pub a_rr_39: FixedRotateRightU64Operation<T>,
+    pub s0_intermediate: XorU64Operation<T>,
+    pub s0: XorU64Operation<T>,
+
+    pub a_and_b: AndU64Operation<T>,
+    pub a_and_c: AndU64Operation<T>,
+    pub b_and_c: AndU64Operation<T>,
+    pub maj_intermediate: XorU64Operation<T>,
+    pub maj: XorU64Operation<T>,
+
+    pub temp2: AddU64Operation<T>,
+
+    pub d_add_temp1: AddU64Operation<T>,
+    pub temp1_add_temp2: AddU64Operation<T>,
+
+    /// The working variable added to the state in the current row of the finalize phase.
+    pub finalized_operand: [Word<T>; 2],
+    pub finalize_add: AddU64Operation<T>,
+
+    // The initialize phase is `octet_num[0]` and the finalize phase is `octet_num[11]`.
+    pub is_compression: T,
+
+    pub is_real: T,
+}
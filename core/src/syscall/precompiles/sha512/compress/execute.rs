@@ -0,0 +1,96 @@
+use crate::{
+    runtime::{Register, Syscall},
+    syscall::precompiles::{
+        sha512::{mr_u64, mw_u64, Sha512CompressEvent, SHA512_COMPRESS_K},
+        SyscallContext,
+    },
+};
+
+use super::Sha512CompressChip;
+
+impl Syscall for Sha512CompressChip {
+    fn num_extra_cycles(&self) -> u32 {
+        8 * 4 + 80 * 4 + 8 * 4
+    }
+
+    fn execute(&self, rt: &mut SyscallContext) -> u32 {
+        // Read `w_ptr` and `h_ptr` from registers a0 and a1.
+        // TODO: these will have to be be constrained, but can do it later.
+        let w_ptr = rt.register_unsafe(Register::X10);
+        let h_ptr = rt.register_unsafe(Register::X11);
+        if w_ptr % 4 != 0 || h_ptr % 4 != 0 {
+            panic!("sha512 compress: pointers must be word aligned");
+        }
+
+        let saved_clk = rt.clk;
+        let mut h_read_records = Vec::new();
+        let mut w_i_read_records = Vec::new();
+        let mut h_write_records = Vec::new();
+
+        // Execute the "initialize" phase.
+        let mut hx = [0u64; 8];
+        for i in 0..8 {
+            let (records, value) = mr_u64(rt, h_ptr + i as u32 * 8);
+            h_read_records.push(records);
+            hx[i] = value;
+            rt.clk += 4;
+        }
+
+        // Execute the "compress" phase.
+        let mut original_w = Vec::new();
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = hx;
+        for i in 0..80 {
+            let (records, w_i) = mr_u64(rt, w_ptr + i as u32 * 8);
+            original_w.push(w_i);
+            w_i_read_records.push(records);
+
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA512_COMPRESS_K[i])
+                .wrapping_add(w_i);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+
+            rt.clk += 4;
+        }
+
+        // Execute the "finalize" phase.
+        let v = [a, b, c, d, e, f, g, h];
+        for i in 0..8 {
+            let records = mw_u64(rt, h_ptr + i as u32 * 8, hx[i].wrapping_add(v[i]));
+            h_write_records.push(records);
+            rt.clk += 4;
+        }
+
+        // Push the SHA512 compress event.
+        let shard = rt.current_shard();
+        rt.record_mut()
+            .sha512_compress_events
+            .push(Sha512CompressEvent {
+                shard,
+                clk: saved_clk,
+                w_ptr,
+                h_ptr,
+                w: original_w,
+                h: hx,
+                h_read_records: h_read_records.try_into().unwrap(),
+                w_i_read_records,
+                h_write_records: h_write_records.try_into().unwrap(),
+            });
+
+        w_ptr
+    }
+}
@@ -0,0 +1,167 @@
+use std::borrow::BorrowMut;
+
+use p3_field::PrimeField;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::{air::MachineAir, operations::u64_to_words, runtime::ExecutionRecord};
+
+use super::{
+    columns::{Sha512CompressCols, NUM_SHA512_COMPRESS_COLS},
+    Sha512CompressChip, SHA512_COMPRESS_K,
+};
+
+impl<F: PrimeField> MachineAir<F> for Sha512CompressChip {
+    type Record = ExecutionRecord;
+
+    fn name(&self) -> String {
+        "Sha512Compress".to_string()
+    }
+
+    fn generate_trace(
+        &self,
+        input: &ExecutionRecord,
+        output: &mut ExecutionRecord,
+    ) -> RowMajorMatrix<F> {
+        let mut rows = Vec::new();
+
+        let mut new_field_events = Vec::new();
+        for event in input.sha512_compress_events.iter() {
+            let mut row_idx = 0u32;
+
+            // Fill in the columns that are shared by every row of the event.
+            let mut new_row = |octet: usize, octet_num: usize, v: &[u64; 8]| {
+                let mut row = [F::zero(); NUM_SHA512_COMPRESS_COLS];
+                let cols: &mut Sha512CompressCols<F> = row.as_mut_slice().borrow_mut();
+                cols.shard = F::from_canonical_u32(event.shard);
+                cols.clk = F::from_canonical_u32(event.clk + row_idx * 4);
+                cols.w_ptr = F::from_canonical_u32(event.w_ptr);
+                cols.h_ptr = F::from_canonical_u32(event.h_ptr);
+                cols.octet[octet] = F::one();
+                cols.octet_num[octet_num] = F::one();
+                cols.a = u64_to_words(v[0]);
+                cols.b = u64_to_words(v[1]);
+                cols.c = u64_to_words(v[2]);
+                cols.d = u64_to_words(v[3]);
+                cols.e = u64_to_words(v[4]);
+                cols.f = u64_to_words(v[5]);
+                cols.g = u64_to_words(v[6]);
+                cols.h = u64_to_words(v[7]);
+                cols.is_real = F::one();
+                row_idx += 1;
+                row
+            };
+
+            // Load a, b, c, d, e, f, g, h. Every row of this phase holds the whole state.
+            for j in 0..8usize {
+                let mut row = new_row(j, 0, &event.h);
+                let cols: &mut Sha512CompressCols<F> = row.as_mut_slice().borrow_mut();
+                for k in 0..2 {
+                    cols.mem[k].populate_read(event.h_read_records[j][k], &mut new_field_events);
+                }
+                cols.mem_addr = F::from_canonical_u32(event.h_ptr + (j * 8) as u32);
+                rows.push(row);
+            }
+
+            // Performs the compress operation.
+            let mut v = event.h;
+            for j in 0..80usize {
+                let mut row = new_row(j % 8, 1 + j / 8, &v);
+                let cols: &mut Sha512CompressCols<F> = row.as_mut_slice().borrow_mut();
+
+                cols.is_compression = F::one();
+                for k in 0..2 {
+                    cols.mem[k].populate_read(event.w_i_read_records[j][k], &mut new_field_events);
+                }
+                cols.mem_addr = F::from_canonical_u32(event.w_ptr + (j * 8) as u32);
+                cols.k = u64_to_words(SHA512_COMPRESS_K[j]);
+
+                let [a, b, c, d, e, f, g, h] = v;
+
+                let e_rr_14 = cols.e_rr_14.populate(output, e, 14);
+                let e_rr_18 = cols.e_rr_18.populate(output, e, 18);
+                let e_rr_41 = cols.e_rr_41.populate(output, e, 41);
+                let s1_intermediate = cols.s1_intermediate.populate(output, e_rr_14, e_rr_18);
+                let s1 = cols.s1.populate(output, s1_intermediate, e_rr_41);
+
+                let e_and_f = cols.e_and_f.populate(output, e, f);
+                let e_not = cols.e_not.populate(output, e);
+                let e_not_and_g = cols.e_not_and_g.populate(output, e_not, g);
+                let ch = cols.ch.populate(output, e_and_f, e_not_and_g);
+
+                let h_add_s1 = cols.temp1_intermediate[0].populate(output, h, s1);
+                let h_add_s1_add_ch = cols.temp1_intermediate[1].populate(output, h_add_s1, ch);
+                let h_add_s1_add_ch_add_k = cols.temp1_intermediate[2].populate(
+                    output,
+                    h_add_s1_add_ch,
+                    SHA512_COMPRESS_K[j],
+                );
+                let temp1 = cols
+                    .temp1
+                    .populate(output, h_add_s1_add_ch_add_k, event.w[j]);
+
+                let a_rr_28 = cols.a_rr_28.populate(output, a, 28);
+                let a_rr_34 = cols.a_rr_34.populate(output, a, 34);
+                let a_rr_39 = cols.a_rr_39.populate(output, a, 39);
+                let s0_intermediate = cols.s0_intermediate.populate(output, a_rr_28, a_rr_34);
+                let s0 = cols.s0.populate(output, s0_intermediate, a_rr_39);
+
+                let a_and_b = cols.a_and_b.populate(output, a, b);
+                let a_and_c = cols.a_and_c.populate(output, a, c);
+                let b_and_c = cols.b_and_c.populate(output, b, c);
+                let maj_intermediate = cols.maj_intermediate.populate(output, a_and_b, a_and_c);
+                let maj = cols.maj.populate(output, maj_intermediate, b_and_c);
+
+                let temp2 = cols.temp2.populate(output, s0, maj);
+
+                let d_add_temp1 = cols.d_add_temp1.populate(output, d, temp1);
+                let temp1_add_temp2 = cols.temp1_add_temp2.populate(output, temp1, temp2);
+
+                v = [temp1_add_temp2, a, b, c, d_add_temp1, e, f, g];
+
+                rows.push(row);
+            }
+
+            // Store a, b, c, d, e, f, g, h. Every row of this phase holds the final working
+            // variables.
+            for j in 0..8usize {
+                let mut row = new_row(j, 11, &v);
+                let cols: &mut Sha512CompressCols<F> = row.as_mut_slice().borrow_mut();
+
+                cols.finalized_operand = u64_to_words(v[j]);
+                cols.finalize_add.populate(output, event.h[j], v[j]);
+                for k in 0..2 {
+                    cols.mem[k].populate_write(event.h_write_records[j][k], &mut new_field_events);
+                }
+                cols.mem_addr = F::from_canonical_u32(event.h_ptr + (j * 8) as u32);
+                rows.push(row);
+            }
+        }
+
+        output.add_field_events(&new_field_events);
+
+        let nb_rows = rows.len();
+        let mut padded_nb_rows = nb_rows.next_power_of_two();
+        if padded_nb_rows == 2 || padded_nb_rows == 1 {
+            padded_nb_rows = 4;
+        }
+
+        for _ in nb_rows..padded_nb_rows {
+            let row = [F::zero(); NUM_SHA512_COMPRESS_COLS];
+            rows.push(row);
+        }
+
+        // Convert the trace to a row major matrix.
+        RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_SHA512_COMPRESS_COLS,
+        )
+    }
+
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.sha512_compress_events.len() * 96
+    }
+
+    fn included(&self, shard: &Self::Record) -> bool {
+        !shard.sha512_compress_events.is_empty()
+    }
+}
@@ -0,0 +1,237 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+
+use super::{Sha512ExtendChip, Sha512ExtendCols, NUM_SHA512_EXTEND_COLS};
+use crate::air::{BaseAirBuilder, SP1AirBuilder, Word, WordAirBuilder};
+use crate::memory::MemoryCols;
+use crate::operations::{
+    AddU64Operation, FixedRotateRightU64Operation, FixedShiftRightU64Operation, XorU64Operation,
+};
+use core::borrow::Borrow;
+use p3_field::AbstractField;
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for Sha512ExtendChip {
+    fn width(&self) -> usize {
+        NUM_SHA512_EXTEND_COLS
+    }
+}
+
+/// Constrains an access to the 64-bit word at `addr` as a pair of 32-bit words.
+fn constraint_u64_access<AB: SP1AirBuilder, M: MemoryCols<AB::Var>>(
+    builder: &mut AB,
+    shard: AB::Var,
+    clk: AB::Expr,
+    addr: AB::Expr,
+    access: &[M; 2],
+    is_real: AB::Var,
+) {
+    for (k, access) in access.iter().enumerate() {
+        builder.constraint_memory_access(
+            shard,
+            clk.clone(),
+            addr.clone() + AB::F::from_canonical_usize(k * 4),
+            access,
+            is_real,
+        );
+    }
+}
+
+/// The value of a 64-bit word accessed as a pair of 32-bit words.
+fn u64_value<T: Copy, M: MemoryCols<T>>(access: &[M; 2]) -> [Word<T>; 2] {
+    [*access[0].value(), *access[1].value()]
+}
+
+impl<AB> Air<AB> for Sha512ExtendChip
+where
+    AB: SP1AirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        // Initialize columns.
+        let main = builder.main();
+        let local: &Sha512ExtendCols<AB::Var> = main.row_slice(0).borrow();
+        let next: &Sha512ExtendCols<AB::Var> = main.row_slice(1).borrow();
+        let i_start = AB::F::from_canonical_u32(16);
+        let nb_cycles_per_extend = AB::F::from_canonical_u64(20);
+        let nb_bytes_in_u64 = AB::F::from_canonical_u32(8);
+
+        // Evaluate the control flags.
+        self.eval_flags(builder);
+
+        builder.assert_bool(local.is_real);
+
+        // Copy over the inputs until the result has been computed (every 64 rows).
+        builder
+            .when_transition()
+            .when_not(local.cycle_64_end)
+            .assert_eq(local.shard, next.shard);
+        builder
+            .when_transition()
+            .when_not(local.cycle_64_end)
+            .assert_eq(local.clk, next.clk);
+        builder
+            .when_transition()
+            .when_not(local.cycle_64_end)
+            .assert_eq(local.w_ptr, next.w_ptr);
+        builder
+            .when_transition()
+            .when_not(local.cycle_64_end)
+            .assert_eq(local.is_real, next.is_real);
+
+        let clk = local.clk + (local.i - i_start) * nb_cycles_per_extend;
+
+        // Read w[i-15].
+        constraint_u64_access(
+            builder,
+            local.shard,
+            clk.clone(),
+            local.w_ptr + (local.i - AB::F::from_canonical_u32(15)) * nb_bytes_in_u64,
+            &local.w_i_minus_15,
+            local.is_real,
+        );
+
+        // Read w[i-2].
+        constraint_u64_access(
+            builder,
+            local.shard,
+            clk.clone() + AB::F::from_canonical_u32(4),
+            local.w_ptr + (local.i - AB::F::from_canonical_u32(2)) * nb_bytes_in_u64,
+            &local.w_i_minus_2,
+            local.is_real,
+        );
+
+        // Read w[i-16].
+        constraint_u64_access(
+            builder,
+            local.shard,
+            clk.clone() + AB::F::from_canonical_u32(8),
+            local.w_ptr + (local.i - AB::F::from_canonical_u32(16)) * nb_bytes_in_u64,
+            &local.w_i_minus_16,
+            local.is_real,
+        );
+
+        // Read w[i-7].
+        constraint_u64_access(
+            builder,
+            local.shard,
+            clk.clone() + AB::F::from_canonical_u32(12),
+            local.w_ptr + (local.i - AB::F::from_canonical_u32(7)) * nb_bytes_in_u64,
+            &local.w_i_minus_7,
+            local.is_real,
+        );
+
+        // Compute `s0`.
+        let w_i_minus_15 = u64_value(&local.w_i_minus_15);
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            w_i_minus_15,
+            1,
+            local.w_i_minus_15_rr_1,
+            local.is_real,
+        );
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            w_i_minus_15,
+            8,
+            local.w_i_minus_15_rr_8,
+            local.is_real,
+        );
+        FixedShiftRightU64Operation::<AB::F>::eval(
+            builder,
+            w_i_minus_15,
+            7,
+            local.w_i_minus_15_rs_7,
+            local.is_real,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.w_i_minus_15_rr_1.value,
+            local.w_i_minus_15_rr_8.value,
+            local.s0_intermediate,
+            local.is_real,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.s0_intermediate.value,
+            local.w_i_minus_15_rs_7.value,
+            local.s0,
+            local.is_real,
+        );
+
+        // Compute `s1`.
+        let w_i_minus_2 = u64_value(&local.w_i_minus_2);
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            w_i_minus_2,
+            19,
+            local.w_i_minus_2_rr_19,
+            local.is_real,
+        );
+        FixedRotateRightU64Operation::<AB::F>::eval(
+            builder,
+            w_i_minus_2,
+            61,
+            local.w_i_minus_2_rr_61,
+            local.is_real,
+        );
+        FixedShiftRightU64Operation::<AB::F>::eval(
+            builder,
+            w_i_minus_2,
+            6,
+            local.w_i_minus_2_rs_6,
+            local.is_real,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.w_i_minus_2_rr_19.value,
+            local.w_i_minus_2_rr_61.value,
+            local.s1_intermediate,
+            local.is_real,
+        );
+        XorU64Operation::<AB::F>::eval(
+            builder,
+            local.s1_intermediate.value,
+            local.w_i_minus_2_rs_6.value,
+            local.s1,
+            local.is_real,
+        );
+
+        // Compute `s2`.
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            u64_value(&local.w_i_minus_16),
+            local.s0.value,
+            local.s2_intermediate_0,
+            local.is_real,
+        );
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            local.s2_intermediate_0.value,
+            u64_value(&local.w_i_minus_7),
+            local.s2_intermediate_1,
+            local.is_real,
+        );
+        AddU64Operation::<AB::F>::eval(
+            builder,
+            local.s2_intermediate_1.value,
+            local.s1.value,
+            local.s2,
+            local.is_real,
+        );
+
+        // Write `s2` to `w[i]`.
+        let w_i = u64_value(&local.w_i);
+        for k in 0..2 {
+            builder
+                .when(local.is_real)
+                .assert_word_eq(w_i[k], local.s2.value[k]);
+        }
+        constraint_u64_access(
+            builder,
+            local.shard,
+            clk + AB::F::from_canonical_u32(16),
+            local.w_ptr + local.i * nb_bytes_in_u64,
+            &local.w_i,
+            local.is_real,
+        );
+    }
+}
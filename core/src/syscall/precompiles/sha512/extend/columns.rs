@@ -0,0 +1,63 @@
+use std::mem::size_of;
+
+use sp1_derive::AlignedBorrow;
+
+use crate::memory::MemoryReadCols;
+use crate::memory::MemoryWriteCols;
+use crate::operations::AddU64Operation;
+use crate::operations::FixedRotateRightU64Operation;
+use crate::operations::FixedShiftRightU64Operation;
+use crate::operations::XorU64Operation;
+
+pub const NUM_SHA512_EXTEND_COLS: usize = size_of::<Sha512ExtendCols<u8>>();
+
+#[derive(AlignedBorrow, Default, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Sha512ExtendCols<T> {
+    /// Inputs.
+    pub shard: T,
+    pub clk: T,
+    pub w_ptr: T,
+
+    /// Control flags.
+    pub i: T,
+    pub cycle_16: T,
+    pub cycle_16_minus_g: T,
+    pub cycle_16_minus_g_inv: T,
+    pub cycle_16_start: T,
+    pub cycle_16_minus_one: T,
+    pub cycle_16_minus_one_inv: T,
+    pub cycle_16_end: T,
+    pub cycle_64: [T; 4],
+    pub cycle_64_start: T,
+    pub cycle_64_end: T,
+
+    /// Computing `s0`.
+    pub w_i_minus_15: [MemoryReadCols<T>; 2],
+    pub w_i_minus_15_rr_1: FixedRotateRightU64Operation<T>,
+    pub w_i_minus_15_rr_8: FixedRotateRightU64Operation<T>,
+    pub w_i_minus_15_rs_7: FixedShiftRightU64Operation<T>,
+    pub s0_intermediate: XorU64Operation<T>,
+    pub s0: XorU64Operation<T>,
+
+    /// Computing `s1`.
+    pub w_i_minus_2: [MemoryReadCols<T>; 2],
+    pub w_i_minus_2_rr_19: FixedRotateRightU64Operation<T>,
+    pub w_i_minus_2_rr_61: FixedRotateRightU64Operation<T>,
+    pub w_i_minus_2_rs_6: FixedShiftRightU64Operation<T>,
+    pub s1_intermediate: XorU64Operation<T>,
+    pub s1: XorU64Operation<T>,
+
+    /// Computing `s2 = w[i-16] + s0 + w[i-7] + s1`.
+    pub w_i_minus_16: [MemoryReadCols<T>; 2],
+    pub w_i_minus_7: [MemoryReadCols<T>; 2],
+    pub s2_intermediate_0: AddU64Operation<T>,
+    pub s2_intermediate_1: AddU64Operation<T>,
+    pub s2: AddU64Operation<T>,
+
+    /// Result.
+    pub w_i: [MemoryWriteCols<T>; 2],
+
+    /// Selector.
+    pub is_real: T,
+}
@@ -0,0 +1,88 @@
+use crate::{
+    runtime::{Register, Syscall},
+    syscall::precompiles::{
+        sha512::{mr_u64, mw_u64, Sha512ExtendEvent},
+        SyscallContext,
+    },
+};
+
+use super::Sha512ExtendChip;
+
+impl Syscall for Sha512ExtendChip {
+    fn num_extra_cycles(&self) -> u32 {
+        64 * 20
+    }
+
+    fn execute(&self, rt: &mut SyscallContext) -> u32 {
+        // Read `w_ptr` from register a0.
+        // TODO: this is underconstrained.
+        let w_ptr = rt.register_unsafe(Register::X10);
+        if w_ptr % 4 != 0 {
+            panic!("sha512 extend: pointer must be word aligned");
+        }
+
+        let clk_init = rt.clk;
+        let mut w_i_minus_15_reads = Vec::new();
+        let mut w_i_minus_2_reads = Vec::new();
+        let mut w_i_minus_16_reads = Vec::new();
+        let mut w_i_minus_7_reads = Vec::new();
+        let mut w_i_writes = Vec::new();
+
+        for i in 16..80 {
+            // Read w[i-15].
+            let (records, w_i_minus_15) = mr_u64(rt, w_ptr + (i - 15) * 8);
+            w_i_minus_15_reads.push(records);
+            rt.clk += 4;
+
+            // Compute `s0`.
+            let s0 =
+                w_i_minus_15.rotate_right(1) ^ w_i_minus_15.rotate_right(8) ^ (w_i_minus_15 >> 7);
+
+            // Read w[i-2].
+            let (records, w_i_minus_2) = mr_u64(rt, w_ptr + (i - 2) * 8);
+            w_i_minus_2_reads.push(records);
+            rt.clk += 4;
+
+            // Compute `s1`.
+            let s1 =
+                w_i_minus_2.rotate_right(19) ^ w_i_minus_2.rotate_right(61) ^ (w_i_minus_2 >> 6);
+
+            // Read w[i-16].
+            let (records, w_i_minus_16) = mr_u64(rt, w_ptr + (i - 16) * 8);
+            w_i_minus_16_reads.push(records);
+            rt.clk += 4;
+
+            // Read w[i-7].
+            let (records, w_i_minus_7) = mr_u64(rt, w_ptr + (i - 7) * 8);
+            w_i_minus_7_reads.push(records);
+            rt.clk += 4;
+
+            // Compute `w_i`.
+            let w_i = w_i_minus_16
+                .wrapping_add(s0)
+                .wrapping_add(w_i_minus_7)
+                .wrapping_add(s1);
+
+            // Write w[i].
+            w_i_writes.push(mw_u64(rt, w_ptr + i * 8, w_i));
+            rt.clk += 4;
+        }
+
+        // Push the SHA512 extend event.
+        let shard = rt.current_shard();
+        rt.record_mut()
+            .sha512_extend_events
+            .push(Sha512ExtendEvent {
+                shard,
+                clk: clk_init,
+                w_ptr,
+                w_i_minus_15_reads,
+                w_i_minus_2_reads,
+                w_i_minus_16_reads,
+                w_i_minus_7_reads,
+                w_i_writes,
+            });
+
+        w_ptr
+    }
+}
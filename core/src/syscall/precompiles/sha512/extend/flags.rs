@@ -0,0 +1,119 @@
+use core::borrow::Borrow;
+use p3_air::AirBuilder;
+use p3_baby_bear::BabyBear;
+use p3_field::AbstractField;
+use p3_field::Field;
+use p3_field::PrimeField32;
+use p3_field::TwoAdicField;
+
+use p3_matrix::MatrixRowSlices;
+
+use crate::air::SP1AirBuilder;
+
+use super::Sha512ExtendChip;
+use super::Sha512ExtendCols;
+
+impl<F: Field> Sha512ExtendCols<F> {
+    pub fn populate_flags(&mut self, i: usize) {
+        // The generator of the multiplicative subgroup.
+        let g = F::from_canonical_u32(BabyBear::two_adic_generator(4).as_canonical_u32());
+
+        // Populate the columns needed to keep track of cycles of 16 rows.
+        self.cycle_16 = g.exp_u64((i + 1) as u64);
+
+        // Populate the columns needed to track the start of a cycle of 16 rows.
+        self.cycle_16_minus_g = self.cycle_16 - g;
+        self.cycle_16_minus_g_inv = self
+            .cycle_16_minus_g
+            .try_inverse()
+            .unwrap_or_else(|| F::zero());
+        self.cycle_16_start = F::from_bool(self.cycle_16_minus_g == F::zero());
+
+        // Populate the columns needed to track the end of a cycle of 16 rows.
+        self.cycle_16_minus_one = self.cycle_16 - F::one();
+        self.cycle_16_minus_one_inv = self
+            .cycle_16_minus_one
+            .try_inverse()
+            .unwrap_or_else(|| F::zero());
+        self.cycle_16_end = F::from_bool(self.cycle_16_minus_one == F::zero());
+
+        // Populate the columns needed to keep track of cycles of 64 rows.
+        let j = 16 + (i % 64);
+        self.i = F::from_canonical_usize(j);
+        for k in 0..4 {
+            self.cycle_64[k] = F::from_bool((16 * (k + 1)..16 * (k + 2)).contains(&j));
+        }
+        self.cycle_64_start = self.cycle_64[0] * self.cycle_16_start;
+        self.cycle_64_end = self.cycle_64[3] * self.cycle_16_end;
+    }
+}
+
+impl Sha512ExtendChip {
+    pub fn eval_flags<AB: SP1AirBuilder>(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &Sha512ExtendCols<AB::Var> = main.row_slice(0).borrow();
+        let next: &Sha512ExtendCols<AB::Var> = main.row_slice(1).borrow();
+
+        let one = AB::Expr::from(AB::F::one());
+        let g = AB::F::from_canonical_u32(BabyBear::two_adic_generator(4).as_canonical_u32());
+
+        // Initialize counter variables on the first row.
+        builder.when_first_row().assert_eq(local.cycle_16, g);
+        builder.when_first_row().assert_one(local.cycle_64[0]);
+
+        // Multiply the current cycle by the generator of group with order 16.
+        builder
+            .when_transition()
+            .assert_eq(local.cycle_16 * g, next.cycle_16);
+
+        // Calculate whether it's the beginning of the cycle of 16 rows.
+        builder.assert_eq(local.cycle_16 - g, local.cycle_16_minus_g);
+        builder.assert_eq(
+            one.clone() - local.cycle_16_minus_g * local.cycle_16_minus_g_inv,
+            local.cycle_16_start,
+        );
+        builder.assert_zero(local.cycle_16_minus_g * local.cycle_16_start);
+
+        // Calculate whether it's the end of the cycle of 16 rows.
+        builder.assert_eq(local.cycle_16 - one.clone(), local.cycle_16_minus_one);
+        builder.assert_eq(
+            one.clone() - local.cycle_16_minus_one * local.cycle_16_minus_one_inv,
+            local.cycle_16_end,
+        );
+        builder.assert_zero(local.cycle_16_minus_one * local.cycle_16_end);
+
+        // Increment the indices of `cycles_64` when 16 rows have passed. Otherwise, keep them the
+        // same.
+        for i in 0..4 {
+            builder.assert_bool(local.cycle_64[i]);
+            builder
+                .when_transition()
+                .when(local.cycle_16_end)
+                .assert_eq(local.cycle_64[i], next.cycle_64[(i + 1) % 4]);
+            builder
+                .when_transition()
+                .when(one.clone() - local.cycle_16_end)
+                .assert_eq(local.cycle_64[i], next.cycle_64[i]);
+        }
+
+        // Compute whether it's the start/end of the cycle of 64 rows.
+        builder.assert_eq(
+            local.cycle_16_start * local.cycle_64[0],
+            local.cycle_64_start,
+        );
+        builder.assert_eq(local.cycle_16_end * local.cycle_64[3], local.cycle_64_end);
+
+        // Increment `i` by one. Once it reaches the end of the cycle, reset it to 16.
+        builder
+            .when_first_row()
+            .assert_eq(local.i, AB::F::from_canonical_u32(16));
+        builder
+            .when_transition()
+            .when(local.cycle_64_end)
+            .assert_eq(next.i, AB::F::from_canonical_u32(16));
+        builder
+            .when_transition()
+            .when(one.clone() - local.cycle_64_end)
+            .assert_eq(local.i + one, next.i);
+    }
+}
@@ -0,0 +1,105 @@
+mod air;
+mod columns;
+mod execute;
+mod flags;
+mod trace;
+
+pub use columns::*;
+
+use crate::runtime::{MemoryReadRecord, MemoryWriteRecord};
+use serde::{Deserialize, Serialize};
+
+/// An event for the `SHA512_EXTEND` precompile.
+///
+/// Every 64-bit word of the message schedule is stored as two consecutive 32-bit words, so each
+/// access is a pair of memory records.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sha512ExtendEvent {
+    pub shard: u32,
+    pub clk: u32,
+    pub w_ptr: u32,
+    pub w_i_minus_15_reads: Vec<[MemoryReadRecord; 2]>,
+    pub w_i_minus_2_reads: Vec<[MemoryReadRecord; 2]>,
+    pub w_i_minus_16_reads: Vec<[MemoryReadRecord; 2]>,
+    pub w_i_minus_7_reads: Vec<[MemoryReadRecord; 2]>,
+    pub w_i_writes: Vec<[MemoryWriteRecord; 2]>,
+}
+
+#[derive(Default)]
+pub struct Sha512ExtendChip;
+
+impl Sha512ExtendChip {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+pub fn sha512_extend(w: &mut [u64]) {
+    for i in 16..80 {
+        let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+        let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+}
+
+#[cfg(test)]
+pub mod extend_tests {
+
+    use crate::{
+        runtime::{Instruction, Opcode, Program, Runtime},
+        syscall::precompiles::sha512::u64_from_u32_pair,
+        utils::{run_test, setup_logger},
+    };
+
+    use super::sha512_extend;
+
+    pub fn sha512_extend_program() -> Program {
+        let w_ptr = 100;
+        let mut instructions = vec![];
+        for i in 0..160 {
+            instructions.extend(vec![
+                Instruction::new(Opcode::ADD, 29, 0, i * 0x01010101 + 5, false, true),
+                Instruction::new(Opcode::ADD, 30, 0, w_ptr + i * 4, false, true),
+                Instruction::new(Opcode::SW, 29, 30, 0, false, true),
+            ]);
+        }
+        instructions.extend(vec![
+            Instruction::new(Opcode::ADD, 5, 0, 114, false, true),
+            Instruction::new(Opcode::ADD, 10, 0, w_ptr, false, true),
+            Instruction::new(Opcode::ECALL, 10, 5, 0, false, true),
+        ]);
+        Program::new(instructions, 0, 0)
+    }
+
+    #[test]
+    fn test_sha512_extend_execute() {
+        let program = sha512_extend_program();
+        let mut runtime = Runtime::new(program);
+        runtime.run();
+
+        let mut expected = (0..80u32)
+            .map(|i| {
+                u64_from_u32_pair(
+                    (2 * i).wrapping_mul(0x01010101) + 5,
+                    (2 * i + 1).wrapping_mul(0x01010101) + 5,
+                )
+            })
+            .collect::<Vec<_>>();
+        sha512_extend(&mut expected);
+
+        let w = (0..80)
+            .map(|i| u64_from_u32_pair(runtime.word(100 + i * 8), runtime.word(104 + i * 8)))
+            .collect::<Vec<_>>();
+        assert_eq!(w, expected);
+    }
+
+    #[test]
+    fn test_sha512_extend_prove() {
+        setup_logger();
+        let program = sha512_extend_program();
+        run_test(program).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+use std::borrow::BorrowMut;
+
+use p3_field::PrimeField;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::{
+    air::MachineAir, runtime::ExecutionRecord, syscall::precompiles::sha512::u64_from_u32_pair,
+};
+
+use super::{Sha512ExtendChip, Sha512ExtendCols, NUM_SHA512_EXTEND_COLS};
+
+impl<F: PrimeField> MachineAir<F> for Sha512ExtendChip {
+    type Record = ExecutionRecord;
+
+    fn name(&self) -> String {
+        "Sha512Extend".to_string()
+    }
+
+    fn generate_trace(
+        &self,
+        input: &ExecutionRecord,
+        output: &mut ExecutionRecord,
+    ) -> RowMajorMatrix<F> {
+        let mut rows = Vec::new();
+
+        let mut new_field_events = Vec::new();
+        for event in input.sha512_extend_events.iter() {
+            for j in 0..64usize {
+                let mut row = [F::zero(); NUM_SHA512_EXTEND_COLS];
+                let cols: &mut Sha512ExtendCols<F> = row.as_mut_slice().borrow_mut();
+
+                cols.populate_flags(j);
+                cols.shard = F::from_canonical_u32(event.shard);
+                cols.clk = F::from_canonical_u32(event.clk);
+                cols.w_ptr = F::from_canonical_u32(event.w_ptr);
+
+                for k in 0..2 {
+                    cols.w_i_minus_15[k]
+                        .populate(event.w_i_minus_15_reads[j][k], &mut new_field_events);
+                    cols.w_i_minus_2[k]
+                        .populate(event.w_i_minus_2_reads[j][k], &mut new_field_events);
+                    cols.w_i_minus_16[k]
+                        .populate(event.w_i_minus_16_reads[j][k], &mut new_field_events);
+                    cols.w_i_minus_7[k]
+                        .populate(event.w_i_minus_7_reads[j][k], &mut new_field_events);
+                    cols.w_i[k].populate(event.w_i_writes[j][k], &mut new_field_events);
+                }
+
+                // Compute `s0`.
+                let [lo, hi] = event.w_i_minus_15_reads[j];
+                let w_i_minus_15 = u64_from_u32_pair(lo.value, hi.value);
+                let w_i_minus_15_rr_1 = cols.w_i_minus_15_rr_1.populate(output, w_i_minus_15, 1);
+                let w_i_minus_15_rr_8 = cols.w_i_minus_15_rr_8.populate(output, w_i_minus_15, 8);
+                let w_i_minus_15_rs_7 = cols.w_i_minus_15_rs_7.populate(output, w_i_minus_15, 7);
+                let s0_intermediate =
+                    cols.s0_intermediate
+                        .populate(output, w_i_minus_15_rr_1, w_i_minus_15_rr_8);
+                let s0 = cols.s0.populate(output, s0_intermediate, w_i_minus_15_rs_7);
+
+                // Compute `s1`.
+                let [lo, hi] = event.w_i_minus_2_reads[j];
+                let w_i_minus_2 = u64_from_u32_pair(lo.value, hi.value);
+                let w_i_minus_2_rr_19 = cols.w_i_minus_2_rr_19.populate(output, w_i_minus_2, 19);
+                let w_i_minus_2_rr_61 = cols.w_i_minus_2_rr_61.populate(output, w_i_minus_2, 61);
+                let w_i_minus_2_rs_6 = cols.w_i_minus_2_rs_6.populate(output, w_i_minus_2, 6);
+                let s1_intermediate =
+                    cols.s1_intermediate
+                        .populate(output, w_i_minus_2_rr_19, w_i_minus_2_rr_61);
+                let s1 = cols.s1.populate(output, s1_intermediate, w_i_minus_2_rs_6);
+
+                // Compute `s2`.
+                let [lo, hi] = event.w_i_minus_16_reads[j];
+                let w_i_minus_16 = u64_from_u32_pair(lo.value, hi.value);
+                let [lo, hi] = event.w_i_minus_7_reads[j];
+                let w_i_minus_7 = u64_from_u32_pair(lo.value, hi.value);
+                let s2_intermediate_0 = cols.s2_intermediate_0.populate(output, w_i_minus_16, s0);
+                let s2_intermediate_1 =
+                    cols.s2_intermediate_1
+                        .populate(output, s2_intermediate_0, w_i_minus_7);
+                cols.s2.populate(output, s2_intermediate_1, s1);
+
+                cols.is_real = F::one();
+                rows.push(row);
+            }
+        }
+
+        output.add_field_events(&new_field_events);
+
+        let nb_rows = rows.len();
+        let mut padded_nb_rows = nb_rows.next_power_of_two();
+        if padded_nb_rows == 2 || padded_nb_rows == 1 {
+            padded_nb_rows = 4;
+        }
+        for i in nb_rows..padded_nb_rows {
+            let mut row = [F::zero(); NUM_SHA512_EXTEND_COLS];
+            let cols: &mut Sha512ExtendCols<F> = row.as_mut_slice().borrow_mut();
+            cols.populate_flags(i);
+            rows.push(row);
+        }
+
+        // Convert the trace to a row major matrix.
+        RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_SHA512_EXTEND_COLS,
+        )
+    }
+
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.sha512_extend_events.len() * 64
+    }
+
+    fn included(&self, shard: &Self::Record) -> bool {
+        !shard.sha512_extend_events.is_empty()
+    }
+}
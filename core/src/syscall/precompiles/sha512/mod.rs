@@ -0,0 +1,33 @@
+mod compress;
+mod extend;
+
+pub use compress::*;
+pub use extend::*;
+
+use crate::runtime::{MemoryReadRecord, MemoryWriteRecord, SyscallContext};
+
+/// Joins the low and high words of a 64-bit SHA-512 word as it is laid out in memory.
+pub const fn u64_from_u32_pair(lo: u32, hi: u32) -> u64 {
+    (lo as u64) | ((hi as u64) << 32)
+}
+
+/// Splits a 64-bit SHA-512 word into the low and high words it is stored as in memory.
+pub const fn u64_to_u32_pair(value: u64) -> [u32; 2] {
+    [value as u32, (value >> 32) as u32]
+}
+
+/// Reads the 64-bit word at `addr` as a pair of 32-bit words.
+pub fn mr_u64(rt: &mut SyscallContext, addr: u32) -> ([MemoryReadRecord; 2], u64) {
+    let (records, values) = rt.mr_slice(addr, 2);
+    (
+        records.try_into().unwrap(),
+        u64_from_u32_pair(values[0], values[1]),
+    )
+}
+
+/// Writes the 64-bit word `value` at `addr` as a pair of 32-bit words.
+pub fn mw_u64(rt: &mut SyscallContext, addr: u32, value: u64) -> [MemoryWriteRecord; 2] {
+    rt.mw_slice(addr, &u64_to_u32_pair(value))
+        .try_into()
+        .unwrap()
+}
@@ -245,6 +245,10 @@ impl<F: PrimeField32, E: EllipticCurve + WeierstrassParameters> MachineAir<F>
         )
     }
 
+    fn num_rows(&self, input: &ExecutionRecord) -> usize {
+        input.weierstrass_double_events.len()
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
         !shard.weierstrass_double_events.is_empty()
     }
@@ -1,27 +1,45 @@
-/// Gets the number of rows which by default should be used for each chip to maximize padding.
-///
-/// Some chips, such as FieldLTU, may use a constant multiple of this value to optimize performance.
-pub fn shard_size() -> usize {
-    let value = match std::env::var("SHARD_SIZE") {
-        Ok(val) => val.parse().unwrap(),
-        Err(_) => 1 << 19,
-    };
-    assert!(value != 0 && (value & (value - 1)) == 0);
-    value
+//! Environment variables which override the default [`ProverOptions`](super::ProverOptions).
+
+/// Gets the number of rows which should be used for each chip from `SHARD_SIZE`, if it is set.
+pub fn shard_size() -> Option<usize> {
+    std::env::var("SHARD_SIZE")
+        .ok()
+        .map(|val| val.parse().expect("SHARD_SIZE must be a number"))
+}
+
+/// Gets the number of shards after which we should save the shard commits to disk from
+/// `SAVE_DISK_THRESHOLD`, if it is set.
+pub fn save_disk_threshold() -> Option<usize> {
+    std::env::var("SAVE_DISK_THRESHOLD")
+        .ok()
+        .map(|val| val.parse().expect("SAVE_DISK_THRESHOLD must be a number"))
+}
+
+/// Gets the flag for whether to recreate the shard commitments instead of saving them to disk
+/// from `RECONSTRUCT_COMMITMENTS`, if it is set.
+pub fn reconstruct_commitments() -> Option<bool> {
+    std::env::var("RECONSTRUCT_COMMITMENTS")
+        .ok()
+        .map(|val| val == "true")
+}
+
+/// Gets the number of bytes of shard data which may be held in memory from `MEMORY_BUDGET`, if it
+/// is set.
+pub fn memory_budget() -> Option<usize> {
+    std::env::var("MEMORY_BUDGET")
+        .ok()
+        .map(|val| val.parse().expect("MEMORY_BUDGET must be a number"))
 }
 
-/// Gets the number of shards after which we should save the shard commits to disk.
-pub fn save_disk_threshold() -> usize {
-    match std::env::var("SAVE_DISK_THRESHOLD") {
-        Ok(val) => val.parse().unwrap(),
-        Err(_) => 256,
-    }
+/// Gets the target number of cells committed for each shard from `MAX_SHARD_CELLS`, if it is set.
+pub fn max_shard_cells() -> Option<usize> {
+    std::env::var("MAX_SHARD_CELLS")
+        .ok()
+        .map(|val| val.parse().expect("MAX_SHARD_CELLS must be a number"))
 }
 
-/// Gets the flag for whether to recreate the shard commitments instead of saving them to disk.
-pub fn reconstruct_commitments() -> bool {
-    match std::env::var("RECONSTRUCT_COMMITMENTS") {
-        Ok(val) => val == "true",
-        Err(_) => true,
-    }
+/// Gets the directory where the shard data which exceeds the memory budget is spilled from
+/// `SPILL_DIR`, if it is set.
+pub fn spill_dir() -> Option<std::path::PathBuf> {
+    std::env::var("SPILL_DIR").ok().map(Into::into)
 }
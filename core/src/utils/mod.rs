@@ -2,19 +2,27 @@ mod buffer;
 pub mod ec;
 pub mod env;
 mod logger;
-mod poseidon2_instance;
+mod options;
+pub mod poseidon2_instance;
 mod programs;
+mod progress;
 mod prove;
 mod tracer;
 
 pub use buffer::*;
 pub use logger::*;
+pub use options::*;
+pub use progress::*;
 pub use prove::*;
 pub use tracer::*;
 
 #[cfg(test)]
 pub use programs::*;
 
+use p3_maybe_rayon::prelude::{ParallelIterator, ParallelSlice};
+
+use crate::runtime::ExecutionRecord;
+use crate::stark::MachineRecord;
 use crate::{memory::MemoryCols, operations::field::params::Limbs};
 
 pub const fn indices_arr<const N: usize>() -> [usize; N] {
@@ -60,6 +68,41 @@ pub fn limbs_from_access<T: Copy, M: MemoryCols<T>>(cols: &[M]) -> Limbs<T> {
     Limbs(sized)
 }
 
+/// Generates a row of a trace for each of `events`, in parallel chunks of events.
+///
+/// The events which the rows emit for other chips are recorded separately for each chunk, then
+/// appended to `output` in the order of the events, as a sequential generation would.
+pub fn par_generate_rows<E, R>(
+    events: &[E],
+    output: &mut ExecutionRecord,
+    event_to_row: impl Fn(&E, &mut ExecutionRecord) -> R + Sync,
+) -> Vec<R>
+where
+    E: Sync,
+    R: Send,
+{
+    let chunk_size = std::cmp::max(events.len() / num_cpus::get(), 1);
+    let rows_and_records = events
+        .par_chunks(chunk_size)
+        .map(|events| {
+            let mut record = ExecutionRecord::default();
+            record.set_index(output.index());
+            let rows = events
+                .iter()
+                .map(|event| event_to_row(event, &mut record))
+                .collect::<Vec<_>>();
+            (rows, record)
+        })
+        .collect::<Vec<_>>();
+
+    let mut rows = Vec::with_capacity(events.len());
+    for (chunk_rows, mut record) in rows_and_records {
+        rows.extend(chunk_rows);
+        output.append(&mut record);
+    }
+    rows
+}
+
 pub fn pad_rows<T: Clone, const N: usize>(rows: &mut Vec<[T; N]>, row_fn: impl Fn() -> [T; N]) {
     let nb_rows = rows.len();
     let mut padded_nb_rows = nb_rows.next_power_of_two();
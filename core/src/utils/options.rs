@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+
+use crate::runtime::ShardingConfig;
+
+use super::env;
+
+/// The default number of rows of each chip in a shard.
+pub const DEFAULT_SHARD_SIZE: usize = 1 << 19;
+
+/// The default number of shards after which the shard commits are saved to disk.
+pub const DEFAULT_SAVE_DISK_THRESHOLD: usize = 256;
+
+/// Options for the execution of a program by the [`Runtime`](crate::runtime::Runtime).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeOptions {
+    /// The number of cycles of each shard, which must be a power of two.
+    pub shard_size: usize,
+}
+
+impl Default for RuntimeOptions {
+    fn default() -> Self {
+        Self {
+            shard_size: DEFAULT_SHARD_SIZE,
+        }
+    }
+}
+
+impl RuntimeOptions {
+    /// The default options, overridden by the `SHARD_SIZE` environment variable if it is set.
+    pub fn from_env() -> Self {
+        ProverOptions::from_env().runtime_options()
+    }
+}
+
+/// A limit on the memory used by the shard data kept between the commit and the open phases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryBudget {
+    /// The number of bytes of shard data which may be held in memory.
+    pub limit: usize,
+    /// The directory where the shards which do not fit in memory are spilled, or `None` to
+    /// recompute them in the open phase instead.
+    pub spill_dir: Option<PathBuf>,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            spill_dir: None,
+        }
+    }
+
+    pub fn with_spill_dir(mut self, spill_dir: impl Into<PathBuf>) -> Self {
+        self.spill_dir = Some(spill_dir.into());
+        self
+    }
+}
+
+/// Options for proving the execution of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProverOptions {
+    /// The number of rows which by default should be used for each chip, which must be a power
+    /// of two.
+    ///
+    /// Some chips, such as FieldLTU, may use a constant multiple of this value to optimize
+    /// performance.
+    pub shard_size: usize,
+    /// The number of shards after which the shard commits are saved to disk instead of being kept
+    /// in memory.
+    pub save_disk_threshold: usize,
+    /// Whether to recreate the shard commitments when opening the shards instead of keeping them.
+    pub reconstruct_commitments: bool,
+    /// The memory budget deciding for each shard whether to keep, spill or recompute its data,
+    /// in place of `save_disk_threshold` and `reconstruct_commitments`.
+    pub memory_budget: Option<MemoryBudget>,
+    /// The target number of cells committed for each shard, to pack the events of the shards by
+    /// their cost instead of splitting them by `shard_size` alone.
+    pub max_shard_cells: Option<usize>,
+}
+
+impl Default for ProverOptions {
+    fn default() -> Self {
+        Self {
+            shard_size: DEFAULT_SHARD_SIZE,
+            save_disk_threshold: DEFAULT_SAVE_DISK_THRESHOLD,
+            reconstruct_commitments: true,
+            memory_budget: None,
+            max_shard_cells: None,
+        }
+    }
+}
+
+impl ProverOptions {
+    /// The default options, overridden by the `SHARD_SIZE`, `SAVE_DISK_THRESHOLD`,
+    /// `RECONSTRUCT_COMMITMENTS`, `MEMORY_BUDGET`, `SPILL_DIR` and `MAX_SHARD_CELLS` environment
+    /// variables when they are set. The spill directory is only used with a memory budget.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            shard_size: env::shard_size().unwrap_or(default.shard_size),
+            save_disk_threshold: env::save_disk_threshold().unwrap_or(default.save_disk_threshold),
+            reconstruct_commitments: env::reconstruct_commitments()
+                .unwrap_or(default.reconstruct_commitments),
+            memory_budget: env::memory_budget().map(|limit| MemoryBudget {
+                limit,
+                spill_dir: env::spill_dir(),
+            }),
+            max_shard_cells: env::max_shard_cells(),
+        }
+    }
+
+    pub fn with_shard_size(mut self, shard_size: usize) -> Self {
+        self.shard_size = shard_size;
+        self
+    }
+
+    pub fn with_save_disk_threshold(mut self, save_disk_threshold: usize) -> Self {
+        self.save_disk_threshold = save_disk_threshold;
+        self
+    }
+
+    pub fn with_reconstruct_commitments(mut self, reconstruct_commitments: bool) -> Self {
+        self.reconstruct_commitments = reconstruct_commitments;
+        self
+    }
+
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    pub fn with_max_shard_cells(mut self, max_shard_cells: usize) -> Self {
+        self.max_shard_cells = Some(max_shard_cells);
+        self
+    }
+
+    /// The options of the runtime executing the program to prove.
+    pub const fn runtime_options(&self) -> RuntimeOptions {
+        RuntimeOptions {
+            shard_size: self.shard_size,
+        }
+    }
+
+    /// The configuration splitting the execution record into shards, without packing: the cost
+    /// model of the packing depends on the machine, so it is added by the caller.
+    pub fn sharding_config(&self) -> ShardingConfig {
+        ShardingConfig::new(self.shard_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_are_independent() {
+        let small = ProverOptions::default().with_shard_size(1 << 10);
+        let large = ProverOptions::default().with_shard_size(1 << 20);
+        assert_eq!(small.sharding_config().shard_size(), 1 << 10);
+        assert_eq!(large.sharding_config().shard_size(), 1 << 20);
+        assert_eq!(small.runtime_options().shard_size, 1 << 10);
+        assert_eq!(
+            ProverOptions::default().sharding_config().field_len,
+            4 * DEFAULT_SHARD_SIZE
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A phase of the generation of a proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProvePhase {
+    /// The execution of the program, shard by shard.
+    Execute,
+    /// The generation and commitment of the main traces of the shards.
+    Commit,
+    /// The proofs of the shards.
+    Open,
+}
+
+impl Display for ProvePhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProvePhase::Execute => write!(f, "execute"),
+            ProvePhase::Commit => write!(f, "commit"),
+            ProvePhase::Open => write!(f, "open"),
+        }
+    }
+}
+
+/// The progress of a phase, reported each time a shard is done.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub phase: ProvePhase,
+    /// The number of shards done in this phase.
+    pub done: usize,
+    /// The number of shards of this phase, which is not known during the execution.
+    pub total: Option<usize>,
+    /// The time since the start of this phase.
+    pub elapsed: Duration,
+    /// The estimated time until the end of this phase, assuming the remaining shards take as long
+    /// as the ones done.
+    pub eta: Option<Duration>,
+    /// The memory and disk usage of the shard data so far.
+    pub memory: MemoryUsage,
+}
+
+/// The memory and disk usage of the shard data, i.e. the main traces of the shards and their
+/// commitments, which are kept, spilled to disk or recomputed between the commit and open phases.
+///
+/// The sizes in memory are estimated from the dimensions of the traces.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The number of bytes of shard data held in memory.
+    pub current_bytes: usize,
+    /// The largest number of bytes of shard data held in memory at once.
+    pub peak_bytes: usize,
+    /// The number of bytes of shard data spilled to disk.
+    pub bytes_written: u64,
+    /// The number of bytes of shard data read back from disk.
+    pub bytes_read: u64,
+    /// The number of shards kept in memory between the phases.
+    pub shards_kept: usize,
+    /// The number of shards spilled to disk between the phases.
+    pub shards_spilled: usize,
+    /// The number of shards recomputed in the open phase.
+    pub shards_recomputed: usize,
+}
+
+/// Accounts for the shard data of a proof, shared by the threads proving its shards.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryTracker {
+    current_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+    bytes_written: AtomicU64,
+    bytes_read: AtomicU64,
+    shards_kept: AtomicUsize,
+    shards_spilled: AtomicUsize,
+    shards_recomputed: AtomicUsize,
+}
+
+impl MemoryTracker {
+    /// Records that `bytes` of shard data are now held in memory.
+    pub(crate) fn allocate(&self, bytes: usize) {
+        let current = self.current_bytes.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        self.peak_bytes.fetch_max(current, Ordering::SeqCst);
+    }
+
+    /// Records that `bytes` of shard data are not held in memory anymore.
+    pub(crate) fn free(&self, bytes: usize) {
+        self.current_bytes.fetch_sub(bytes, Ordering::SeqCst);
+    }
+
+    /// Whether the shard data held in memory fits in `limit` bytes.
+    pub(crate) fn fits(&self, limit: usize) -> bool {
+        self.current_bytes.load(Ordering::SeqCst) <= limit
+    }
+
+    pub(crate) fn keep(&self) {
+        self.shards_kept.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn spill(&self, bytes_written: u64) {
+        self.bytes_written
+            .fetch_add(bytes_written, Ordering::Relaxed);
+        self.shards_spilled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn read(&self, bytes_read: u64) {
+        self.bytes_read.fetch_add(bytes_read, Ordering::Relaxed);
+    }
+
+    pub(crate) fn recompute(&self) {
+        self.shards_recomputed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            current_bytes: self.current_bytes.load(Ordering::SeqCst),
+            peak_bytes: self.peak_bytes.load(Ordering::SeqCst),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            shards_kept: self.shards_kept.load(Ordering::Relaxed),
+            shards_spilled: self.shards_spilled.load(Ordering::Relaxed),
+            shards_recomputed: self.shards_recomputed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The error returned when a proof is cancelled through its [`CancellationToken`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the proof was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// A token to cancel a proof from another thread. The clones of a token share its state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the cancellation of the proofs using this token. The provers stop at the next
+    /// shard or chip instead of finishing the proof.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns [`Cancelled`] if the cancellation was requested.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reports the progress of a proof and lets it be cancelled.
+///
+/// The default monitor reports nothing and is never cancelled.
+#[derive(Clone, Default)]
+pub struct ProgressMonitor {
+    callback: Option<Arc<dyn Fn(&Progress) + Send + Sync>>,
+    cancellation: CancellationToken,
+    memory: Arc<MemoryTracker>,
+}
+
+impl ProgressMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `callback` with each progress update. The callback may be called from several
+    /// threads at once.
+    pub fn with_callback(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sends each progress update to a channel. The updates are dropped once the receiver is.
+    pub fn with_channel(self, sender: Sender<Progress>) -> Self {
+        self.with_callback(move |progress| {
+            let _ = sender.send(progress.clone());
+        })
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Returns [`Cancelled`] if the cancellation was requested.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        self.cancellation.check()
+    }
+
+    /// The memory and disk usage of the shard data of the proofs monitored so far.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.memory.usage()
+    }
+
+    pub(crate) fn memory(&self) -> &MemoryTracker {
+        &self.memory
+    }
+
+    /// Starts to track a phase of `total` shards.
+    pub fn start(&self, phase: ProvePhase, total: Option<usize>) -> PhaseProgress {
+        let progress = PhaseProgress {
+            monitor: self.clone(),
+            phase,
+            total,
+            start: Instant::now(),
+            done: AtomicUsize::new(0),
+        };
+        progress.report(0);
+        progress
+    }
+}
+
+impl std::fmt::Debug for ProgressMonitor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressMonitor")
+            .field("callback", &self.callback.is_some())
+            .field("cancellation", &self.cancellation)
+            .field("memory", &self.memory)
+            .finish()
+    }
+}
+
+/// The progress of a phase, shared by the threads proving its shards.
+#[derive(Debug)]
+pub struct PhaseProgress {
+    monitor: ProgressMonitor,
+    phase: ProvePhase,
+    total: Option<usize>,
+    start: Instant,
+    done: AtomicUsize,
+}
+
+impl PhaseProgress {
+    /// Records that a shard is done, and returns the number of shards done.
+    pub fn advance(&self) -> usize {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.report(done);
+        done
+    }
+
+    fn report(&self, done: usize) {
+        let Some(callback) = self.monitor.callback.as_ref() else {
+            return;
+        };
+        let elapsed = self.start.elapsed();
+        let eta = match self.total {
+            Some(total) if done > 0 => {
+                Some(elapsed.mul_f64(total.saturating_sub(done) as f64 / done as f64))
+            }
+            _ => None,
+        };
+        callback(&Progress {
+            phase: self.phase,
+            done,
+            total: self.total,
+            elapsed,
+            eta,
+            memory: self.monitor.memory.usage(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_progress_monitor() {
+        let (sender, receiver) = channel();
+        let token = CancellationToken::new();
+        let monitor = ProgressMonitor::new()
+            .with_channel(sender)
+            .with_cancellation(token.clone());
+
+        let phase = monitor.start(ProvePhase::Commit, Some(2));
+        assert_eq!(phase.advance(), 1);
+        assert_eq!(phase.advance(), 2);
+        let updates = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(
+            updates.iter().map(|p| p.done).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert!(updates.iter().all(|p| p.phase == ProvePhase::Commit));
+        assert_eq!(updates[0].eta, None);
+        assert_eq!(updates[2].eta, Some(Duration::ZERO));
+
+        assert_eq!(monitor.check(), Ok(()));
+        token.cancel();
+        assert_eq!(monitor.check(), Err(Cancelled));
+    }
+}
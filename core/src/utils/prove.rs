@@ -2,10 +2,11 @@ use std::time::Instant;
 
 use crate::stark::RiscvAir;
 use crate::utils::poseidon2_instance::RC_16_30;
+use crate::utils::{ProgressMonitor, ProverOptions};
 use crate::{
     runtime::{Program, Runtime},
     stark::StarkGenericConfig,
-    stark::{LocalProver, OpeningProof, ShardMainData},
+    stark::{LocalProver, OpeningProof, ProverError, ShardCostModel, ShardMainData, ShardPacking},
 };
 pub use baby_bear_blake3::BabyBearBlake3;
 use p3_commit::Pcs;
@@ -33,13 +34,14 @@ pub fn get_cycles(program: Program) -> u64 {
 }
 
 pub fn prove(program: Program) -> crate::stark::Proof<BabyBearBlake3> {
+    let options = ProverOptions::from_env();
     let runtime = tracing::info_span!("runtime.run(...)").in_scope(|| {
-        let mut runtime = Runtime::new(program);
+        let mut runtime = Runtime::with_options(program, options.runtime_options());
         runtime.run();
         runtime
     });
     let config = BabyBearBlake3::new();
-    prove_core(config, runtime)
+    prove_core(config, runtime, &options)
 }
 
 #[cfg(test)]
@@ -97,7 +99,27 @@ pub fn prove_elf(elf: &[u8]) -> crate::stark::Proof<BabyBearBlake3> {
 pub fn prove_core<SC: StarkGenericConfig + StarkUtils + Send + Sync + Serialize>(
     config: SC,
     runtime: Runtime,
+    options: &ProverOptions,
 ) -> crate::stark::Proof<SC>
+where
+    SC::Challenger: Clone,
+    OpeningProof<SC>: Send + Sync,
+    <SC::Pcs as Pcs<SC::Val, RowMajorMatrix<SC::Val>>>::Commitment: Send + Sync,
+    <SC::Pcs as Pcs<SC::Val, RowMajorMatrix<SC::Val>>>::ProverData: Send + Sync,
+    ShardMainData<SC>: Serialize + DeserializeOwned,
+    <SC as StarkGenericConfig>::Val: PrimeField32,
+{
+    prove_core_with_monitor(config, runtime, options, &ProgressMonitor::default())
+        .expect("a proof without a cancellation token cannot be cancelled")
+}
+
+/// Proves an executed runtime, reporting the progress to `monitor`, which may cancel the proof.
+pub fn prove_core_with_monitor<SC: StarkGenericConfig + StarkUtils + Send + Sync + Serialize>(
+    config: SC,
+    runtime: Runtime,
+    options: &ProverOptions,
+    monitor: &ProgressMonitor,
+) -> Result<crate::stark::Proof<SC>, ProverError>
 where
     SC::Challenger: Clone,
     OpeningProof<SC>: Send + Sync,
@@ -112,11 +134,24 @@ where
 
     let machine = RiscvAir::machine(config);
     let (pk, _) = machine.setup(runtime.program.as_ref());
+    let mut sharding_config = options.sharding_config();
+    if let Some(max_cells) = options.max_shard_cells {
+        let packing = ShardPacking::new(ShardCostModel::new(&machine), max_cells);
+        sharding_config = sharding_config.with_packing(packing);
+    }
 
     // Prove the program.
     let cycles = runtime.state.global_clk;
-    let proof = tracing::info_span!("prove")
-        .in_scope(|| machine.prove::<LocalProver<_, _>>(&pk, runtime.record, &mut challenger));
+    let proof = tracing::info_span!("prove").in_scope(|| {
+        machine.prove_with_options(
+            &LocalProver::with_options(options.clone()),
+            &pk,
+            runtime.record,
+            &mut challenger,
+            &sharding_config,
+            monitor,
+        )
+    })?;
     let time = start.elapsed().as_millis();
     let nb_bytes = bincode::serialize(&proof).unwrap().len();
 
@@ -128,7 +163,7 @@ where
         Size::from_bytes(nb_bytes),
     );
 
-    proof
+    Ok(proof)
 }
 
 pub fn uni_stark_prove<SC, A>(
@@ -0,0 +1,89 @@
+use std::io::{self, BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+
+use sp1_core::runtime::{Program, Runtime};
+use sp1_core::stark::{DistributedProver, RiscvAir, WorkerAddress};
+use sp1_core::utils::{setup_logger, BabyBearBlake3, ProgressMonitor, ProverOptions, StarkUtils};
+
+const FIBONACCI_ELF_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../examples/fibonacci/program/elf/riscv32im-succinct-zkvm-elf"
+);
+
+/// A worker process, killed when dropped.
+struct Worker {
+    process: Child,
+    address: WorkerAddress,
+}
+
+impl Worker {
+    /// Starts the `sp1-worker` binary on a free port, and waits for it to listen.
+    fn spawn() -> Self {
+        let mut process = Command::new(env!("CARGO_BIN_EXE_sp1-worker"))
+            .args(["--listen", "127.0.0.1:0", "--elf", FIBONACCI_ELF_PATH])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // The address is printed once the worker listens, after the logs of its setup if any.
+        let mut stdout = BufReader::new(process.stdout.take().unwrap());
+        let address = loop {
+            let mut line = String::new();
+            assert!(
+                stdout.read_line(&mut line).unwrap() > 0,
+                "the worker exited"
+            );
+            if let Ok(address) = line.trim().parse::<WorkerAddress>() {
+                break address;
+            }
+        };
+        // Keep reading the logs, so that the worker does not block on a full pipe.
+        std::thread::spawn(move || io::copy(&mut stdout, &mut io::sink()));
+
+        Self { process, address }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+#[test]
+fn test_distributed_prover_with_worker_processes() {
+    setup_logger();
+    let options = ProverOptions::default().with_shard_size(1 << 12);
+    let mut runtime = Runtime::with_options(
+        Program::from(std::fs::read(FIBONACCI_ELF_PATH).unwrap().as_slice()),
+        options.runtime_options(),
+    );
+    runtime.run();
+
+    let workers = [Worker::spawn(), Worker::spawn()];
+    let prover = DistributedProver::new(
+        workers
+            .iter()
+            .map(|worker| worker.address.clone())
+            .collect(),
+    );
+
+    let machine = RiscvAir::machine(BabyBearBlake3::new());
+    let (pk, vk) = machine.setup(runtime.program.as_ref());
+    let mut challenger = machine.config().challenger();
+    let proof = machine
+        .prove_with_options(
+            &prover,
+            &pk,
+            runtime.record,
+            &mut challenger,
+            &options.sharding_config(),
+            &ProgressMonitor::default(),
+        )
+        .unwrap();
+    assert!(proof.shard_proofs.len() > 1);
+
+    let mut challenger = machine.config().challenger();
+    machine.verify(&vk, &proof, &mut challenger).unwrap();
+}
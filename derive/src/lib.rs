@@ -155,6 +155,13 @@ pub fn machine_air_derive(input: TokenStream) -> TokenStream {
                 }
             });
 
+            let num_rows_arms = variants.iter().map(|(variant_name, field)| {
+                let field_ty = &field.ty;
+                quote! {
+                    #name::#variant_name(x) => <#field_ty as #sp1_core_path::air::MachineAir<F>>::num_rows(x, input)
+                }
+            });
+
             let generate_dependencies_arms = variants.iter().map(|(variant_name, field)| {
                 let field_ty = &field.ty;
                 quote! {
@@ -162,6 +169,13 @@ pub fn machine_air_derive(input: TokenStream) -> TokenStream {
                 }
             });
 
+            let dependency_phase_arms = variants.iter().map(|(variant_name, field)| {
+                let field_ty = &field.ty;
+                quote! {
+                    #name::#variant_name(x) => <#field_ty as #sp1_core_path::air::MachineAir<F>>::dependency_phase(x)
+                }
+            });
+
             let included_arms = variants.iter().map(|(variant_name, field)| {
                 let field_ty = &field.ty;
                 quote! {
@@ -187,7 +201,7 @@ pub fn machine_air_derive(input: TokenStream) -> TokenStream {
 
                     fn generate_preprocessed_trace(
                         &self,
-                        program: &#sp1_core_path::runtime::Program,
+                        program: &<#execution_record_path as #sp1_core_path::stark::MachineRecord>::Program,
                     ) -> Option<p3_matrix::dense::RowMajorMatrix<F>> {
                         match self {
                             #(#generate_preprocessed_trace_arms,)*
@@ -204,6 +218,12 @@ pub fn machine_air_derive(input: TokenStream) -> TokenStream {
                         }
                     }
 
+                    fn num_rows(&self, input: &#execution_record_path) -> usize {
+                        match self {
+                            #(#num_rows_arms,)*
+                        }
+                    }
+
                     fn generate_dependencies(
                         &self,
                         input: &#execution_record_path,
@@ -214,6 +234,12 @@ pub fn machine_air_derive(input: TokenStream) -> TokenStream {
                         }
                     }
 
+                    fn dependency_phase(&self) -> usize {
+                        match self {
+                            #(#dependency_phase_arms,)*
+                        }
+                    }
+
                     fn included(&self, shard: &Self::Record) -> bool {
                         match self {
                             #(#included_arms,)*
@@ -229,12 +255,13 @@ pub fn machine_air_derive(input: TokenStream) -> TokenStream {
                 }
             });
 
-            // Attach an extra generic AB : crate::air::SP1AirBuilder to the generics of the enum
+            // Attach an extra generic AB : crate::air::SP1AirBuilder + p3_air::PairBuilder to the
+            // generics of the enum, so that the chips can read their preprocessed columns.
             let generics = &ast.generics;
             let mut new_generics = generics.clone();
-            new_generics
-                .params
-                .push(syn::parse_quote! { AB: #sp1_core_path::air::SP1AirBuilder<F = F> });
+            new_generics.params.push(
+                syn::parse_quote! { AB: #sp1_core_path::air::SP1AirBuilder<F = F> + p3_air::PairBuilder },
+            );
 
             let (air_impl_generics, _, _) = new_generics.split_for_impl();
 
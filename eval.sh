@@ -1,15 +1,20 @@
 #!/bin/bash
 set -e
 
-declare -a programs=("fibonacci" "ssz-withdrawals" "tendermint")
+declare -a programs=("fibonacci" "ssz-withdrawals" "tendermint-benchmark")
 declare -a hash_functions=("poseidon" "blake3" "keccak256")
 declare -a shard_sizes=("262144" "524288" "1048576" "2097152" "4194304")
 declare -i runs=5
 
 root_directory=$(pwd)
 
-benchmark_path="${root_directory}/benchmark.csv"
+# The reports are written to `benchmarks/<hashfn>-<shard_size>.json`. If a baseline directory is
+# given as the first argument, each report is compared against the one with the same name in it.
+benchmark_directory="${root_directory}/benchmarks"
+baseline_directory="$1"
+mkdir -p "$benchmark_directory"
 
+program_args=()
 for program in "${programs[@]}"; do
     echo "Processing program: $program"
 
@@ -24,23 +29,30 @@ for program in "${programs[@]}"; do
     if ! RUSTFLAGS="-C passes=loweratomic -C link-arg=-Ttext=0x00200800 -C panic=abort" \
         CARGO_NET_GIT_FETCH_WITH_CLI=true \
         cargo prove build; then
-        echo "Failed to build $program, skipping..."
-        continue
+        echo "Failed to build $program, using the prebuilt ELF"
     fi
 
-    echo "Building $program done"
-    elf_path="${program_directory}/elf/riscv32im-succinct-zkvm-elf"
-
-    cd "${root_directory}/eval"
-    for hash_fn in "${hash_functions[@]}"; do
-        for shard_size in "${shard_sizes[@]}"; do
-            echo "Running $program with hash function $hash_fn and shard size $shard_size, $runs times"
-            if ! CARGO_NET_GIT_FETCH_WITH_CLI=true RUSTFLAGS='-C target-cpu=native' cargo run -p sp1-eval --release -- \
-                --program $program --hashfn $hash_fn --shard-size $shard_size --benchmark-path "$benchmark_path" --elf-path "$elf_path" --runs $runs; then
-                echo "Error running evaluation for $program with hash function $hash_fn and shard size $shard_size"
-            fi
-        done
+    program_args+=("--program" "$program=${program_directory}/elf/riscv32im-succinct-zkvm-elf")
+    cd "$root_directory"
+done
+
+status=0
+for hash_fn in "${hash_functions[@]}"; do
+    for shard_size in "${shard_sizes[@]}"; do
+        report="${benchmark_directory}/${hash_fn}-${shard_size}.json"
+        baseline_args=()
+        if [ -n "$baseline_directory" ]; then
+            baseline_args=("--baseline" "${baseline_directory}/${hash_fn}-${shard_size}.json")
+        fi
+
+        echo "Running with hash function $hash_fn and shard size $shard_size, $runs times"
+        if ! RUSTFLAGS='-C target-cpu=native' cargo run -p sp1-eval --release -- run \
+            "${program_args[@]}" --hashfn $hash_fn --shard-size $shard_size --runs $runs \
+            --output "$report" "${baseline_args[@]}"; then
+            echo "Error or regression with hash function $hash_fn and shard size $shard_size"
+            status=1
+        fi
     done
+done
 
-    cd "$root_directory"
-done
\ No newline at end of file
+exit $status
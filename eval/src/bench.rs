@@ -0,0 +1,195 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sp1_core::runtime::{Program, Runtime};
+use sp1_core::stark::{
+    Com, LocalProver, MachineStark, OpeningProof, PcsProverData, RiscvAir, ShardCostModel,
+    ShardMainData, ShardPacking, StarkGenericConfig,
+};
+use sp1_core::utils::{Progress, ProgressMonitor, ProvePhase, ProverOptions, StarkUtils};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::report::{ChipCells, PhaseTimings, ProgramReport};
+
+/// The measurements of a proof of a program.
+pub struct Measurement {
+    pub cycles: u64,
+    pub shards: usize,
+    pub proof_size: usize,
+    pub phases: PhaseTimings,
+    pub chips: Vec<ChipCells>,
+}
+
+/// Proves `program` `runs` times, and reports the median duration of each phase.
+pub fn bench_program<SC>(
+    name: &str,
+    config: SC,
+    program: &Program,
+    options: &ProverOptions,
+    runs: usize,
+) -> ProgramReport
+where
+    SC: StarkGenericConfig + StarkUtils + Clone + Send + Sync + Serialize,
+    SC::Challenger: Clone,
+    Com<SC>: Send + Sync,
+    PcsProverData<SC>: Send + Sync,
+    OpeningProof<SC>: Send + Sync,
+    ShardMainData<SC>: Serialize + DeserializeOwned,
+{
+    if !reset_peak_rss() {
+        eprintln!("Failed to reset the peak RSS, it includes the programs proven before {name}");
+    }
+    let measurements = (0..runs.max(1))
+        .map(|run| {
+            let measurement = measure(config.clone(), program, options);
+            println!(
+                "{}: run {}/{}: {} cycles, {} shards, {:?}",
+                name,
+                run + 1,
+                runs.max(1),
+                measurement.cycles,
+                measurement.shards,
+                measurement.phases
+            );
+            measurement
+        })
+        .collect::<Vec<_>>();
+    let peak_rss = peak_rss();
+
+    let timings = measurements.iter().map(|m| m.phases).collect::<Vec<_>>();
+    let Measurement {
+        cycles,
+        shards,
+        proof_size,
+        chips,
+        ..
+    } = measurements.into_iter().next().unwrap();
+    ProgramReport {
+        program: name.to_string(),
+        cycles,
+        shards,
+        proof_size,
+        phases: PhaseTimings::median(&timings),
+        chips,
+        peak_rss,
+    }
+}
+
+/// Proves `program`, timing each phase of the proof from the progress reported by the prover.
+///
+/// The sharding is timed until the commit phase starts, and the commit and open phases are timed
+/// by the prover, the shards of a phase being proven in parallel.
+pub fn measure<SC>(config: SC, program: &Program, options: &ProverOptions) -> Measurement
+where
+    SC: StarkGenericConfig + StarkUtils + Send + Sync + Serialize,
+    SC::Challenger: Clone,
+    Com<SC>: Send + Sync,
+    PcsProverData<SC>: Send + Sync,
+    OpeningProof<SC>: Send + Sync,
+    ShardMainData<SC>: Serialize + DeserializeOwned,
+{
+    let mut phases = PhaseTimings::default();
+
+    // Execute the program.
+    let start = Instant::now();
+    let mut runtime = Runtime::with_options(program.clone(), options.runtime_options());
+    runtime.run();
+    phases.execute = start.elapsed().as_secs_f64();
+    let cycles = runtime.state.global_clk as u64;
+
+    let machine: MachineStark<SC, RiscvAir<SC::Val>> = RiscvAir::machine(config);
+    let (pk, vk) = machine.setup(runtime.program.as_ref());
+    let mut sharding_config = options.sharding_config();
+    if let Some(max_cells) = options.max_shard_cells {
+        let packing = ShardPacking::new(ShardCostModel::new(&machine), max_cells);
+        sharding_config = sharding_config.with_packing(packing);
+    }
+
+    // Prove the record, recording the duration of each phase as reported by the prover.
+    let timings = Arc::new(Mutex::new(phases));
+    let start = Instant::now();
+    let monitor = ProgressMonitor::new().with_callback({
+        let timings = timings.clone();
+        move |progress: &Progress| {
+            let mut timings = timings.lock().unwrap();
+            let elapsed = progress.elapsed.as_secs_f64();
+            match progress.phase {
+                ProvePhase::Execute => {}
+                ProvePhase::Commit => {
+                    if progress.done == 0 {
+                        timings.trace = start
+                            .elapsed()
+                            .saturating_sub(progress.elapsed)
+                            .as_secs_f64();
+                    }
+                    timings.commit = timings.commit.max(elapsed);
+                }
+                ProvePhase::Open => timings.open = timings.open.max(elapsed),
+            }
+        }
+    });
+    let proof = machine
+        .prove_with_options(
+            &LocalProver::with_options(options.clone()),
+            &pk,
+            runtime.record,
+            &mut machine.config().challenger(),
+            &sharding_config,
+            &monitor,
+        )
+        .expect("the proof is not cancelled");
+    let mut phases = *timings.lock().unwrap();
+
+    // Verify the proof.
+    let start = Instant::now();
+    let mut challenger = machine.config().challenger();
+    machine
+        .verify(&vk, &proof, &mut challenger)
+        .expect("failed to verify the proof");
+    phases.verify = start.elapsed().as_secs_f64();
+
+    let mut chips = BTreeMap::<String, ChipCells>::new();
+    for shard_proof in proof.shard_proofs.iter() {
+        let dimensions = shard_proof.main_dimensions();
+        for (chip, (width, height)) in shard_proof.chip_ids.iter().zip(dimensions) {
+            let cells = chips.entry(chip.clone()).or_insert_with(|| ChipCells {
+                chip: chip.clone(),
+                width,
+                rows: 0,
+                cells: 0,
+            });
+            cells.rows += height as u64;
+            cells.cells += (width * height) as u64;
+        }
+    }
+
+    Measurement {
+        cycles,
+        shards: proof.shard_proofs.len(),
+        proof_size: bincode::serialize(&proof).unwrap().len(),
+        phases,
+        chips: chips.into_values().collect(),
+    }
+}
+
+/// Resets the peak resident set size of the process, so that it only accounts for what is proven
+/// from now on. Returns false if the kernel does not allow it.
+fn reset_peak_rss() -> bool {
+    fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+/// The peak resident set size of the process in bytes, read from `/proc/self/status`.
+fn peak_rss() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
@@ -1,18 +1,29 @@
-use clap::{command, Parser};
-use csv::WriterBuilder;
-use serde::Serialize;
-use sp1_core::runtime::{Program, Runtime};
-use sp1_core::utils::{get_cycles, prove_core, BabyBearBlake3, BabyBearKeccak, BabyBearPoseidon2};
-use sp1_core::{SP1ProofWithIO, SP1Stdin, SP1Stdout, SP1Verifier};
+mod bench;
+mod report;
+
+use clap::{command, Parser, Subcommand};
+use sp1_core::runtime::Program;
+use sp1_core::utils::{BabyBearBlake3, BabyBearKeccak, BabyBearPoseidon2, ProverOptions};
 use std::fmt;
-use std::fs::OpenOptions;
-use std::io;
-use std::{fs, time::Instant};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::bench::bench_program;
+use crate::report::{BenchmarkReport, Comparison, Thresholds};
+
+/// The examples benchmarked by default, which read no input.
+const DEFAULT_PROGRAMS: [&str; 6] = [
+    "fibonacci",
+    "cycle-tracking",
+    "ed25519",
+    "rsa",
+    "ssz-withdrawals",
+    "tendermint-benchmark",
+];
 
 /// An identifier used to select the hash function to evaluate.
-#[derive(clap::ValueEnum, Clone)]
+#[derive(clap::ValueEnum, Clone, Copy)]
 enum HashFnId {
-    Sha256,
     Poseidon,
     Blake3,
     Keccak256,
@@ -21,7 +32,6 @@ enum HashFnId {
 impl fmt::Display for HashFnId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let hash_fn_str = match self {
-            HashFnId::Sha256 => "sha-256",
             HashFnId::Poseidon => "poseidon",
             HashFnId::Blake3 => "blake3",
             HashFnId::Keccak256 => "keccak256",
@@ -30,203 +40,205 @@ impl fmt::Display for HashFnId {
     }
 }
 
-/// The performance report of a zkVM on a program.
-#[derive(Debug, Serialize)]
-pub struct PerformanceReport {
-    /// The program that is being evaluated.
-    pub program: String,
+#[derive(Parser)]
+#[command(about = "Benchmark the phases of the proofs of the zkVM on a set of programs.")]
+struct EvalArgs {
+    #[command(subcommand)]
+    command: Command,
+}
 
-    /// The hash function that is being evaluated.
-    pub hashfn: String,
+#[derive(Subcommand)]
+enum Command {
+    /// Prove the programs and write the measurements to a JSON report.
+    Run(RunArgs),
+    /// Compare a JSON report against a baseline, and fail if a metric regressed.
+    Compare(CompareArgs),
+}
 
-    /// The shard size that is being evaluated.
-    pub shard_size: u64,
+#[derive(Parser)]
+struct RunArgs {
+    /// The programs to benchmark, as `name=path/to/elf`. Defaults to the examples which read no
+    /// input, whose ELFs are taken from `--examples-dir`.
+    #[arg(long = "program", value_parser = parse_program)]
+    pub programs: Vec<(String, PathBuf)>,
 
-    /// The reported number of cycles.
-    pub cycles: u64,
+    /// The directory of the examples, used when no program is given.
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples"))]
+    pub examples_dir: PathBuf,
 
-    /// The reported speed in cycles per second.
-    pub speed: f64,
+    #[arg(long, value_enum, default_value_t = HashFnId::Poseidon)]
+    pub hashfn: HashFnId,
 
-    /// The reported duration of the execution in seconds.
-    pub execution_duration: f64,
+    /// The number of cycles per shard. Defaults to the shard size of the prover options.
+    #[arg(long)]
+    pub shard_size: Option<usize>,
 
-    /// The reported duration of the prover in seconds.
-    pub prove_duration: f64,
+    /// The number of times each program is proven. The median of each phase is reported.
+    #[arg(long, default_value_t = 1)]
+    pub runs: usize,
 
-    /// The reported duration of the verifier in seconds.
-    pub verify_duration: f64,
-}
+    /// The path of the JSON report.
+    #[arg(long, default_value = "benchmark.json")]
+    pub output: PathBuf,
 
-#[derive(Parser, Clone)]
-#[command(about = "Evaluate the performance of a zkVM on a program.")]
-struct EvalArgs {
+    /// A baseline to compare the report against once the programs are proven.
     #[arg(long)]
-    pub program: String,
+    pub baseline: Option<PathBuf>,
 
-    #[arg(long)]
-    pub hashfn: HashFnId,
+    #[command(flatten)]
+    pub thresholds: ThresholdArgs,
+}
 
-    #[arg(long)]
-    pub shard_size: u64,
+#[derive(Parser)]
+struct CompareArgs {
+    /// The JSON report of the baseline.
+    pub baseline: PathBuf,
 
-    #[arg(long)]
-    pub benchmark_path: String,
+    /// The JSON report to compare against the baseline.
+    pub current: PathBuf,
 
-    #[arg(long)]
-    pub elf_path: String,
+    #[command(flatten)]
+    pub thresholds: ThresholdArgs,
+}
 
-    #[arg(long, default_value_t = 1)]
-    pub runs: usize,
+#[derive(Parser)]
+struct ThresholdArgs {
+    /// The relative increase of the duration of a phase above which it is a regression.
+    #[arg(long, default_value_t = Thresholds::default().time)]
+    pub time_threshold: f64,
+
+    /// The duration in seconds under which the changes of a phase are ignored.
+    #[arg(long, default_value_t = Thresholds::default().min_time)]
+    pub min_time: f64,
+
+    /// The relative increase of the cells of a chip above which it is a regression.
+    #[arg(long, default_value_t = Thresholds::default().cells)]
+    pub cells_threshold: f64,
+
+    /// The relative increase of the peak RSS above which it is a regression.
+    #[arg(long, default_value_t = Thresholds::default().rss)]
+    pub rss_threshold: f64,
 }
 
-fn main() {
-    let args = EvalArgs::parse();
+impl From<&ThresholdArgs> for Thresholds {
+    fn from(args: &ThresholdArgs) -> Self {
+        Self {
+            time: args.time_threshold,
+            min_time: args.min_time,
+            cells: args.cells_threshold,
+            rss: args.rss_threshold,
+        }
+    }
+}
 
-    // Load the program.
-    let elf_path = &args.elf_path;
-    let program = Program::from_elf(elf_path);
-    let cycles = get_cycles(program.clone());
-
-    // Initialize total duration counters.
-    let mut total_execution_duration = 0f64;
-    let mut total_prove_duration = 0f64;
-    let mut total_verify_duration = 0f64;
-
-    // Perform runs.
-    for _ in 0..args.runs {
-        let elf = fs::read(elf_path).expect("Failed to read ELF file");
-        let (execution_duration, prove_duration, verify_duration) =
-            run_evaluation(&args.hashfn, &program, &elf);
-
-        // Accumulate durations.
-        total_execution_duration += execution_duration;
-        total_prove_duration += prove_duration;
-        total_verify_duration += verify_duration;
+fn parse_program(arg: &str) -> Result<(String, PathBuf), String> {
+    let (name, path) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected `name=path/to/elf`, got `{}`", arg))?;
+    Ok((name.to_string(), PathBuf::from(path)))
+}
+
+fn main() -> ExitCode {
+    let args = EvalArgs::parse();
+    let result = match args.command {
+        Command::Run(args) => run(args),
+        Command::Compare(args) => BenchmarkReport::read(&args.baseline)
+            .and_then(|baseline| Ok((baseline, BenchmarkReport::read(&args.current)?)))
+            .map_err(|e| format!("Failed to read the reports: {}", e))
+            .and_then(|(baseline, current)| {
+                compare(&baseline, &current, &(&args.thresholds).into())
+            }),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
     }
+}
 
-    // Calculate average durations.
-    let avg_execution_duration = total_execution_duration / args.runs as f64;
-    let avg_prove_duration = total_prove_duration / args.runs as f64;
-    let avg_verify_duration = total_verify_duration / args.runs as f64;
+/// Benchmarks the programs, and returns whether there is no regression against the baseline.
+fn run(args: RunArgs) -> Result<bool, String> {
+    let programs = if args.programs.is_empty() {
+        DEFAULT_PROGRAMS
+            .iter()
+            .map(|name| (name.to_string(), example_elf(&args.examples_dir, name)))
+            .collect()
+    } else {
+        args.programs
+    };
 
-    let report = PerformanceReport {
-        program: args.program,
+    let mut options = ProverOptions::from_env();
+    if let Some(shard_size) = args.shard_size {
+        options = options.with_shard_size(shard_size);
+    }
+
+    let mut report = BenchmarkReport {
         hashfn: args.hashfn.to_string(),
-        shard_size: args.shard_size,
-        cycles,
-        speed: cycles as f64 / avg_prove_duration,
-        execution_duration: avg_execution_duration,
-        prove_duration: avg_prove_duration,
-        verify_duration: avg_verify_duration,
+        shard_size: options.shard_size,
+        runs: args.runs,
+        programs: Vec::new(),
     };
+    for (name, elf_path) in programs {
+        let elf = std::fs::read(&elf_path)
+            .map_err(|e| format!("Failed to read {}: {}", elf_path.display(), e))?;
+        let program = Program::from(elf.as_slice());
+        let program_report = match args.hashfn {
+            HashFnId::Poseidon => bench_program(
+                &name,
+                BabyBearPoseidon2::new(),
+                &program,
+                &options,
+                args.runs,
+            ),
+            HashFnId::Blake3 => {
+                bench_program(&name, BabyBearBlake3::new(), &program, &options, args.runs)
+            }
+            HashFnId::Keccak256 => {
+                bench_program(&name, BabyBearKeccak::new(), &program, &options, args.runs)
+            }
+        };
+        report.programs.push(program_report);
 
-    // Write the report.
-    if let Err(e) = write_report(report, &args.benchmark_path) {
-        eprintln!("Failed to write report: {}", e);
+        // Write the report after each program, so that a failure keeps the measurements so far.
+        report
+            .write(&args.output)
+            .map_err(|e| format!("Failed to write the report: {}", e))?;
     }
-}
+    println!("Wrote the report to {}", args.output.display());
 
-fn run_evaluation(hashfn: &HashFnId, program: &Program, elf: &[u8]) -> (f64, f64, f64) {
-    match hashfn {
-        HashFnId::Blake3 => {
-            let mut runtime = Runtime::new(program.clone());
-            let execution_start = Instant::now();
-            runtime.run();
-            let execution_duration = execution_start.elapsed().as_secs_f64();
-
-            let config = BabyBearBlake3::new();
-            let prove_start = Instant::now();
-            let proof = prove_core(config.clone(), runtime);
-            let prove_duration = prove_start.elapsed().as_secs_f64();
-            let proof = SP1ProofWithIO {
-                stdin: SP1Stdin::new(),
-                stdout: SP1Stdout::new(),
-                proof,
-            };
-
-            let verify_start = Instant::now();
-            SP1Verifier::verify_with_config(elf, &proof, config).unwrap();
-            let verify_duration = verify_start.elapsed().as_secs_f64();
-
-            (execution_duration, prove_duration, verify_duration)
-        }
-        HashFnId::Poseidon => {
-            let mut runtime = Runtime::new(program.clone());
-            let execution_start = Instant::now();
-            runtime.run();
-            let execution_duration = execution_start.elapsed().as_secs_f64();
-
-            let config = BabyBearPoseidon2::new();
-            let prove_start = Instant::now();
-            let proof = prove_core(config.clone(), runtime);
-            let prove_duration = prove_start.elapsed().as_secs_f64();
-            let proof = SP1ProofWithIO {
-                stdin: SP1Stdin::new(),
-                stdout: SP1Stdout::new(),
-                proof,
-            };
-
-            let verify_start = Instant::now();
-            SP1Verifier::verify_with_config(elf, &proof, config).unwrap();
-            let verify_duration = verify_start.elapsed().as_secs_f64();
-
-            (execution_duration, prove_duration, verify_duration)
-        }
-        HashFnId::Keccak256 => {
-            let mut runtime = Runtime::new(program.clone());
-            let execution_start = Instant::now();
-            runtime.run();
-            let execution_duration = execution_start.elapsed().as_secs_f64();
-
-            let config = BabyBearKeccak::new();
-            let prove_start = Instant::now();
-            let proof = prove_core(config.clone(), runtime);
-            let prove_duration = prove_start.elapsed().as_secs_f64();
-            let proof = SP1ProofWithIO {
-                stdin: SP1Stdin::new(),
-                stdout: SP1Stdout::new(),
-                proof,
-            };
-
-            let verify_start = Instant::now();
-            SP1Verifier::verify_with_config(elf, &proof, config).unwrap();
-            let verify_duration = verify_start.elapsed().as_secs_f64();
-
-            (execution_duration, prove_duration, verify_duration)
+    match args.baseline {
+        Some(path) => {
+            let baseline = BenchmarkReport::read(&path)
+                .map_err(|e| format!("Failed to read the baseline: {}", e))?;
+            compare(&baseline, &report, &(&args.thresholds).into())
         }
-        _ => panic!("Unsupported hash function"),
+        None => Ok(true),
     }
 }
 
-fn write_report(report: PerformanceReport, benchmark_path: &str) -> io::Result<()> {
-    // Check if the file exists and is empty to determine if the header needs to be written.
-    let write_header = fs::metadata(benchmark_path).map_or(true, |meta| meta.len() == 0);
-
-    let file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(benchmark_path)?;
-
-    let mut writer = WriterBuilder::new().has_headers(false).from_writer(file);
-
-    // Manually write the header if needed.
-    if write_header {
-        writer.write_record([
-            "program",
-            "hashfn",
-            "shard_size",
-            "cycles",
-            "speed",
-            "execution_duration",
-            "prove_duration",
-            "verify_duration",
-        ])?;
+/// Prints the comparison of `current` against `baseline`, and returns whether there is no
+/// regression.
+fn compare(
+    baseline: &BenchmarkReport,
+    current: &BenchmarkReport,
+    thresholds: &Thresholds,
+) -> Result<bool, String> {
+    let comparison = Comparison::new(baseline, current, thresholds)?;
+    print!("{}", comparison);
+    if comparison.has_regressions() {
+        eprintln!(
+            "{} regressions against the baseline",
+            comparison.regressions().count()
+        );
     }
+    Ok(!comparison.has_regressions())
+}
 
-    // Serialize the report to the CSV file.
-    writer.serialize(report)?;
-    writer.flush()?;
-
-    Ok(())
+fn example_elf(examples_dir: &Path, name: &str) -> PathBuf {
+    examples_dir
+        .join(name)
+        .join("program/elf/riscv32im-succinct-zkvm-elf")
 }
@@ -0,0 +1,391 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The results of a benchmark run: one report per program, for a hash function and a shard size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    /// The hash function of the configuration used to prove the programs.
+    pub hashfn: String,
+
+    /// The number of cycles per shard.
+    pub shard_size: usize,
+
+    /// The number of times each program was proven.
+    pub runs: usize,
+
+    pub programs: Vec<ProgramReport>,
+}
+
+/// The results of the benchmark of a program.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgramReport {
+    pub program: String,
+
+    /// The number of cycles of the execution.
+    pub cycles: u64,
+
+    /// The number of shards of the proof.
+    pub shards: usize,
+
+    /// The size of the serialized proof in bytes.
+    pub proof_size: usize,
+
+    /// The median duration of each phase over the runs, in seconds.
+    pub phases: PhaseTimings,
+
+    /// The main trace cells of each chip used by the program, summed over the shards.
+    pub chips: Vec<ChipCells>,
+
+    /// The peak resident set size of the process while proving the program, in bytes, if it can
+    /// be read from `/proc/self/status`.
+    pub peak_rss: Option<u64>,
+}
+
+impl ProgramReport {
+    /// The main trace cells of all the chips.
+    pub fn total_cells(&self) -> u64 {
+        self.chips.iter().map(|chip| chip.cells).sum()
+    }
+}
+
+/// The durations of the phases of a proof, in seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    /// The execution of the program.
+    pub execute: f64,
+
+    /// The generation of the dependencies and the sharding of the execution record.
+    pub trace: f64,
+
+    /// The generation of the main traces and the commitment to them.
+    pub commit: f64,
+
+    /// The proofs of the shards, from the commitments of the main traces.
+    pub open: f64,
+
+    /// The verification of the proof.
+    pub verify: f64,
+}
+
+impl PhaseTimings {
+    pub const NAMES: [&'static str; 5] = ["execute", "trace", "commit", "open", "verify"];
+
+    pub fn values(&self) -> [f64; 5] {
+        [
+            self.execute,
+            self.trace,
+            self.commit,
+            self.open,
+            self.verify,
+        ]
+    }
+
+    /// The median of each phase over `runs`, which is more robust to a noisy run than the mean.
+    pub fn median(runs: &[PhaseTimings]) -> PhaseTimings {
+        let median = |phase: fn(&PhaseTimings) -> f64| {
+            let mut values = runs.iter().map(phase).collect::<Vec<_>>();
+            values.sort_by(f64::total_cmp);
+            match values.len() {
+                0 => 0.0,
+                n if n % 2 == 1 => values[n / 2],
+                n => (values[n / 2 - 1] + values[n / 2]) / 2.0,
+            }
+        };
+        PhaseTimings {
+            execute: median(|t| t.execute),
+            trace: median(|t| t.trace),
+            commit: median(|t| t.commit),
+            open: median(|t| t.open),
+            verify: median(|t| t.verify),
+        }
+    }
+}
+
+/// The main trace of a chip, summed over the shards.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChipCells {
+    pub chip: String,
+
+    /// The number of columns of the main trace.
+    pub width: usize,
+
+    /// The number of rows of the main traces, padded to a power of two.
+    pub rows: u64,
+
+    /// The number of cells of the main traces, i.e. `width * rows`.
+    pub cells: u64,
+}
+
+impl BenchmarkReport {
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        serde_json::from_slice(&bytes).map_err(io::Error::from)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::from)?;
+        fs::write(path, json)
+    }
+}
+
+/// The relative increases over the baseline above which a metric is a regression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// The relative increase of the duration of a phase, e.g. `0.1` for 10%.
+    pub time: f64,
+
+    /// The duration in seconds under which the changes of a phase are ignored, since short
+    /// phases are dominated by noise.
+    pub min_time: f64,
+
+    /// The relative increase of the cells of a chip.
+    pub cells: f64,
+
+    /// The relative increase of the peak resident set size.
+    pub rss: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            time: 0.1,
+            min_time: 0.05,
+            cells: 0.0,
+            rss: 0.1,
+        }
+    }
+}
+
+/// The change of a metric of a program between the baseline and the current run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub program: String,
+    pub metric: String,
+    pub baseline: f64,
+    pub current: f64,
+    pub regression: bool,
+}
+
+impl Change {
+    /// The relative change over the baseline, if the baseline is not zero.
+    pub fn relative(&self) -> Option<f64> {
+        (self.baseline != 0.0).then(|| self.current / self.baseline - 1.0)
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<24} {:<32} {:>16.3} {:>16.3}",
+            self.program, self.metric, self.baseline, self.current
+        )?;
+        match self.relative() {
+            Some(relative) => write!(f, " {:>+9.1}%", relative * 100.0)?,
+            None => write!(f, " {:>10}", "new")?,
+        }
+        if self.regression {
+            write!(f, "  REGRESSION")?;
+        }
+        Ok(())
+    }
+}
+
+/// The comparison of a benchmark run against a baseline.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Comparison {
+    pub changes: Vec<Change>,
+
+    /// The programs of the baseline which are not in the current run.
+    pub missing: Vec<String>,
+}
+
+impl Comparison {
+    /// Compares the programs of `current` with the ones of `baseline` with the same name.
+    ///
+    /// The reports must have been produced with the same hash function and shard size, since the
+    /// metrics of different configurations are not comparable.
+    pub fn new(
+        baseline: &BenchmarkReport,
+        current: &BenchmarkReport,
+        thresholds: &Thresholds,
+    ) -> Result<Self, String> {
+        if baseline.hashfn != current.hashfn || baseline.shard_size != current.shard_size {
+            return Err(format!(
+                "the baseline was run with {} and a shard size of {}, but the current run with {} \
+                and a shard size of {}",
+                baseline.hashfn, baseline.shard_size, current.hashfn, current.shard_size
+            ));
+        }
+
+        let mut comparison = Comparison::default();
+        for base in baseline.programs.iter() {
+            let Some(program) = current.programs.iter().find(|p| p.program == base.program) else {
+                comparison.missing.push(base.program.clone());
+                continue;
+            };
+            comparison.compare_program(base, program, thresholds);
+        }
+        Ok(comparison)
+    }
+
+    fn compare_program(
+        &mut self,
+        baseline: &ProgramReport,
+        current: &ProgramReport,
+        thresholds: &Thresholds,
+    ) {
+        let mut push = |metric: String, baseline: f64, current: f64, regression: bool| {
+            self.changes.push(Change {
+                program: current.program.clone(),
+                metric,
+                baseline,
+                current,
+                regression,
+            })
+        };
+
+        let phases = PhaseTimings::NAMES
+            .iter()
+            .zip(baseline.phases.values())
+            .zip(current.phases.values());
+        for ((name, base), time) in phases {
+            let regression = time > base * (1.0 + thresholds.time) && time > thresholds.min_time;
+            push(format!("{} (s)", name), base, time, regression);
+        }
+
+        for chip in current.chips.iter() {
+            let base = baseline
+                .chips
+                .iter()
+                .find(|c| c.chip == chip.chip)
+                .map_or(0, |c| c.cells);
+            let regression = chip.cells as f64 > base as f64 * (1.0 + thresholds.cells);
+            push(
+                format!("{} cells", chip.chip),
+                base as f64,
+                chip.cells as f64,
+                regression,
+            );
+        }
+        push(
+            "total cells".to_string(),
+            baseline.total_cells() as f64,
+            current.total_cells() as f64,
+            false,
+        );
+
+        if let (Some(base), Some(rss)) = (baseline.peak_rss, current.peak_rss) {
+            let regression = rss as f64 > base as f64 * (1.0 + thresholds.rss);
+            push(
+                "peak rss (MiB)".to_string(),
+                base as f64 / (1 << 20) as f64,
+                rss as f64 / (1 << 20) as f64,
+                regression,
+            );
+        }
+    }
+
+    pub fn regressions(&self) -> impl Iterator<Item = &Change> {
+        self.changes.iter().filter(|change| change.regression)
+    }
+
+    pub fn has_regressions(&self) -> bool {
+        self.regressions().next().is_some()
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:<32} {:>16} {:>16} {:>10}",
+            "program", "metric", "baseline", "current", "change"
+        )?;
+        for change in self.changes.iter() {
+            writeln!(f, "{}", change)?;
+        }
+        for program in self.missing.iter() {
+            writeln!(f, "{:<24} missing from the current run", program)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(open: f64, cpu_cells: u64, peak_rss: u64) -> BenchmarkReport {
+        BenchmarkReport {
+            hashfn: "poseidon".to_string(),
+            shard_size: 1 << 19,
+            runs: 3,
+            programs: vec![ProgramReport {
+                program: "fibonacci".to_string(),
+                cycles: 10_000,
+                shards: 1,
+                proof_size: 1 << 20,
+                phases: PhaseTimings {
+                    execute: 0.01,
+                    trace: 0.5,
+                    commit: 1.0,
+                    open,
+                    verify: 0.1,
+                },
+                chips: vec![ChipCells {
+                    chip: "CPU".to_string(),
+                    width: 4,
+                    rows: cpu_cells / 4,
+                    cells: cpu_cells,
+                }],
+                peak_rss: Some(peak_rss),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_median() {
+        let timings = [3.0, 1.0, 2.0].map(|open| PhaseTimings {
+            open,
+            ..Default::default()
+        });
+        assert_eq!(PhaseTimings::median(&timings).open, 2.0);
+        assert_eq!(PhaseTimings::median(&timings[..2]).open, 2.0);
+    }
+
+    #[test]
+    fn test_comparison() {
+        let baseline = report(2.0, 1 << 16, 1 << 30);
+        let thresholds = Thresholds::default();
+
+        let comparison = Comparison::new(&baseline, &baseline, &thresholds).unwrap();
+        assert!(!comparison.has_regressions());
+
+        // A slower open phase, more cells and a higher peak memory are regressions.
+        let current = report(3.0, 1 << 17, 1 << 31);
+        let comparison = Comparison::new(&baseline, &current, &thresholds).unwrap();
+        let regressions = comparison
+            .regressions()
+            .map(|change| change.metric.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(regressions, ["open (s)", "CPU cells", "peak rss (MiB)"]);
+
+        // Changes within the thresholds are not.
+        let current = report(2.1, 1 << 16, (1 << 30) + (1 << 20));
+        let comparison = Comparison::new(&baseline, &current, &thresholds).unwrap();
+        assert!(!comparison.has_regressions());
+
+        let mut current = baseline.clone();
+        current.hashfn = "blake3".to_string();
+        assert!(Comparison::new(&baseline, &current, &thresholds).is_err());
+
+        current = baseline.clone();
+        current.programs.clear();
+        let comparison = Comparison::new(&baseline, &current, &thresholds).unwrap();
+        assert_eq!(comparison.missing, ["fibonacci"]);
+    }
+}
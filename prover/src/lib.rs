@@ -0,0 +1,1097 @@
+//! Recursive compression of the proofs of `sp1-core`.
+//!
+//! The shard proofs of a core proof are verified by leaf programs of the recursion VM, one for each
+//! shape of shard proof, whose proofs are in turn verified in batches by a single reduce program
+//! until a single proof remains. The traces of all these programs are padded to the same heights,
+//! so that the reduce program verifies proofs of a single shape, and the programs read the state of
+//! the challenger of the core proof from the witness stream, so that none of them depends on the
+//! number of shards.
+//!
+//! The programs commit as public values the range of shards they verified, the challenger states
+//! before and after observing the main commitments of these shards, the sum of their cumulative
+//! sums and the verifying keys the reduce program verifies proofs against.
+
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+use anyhow::{bail, Result};
+use p3_challenger::CanObserve;
+use p3_field::{AbstractExtensionField, AbstractField};
+use serde::{Deserialize, Serialize};
+use sp1_core::air::MachineAir;
+use sp1_core::stark::{
+    LocalProver, MachineChip, ProgramVerificationError, Proof, ProvingKey, RiscvAir, ShardProof,
+    VerifyingKey,
+};
+use sp1_core::utils::baby_bear_poseidon2::{Challenge, Val};
+use sp1_core::utils::{BabyBearPoseidon2, StarkUtils};
+use sp1_recursion_compiler::asm::VmBuilder;
+use sp1_recursion_compiler::ir::{Builder, Config, Felt, SymbolicExt, SymbolicFelt};
+use sp1_recursion_compiler::verifier::{
+    compress, ext_from_coefficients, hint_digest, permute, read_proof, verify_proof, verify_shard,
+    write_digest, write_proof, write_shard_proof, DigestVariable, DuplexChallengerVariable,
+    PcsWitness, ShardProofVariable, ShardShape, DIGEST_SIZE,
+};
+use sp1_recursion_core::air::Block;
+use sp1_recursion_core::poseidon2::{self, WIDTH};
+use sp1_recursion_core::runtime::{ExecutionRecord, Program, Runtime};
+use sp1_recursion_core::stark::RecursionAir;
+
+/// The number of proofs of the recursion VM verified by the reduce program.
+pub const REDUCE_BATCH_SIZE: usize = 2;
+
+/// The number of values of a [`ChallengerState`].
+const CHALLENGER_STATE_SIZE: usize = WIDTH + DIGEST_SIZE + 1;
+
+/// The number of public values of the programs of the recursion tree.
+const NUM_PUBLIC_VALUES: usize = 2 + 3 * CHALLENGER_STATE_SIZE + 4 + 2 * DIGEST_SIZE;
+
+/// A prover that compresses the proofs generated by `sp1_core::SP1Prover`.
+pub struct SP1Prover;
+
+/// A verifier that can verify proofs generated by `SP1Prover::compress`.
+pub struct SP1Verifier;
+
+/// A proof of the recursion VM attesting that all the shard proofs of a core proof are valid.
+///
+/// Besides the root proof, it only holds the shapes from which the verifier rebuilds the leaf
+/// programs and the reduce program, so its size does not grow with the number of shards of the
+/// core proof.
+#[derive(Serialize, Deserialize)]
+pub struct CompressedProof {
+    /// The proof of the root program of the recursion tree.
+    pub proof: Proof<BabyBearPoseidon2>,
+    /// The shape of the proofs of all the programs of the recursion tree, whose traces are padded
+    /// to its log degrees.
+    pub shape: ShardShape,
+    /// The distinct shapes of the shard proofs of the core proof, which determine the leaf
+    /// programs.
+    pub shard_shapes: Vec<ShardShape>,
+}
+
+/// An error returned by `SP1Verifier::verify_compressed`.
+#[derive(Debug)]
+pub enum CompressedVerificationError {
+    /// The shapes of the proof do not describe programs of a recursion tree.
+    InvalidShape,
+    /// The proof of the root program is invalid.
+    InvalidProof(ProgramVerificationError),
+    /// The public values of the root program are not those of a complete core proof.
+    InvalidPublicValues,
+}
+
+impl Display for CompressedVerificationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompressedVerificationError::InvalidShape => {
+                write!(
+                    f,
+                    "The shapes of the proof do not describe a recursion tree"
+                )
+            }
+            CompressedVerificationError::InvalidProof(error) => {
+                write!(f, "Invalid root proof: {}", error)
+            }
+            CompressedVerificationError::InvalidPublicValues => {
+                write!(f, "The public values do not match a complete core proof")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompressedVerificationError {}
+
+impl From<ProgramVerificationError> for CompressedVerificationError {
+    fn from(error: ProgramVerificationError) -> Self {
+        CompressedVerificationError::InvalidProof(error)
+    }
+}
+
+impl SP1Prover {
+    /// Recursively aggregates the shard proofs of `proof` into a single proof.
+    ///
+    /// Each shard proof is verified by the leaf program of its shape, and the proofs of these
+    /// programs are then verified in batches of `REDUCE_BATCH_SIZE` by the reduce program until a
+    /// single proof remains. The traces of the programs are padded to the smallest heights found
+    /// to fit all of them, which may take a few attempts. The core proof must be generated with
+    /// `BabyBearPoseidon2`, which is the config the verifier programs implement.
+    pub fn compress(proof: &Proof<BabyBearPoseidon2>) -> Result<CompressedProof> {
+        if proof.shard_proofs.is_empty() {
+            bail!("the proof has no shard proofs");
+        }
+
+        // The programs prove the sum of the cumulative sums, which `verify_compressed` checks, but
+        // an unbalanced proof is rejected before it is compressed.
+        let cumulative_sum: Challenge = proof
+            .shard_proofs
+            .iter()
+            .map(|shard_proof| shard_proof.cumulative_sum())
+            .sum();
+        if !cumulative_sum.is_zero() {
+            bail!("the cumulative sum of the shard proofs is not zero");
+        }
+
+        let (shard_shapes, leaves, challenger) = leaf_inputs(proof)?;
+
+        // Start from the heights of the traces of the leaf programs, which grow until the reduce
+        // program fits in them as well. The leaf programs only pass the digests of the verifying
+        // keys through, so their traces do not depend on them.
+        let core_machine = RiscvAir::machine(BabyBearPoseidon2::new());
+        let no_digest = [Val::zero(); DIGEST_SIZE];
+        let mut shape = shard_shapes
+            .iter()
+            .enumerate()
+            .map(|(leaf, shard_shape)| {
+                let input = leaves.iter().find(|input| input.leaf == leaf).unwrap();
+                let stream = leaf_stream(input, &challenger, no_digest, no_digest);
+                let program = leaf_program(core_machine.chips(), shard_shape);
+                trace_shape(&execute(&program, stream))
+            })
+            .reduce(|shape, other| max_shape(&shape, &other))
+            .unwrap();
+
+        loop {
+            tracing::info!(
+                "padding the traces to the log degrees {:?}",
+                shape.log_degrees
+            );
+            let programs = RecursionPrograms::new(&shard_shapes, &shape);
+            match programs.prove(&leaves, &challenger) {
+                Ok(proof) => {
+                    return Ok(CompressedProof {
+                        proof,
+                        shape,
+                        shard_shapes,
+                    })
+                }
+                Err(trace_shape) => shape = max_shape(&shape, &trace_shape),
+            }
+        }
+    }
+}
+
+impl SP1Verifier {
+    /// Verify a proof generated by `SP1Prover::compress`.
+    ///
+    /// The verifying keys of the leaf programs and of the reduce program are derived by rebuilding
+    /// the programs from the shapes of the proof and setting them up, so the cost of the
+    /// verification only grows with the number of distinct shapes of shard proofs. The root proof
+    /// must then cover all the shards of a core proof, from the initial challenger state to the
+    /// one its shards are verified from, with a zero cumulative sum.
+    pub fn verify_compressed(proof: &CompressedProof) -> Result<(), CompressedVerificationError> {
+        let core_machine = RiscvAir::machine(BabyBearPoseidon2::new());
+        let machine = RecursionAir::machine(BabyBearPoseidon2::new());
+        let valid_shapes = !proof.shard_shapes.is_empty()
+            && proof
+                .shard_shapes
+                .iter()
+                .all(|shape| shape.chips(core_machine.chips()).is_some())
+            && proof.shape.chips(machine.chips()).map(|chips| chips.len())
+                == Some(machine.chips().len());
+        if !valid_shapes {
+            return Err(CompressedVerificationError::InvalidShape);
+        }
+        let public_values = PublicValues::from_slice(&proof.proof.public_values)
+            .ok_or(CompressedVerificationError::InvalidPublicValues)?;
+
+        let programs = RecursionPrograms::new(&proof.shard_shapes, &proof.shape);
+        if !programs.has_shape() {
+            return Err(CompressedVerificationError::InvalidShape);
+        }
+
+        // The root program is a leaf program only if the core proof has a single shard.
+        let root = if public_values.end == Val::one() {
+            match programs.leaves.as_slice() {
+                [leaf] => leaf,
+                _ => return Err(CompressedVerificationError::InvalidShape),
+            }
+        } else {
+            &programs.reduce
+        };
+        let mut challenger = machine.config().challenger();
+        tracing::info_span!("verify compressed")
+            .in_scope(|| machine.verify(&root.vk, &proof.proof, &mut challenger))?;
+
+        let valid = public_values.start == Val::zero()
+            && public_values.start_challenger == ChallengerState::new()
+            && public_values.end_challenger == public_values.challenger
+            && public_values.cumulative_sum == [Val::zero(); 4]
+            && public_values.leaf_vks_root == programs.leaf_vks_root()
+            && public_values.reduce_vk == programs.reduce.vk_digest();
+        if !valid {
+            return Err(CompressedVerificationError::InvalidPublicValues);
+        }
+        Ok(())
+    }
+}
+
+/// The state of the challenger of the core machine after observing the main commitments of the
+/// first shards of a core proof.
+///
+/// The `DuplexChallenger` of `BabyBearPoseidon2` overwrites its whole sponge state with every
+/// `WIDTH` observed values, so once it observed whole commitments its input buffer holds either
+/// nothing or a single commitment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ChallengerState {
+    sponge_state: [Val; WIDTH],
+    input_buffer: [Val; DIGEST_SIZE],
+    is_buffered: bool,
+}
+
+impl ChallengerState {
+    /// The state of a new challenger.
+    fn new() -> Self {
+        Self {
+            sponge_state: [Val::zero(); WIDTH],
+            input_buffer: [Val::zero(); DIGEST_SIZE],
+            is_buffered: false,
+        }
+    }
+
+    /// The state after observing `commit`.
+    fn observe(&self, commit: [Val; DIGEST_SIZE]) -> Self {
+        let sponge_state = if self.is_buffered {
+            poseidon2::permute(core::array::from_fn(|i| {
+                if i < DIGEST_SIZE {
+                    self.input_buffer[i]
+                } else {
+                    commit[i - DIGEST_SIZE]
+                }
+            }))
+        } else {
+            self.sponge_state
+        };
+        Self {
+            sponge_state,
+            input_buffer: commit,
+            is_buffered: !self.is_buffered,
+        }
+    }
+
+    /// Reads a state from public values, in the order of [`ChallengerStateVariable::to_vec`].
+    fn read(values: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        let sponge_state = core::array::from_fn(|_| values.next().unwrap_or_default());
+        let input_buffer = core::array::from_fn(|_| values.next().unwrap_or_default());
+        let is_buffered = match values.next()? {
+            value if value == Val::zero() => false,
+            value if value == Val::one() => true,
+            _ => return None,
+        };
+        Some(Self {
+            sponge_state,
+            input_buffer,
+            is_buffered,
+        })
+    }
+
+    /// Appends the state to the witness stream, as read by [`ChallengerStateVariable::read`].
+    fn write(&self, stream: &mut VecDeque<Block<Val>>) {
+        stream.extend(self.sponge_state.map(Block::from));
+        write_digest(stream, self.input_buffer);
+        stream.push_back(Block::from(Val::from_bool(self.is_buffered)));
+    }
+}
+
+/// A [`ChallengerState`] in the DSL.
+#[derive(Clone)]
+struct ChallengerStateVariable<C: Config> {
+    sponge_state: [Felt<C::F>; WIDTH],
+    input_buffer: DigestVariable<C>,
+    is_buffered: Felt<C::F>,
+}
+
+impl<C: Config> ChallengerStateVariable<C> {
+    /// Reads a state from the witness stream.
+    fn read(builder: &mut Builder<C>) -> Self {
+        let state = Self {
+            sponge_state: core::array::from_fn(|_| builder.hint()),
+            input_buffer: hint_digest(builder),
+            is_buffered: builder.hint(),
+        };
+        assert_bool(builder, state.is_buffered);
+        state
+    }
+
+    /// The state after observing `commit`, as [`ChallengerState::observe`].
+    fn observe(&self, builder: &mut Builder<C>, commit: &DigestVariable<C>) -> Self {
+        let input = core::array::from_fn(|i| {
+            if i < DIGEST_SIZE {
+                self.input_buffer[i]
+            } else {
+                commit[i - DIGEST_SIZE]
+            }
+        });
+        let output = permute(builder, input);
+        Self {
+            sponge_state: core::array::from_fn(|i| {
+                select(builder, self.is_buffered, output[i], self.sponge_state[i])
+            }),
+            input_buffer: *commit,
+            is_buffered: builder.eval(SymbolicFelt::from(C::F::one()) - self.is_buffered),
+        }
+    }
+
+    /// The challenger in this state.
+    ///
+    /// The first sample of the challenger absorbs the buffered commitment, if there is one, with a
+    /// duplexing which is performed here instead.
+    fn challenger(&self, builder: &mut Builder<C>) -> DuplexChallengerVariable<C> {
+        let input = core::array::from_fn(|i| {
+            if i < DIGEST_SIZE {
+                self.input_buffer[i]
+            } else {
+                self.sponge_state[i]
+            }
+        });
+        let output = permute(builder, input);
+        let sponge_state = core::array::from_fn(|i| {
+            select(builder, self.is_buffered, output[i], self.sponge_state[i])
+        });
+        DuplexChallengerVariable::from_sponge_state(sponge_state)
+    }
+
+    fn to_vec(&self) -> Vec<Felt<C::F>> {
+        let mut values = self.sponge_state.to_vec();
+        values.extend(self.input_buffer);
+        values.push(self.is_buffered);
+        values
+    }
+}
+
+/// The public values committed by the programs of the recursion tree.
+struct PublicValuesVariable<C: Config> {
+    /// The range of shards of the core proof verified by the program.
+    start: Felt<C::F>,
+    end: Felt<C::F>,
+    /// The challenger states before and after observing the main commitments of these shards.
+    start_challenger: ChallengerStateVariable<C>,
+    end_challenger: ChallengerStateVariable<C>,
+    /// The challenger state after observing the main commitments of all the shards, from which
+    /// the shards are verified.
+    challenger: ChallengerStateVariable<C>,
+    /// The sum of the cumulative sums of the verified shards.
+    cumulative_sum: [Felt<C::F>; 4],
+    /// The root of the Merkle tree of the verifying keys of the leaf programs.
+    leaf_vks_root: DigestVariable<C>,
+    /// The verifying key of the reduce program.
+    reduce_vk: DigestVariable<C>,
+}
+
+impl<C: Config> PublicValuesVariable<C> {
+    /// Reads the public values of a proof of the recursion tree from the witness stream.
+    fn read(builder: &mut Builder<C>) -> Self {
+        Self {
+            start: builder.hint(),
+            end: builder.hint(),
+            start_challenger: ChallengerStateVariable::read(builder),
+            end_challenger: ChallengerStateVariable::read(builder),
+            challenger: ChallengerStateVariable::read(builder),
+            cumulative_sum: core::array::from_fn(|_| builder.hint()),
+            leaf_vks_root: hint_digest(builder),
+            reduce_vk: hint_digest(builder),
+        }
+    }
+
+    fn to_vec(&self) -> Vec<Felt<C::F>> {
+        let mut values = vec![self.start, self.end];
+        values.extend(self.start_challenger.to_vec());
+        values.extend(self.end_challenger.to_vec());
+        values.extend(self.challenger.to_vec());
+        values.extend(self.cumulative_sum);
+        values.extend(self.leaf_vks_root);
+        values.extend(self.reduce_vk);
+        values
+    }
+
+    fn commit(&self, builder: &mut Builder<C>) {
+        for value in self.to_vec() {
+            builder.commit_public_value(value);
+        }
+    }
+}
+
+/// The public values of a proof of the recursion tree, as committed by [`PublicValuesVariable`].
+struct PublicValues {
+    start: Val,
+    end: Val,
+    start_challenger: ChallengerState,
+    end_challenger: ChallengerState,
+    challenger: ChallengerState,
+    cumulative_sum: [Val; 4],
+    leaf_vks_root: [Val; DIGEST_SIZE],
+    reduce_vk: [Val; DIGEST_SIZE],
+}
+
+impl PublicValues {
+    /// Parses the public values of a proof, or returns `None` if they are not of this form.
+    fn from_slice(values: &[Val]) -> Option<Self> {
+        if values.len() != NUM_PUBLIC_VALUES {
+            return None;
+        }
+        let mut values = values.iter().copied();
+        Some(Self {
+            start: values.next()?,
+            end: values.next()?,
+            start_challenger: ChallengerState::read(&mut values)?,
+            end_challenger: ChallengerState::read(&mut values)?,
+            challenger: ChallengerState::read(&mut values)?,
+            cumulative_sum: core::array::from_fn(|_| values.next().unwrap_or_default()),
+            leaf_vks_root: core::array::from_fn(|_| values.next().unwrap_or_default()),
+            reduce_vk: core::array::from_fn(|_| values.next().unwrap_or_default()),
+        })
+    }
+}
+
+/// The input of the leaf program verifying a shard proof.
+struct LeafInput<'a> {
+    /// The index of the shard in the core proof.
+    index: usize,
+    /// The index of the shape of the shard proof among the shapes of the leaf programs.
+    leaf: usize,
+    shard_proof: &'a ShardProof<BabyBearPoseidon2>,
+    /// The witness of the opening proof of the shard proof.
+    witness: PcsWitness,
+    /// The challenger state before observing the main commitment of the shard.
+    start_challenger: ChallengerState,
+}
+
+/// Splits the shard proofs of `proof` into the inputs of the leaf programs, returning the distinct
+/// shapes of the shard proofs, the inputs, and the challenger state after observing the main
+/// commitments of all the shards.
+fn leaf_inputs(
+    proof: &Proof<BabyBearPoseidon2>,
+) -> Result<(Vec<ShardShape>, Vec<LeafInput>, ChallengerState)> {
+    let core_machine = RiscvAir::machine(BabyBearPoseidon2::new());
+
+    // The core machine has no preprocessed traces, so the verifier of a shard only observes the
+    // main commitments.
+    let mut challenger = core_machine.config().challenger();
+    let mut state = ChallengerState::new();
+    let mut start_challengers = Vec::new();
+    for shard_proof in proof.shard_proofs.iter() {
+        start_challengers.push(state.clone());
+        challenger.observe(shard_proof.commitment.main_commit.clone());
+        state = state.observe(shard_proof.commitment.main_commit.clone().into());
+    }
+
+    let mut shard_shapes = Vec::new();
+    let mut leaves = Vec::new();
+    for (index, (shard_proof, start_challenger)) in
+        proof.shard_proofs.iter().zip(start_challengers).enumerate()
+    {
+        let shape = ShardShape::of(shard_proof);
+        let Some(shard_chips) = shape.chips(core_machine.chips()) else {
+            bail!("the proof has a shard proof of unknown chips");
+        };
+        let witness =
+            PcsWitness::for_shard(&shard_chips, None, shard_proof, &mut challenger.clone());
+        let leaf = match shard_shapes.iter().position(|other| *other == shape) {
+            Some(leaf) => leaf,
+            None => {
+                shard_shapes.push(shape);
+                shard_shapes.len() - 1
+            }
+        };
+        leaves.push(LeafInput {
+            index,
+            leaf,
+            shard_proof,
+            witness,
+            start_challenger,
+        });
+    }
+    Ok((shard_shapes, leaves, state))
+}
+
+/// The witness stream of the leaf program for `input`, as read by [`leaf_program`].
+fn leaf_stream(
+    input: &LeafInput,
+    challenger: &ChallengerState,
+    leaf_vks_root: [Val; DIGEST_SIZE],
+    reduce_vk: [Val; DIGEST_SIZE],
+) -> VecDeque<Block<Val>> {
+    let mut stream = VecDeque::new();
+    stream.push_back(Block::from(Val::from_canonical_usize(input.index)));
+    input.start_challenger.write(&mut stream);
+    challenger.write(&mut stream);
+    write_digest(&mut stream, leaf_vks_root);
+    write_digest(&mut stream, reduce_vk);
+    write_shard_proof(&mut stream, input.shard_proof, &input.witness);
+    let cumulative_sum = input.shard_proof.cumulative_sum();
+    let coefficients: &[Val] = cumulative_sum.as_base_slice();
+    stream.extend(coefficients.iter().map(|value| Block::from(*value)));
+    stream
+}
+
+/// Builds the program verifying a shard proof of shape `shape`.
+///
+/// The program reads from the witness stream the index of the shard, the challenger state before
+/// observing its main commitment, the challenger state after observing all the main commitments,
+/// the public values on the verifying keys of the tree, the shard proof and the coefficients of its
+/// cumulative sum.
+fn leaf_program(
+    chips: &[MachineChip<BabyBearPoseidon2, RiscvAir<Val>>],
+    shape: &ShardShape,
+) -> Program<Val> {
+    let mut builder = VmBuilder::<Val, Challenge>::default();
+    let shard_chips = shape.chips(chips).expect("invalid shard shape");
+
+    let start: Felt<Val> = builder.hint();
+    let start_challenger = ChallengerStateVariable::read(&mut builder);
+    let challenger = ChallengerStateVariable::read(&mut builder);
+    let leaf_vks_root = hint_digest(&mut builder);
+    let reduce_vk = hint_digest(&mut builder);
+
+    let proof = ShardProofVariable::read(&mut builder, &shard_chips, shape);
+    let end_challenger = start_challenger.observe(&mut builder, &proof.commitment.main_commit);
+    let mut shard_challenger = challenger.challenger(&mut builder);
+    verify_shard(
+        &mut builder,
+        &shard_chips,
+        None,
+        &mut shard_challenger,
+        &proof,
+    );
+
+    let mut cumulative_sum = SymbolicExt::Const(Challenge::zero());
+    for values in proof.opened_values.iter() {
+        cumulative_sum = cumulative_sum + values.cumulative_sum;
+    }
+    let coefficients = core::array::from_fn(|_| builder.hint());
+    let hinted_sum = ext_from_coefficients(&mut builder, &coefficients);
+    builder.assert_ext_eq(cumulative_sum, hinted_sum);
+
+    let public_values = PublicValuesVariable {
+        start,
+        end: builder.eval(start + Val::one()),
+        start_challenger,
+        end_challenger,
+        challenger,
+        cumulative_sum: coefficients,
+        leaf_vks_root,
+        reduce_vk,
+    };
+    public_values.commit(&mut builder);
+
+    builder.compile()
+}
+
+/// Builds the program verifying `REDUCE_BATCH_SIZE` proofs of shape `shape` of the recursion tree,
+/// given the depth of the Merkle tree of the verifying keys of the leaf programs.
+///
+/// For each proof, the program reads from the witness stream its public values, the digest of its
+/// verifying key, whether it is the proof of a leaf program, the Merkle path of the digest and the
+/// proof. The proof of a leaf program must be of a verifying key of the tree, and other proofs of
+/// the verifying key of the reduce program, both as exposed by the proofs. The proofs must cover
+/// consecutive ranges of shards of the same core proof.
+fn reduce_program(shape: &ShardShape, leaf_vks_depth: usize) -> Program<Val> {
+    let machine = RecursionAir::machine(BabyBearPoseidon2::new());
+    let mut builder = VmBuilder::<Val, Challenge>::default();
+
+    let mut children = Vec::new();
+    for _ in 0..REDUCE_BATCH_SIZE {
+        let public_values = PublicValuesVariable::read(&mut builder);
+        let vk = hint_digest(&mut builder);
+        let is_leaf: Felt<Val> = builder.hint();
+        assert_bool(&mut builder, is_leaf);
+        let is_reduce: Felt<Val> = builder.eval(SymbolicFelt::from(Val::one()) - is_leaf);
+        let leaf_vks_root = read_merkle_root(&mut builder, &vk, leaf_vks_depth);
+        for i in 0..DIGEST_SIZE {
+            builder.assert_felt_eq(
+                (leaf_vks_root[i] - public_values.leaf_vks_root[i]) * is_leaf,
+                Val::zero(),
+            );
+            builder.assert_felt_eq(
+                (vk[i] - public_values.reduce_vk[i]) * is_reduce,
+                Val::zero(),
+            );
+        }
+
+        let proofs = read_proof(&mut builder, machine.chips(), core::slice::from_ref(shape));
+        verify_proof(
+            &mut builder,
+            machine.chips(),
+            Some(&vk),
+            &public_values.to_vec(),
+            &proofs,
+        );
+        children.push(public_values);
+    }
+
+    let first = &children[0];
+    let last = children.last().unwrap();
+    for pair in children.windows(2) {
+        builder.assert_felt_eq(pair[0].end, pair[1].start);
+        assert_felts_eq(
+            &mut builder,
+            &pair[0].end_challenger.to_vec(),
+            &pair[1].start_challenger.to_vec(),
+        );
+    }
+    for child in children[1..].iter() {
+        assert_felts_eq(
+            &mut builder,
+            &child.challenger.to_vec(),
+            &first.challenger.to_vec(),
+        );
+        assert_felts_eq(&mut builder, &child.leaf_vks_root, &first.leaf_vks_root);
+        assert_felts_eq(&mut builder, &child.reduce_vk, &first.reduce_vk);
+    }
+    let cumulative_sum = core::array::from_fn(|i| {
+        let mut sum: Felt<Val> = builder.eval(Val::zero());
+        for child in children.iter() {
+            sum = builder.eval(sum + child.cumulative_sum[i]);
+        }
+        sum
+    });
+
+    let public_values = PublicValuesVariable {
+        start: first.start,
+        end: last.end,
+        start_challenger: first.start_challenger.clone(),
+        end_challenger: last.end_challenger.clone(),
+        challenger: first.challenger.clone(),
+        cumulative_sum,
+        leaf_vks_root: first.leaf_vks_root,
+        reduce_vk: first.reduce_vk,
+    };
+    public_values.commit(&mut builder);
+
+    builder.compile()
+}
+
+/// A program of the recursion tree, along with its keys.
+struct RecursionProgram {
+    program: Program<Val>,
+    pk: ProvingKey<BabyBearPoseidon2>,
+    vk: VerifyingKey<BabyBearPoseidon2>,
+}
+
+impl RecursionProgram {
+    /// Pads the traces of `program` to the log degrees of `shape` and sets it up.
+    fn new(mut program: Program<Val>, shape: &ShardShape) -> Self {
+        program.min_trace_log_heights = shape
+            .chip_ids
+            .iter()
+            .cloned()
+            .zip(shape.log_degrees.iter().copied())
+            .collect();
+        let machine = RecursionAir::machine(BabyBearPoseidon2::new());
+        let (pk, vk) = machine.setup(&program);
+        Self { program, pk, vk }
+    }
+
+    /// The commitment of the verifying key to the preprocessed traces.
+    fn vk_digest(&self) -> [Val; DIGEST_SIZE] {
+        self.vk
+            .commit
+            .clone()
+            .expect("the program chip has a preprocessed trace")
+            .into()
+    }
+
+    /// Runs the program with the given witness stream and proves its execution, or returns the
+    /// shape of its traces if they do not fit in `shape`.
+    fn prove(
+        &self,
+        shape: &ShardShape,
+        witness_stream: VecDeque<Block<Val>>,
+    ) -> Result<Proof<BabyBearPoseidon2>, ShardShape> {
+        let record = execute(&self.program, witness_stream);
+        let trace_shape = trace_shape(&record);
+        if trace_shape != *shape {
+            return Err(trace_shape);
+        }
+
+        let machine = RecursionAir::machine(BabyBearPoseidon2::new());
+        let mut challenger = machine.config().challenger();
+        Ok(tracing::info_span!("prove")
+            .in_scope(|| machine.prove::<LocalProver<_, _>>(&self.pk, record, &mut challenger)))
+    }
+}
+
+/// The leaf programs of the shapes of shard proofs of a core proof and the reduce program, whose
+/// traces are padded to the same shape.
+struct RecursionPrograms {
+    shape: ShardShape,
+    leaves: Vec<RecursionProgram>,
+    reduce: RecursionProgram,
+    /// The layers of the Merkle tree of the verifying keys of the leaf programs, from the leaves to
+    /// the root.
+    leaf_vks_tree: Vec<Vec<[Val; DIGEST_SIZE]>>,
+}
+
+impl RecursionPrograms {
+    fn new(shard_shapes: &[ShardShape], shape: &ShardShape) -> Self {
+        let core_machine = RiscvAir::machine(BabyBearPoseidon2::new());
+        let leaves = shard_shapes
+            .iter()
+            .map(|shard_shape| {
+                RecursionProgram::new(leaf_program(core_machine.chips(), shard_shape), shape)
+            })
+            .collect::<Vec<_>>();
+        let leaf_vks_tree = merkle_tree(leaves.iter().map(RecursionProgram::vk_digest).collect());
+        let reduce = RecursionProgram::new(reduce_program(shape, leaf_vks_tree.len() - 1), shape);
+        Self {
+            shape: shape.clone(),
+            leaves,
+            reduce,
+            leaf_vks_tree,
+        }
+    }
+
+    fn leaf_vks_root(&self) -> [Val; DIGEST_SIZE] {
+        self.leaf_vks_tree.last().unwrap()[0]
+    }
+
+    /// Whether the preprocessed traces of all the programs have the heights of the shape, which is
+    /// not the case if a program is too long for it.
+    fn has_shape(&self) -> bool {
+        let machine = RecursionAir::machine(BabyBearPoseidon2::new());
+        let chips = self
+            .shape
+            .chips(machine.chips())
+            .expect("invalid recursion shape");
+        let chip_information = self.shape.preprocessed_chips(&chips);
+        self.leaves
+            .iter()
+            .chain([&self.reduce])
+            .all(|program| program.vk.chip_information == chip_information)
+    }
+
+    /// Proves the recursion tree of the leaf programs with the given inputs, whose shards are
+    /// verified from the challenger state `challenger`, returning the proof of its root.
+    ///
+    /// Returns the shape of the traces of a program instead if they do not fit in the shape of the
+    /// programs.
+    fn prove(
+        &self,
+        leaves: &[LeafInput],
+        challenger: &ChallengerState,
+    ) -> Result<Proof<BabyBearPoseidon2>, ShardShape> {
+        let mut layer = Vec::new();
+        for input in leaves.iter() {
+            let proof =
+                tracing::info_span!("compress shard", shard = input.index).in_scope(|| {
+                    let stream = leaf_stream(
+                        input,
+                        challenger,
+                        self.leaf_vks_root(),
+                        self.reduce.vk_digest(),
+                    );
+                    self.leaves[input.leaf].prove(&self.shape, stream)
+                })?;
+            layer.push((Some(input.leaf), proof));
+        }
+
+        let mut layer_index = 0;
+        while layer.len() > 1 {
+            let mut next_layer = Vec::new();
+            for batch in batches(layer) {
+                // An incomplete batch is carried over to the next layer as is.
+                if batch.len() < REDUCE_BATCH_SIZE {
+                    next_layer.extend(batch);
+                    continue;
+                }
+                let proof = tracing::info_span!("reduce", layer = layer_index).in_scope(|| {
+                    let mut stream = VecDeque::new();
+                    for (leaf, proof) in batch.iter() {
+                        self.write_child(&mut stream, *leaf, proof);
+                    }
+                    self.reduce.prove(&self.shape, stream)
+                })?;
+                next_layer.push((None, proof));
+            }
+            layer = next_layer;
+            layer_index += 1;
+        }
+        Ok(layer.pop().unwrap().1)
+    }
+
+    /// Appends the input of the reduce program for a proof of the tree to the witness stream, as
+    /// read by [`reduce_program`], given the index of the leaf program of the proof if it is the
+    /// proof of a leaf program.
+    fn write_child(
+        &self,
+        stream: &mut VecDeque<Block<Val>>,
+        leaf: Option<usize>,
+        proof: &Proof<BabyBearPoseidon2>,
+    ) {
+        let program = leaf.map_or(&self.reduce, |leaf| &self.leaves[leaf]);
+        stream.extend(proof.public_values.iter().map(|value| Block::from(*value)));
+        write_digest(stream, program.vk_digest());
+        stream.push_back(Block::from(Val::from_bool(leaf.is_some())));
+
+        // The Merkle path of a proof of the reduce program is not checked.
+        let mut index = leaf.unwrap_or(0);
+        for nodes in self.leaf_vks_tree[..self.leaf_vks_tree.len() - 1].iter() {
+            stream.push_back(Block::from(Val::from_canonical_usize(index & 1)));
+            write_digest(stream, nodes[index ^ 1]);
+            index >>= 1;
+        }
+
+        let machine = RecursionAir::machine(BabyBearPoseidon2::new());
+        write_proof(stream, machine.chips(), &program.vk, proof);
+    }
+}
+
+/// The layers of the Merkle tree of `leaves`, from the leaves, padded to a power of two by
+/// repeating the last one, to the root.
+fn merkle_tree(mut leaves: Vec<[Val; DIGEST_SIZE]>) -> Vec<Vec<[Val; DIGEST_SIZE]>> {
+    let last = *leaves.last().expect("no leaves");
+    leaves.resize(leaves.len().next_power_of_two(), last);
+    let mut layers = vec![leaves];
+    while layers.last().unwrap().len() > 1 {
+        let layer = layers
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| {
+                let output = poseidon2::permute(core::array::from_fn(|i| {
+                    if i < DIGEST_SIZE {
+                        pair[0][i]
+                    } else {
+                        pair[1][i - DIGEST_SIZE]
+                    }
+                }));
+                core::array::from_fn(|i| output[i])
+            })
+            .collect();
+        layers.push(layer);
+    }
+    layers
+}
+
+/// Reads the Merkle path of `leaf` in a tree of depth `depth` from the witness stream, as a bit
+/// for each layer, set if the node is on the right, and its sibling, returning the root of the
+/// tree.
+fn read_merkle_root<C: Config>(
+    builder: &mut Builder<C>,
+    leaf: &DigestVariable<C>,
+    depth: usize,
+) -> DigestVariable<C> {
+    let mut root = *leaf;
+    for _ in 0..depth {
+        let bit: Felt<C::F> = builder.hint();
+        assert_bool(builder, bit);
+        let sibling = hint_digest(builder);
+        let left = core::array::from_fn(|i| select(builder, bit, sibling[i], root[i]));
+        let right = core::array::from_fn(|i| select(builder, bit, root[i], sibling[i]));
+        root = compress(builder, &left, &right);
+    }
+    root
+}
+
+/// Evaluates to `when_true` if the boolean `condition` is set, and to `when_false` otherwise.
+fn select<C: Config>(
+    builder: &mut Builder<C>,
+    condition: Felt<C::F>,
+    when_true: Felt<C::F>,
+    when_false: Felt<C::F>,
+) -> Felt<C::F> {
+    builder.eval((when_true - when_false) * condition + when_false)
+}
+
+fn assert_bool<C: Config>(builder: &mut Builder<C>, value: Felt<C::F>) {
+    builder.assert_felt_eq((value - C::F::one()) * value, C::F::zero());
+}
+
+fn assert_felts_eq<C: Config>(
+    builder: &mut Builder<C>,
+    values: &[Felt<C::F>],
+    other: &[Felt<C::F>],
+) {
+    for (value, other) in values.iter().zip(other) {
+        builder.assert_felt_eq(*value, *other);
+    }
+}
+
+/// The log degree of each chip of `shape` which is the largest in `shape` and `other`.
+fn max_shape(shape: &ShardShape, other: &ShardShape) -> ShardShape {
+    ShardShape {
+        chip_ids: shape.chip_ids.clone(),
+        log_degrees: shape
+            .log_degrees
+            .iter()
+            .zip(other.log_degrees.iter())
+            .map(|(a, b)| *a.max(b))
+            .collect(),
+    }
+}
+
+/// Splits a layer of the recursion tree into the batches verified by the reduce program.
+fn batches<T>(layer: Vec<T>) -> Vec<Vec<T>> {
+    let mut batches = Vec::new();
+    let mut remaining = layer.into_iter().peekable();
+    while remaining.peek().is_some() {
+        batches.push(remaining.by_ref().take(REDUCE_BATCH_SIZE).collect());
+    }
+    batches
+}
+
+/// Runs a program of the recursion VM with the given witness stream, returning its execution
+/// record.
+fn execute(program: &Program<Val>, witness_stream: VecDeque<Block<Val>>) -> ExecutionRecord<Val> {
+    let mut runtime = Runtime::<Val, Challenge>::new(program);
+    runtime.witness_stream = witness_stream;
+    tracing::info_span!("execute").in_scope(|| runtime.run());
+    runtime.record
+}
+
+/// The shape of the proof of an execution record, with the log degrees of the traces of all the
+/// chips of the recursion machine.
+fn trace_shape(record: &ExecutionRecord<Val>) -> ShardShape {
+    let machine = RecursionAir::machine(BabyBearPoseidon2::new());
+    let (chip_ids, log_degrees) = machine
+        .chips()
+        .iter()
+        .map(|chip| {
+            let trace = chip.generate_trace(record, &mut ExecutionRecord::default());
+            let height = trace.values.len() / trace.width;
+            (chip.name(), height.trailing_zeros() as usize)
+        })
+        .unzip();
+    ShardShape {
+        chip_ids,
+        log_degrees,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sp1_core::runtime::{Instruction, Opcode, Program as CoreProgram, Runtime as CoreRuntime};
+    use sp1_core::utils::{ProgressMonitor, ProverOptions};
+
+    /// Proves a program of additions with the core machine, in shards of 16 cycles.
+    fn prove_core() -> Proof<BabyBearPoseidon2> {
+        let instructions = (0..40)
+            .map(|_| Instruction::new(Opcode::ADD, 29, 29, 1, false, true))
+            .collect();
+        let program = CoreProgram::new(instructions, 0, 0);
+        let options = ProverOptions::default().with_shard_size(1 << 4);
+        let mut runtime = CoreRuntime::with_options(program, options.runtime_options());
+        runtime.run();
+
+        let machine = RiscvAir::machine(BabyBearPoseidon2::new());
+        let (pk, _) = machine.setup(runtime.program.as_ref());
+        machine
+            .prove_with_options(
+                &LocalProver::with_options(options.clone()),
+                &pk,
+                runtime.record,
+                &mut machine.config().challenger(),
+                &options.sharding_config(),
+                &ProgressMonitor::new(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_challenger_state() {
+        let proof = prove_core();
+        let (_, _, state) = leaf_inputs(&proof).unwrap();
+
+        // The state samples as the challenger which observed the main commitments.
+        let mut challenger = BabyBearPoseidon2::new().challenger();
+        for shard_proof in proof.shard_proofs.iter() {
+            challenger.observe(shard_proof.commitment.main_commit.clone());
+        }
+        let mut builder = VmBuilder::<Val, Challenge>::default();
+        let state_variable = ChallengerStateVariable::read(&mut builder);
+        let mut challenger_variable = state_variable.challenger(&mut builder);
+        for _ in 0..2 * WIDTH {
+            let sample = challenger_variable.sample(&mut builder);
+            let expected: Val = p3_challenger::CanSample::sample(&mut challenger);
+            builder.assert_felt_eq(sample, expected);
+        }
+
+        let mut stream = VecDeque::new();
+        state.write(&mut stream);
+        let mut runtime = Runtime::<Val, Challenge>::new(&builder.compile());
+        runtime.witness_stream = stream;
+        runtime.run();
+    }
+
+    #[test]
+    fn test_compress() {
+        let proof = prove_core();
+        assert_eq!(proof.shard_proofs.len(), 3);
+
+        let compressed = SP1Prover::compress(&proof).unwrap();
+        assert!(compressed.shard_shapes.len() <= 3);
+        assert_eq!(
+            ShardShape::of(&compressed.proof.shard_proofs[0]),
+            compressed.shape
+        );
+        let public_values = PublicValues::from_slice(&compressed.proof.public_values).unwrap();
+        assert_eq!(public_values.end, Val::from_canonical_usize(3));
+        SP1Verifier::verify_compressed(&compressed).unwrap();
+    }
+
+    #[test]
+    fn test_compress_tampered() {
+        let proof = prove_core();
+        let mut compressed = SP1Prover::compress(&proof).unwrap();
+
+        // The root proof of the first two shards only.
+        let (shard_shapes, leaves, challenger) = leaf_inputs(&proof).unwrap();
+        let programs = RecursionPrograms::new(&shard_shapes, &compressed.shape);
+        let prefix_proof = programs.prove(&leaves[..2], &challenger).unwrap();
+        let root_proof = std::mem::replace(&mut compressed.proof, prefix_proof);
+        assert!(matches!(
+            SP1Verifier::verify_compressed(&compressed),
+            Err(CompressedVerificationError::InvalidPublicValues)
+        ));
+        compressed.proof = root_proof;
+
+        // A root proof with other public values.
+        let start = compressed.proof.public_values[0];
+        compressed.proof.public_values[0] = Val::one();
+        assert!(matches!(
+            SP1Verifier::verify_compressed(&compressed),
+            Err(CompressedVerificationError::InvalidProof(_))
+        ));
+        compressed.proof.public_values[0] = start;
+
+        // The proof of a program which verifies nothing, with the expected public values.
+        let mut builder = VmBuilder::<Val, Challenge>::default();
+        for value in compressed.proof.public_values.iter() {
+            let value: Felt<Val> = builder.eval(*value);
+            builder.commit_public_value(value);
+        }
+        let program = builder.compile();
+        let machine = RecursionAir::machine(BabyBearPoseidon2::new());
+        let (pk, _) = machine.setup(&program);
+        let record = execute(&program, VecDeque::new());
+        let empty_proof =
+            machine.prove::<LocalProver<_, _>>(&pk, record, &mut machine.config().challenger());
+        let root_proof = std::mem::replace(&mut compressed.proof, empty_proof);
+        assert!(matches!(
+            SP1Verifier::verify_compressed(&compressed),
+            Err(CompressedVerificationError::InvalidProof(_))
+        ));
+        compressed.proof = root_proof;
+
+        // Programs padded to other heights, whose verifying keys differ.
+        compressed.shape.log_degrees[0] += 1;
+        assert!(matches!(
+            SP1Verifier::verify_compressed(&compressed),
+            Err(CompressedVerificationError::InvalidProof(_))
+        ));
+        compressed.shape.log_degrees[0] -= 1;
+
+        // A shape which is not of all the chips of the recursion machine.
+        let chip_id = compressed.shape.chip_ids.pop().unwrap();
+        let log_degree = compressed.shape.log_degrees.pop().unwrap();
+        assert!(matches!(
+            SP1Verifier::verify_compressed(&compressed),
+            Err(CompressedVerificationError::InvalidShape)
+        ));
+        compressed.shape.chip_ids.push(chip_id);
+        compressed.shape.log_degrees.push(log_degree);
+
+        SP1Verifier::verify_compressed(&compressed).unwrap();
+    }
+}
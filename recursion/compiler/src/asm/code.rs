@@ -53,6 +53,7 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AssemblyCode<F, EF> {
 
         Program {
             instructions: machine_code,
+            min_trace_log_heights: BTreeMap::new(),
         }
     }
 }
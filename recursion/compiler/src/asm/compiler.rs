@@ -8,16 +8,21 @@ use alloc::vec::Vec;
 
 use p3_field::ExtensionField;
 use p3_field::PrimeField32;
-use sp1_recursion_core::runtime::Program;
+use sp1_recursion_core::runtime::{Program, HEAP_START_ADDRESS};
 
 use crate::asm::AsmInstruction;
 use crate::ir::Builder;
 use crate::ir::Usize;
-use crate::ir::{Config, DslIR, Ext, Felt, Var};
+use crate::ir::{Config, DslIR, Ext, Felt, FunctionVariable, MemVariable, Ptr, Var};
 use p3_field::Field;
 
 pub(crate) const ZERO: i32 = 0;
 pub(crate) const HEAP_PTR: i32 = -4;
+pub(crate) const A0: i32 = -5;
+/// The return address of a function call, in the frame of the function.
+pub(crate) const RA: i32 = -6;
+/// The size of the frame of the caller of a function, in the frame of the function.
+pub(crate) const FP_OFFSET: i32 = -7;
 
 pub type VmBuilder<F, EF> = Builder<AsmConfig<F, EF>>;
 
@@ -26,8 +31,19 @@ pub struct AsmCompiler<F, EF> {
     pub basic_blocks: Vec<BasicBlock<F, EF>>,
 
     function_labels: BTreeMap<String, F>,
+
+    /// The parameters and results of the functions defined so far.
+    functions: BTreeMap<String, FunctionSignature<F, EF>>,
+
+    /// The size of the frame of the code being compiled.
+    frame_size: usize,
 }
 
+type FunctionSignature<F, EF> = (
+    Vec<FunctionVariable<AsmConfig<F, EF>>>,
+    Vec<FunctionVariable<AsmConfig<F, EF>>>,
+);
+
 #[derive(Debug, Clone)]
 pub struct AsmConfig<F, EF>(PhantomData<(F, EF)>);
 
@@ -38,19 +54,35 @@ impl<F: Field, EF: ExtensionField<F>> Config for AsmConfig<F, EF> {
 }
 
 impl<F: PrimeField32, EF: ExtensionField<F>> VmBuilder<F, EF> {
-    pub fn compile_to_asm(self) -> AssemblyCode<F, EF> {
+    pub fn compile_to_asm(mut self) -> AssemblyCode<F, EF> {
+        let report = self.optimize();
+        tracing::debug!("optimized program: {}", report);
         let mut compiler = AsmCompiler::new();
+        compiler.frame_size = frame_size(&self);
         compiler.build(self.operations);
         compiler.code()
     }
 
-    pub fn compile(self) -> Program<F> {
+    pub fn compile(mut self) -> Program<F> {
+        let report = self.optimize();
+        tracing::debug!("optimized program: {}", report);
         let mut compiler = AsmCompiler::new();
+        compiler.frame_size = frame_size(&self);
         compiler.build(self.operations);
         compiler.compile()
     }
 }
 
+/// The number of memory cells below the frame pointer which are used by the variables of a
+/// program. The frame of a called function starts right after.
+fn frame_size<C: Config>(builder: &Builder<C>) -> usize {
+    let count = builder
+        .var_count
+        .max(builder.felt_count)
+        .max(builder.ext_count) as usize;
+    3 * count + 8
+}
+
 impl<F> Var<F> {
     fn fp(&self) -> i32 {
         -((self.0 as i32) * 3 + 1 + 8)
@@ -63,19 +95,43 @@ impl<F> Felt<F> {
     }
 }
 
+impl<F> Ptr<F> {
+    fn fp(&self) -> i32 {
+        self.address.fp()
+    }
+}
+
 impl<F, EF> Ext<F, EF> {
     pub fn fp(&self) -> i32 {
         -((self.0 as i32) * 3 + 8)
     }
 }
 
+impl<F: Field, EF: ExtensionField<F>> FunctionVariable<AsmConfig<F, EF>> {
+    fn fp(&self) -> i32 {
+        match self {
+            FunctionVariable::Var(var) => var.fp(),
+            FunctionVariable::Felt(felt) => felt.fp(),
+            FunctionVariable::Ext(ext) => ext.fp(),
+        }
+    }
+}
+
 impl<F: PrimeField32, EF: ExtensionField<F>> AsmCompiler<F, EF> {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self {
+        let mut compiler = Self {
             basic_blocks: vec![BasicBlock::new()],
             function_labels: BTreeMap::new(),
-        }
+            functions: BTreeMap::new(),
+            frame_size: 0,
+        };
+        // Point the heap ptr to the start of the heap.
+        compiler.push(AsmInstruction::IMM(
+            HEAP_PTR,
+            F::from_canonical_usize(HEAP_START_ADDRESS),
+        ));
+        compiler
     }
 
     pub fn build(&mut self, operations: Vec<DslIR<AsmConfig<F, EF>>>) {
@@ -108,8 +164,13 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmCompiler<F, EF> {
                 DslIR::AddEI(dst, lhs, rhs) => {
                     self.push(AsmInstruction::EADDI(dst.fp(), lhs.fp(), rhs));
                 }
-                DslIR::AddEF(_dst, _lhs, _rhs) => todo!(),
-                DslIR::AddEFFI(_dst, _lhs, _rhs) => todo!(),
+                DslIR::AddEF(dst, lhs, rhs) => {
+                    self.push(AsmInstruction::EADD(dst.fp(), lhs.fp(), rhs.fp()));
+                }
+                DslIR::AddEFFI(dst, lhs, rhs) => {
+                    // A felt is stored as the block of its embedding in the extension field.
+                    self.push(AsmInstruction::EADDI(dst.fp(), lhs.fp(), rhs));
+                }
                 DslIR::AddEFI(dst, lhs, rhs) => {
                     self.push(AsmInstruction::EADDI(
                         dst.fp(),
@@ -136,10 +197,10 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmCompiler<F, EF> {
                     self.push(AsmInstruction::SUBIN(dst.fp(), lhs, rhs.fp()));
                 }
                 DslIR::NegV(dst, src) => {
-                    self.push(AsmInstruction::SUBIN(dst.fp(), F::one(), src.fp()));
+                    self.push(AsmInstruction::SUBIN(dst.fp(), F::zero(), src.fp()));
                 }
                 DslIR::NegF(dst, src) => {
-                    self.push(AsmInstruction::SUBIN(dst.fp(), F::one(), src.fp()));
+                    self.push(AsmInstruction::SUBIN(dst.fp(), F::zero(), src.fp()));
                 }
                 DslIR::DivF(dst, lhs, rhs) => {
                     self.push(AsmInstruction::DIV(dst.fp(), lhs.fp(), rhs.fp()));
@@ -156,7 +217,9 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmCompiler<F, EF> {
                 DslIR::InvF(dst, src) => {
                     self.push(AsmInstruction::DIVIN(dst.fp(), F::one(), src.fp()));
                 }
-                DslIR::DivEF(_dst, _lhs, _rhs) => todo!(),
+                DslIR::DivEF(dst, lhs, rhs) => {
+                    self.push(AsmInstruction::EDIV(dst.fp(), lhs.fp(), rhs.fp()));
+                }
                 DslIR::DivEFI(dst, lhs, rhs) => {
                     self.push(AsmInstruction::EDIVI(
                         dst.fp(),
@@ -190,7 +253,9 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmCompiler<F, EF> {
                         rhs.fp(),
                     ));
                 }
-                DslIR::SubEF(_dst, _lhs, _rhs) => todo!(),
+                DslIR::SubEF(dst, lhs, rhs) => {
+                    self.push(AsmInstruction::ESUB(dst.fp(), lhs.fp(), rhs.fp()));
+                }
                 DslIR::SubEFI(dst, lhs, rhs) => {
                     self.push(AsmInstruction::ESUBI(
                         dst.fp(),
@@ -208,7 +273,7 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmCompiler<F, EF> {
                     self.push(AsmInstruction::ESUBI(dst.fp(), lhs.fp(), rhs));
                 }
                 DslIR::NegE(dst, src) => {
-                    self.push(AsmInstruction::ESUBIN(dst.fp(), EF::one(), src.fp()));
+                    self.push(AsmInstruction::ESUBIN(dst.fp(), EF::zero(), src.fp()));
                 }
                 DslIR::MulV(dst, lhs, rhs) => {
                     self.push(AsmInstruction::MUL(dst.fp(), lhs.fp(), rhs.fp()));
@@ -228,8 +293,16 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmCompiler<F, EF> {
                 DslIR::MulEI(dst, lhs, rhs) => {
                     self.push(AsmInstruction::EMULI(dst.fp(), lhs.fp(), rhs));
                 }
-                DslIR::MulEF(_dst, _lhs, _rhs) => todo!(),
-                DslIR::MulEFI(_dst, _lhs, _rhs) => todo!(),
+                DslIR::MulEF(dst, lhs, rhs) => {
+                    self.push(AsmInstruction::EMUL(dst.fp(), lhs.fp(), rhs.fp()));
+                }
+                DslIR::MulEFI(dst, lhs, rhs) => {
+                    self.push(AsmInstruction::EMULI(
+                        dst.fp(),
+                        lhs.fp(),
+                        EF::from_base(rhs),
+                    ));
+                }
                 DslIR::IfEq(lhs, rhs, then_block, else_block) => {
                     let if_compiler = IfCompiler {
                         compiler: self,
@@ -299,10 +372,14 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmCompiler<F, EF> {
                         if start > end {
                             panic!("Start of the loop is greater than the end of the loop");
                         }
-                        for _ in start..end {
+                        for i in start..end {
+                            self.push(AsmInstruction::IMM(
+                                loop_var.fp(),
+                                F::from_canonical_usize(i),
+                            ));
                             self.build(block.clone());
                         }
-                        return;
+                        continue;
                     }
                     let for_compiler = ForCompiler {
                         compiler: self,
@@ -432,22 +509,179 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmCompiler<F, EF> {
                     };
                     if_compiler.then(|builder| builder.push(AsmInstruction::TRAP));
                 }
-                _ => todo!(),
+                DslIR::Alloc(ptr, len, size) => {
+                    self.alloc(ptr, len, size);
+                }
+                DslIR::LoadV(var, ptr, index) => {
+                    self.load(
+                        var.fp(),
+                        ptr,
+                        index,
+                        <Var<F> as MemVariable<AsmConfig<F, EF>>>::size_of(),
+                    );
+                }
+                DslIR::LoadF(var, ptr, index) => {
+                    self.load(
+                        var.fp(),
+                        ptr,
+                        index,
+                        <Felt<F> as MemVariable<AsmConfig<F, EF>>>::size_of(),
+                    );
+                }
+                DslIR::LoadE(var, ptr, index) => {
+                    self.load(
+                        var.fp(),
+                        ptr,
+                        index,
+                        <Ext<F, EF> as MemVariable<AsmConfig<F, EF>>>::size_of(),
+                    );
+                }
+                DslIR::StoreV(var, ptr, index) => {
+                    self.store(
+                        ptr,
+                        var.fp(),
+                        index,
+                        <Var<F> as MemVariable<AsmConfig<F, EF>>>::size_of(),
+                    );
+                }
+                DslIR::StoreF(var, ptr, index) => {
+                    self.store(
+                        ptr,
+                        var.fp(),
+                        index,
+                        <Felt<F> as MemVariable<AsmConfig<F, EF>>>::size_of(),
+                    );
+                }
+                DslIR::StoreE(var, ptr, index) => {
+                    self.store(
+                        ptr,
+                        var.fp(),
+                        index,
+                        <Ext<F, EF> as MemVariable<AsmConfig<F, EF>>>::size_of(),
+                    );
+                }
+                DslIR::Poseidon2PermuteBabyBear(dst, src) => {
+                    self.push(AsmInstruction::Poseidon2Permute(dst.fp(), src.fp()));
+                }
+                DslIR::Num2BitsF(dst, value) => {
+                    self.push(AsmInstruction::NUM2BITS(dst.fp(), value.fp()));
+                }
+                DslIR::HintV(var) => {
+                    self.push(AsmInstruction::HINT(var.fp()));
+                }
+                DslIR::HintF(var) => {
+                    self.push(AsmInstruction::HINT(var.fp()));
+                }
+                DslIR::HintE(var) => {
+                    self.push(AsmInstruction::HINT(var.fp()));
+                }
+                DslIR::CommitF(var) => {
+                    self.push(AsmInstruction::COMMIT(var.fp()));
+                }
+                DslIR::Function(name, parameters, results, body) => {
+                    // The body is emitted in place, so the main flow jumps over it.
+                    let definition_label = self.block_label();
+                    self.basic_block();
+                    self.function_labels
+                        .insert(name.clone(), self.block_label());
+                    self.functions.insert(name, (parameters, results));
+
+                    let caller_frame_size =
+                        core::mem::replace(&mut self.frame_size, frame_size(&body));
+                    self.build(body.operations);
+                    // Return to the caller, restoring its frame pointer.
+                    self.push(AsmInstruction::JALR(ZERO, RA, FP_OFFSET));
+                    self.frame_size = caller_frame_size;
+
+                    self.basic_block();
+                    let main_flow_label = self.block_label();
+                    self.push_to_block(definition_label, AsmInstruction::j(main_flow_label));
+                }
+                DslIR::Call(name, arguments, results) => {
+                    let label = *self
+                        .function_labels
+                        .get(&name)
+                        .unwrap_or_else(|| panic!("function {} is not defined", name));
+                    let (parameters, returns) = self.functions[&name].clone();
+
+                    // The frame of the function starts right after the frame of the caller.
+                    let frame = -(self.frame_size as i32);
+                    for (argument, parameter) in arguments.iter().zip(parameters.iter()) {
+                        self.copy(frame + parameter.fp(), argument.fp(), argument);
+                    }
+                    self.push(AsmInstruction::ADDI(frame + HEAP_PTR, HEAP_PTR, F::zero()));
+                    self.push(AsmInstruction::IMM(
+                        frame + FP_OFFSET,
+                        F::from_canonical_usize(self.frame_size),
+                    ));
+                    self.push(AsmInstruction::JAL(
+                        frame + RA,
+                        label,
+                        -F::from_canonical_usize(self.frame_size),
+                    ));
+
+                    // The function may have allocated memory, and its frame is still intact.
+                    self.push(AsmInstruction::ADDI(HEAP_PTR, frame + HEAP_PTR, F::zero()));
+                    for (result, value) in results.iter().zip(returns.iter()) {
+                        self.copy(result.fp(), frame + value.fp(), result);
+                    }
+                }
             }
         }
     }
 
-    pub fn alloc(&mut self, ptr: Var<F>, len: Usize<F>) {
+    pub fn alloc(&mut self, ptr: Ptr<F>, len: Usize<F>, size: usize) {
         // Load the current heap ptr address to the stack value and advance the heap ptr.
+        let size = F::from_canonical_usize(size);
+        self.push(AsmInstruction::ADDI(ptr.fp(), HEAP_PTR, F::zero()));
         match len {
             Usize::Const(len) => {
                 let len = F::from_canonical_usize(len);
-                self.push(AsmInstruction::IMM(ptr.fp(), len));
-                self.push(AsmInstruction::ADDI(HEAP_PTR, HEAP_PTR, len));
+                self.push(AsmInstruction::ADDI(HEAP_PTR, HEAP_PTR, len * size));
             }
             Usize::Var(len) => {
-                self.push(AsmInstruction::ADDI(ptr.fp(), len.fp(), F::zero()));
-                self.push(AsmInstruction::ADDI(HEAP_PTR, HEAP_PTR, F::one()));
+                self.push(AsmInstruction::MULI(A0, len.fp(), size));
+                self.push(AsmInstruction::ADD(HEAP_PTR, HEAP_PTR, A0));
+            }
+        }
+    }
+
+    /// Loads the element at `index` of the array at `ptr` into `dst(fp)`.
+    fn load(&mut self, dst: i32, ptr: Ptr<F>, index: Usize<F>, size: usize) {
+        match index {
+            Usize::Const(index) => {
+                let offset = F::from_canonical_usize(index * size);
+                self.push(AsmInstruction::LOAD(dst, ptr.fp(), offset));
+            }
+            Usize::Var(index) => {
+                let size = F::from_canonical_usize(size);
+                self.push(AsmInstruction::MULI(A0, index.fp(), size));
+                self.push(AsmInstruction::ADD(A0, A0, ptr.fp()));
+                self.push(AsmInstruction::LOAD(dst, A0, F::zero()));
+            }
+        }
+    }
+
+    /// Copies `src(fp)` to `dst(fp)`, where both hold a value of the same type as `variable`.
+    fn copy(&mut self, dst: i32, src: i32, variable: &FunctionVariable<AsmConfig<F, EF>>) {
+        match variable {
+            FunctionVariable::Ext(_) => self.push(AsmInstruction::EADDI(dst, src, EF::zero())),
+            _ => self.push(AsmInstruction::ADDI(dst, src, F::zero())),
+        }
+    }
+
+    /// Stores `src(fp)` as the element at `index` of the array at `ptr`.
+    fn store(&mut self, ptr: Ptr<F>, src: i32, index: Usize<F>, size: usize) {
+        match index {
+            Usize::Const(index) => {
+                let offset = F::from_canonical_usize(index * size);
+                self.push(AsmInstruction::STORE(ptr.fp(), src, offset));
+            }
+            Usize::Var(index) => {
+                let size = F::from_canonical_usize(size);
+                self.push(AsmInstruction::MULI(A0, index.fp(), size));
+                self.push(AsmInstruction::ADD(A0, A0, ptr.fp()));
+                self.push(AsmInstruction::STORE(A0, src, F::zero()));
             }
         }
     }
@@ -16,6 +16,12 @@ pub enum AsmInstruction<F, EF> {
     LW(i32, i32),
     /// Store word (src, dst) : store a value from src(fp) into the address stored at dest(fp).
     SW(i32, i32),
+    /// Load from the heap (dst, ptr, offset) : load the value at the address stored at ptr(fp)
+    /// plus offset into dst(fp).
+    LOAD(i32, i32, F),
+    /// Store to the heap (ptr, src, offset) : store the value in src(fp) to the address stored at
+    /// ptr(fp) plus offset.
+    STORE(i32, i32, F),
     // Get immediate (dst, value) : load a value into the dest(fp).
     IMM(i32, F),
     /// Add, dst = lhs + rhs.
@@ -67,9 +73,11 @@ pub enum AsmInstruction<F, EF> {
     /// Divide value from immediate extension, dst = lhs / rhs.
     EDIVIN(i32, EF, i32),
 
-    /// Jump and link
+    /// Jump and link (dst, label, offset) : store the return address in dst(fp), jump to the label
+    /// and move the frame pointer by offset.
     JAL(i32, F, F),
-    /// Jump and link value
+    /// Jump and link value (dst, target, offset) : store the return address in dst(fp), jump to the
+    /// address stored at target(fp) and move the frame pointer by the value stored at offset(fp).
     JALR(i32, i32, i32),
     /// Branch not equal
     BNE(F, i32, i32),
@@ -89,6 +97,19 @@ pub enum AsmInstruction<F, EF> {
     EBEQI(F, i32, EF),
     /// Trap
     TRAP,
+
+    /// Poseidon2 permutation (dst, src) : permute the state at the address stored at src(fp) and
+    /// write it to the address stored at dst(fp).
+    Poseidon2Permute(i32, i32),
+
+    /// Bit decomposition (dst, src) : write the bits of src(fp) to the address stored at dst(fp).
+    NUM2BITS(i32, i32),
+
+    /// Hint (dst) : read the next value of the witness stream into dst(fp).
+    HINT(i32),
+
+    /// Commit (src) : expose src(fp) as the next public value of the proof.
+    COMMIT(i32),
 }
 
 impl<F: PrimeField32, EF: ExtensionField<F>> AsmInstruction<F, EF> {
@@ -129,6 +150,26 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmInstruction<F, EF> {
                 false,
                 false,
             ),
+            AsmInstruction::LOAD(dst, ptr, offset) => Instruction::new(
+                Opcode::LOAD,
+                i32_f(dst),
+                i32_f_arr(ptr),
+                f_u32(offset),
+                false,
+                false,
+                false,
+                false,
+            ),
+            AsmInstruction::STORE(ptr, src, offset) => Instruction::new(
+                Opcode::STORE,
+                i32_f(ptr),
+                i32_f_arr(src),
+                f_u32(offset),
+                false,
+                false,
+                false,
+                false,
+            ),
             AsmInstruction::IMM(dst, value) => Instruction::new(
                 Opcode::LW,
                 i32_f(dst),
@@ -484,12 +525,13 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmInstruction<F, EF> {
             AsmInstruction::JAL(dst, label, offset) => {
                 let pc_offset =
                     F::from_canonical_usize(label_to_pc[&label]) - F::from_canonical_usize(pc);
+                // The pc offset and the frame pointer offset are both immediates.
                 Instruction::new(
                     Opcode::JAL,
                     i32_f(dst),
                     f_u32(pc_offset),
                     f_u32(offset),
-                    false,
+                    true,
                     false,
                     true,
                     false,
@@ -515,6 +557,46 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmInstruction<F, EF> {
                 false,
                 false,
             ),
+            AsmInstruction::Poseidon2Permute(dst, src) => Instruction::new(
+                Opcode::POSEIDON2_PERM,
+                i32_f(dst),
+                i32_f_arr(src),
+                zero,
+                false,
+                false,
+                true,
+                false,
+            ),
+            AsmInstruction::NUM2BITS(dst, src) => Instruction::new(
+                Opcode::NUM2BITS,
+                i32_f(dst),
+                i32_f_arr(src),
+                zero,
+                false,
+                false,
+                true,
+                false,
+            ),
+            AsmInstruction::HINT(dst) => Instruction::new(
+                Opcode::HINT,
+                i32_f(dst),
+                zero,
+                zero,
+                false,
+                false,
+                false,
+                false,
+            ),
+            AsmInstruction::COMMIT(src) => Instruction::new(
+                Opcode::COMMIT,
+                i32_f(src),
+                zero,
+                zero,
+                true,
+                false,
+                true,
+                false,
+            ),
         }
     }
 
@@ -522,6 +604,12 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmInstruction<F, EF> {
         match self {
             AsmInstruction::LW(dst, src) => write!(f, "lw    ({})fp, ({})fp", dst, src),
             AsmInstruction::SW(dst, src) => write!(f, "sw    ({})fp, ({})fp", dst, src),
+            AsmInstruction::LOAD(dst, ptr, offset) => {
+                write!(f, "load  ({})fp, ({})fp, {}", dst, ptr, offset)
+            }
+            AsmInstruction::STORE(ptr, src, offset) => {
+                write!(f, "store ({})fp, ({})fp, {}", ptr, src, offset)
+            }
             AsmInstruction::IMM(dst, value) => write!(f, "imm   ({})fp, {}", dst, value),
             AsmInstruction::ADD(dst, lhs, rhs) => {
                 write!(f, "add   ({})fp, ({})fp, ({})fp", dst, lhs, rhs)
@@ -679,6 +767,14 @@ impl<F: PrimeField32, EF: ExtensionField<F>> AsmInstruction<F, EF> {
                 )
             }
             AsmInstruction::TRAP => write!(f, "trap"),
+            AsmInstruction::Poseidon2Permute(dst, src) => {
+                write!(f, "poseidon2_perm ({})fp, ({})fp", dst, src)
+            }
+            AsmInstruction::NUM2BITS(dst, src) => {
+                write!(f, "num2bits ({})fp, ({})fp", dst, src)
+            }
+            AsmInstruction::HINT(dst) => write!(f, "hint  ({})fp", dst),
+            AsmInstruction::COMMIT(src) => write!(f, "commit ({})fp", src),
         }
     }
 }
@@ -56,6 +56,20 @@ func NewExtensionVariable(value [4]int) *ExtensionVariable {
 	return &ExtensionVariable{value: [4]*Variable{a, b, c, d}}
 }
 
+func (c *Chip) FromVariable(value frontend.Variable) *Variable {
+	return &Variable{
+		value: c.field.NewElement(value),
+	}
+}
+
+func (c *Chip) FromExtensionVariable(value [4]frontend.Variable) *ExtensionVariable {
+	a := c.FromVariable(value[0])
+	b := c.FromVariable(value[1])
+	d := c.FromVariable(value[2])
+	e := c.FromVariable(value[3])
+	return &ExtensionVariable{value: [4]*Variable{a, b, d, e}}
+}
+
 func (c *Chip) Add(a, b *Variable) *Variable {
 	return &Variable{
 		value: c.field.Add(a.value, b.value),
@@ -86,6 +100,23 @@ func (c *Chip) Inv(a *Variable) *Variable {
 	}
 }
 
+func (c *Chip) Div(a, b *Variable) *Variable {
+	return &Variable{
+		value: c.field.Div(a.value, b.value),
+	}
+}
+
+func (c *Chip) ToBinary(a *Variable) []*Variable {
+	bits := c.field.ToBits(a.value)
+	result := make([]*Variable, len(bits))
+	for i, bit := range bits {
+		result[i] = &Variable{
+			value: c.field.FromBits(bit),
+		}
+	}
+	return result
+}
+
 func (c *Chip) AssertEq(a, b *Variable) {
 	c.field.AssertIsEqual(a.value, b.value)
 }
@@ -158,6 +189,32 @@ func (c *Chip) InvExtension(a *ExtensionVariable) *ExtensionVariable {
 	return &ExtensionVariable{value: v}
 }
 
+func (c *Chip) DivExtension(a, b *ExtensionVariable) *ExtensionVariable {
+	return c.MulExtension(a, c.InvExtension(b))
+}
+
+func (c *Chip) AddFelt(a *ExtensionVariable, b *Variable) *ExtensionVariable {
+	v1 := c.Add(a.value[0], b)
+	return &ExtensionVariable{value: [4]*Variable{v1, a.value[1], a.value[2], a.value[3]}}
+}
+
+func (c *Chip) SubFelt(a *ExtensionVariable, b *Variable) *ExtensionVariable {
+	v1 := c.Sub(a.value[0], b)
+	return &ExtensionVariable{value: [4]*Variable{v1, a.value[1], a.value[2], a.value[3]}}
+}
+
+func (c *Chip) MulFelt(a *ExtensionVariable, b *Variable) *ExtensionVariable {
+	v1 := c.Mul(a.value[0], b)
+	v2 := c.Mul(a.value[1], b)
+	v3 := c.Mul(a.value[2], b)
+	v4 := c.Mul(a.value[3], b)
+	return &ExtensionVariable{value: [4]*Variable{v1, v2, v3, v4}}
+}
+
+func (c *Chip) DivFelt(a *ExtensionVariable, b *Variable) *ExtensionVariable {
+	return c.MulFelt(a, c.Inv(b))
+}
+
 func (c *Chip) AssertEqExtension(a, b *ExtensionVariable) {
 	c.AssertEq(a.value[0], b.value[0])
 	c.AssertEq(a.value[1], b.value[1])
@@ -187,3 +244,72 @@ func (c *Chip) SelectExtension(cond frontend.Variable, a, b *ExtensionVariable)
 	v4 := c.Select(cond, a.value[3], b.value[3])
 	return &ExtensionVariable{value: [4]*Variable{v1, v2, v3, v4}}
 }
+
+// Applies the Poseidon2 permutation of width 16 used by the recursion VM to a state. The rounds
+// in [partialRoundsStart, partialRoundsEnd) are partial rounds and the others are full rounds.
+func (c *Chip) Poseidon2Permute(
+	input [16]*Variable,
+	roundConstants [][16]int,
+	internalDiagonal [16]int,
+	partialRoundsStart, partialRoundsEnd int,
+) [16]*Variable {
+	state := input
+	c.poseidon2ExternalLayer(&state)
+	for round, constants := range roundConstants {
+		if round < partialRoundsStart || round >= partialRoundsEnd {
+			for i := range state {
+				state[i] = c.poseidon2Sbox(c.Add(state[i], NewVariable(constants[i])))
+			}
+			c.poseidon2ExternalLayer(&state)
+		} else {
+			state[0] = c.poseidon2Sbox(c.Add(state[0], NewVariable(constants[0])))
+			c.poseidon2InternalLayer(&state, internalDiagonal)
+		}
+	}
+	return state
+}
+
+// The S-box x -> x^7.
+func (c *Chip) poseidon2Sbox(x *Variable) *Variable {
+	x2 := c.Mul(x, x)
+	x3 := c.Mul(x2, x)
+	x6 := c.Mul(x3, x3)
+	return c.Mul(x6, x)
+}
+
+// The 4x4 MDS matrix applied to each chunk of four elements, followed by adding the sum of the
+// elements in the same position of every chunk.
+func (c *Chip) poseidon2ExternalLayer(state *[16]*Variable) {
+	two := NewVariable(2)
+	four := NewVariable(4)
+	for j := 0; j < 16; j += 4 {
+		t0 := c.Add(state[j], state[j+1])
+		t1 := c.Add(state[j+2], state[j+3])
+		t2 := c.Add(c.Mul(state[j+1], two), t1)
+		t3 := c.Add(c.Mul(state[j+3], two), t0)
+		t4 := c.Add(c.Mul(t1, four), t3)
+		t5 := c.Add(c.Mul(t0, four), t2)
+		state[j] = c.Add(t3, t5)
+		state[j+1] = t5
+		state[j+2] = c.Add(t2, t4)
+		state[j+3] = t4
+	}
+	var sums [4]*Variable
+	for k := range sums {
+		sums[k] = c.Add(c.Add(state[k], state[k+4]), c.Add(state[k+8], state[k+12]))
+	}
+	for i := range state {
+		state[i] = c.Add(state[i], sums[i%4])
+	}
+}
+
+// The internal linear layer x -> diag * x + sum(x) used by the partial rounds.
+func (c *Chip) poseidon2InternalLayer(state *[16]*Variable, diagonal [16]int) {
+	sum := state[0]
+	for i := 1; i < 16; i++ {
+		sum = c.Add(sum, state[i])
+	}
+	for i := range state {
+		state[i] = c.Add(c.Mul(state[i], NewVariable(diagonal[i])), sum)
+	}
+}
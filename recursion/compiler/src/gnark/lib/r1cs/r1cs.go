@@ -0,0 +1,65 @@
+// Package r1cs imports the rank-1 constraint systems exported by the R1CS backend of
+// sp1-recursion-compiler into gnark circuits.
+package r1cs
+
+import (
+	"encoding/json"
+	"fmt"
+	"math/big"
+
+	"github.com/consensys/gnark/frontend"
+)
+
+// A term of a linear combination: the index of a variable and its coefficient, in decimal.
+type term [2]interface{}
+
+type system struct {
+	NumVariables int         `json:"num_variables"`
+	Constraints  [][3][]term `json:"constraints"`
+}
+
+// Import asserts the constraints of an exported system, given as JSON, over the variables of a
+// circuit. The variable 0 of the system is the constant one, so the witness has one variable
+// fewer than the system.
+func Import(api frontend.API, data []byte, witness []frontend.Variable) error {
+	var s system
+	if err := json.Unmarshal(data, &s); err != nil {
+		return err
+	}
+	if len(witness)+1 != s.NumVariables {
+		return fmt.Errorf("expected %d variables, got %d", s.NumVariables-1, len(witness))
+	}
+	variables := append([]frontend.Variable{frontend.Variable(1)}, witness...)
+	for _, constraint := range s.Constraints {
+		var lcs [3]frontend.Variable
+		for i, lc := range constraint {
+			value, err := evaluate(api, lc, variables)
+			if err != nil {
+				return err
+			}
+			lcs[i] = value
+		}
+		api.AssertIsEqual(api.Mul(lcs[0], lcs[1]), lcs[2])
+	}
+	return nil
+}
+
+func evaluate(api frontend.API, lc []term, variables []frontend.Variable) (frontend.Variable, error) {
+	var sum frontend.Variable = 0
+	for _, t := range lc {
+		index, ok := t[0].(float64)
+		if !ok || int(index) >= len(variables) {
+			return nil, fmt.Errorf("invalid variable %v", t[0])
+		}
+		digits, ok := t[1].(string)
+		if !ok {
+			return nil, fmt.Errorf("invalid coefficient %v", t[1])
+		}
+		coefficient, ok := new(big.Int).SetString(digits, 10)
+		if !ok {
+			return nil, fmt.Errorf("invalid coefficient %s", digits)
+		}
+		sum = api.Add(sum, api.Mul(variables[int(index)], coefficient))
+	}
+	return sum, nil
+}
@@ -1,7 +1,15 @@
+use core::fmt;
 use core::marker::PhantomData;
 use std::collections::HashMap;
 
-use crate::ir::{Config, DslIR};
+use p3_field::{AbstractExtensionField, PrimeField32};
+use sp1_core::utils::poseidon2_instance::RC_16_30;
+use sp1_recursion_core::poseidon2::{
+    internal_diagonal, PARTIAL_ROUNDS_END, PARTIAL_ROUNDS_START, WIDTH,
+};
+
+use crate::ir::{Config, DslIR, Ptr, Usize};
+use crate::opt::optimize;
 
 const GNARK_TEMPLATE: &str = include_str!("lib/template.txt");
 
@@ -10,34 +18,145 @@ pub fn indent(lines: Vec<String>) -> Vec<String> {
     lines.into_iter().map(|x| format!("\t{}", x)).collect()
 }
 
-/// Masks the evaluation of lines based on a condition.
-pub fn mask(cond: String, lines: Vec<String>) -> Vec<String> {
-    lines
+/// The Go slice which models the memory allocated at `ptr`, with one element per variable.
+fn memory<N>(ptr: Ptr<N>) -> String {
+    format!("mem_{}", ptr.address.id())
+}
+
+/// The offset of a variable in a memory slice. Variable offsets must be constant when the circuit
+/// is defined, e.g. loop counters.
+fn offset<N>(offset: Usize<N>) -> String {
+    match offset {
+        Usize::Const(offset) => offset.to_string(),
+        Usize::Var(offset) => format!("constIndex(api, {})", offset.id()),
+    }
+}
+
+/// The Go type of a variable.
+fn go_type(id: &str) -> &'static str {
+    if id.starts_with("mem") {
+        "[]interface{}"
+    } else if id.contains("var") {
+        "frontend.Variable"
+    } else if id.contains("felt") {
+        "*babybear.Variable"
+    } else if id.contains("ext") {
+        "*babybear.ExtensionVariable"
+    } else if id.contains("backend") {
+        "frontend.Variable"
+    } else {
+        panic!("Unknown variable type")
+    }
+}
+
+/// The zero value of a variable of the Go type `ty`.
+fn go_zero(ty: &str) -> &'static str {
+    match ty {
+        "*babybear.Variable" => "babybear.NewVariable(0)",
+        "*babybear.ExtensionVariable" => "babybear.NewExtensionVariable([4]int{0, 0, 0, 0})",
+        _ => "frontend.Variable(0)",
+    }
+}
+
+/// The function which selects one of two values of the Go type `ty`.
+fn go_select(ty: &str) -> &'static str {
+    match ty {
+        "*babybear.Variable" => "fieldChip.Select",
+        "*babybear.ExtensionVariable" => "fieldChip.SelectExtension",
+        _ => "api.Select",
+    }
+}
+
+/// An extension field element as a constant of the circuit.
+fn ext_imm<C: Config>(value: C::EF) -> String {
+    let coefficients = value
+        .as_base_slice()
         .iter()
-        .map(|line| {
-            let parts = line.split('=').collect::<Vec<_>>();
-            let dst = parts[0].trim_end();
-            let expr = parts[1].trim_end();
-            if dst.contains("var") {
-                format!("{dst} = api.Select({cond}, {expr}, {dst}")
-            } else if dst.contains("felt") {
-                format!("{dst} = fieldChip.Select({cond}, {expr}, {dst})")
-            } else if dst.contains("ext") {
-                format!("{dst} = fieldChip.SelectExtension({cond}, {expr}, {dst}")
-            } else {
-                panic!("unexpected dst for DslIR::IfEq")
-            }
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>();
+    format!(
+        "babybear.NewExtensionVariable([4]int{{{}}})",
+        coefficients.join(", ")
+    )
+}
+
+/// The Go declarations of the round constants and internal diagonal of the Poseidon2
+/// permutation.
+fn poseidon2_constants() -> Vec<String> {
+    let round_constants = RC_16_30
+        .iter()
+        .map(|round| {
+            let round = round
+                .iter()
+                .map(|c| c.as_canonical_u32().to_string())
+                .collect::<Vec<_>>();
+            format!("{{{}}}", round.join(", "))
         })
-        .collect()
+        .collect::<Vec<_>>();
+    let diagonal = internal_diagonal()
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>();
+    vec![
+        format!(
+            "poseidon2RoundConstants := [][{WIDTH}]int{{{}}}",
+            round_constants.join(", ")
+        ),
+        format!(
+            "poseidon2InternalDiagonal := [{WIDTH}]int{{{}}}",
+            diagonal.join(", ")
+        ),
+    ]
+}
+
+/// An instruction of a program which cannot be lowered to a gnark circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GnarkError {
+    /// A loop whose bounds are not constant, as the circuit is unrolled when it is defined.
+    VariableLoopBounds,
+    /// A public value, as the circuit has no public inputs.
+    CommitPublicValue,
+}
+
+impl fmt::Display for GnarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GnarkError::VariableLoopBounds => {
+                write!(f, "loops in a gnark circuit must have constant bounds")
+            }
+            GnarkError::CommitPublicValue => {
+                write!(f, "public values are not supported by the gnark backend")
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct GnarkBackend<C: Config> {
     pub nb_backend_vars: usize,
+    pub nb_witness_vars: usize,
     pub used: HashMap<String, bool>,
+    /// The condition under which the emitted lines take effect, inside the branches of a
+    /// conditional or the body of a function.
+    pub cond: Option<String>,
+    /// Whether the circuit applies the Poseidon2 permutation, whose constants are then declared.
+    pub poseidon2: bool,
     pub phantom: PhantomData<C>,
 }
 
+impl<C: Config> Default for GnarkBackend<C> {
+    fn default() -> Self {
+        Self {
+            nb_backend_vars: 0,
+            nb_witness_vars: 0,
+            used: HashMap::new(),
+            cond: None,
+            poseidon2: false,
+            phantom: PhantomData,
+        }
+    }
+}
+
 impl<C: Config> GnarkBackend<C> {
     pub fn alloc(&mut self) -> String {
         let id = format!("backend{}", self.nb_backend_vars);
@@ -45,608 +164,663 @@ impl<C: Config> GnarkBackend<C> {
         id
     }
 
-    pub fn assign(&mut self, id: String) -> &str {
+    /// Allocates the counter of a loop.
+    pub fn index(&mut self) -> String {
+        let id = format!("i{}", self.nb_backend_vars);
+        self.nb_backend_vars += 1;
+        id
+    }
+
+    /// Returns the next element of the witness of the circuit, which supplies the hinted values.
+    pub fn witness(&mut self) -> String {
+        let id = format!("circuit.Witness[{}]", self.nb_witness_vars);
+        self.nb_witness_vars += 1;
+        id
+    }
+
+    /// Assigns `expr` to the variable `id`, which keeps its value when the current condition is
+    /// false.
+    pub fn assign(&mut self, id: String, expr: String) -> String {
         self.used.insert(id.clone(), true);
-        "="
+        match &self.cond {
+            Some(cond) => format!("{id} = {}({cond}, {expr}, {id})", go_select(go_type(&id))),
+            None => format!("{id} = {expr}"),
+        }
+    }
+
+    /// Stores `value`, of the Go type `ty`, at `offset` of the memory slice `mem`, which keeps its
+    /// value when the current condition is false.
+    fn store(&self, mem: &str, offset: &str, value: &str, ty: &str) -> Vec<String> {
+        match &self.cond {
+            Some(cond) => vec![
+                format!("if {mem}[{offset}] == nil {{"),
+                format!("\t{mem}[{offset}] = {}", go_zero(ty)),
+                "}".to_string(),
+                format!(
+                    "{mem}[{offset}] = {}({cond}, {value}, {mem}[{offset}].({ty}))",
+                    go_select(ty)
+                ),
+            ],
+            None => vec![format!("{mem}[{offset}] = {value}")],
+        }
+    }
+
+    /// Asserts that `a` and `b`, of the Go type `ty`, are equal (or different) when the current
+    /// condition holds. Under a condition, native variables assert that the condition times their
+    /// difference is zero, and emulated ones replace `a` by a value which passes the assertion
+    /// when the condition is false.
+    fn assert(&self, ty: &str, equal: bool, a: String, b: String) -> String {
+        let (assert_eq, assert_ne, add, one) = match ty {
+            "*babybear.Variable" => (
+                "fieldChip.AssertEq",
+                "fieldChip.AssertNe",
+                "fieldChip.Add",
+                "babybear.NewVariable(1)",
+            ),
+            "*babybear.ExtensionVariable" => (
+                "fieldChip.AssertEqExtension",
+                "fieldChip.AssertNeExtension",
+                "fieldChip.AddExtension",
+                "babybear.NewExtensionVariable([4]int{1, 0, 0, 0})",
+            ),
+            _ => (
+                "api.AssertIsEqual",
+                "api.AssertIsDifferent",
+                "api.Add",
+                "frontend.Variable(1)",
+            ),
+        };
+        let select = go_select(ty);
+        match (&self.cond, equal) {
+            (None, true) => format!("{assert_eq}({a}, {b})"),
+            (None, false) => format!("{assert_ne}({a}, {b})"),
+            (Some(cond), true) if ty == "frontend.Variable" => {
+                format!("{assert_eq}(api.Mul({cond}, api.Sub({a}, {b})), frontend.Variable(0))")
+            }
+            (Some(cond), true) => format!("{assert_eq}({select}({cond}, {a}, {b}), {b})"),
+            (Some(cond), false) => {
+                format!("{assert_ne}({select}({cond}, {a}, {add}({b}, {one})), {b})")
+            }
+        }
+    }
+
+    /// Emits the branches of a conditional on `cond`, which is one when the condition holds and
+    /// zero otherwise. Each branch is masked by its own condition within the current one.
+    fn conditional(
+        &mut self,
+        cond: String,
+        then_branch: Vec<DslIR<C>>,
+        else_branch: Vec<DslIR<C>>,
+    ) -> Result<Vec<String>, GnarkError> {
+        let outer = self.cond.take();
+        let then_cond = self.alloc();
+        let else_cond = self.alloc();
+        let then_expr = match &outer {
+            Some(outer) => format!("api.Mul({outer}, {cond})"),
+            None => cond,
+        };
+        let else_expr = format!(
+            "api.Sub({}, {then_cond})",
+            outer.as_deref().unwrap_or("frontend.Variable(1)")
+        );
+        let mut lines = vec![
+            self.assign(then_cond.clone(), then_expr),
+            self.assign(else_cond.clone(), else_expr),
+        ];
+
+        self.cond = Some(then_cond);
+        let then_lines = self.emit(then_branch);
+        self.cond = Some(else_cond);
+        let else_lines = self.emit(else_branch);
+        self.cond = outer;
+
+        lines.extend(then_lines?);
+        lines.extend(else_lines?);
+        Ok(lines)
     }
 
-    pub fn emit(&mut self, operations: Vec<DslIR<C>>) -> Vec<String> {
+    pub fn emit(&mut self, operations: Vec<DslIR<C>>) -> Result<Vec<String>, GnarkError> {
         let mut lines: Vec<String> = Vec::new();
         for instruction in operations {
             match instruction {
                 DslIR::Imm(a, b) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!("{} {} frontend.Variable({})", a.id(), operator, b));
+                    lines.push(self.assign(a.id(), format!("frontend.Variable({})", b)));
                 }
                 DslIR::ImmFelt(a, b) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} babybear.NewVariable({})",
-                        a.id(),
-                        operator,
-                        b
-                    ));
+                    lines.push(self.assign(a.id(), format!("babybear.NewVariable({})", b)));
                 }
                 DslIR::ImmExt(a, b) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} babybear.NewExtensionVariable({})",
-                        a.id(),
-                        operator,
-                        b
-                    ));
+                    lines.push(self.assign(a.id(), ext_imm::<C>(b)));
                 }
                 DslIR::AddV(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} api.Add({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ))
+                    lines.push(self.assign(a.id(), format!("api.Add({}, {})", b.id(), c.id())));
                 }
                 DslIR::AddVI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} api.Add({}, frontend.Variable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("api.Add({}, frontend.Variable({}))", b.id(), c),
                     ));
                 }
                 DslIR::AddF(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Add({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(
+                        self.assign(a.id(), format!("fieldChip.Add({}, {})", b.id(), c.id())),
+                    );
                 }
                 DslIR::AddFI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Add({}, babybear.NewVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("fieldChip.Add({}, babybear.NewVariable({}))", b.id(), c),
                     ));
                 }
                 DslIR::AddE(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.AddExtension({}, {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
+                        format!("fieldChip.AddExtension({}, {})", b.id(), c.id()),
                     ));
                 }
                 DslIR::AddEI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.AddExtension({}, babybear.NewExtensionVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("fieldChip.AddExtension({}, {})", b.id(), ext_imm::<C>(c)),
                     ));
                 }
                 DslIR::AddEFI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.AddFelt({}, babybear.NewVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("fieldChip.AddFelt({}, babybear.NewVariable({}))", b.id(), c),
                     ));
                 }
                 DslIR::AddEFFI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.AddFelt(babybear.NewExtensionVariable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        c,
-                        b.id()
+                        format!("fieldChip.AddFelt({}, {})", ext_imm::<C>(c), b.id()),
                     ));
                 }
                 DslIR::AddEF(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.AddFelt({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(
+                        self.assign(a.id(), format!("fieldChip.AddFelt({}, {})", b.id(), c.id())),
+                    );
                 }
                 DslIR::MulV(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} api.Mul({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(self.assign(a.id(), format!("api.Mul({}, {})", b.id(), c.id())));
                 }
                 DslIR::MulVI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} api.Mul(frontend.Variable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("api.Mul(frontend.Variable({}), {})", b.id(), c),
                     ));
                 }
                 DslIR::MulF(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Mul({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(
+                        self.assign(a.id(), format!("fieldChip.Mul({}, {})", b.id(), c.id())),
+                    );
                 }
                 DslIR::MulFI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Mul({}, babybear.NewVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("fieldChip.Mul({}, babybear.NewVariable({}))", b.id(), c),
                     ));
                 }
                 DslIR::MulE(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.MulExtension({}, {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
+                        format!("fieldChip.MulExtension({}, {})", b.id(), c.id()),
                     ));
                 }
                 DslIR::MulEI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.MulExtension({}, babybear.NewExtensionVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("fieldChip.MulExtension({}, {})", b.id(), ext_imm::<C>(c)),
                     ));
                 }
                 DslIR::MulEFI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.MulFelt({}, babybear.NewVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        c,
-                        b.id()
+                        format!("fieldChip.MulFelt({}, babybear.NewVariable({}))", b.id(), c),
                     ));
                 }
                 DslIR::MulEF(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Mul({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(
+                        self.assign(a.id(), format!("fieldChip.MulFelt({}, {})", b.id(), c.id())),
+                    );
                 }
                 DslIR::SubV(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} api.Sub({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(self.assign(a.id(), format!("api.Sub({}, {})", b.id(), c.id())));
                 }
                 DslIR::SubVI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} api.Sub(frontend.Variable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("api.Sub(frontend.Variable({}), {})", b.id(), c),
                     ));
                 }
                 DslIR::SubVIN(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} api.Sub(frontend.Variable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b,
-                        c.id()
+                        format!("api.Sub(frontend.Variable({}), {})", b, c.id()),
                     ));
                 }
                 DslIR::SubF(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Sub({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(
+                        self.assign(a.id(), format!("fieldChip.Sub({}, {})", b.id(), c.id())),
+                    );
                 }
                 DslIR::SubFI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Sub({}, babybear.NewVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("fieldChip.Sub({}, babybear.NewVariable({}))", b.id(), c),
                     ));
                 }
                 DslIR::SubFIN(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Sub(babybear.NewVariable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b,
-                        c.id()
+                        format!("fieldChip.Sub(babybear.NewVariable({}), {})", b, c.id()),
                     ));
                 }
                 DslIR::SubE(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.SubExtension({}, {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
+                        format!("fieldChip.SubExtension({}, {})", b.id(), c.id()),
                     ));
                 }
                 DslIR::SubEI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.SubExtension({}, babybear.NewExtensionVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("fieldChip.SubExtension({}, {})", b.id(), ext_imm::<C>(c)),
                     ));
                 }
                 DslIR::SubEIN(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.SubExtension(babybear.NewExtensionVariable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b,
-                        c.id()
+                        format!("fieldChip.SubExtension({}, {})", ext_imm::<C>(b), c.id()),
                     ));
                 }
                 DslIR::SubEFI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.SubFelt({}, babybear.NewVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        c,
-                        b.id()
+                        format!("fieldChip.SubFelt({}, babybear.NewVariable({}))", b.id(), c),
                     ));
                 }
                 DslIR::SubEFIN(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.SubFelt(babybear.NewExtensionVariable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b,
-                        c.id()
+                        format!(
+                            "fieldChip.SubExtension({}, {})",
+                            ext_imm::<C>(C::EF::from_base(b)),
+                            c.id()
+                        ),
                     ));
                 }
                 DslIR::SubEF(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.SubFelt({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(
+                        self.assign(a.id(), format!("fieldChip.SubFelt({}, {})", b.id(), c.id())),
+                    );
                 }
                 DslIR::DivF(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Div({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(
+                        self.assign(a.id(), format!("fieldChip.Div({}, {})", b.id(), c.id())),
+                    );
                 }
                 DslIR::DivFI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Div({}, babybear.NewVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("fieldChip.Div({}, babybear.NewVariable({}))", b.id(), c),
                     ));
                 }
                 DslIR::DivFIN(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.Div(babybear.NewVariable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b,
-                        c.id()
+                        format!("fieldChip.Div(babybear.NewVariable({}), {})", b, c.id()),
                     ));
                 }
                 DslIR::DivE(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.DivExtension({}, {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
+                        format!("fieldChip.DivExtension({}, {})", b.id(), c.id()),
                     ));
                 }
                 DslIR::DivEI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.DivExtension({}, babybear.NewExtensionVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b.id(),
-                        c
+                        format!("fieldChip.DivExtension({}, {})", b.id(), ext_imm::<C>(c)),
                     ));
                 }
                 DslIR::DivEIN(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.DivExtension(babybear.NewExtensionVariable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b,
-                        c.id()
+                        format!("fieldChip.DivExtension({}, {})", ext_imm::<C>(b), c.id()),
                     ));
                 }
                 DslIR::DivEFI(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.DivFelt({}, babybear.NewVariable({}))",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        c,
-                        b.id()
+                        format!("fieldChip.DivFelt({}, babybear.NewVariable({}))", b.id(), c),
                     ));
                 }
                 DslIR::DivEFIN(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.DivFelt(babybear.NewExtensionVariable({}), {})",
+                    lines.push(self.assign(
                         a.id(),
-                        operator,
-                        b,
-                        c.id()
+                        format!(
+                            "fieldChip.DivExtension({}, {})",
+                            ext_imm::<C>(C::EF::from_base(b)),
+                            c.id()
+                        ),
                     ));
                 }
                 DslIR::DivEF(a, b, c) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.DivFelt({}, {})",
-                        a.id(),
-                        operator,
-                        b.id(),
-                        c.id()
-                    ));
+                    lines.push(
+                        self.assign(a.id(), format!("fieldChip.DivFelt({}, {})", b.id(), c.id())),
+                    );
                 }
                 DslIR::NegV(a, b) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!("{} {} api.Neg({})", a.id(), operator, b.id()));
+                    lines.push(self.assign(a.id(), format!("api.Neg({})", b.id())));
                 }
                 DslIR::NegF(a, b) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!("{} {} fieldChip.Neg({})", a.id(), operator, b.id()));
+                    lines.push(self.assign(a.id(), format!("fieldChip.Neg({})", b.id())));
                 }
                 DslIR::NegE(a, b) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.NegExtension({})",
-                        a.id(),
-                        operator,
-                        b.id()
-                    ));
+                    lines.push(self.assign(a.id(), format!("fieldChip.NegExtension({})", b.id())));
                 }
                 DslIR::InvV(a, b) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!("{} {} api.Inv({})", a.id(), operator, b.id()));
+                    lines.push(self.assign(a.id(), format!("api.Inv({})", b.id())));
                 }
                 DslIR::InvF(a, b) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!("{} {} fieldChip.Inv({})", a.id(), operator, b.id()));
+                    lines.push(self.assign(a.id(), format!("fieldChip.Inv({})", b.id())));
                 }
                 DslIR::InvE(a, b) => {
-                    let operator = self.assign(a.id());
-                    lines.push(format!(
-                        "{} {} fieldChip.InvExtension({})",
-                        a.id(),
-                        operator,
-                        b.id()
-                    ))
-                }
-                DslIR::For(a, b, _, d) => {
-                    lines.push(format!("for i := {}; i < {}; i++ {{", a.value(), b.value()));
-                    lines.extend(indent(self.emit(d)));
+                    lines.push(self.assign(a.id(), format!("fieldChip.InvExtension({})", b.id())));
+                }
+                DslIR::For(a, b, c, d) => {
+                    let (start, end) = match (a, b) {
+                        (Usize::Const(start), Usize::Const(end)) => (start, end),
+                        _ => return Err(GnarkError::VariableLoopBounds),
+                    };
+                    // The counter is assigned whatever the condition, so that the memory offsets
+                    // computed from it stay constant.
+                    let index = self.index();
+                    self.used.insert(c.id(), true);
+                    lines.push(format!(
+                        "for {index} := {start}; {index} < {end}; {index}++ {{"
+                    ));
+                    lines.push(format!("\t{} = frontend.Variable({})", c.id(), index));
+                    lines.extend(indent(self.emit(d)?));
                     lines.push("}".to_string());
                 }
                 DslIR::IfEq(a, b, c, d) => {
-                    let cond = self.alloc();
-                    let operator = self.assign(cond.clone());
-                    lines.push(format!(
-                        "{} {} api.IsZero(api.Sub({}, {}))",
-                        cond,
-                        operator,
-                        a.id(),
-                        b.id()
-                    ));
-                    lines.extend(mask(cond.clone(), self.emit(c)));
-                    lines.extend(mask(cond.clone(), self.emit(d)));
+                    let cond = format!("api.IsZero(api.Sub({}, {}))", a.id(), b.id());
+                    lines.extend(self.conditional(cond, c, d)?);
                 }
                 DslIR::IfNe(a, b, c, d) => {
-                    let cond = self.alloc();
-                    let operator = self.assign(cond.clone());
-                    lines.push(format!(
-                        "{} {} api.Sub(frontend.Variable(1), api.IsZero(api.Sub({}, {})))",
-                        cond,
-                        operator,
+                    let cond = format!(
+                        "api.Sub(frontend.Variable(1), api.IsZero(api.Sub({}, {})))",
                         a.id(),
                         b.id()
-                    ));
-                    lines.extend(mask(cond.clone(), self.emit(c)));
-                    lines.extend(mask(cond.clone(), self.emit(d)));
+                    );
+                    lines.extend(self.conditional(cond, c, d)?);
                 }
                 DslIR::IfEqI(a, b, c, d) => {
-                    let cond = self.alloc();
-                    let operator = self.assign(cond.clone());
-                    lines.push(format!(
-                        "{} {} api.IsZero(api.Sub({}, frontend.Variable({})))",
-                        cond,
-                        operator,
-                        a.id(),
-                        b
-                    ));
-                    lines.extend(mask(cond.clone(), self.emit(c)));
-                    lines.extend(mask(cond.clone(), self.emit(d)));
+                    let cond = format!("api.IsZero(api.Sub({}, frontend.Variable({})))", a.id(), b);
+                    lines.extend(self.conditional(cond, c, d)?);
                 }
                 DslIR::IfNeI(a, b, c, d) => {
-                    let cond = self.alloc();
-                    let operator = self.assign(cond.clone());
-                    lines.push(format!(
-                        "{} {} api.Sub(frontend.Variable(1), api.IsZero(api.Sub({}, frontend.Variable({}))))",
-                        cond,
-                        operator,
+                    let cond = format!(
+                        "api.Sub(frontend.Variable(1), api.IsZero(api.Sub({}, frontend.Variable({}))))",
                         a.id(),
                         b
-                    ));
-                    lines.extend(mask(cond.clone(), self.emit(c)));
-                    lines.extend(mask(cond.clone(), self.emit(d)));
+                    );
+                    lines.extend(self.conditional(cond, c, d)?);
                 }
                 DslIR::AssertEqV(a, b) => {
-                    lines.push(format!("api.AssertEq({}, {})", a.id(), b.id()));
+                    lines.push(self.assert("frontend.Variable", true, a.id(), b.id()));
                 }
                 DslIR::AssertNeV(a, b) => {
-                    lines.push(format!("api.AssertNe({}, {})", a.id(), b.id()));
+                    lines.push(self.assert("frontend.Variable", false, a.id(), b.id()));
                 }
                 DslIR::AssertEqF(a, b) => {
-                    lines.push(format!("fieldChip.AssertEq({}, {})", a.id(), b.id()));
+                    lines.push(self.assert("*babybear.Variable", true, a.id(), b.id()));
                 }
                 DslIR::AssertNeF(a, b) => {
-                    lines.push(format!("fieldChip.AssertNe({}, {})", a.id(), b.id()));
+                    lines.push(self.assert("*babybear.Variable", false, a.id(), b.id()));
                 }
                 DslIR::AssertEqE(a, b) => {
-                    lines.push(format!(
-                        "fieldChip.AssertEqExtension({}, {})",
-                        a.id(),
-                        b.id()
-                    ));
+                    lines.push(self.assert("*babybear.ExtensionVariable", true, a.id(), b.id()));
                 }
                 DslIR::AssertNeE(a, b) => {
-                    lines.push(format!(
-                        "fieldChip.AssertNeExtension({}, {})",
-                        a.id(),
-                        b.id()
-                    ));
+                    lines.push(self.assert("*babybear.ExtensionVariable", false, a.id(), b.id()));
                 }
                 DslIR::AssertEqVI(a, b) => {
-                    lines.push(format!(
-                        "api.AssertEq({}, frontend.Variable({}))",
-                        a.id(),
-                        b
-                    ));
+                    let b = format!("frontend.Variable({})", b);
+                    lines.push(self.assert("frontend.Variable", true, a.id(), b));
                 }
                 DslIR::AssertNeVI(a, b) => {
-                    lines.push(format!(
-                        "api.AssertNe({}, frontend.Variable({}))",
+                    let b = format!("frontend.Variable({})", b);
+                    lines.push(self.assert("frontend.Variable", false, a.id(), b));
+                }
+                DslIR::AssertEqFI(a, b) => {
+                    let b = format!("babybear.NewVariable({})", b);
+                    lines.push(self.assert("*babybear.Variable", true, a.id(), b));
+                }
+                DslIR::AssertNeFI(a, b) => {
+                    let b = format!("babybear.NewVariable({})", b);
+                    lines.push(self.assert("*babybear.Variable", false, a.id(), b));
+                }
+                DslIR::AssertEqEI(a, b) => {
+                    let b = ext_imm::<C>(b);
+                    lines.push(self.assert("*babybear.ExtensionVariable", true, a.id(), b));
+                }
+                DslIR::AssertNeEI(a, b) => {
+                    let b = ext_imm::<C>(b);
+                    lines.push(self.assert("*babybear.ExtensionVariable", false, a.id(), b));
+                }
+                DslIR::Alloc(a, b, _) => {
+                    let mem = memory(a);
+                    let len = offset(b);
+                    self.used.insert(mem.clone(), true);
+                    if self.cond.is_some() {
+                        // The slice is shared by both branches of the conditional, so memory
+                        // allocated in a branch only ever grows it.
+                        lines.push(format!("if len({mem}) < {len} {{"));
+                        lines.push(format!(
+                            "\t{mem} = append({mem}, make([]interface{{}}, {len}-len({mem}))...)"
+                        ));
+                        lines.push("}".to_string());
+                    } else {
+                        lines.push(format!("{mem} = make([]interface{{}}, {len})"));
+                    }
+                }
+                DslIR::LoadV(a, b, c) => {
+                    lines.push(self.assign(
                         a.id(),
-                        b
+                        format!("{}[{}].(frontend.Variable)", memory(b), offset(c)),
                     ));
                 }
-                DslIR::AssertEqFI(a, b) => {
-                    lines.push(format!(
-                        "fieldChip.AssertEq({}, babybear.NewVariable({}))",
+                DslIR::LoadF(a, b, c) => {
+                    lines.push(self.assign(
                         a.id(),
-                        b
+                        format!("{}[{}].(*babybear.Variable)", memory(b), offset(c)),
                     ));
                 }
-                DslIR::AssertNeFI(a, b) => {
-                    lines.push(format!(
-                        "fieldChip.AssertNe({}, babybear.NewVariable({}))",
+                DslIR::LoadE(a, b, c) => {
+                    lines.push(self.assign(
                         a.id(),
-                        b
+                        format!("{}[{}].(*babybear.ExtensionVariable)", memory(b), offset(c)),
                     ));
                 }
-                DslIR::AssertEqEI(a, b) => {
+                DslIR::StoreV(a, b, c) => {
+                    lines.extend(self.store(&memory(b), &offset(c), &a.id(), "frontend.Variable"));
+                }
+                DslIR::StoreF(a, b, c) => {
+                    lines.extend(self.store(&memory(b), &offset(c), &a.id(), "*babybear.Variable"));
+                }
+                DslIR::StoreE(a, b, c) => {
+                    lines.extend(self.store(
+                        &memory(b),
+                        &offset(c),
+                        &a.id(),
+                        "*babybear.ExtensionVariable",
+                    ));
+                }
+                DslIR::Poseidon2PermuteBabyBear(a, b) => {
+                    self.poseidon2 = true;
+                    lines.push("{".to_string());
+                    lines.push(format!("\tvar input [{WIDTH}]*babybear.Variable"));
+                    lines.push("\tfor i := range input {".to_string());
                     lines.push(format!(
-                        "fieldChip.AssertEqExtension({}, babybear.NewExtensionVariable({}))",
-                        a.id(),
-                        b
+                        "\t\tinput[i] = {}[i].(*babybear.Variable)",
+                        memory(b)
+                    ));
+                    lines.push("\t}".to_string());
+                    lines.push(format!(
+                        "\tfor i, value := range fieldChip.Poseidon2Permute(input, poseidon2RoundConstants, poseidon2InternalDiagonal, {PARTIAL_ROUNDS_START}, {PARTIAL_ROUNDS_END}) {{"
                     ));
+                    let store = self.store(&memory(a), "i", "value", "*babybear.Variable");
+                    lines.extend(indent(indent(store)));
+                    lines.push("\t}".to_string());
+                    lines.push("}".to_string());
                 }
-                DslIR::AssertNeEI(a, b) => {
+                DslIR::CommitF(_) => return Err(GnarkError::CommitPublicValue),
+                DslIR::Num2BitsF(a, b) => {
                     lines.push(format!(
-                        "fieldChip.AssertNeExtension({}, babybear.NewExtensionVariable({}))",
-                        a.id(),
-                        b
+                        "for i, bit := range fieldChip.ToBinary({}) {{",
+                        b.id()
+                    ));
+                    let store = self.store(&memory(a), "i", "bit", "*babybear.Variable");
+                    lines.extend(indent(store));
+                    lines.push("}".to_string());
+                }
+                DslIR::HintV(a) => {
+                    let witness = self.witness();
+                    lines.push(self.assign(a.id(), witness));
+                }
+                DslIR::HintF(a) => {
+                    let witness = self.witness();
+                    lines.push(self.assign(a.id(), format!("fieldChip.FromVariable({})", witness)));
+                }
+                DslIR::Function(a, b, c, d) => {
+                    // The body is emitted as a closure, with variables of its own, whose effects
+                    // are masked by the condition it is called under. Note that hints read the
+                    // same elements of the witness at every call.
+                    let mut backend = GnarkBackend::<C> {
+                        nb_backend_vars: self.nb_backend_vars,
+                        nb_witness_vars: self.nb_witness_vars,
+                        cond: Some("cond".to_string()),
+                        poseidon2: self.poseidon2,
+                        ..Default::default()
+                    };
+                    let operations = backend.emit(d.operations)?;
+                    self.nb_backend_vars = backend.nb_backend_vars;
+                    self.nb_witness_vars = backend.nb_witness_vars;
+                    self.poseidon2 = backend.poseidon2;
+
+                    let parameters = b.iter().map(|p| p.id()).collect::<Vec<_>>();
+                    let signature = std::iter::once("cond frontend.Variable".to_string())
+                        .chain(
+                            parameters
+                                .iter()
+                                .map(|id| format!("{} {}", id, go_type(id))),
+                        )
+                        .collect::<Vec<_>>();
+                    let results = c.iter().map(|r| r.id()).collect::<Vec<_>>();
+                    let result_types = results.iter().map(|id| go_type(id)).collect::<Vec<_>>();
+                    lines.push(format!(
+                        "fn_{} := func({}) ({}) {{",
+                        a,
+                        signature.join(", "),
+                        result_types.join(", ")
+                    ));
+                    lines.extend(indent(backend.declarations(&parameters)));
+                    lines.extend(indent(operations));
+                    lines.push(format!("\treturn {}", results.join(", ")));
+                    lines.push("}".to_string());
+                }
+                DslIR::Call(a, b, c) => {
+                    let cond = self.cond.as_deref().unwrap_or("frontend.Variable(1)");
+                    let arguments = std::iter::once(cond.to_string())
+                        .chain(b.iter().map(|v| v.id()))
+                        .collect::<Vec<_>>();
+                    if c.is_empty() {
+                        lines.push(format!("fn_{}({})", a, arguments.join(", ")));
+                    } else if self.cond.is_none() {
+                        let results = c.iter().map(|r| r.id()).collect::<Vec<_>>();
+                        for result in results.iter() {
+                            self.used.insert(result.clone(), true);
+                        }
+                        lines.push(format!(
+                            "{} = fn_{}({})",
+                            results.join(", "),
+                            a,
+                            arguments.join(", ")
+                        ));
+                    } else {
+                        // The return values are only copied to the results when the condition
+                        // holds.
+                        let values = c.iter().map(|_| self.alloc()).collect::<Vec<_>>();
+                        lines.push("{".to_string());
+                        lines.push(format!(
+                            "\t{} := fn_{}({})",
+                            values.join(", "),
+                            a,
+                            arguments.join(", ")
+                        ));
+                        for (result, value) in c.iter().zip(values) {
+                            let line = self.assign(result.id(), value);
+                            lines.push(format!("\t{}", line));
+                        }
+                        lines.push("}".to_string());
+                    }
+                }
+                DslIR::HintE(a) => {
+                    let witness = (0..4).map(|_| self.witness()).collect::<Vec<_>>();
+                    lines.push(self.assign(
+                        a.id(),
+                        format!(
+                            "fieldChip.FromExtensionVariable([4]frontend.Variable{{{}}})",
+                            witness.join(", ")
+                        ),
                     ));
                 }
-                _ => todo!(),
             };
         }
-        lines
+        Ok(lines)
     }
 
-    pub fn compile(&mut self, program: Vec<DslIR<C>>) -> String {
-        let operations = self.emit(program);
-        let initializes = self
+    /// Declares the variables assigned by the emitted lines, except the `excluded` ones, and
+    /// initializes them to zero so that masked assignments can read them.
+    fn declarations(&self, excluded: &[String]) -> Vec<String> {
+        let mut ids = self
             .used
             .keys()
+            .filter(|id| !excluded.contains(id))
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.into_iter()
             .map(|id| {
-                if id.contains("var") {
-                    format!("var {} frontend.Variable", id)
-                } else if id.contains("felt") {
-                    format!("var {} *babybear.Variable", id)
-                } else if id.contains("ext") {
-                    format!("var {} *babybear.ExtensionVariable", id)
-                } else if id.contains("backend") {
-                    format!("var {} frontend.Variable", id)
+                let ty = go_type(id);
+                if id.starts_with("mem") {
+                    format!("var {} {}", id, ty)
                 } else {
-                    panic!("Unknown variable type")
+                    format!("var {} {} = {}", id, ty, go_zero(ty))
                 }
             })
-            .collect::<Vec<_>>();
+            .collect()
+    }
+
+    /// Compiles the program to the source of a gnark circuit.
+    pub fn compile(&mut self, program: Vec<DslIR<C>>) -> Result<String, GnarkError> {
+        let (program, report) = optimize(program);
+        tracing::debug!("optimized program: {}", report);
+        let operations = self.emit(program)?;
+        let initializes = self.declarations(&[]);
 
         let mut lines = Vec::new();
+        if self.poseidon2 {
+            lines.extend(vec!["".to_string(), "// Constants.".to_string()]);
+            lines.extend(poseidon2_constants());
+        }
         lines.extend(vec!["".to_string(), "// Variables.".to_string()]);
         lines.extend(initializes);
         lines.extend(vec!["".to_string(), "// Operations.".to_string()]);
         lines.extend(operations);
-        GNARK_TEMPLATE.replace("{{LINES}}", &indent(lines).join("\n"))
+        Ok(GNARK_TEMPLATE.replace("{{LINES}}", &indent(lines).join("\n")))
     }
 }
 
@@ -658,7 +832,7 @@ mod tests {
     use p3_baby_bear::BabyBear;
     use p3_field::{extension::BinomialExtensionField, AbstractField};
 
-    use crate::ir::{Felt, Usize, Var};
+    use crate::ir::{Felt, Var};
     use crate::prelude::Builder;
 
     use super::*;
@@ -688,12 +862,8 @@ mod tests {
             DslIR::NegV(Var::new(9), Var::new(8)),
             DslIR::InvV(Var::new(10), Var::new(9)),
         ];
-        let mut backend = GnarkBackend::<BabyBearConfig> {
-            nb_backend_vars: 0,
-            used: HashMap::new(),
-            phantom: PhantomData,
-        };
-        let result = backend.compile(program);
+        let mut backend = GnarkBackend::<BabyBearConfig>::default();
+        let result = backend.compile(program).unwrap();
         println!("{:?}", result);
     }
 
@@ -734,12 +904,8 @@ mod tests {
             },
         );
 
-        let mut backend = GnarkBackend::<BabyBearConfig> {
-            nb_backend_vars: 0,
-            used: HashMap::new(),
-            phantom: PhantomData,
-        };
-        let result = backend.compile(builder.operations);
+        let mut backend = GnarkBackend::<BabyBearConfig>::default();
+        let result = backend.compile(builder.operations).unwrap();
 
         // Write to file.
         let manifest_dir = env!("CARGO_MANIFEST_DIR");
@@ -747,4 +913,79 @@ mod tests {
         let mut file = File::create(path).unwrap();
         file.write_all(result.as_bytes()).unwrap();
     }
+
+    #[test]
+    fn test_memory_and_hints() {
+        let mut builder = Builder::<BabyBearConfig>::default();
+        let hinted: Felt<_> = builder.hint();
+        let ptr = builder.vec::<Felt<_>, _>(4).ptr;
+        builder.range(0, 4).for_each(|i, builder| {
+            builder.store(ptr, i, hinted);
+        });
+        let value: Felt<_> = builder.uninit();
+        builder.load(value, ptr, 3);
+        builder.assert_felt_eq(value, hinted);
+        builder.num2bits_f(hinted);
+
+        let mut backend = GnarkBackend::<BabyBearConfig>::default();
+        let result = backend.compile(builder.operations).unwrap();
+        assert!(result.contains("fieldChip.FromVariable(circuit.Witness[0])"));
+        assert!(result.contains("make([]interface{}, 4)"));
+        assert!(result.contains("[constIndex(api, var"));
+        assert!(result.contains("fieldChip.ToBinary(felt0)"));
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let mut builder = Builder::<BabyBearConfig>::default();
+        let a: Var<_> = builder.hint();
+        let b: Felt<_> = builder.hint();
+        let ptr = builder.vec::<Felt<_>, _>(1).ptr;
+        builder.if_eq(a, BabyBear::one()).then_or_else(
+            |builder| {
+                builder.assert_var_eq(a, BabyBear::one());
+                builder.assert_felt_ne(b, BabyBear::zero());
+                builder.store(ptr, 0, b);
+            },
+            |builder| {
+                builder.assert_var_ne(a, BabyBear::one());
+            },
+        );
+
+        let mut backend = GnarkBackend::<BabyBearConfig>::default();
+        let result = backend.compile(builder.operations).unwrap();
+        assert!(result.contains("backend0 = api.IsZero(api.Sub(var0, frontend.Variable(1)))"));
+        assert!(result.contains("backend1 = api.Sub(frontend.Variable(1), backend0)"));
+        assert!(result.contains(
+            "api.AssertIsEqual(api.Mul(backend0, api.Sub(var0, frontend.Variable(1))), frontend.Variable(0))"
+        ));
+        assert!(result.contains(
+            "fieldChip.AssertNe(fieldChip.Select(backend0, felt0, fieldChip.Add(babybear.NewVariable(0), babybear.NewVariable(1))), babybear.NewVariable(0))"
+        ));
+        assert!(result.contains("[0] = fieldChip.Select(backend0, felt0, mem_"));
+        assert!(result.contains(
+            "api.AssertIsDifferent(api.Select(backend1, var0, api.Add(frontend.Variable(1), frontend.Variable(1))), frontend.Variable(1))"
+        ));
+    }
+
+    #[test]
+    fn test_functions() {
+        let mut builder = Builder::<BabyBearConfig>::default();
+        let add = builder.function("add", |builder, (a, b): (Felt<_>, Felt<_>)| -> Felt<_> {
+            builder.eval(a + b)
+        });
+        let a: Felt<_> = builder.eval(BabyBear::one());
+        let b = builder.call(&add, (a, a));
+        builder.assert_felt_eq(b, BabyBear::two());
+
+        let mut backend = GnarkBackend::<BabyBearConfig>::default();
+        let result = backend.compile(builder.operations).unwrap();
+        assert!(result.contains(
+            "fn_add := func(cond frontend.Variable, felt0 *babybear.Variable, felt1 *babybear.Variable) (*babybear.Variable) {"
+        ));
+        assert!(result.contains("\t\treturn felt2"));
+        assert!(result
+            .contains("\t\tfelt2 = fieldChip.Select(cond, fieldChip.Add(felt0, felt1), felt2)"));
+        assert!(result.contains("felt1 = fn_add(frontend.Variable(1), felt0, felt0)"));
+    }
 }
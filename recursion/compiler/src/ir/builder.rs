@@ -1,14 +1,16 @@
 use super::{Config, DslIR, Ext, SymbolicExt, SymbolicFelt, Usize};
-use super::{Felt, Var};
+use super::{Felt, Var, Vector};
 use super::{SymbolicVar, Variable};
 use alloc::vec::Vec;
+use p3_field::AbstractField;
+use sp1_recursion_core::num2bits::NUM_BITS;
 
 #[derive(Debug, Clone)]
 pub struct Builder<C: Config> {
     pub(crate) felt_count: u32,
     pub(crate) ext_count: u32,
     pub(crate) var_count: u32,
-    pub(crate) operations: Vec<DslIR<C>>,
+    pub operations: Vec<DslIR<C>>,
 }
 
 impl<C: Config> Default for Builder<C> {
@@ -32,7 +34,16 @@ impl<C: Config> Builder<C> {
         }
     }
 
-    pub(crate) fn push(&mut self, op: DslIR<C>) {
+    /// Reserves the variables allocated by the builder of a nested block, so that the variable
+    /// counts bound the frame of the program.
+    fn reserve(&mut self, nested: &Builder<C>) {
+        self.var_count = self.var_count.max(nested.var_count);
+        self.felt_count = self.felt_count.max(nested.felt_count);
+        self.ext_count = self.ext_count.max(nested.ext_count);
+    }
+
+    /// Pushes an instruction to the program, bypassing the typed builder APIs.
+    pub fn push(&mut self, op: DslIR<C>) {
         self.operations.push(op);
     }
 
@@ -157,6 +168,33 @@ impl<C: Config> Builder<C> {
             builder: self,
         }
     }
+
+    /// Exposes a felt as the next public value of the proof of this program.
+    pub fn commit_public_value(&mut self, value: Felt<C::F>) {
+        self.push(DslIR::CommitF(value));
+    }
+
+    /// Decomposes a felt into the `NUM_BITS` little-endian bits of its canonical representation.
+    pub fn num2bits_f(&mut self, num: Felt<C::F>) -> Vec<Felt<C::F>> {
+        let output: Vector<C, Felt<C::F>> = self.vec(NUM_BITS);
+        self.push(DslIR::Num2BitsF(output.ptr, num));
+        (0..NUM_BITS)
+            .map(|i| {
+                let bit = self.uninit();
+                self.load(bit, output.ptr, i);
+                bit
+            })
+            .collect()
+    }
+
+    /// Recomposes a felt from its little-endian bits.
+    pub fn bits2num_f(&mut self, bits: &[Felt<C::F>]) -> Felt<C::F> {
+        let mut num = SymbolicFelt::Const(C::F::zero());
+        for (i, bit) in bits.iter().enumerate() {
+            num = num + *bit * C::F::from_canonical_u32(1 << i);
+        }
+        self.eval(num)
+    }
 }
 
 pub struct IfBuilder<'a, C: Config> {
@@ -187,6 +225,7 @@ impl<'a, C: Config> IfBuilder<'a, C> {
             self.builder.ext_count,
         );
         f(&mut f_builder);
+        self.builder.reserve(&f_builder);
         let then_instructions = f_builder.operations;
 
         // Dispatch instructions to the correct conditional block.
@@ -235,6 +274,7 @@ impl<'a, C: Config> IfBuilder<'a, C> {
 
         // Execute the `then` and `else_then` blocks and collect the instructions.
         then_f(&mut then_builder);
+        self.builder.reserve(&then_builder);
         let then_instructions = then_builder.operations;
 
         let mut else_builder = Builder::<C>::new(
@@ -243,6 +283,7 @@ impl<'a, C: Config> IfBuilder<'a, C> {
             self.builder.ext_count,
         );
         else_f(&mut else_builder);
+        self.builder.reserve(&else_builder);
         let else_instructions = else_builder.operations;
 
         // Dispatch instructions to the correct conditional block.
@@ -362,6 +403,7 @@ impl<'a, C: Config> RangeBuilder<'a, C> {
         );
 
         f(loop_variable, &mut loop_body_builder);
+        self.builder.reserve(&loop_body_builder);
 
         let loop_instructions = loop_body_builder.operations;
 
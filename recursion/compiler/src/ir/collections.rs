@@ -9,10 +9,10 @@ pub enum Slice<C: Config, T> {
 
 #[allow(dead_code)]
 pub struct Vector<C: Config, T> {
-    ptr: Ptr<C::N>,
-    len: Usize<C::N>,
-    cap: Usize<C::N>,
-    _marker: PhantomData<T>,
+    pub(crate) ptr: Ptr<C::N>,
+    pub(crate) len: Usize<C::N>,
+    pub(crate) cap: Usize<C::N>,
+    pub(crate) _marker: PhantomData<T>,
 }
 
 impl<C: Config, V: MemVariable<C>> Vector<C, V> {
@@ -0,0 +1,198 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use std::collections::HashSet;
+
+use super::{Builder, Config, DslIR, Ext, Felt, Ptr, Var, Variable};
+use crate::opt::{collect_variables, function_id, Kind};
+
+/// A variable passed to or returned from a function.
+#[derive(Debug, Clone)]
+pub enum FunctionVariable<C: Config> {
+    Var(Var<C::N>),
+    Felt(Felt<C::F>),
+    Ext(Ext<C::F, C::EF>),
+}
+
+impl<C: Config> FunctionVariable<C> {
+    pub fn id(&self) -> String {
+        match self {
+            FunctionVariable::Var(var) => var.id(),
+            FunctionVariable::Felt(felt) => felt.id(),
+            FunctionVariable::Ext(ext) => ext.id(),
+        }
+    }
+}
+
+/// The arguments or the results of a function.
+pub trait FunctionVariables<C: Config>: Clone {
+    /// Allocates new variables of the same types.
+    fn uninit(builder: &mut Builder<C>) -> Self;
+
+    /// Returns the variables, in the order in which they are passed.
+    fn variables(&self) -> Vec<FunctionVariable<C>>;
+}
+
+impl<C: Config> FunctionVariables<C> for Var<C::N> {
+    fn uninit(builder: &mut Builder<C>) -> Self {
+        builder.uninit()
+    }
+
+    fn variables(&self) -> Vec<FunctionVariable<C>> {
+        vec![FunctionVariable::Var(*self)]
+    }
+}
+
+impl<C: Config> FunctionVariables<C> for Felt<C::F> {
+    fn uninit(builder: &mut Builder<C>) -> Self {
+        builder.uninit()
+    }
+
+    fn variables(&self) -> Vec<FunctionVariable<C>> {
+        vec![FunctionVariable::Felt(*self)]
+    }
+}
+
+impl<C: Config> FunctionVariables<C> for Ext<C::F, C::EF> {
+    fn uninit(builder: &mut Builder<C>) -> Self {
+        builder.uninit()
+    }
+
+    fn variables(&self) -> Vec<FunctionVariable<C>> {
+        vec![FunctionVariable::Ext(*self)]
+    }
+}
+
+impl<C: Config> FunctionVariables<C> for Ptr<C::N> {
+    fn uninit(builder: &mut Builder<C>) -> Self {
+        Ptr::uninit(builder)
+    }
+
+    fn variables(&self) -> Vec<FunctionVariable<C>> {
+        vec![FunctionVariable::Var(self.address)]
+    }
+}
+
+impl<C: Config, V: FunctionVariables<C>, const N: usize> FunctionVariables<C> for [V; N] {
+    fn uninit(builder: &mut Builder<C>) -> Self {
+        core::array::from_fn(|_| V::uninit(builder))
+    }
+
+    fn variables(&self) -> Vec<FunctionVariable<C>> {
+        self.iter().flat_map(|v| v.variables()).collect()
+    }
+}
+
+impl<C: Config> FunctionVariables<C> for () {
+    fn uninit(_: &mut Builder<C>) -> Self {}
+
+    fn variables(&self) -> Vec<FunctionVariable<C>> {
+        vec![]
+    }
+}
+
+macro_rules! impl_function_variables_for_tuple {
+    ($($name:ident),+) => {
+        impl<C: Config, $($name: FunctionVariables<C>),+> FunctionVariables<C> for ($($name,)+) {
+            fn uninit(builder: &mut Builder<C>) -> Self {
+                ($($name::uninit(builder),)+)
+            }
+
+            #[allow(non_snake_case)]
+            fn variables(&self) -> Vec<FunctionVariable<C>> {
+                let ($($name,)+) = self;
+                let mut variables = Vec::new();
+                $(variables.extend($name.variables());)+
+                variables
+            }
+        }
+    };
+}
+
+impl_function_variables_for_tuple!(A);
+impl_function_variables_for_tuple!(A, B);
+impl_function_variables_for_tuple!(A, B, D);
+impl_function_variables_for_tuple!(A, B, D, E);
+
+/// A function defined with `Builder::function`, which can be called with `Builder::call`.
+#[derive(Debug, Clone)]
+pub struct Function<C: Config, A, R> {
+    name: String,
+    _marker: PhantomData<(C, A, R)>,
+}
+
+impl<C: Config, A, R> Function<C, A, R> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<C: Config> Builder<C> {
+    /// Defines a function whose body is built once by `f` from its arguments.
+    ///
+    /// The body runs in its own frame, so its code is emitted only once however many times the
+    /// function is called. Functions must be defined outside of conditional blocks and loops.
+    ///
+    /// The body can only use its arguments and its own variables: it panics if it reads, assigns
+    /// or returns a variable of the caller, which must be passed as an argument instead.
+    pub fn function<A, R>(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&mut Builder<C>, A) -> R,
+    ) -> Function<C, A, R>
+    where
+        A: FunctionVariables<C>,
+        R: FunctionVariables<C>,
+    {
+        // The ids of the body start after the ones of the caller, so that its variables can be
+        // told apart from the caller's.
+        let mut body = Builder::<C>::new(self.var_count, self.felt_count, self.ext_count);
+        let parameters = A::uninit(&mut body);
+        let results = f(&mut body, parameters.clone());
+
+        let mut ids = HashSet::new();
+        collect_variables(&body.operations, &mut ids);
+        ids.extend(results.variables().iter().map(function_id));
+        let foreign = ids.into_iter().find(|(kind, id)| {
+            let count = match kind {
+                Kind::Var => self.var_count,
+                Kind::Felt => self.felt_count,
+                Kind::Ext => self.ext_count,
+            };
+            *id < count
+        });
+        if let Some((kind, id)) = foreign {
+            panic!(
+                "the function {} uses the {:?} {} of its caller, pass it as an argument instead",
+                name, kind, id
+            );
+        }
+
+        self.push(DslIR::Function(
+            name.to_string(),
+            parameters.variables(),
+            results.variables(),
+            body,
+        ));
+        Function {
+            name: name.to_string(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Calls a function with the given arguments, and returns copies of its results.
+    pub fn call<A, R>(&mut self, function: &Function<C, A, R>, arguments: A) -> R
+    where
+        A: FunctionVariables<C>,
+        R: FunctionVariables<C>,
+    {
+        let results = R::uninit(self);
+        self.push(DslIR::Call(
+            function.name.clone(),
+            arguments.variables(),
+            results.variables(),
+        ));
+        results
+    }
+}
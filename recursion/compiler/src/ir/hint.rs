@@ -0,0 +1,60 @@
+use p3_field::AbstractField;
+
+use super::{Builder, Config, DslIR, Ext, Felt, MemVariable, Slice, Usize, Var, Vector};
+
+/// A variable that can be read from the witness stream of the runtime.
+pub trait Hintable<C: Config>: MemVariable<C> {
+    fn hint(builder: &mut Builder<C>) -> Self;
+}
+
+impl<C: Config> Hintable<C> for Var<C::N> {
+    fn hint(builder: &mut Builder<C>) -> Self {
+        let var = builder.uninit();
+        builder.push(DslIR::HintV(var));
+        var
+    }
+}
+
+impl<C: Config> Hintable<C> for Felt<C::F> {
+    fn hint(builder: &mut Builder<C>) -> Self {
+        let felt = builder.uninit();
+        builder.push(DslIR::HintF(felt));
+        felt
+    }
+}
+
+impl<C: Config> Hintable<C> for Ext<C::F, C::EF> {
+    fn hint(builder: &mut Builder<C>) -> Self {
+        let ext = builder.uninit();
+        builder.push(DslIR::HintE(ext));
+        ext
+    }
+}
+
+impl<C: Config> Builder<C> {
+    /// Reads the next value of the witness stream.
+    ///
+    /// The value is supplied by the prover and is not constrained in any way.
+    pub fn hint<V: Hintable<C>>(&mut self) -> V {
+        V::hint(self)
+    }
+
+    /// Reads a vector of the witness stream, written as its length followed by its elements (see
+    /// `Runtime::write_felts` and `Runtime::write_exts`).
+    pub fn hint_vec<V: Hintable<C>>(&mut self) -> Slice<C, V> {
+        let len: Var<C::N> = self.hint();
+        let vec: Vector<C, V> = self.vec(len);
+        let ptr = vec.ptr;
+        // Loops run their body at least once, so empty vectors are skipped explicitly.
+        self.if_ne(len, C::N::zero()).then(|builder| {
+            builder.range(0, len).for_each(|i, builder| {
+                let value: V = builder.hint();
+                builder.store(ptr, i, value);
+            });
+        });
+        Slice::Vec(Vector {
+            len: Usize::Var(len),
+            ..vec
+        })
+    }
+}
@@ -1,6 +1,6 @@
-use super::Ptr;
+use alloc::string::String;
 
-use super::{Config, Ext, Felt, Usize, Var};
+use super::{Builder, Config, Ext, Felt, FunctionVariable, Ptr, Usize, Var};
 
 #[derive(Debug, Clone)]
 pub enum DslIR<C: Config> {
@@ -85,4 +85,34 @@ pub enum DslIR<C: Config> {
     StoreF(Felt<C::F>, Ptr<C::N>, Usize<C::N>),
     /// Store extension field
     StoreE(Ext<C::F, C::EF>, Ptr<C::N>, Usize<C::N>),
+    // Hash instructions.
+    /// Permute the Poseidon2 state of 16 felts (dst, src) : read the state at `src` and write its
+    /// permutation to `dst`.
+    Poseidon2PermuteBabyBear(Ptr<C::N>, Ptr<C::N>),
+    // Bit instructions.
+    /// Decompose a field element into its canonical little-endian bits (dst, value) : write the
+    /// bits of `value` to the array at `dst`.
+    Num2BitsF(Ptr<C::N>, Felt<C::F>),
+    // Hint instructions.
+    /// Read the next value of the witness stream into a variable.
+    HintV(Var<C::N>),
+    /// Read the next value of the witness stream into a field element.
+    HintF(Felt<C::F>),
+    /// Read the next value of the witness stream into an extension field element.
+    HintE(Ext<C::F, C::EF>),
+    // Public value instructions.
+    /// Expose a field element as the next public value of the proof.
+    CommitF(Felt<C::F>),
+    // Function instructions.
+    /// Define a function (name, parameters, results, body) : the body reads its arguments from the
+    /// parameters and leaves its return values in the results.
+    Function(
+        String,
+        Vec<FunctionVariable<C>>,
+        Vec<FunctionVariable<C>>,
+        Builder<C>,
+    ),
+    /// Call a function (name, arguments, results) : pass the arguments to the function and copy
+    /// its return values to the results.
+    Call(String, Vec<FunctionVariable<C>>, Vec<FunctionVariable<C>>),
 }
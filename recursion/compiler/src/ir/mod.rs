@@ -2,7 +2,10 @@ use p3_field::{ExtensionField, Field};
 
 mod builder;
 mod collections;
+mod function;
+mod hint;
 mod instructions;
+mod poseidon;
 mod ptr;
 mod symbolic;
 mod types;
@@ -10,6 +13,8 @@ mod var;
 
 pub use builder::*;
 pub use collections::*;
+pub use function::*;
+pub use hint::*;
 pub use instructions::*;
 pub use ptr::*;
 pub use symbolic::*;
@@ -0,0 +1,29 @@
+use sp1_recursion_core::poseidon2::WIDTH;
+
+use super::{Builder, Config, DslIR, Felt, Slice, Usize, Vector};
+
+impl<C: Config> Builder<C> {
+    /// Applies the Poseidon2 permutation of `BabyBearPoseidon2` to a state of 16 felts, returning
+    /// the permuted state in a newly allocated vector.
+    pub fn poseidon2_permute(&mut self, state: &Slice<C, Felt<C::F>>) -> Slice<C, Felt<C::F>> {
+        let src = match state {
+            Slice::Fixed(values) => {
+                assert_eq!(values.len(), WIDTH, "invalid Poseidon2 state length");
+                // The permutation reads its input from memory, so copy the state to the heap.
+                let input: Vector<C, Felt<C::F>> = self.vec(WIDTH);
+                for (i, value) in values.iter().enumerate() {
+                    self.store(input.ptr, i, *value);
+                }
+                input.ptr
+            }
+            Slice::Vec(input) => input.ptr,
+        };
+
+        let output: Vector<C, Felt<C::F>> = self.vec(WIDTH);
+        self.push(DslIR::Poseidon2PermuteBabyBear(output.ptr, src));
+        Slice::Vec(Vector {
+            len: Usize::from(WIDTH),
+            ..output
+        })
+    }
+}
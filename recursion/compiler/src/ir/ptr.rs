@@ -2,7 +2,7 @@ use super::{Builder, Config, DslIR, MemVariable, SymbolicVar, Usize, Var, Variab
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ptr<N> {
-    address: Var<N>,
+    pub address: Var<N>,
 }
 
 impl<C: Config> Builder<C> {
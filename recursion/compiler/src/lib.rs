@@ -4,7 +4,10 @@ pub mod asm;
 pub mod builder;
 pub mod gnark;
 pub mod ir;
+pub mod opt;
+pub mod r1cs;
 pub mod util;
+pub mod verifier;
 
 pub mod prelude {
     pub use crate::asm::AsmCompiler;
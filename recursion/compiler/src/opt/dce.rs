@@ -0,0 +1,175 @@
+use alloc::vec::Vec;
+use std::collections::HashSet;
+
+use super::expr::{decode, function_id, BinOp, Ex, Expr, Id, Kind};
+use crate::ir::{Config, DslIR, Ptr, Usize};
+
+/// Removes the arithmetic instructions whose results are never read, and the blocks left empty,
+/// until none remains.
+///
+/// The variables in `live` are read after the program, such as the results of a function.
+pub(crate) fn eliminate_dead_code<C: Config>(
+    mut operations: Vec<DslIR<C>>,
+    live: &HashSet<Id>,
+) -> Vec<DslIR<C>> {
+    // Function bodies run in their own frames, with their results as the only live variables.
+    for op in operations.iter_mut() {
+        if let DslIR::Function(_, _, results, body) = op {
+            let live = results.iter().map(function_id).collect();
+            let body_operations = core::mem::take(&mut body.operations);
+            body.operations = eliminate_dead_code(body_operations, &live);
+        }
+    }
+
+    loop {
+        let mut reads = live.clone();
+        for op in operations.iter() {
+            collect_reads(op, &mut reads);
+        }
+        let before = super::count(&operations);
+        operations = remove_dead(operations, &reads);
+        if super::count(&operations) == before {
+            return operations;
+        }
+    }
+}
+
+fn remove_dead<C: Config>(operations: Vec<DslIR<C>>, reads: &HashSet<Id>) -> Vec<DslIR<C>> {
+    operations
+        .into_iter()
+        .filter_map(|op| match op {
+            DslIR::For(start, end, loop_var, body) => {
+                let body = remove_dead(body, reads);
+                (!body.is_empty() || reads.contains(&(Kind::Var, loop_var.0)))
+                    .then(|| DslIR::For(start, end, loop_var, body))
+            }
+            DslIR::IfEq(lhs, rhs, then, else_) => branch(then, else_, reads, |then, else_| {
+                DslIR::IfEq(lhs, rhs, then, else_)
+            }),
+            DslIR::IfNe(lhs, rhs, then, else_) => branch(then, else_, reads, |then, else_| {
+                DslIR::IfNe(lhs, rhs, then, else_)
+            }),
+            DslIR::IfEqI(lhs, rhs, then, else_) => branch(then, else_, reads, |then, else_| {
+                DslIR::IfEqI(lhs, rhs, then, else_)
+            }),
+            DslIR::IfNeI(lhs, rhs, then, else_) => branch(then, else_, reads, |then, else_| {
+                DslIR::IfNeI(lhs, rhs, then, else_)
+            }),
+            op => match decode(&op) {
+                Some((dst, expr)) if !reads.contains(&dst) && !may_fail::<C>(&expr) => None,
+                _ => Some(op),
+            },
+        })
+        .collect()
+}
+
+/// Divisions by variables are kept even when their result is unused, as they fail on zero.
+fn may_fail<C: Config>(expr: &Ex<C>) -> bool {
+    matches!(expr, Expr::Binary(BinOp::Div, _, rhs) if !rhs.is_const() || rhs.is_zero())
+}
+
+fn branch<C: Config>(
+    then: Vec<DslIR<C>>,
+    else_: Vec<DslIR<C>>,
+    reads: &HashSet<Id>,
+    f: impl FnOnce(Vec<DslIR<C>>, Vec<DslIR<C>>) -> DslIR<C>,
+) -> Option<DslIR<C>> {
+    let then = remove_dead(then, reads);
+    let else_ = remove_dead(else_, reads);
+    (!then.is_empty() || !else_.is_empty()).then(|| f(then, else_))
+}
+
+/// Collects the variables read by an instruction, including the ones of nested blocks in the same
+/// frame.
+pub(super) fn collect_reads<C: Config>(op: &DslIR<C>, reads: &mut HashSet<Id>) {
+    if let Some((_, expr)) = decode(op) {
+        if let Expr::Binary(_, lhs, rhs) = expr {
+            reads.extend(lhs.id());
+            reads.extend(rhs.id());
+        }
+        return;
+    }
+
+    match op {
+        DslIR::For(start, end, _, body) => {
+            read_usize(start, reads);
+            read_usize(end, reads);
+            for op in body {
+                collect_reads(op, reads);
+            }
+        }
+        DslIR::IfEq(lhs, rhs, then, else_) | DslIR::IfNe(lhs, rhs, then, else_) => {
+            reads.extend([(Kind::Var, lhs.0), (Kind::Var, rhs.0)]);
+            for op in then.iter().chain(else_) {
+                collect_reads(op, reads);
+            }
+        }
+        DslIR::IfEqI(lhs, _, then, else_) | DslIR::IfNeI(lhs, _, then, else_) => {
+            reads.insert((Kind::Var, lhs.0));
+            for op in then.iter().chain(else_) {
+                collect_reads(op, reads);
+            }
+        }
+        DslIR::AssertEqV(lhs, rhs) | DslIR::AssertNeV(lhs, rhs) => {
+            reads.extend([(Kind::Var, lhs.0), (Kind::Var, rhs.0)]);
+        }
+        DslIR::AssertEqVI(lhs, _) | DslIR::AssertNeVI(lhs, _) => {
+            reads.insert((Kind::Var, lhs.0));
+        }
+        DslIR::AssertEqF(lhs, rhs) | DslIR::AssertNeF(lhs, rhs) => {
+            reads.extend([(Kind::Felt, lhs.0), (Kind::Felt, rhs.0)]);
+        }
+        DslIR::AssertEqFI(lhs, _) | DslIR::AssertNeFI(lhs, _) => {
+            reads.insert((Kind::Felt, lhs.0));
+        }
+        DslIR::AssertEqE(lhs, rhs) | DslIR::AssertNeE(lhs, rhs) => {
+            reads.extend([(Kind::Ext, lhs.0), (Kind::Ext, rhs.0)]);
+        }
+        DslIR::AssertEqEI(lhs, _) | DslIR::AssertNeEI(lhs, _) => {
+            reads.insert((Kind::Ext, lhs.0));
+        }
+        DslIR::Alloc(_, len, _) => read_usize(len, reads),
+        DslIR::LoadV(_, ptr, index) | DslIR::LoadF(_, ptr, index) | DslIR::LoadE(_, ptr, index) => {
+            read_ptr(ptr, reads);
+            read_usize(index, reads);
+        }
+        DslIR::StoreV(value, ptr, index) => {
+            reads.insert((Kind::Var, value.0));
+            read_ptr(ptr, reads);
+            read_usize(index, reads);
+        }
+        DslIR::StoreF(value, ptr, index) => {
+            reads.insert((Kind::Felt, value.0));
+            read_ptr(ptr, reads);
+            read_usize(index, reads);
+        }
+        DslIR::StoreE(value, ptr, index) => {
+            reads.insert((Kind::Ext, value.0));
+            read_ptr(ptr, reads);
+            read_usize(index, reads);
+        }
+        DslIR::Poseidon2PermuteBabyBear(dst, src) => {
+            read_ptr(dst, reads);
+            read_ptr(src, reads);
+        }
+        DslIR::Num2BitsF(output, value) => {
+            read_ptr(output, reads);
+            reads.insert((Kind::Felt, value.0));
+        }
+        DslIR::CommitF(value) => {
+            reads.insert((Kind::Felt, value.0));
+        }
+        DslIR::Call(_, arguments, _) => reads.extend(arguments.iter().map(function_id)),
+        _ => {}
+    }
+}
+
+fn read_usize<N>(value: &Usize<N>, reads: &mut HashSet<Id>) {
+    if let Usize::Var(var) = value {
+        reads.insert((Kind::Var, var.0));
+    }
+}
+
+fn read_ptr<N>(ptr: &Ptr<N>, reads: &mut HashSet<Id>) {
+    reads.insert((Kind::Var, ptr.address.0));
+}
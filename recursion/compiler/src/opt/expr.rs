@@ -0,0 +1,319 @@
+use core::marker::PhantomData;
+
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+
+use crate::ir::{self, Config, DslIR, FunctionVariable};
+
+/// The kind of a variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Kind {
+    Var,
+    Felt,
+    Ext,
+}
+
+/// A variable, identified by its kind and its index.
+pub(crate) type Id = (Kind, u32);
+
+/// An operand of an arithmetic instruction, either a variable or a constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Operand<N, F, EF> {
+    Var(u32),
+    Felt(u32),
+    Ext(u32),
+    ConstV(N),
+    ConstF(F),
+    ConstE(EF),
+}
+
+pub(crate) type Op<C> = Operand<<C as Config>::N, <C as Config>::F, <C as Config>::EF>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// The value computed by an arithmetic instruction.
+///
+/// Negations and inversions are represented as `0 - x` and `1 / x`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Expr<N, F, EF> {
+    Const(Operand<N, F, EF>),
+    Copy(Operand<N, F, EF>),
+    Binary(BinOp, Operand<N, F, EF>, Operand<N, F, EF>),
+}
+
+pub(crate) type Ex<C> = Expr<<C as Config>::N, <C as Config>::F, <C as Config>::EF>;
+
+impl<N: Field, F: Field, EF: AbstractExtensionField<F> + Field> Operand<N, F, EF> {
+    /// Returns the variable of the operand, if any.
+    pub(crate) fn id(&self) -> Option<Id> {
+        match self {
+            Operand::Var(i) => Some((Kind::Var, *i)),
+            Operand::Felt(i) => Some((Kind::Felt, *i)),
+            Operand::Ext(i) => Some((Kind::Ext, *i)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_id(id: Id) -> Self {
+        match id {
+            (Kind::Var, i) => Operand::Var(i),
+            (Kind::Felt, i) => Operand::Felt(i),
+            (Kind::Ext, i) => Operand::Ext(i),
+        }
+    }
+
+    pub(crate) fn is_const(&self) -> bool {
+        self.id().is_none()
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        match self {
+            Operand::ConstV(c) => c.is_zero(),
+            Operand::ConstF(c) => c.is_zero(),
+            Operand::ConstE(c) => c.is_zero(),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn is_one(&self) -> bool {
+        match self {
+            Operand::ConstV(c) => c.is_one(),
+            Operand::ConstF(c) => c.is_one(),
+            Operand::ConstE(c) => c.is_one(),
+            _ => false,
+        }
+    }
+
+    /// Returns the additive inverse of a constant.
+    pub(crate) fn neg(&self) -> Option<Self> {
+        match self {
+            Operand::ConstV(c) => Some(Operand::ConstV(-*c)),
+            Operand::ConstF(c) => Some(Operand::ConstF(-*c)),
+            Operand::ConstE(c) => Some(Operand::ConstE(-*c)),
+            _ => None,
+        }
+    }
+
+    /// Returns the multiplicative inverse of a nonzero constant.
+    pub(crate) fn inverse(&self) -> Option<Self> {
+        match self {
+            Operand::ConstV(c) => c.try_inverse().map(Operand::ConstV),
+            Operand::ConstF(c) => c.try_inverse().map(Operand::ConstF),
+            Operand::ConstE(c) => c.try_inverse().map(Operand::ConstE),
+            _ => None,
+        }
+    }
+
+    /// Returns the constant as an element of the extension field.
+    fn as_ext(&self) -> Option<EF> {
+        match self {
+            Operand::ConstF(c) => Some(EF::from_base(*c)),
+            Operand::ConstE(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    /// Returns the zero constant of a kind.
+    pub(crate) fn zero(kind: Kind) -> Self {
+        match kind {
+            Kind::Var => Operand::ConstV(N::zero()),
+            Kind::Felt => Operand::ConstF(F::zero()),
+            Kind::Ext => Operand::ConstE(EF::zero()),
+        }
+    }
+}
+
+/// Returns the variable passed to or returned from a function.
+pub(crate) fn function_id<C: Config>(variable: &FunctionVariable<C>) -> Id {
+    match variable {
+        FunctionVariable::Var(var) => (Kind::Var, var.0),
+        FunctionVariable::Felt(felt) => (Kind::Felt, felt.0),
+        FunctionVariable::Ext(ext) => (Kind::Ext, ext.0),
+    }
+}
+
+/// Computes `lhs op rhs` for constants, as a value of kind `kind`. Divisions by zero are not
+/// evaluated, so that they still fail at runtime.
+pub(crate) fn fold<N, F, EF>(
+    kind: Kind,
+    op: BinOp,
+    lhs: Operand<N, F, EF>,
+    rhs: Operand<N, F, EF>,
+) -> Option<Operand<N, F, EF>>
+where
+    N: Field,
+    F: Field,
+    EF: AbstractExtensionField<F> + Field,
+{
+    fn apply<T: Field>(op: BinOp, lhs: T, rhs: T) -> Option<T> {
+        match op {
+            BinOp::Add => Some(lhs + rhs),
+            BinOp::Sub => Some(lhs - rhs),
+            BinOp::Mul => Some(lhs * rhs),
+            BinOp::Div => rhs.try_inverse().map(|inverse| lhs * inverse),
+        }
+    }
+
+    match (kind, lhs, rhs) {
+        (Kind::Var, Operand::ConstV(lhs), Operand::ConstV(rhs)) => {
+            apply(op, lhs, rhs).map(Operand::ConstV)
+        }
+        (Kind::Felt, Operand::ConstF(lhs), Operand::ConstF(rhs)) => {
+            apply(op, lhs, rhs).map(Operand::ConstF)
+        }
+        (Kind::Ext, lhs, rhs) => apply(op, lhs.as_ext()?, rhs.as_ext()?).map(Operand::ConstE),
+        _ => None,
+    }
+}
+
+/// Decodes an arithmetic instruction into its destination and the value it computes.
+pub(crate) fn decode<C: Config>(instruction: &DslIR<C>) -> Option<(Id, Ex<C>)> {
+    use BinOp::*;
+    use Operand::*;
+
+    let v = |var: &ir::Var<C::N>| Var(var.0);
+    let f = |felt: &ir::Felt<C::F>| Felt(felt.0);
+    let e = |ext: &ir::Ext<C::F, C::EF>| Ext(ext.0);
+    let var = |var: &ir::Var<C::N>| (Kind::Var, var.0);
+    let felt = |felt: &ir::Felt<C::F>| (Kind::Felt, felt.0);
+    let ext = |ext: &ir::Ext<C::F, C::EF>| (Kind::Ext, ext.0);
+
+    let decoded = match instruction {
+        DslIR::Imm(d, c) => (var(d), Expr::Const(ConstV(*c))),
+        DslIR::ImmFelt(d, c) => (felt(d), Expr::Const(ConstF(*c))),
+        DslIR::ImmExt(d, c) => (ext(d), Expr::Const(ConstE(*c))),
+
+        DslIR::AddV(d, a, b) => (var(d), Expr::Binary(Add, v(a), v(b))),
+        DslIR::AddVI(d, a, c) => (var(d), Expr::Binary(Add, v(a), ConstV(*c))),
+        DslIR::SubV(d, a, b) => (var(d), Expr::Binary(Sub, v(a), v(b))),
+        DslIR::SubVI(d, a, c) => (var(d), Expr::Binary(Sub, v(a), ConstV(*c))),
+        DslIR::SubVIN(d, c, b) => (var(d), Expr::Binary(Sub, ConstV(*c), v(b))),
+        DslIR::MulV(d, a, b) => (var(d), Expr::Binary(Mul, v(a), v(b))),
+        DslIR::MulVI(d, a, c) => (var(d), Expr::Binary(Mul, v(a), ConstV(*c))),
+        DslIR::NegV(d, a) => (var(d), Expr::Binary(Sub, ConstV(C::N::zero()), v(a))),
+        DslIR::InvV(d, a) => (var(d), Expr::Binary(Div, ConstV(C::N::one()), v(a))),
+
+        DslIR::AddF(d, a, b) => (felt(d), Expr::Binary(Add, f(a), f(b))),
+        DslIR::AddFI(d, a, c) => (felt(d), Expr::Binary(Add, f(a), ConstF(*c))),
+        DslIR::SubF(d, a, b) => (felt(d), Expr::Binary(Sub, f(a), f(b))),
+        DslIR::SubFI(d, a, c) => (felt(d), Expr::Binary(Sub, f(a), ConstF(*c))),
+        DslIR::SubFIN(d, c, b) => (felt(d), Expr::Binary(Sub, ConstF(*c), f(b))),
+        DslIR::MulF(d, a, b) => (felt(d), Expr::Binary(Mul, f(a), f(b))),
+        DslIR::MulFI(d, a, c) => (felt(d), Expr::Binary(Mul, f(a), ConstF(*c))),
+        DslIR::DivF(d, a, b) => (felt(d), Expr::Binary(Div, f(a), f(b))),
+        DslIR::DivFI(d, a, c) => (felt(d), Expr::Binary(Div, f(a), ConstF(*c))),
+        DslIR::DivFIN(d, c, b) => (felt(d), Expr::Binary(Div, ConstF(*c), f(b))),
+        DslIR::NegF(d, a) => (felt(d), Expr::Binary(Sub, ConstF(C::F::zero()), f(a))),
+        DslIR::InvF(d, a) => (felt(d), Expr::Binary(Div, ConstF(C::F::one()), f(a))),
+
+        DslIR::AddE(d, a, b) => (ext(d), Expr::Binary(Add, e(a), e(b))),
+        DslIR::AddEI(d, a, c) => (ext(d), Expr::Binary(Add, e(a), ConstE(*c))),
+        DslIR::AddEF(d, a, b) => (ext(d), Expr::Binary(Add, e(a), f(b))),
+        DslIR::AddEFI(d, a, c) => (ext(d), Expr::Binary(Add, e(a), ConstF(*c))),
+        DslIR::AddEFFI(d, a, c) => (ext(d), Expr::Binary(Add, f(a), ConstE(*c))),
+        DslIR::SubE(d, a, b) => (ext(d), Expr::Binary(Sub, e(a), e(b))),
+        DslIR::SubEI(d, a, c) => (ext(d), Expr::Binary(Sub, e(a), ConstE(*c))),
+        DslIR::SubEIN(d, c, b) => (ext(d), Expr::Binary(Sub, ConstE(*c), e(b))),
+        DslIR::SubEF(d, a, b) => (ext(d), Expr::Binary(Sub, e(a), f(b))),
+        DslIR::SubEFI(d, a, c) => (ext(d), Expr::Binary(Sub, e(a), ConstF(*c))),
+        DslIR::SubEFIN(d, c, b) => (ext(d), Expr::Binary(Sub, ConstF(*c), e(b))),
+        DslIR::MulE(d, a, b) => (ext(d), Expr::Binary(Mul, e(a), e(b))),
+        DslIR::MulEI(d, a, c) => (ext(d), Expr::Binary(Mul, e(a), ConstE(*c))),
+        DslIR::MulEF(d, a, b) => (ext(d), Expr::Binary(Mul, e(a), f(b))),
+        DslIR::MulEFI(d, a, c) => (ext(d), Expr::Binary(Mul, e(a), ConstF(*c))),
+        DslIR::DivE(d, a, b) => (ext(d), Expr::Binary(Div, e(a), e(b))),
+        DslIR::DivEI(d, a, c) => (ext(d), Expr::Binary(Div, e(a), ConstE(*c))),
+        DslIR::DivEIN(d, c, b) => (ext(d), Expr::Binary(Div, ConstE(*c), e(b))),
+        DslIR::DivEF(d, a, b) => (ext(d), Expr::Binary(Div, e(a), f(b))),
+        DslIR::DivEFI(d, a, c) => (ext(d), Expr::Binary(Div, e(a), ConstF(*c))),
+        DslIR::DivEFIN(d, c, b) => (ext(d), Expr::Binary(Div, ConstF(*c), e(b))),
+        DslIR::NegE(d, a) => (ext(d), Expr::Binary(Sub, ConstE(C::EF::zero()), e(a))),
+        DslIR::InvE(d, a) => (ext(d), Expr::Binary(Div, ConstE(C::EF::one()), e(a))),
+        _ => return None,
+    };
+    Some(decoded)
+}
+
+/// Encodes the instruction computing `expr` into `dst`, if the DSL has one.
+pub(crate) fn encode<C: Config>(dst: Id, expr: Ex<C>) -> Option<DslIR<C>> {
+    use BinOp::*;
+    use Operand::*;
+
+    let v = |i: u32| ir::Var(i, PhantomData);
+    let f = |i: u32| ir::Felt(i, PhantomData);
+    let e = |i: u32| ir::Ext(i, PhantomData);
+    let d = dst.1;
+
+    let expr = match expr {
+        Expr::Copy(src) => Expr::Binary(Add, src, Operand::zero(dst.0)),
+        expr => expr,
+    };
+
+    let instruction = match (dst.0, expr) {
+        (Kind::Var, Expr::Const(ConstV(c))) => DslIR::Imm(v(d), c),
+        (Kind::Felt, Expr::Const(ConstF(c))) => DslIR::ImmFelt(f(d), c),
+        (Kind::Ext, Expr::Const(c)) => DslIR::ImmExt(e(d), c.as_ext()?),
+
+        (Kind::Var, Expr::Binary(op, lhs, rhs)) => match (op, lhs, rhs) {
+            (Add, Var(a), Var(b)) => DslIR::AddV(v(d), v(a), v(b)),
+            (Add, Var(a), ConstV(c)) | (Add, ConstV(c), Var(a)) => DslIR::AddVI(v(d), v(a), c),
+            (Sub, Var(a), Var(b)) => DslIR::SubV(v(d), v(a), v(b)),
+            (Sub, Var(a), ConstV(c)) => DslIR::SubVI(v(d), v(a), c),
+            (Sub, ConstV(c), Var(b)) if c.is_zero() => DslIR::NegV(v(d), v(b)),
+            (Sub, ConstV(c), Var(b)) => DslIR::SubVIN(v(d), c, v(b)),
+            (Mul, Var(a), Var(b)) => DslIR::MulV(v(d), v(a), v(b)),
+            (Mul, Var(a), ConstV(c)) | (Mul, ConstV(c), Var(a)) => DslIR::MulVI(v(d), v(a), c),
+            (Div, ConstV(c), Var(b)) if c.is_one() => DslIR::InvV(v(d), v(b)),
+            _ => return None,
+        },
+
+        (Kind::Felt, Expr::Binary(op, lhs, rhs)) => match (op, lhs, rhs) {
+            (Add, Felt(a), Felt(b)) => DslIR::AddF(f(d), f(a), f(b)),
+            (Add, Felt(a), ConstF(c)) | (Add, ConstF(c), Felt(a)) => DslIR::AddFI(f(d), f(a), c),
+            (Sub, Felt(a), Felt(b)) => DslIR::SubF(f(d), f(a), f(b)),
+            (Sub, Felt(a), ConstF(c)) => DslIR::SubFI(f(d), f(a), c),
+            (Sub, ConstF(c), Felt(b)) => DslIR::SubFIN(f(d), c, f(b)),
+            (Mul, Felt(a), Felt(b)) => DslIR::MulF(f(d), f(a), f(b)),
+            (Mul, Felt(a), ConstF(c)) | (Mul, ConstF(c), Felt(a)) => DslIR::MulFI(f(d), f(a), c),
+            (Div, Felt(a), Felt(b)) => DslIR::DivF(f(d), f(a), f(b)),
+            (Div, Felt(a), ConstF(c)) => DslIR::DivFI(f(d), f(a), c),
+            (Div, ConstF(c), Felt(b)) => DslIR::DivFIN(f(d), c, f(b)),
+            _ => return None,
+        },
+
+        (Kind::Ext, Expr::Binary(op, lhs, rhs)) => match (op, lhs, rhs) {
+            (Add, Ext(a), Ext(b)) => DslIR::AddE(e(d), e(a), e(b)),
+            (Add, Ext(a), ConstE(c)) | (Add, ConstE(c), Ext(a)) => DslIR::AddEI(e(d), e(a), c),
+            (Add, Ext(a), Felt(b)) | (Add, Felt(b), Ext(a)) => DslIR::AddEF(e(d), e(a), f(b)),
+            (Add, Ext(a), ConstF(c)) | (Add, ConstF(c), Ext(a)) => DslIR::AddEFI(e(d), e(a), c),
+            (Add, Felt(a), c) | (Add, c, Felt(a)) if c.is_const() => {
+                DslIR::AddEFFI(e(d), f(a), c.as_ext()?)
+            }
+            (Sub, Ext(a), Ext(b)) => DslIR::SubE(e(d), e(a), e(b)),
+            (Sub, Ext(a), ConstE(c)) => DslIR::SubEI(e(d), e(a), c),
+            (Sub, ConstE(c), Ext(b)) => DslIR::SubEIN(e(d), c, e(b)),
+            (Sub, Ext(a), Felt(b)) => DslIR::SubEF(e(d), e(a), f(b)),
+            (Sub, Ext(a), ConstF(c)) => DslIR::SubEFI(e(d), e(a), c),
+            (Sub, ConstF(c), Ext(b)) => DslIR::SubEFIN(e(d), c, e(b)),
+            (Mul, Ext(a), Ext(b)) => DslIR::MulE(e(d), e(a), e(b)),
+            (Mul, Ext(a), ConstE(c)) | (Mul, ConstE(c), Ext(a)) => DslIR::MulEI(e(d), e(a), c),
+            (Mul, Ext(a), Felt(b)) | (Mul, Felt(b), Ext(a)) => DslIR::MulEF(e(d), e(a), f(b)),
+            (Mul, Ext(a), ConstF(c)) | (Mul, ConstF(c), Ext(a)) => DslIR::MulEFI(e(d), e(a), c),
+            (Div, Ext(a), Ext(b)) => DslIR::DivE(e(d), e(a), e(b)),
+            (Div, Ext(a), ConstE(c)) => DslIR::DivEI(e(d), e(a), c),
+            (Div, ConstE(c), Ext(b)) => DslIR::DivEIN(e(d), c, e(b)),
+            (Div, Ext(a), Felt(b)) => DslIR::DivEF(e(d), e(a), f(b)),
+            (Div, Ext(a), ConstF(c)) => DslIR::DivEFI(e(d), e(a), c),
+            (Div, ConstF(c), Ext(b)) => DslIR::DivEFIN(e(d), c, e(b)),
+            _ => return None,
+        },
+
+        _ => return None,
+    };
+    Some(instruction)
+}
@@ -0,0 +1,225 @@
+//! Optimization passes over the operations of a program, which run before they are compiled by a
+//! backend.
+mod dce;
+mod expr;
+mod simplify;
+
+use alloc::vec::Vec;
+use core::fmt;
+use std::collections::HashSet;
+
+use crate::ir::{Builder, Config, DslIR};
+
+use dce::{collect_reads, eliminate_dead_code};
+use expr::decode;
+pub(crate) use expr::{function_id, Id, Kind};
+use simplify::{definitions, Simplifier};
+
+/// The number of instructions of a program before and after its optimization.
+///
+/// Blocks count as one instruction, plus the instructions they contain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptimizationReport {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} instructions before optimization, {} after",
+            self.before, self.after
+        )
+    }
+}
+
+/// Optimizes a program with constant folding, algebraic simplification, common subexpression
+/// elimination, immediate fusion and dead code elimination.
+pub fn optimize<C: Config>(operations: Vec<DslIR<C>>) -> (Vec<DslIR<C>>, OptimizationReport) {
+    let before = count(&operations);
+    let operations = Simplifier::default().run(operations);
+    let operations = eliminate_dead_code(operations, &HashSet::new());
+    let after = count(&operations);
+    (operations, OptimizationReport { before, after })
+}
+
+/// Counts the instructions of a program, including the ones of nested blocks and functions.
+pub fn count<C: Config>(operations: &[DslIR<C>]) -> usize {
+    operations
+        .iter()
+        .map(|op| match op {
+            DslIR::For(_, _, _, body) => 1 + count(body),
+            DslIR::IfEq(_, _, then, else_)
+            | DslIR::IfNe(_, _, then, else_)
+            | DslIR::IfEqI(_, _, then, else_)
+            | DslIR::IfNeI(_, _, then, else_) => 1 + count(then) + count(else_),
+            DslIR::Function(_, _, _, body) => 1 + count(&body.operations),
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Collects the variables read or assigned by a program, including the ones of nested blocks but
+/// not of nested functions, which run in their own frame.
+pub(crate) fn collect_variables<C: Config>(operations: &[DslIR<C>], ids: &mut HashSet<Id>) {
+    for op in operations {
+        collect_reads(op, ids);
+        ids.extend(decode(op).map(|(dst, _)| dst));
+        ids.extend(definitions(op));
+        match op {
+            DslIR::For(_, _, var, body) => {
+                ids.insert((Kind::Var, var.0));
+                collect_variables(body, ids);
+            }
+            DslIR::IfEq(_, _, then, else_)
+            | DslIR::IfNe(_, _, then, else_)
+            | DslIR::IfEqI(_, _, then, else_)
+            | DslIR::IfNeI(_, _, then, else_) => {
+                collect_variables(then, ids);
+                collect_variables(else_, ids);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<C: Config> Builder<C> {
+    /// Optimizes the operations of the builder in place.
+    pub fn optimize(&mut self) -> OptimizationReport {
+        let operations = core::mem::take(&mut self.operations);
+        let (operations, report) = optimize(operations);
+        self.operations = operations;
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::{extension::BinomialExtensionField, AbstractField};
+
+    use super::*;
+    use crate::asm::VmBuilder;
+    use crate::ir::{Ext, Felt, Var};
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    #[test]
+    fn test_fold_constants() {
+        let mut builder = VmBuilder::<F, EF>::default();
+        let a: Felt<_> = builder.eval(F::from_canonical_u32(3));
+        let b: Felt<_> = builder.eval(F::from_canonical_u32(4));
+        let c: Felt<_> = builder.eval(a * b + F::one());
+        builder.assert_felt_eq(c, F::from_canonical_u32(13));
+        let x: Ext<_, _> = builder.eval(EF::from_canonical_u32(2));
+        let y: Ext<_, _> = builder.eval(x * x + x);
+        builder.assert_ext_eq(y, EF::from_canonical_u32(6));
+
+        let report = builder.optimize();
+        assert_eq!(report.after, 0);
+        assert!(report.before > 0);
+    }
+
+    #[test]
+    fn test_remove_identities() {
+        let mut builder = VmBuilder::<F, EF>::default();
+        let a: Felt<_> = builder.hint();
+        let b: Felt<_> = builder.eval(a + F::zero());
+        let c: Felt<_> = builder.eval(b * F::one());
+        builder.assert_felt_eq(c, F::one());
+
+        builder.optimize();
+        assert!(matches!(
+            builder.operations.as_slice(),
+            [DslIR::HintF(_), DslIR::AssertEqFI(lhs, value)] if lhs.0 == a.0 && value.is_one()
+        ));
+    }
+
+    #[test]
+    fn test_fuse_immediates() {
+        let mut builder = VmBuilder::<F, EF>::default();
+        let a: Var<_> = builder.hint();
+        let b: Var<_> = builder.eval(a + F::one());
+        let c: Var<_> = builder.eval(b + F::two());
+        let d: Var<_> = builder.eval(c - F::from_canonical_u32(5));
+        builder.assert_var_ne(d, F::zero());
+
+        builder.optimize();
+        assert!(matches!(
+            builder.operations.as_slice(),
+            [DslIR::HintV(_), DslIR::AddVI(_, src, value), DslIR::AssertNeVI(_, _)]
+                if src.0 == a.0 && *value == -F::two()
+        ));
+    }
+
+    #[test]
+    fn test_eliminate_common_subexpressions() {
+        let mut builder = VmBuilder::<F, EF>::default();
+        let a: Ext<_, _> = builder.hint();
+        let b: Ext<_, _> = builder.eval(a * a);
+        let c: Ext<_, _> = builder.eval(a * a);
+        let d: Ext<_, _> = builder.eval(b + c);
+        builder.assert_ext_ne(d, EF::zero());
+
+        builder.optimize();
+        let products = builder
+            .operations
+            .iter()
+            .filter(|op| matches!(op, DslIR::MulE(..)))
+            .count();
+        assert_eq!(products, 1);
+        assert_eq!(builder.operations.len(), 4);
+    }
+
+    #[test]
+    fn test_keep_block_definitions() {
+        let mut builder = VmBuilder::<F, EF>::default();
+        let cond: Var<_> = builder.hint();
+        let x: Var<_> = builder.eval(F::one());
+        builder.if_eq(cond, F::one()).then(|builder| {
+            builder.assign(x, F::two());
+        });
+        builder.assert_var_eq(x, F::one());
+
+        let y: Felt<_> = builder.eval(F::zero());
+        builder.range(0, cond).for_each(|_, builder| {
+            builder.assign(y, y + F::one());
+        });
+        builder.assert_felt_eq(y, F::zero());
+
+        // A division by a variable is kept even if its result is unused.
+        let z: Felt<_> = builder.hint();
+        let _: Felt<_> = builder.eval(F::one() / z);
+
+        builder.optimize();
+        let asserts = builder
+            .operations
+            .iter()
+            .filter(|op| matches!(op, DslIR::AssertEqVI(..) | DslIR::AssertEqFI(..)))
+            .count();
+        assert_eq!(asserts, 2);
+        assert!(matches!(
+            builder.operations.last(),
+            Some(DslIR::DivFIN(..) | DslIR::InvF(..))
+        ));
+    }
+
+    #[test]
+    fn test_branch_on_constant() {
+        let mut builder = VmBuilder::<F, EF>::default();
+        let a: Var<_> = builder.eval(F::two());
+        let x: Felt<_> = builder.hint();
+        builder.if_eq(a, F::two()).then_or_else(
+            |builder| builder.assert_felt_eq(x, F::one()),
+            |builder| builder.assert_felt_eq(x, F::two()),
+        );
+
+        builder.optimize();
+        assert!(matches!(
+            builder.operations.as_slice(),
+            [DslIR::HintF(_), DslIR::AssertEqFI(_, value)] if value.is_one()
+        ));
+    }
+}
@@ -0,0 +1,432 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use std::collections::HashMap;
+
+use super::expr::{decode, encode, fold, function_id, BinOp, Ex, Expr, Id, Kind, Op, Operand};
+use crate::ir::{self, Config, DslIR};
+
+/// The key of a computed expression: the kind of its result, the operation, its operands and the
+/// versions of its operands.
+type ExprKey<C> = (Kind, BinOp, Op<C>, Op<C>, u32, u32);
+
+/// What is known about the values of the variables at some point of a block.
+///
+/// Every fact records the versions of the variables it involves, and is only valid as long as none
+/// of them has been redefined since.
+struct Knowledge<C: Config> {
+    /// The variables holding a constant: `id -> (value, version of id)`.
+    constants: HashMap<Id, (Op<C>, u32)>,
+    /// The variables holding a copy of another: `id -> (src, version of id, version of src)`.
+    copies: HashMap<Id, (Id, u32, u32)>,
+    /// The variables holding a computed expression: `key -> (id, version of id)`.
+    expressions: HashMap<ExprKey<C>, (Id, u32)>,
+    /// The variables holding `src op c`: `id -> (op, src, c, version of id, version of src)`.
+    affine: HashMap<Id, (BinOp, Id, Op<C>, u32, u32)>,
+}
+
+impl<C: Config> Default for Knowledge<C> {
+    fn default() -> Self {
+        Self {
+            constants: HashMap::new(),
+            copies: HashMap::new(),
+            expressions: HashMap::new(),
+            affine: HashMap::new(),
+        }
+    }
+}
+
+/// Folds constants, propagates copies, simplifies algebraic identities, eliminates common
+/// subexpressions and fuses constants into immediate instructions, in a single forward pass.
+///
+/// Blocks which may run several times or not at all (loops and branches) start without any
+/// knowledge of the enclosing block, and the variables they define are forgotten afterwards.
+pub(crate) struct Simplifier<C: Config> {
+    versions: HashMap<Id, u32>,
+    known: Knowledge<C>,
+}
+
+impl<C: Config> Default for Simplifier<C> {
+    fn default() -> Self {
+        Self {
+            versions: HashMap::new(),
+            known: Knowledge::default(),
+        }
+    }
+}
+
+impl<C: Config> Simplifier<C> {
+    pub(crate) fn run(&mut self, operations: Vec<DslIR<C>>) -> Vec<DslIR<C>> {
+        let mut output = Vec::with_capacity(operations.len());
+        for op in operations {
+            self.instruction(op, &mut output);
+        }
+        output
+    }
+
+    fn nested(&mut self, operations: Vec<DslIR<C>>) -> Vec<DslIR<C>> {
+        let known = core::mem::take(&mut self.known);
+        let output = self.run(operations);
+        self.known = known;
+        output
+    }
+
+    fn version(&self, id: Id) -> u32 {
+        self.versions.get(&id).copied().unwrap_or(0)
+    }
+
+    fn operand_version(&self, operand: Op<C>) -> u32 {
+        operand.id().map(|id| self.version(id)).unwrap_or(0)
+    }
+
+    /// Records that a variable is assigned a new value, invalidating the facts involving it.
+    fn define(&mut self, id: Id) {
+        *self.versions.entry(id).or_insert(0) += 1;
+    }
+
+    fn constant(&self, id: Id) -> Option<Op<C>> {
+        match self.known.constants.get(&id) {
+            Some((value, version)) if *version == self.version(id) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn copy_of(&self, id: Id) -> Option<Id> {
+        match self.known.copies.get(&id) {
+            Some((src, version, src_version))
+                if *version == self.version(id) && *src_version == self.version(*src) =>
+            {
+                Some(*src)
+            }
+            _ => None,
+        }
+    }
+
+    /// Replaces an operand by a constant or by the variable it is a copy of, when known.
+    fn resolve(&self, operand: Op<C>) -> Op<C> {
+        let Some(id) = operand.id() else {
+            return operand;
+        };
+        if let Some(value) = self.constant(id) {
+            return value;
+        }
+        self.copy_of(id).map(Operand::from_id).unwrap_or(operand)
+    }
+
+    fn instruction(&mut self, op: DslIR<C>, output: &mut Vec<DslIR<C>>) {
+        if let Some((dst, expr)) = decode(&op) {
+            self.arithmetic(op, dst, expr, output);
+            return;
+        }
+
+        let var = |var: &ir::Var<C::N>| Operand::Var(var.0);
+        match op {
+            DslIR::For(start, end, loop_var, body) => {
+                let id = (Kind::Var, loop_var.0);
+                self.define(id);
+                let body = self.nested(body);
+                self.define(id);
+                output.push(DslIR::For(start, end, loop_var, body));
+            }
+            DslIR::IfEq(lhs, rhs, then, else_) => {
+                self.branch(true, var(&lhs), var(&rhs), then, else_, output)
+            }
+            DslIR::IfNe(lhs, rhs, then, else_) => {
+                self.branch(false, var(&lhs), var(&rhs), then, else_, output)
+            }
+            DslIR::IfEqI(lhs, rhs, then, else_) => {
+                self.branch(true, var(&lhs), Operand::ConstV(rhs), then, else_, output)
+            }
+            DslIR::IfNeI(lhs, rhs, then, else_) => {
+                self.branch(false, var(&lhs), Operand::ConstV(rhs), then, else_, output)
+            }
+            DslIR::AssertEqV(lhs, rhs) => {
+                self.assert(op, true, var(&lhs), var(&rhs), output);
+            }
+            DslIR::AssertNeV(lhs, rhs) => {
+                self.assert(op, false, var(&lhs), var(&rhs), output);
+            }
+            DslIR::AssertEqVI(lhs, rhs) => {
+                self.assert(op, true, var(&lhs), Operand::ConstV(rhs), output);
+            }
+            DslIR::AssertNeVI(lhs, rhs) => {
+                self.assert(op, false, var(&lhs), Operand::ConstV(rhs), output);
+            }
+            DslIR::AssertEqF(lhs, rhs) => {
+                self.assert(op, true, Operand::Felt(lhs.0), Operand::Felt(rhs.0), output);
+            }
+            DslIR::AssertNeF(lhs, rhs) => {
+                self.assert(
+                    op,
+                    false,
+                    Operand::Felt(lhs.0),
+                    Operand::Felt(rhs.0),
+                    output,
+                );
+            }
+            DslIR::AssertEqFI(lhs, rhs) => {
+                self.assert(op, true, Operand::Felt(lhs.0), Operand::ConstF(rhs), output);
+            }
+            DslIR::AssertNeFI(lhs, rhs) => {
+                self.assert(
+                    op,
+                    false,
+                    Operand::Felt(lhs.0),
+                    Operand::ConstF(rhs),
+                    output,
+                );
+            }
+            DslIR::AssertEqE(lhs, rhs) => {
+                self.assert(op, true, Operand::Ext(lhs.0), Operand::Ext(rhs.0), output);
+            }
+            DslIR::AssertNeE(lhs, rhs) => {
+                self.assert(op, false, Operand::Ext(lhs.0), Operand::Ext(rhs.0), output);
+            }
+            DslIR::AssertEqEI(lhs, rhs) => {
+                self.assert(op, true, Operand::Ext(lhs.0), Operand::ConstE(rhs), output);
+            }
+            DslIR::AssertNeEI(lhs, rhs) => {
+                self.assert(op, false, Operand::Ext(lhs.0), Operand::ConstE(rhs), output);
+            }
+            DslIR::Function(name, parameters, results, mut body) => {
+                // The body runs in its own frame.
+                body.operations = Simplifier::default().run(body.operations);
+                output.push(DslIR::Function(name, parameters, results, body));
+            }
+            op => {
+                for id in definitions(&op) {
+                    self.define(id);
+                }
+                output.push(op);
+            }
+        }
+    }
+
+    fn arithmetic(&mut self, original: DslIR<C>, dst: Id, expr: Ex<C>, output: &mut Vec<DslIR<C>>) {
+        let expr = match expr {
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (self.resolve(lhs), self.resolve(rhs));
+                self.simplify(dst.0, op, lhs, rhs)
+            }
+            expr => expr,
+        };
+
+        match expr {
+            Expr::Const(value) => {
+                let value = fold(dst.0, BinOp::Add, value, Operand::zero(dst.0))
+                    .expect("constant of the wrong kind");
+                if self.constant(dst) == Some(value) {
+                    return;
+                }
+                output.push(encode::<C>(dst, Expr::Const(value)).unwrap());
+                self.define(dst);
+                let version = self.version(dst);
+                self.known.constants.insert(dst, (value, version));
+            }
+            Expr::Copy(src) => self.copy(dst, src, output),
+            Expr::Binary(op, lhs, rhs) => {
+                let key = (
+                    dst.0,
+                    op,
+                    lhs,
+                    rhs,
+                    self.operand_version(lhs),
+                    self.operand_version(rhs),
+                );
+                if let Some((holder, version)) = self.known.expressions.get(&key).copied() {
+                    if version == self.version(holder) {
+                        self.copy(dst, Operand::from_id(holder), output);
+                        return;
+                    }
+                }
+
+                // Instructions without an encoding are kept as they are, as simplifications never
+                // change the values of the variables they read.
+                output.push(encode::<C>(dst, expr).unwrap_or(original));
+                self.define(dst);
+
+                if lhs.id() == Some(dst) || rhs.id() == Some(dst) {
+                    return;
+                }
+                let version = self.version(dst);
+                self.known.expressions.insert(key, (dst, version));
+                if let (BinOp::Add | BinOp::Mul, Some(src)) = (op, lhs.id()) {
+                    if rhs.is_const() && src.0 == dst.0 {
+                        let src_version = self.version(src);
+                        self.known
+                            .affine
+                            .insert(dst, (op, src, rhs, version, src_version));
+                    }
+                }
+            }
+        }
+    }
+
+    fn copy(&mut self, dst: Id, src: Op<C>, output: &mut Vec<DslIR<C>>) {
+        let src_id = src.id().expect("copy of a constant");
+        if src_id == dst || self.copy_of(dst) == Some(src_id) {
+            return;
+        }
+        output.push(encode::<C>(dst, Expr::Copy(src)).expect("copy between incompatible kinds"));
+        self.define(dst);
+        if src_id.0 == dst.0 {
+            let (version, src_version) = (self.version(dst), self.version(src_id));
+            self.known
+                .copies
+                .insert(dst, (src_id, version, src_version));
+        }
+    }
+
+    /// Simplifies `lhs op rhs`, whose operands are already resolved.
+    fn simplify(&self, kind: Kind, op: BinOp, lhs: Op<C>, rhs: Op<C>) -> Ex<C> {
+        if lhs.is_const() && rhs.is_const() {
+            return match fold(kind, op, lhs, rhs) {
+                Some(value) => Expr::Const(value),
+                None => Expr::Binary(op, lhs, rhs),
+            };
+        }
+
+        // Constants go on the right of commutative operations, and subtractions and divisions by
+        // constants become additions and multiplications.
+        let (lhs, rhs) = match op {
+            BinOp::Add | BinOp::Mul if lhs.is_const() => (rhs, lhs),
+            _ => (lhs, rhs),
+        };
+        match op {
+            BinOp::Sub if rhs.is_const() => {
+                return self.simplify(kind, BinOp::Add, lhs, rhs.neg().unwrap());
+            }
+            BinOp::Div if rhs.is_const() => {
+                if let Some(inverse) = rhs.inverse() {
+                    return self.simplify(kind, BinOp::Mul, lhs, inverse);
+                }
+            }
+            _ => {}
+        }
+
+        match op {
+            BinOp::Add if rhs.is_zero() => return Expr::Copy(lhs),
+            BinOp::Mul if rhs.is_one() => return Expr::Copy(lhs),
+            BinOp::Mul if rhs.is_zero() => return Expr::Const(Operand::zero(kind)),
+            BinOp::Sub if lhs == rhs => return Expr::Const(Operand::zero(kind)),
+            _ => {}
+        }
+
+        // Fuse `(src op c1) op c2` into `src op (c1 op c2)`.
+        if let (BinOp::Add | BinOp::Mul, Some(id)) = (op, lhs.id()) {
+            if rhs.is_const() && id.0 == kind {
+                if let Some(&(inner, src, c, version, src_version)) = self.known.affine.get(&id) {
+                    if inner == op
+                        && version == self.version(id)
+                        && src_version == self.version(src)
+                    {
+                        if let Some(c) = fold(kind, op, c, rhs) {
+                            return self.simplify(kind, op, Operand::from_id(src), c);
+                        }
+                    }
+                }
+            }
+        }
+
+        Expr::Binary(op, lhs, rhs)
+    }
+
+    fn branch(
+        &mut self,
+        eq: bool,
+        lhs: Op<C>,
+        rhs: Op<C>,
+        then: Vec<DslIR<C>>,
+        else_: Vec<DslIR<C>>,
+        output: &mut Vec<DslIR<C>>,
+    ) {
+        let v = |i: u32| ir::Var(i, PhantomData);
+        let (lhs, rhs) = (self.resolve(lhs), self.resolve(rhs));
+        let (lhs, rhs) = if lhs.is_const() {
+            (rhs, lhs)
+        } else {
+            (lhs, rhs)
+        };
+
+        // The condition is known, so only the taken branch is kept, inline.
+        if let (Operand::ConstV(lhs), Operand::ConstV(rhs)) = (lhs, rhs) {
+            let taken = if (lhs == rhs) == eq { then } else { else_ };
+            for op in taken {
+                self.instruction(op, output);
+            }
+            return;
+        }
+
+        let then = self.nested(then);
+        let else_ = self.nested(else_);
+        let op = match (eq, lhs, rhs) {
+            (true, Operand::Var(a), Operand::Var(b)) => DslIR::IfEq(v(a), v(b), then, else_),
+            (false, Operand::Var(a), Operand::Var(b)) => DslIR::IfNe(v(a), v(b), then, else_),
+            (true, Operand::Var(a), Operand::ConstV(c)) => DslIR::IfEqI(v(a), c, then, else_),
+            (false, Operand::Var(a), Operand::ConstV(c)) => DslIR::IfNeI(v(a), c, then, else_),
+            _ => unreachable!("branch on a non-var operand"),
+        };
+        output.push(op);
+    }
+
+    fn assert(
+        &mut self,
+        original: DslIR<C>,
+        eq: bool,
+        lhs: Op<C>,
+        rhs: Op<C>,
+        output: &mut Vec<DslIR<C>>,
+    ) {
+        let (lhs, rhs) = (self.resolve(lhs), self.resolve(rhs));
+        let (lhs, rhs) = if lhs.is_const() {
+            (rhs, lhs)
+        } else {
+            (lhs, rhs)
+        };
+
+        // Assertions between constants which hold are removed, and the failing ones are kept.
+        if lhs.is_const() {
+            if (lhs == rhs) != eq {
+                output.push(original);
+            }
+            return;
+        }
+        output.push(assertion::<C>(eq, lhs, rhs).unwrap_or(original));
+    }
+}
+
+/// Encodes the assertion `lhs == rhs` or `lhs != rhs`, where `lhs` is a variable.
+fn assertion<C: Config>(eq: bool, lhs: Op<C>, rhs: Op<C>) -> Option<DslIR<C>> {
+    use Operand::*;
+
+    let v = |i: u32| ir::Var(i, PhantomData);
+    let f = |i: u32| ir::Felt(i, PhantomData);
+    let e = |i: u32| ir::Ext(i, PhantomData);
+    let op = match (eq, lhs, rhs) {
+        (true, Var(a), Var(b)) => DslIR::AssertEqV(v(a), v(b)),
+        (false, Var(a), Var(b)) => DslIR::AssertNeV(v(a), v(b)),
+        (true, Var(a), ConstV(c)) => DslIR::AssertEqVI(v(a), c),
+        (false, Var(a), ConstV(c)) => DslIR::AssertNeVI(v(a), c),
+        (true, Felt(a), Felt(b)) => DslIR::AssertEqF(f(a), f(b)),
+        (false, Felt(a), Felt(b)) => DslIR::AssertNeF(f(a), f(b)),
+        (true, Felt(a), ConstF(c)) => DslIR::AssertEqFI(f(a), c),
+        (false, Felt(a), ConstF(c)) => DslIR::AssertNeFI(f(a), c),
+        (true, Ext(a), Ext(b)) => DslIR::AssertEqE(e(a), e(b)),
+        (false, Ext(a), Ext(b)) => DslIR::AssertNeE(e(a), e(b)),
+        (true, Ext(a), ConstE(c)) => DslIR::AssertEqEI(e(a), c),
+        (false, Ext(a), ConstE(c)) => DslIR::AssertNeEI(e(a), c),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Returns the variables assigned by an instruction which is neither arithmetic nor a block.
+pub(super) fn definitions<C: Config>(op: &DslIR<C>) -> Vec<Id> {
+    match op {
+        DslIR::LoadV(dst, _, _) | DslIR::HintV(dst) => vec![(Kind::Var, dst.0)],
+        DslIR::LoadF(dst, _, _) | DslIR::HintF(dst) => vec![(Kind::Felt, dst.0)],
+        DslIR::LoadE(dst, _, _) | DslIR::HintE(dst) => vec![(Kind::Ext, dst.0)],
+        DslIR::Alloc(ptr, _, _) => vec![(Kind::Var, ptr.address.0)],
+        DslIR::Call(_, _, results) => results.iter().map(function_id).collect(),
+        _ => vec![],
+    }
+}
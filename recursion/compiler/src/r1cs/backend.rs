@@ -0,0 +1,943 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use std::collections::HashMap;
+
+use p3_field::{AbstractExtensionField, AbstractField, PrimeField, PrimeField32};
+use sp1_recursion_core::num2bits::NUM_BITS;
+
+use super::witness::{from_felt, from_u128, to_u128};
+use super::{Circuit, Constraint, LinearCombination, R1CSError, WitnessStep, R1CS};
+use crate::ir::{
+    Builder, Config, DslIR, Ext, Felt, FunctionVariable, MemVariable, Ptr, Usize, Var,
+};
+use crate::opt::optimize;
+
+type Lc<C> = LinearCombination<<C as Config>::N>;
+
+/// The number of bits of the emulated field elements.
+const FELT_BITS: usize = 31;
+
+/// The value of a variable, or of a memory cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value<N> {
+    Var(LinearCombination<N>),
+    Felt(LinearCombination<N>),
+    Ext([LinearCombination<N>; 4]),
+}
+
+/// The values of the variables of the current frame and of the memory.
+#[derive(Debug, Clone)]
+struct State<N> {
+    vars: HashMap<u32, LinearCombination<N>>,
+    felts: HashMap<u32, LinearCombination<N>>,
+    exts: HashMap<u32, [LinearCombination<N>; 4]>,
+    memory: HashMap<usize, Value<N>>,
+    heap: usize,
+}
+
+impl<N> Default for State<N> {
+    fn default() -> Self {
+        Self {
+            vars: HashMap::new(),
+            felts: HashMap::new(),
+            exts: HashMap::new(),
+            memory: HashMap::new(),
+            heap: 0,
+        }
+    }
+}
+
+/// Lowers a program to a rank-1 constraint system over `C::N`, emulating the arithmetic of `C::F`
+/// and `C::EF`.
+///
+/// Control flow is resolved when the circuit is built: loops are unrolled, so their bounds must be
+/// constant, and both branches of a conditional are lowered, the variables they assign being
+/// selected afterwards. Memory indices must be constant too. Hints read in a conditional block
+/// are read whichever branch is taken. Poseidon2 permutations are not supported.
+pub struct R1CSBackend<C: Config> {
+    r1cs: R1CS<C::N>,
+    steps: Vec<WitnessStep<C::N>>,
+    constraint_counts: BTreeMap<String, usize>,
+    state: State<C::N>,
+    functions: HashMap<
+        String,
+        (
+            Vec<FunctionVariable<C>>,
+            Vec<FunctionVariable<C>>,
+            Builder<C>,
+        ),
+    >,
+    /// The condition under which the current block runs, which is zero or one.
+    condition: Lc<C>,
+}
+
+impl<C: Config> Default for R1CSBackend<C> {
+    fn default() -> Self {
+        Self {
+            r1cs: R1CS::default(),
+            steps: Vec::new(),
+            constraint_counts: BTreeMap::new(),
+            state: State::default(),
+            functions: HashMap::new(),
+            condition: LinearCombination::constant(C::N::one()),
+        }
+    }
+}
+
+impl<C: Config> R1CSBackend<C>
+where
+    C::N: PrimeField,
+    C::F: PrimeField32,
+{
+    /// Lowers a program to a circuit.
+    ///
+    /// Returns [`R1CSError::Poseidon2Permute`] if the program permutes with Poseidon2, which has
+    /// no constraints in this backend, and [`R1CSError::CommitPublicValue`] if it commits a public
+    /// value.
+    pub fn compile(mut self, program: Vec<DslIR<C>>) -> Result<Circuit<C>, R1CSError> {
+        assert_eq!(
+            C::EF::D,
+            4,
+            "the R1CS backend only supports extensions of degree 4"
+        );
+        let (program, report) = optimize(program);
+        tracing::debug!("optimized program: {}", report);
+        self.run(program)?;
+        Ok(Circuit {
+            r1cs: self.r1cs,
+            steps: self.steps,
+            constraint_counts: self.constraint_counts,
+        })
+    }
+
+    fn run(&mut self, operations: Vec<DslIR<C>>) -> Result<(), R1CSError> {
+        for op in operations {
+            let before = self.r1cs.num_constraints();
+            let label = match op {
+                DslIR::For(..) | DslIR::Function(..) | DslIR::Call(..) => None,
+                DslIR::IfEq(..) | DslIR::IfNe(..) | DslIR::IfEqI(..) | DslIR::IfNeI(..) => None,
+                ref op => Some(label(op)),
+            };
+            self.instruction(op)?;
+            if let Some(label) = label {
+                self.count(label, before);
+            }
+        }
+        Ok(())
+    }
+
+    /// Attributes the constraints emitted since `before` to an instruction.
+    fn count(&mut self, label: String, before: usize) {
+        let count = self.r1cs.num_constraints() - before;
+        *self.constraint_counts.entry(label).or_insert(0) += count;
+    }
+
+    fn instruction(&mut self, op: DslIR<C>) -> Result<(), R1CSError> {
+        match op {
+            DslIR::Imm(a, b) => self.set_var(a, Lc::<C>::constant(b)),
+            DslIR::ImmFelt(a, b) => self.set_felt(a, self.felt_constant(b)),
+            DslIR::ImmExt(a, b) => {
+                let value = self.ext_constant(b);
+                self.set_ext(a, value)
+            }
+
+            DslIR::AddV(a, b, c) => self.set_var(a, self.var(b) + self.var(c)),
+            DslIR::AddVI(a, b, c) => self.set_var(a, self.var(b) + Lc::<C>::constant(c)),
+            DslIR::SubV(a, b, c) => self.set_var(a, self.var(b) - self.var(c)),
+            DslIR::SubVI(a, b, c) => self.set_var(a, self.var(b) - Lc::<C>::constant(c)),
+            DslIR::SubVIN(a, b, c) => self.set_var(a, Lc::<C>::constant(b) - self.var(c)),
+            DslIR::MulV(a, b, c) => {
+                let value = self.product(&self.var(b), &self.var(c));
+                self.set_var(a, value)
+            }
+            DslIR::MulVI(a, b, c) => self.set_var(a, self.var(b) * c),
+            DslIR::NegV(a, b) => self.set_var(a, -self.var(b)),
+            DslIR::InvV(a, b) => {
+                let value = self.var_inverse(self.var(b));
+                self.set_var(a, value)
+            }
+
+            DslIR::AddF(a, b, c) => self.felt_op(a, b, c, Self::felt_add),
+            DslIR::AddFI(a, b, c) => self.felt_op_imm(a, b, c, Self::felt_add),
+            DslIR::SubF(a, b, c) => self.felt_op(a, b, c, Self::felt_sub),
+            DslIR::SubFI(a, b, c) => self.felt_op_imm(a, b, c, Self::felt_sub),
+            DslIR::SubFIN(a, b, c) => {
+                let value = self.felt_sub(self.felt_constant(b), self.felt(c));
+                self.set_felt(a, value)
+            }
+            DslIR::MulF(a, b, c) => self.felt_op(a, b, c, Self::felt_mul),
+            DslIR::MulFI(a, b, c) => self.felt_op_imm(a, b, c, Self::felt_mul),
+            DslIR::DivF(a, b, c) => self.felt_op(a, b, c, Self::felt_div),
+            DslIR::DivFI(a, b, c) => self.felt_op_imm(a, b, c, Self::felt_div),
+            DslIR::DivFIN(a, b, c) => {
+                let value = self.felt_div(self.felt_constant(b), self.felt(c));
+                self.set_felt(a, value)
+            }
+            DslIR::NegF(a, b) => {
+                let value = self.felt_sub(Lc::<C>::zero(), self.felt(b));
+                self.set_felt(a, value)
+            }
+            DslIR::InvF(a, b) => {
+                let value = self.felt_inverse(self.felt(b));
+                self.set_felt(a, value)
+            }
+
+            DslIR::AddE(a, b, c) => self.ext_op(a, self.ext(b), self.ext(c), Self::ext_add),
+            DslIR::AddEI(a, b, c) => {
+                self.ext_op(a, self.ext(b), self.ext_constant(c), Self::ext_add)
+            }
+            DslIR::AddEF(a, b, c) => self.ext_op(a, self.ext(b), self.lift(c), Self::ext_add),
+            DslIR::AddEFI(a, b, c) => {
+                let rhs = self.ext_constant(C::EF::from_base(c));
+                self.ext_op(a, self.ext(b), rhs, Self::ext_add)
+            }
+            DslIR::AddEFFI(a, b, c) => {
+                self.ext_op(a, self.lift(b), self.ext_constant(c), Self::ext_add)
+            }
+            DslIR::SubE(a, b, c) => self.ext_op(a, self.ext(b), self.ext(c), Self::ext_sub),
+            DslIR::SubEI(a, b, c) => {
+                self.ext_op(a, self.ext(b), self.ext_constant(c), Self::ext_sub)
+            }
+            DslIR::SubEIN(a, b, c) => {
+                self.ext_op(a, self.ext_constant(b), self.ext(c), Self::ext_sub)
+            }
+            DslIR::SubEF(a, b, c) => self.ext_op(a, self.ext(b), self.lift(c), Self::ext_sub),
+            DslIR::SubEFI(a, b, c) => {
+                let rhs = self.ext_constant(C::EF::from_base(c));
+                self.ext_op(a, self.ext(b), rhs, Self::ext_sub)
+            }
+            DslIR::SubEFIN(a, b, c) => {
+                let lhs = self.ext_constant(C::EF::from_base(b));
+                self.ext_op(a, lhs, self.ext(c), Self::ext_sub)
+            }
+            DslIR::MulE(a, b, c) => self.ext_op(a, self.ext(b), self.ext(c), Self::ext_mul),
+            DslIR::MulEI(a, b, c) => {
+                self.ext_op(a, self.ext(b), self.ext_constant(c), Self::ext_mul)
+            }
+            DslIR::MulEF(a, b, c) => self.ext_op(a, self.ext(b), self.lift(c), Self::ext_mul),
+            DslIR::MulEFI(a, b, c) => {
+                let rhs = self.ext_constant(C::EF::from_base(c));
+                self.ext_op(a, self.ext(b), rhs, Self::ext_mul)
+            }
+            DslIR::DivE(a, b, c) => self.ext_op(a, self.ext(b), self.ext(c), Self::ext_div),
+            DslIR::DivEI(a, b, c) => {
+                self.ext_op(a, self.ext(b), self.ext_constant(c), Self::ext_div)
+            }
+            DslIR::DivEIN(a, b, c) => {
+                self.ext_op(a, self.ext_constant(b), self.ext(c), Self::ext_div)
+            }
+            DslIR::DivEF(a, b, c) => self.ext_op(a, self.ext(b), self.lift(c), Self::ext_div),
+            DslIR::DivEFI(a, b, c) => {
+                let rhs = self.ext_constant(C::EF::from_base(c));
+                self.ext_op(a, self.ext(b), rhs, Self::ext_div)
+            }
+            DslIR::DivEFIN(a, b, c) => {
+                let lhs = self.ext_constant(C::EF::from_base(b));
+                self.ext_op(a, lhs, self.ext(c), Self::ext_div)
+            }
+            DslIR::NegE(a, b) => {
+                let zero = self.ext_constant(C::EF::zero());
+                self.ext_op(a, zero, self.ext(b), Self::ext_sub)
+            }
+            DslIR::InvE(a, b) => {
+                let value = self.ext_inverse(self.ext(b));
+                self.set_ext(a, value)
+            }
+
+            DslIR::For(start, end, loop_var, body) => {
+                let start = self.constant_usize(start, "loop bounds");
+                let end = self.constant_usize(end, "loop bounds");
+                for i in start..end {
+                    self.set_var(loop_var, Lc::<C>::constant(C::N::from_canonical_usize(i)));
+                    self.run(body.clone())?;
+                }
+            }
+            DslIR::IfEq(lhs, rhs, then, else_) => {
+                let diff = self.var(lhs) - self.var(rhs);
+                self.branch("IfEq", diff, then, else_)?;
+            }
+            DslIR::IfNe(lhs, rhs, then, else_) => {
+                let diff = self.var(lhs) - self.var(rhs);
+                self.branch("IfNe", diff, else_, then)?;
+            }
+            DslIR::IfEqI(lhs, rhs, then, else_) => {
+                let diff = self.var(lhs) - Lc::<C>::constant(rhs);
+                self.branch("IfEqI", diff, then, else_)?;
+            }
+            DslIR::IfNeI(lhs, rhs, then, else_) => {
+                let diff = self.var(lhs) - Lc::<C>::constant(rhs);
+                self.branch("IfNeI", diff, else_, then)?;
+            }
+
+            DslIR::AssertEqV(a, b) => self.assert_zero(self.var(a) - self.var(b)),
+            DslIR::AssertNeV(a, b) => self.assert_nonzero(self.var(a) - self.var(b)),
+            DslIR::AssertEqVI(a, b) => self.assert_zero(self.var(a) - Lc::<C>::constant(b)),
+            DslIR::AssertNeVI(a, b) => self.assert_nonzero(self.var(a) - Lc::<C>::constant(b)),
+            DslIR::AssertEqF(a, b) => self.assert_zero(self.felt(a) - self.felt(b)),
+            DslIR::AssertNeF(a, b) => self.assert_nonzero(self.felt(a) - self.felt(b)),
+            DslIR::AssertEqFI(a, b) => self.assert_zero(self.felt(a) - self.felt_constant(b)),
+            DslIR::AssertNeFI(a, b) => self.assert_nonzero(self.felt(a) - self.felt_constant(b)),
+            DslIR::AssertEqE(a, b) => self.assert_ext_eq(self.ext(a), self.ext(b)),
+            DslIR::AssertNeE(a, b) => self.assert_ext_ne(self.ext(a), self.ext(b)),
+            DslIR::AssertEqEI(a, b) => self.assert_ext_eq(self.ext(a), self.ext_constant(b)),
+            DslIR::AssertNeEI(a, b) => self.assert_ext_ne(self.ext(a), self.ext_constant(b)),
+
+            DslIR::Alloc(ptr, len, size) => {
+                let len = self.constant_usize(len, "allocation lengths");
+                let base = self.state.heap;
+                self.state.heap += len * size;
+                self.set_var(
+                    ptr.address,
+                    Lc::<C>::constant(C::N::from_canonical_usize(base)),
+                );
+            }
+            DslIR::LoadV(var, ptr, index) => {
+                let address = self.address(ptr, index, <Var<C::N> as MemVariable<C>>::size_of());
+                match self.state.memory.get(&address) {
+                    Some(Value::Var(value)) => self.set_var(var, value.clone()),
+                    None => self.set_var(var, Lc::<C>::zero()),
+                    Some(_) => panic!("load of a var from a memory cell of another type"),
+                }
+            }
+            DslIR::LoadF(felt, ptr, index) => {
+                let address = self.address(ptr, index, <Felt<C::F> as MemVariable<C>>::size_of());
+                match self.state.memory.get(&address) {
+                    Some(Value::Felt(value)) => self.set_felt(felt, value.clone()),
+                    None => self.set_felt(felt, Lc::<C>::zero()),
+                    Some(_) => panic!("load of a felt from a memory cell of another type"),
+                }
+            }
+            DslIR::LoadE(ext, ptr, index) => {
+                let size = <Ext<C::F, C::EF> as MemVariable<C>>::size_of();
+                let address = self.address(ptr, index, size);
+                match self.state.memory.get(&address) {
+                    Some(Value::Ext(value)) => self.set_ext(ext, value.clone()),
+                    None => self.set_ext(ext, self.ext_constant(C::EF::zero())),
+                    Some(_) => panic!("load of an ext from a memory cell of another type"),
+                }
+            }
+            DslIR::StoreV(var, ptr, index) => {
+                let address = self.address(ptr, index, <Var<C::N> as MemVariable<C>>::size_of());
+                self.state.memory.insert(address, Value::Var(self.var(var)));
+            }
+            DslIR::StoreF(felt, ptr, index) => {
+                let address = self.address(ptr, index, <Felt<C::F> as MemVariable<C>>::size_of());
+                self.state
+                    .memory
+                    .insert(address, Value::Felt(self.felt(felt)));
+            }
+            DslIR::StoreE(ext, ptr, index) => {
+                let size = <Ext<C::F, C::EF> as MemVariable<C>>::size_of();
+                let address = self.address(ptr, index, size);
+                self.state.memory.insert(address, Value::Ext(self.ext(ext)));
+            }
+            DslIR::Num2BitsF(ptr, felt) => {
+                let bits = self.canonical(self.felt(felt));
+                let size = <Felt<C::F> as MemVariable<C>>::size_of();
+                for (i, bit) in bits.into_iter().take(NUM_BITS).enumerate() {
+                    let address = self.address(ptr, Usize::Const(i), size);
+                    let value = Value::Felt(Lc::<C>::variable(bit));
+                    self.state.memory.insert(address, value);
+                }
+            }
+            DslIR::Poseidon2PermuteBabyBear(_, _) => return Err(R1CSError::Poseidon2Permute),
+            DslIR::CommitF(_) => return Err(R1CSError::CommitPublicValue),
+
+            DslIR::HintV(var) => {
+                let variable = self.hint();
+                self.set_var(var, Lc::<C>::variable(variable));
+            }
+            DslIR::HintF(felt) => {
+                let value = self.hint_felt();
+                self.set_felt(felt, value);
+            }
+            DslIR::HintE(ext) => {
+                let value = core::array::from_fn(|_| self.hint_felt());
+                self.set_ext(ext, value);
+            }
+
+            DslIR::Function(name, parameters, results, body) => {
+                self.functions.insert(name, (parameters, results, body));
+            }
+            DslIR::Call(name, arguments, results) => self.call(&name, arguments, results)?,
+        }
+        Ok(())
+    }
+
+    fn var(&self, var: Var<C::N>) -> Lc<C> {
+        self.state
+            .vars
+            .get(&var.0)
+            .cloned()
+            .unwrap_or(Lc::<C>::zero())
+    }
+
+    fn felt(&self, felt: Felt<C::F>) -> Lc<C> {
+        self.state
+            .felts
+            .get(&felt.0)
+            .cloned()
+            .unwrap_or(Lc::<C>::zero())
+    }
+
+    fn ext(&self, ext: Ext<C::F, C::EF>) -> [Lc<C>; 4] {
+        match self.state.exts.get(&ext.0) {
+            Some(value) => value.clone(),
+            None => self.ext_constant(C::EF::zero()),
+        }
+    }
+
+    fn set_var(&mut self, var: Var<C::N>, value: Lc<C>) {
+        self.state.vars.insert(var.0, value);
+    }
+
+    fn set_felt(&mut self, felt: Felt<C::F>, value: Lc<C>) {
+        self.state.felts.insert(felt.0, value);
+    }
+
+    fn set_ext(&mut self, ext: Ext<C::F, C::EF>, value: [Lc<C>; 4]) {
+        self.state.exts.insert(ext.0, value);
+    }
+
+    fn felt_constant(&self, value: C::F) -> Lc<C> {
+        Lc::<C>::constant(from_felt::<C>(value))
+    }
+
+    fn ext_constant(&self, value: C::EF) -> [Lc<C>; 4] {
+        let coordinates = value.as_base_slice();
+        core::array::from_fn(|i| self.felt_constant(coordinates[i]))
+    }
+
+    /// Embeds a felt into the extension field.
+    fn lift(&self, felt: Felt<C::F>) -> [Lc<C>; 4] {
+        [
+            self.felt(felt),
+            Lc::<C>::zero(),
+            Lc::<C>::zero(),
+            Lc::<C>::zero(),
+        ]
+    }
+
+    /// Returns the value of a constant index or bound.
+    fn constant_usize(&self, value: Usize<C::N>, what: &str) -> usize {
+        match value {
+            Usize::Const(value) => value,
+            Usize::Var(var) => match self.var(var).as_constant() {
+                Some(value) => to_u128(value) as usize,
+                None => panic!("{} must be constant in the R1CS backend", what),
+            },
+        }
+    }
+
+    fn address(&self, ptr: Ptr<C::N>, index: Usize<C::N>, size: usize) -> usize {
+        let base = self.constant_usize(Usize::Var(ptr.address), "pointers");
+        base + self.constant_usize(index, "memory indices") * size
+    }
+
+    fn new_variable(&mut self) -> usize {
+        self.r1cs.num_variables += 1;
+        self.r1cs.num_variables - 1
+    }
+
+    fn constrain(&mut self, a: Lc<C>, b: Lc<C>, c: Lc<C>) {
+        self.r1cs.constraints.push(Constraint { a, b, c });
+    }
+
+    fn hint(&mut self) -> usize {
+        let variable = self.new_variable();
+        self.steps.push(WitnessStep::Hint(variable));
+        variable
+    }
+
+    fn hint_felt(&mut self) -> Lc<C> {
+        let value = Lc::<C>::variable(self.hint());
+        self.canonical(value.clone());
+        value
+    }
+
+    /// Returns `a * b`, with a constraint unless one of them is constant.
+    fn product(&mut self, a: &Lc<C>, b: &Lc<C>) -> Lc<C> {
+        if let Some(a) = a.as_constant() {
+            return b.clone() * a;
+        }
+        if let Some(b) = b.as_constant() {
+            return a.clone() * b;
+        }
+        let variable = self.new_variable();
+        self.steps
+            .push(WitnessStep::Product(variable, a.clone(), b.clone()));
+        let c = Lc::<C>::variable(variable);
+        self.constrain(a.clone(), b.clone(), c.clone());
+        c
+    }
+
+    /// Decomposes a value of at most `num_bits` bits, and returns its bits.
+    fn bits(&mut self, value: Lc<C>, num_bits: usize) -> Vec<usize> {
+        let bits = (0..num_bits)
+            .map(|_| self.new_variable())
+            .collect::<Vec<_>>();
+        self.steps.push(WitnessStep::Bits {
+            input: value.clone(),
+            bits: bits.clone(),
+        });
+        let one = Lc::<C>::constant(C::N::one());
+        let mut sum = Lc::<C>::zero();
+        let mut power = C::N::one();
+        for bit in bits.iter() {
+            let bit = Lc::<C>::variable(*bit);
+            self.constrain(bit.clone(), bit.clone() - one.clone(), Lc::<C>::zero());
+            sum = sum + bit * power;
+            power = power.double();
+        }
+        self.constrain(sum - value, one, Lc::<C>::zero());
+        bits
+    }
+
+    /// Checks that a value is a canonical felt, and returns its bits.
+    fn canonical(&mut self, value: Lc<C>) -> Vec<usize> {
+        let bits = self.bits(value, FELT_BITS);
+
+        // The modulus minus one is `1111 0...0` in binary, with 27 zeros, so a value of 31 bits
+        // is smaller than the modulus unless its top four bits are set and some other bit is.
+        let modulus = C::F::ORDER_U32;
+        let low_bits = (modulus - 1).trailing_zeros() as usize;
+        assert_eq!(
+            (modulus - 1) >> low_bits,
+            (1 << (FELT_BITS - low_bits)) - 1,
+            "the R1CS backend only supports moduli of the form 2^31 - 2^k + 1"
+        );
+        let mut high = Lc::<C>::variable(bits[low_bits]);
+        for bit in bits[low_bits + 1..].iter() {
+            high = self.product(&high, &Lc::<C>::variable(*bit));
+        }
+        let mut low = Lc::<C>::zero();
+        let mut power = C::N::one();
+        for bit in bits[..low_bits].iter() {
+            low = low + Lc::<C>::variable(*bit) * power;
+            power = power.double();
+        }
+        self.constrain(high, low, Lc::<C>::zero());
+        bits
+    }
+
+    /// Reduces a value of at most `num_bits` bits modulo the emulated modulus.
+    fn reduce(&mut self, value: Lc<C>, num_bits: usize) -> Lc<C> {
+        let modulus = C::F::ORDER_U32 as u128;
+        if let Some(value) = value.as_constant() {
+            return Lc::<C>::constant(from_u128(to_u128(value) % modulus));
+        }
+
+        let quotient = self.new_variable();
+        let remainder = self.new_variable();
+        self.steps.push(WitnessStep::Reduce {
+            input: value.clone(),
+            quotient,
+            remainder,
+        });
+        let quotient = Lc::<C>::variable(quotient);
+        let remainder = Lc::<C>::variable(remainder);
+        self.bits(quotient.clone(), num_bits + 1 - FELT_BITS);
+        self.canonical(remainder.clone());
+        let modulus = C::N::from_canonical_u32(C::F::ORDER_U32);
+        self.constrain(
+            quotient * modulus + remainder.clone() - value,
+            Lc::<C>::constant(C::N::one()),
+            Lc::<C>::zero(),
+        );
+        remainder
+    }
+
+    fn felt_add(&mut self, a: Lc<C>, b: Lc<C>) -> Lc<C> {
+        self.reduce(a + b, FELT_BITS + 1)
+    }
+
+    fn felt_sub(&mut self, a: Lc<C>, b: Lc<C>) -> Lc<C> {
+        let modulus = Lc::<C>::constant(C::N::from_canonical_u32(C::F::ORDER_U32));
+        self.reduce(a - b + modulus, FELT_BITS + 1)
+    }
+
+    fn felt_mul(&mut self, a: Lc<C>, b: Lc<C>) -> Lc<C> {
+        let product = self.product(&a, &b);
+        self.reduce(product, 2 * FELT_BITS)
+    }
+
+    fn felt_inverse(&mut self, a: Lc<C>) -> Lc<C> {
+        if let Some(value) = a.as_constant() {
+            if value.is_zero() {
+                self.fail();
+                return Lc::<C>::zero();
+            }
+            let inverse = super::witness::to_felt::<C>(value).inverse();
+            return self.felt_constant(inverse);
+        }
+        let inverse = self.new_variable();
+        self.steps
+            .push(WitnessStep::FeltInverse(inverse, a.clone()));
+        let inverse = Lc::<C>::variable(inverse);
+        self.canonical(inverse.clone());
+        let product = self.felt_mul(a, inverse.clone());
+        self.assert_zero(product - Lc::<C>::constant(C::N::one()));
+        inverse
+    }
+
+    fn felt_div(&mut self, a: Lc<C>, b: Lc<C>) -> Lc<C> {
+        let inverse = self.felt_inverse(b);
+        self.felt_mul(a, inverse)
+    }
+
+    fn felt_op(
+        &mut self,
+        dst: Felt<C::F>,
+        lhs: Felt<C::F>,
+        rhs: Felt<C::F>,
+        f: fn(&mut Self, Lc<C>, Lc<C>) -> Lc<C>,
+    ) {
+        let value = f(self, self.felt(lhs), self.felt(rhs));
+        self.set_felt(dst, value);
+    }
+
+    fn felt_op_imm(
+        &mut self,
+        dst: Felt<C::F>,
+        lhs: Felt<C::F>,
+        rhs: C::F,
+        f: fn(&mut Self, Lc<C>, Lc<C>) -> Lc<C>,
+    ) {
+        let value = f(self, self.felt(lhs), self.felt_constant(rhs));
+        self.set_felt(dst, value);
+    }
+
+    fn ext_add(&mut self, a: [Lc<C>; 4], b: [Lc<C>; 4]) -> [Lc<C>; 4] {
+        let [a0, a1, a2, a3] = a;
+        let [b0, b1, b2, b3] = b;
+        [
+            self.felt_add(a0, b0),
+            self.felt_add(a1, b1),
+            self.felt_add(a2, b2),
+            self.felt_add(a3, b3),
+        ]
+    }
+
+    fn ext_sub(&mut self, a: [Lc<C>; 4], b: [Lc<C>; 4]) -> [Lc<C>; 4] {
+        let [a0, a1, a2, a3] = a;
+        let [b0, b1, b2, b3] = b;
+        [
+            self.felt_sub(a0, b0),
+            self.felt_sub(a1, b1),
+            self.felt_sub(a2, b2),
+            self.felt_sub(a3, b3),
+        ]
+    }
+
+    fn ext_mul(&mut self, a: [Lc<C>; 4], b: [Lc<C>; 4]) -> [Lc<C>; 4] {
+        // The extension is `F[X] / (X^4 - W)`.
+        let x = C::EF::monomial(1);
+        let w = from_felt::<C>(x.exp_u64(4).as_base_slice()[0]);
+
+        let mut sums: [Lc<C>; 4] = core::array::from_fn(|_| Lc::<C>::zero());
+        for (i, a) in a.iter().enumerate() {
+            for (j, b) in b.iter().enumerate() {
+                let product = self.product(a, b);
+                if i + j < 4 {
+                    sums[i + j] = sums[i + j].clone() + product;
+                } else {
+                    sums[i + j - 4] = sums[i + j - 4].clone() + product * w;
+                }
+            }
+        }
+        // Each sum is smaller than `(1 + 3W) p^2`, which fits in 2 * 31 + 7 bits for `W < 42`.
+        assert!(
+            to_u128(w) < 42,
+            "the R1CS backend only supports small extension constants"
+        );
+        sums.map(|sum| self.reduce(sum, 2 * FELT_BITS + 7))
+    }
+
+    fn ext_inverse(&mut self, a: [Lc<C>; 4]) -> [Lc<C>; 4] {
+        if a.iter().all(|lc| lc.as_constant().is_some()) {
+            let coordinates = a
+                .iter()
+                .map(|lc| super::witness::to_felt::<C>(lc.as_constant().unwrap()))
+                .collect::<Vec<_>>();
+            return match C::EF::from_base_slice(&coordinates).try_inverse() {
+                Some(inverse) => self.ext_constant(inverse),
+                None => {
+                    self.fail();
+                    self.ext_constant(C::EF::zero())
+                }
+            };
+        }
+        let inverse: [usize; 4] = core::array::from_fn(|_| self.new_variable());
+        self.steps.push(WitnessStep::ExtInverse(inverse, a.clone()));
+        let inverse = inverse.map(Lc::<C>::variable);
+        for coordinate in inverse.iter() {
+            self.canonical(coordinate.clone());
+        }
+        let product = self.ext_mul(a, inverse.clone());
+        let one = self.ext_constant(C::EF::one());
+        self.assert_ext_eq(product, one);
+        inverse
+    }
+
+    fn ext_div(&mut self, a: [Lc<C>; 4], b: [Lc<C>; 4]) -> [Lc<C>; 4] {
+        let inverse = self.ext_inverse(b);
+        self.ext_mul(a, inverse)
+    }
+
+    fn ext_op(
+        &mut self,
+        dst: Ext<C::F, C::EF>,
+        lhs: [Lc<C>; 4],
+        rhs: [Lc<C>; 4],
+        f: fn(&mut Self, [Lc<C>; 4], [Lc<C>; 4]) -> [Lc<C>; 4],
+    ) {
+        let value = f(self, lhs, rhs);
+        self.set_ext(dst, value);
+    }
+
+    fn var_inverse(&mut self, a: Lc<C>) -> Lc<C> {
+        if let Some(value) = a.as_constant() {
+            return match value.try_inverse() {
+                Some(inverse) => Lc::<C>::constant(inverse),
+                None => {
+                    self.fail();
+                    Lc::<C>::zero()
+                }
+            };
+        }
+        let inverse = self.new_variable();
+        self.steps.push(WitnessStep::Inverse(inverse, a.clone()));
+        // The inverse is zeroed in blocks which do not run, where the value may be nonzero.
+        let inverse = self.product(&self.condition.clone(), &Lc::<C>::variable(inverse));
+        self.constrain(a, inverse.clone(), self.condition.clone());
+        inverse
+    }
+
+    /// Makes the circuit unsatisfiable if the current block runs.
+    fn fail(&mut self) {
+        self.constrain(
+            self.condition.clone(),
+            Lc::<C>::constant(C::N::one()),
+            Lc::<C>::zero(),
+        );
+    }
+
+    /// Checks that a value is zero if the current block runs.
+    fn assert_zero(&mut self, value: Lc<C>) {
+        match value.as_constant() {
+            Some(value) if value.is_zero() => {}
+            Some(_) => self.fail(),
+            None => self.constrain(self.condition.clone(), value, Lc::<C>::zero()),
+        }
+    }
+
+    /// Checks that a value is nonzero if the current block runs.
+    fn assert_nonzero(&mut self, value: Lc<C>) {
+        match value.as_constant() {
+            Some(value) if value.is_zero() => self.fail(),
+            Some(_) => {}
+            None => {
+                self.var_inverse(value);
+            }
+        }
+    }
+
+    fn assert_ext_eq(&mut self, a: [Lc<C>; 4], b: [Lc<C>; 4]) {
+        for (a, b) in a.into_iter().zip(b) {
+            self.assert_zero(a - b);
+        }
+    }
+
+    fn assert_ext_ne(&mut self, a: [Lc<C>; 4], b: [Lc<C>; 4]) {
+        // The differences of the coordinates are smaller than 2^31 in absolute value, so they are
+        // all zero if and only if the sum of `diff_i * 2^(32 i)` is, which is smaller than the
+        // modulus of `C::N`.
+        let shift = C::N::from_canonical_u64(1 << 32);
+        let mut sum = Lc::<C>::zero();
+        let mut power = C::N::one();
+        for (a, b) in a.into_iter().zip(b) {
+            sum = sum + (a - b) * power;
+            power *= shift;
+        }
+        self.assert_nonzero(sum);
+    }
+
+    /// Returns a variable which is one if a value is zero, and zero otherwise.
+    fn is_zero(&mut self, value: Lc<C>) -> Lc<C> {
+        let inverse = self.new_variable();
+        self.steps
+            .push(WitnessStep::Inverse(inverse, value.clone()));
+        let inverse = Lc::<C>::variable(inverse);
+        let one = Lc::<C>::constant(C::N::one());
+        // `value * inverse` is one if the value is nonzero, and it must be zero otherwise.
+        let nonzero = self.product(&value, &inverse);
+        self.constrain(value, one.clone() - nonzero.clone(), Lc::<C>::zero());
+        one - nonzero
+    }
+
+    /// Lowers a conditional whose `then` branch runs if `diff` is zero.
+    fn branch(
+        &mut self,
+        label: &str,
+        diff: Lc<C>,
+        then: Vec<DslIR<C>>,
+        else_: Vec<DslIR<C>>,
+    ) -> Result<(), R1CSError> {
+        if let Some(diff) = diff.as_constant() {
+            return self.run(if diff.is_zero() { then } else { else_ });
+        }
+
+        let before = self.r1cs.num_constraints();
+        let selector = self.is_zero(diff);
+        let parent = self.condition.clone();
+        let then_condition = self.product(&parent, &selector);
+        self.count(label.to_string(), before);
+
+        let initial = self.state.clone();
+        self.condition = then_condition.clone();
+        self.run(then)?;
+        let then_state = core::mem::replace(&mut self.state, initial);
+        self.condition = parent.clone() - then_condition;
+        self.run(else_)?;
+        let else_state = core::mem::take(&mut self.state);
+        self.condition = parent;
+
+        let before = self.r1cs.num_constraints();
+        self.state = self.merge(&selector, then_state, else_state);
+        self.count(label.to_string(), before);
+        Ok(())
+    }
+
+    /// Merges the states of both branches of a conditional, selecting the values of the `then`
+    /// branch where the selector is one.
+    fn merge(&mut self, selector: &Lc<C>, then: State<C::N>, else_: State<C::N>) -> State<C::N> {
+        let mut select = |then: Lc<C>, else_: Lc<C>| -> Lc<C> {
+            if then == else_ {
+                return then;
+            }
+            let diff = self.product(selector, &(then - else_.clone()));
+            else_ + diff
+        };
+
+        let mut merged = State {
+            heap: then.heap.max(else_.heap),
+            ..State::default()
+        };
+        let mut vars = then.vars;
+        for (id, value) in else_.vars {
+            let value = match vars.remove(&id) {
+                Some(then) => select(then, value),
+                None => value,
+            };
+            merged.vars.insert(id, value);
+        }
+        merged.vars.extend(vars);
+
+        let mut felts = then.felts;
+        for (id, value) in else_.felts {
+            let value = match felts.remove(&id) {
+                Some(then) => select(then, value),
+                None => value,
+            };
+            merged.felts.insert(id, value);
+        }
+        merged.felts.extend(felts);
+
+        let mut exts = then.exts;
+        for (id, value) in else_.exts {
+            let value = match exts.remove(&id) {
+                Some(then) => {
+                    let mut then = then.into_iter();
+                    value.map(|value| select(then.next().unwrap(), value))
+                }
+                None => value,
+            };
+            merged.exts.insert(id, value);
+        }
+        merged.exts.extend(exts);
+
+        let mut memory = then.memory;
+        for (address, value) in else_.memory {
+            let value = match (memory.remove(&address), value) {
+                (None, value) => value,
+                (Some(Value::Var(then)), Value::Var(value)) => Value::Var(select(then, value)),
+                (Some(Value::Felt(then)), Value::Felt(value)) => Value::Felt(select(then, value)),
+                (Some(Value::Ext(then)), Value::Ext(value)) => {
+                    let mut then = then.into_iter();
+                    Value::Ext(value.map(|value| select(then.next().unwrap(), value)))
+                }
+                _ => panic!("memory cell with different types in the branches of a conditional"),
+            };
+            merged.memory.insert(address, value);
+        }
+        merged.memory.extend(memory);
+
+        merged
+    }
+
+    /// Lowers a call by inlining the body of the function in a new frame.
+    fn call(
+        &mut self,
+        name: &str,
+        arguments: Vec<FunctionVariable<C>>,
+        results: Vec<FunctionVariable<C>>,
+    ) -> Result<(), R1CSError> {
+        let (parameters, returns, body) = self
+            .functions
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| panic!("call of the undefined function {}", name));
+
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.get(argument))
+            .collect::<Vec<_>>();
+        let caller = State {
+            memory: HashMap::new(),
+            heap: 0,
+            vars: core::mem::take(&mut self.state.vars),
+            felts: core::mem::take(&mut self.state.felts),
+            exts: core::mem::take(&mut self.state.exts),
+        };
+        for (parameter, argument) in parameters.iter().zip(arguments) {
+            self.set(parameter, argument);
+        }
+        self.run(body.operations)?;
+        let values = returns
+            .iter()
+            .map(|result| self.get(result))
+            .collect::<Vec<_>>();
+
+        self.state.vars = caller.vars;
+        self.state.felts = caller.felts;
+        self.state.exts = caller.exts;
+        for (result, value) in results.iter().zip(values) {
+            self.set(result, value);
+        }
+        Ok(())
+    }
+
+    fn get(&self, variable: &FunctionVariable<C>) -> Value<C::N> {
+        match variable {
+            FunctionVariable::Var(var) => Value::Var(self.var(*var)),
+            FunctionVariable::Felt(felt) => Value::Felt(self.felt(*felt)),
+            FunctionVariable::Ext(ext) => Value::Ext(self.ext(*ext)),
+        }
+    }
+
+    fn set(&mut self, variable: &FunctionVariable<C>, value: Value<C::N>) {
+        match (variable, value) {
+            (FunctionVariable::Var(var), Value::Var(value)) => self.set_var(*var, value),
+            (FunctionVariable::Felt(felt), Value::Felt(value)) => self.set_felt(*felt, value),
+            (FunctionVariable::Ext(ext), Value::Ext(value)) => self.set_ext(*ext, value),
+            _ => panic!("function variable of the wrong type"),
+        }
+    }
+}
+
+/// The name of an instruction, e.g. `AddF`, under which its constraints are counted.
+fn label<C: Config>(op: &DslIR<C>) -> String {
+    let debug = format!("{:?}", op);
+    debug.split('(').next().unwrap_or_default().to_string()
+}
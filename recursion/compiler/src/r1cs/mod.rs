@@ -0,0 +1,362 @@
+//! A backend lowering programs over a large prime field, such as the scalar field of BN254, to a
+//! rank-1 constraint system, along with the generator of its witness.
+//!
+//! Field elements and extension field elements are emulated: every felt is held by one variable
+//! of the constraint system, which is kept reduced with range checks.
+mod backend;
+mod witness;
+
+pub use backend::*;
+pub use witness::*;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Add, Mul, Neg, Sub};
+
+use p3_baby_bear::BabyBear;
+use p3_bn254_fr::Bn254Fr;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeField};
+
+use crate::ir::Config;
+
+/// The configuration of the outer circuit: native variables over the scalar field of BN254, and
+/// emulated BabyBear elements.
+#[derive(Debug, Clone)]
+pub struct OuterConfig;
+
+impl Config for OuterConfig {
+    type N = Bn254Fr;
+    type F = BabyBear;
+    type EF = BinomialExtensionField<BabyBear, 4>;
+}
+
+/// A linear combination of the variables of a constraint system, where the variable `0` is the
+/// constant one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinearCombination<N>(pub BTreeMap<usize, N>);
+
+impl<N: Field> LinearCombination<N> {
+    pub fn zero() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    pub fn constant(value: N) -> Self {
+        Self::term(0, value)
+    }
+
+    pub fn variable(index: usize) -> Self {
+        Self::term(index, N::one())
+    }
+
+    fn term(index: usize, coefficient: N) -> Self {
+        let mut terms = BTreeMap::new();
+        if !coefficient.is_zero() {
+            terms.insert(index, coefficient);
+        }
+        Self(terms)
+    }
+
+    /// Returns the value of the combination if it does not depend on any variable.
+    pub fn as_constant(&self) -> Option<N> {
+        match self.0.iter().next() {
+            None => Some(N::zero()),
+            Some((0, value)) if self.0.len() == 1 => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn evaluate(&self, witness: &[N]) -> N {
+        self.0
+            .iter()
+            .map(|(index, coefficient)| witness[*index] * *coefficient)
+            .sum()
+    }
+}
+
+impl<N: Field> Add for LinearCombination<N> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        for (index, coefficient) in rhs.0 {
+            let sum = self.0.get(&index).copied().unwrap_or(N::zero()) + coefficient;
+            if sum.is_zero() {
+                self.0.remove(&index);
+            } else {
+                self.0.insert(index, sum);
+            }
+        }
+        self
+    }
+}
+
+impl<N: Field> Neg for LinearCombination<N> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self * -N::one()
+    }
+}
+
+impl<N: Field> Sub for LinearCombination<N> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<N: Field> Mul<N> for LinearCombination<N> {
+    type Output = Self;
+
+    fn mul(self, rhs: N) -> Self {
+        if rhs.is_zero() {
+            return Self::zero();
+        }
+        Self(
+            self.0
+                .into_iter()
+                .map(|(index, coefficient)| (index, coefficient * rhs))
+                .collect(),
+        )
+    }
+}
+
+/// The constraint `a * b = c`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint<N> {
+    pub a: LinearCombination<N>,
+    pub b: LinearCombination<N>,
+    pub c: LinearCombination<N>,
+}
+
+/// A rank-1 constraint system, whose variable `0` is the constant one.
+#[derive(Debug, Clone)]
+pub struct R1CS<N> {
+    pub num_variables: usize,
+    pub constraints: Vec<Constraint<N>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum R1CSError {
+    /// The witness does not have one value per variable, or does not start with one.
+    InvalidWitness,
+    /// The constraint with this index is not satisfied.
+    Unsatisfied(usize),
+    /// The program read more hints than were supplied.
+    MissingHint,
+    /// The program permutes with Poseidon2, which has no constraints in the R1CS backend.
+    Poseidon2Permute,
+    /// The program commits a public value, which the circuit has no public inputs for.
+    CommitPublicValue,
+}
+
+impl fmt::Display for R1CSError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            R1CSError::InvalidWitness => write!(f, "invalid witness"),
+            R1CSError::Unsatisfied(index) => write!(f, "constraint {} is not satisfied", index),
+            R1CSError::MissingHint => write!(f, "missing hint"),
+            R1CSError::Poseidon2Permute => {
+                write!(
+                    f,
+                    "Poseidon2 permutations are not supported by the R1CS backend"
+                )
+            }
+            R1CSError::CommitPublicValue => {
+                write!(f, "public values are not supported by the R1CS backend")
+            }
+        }
+    }
+}
+
+impl<N: Field> Default for R1CS<N> {
+    fn default() -> Self {
+        Self {
+            num_variables: 1,
+            constraints: Vec::new(),
+        }
+    }
+}
+
+impl<N: PrimeField> R1CS<N> {
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Checks that a witness satisfies all the constraints.
+    pub fn is_satisfied(&self, witness: &[N]) -> Result<(), R1CSError> {
+        if witness.len() != self.num_variables || witness[0] != N::one() {
+            return Err(R1CSError::InvalidWitness);
+        }
+        for (index, constraint) in self.constraints.iter().enumerate() {
+            let a = constraint.a.evaluate(witness);
+            let b = constraint.b.evaluate(witness);
+            if a * b != constraint.c.evaluate(witness) {
+                return Err(R1CSError::Unsatisfied(index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Exports the constraint system as JSON, with the coefficients as decimal strings:
+    /// `{"num_variables": n, "constraints": [[a, b, c], ...]}`, where each linear combination is a
+    /// list of `[variable, coefficient]` terms. This is the format read by `r1cs.Import` in the
+    /// gnark library.
+    pub fn to_json(&self) -> String {
+        let constraints = self
+            .constraints
+            .iter()
+            .map(|constraint| {
+                format!(
+                    "[{},{},{}]",
+                    lc_to_json(&constraint.a),
+                    lc_to_json(&constraint.b),
+                    lc_to_json(&constraint.c)
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"num_variables\":{},\"constraints\":[{}]}}",
+            self.num_variables,
+            constraints.join(",")
+        )
+    }
+}
+
+fn lc_to_json<N: PrimeField>(lc: &LinearCombination<N>) -> String {
+    let terms =
+        lc.0.iter()
+            .map(|(index, coefficient)| {
+                format!("[{},\"{}\"]", index, coefficient.as_canonical_biguint())
+            })
+            .collect::<Vec<_>>();
+    format!("[{}]", terms.join(","))
+}
+
+/// Exports a witness as a JSON list of decimal strings, as read by `r1cs.Import`.
+pub fn witness_to_json<N: PrimeField>(witness: &[N]) -> String {
+    let values = witness
+        .iter()
+        .map(|value| format!("\"{}\"", value.as_canonical_biguint()))
+        .collect::<Vec<_>>();
+    format!("[{}]", values.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_field::{AbstractExtensionField, AbstractField};
+    use sp1_recursion_core::poseidon2::WIDTH;
+
+    use super::*;
+    use crate::ir::{Builder, Ext, Felt, Slice, Var};
+
+    type F = <OuterConfig as Config>::F;
+    type EF = <OuterConfig as Config>::EF;
+    type N = <OuterConfig as Config>::N;
+
+    fn ext_value() -> EF {
+        EF::from_base_slice(&[1, 2, 3, 4].map(F::from_canonical_u32))
+    }
+
+    /// Builds a program reading a felt, an ext and a var, which only succeeds if the var is one.
+    fn program() -> Builder<OuterConfig> {
+        let mut builder = Builder::<OuterConfig>::default();
+        let x: Felt<_> = builder.hint();
+        let y: Ext<_, _> = builder.hint();
+        let c: Var<_> = builder.hint();
+
+        let z: Felt<_> = builder.eval(x * x + F::one());
+        let w: Ext<_, _> = builder.eval(y * y + y);
+        let y_value = ext_value();
+        builder.assert_ext_eq(w, y_value * y_value + y_value);
+        let inverse: Ext<_, _> = builder.eval(EF::one() / y);
+        builder.assert_ext_eq(inverse * y, EF::one());
+        let inverse: Felt<_> = builder.eval(F::one() / x);
+        builder.assert_felt_eq(inverse * x, F::one());
+
+        let r: Felt<_> = builder.eval(F::zero());
+        builder.if_eq(c, N::one()).then_or_else(
+            |builder| builder.assign(r, z),
+            |builder| builder.assign(r, x),
+        );
+        builder.assert_felt_eq(r, F::from_canonical_u32(26));
+
+        let acc: Felt<_> = builder.eval(F::zero());
+        builder.range(0, 4).for_each(|_, builder| {
+            builder.assign(acc, acc + x);
+        });
+        builder.assert_felt_eq(acc, F::from_canonical_u32(20));
+
+        let mut slice = Slice::Vec(builder.vec(2));
+        builder.set(&mut slice, 1, z);
+        let loaded: Felt<_> = builder.get(&slice, 1);
+        builder.assert_felt_eq(loaded, z);
+
+        let bits = builder.num2bits_f(x);
+        let recomposed = builder.bits2num_f(&bits);
+        builder.assert_felt_eq(recomposed, x);
+        builder
+    }
+
+    fn hints(c: u32) -> Vec<N> {
+        [5, 1, 2, 3, 4, c].map(N::from_canonical_u32).to_vec()
+    }
+
+    #[test]
+    fn test_witness_satisfies_constraints() {
+        let circuit = R1CSBackend::default()
+            .compile(program().operations)
+            .unwrap();
+        let witness = circuit.witness(&hints(1)).unwrap();
+        assert_eq!(circuit.r1cs.is_satisfied(&witness), Ok(()));
+    }
+
+    #[test]
+    fn test_wrong_hint_is_unsatisfied() {
+        let circuit = R1CSBackend::default()
+            .compile(program().operations)
+            .unwrap();
+        let witness = circuit.witness(&hints(0)).unwrap();
+        assert!(matches!(
+            circuit.r1cs.is_satisfied(&witness),
+            Err(R1CSError::Unsatisfied(_))
+        ));
+        assert_eq!(circuit.witness(&hints(1)[..5]), Err(R1CSError::MissingHint));
+    }
+
+    #[test]
+    fn test_constraint_counts() {
+        let circuit = R1CSBackend::default()
+            .compile(program().operations)
+            .unwrap();
+        let total: usize = circuit.constraint_counts.values().sum();
+        assert_eq!(total, circuit.num_constraints());
+        assert!(circuit.constraint_counts["MulE"] > circuit.constraint_counts["MulF"]);
+
+        let json = circuit.r1cs.to_json();
+        assert!(json.starts_with(&format!(
+            "{{\"num_variables\":{},",
+            circuit.r1cs.num_variables
+        )));
+    }
+
+    #[test]
+    fn test_poseidon2_is_unsupported() {
+        let mut builder = Builder::<OuterConfig>::default();
+        let state = (0..WIDTH).map(|_| builder.hint()).collect::<Vec<Felt<_>>>();
+        let c: Var<_> = builder.hint();
+        builder.if_eq(c, N::one()).then(|builder| {
+            let output = builder.poseidon2_permute(&Slice::Fixed(state));
+            let first: Felt<_> = builder.get(&output, 0);
+            builder.assert_felt_ne(first, F::zero());
+        });
+
+        assert!(matches!(
+            R1CSBackend::default().compile(builder.operations),
+            Err(R1CSError::Poseidon2Permute)
+        ));
+    }
+}
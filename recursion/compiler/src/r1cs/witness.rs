@@ -0,0 +1,142 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use p3_field::{AbstractExtensionField, AbstractField, Field, PrimeField, PrimeField32};
+
+use super::{LinearCombination, R1CSError, R1CS};
+use crate::ir::Config;
+
+/// A step of the generation of the witness, which computes the values of some variables from
+/// the values of the previous ones.
+#[derive(Debug, Clone)]
+pub enum WitnessStep<N> {
+    /// Reads the next hint.
+    Hint(usize),
+    /// Computes the product of two linear combinations.
+    Product(usize, LinearCombination<N>, LinearCombination<N>),
+    /// Computes the inverse of a linear combination, or zero if it is zero.
+    Inverse(usize, LinearCombination<N>),
+    /// Computes the quotient and the remainder of the division of an integer by the modulus of
+    /// the emulated field.
+    Reduce {
+        input: LinearCombination<N>,
+        quotient: usize,
+        remainder: usize,
+    },
+    /// Decomposes an integer into little-endian bits.
+    Bits {
+        input: LinearCombination<N>,
+        bits: Vec<usize>,
+    },
+    /// Computes the inverse of an emulated field element, or zero if it is zero.
+    FeltInverse(usize, LinearCombination<N>),
+    /// Computes the inverse of an emulated extension field element, or zero if it is zero.
+    ExtInverse([usize; 4], [LinearCombination<N>; 4]),
+}
+
+/// A program lowered to a rank-1 constraint system, along with the steps to generate its witness.
+#[derive(Debug, Clone)]
+pub struct Circuit<C: Config> {
+    pub r1cs: R1CS<C::N>,
+    pub steps: Vec<WitnessStep<C::N>>,
+    /// The number of constraints emitted for each kind of instruction.
+    pub constraint_counts: BTreeMap<String, usize>,
+}
+
+impl<C: Config> Circuit<C>
+where
+    C::N: PrimeField,
+    C::F: PrimeField32,
+{
+    pub fn num_constraints(&self) -> usize {
+        self.r1cs.num_constraints()
+    }
+
+    /// Generates the witness of the circuit from the hints read by the program, in order. Felts
+    /// are given by their canonical value and extension elements by their four coordinates.
+    pub fn witness(&self, hints: &[C::N]) -> Result<Vec<C::N>, R1CSError> {
+        let mut witness = vec![C::N::zero(); self.r1cs.num_variables];
+        witness[0] = C::N::one();
+        let mut hints = hints.iter();
+        let modulus = C::F::ORDER_U32 as u128;
+
+        for step in self.steps.iter() {
+            match step {
+                WitnessStep::Hint(variable) => {
+                    witness[*variable] = *hints.next().ok_or(R1CSError::MissingHint)?;
+                }
+                WitnessStep::Product(variable, a, b) => {
+                    witness[*variable] = a.evaluate(&witness) * b.evaluate(&witness);
+                }
+                WitnessStep::Inverse(variable, input) => {
+                    let input = input.evaluate(&witness);
+                    witness[*variable] = input.try_inverse().unwrap_or(C::N::zero());
+                }
+                WitnessStep::Reduce {
+                    input,
+                    quotient,
+                    remainder,
+                } => {
+                    let input = to_u128(input.evaluate(&witness));
+                    witness[*quotient] = from_u128(input / modulus);
+                    witness[*remainder] = from_u128(input % modulus);
+                }
+                WitnessStep::Bits { input, bits } => {
+                    let input = to_u128(input.evaluate(&witness));
+                    for (i, bit) in bits.iter().enumerate() {
+                        witness[*bit] = C::N::from_bool((input >> i) & 1 == 1);
+                    }
+                }
+                WitnessStep::FeltInverse(variable, input) => {
+                    let input = to_felt::<C>(input.evaluate(&witness));
+                    let inverse = input.try_inverse().unwrap_or(C::F::zero());
+                    witness[*variable] = from_felt::<C>(inverse);
+                }
+                WitnessStep::ExtInverse(variables, input) => {
+                    let input = input
+                        .iter()
+                        .map(|lc| to_felt::<C>(lc.evaluate(&witness)))
+                        .collect::<Vec<_>>();
+                    let inverse = C::EF::from_base_slice(&input)
+                        .try_inverse()
+                        .unwrap_or(C::EF::zero());
+                    for (variable, value) in variables.iter().zip(inverse.as_base_slice()) {
+                        witness[*variable] = from_felt::<C>(*value);
+                    }
+                }
+            }
+        }
+        Ok(witness)
+    }
+}
+
+/// Returns the low 128 bits of the canonical value of a field element.
+pub(crate) fn to_u128<N: PrimeField>(value: N) -> u128 {
+    let digits = value.as_canonical_biguint().to_u64_digits();
+    let low = digits.first().copied().unwrap_or(0) as u128;
+    let high = digits.get(1).copied().unwrap_or(0) as u128;
+    low | (high << 64)
+}
+
+pub(crate) fn from_u128<N: PrimeField>(value: u128) -> N {
+    let shift = N::from_canonical_u64(1 << 32).square();
+    N::from_canonical_u64((value >> 64) as u64) * shift + N::from_canonical_u64(value as u64)
+}
+
+/// Returns the emulated field element held by a variable, whose value is assumed to be canonical.
+pub(crate) fn to_felt<C: Config>(value: C::N) -> C::F
+where
+    C::N: PrimeField,
+    C::F: PrimeField32,
+{
+    C::F::from_wrapped_u64(to_u128(value) as u64)
+}
+
+pub(crate) fn from_felt<C: Config>(value: C::F) -> C::N
+where
+    C::N: PrimeField,
+    C::F: PrimeField32,
+{
+    C::N::from_canonical_u32(value.as_canonical_u32())
+}
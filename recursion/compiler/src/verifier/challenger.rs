@@ -0,0 +1,120 @@
+use p3_field::{AbstractExtensionField, AbstractField};
+use sp1_recursion_core::poseidon2::WIDTH;
+
+use super::DigestVariable;
+use crate::ir::{Builder, Config, Ext, Felt, Slice, SymbolicExt};
+
+/// The `DuplexChallenger<BabyBear, Perm, 16>` of `BabyBearPoseidon2`, in the DSL.
+///
+/// The sequence of observations and samples of a verifier does not depend on the proof, so the
+/// buffers are tracked at compile time and only the sponge state lives in the program.
+#[derive(Debug, Clone)]
+pub struct DuplexChallengerVariable<C: Config> {
+    sponge_state: [Felt<C::F>; WIDTH],
+    input_buffer: Vec<Felt<C::F>>,
+    output_buffer: Vec<Felt<C::F>>,
+}
+
+impl<C: Config> DuplexChallengerVariable<C> {
+    pub fn new(builder: &mut Builder<C>) -> Self {
+        Self {
+            sponge_state: core::array::from_fn(|_| builder.eval(C::F::zero())),
+            input_buffer: Vec::new(),
+            output_buffer: Vec::new(),
+        }
+    }
+
+    /// The challenger right after a duplexing which produced `sponge_state`, so that it samples
+    /// from `sponge_state` before any other permutation.
+    pub fn from_sponge_state(sponge_state: [Felt<C::F>; WIDTH]) -> Self {
+        Self {
+            sponge_state,
+            input_buffer: Vec::new(),
+            output_buffer: sponge_state.to_vec(),
+        }
+    }
+
+    fn duplexing(&mut self, builder: &mut Builder<C>) {
+        assert!(self.input_buffer.len() <= WIDTH);
+        for (i, value) in self.input_buffer.drain(..).enumerate() {
+            self.sponge_state[i] = value;
+        }
+        self.sponge_state = permute(builder, self.sponge_state);
+
+        self.output_buffer.clear();
+        self.output_buffer.extend(self.sponge_state);
+    }
+
+    pub fn observe(&mut self, builder: &mut Builder<C>, value: Felt<C::F>) {
+        // Any buffered output is now invalid.
+        self.output_buffer.clear();
+
+        self.input_buffer.push(value);
+        if self.input_buffer.len() == WIDTH {
+            self.duplexing(builder);
+        }
+    }
+
+    pub fn observe_slice(&mut self, builder: &mut Builder<C>, values: &[Felt<C::F>]) {
+        for value in values {
+            self.observe(builder, *value);
+        }
+    }
+
+    pub fn observe_commitment(&mut self, builder: &mut Builder<C>, commitment: &DigestVariable<C>) {
+        self.observe_slice(builder, commitment);
+    }
+
+    pub fn sample(&mut self, builder: &mut Builder<C>) -> Felt<C::F> {
+        // If we have buffered inputs, we must perform a duplexing so that the challenge will
+        // reflect them. Or if we've run out of outputs, we must perform a duplexing to get more.
+        if !self.input_buffer.is_empty() || self.output_buffer.is_empty() {
+            self.duplexing(builder);
+        }
+        self.output_buffer
+            .pop()
+            .expect("Output buffer should be non-empty")
+    }
+
+    pub fn sample_ext(&mut self, builder: &mut Builder<C>) -> Ext<C::F, C::EF> {
+        let mut sum: SymbolicExt<C::F, C::EF> = SymbolicExt::Const(C::EF::zero());
+        for i in 0..C::EF::D {
+            let coefficient = self.sample(builder);
+            let mut monomial = vec![C::F::zero(); C::EF::D];
+            monomial[i] = C::F::one();
+            sum = sum + coefficient * SymbolicExt::Const(C::EF::from_base_slice(&monomial));
+        }
+        builder.eval(sum)
+    }
+
+    /// Samples a felt, returning its low `num_bits` bits.
+    pub fn sample_bits(&mut self, builder: &mut Builder<C>, num_bits: usize) -> Vec<Felt<C::F>> {
+        let sample = self.sample(builder);
+        let mut bits = builder.num2bits_f(sample);
+        bits.truncate(num_bits);
+        bits
+    }
+
+    /// Observes the proof-of-work witness and asserts that the low `num_bits` bits of the next
+    /// sample are zero.
+    pub fn check_witness(
+        &mut self,
+        builder: &mut Builder<C>,
+        num_bits: usize,
+        witness: Felt<C::F>,
+    ) {
+        self.observe(builder, witness);
+        for bit in self.sample_bits(builder, num_bits) {
+            builder.assert_felt_eq(bit, C::F::zero());
+        }
+    }
+}
+
+/// Applies the Poseidon2 permutation to `state`.
+pub fn permute<C: Config>(
+    builder: &mut Builder<C>,
+    state: [Felt<C::F>; WIDTH],
+) -> [Felt<C::F>; WIDTH] {
+    let output = builder.poseidon2_permute(&Slice::Fixed(state.to_vec()));
+    core::array::from_fn(|i| builder.get(&output, i))
+}
@@ -0,0 +1,157 @@
+use p3_field::{AbstractExtensionField, AbstractField};
+use p3_uni_stark::SymbolicExpression;
+use sp1_core::air::AirInteraction;
+use sp1_core::stark::{AirOpenedValues, SymbolicConstraints};
+
+use crate::ir::{Builder, Config, Ext, SymbolicExt};
+
+/// The selectors of a trace domain evaluated at the out-of-domain point.
+#[derive(Debug, Clone, Copy)]
+pub struct SelectorsVariable<C: Config> {
+    pub is_first_row: Ext<C::F, C::EF>,
+    pub is_last_row: Ext<C::F, C::EF>,
+    pub is_transition: Ext<C::F, C::EF>,
+}
+
+/// Evaluates a recorded symbolic expression at the opened values of the traces, given as the main
+/// columns followed by the preprocessed columns, as they are numbered by the recording builders.
+pub fn eval_symbolic<C: Config>(
+    expression: &SymbolicExpression<C::F>,
+    local: &[Ext<C::F, C::EF>],
+    next: &[Ext<C::F, C::EF>],
+    selectors: &SelectorsVariable<C>,
+) -> SymbolicExt<C::F, C::EF> {
+    match expression {
+        SymbolicExpression::Variable(v) if v.is_next => SymbolicExt::Val(next[v.column]),
+        SymbolicExpression::Variable(v) => SymbolicExt::Val(local[v.column]),
+        SymbolicExpression::IsFirstRow => SymbolicExt::Val(selectors.is_first_row),
+        SymbolicExpression::IsLastRow => SymbolicExt::Val(selectors.is_last_row),
+        SymbolicExpression::IsTransition => SymbolicExt::Val(selectors.is_transition),
+        SymbolicExpression::Constant(c) => SymbolicExt::Const(C::EF::from_base(*c)),
+        SymbolicExpression::Add { x, y, .. } => {
+            eval_symbolic(x, local, next, selectors) + eval_symbolic(y, local, next, selectors)
+        }
+        SymbolicExpression::Sub { x, y, .. } => {
+            eval_symbolic(x, local, next, selectors) - eval_symbolic(y, local, next, selectors)
+        }
+        SymbolicExpression::Neg { x, .. } => -eval_symbolic(x, local, next, selectors),
+        SymbolicExpression::Mul { x, y, .. } => {
+            eval_symbolic(x, local, next, selectors) * eval_symbolic(y, local, next, selectors)
+        }
+    }
+}
+
+/// Folds the recorded constraints of a chip and those of its permutation argument with powers of
+/// `alpha`, in the same order as `Chip::eval` with a `VerifierConstraintFolder`.
+///
+/// The permutation values are expected as extension elements, i.e. not flattened.
+#[allow(clippy::too_many_arguments)]
+pub fn fold_constraints<C: Config>(
+    builder: &mut Builder<C>,
+    constraints: &SymbolicConstraints<C::F>,
+    preprocessed: &AirOpenedValues<Ext<C::F, C::EF>>,
+    main: &AirOpenedValues<Ext<C::F, C::EF>>,
+    perm: &AirOpenedValues<Ext<C::F, C::EF>>,
+    permutation_challenges: &[Ext<C::F, C::EF>],
+    cumulative_sum: Ext<C::F, C::EF>,
+    selectors: &SelectorsVariable<C>,
+    alpha: Ext<C::F, C::EF>,
+) -> Ext<C::F, C::EF> {
+    let mut accumulator: Ext<C::F, C::EF> = builder.eval(C::EF::zero());
+    let mut fold = |builder: &mut Builder<C>, value: SymbolicExt<C::F, C::EF>| {
+        accumulator = builder.eval(SymbolicExt::Val(accumulator) * alpha + value);
+        accumulator
+    };
+
+    let local = [main.local.as_slice(), &preprocessed.local].concat();
+    let next = [main.next.as_slice(), &preprocessed.next].concat();
+
+    for constraint in constraints.constraints.iter() {
+        let value = eval_symbolic(constraint, &local, &next, selectors);
+        fold(builder, value);
+    }
+
+    // The permutation constraints, as `sp1_core::stark::eval_permutation_constraints`.
+    let (alpha_perm, beta) = (permutation_challenges[0], permutation_challenges[1]);
+    let interactions = constraints
+        .sends
+        .iter()
+        .chain(constraints.receives.iter())
+        .collect::<Vec<&AirInteraction<_>>>();
+    let num_arguments = interactions
+        .iter()
+        .map(|interaction| interaction.kind as usize)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let alphas = powers(builder, alpha_perm, 1, num_arguments);
+    let max_values = interactions
+        .iter()
+        .map(|interaction| interaction.values.len())
+        .max()
+        .unwrap_or(0);
+    let betas = powers(builder, beta, 0, max_values);
+
+    let (perm_local, perm_next) = (&perm.local, &perm.next);
+    let phi_local = SymbolicExt::Val(perm_local[perm_local.len() - 1]);
+    let phi_next = SymbolicExt::Val(perm_next[perm_next.len() - 1]);
+
+    let lhs = phi_next - phi_local.clone();
+    let mut rhs = SymbolicExt::Const(C::EF::zero());
+    let mut phi_0 = SymbolicExt::Const(C::EF::zero());
+
+    for (m, interaction) in interactions.iter().enumerate() {
+        // Ensure that the recipricals of the RLC's were properly calculated.
+        let mut rlc = SymbolicExt::Val(alphas[interaction.kind as usize]);
+        for (value, beta) in interaction.values.iter().zip(betas.iter()) {
+            rlc = rlc + SymbolicExt::Val(*beta) * eval_symbolic(value, &local, &[], selectors);
+        }
+        fold(builder, rlc * perm_local[m] - C::EF::one());
+
+        // Interactions only involve the current row, so the next row is evaluated as a local one.
+        let mult_local = eval_symbolic(&interaction.multiplicity, &local, &[], selectors);
+        let mult_next = eval_symbolic(&interaction.multiplicity, &next, &[], selectors);
+
+        // Ensure that the running sum is computed correctly.
+        if m < constraints.sends.len() {
+            phi_0 = phi_0 + mult_local * perm_local[m];
+            rhs = rhs + mult_next * perm_next[m];
+        } else {
+            phi_0 = phi_0 - mult_local * perm_local[m];
+            rhs = rhs - mult_next * perm_next[m];
+        }
+    }
+
+    // Running sum constraints.
+    fold(
+        builder,
+        SymbolicExt::Val(selectors.is_transition) * (lhs - rhs),
+    );
+    fold(
+        builder,
+        SymbolicExt::Val(selectors.is_first_row) * (phi_local.clone() - phi_0),
+    );
+    fold(
+        builder,
+        SymbolicExt::Val(selectors.is_last_row) * (phi_local - cumulative_sum),
+    )
+}
+
+/// Computes `x^start, ..., x^(start + len - 1)`.
+fn powers<C: Config>(
+    builder: &mut Builder<C>,
+    x: Ext<C::F, C::EF>,
+    start: usize,
+    len: usize,
+) -> Vec<Ext<C::F, C::EF>> {
+    let mut current: Ext<C::F, C::EF> = builder.eval(C::EF::one());
+    for _ in 0..start {
+        current = builder.eval(current * x);
+    }
+    let mut powers = Vec::with_capacity(len);
+    for _ in 0..len {
+        powers.push(current);
+        current = builder.eval(current * x);
+    }
+    powers
+}
@@ -0,0 +1,260 @@
+use alloc::collections::BTreeMap;
+
+use p3_field::{AbstractExtensionField, AbstractField, TwoAdicField};
+use p3_matrix::Dimensions;
+use p3_util::log2_strict_usize;
+
+use super::{verify_batch, DigestVariable, DuplexChallengerVariable, QueryVariable};
+use super::{TwoAdicPcsProofVariable, LOG_BLOWUP, NUM_QUERIES, PROOF_OF_WORK_BITS};
+use crate::ir::{Builder, Config, Ext, Felt, SymbolicExt, SymbolicFelt};
+
+/// Verifies the opening of the committed batches at the given points, as the
+/// `verify_multi_batches` of the `TwoAdicFriPcs` of `BabyBearPoseidon2`.
+///
+/// `dims` are the dimensions of the committed traces, before the low-degree extension.
+pub fn verify_two_adic_pcs<C: Config>(
+    builder: &mut Builder<C>,
+    commits_and_points: &[(DigestVariable<C>, Vec<Vec<Ext<C::F, C::EF>>>)],
+    dims: &[Vec<Dimensions>],
+    values: &[Vec<Vec<Vec<Ext<C::F, C::EF>>>>],
+    proof: &TwoAdicPcsProofVariable<C>,
+    challenger: &mut DuplexChallengerVariable<C>,
+) where
+    C::F: TwoAdicField,
+{
+    let alpha = challenger.sample_ext(builder);
+
+    let betas = proof
+        .commit_phase_commits
+        .iter()
+        .map(|commit| {
+            challenger.observe_commitment(builder, commit);
+            challenger.sample_ext(builder)
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(proof.queries.len(), NUM_QUERIES, "invalid proof shape");
+
+    challenger.check_witness(builder, PROOF_OF_WORK_BITS, proof.pow_witness);
+
+    let log_max_height = proof.commit_phase_commits.len() + LOG_BLOWUP;
+    for query in proof.queries.iter() {
+        let index_bits = challenger.sample_bits(builder, log_max_height);
+        let reduced_openings = reduced_openings(
+            builder,
+            alpha,
+            &index_bits,
+            query,
+            commits_and_points,
+            dims,
+            values,
+        );
+        let folded_eval = verify_query(
+            builder,
+            &proof.commit_phase_commits,
+            &index_bits,
+            query,
+            &betas,
+            &reduced_openings,
+        );
+        builder.assert_ext_eq(folded_eval, proof.final_poly);
+    }
+}
+
+/// Checks the openings of the committed batches at the queried index, and reduces them to a
+/// single evaluation per height.
+fn reduced_openings<C: Config>(
+    builder: &mut Builder<C>,
+    alpha: Ext<C::F, C::EF>,
+    index_bits: &[Felt<C::F>],
+    query: &QueryVariable<C>,
+    commits_and_points: &[(DigestVariable<C>, Vec<Vec<Ext<C::F, C::EF>>>)],
+    dims: &[Vec<Dimensions>],
+    values: &[Vec<Vec<Vec<Ext<C::F, C::EF>>>>],
+) -> BTreeMap<usize, Ext<C::F, C::EF>>
+where
+    C::F: TwoAdicField,
+{
+    let log_max_height = index_bits.len();
+    let mut reduced_openings: BTreeMap<usize, Ext<C::F, C::EF>> = BTreeMap::new();
+    let mut alpha_pows: BTreeMap<usize, Ext<C::F, C::EF>> = BTreeMap::new();
+
+    for (((batch_opening, batch_dims), (batch_commit, batch_points)), batch_at_z) in query
+        .input_openings
+        .iter()
+        .zip(dims)
+        .zip(commits_and_points)
+        .zip(values)
+    {
+        // The committed matrices are the low-degree extensions of the traces.
+        let lde_dims = batch_dims
+            .iter()
+            .map(|dims| Dimensions {
+                width: dims.width,
+                height: dims.height << LOG_BLOWUP,
+            })
+            .collect::<Vec<_>>();
+        let log_batch_max_height = lde_dims
+            .iter()
+            .map(|dims| log2_strict_usize(dims.height))
+            .max()
+            .unwrap();
+        verify_batch(
+            builder,
+            batch_commit,
+            &lde_dims,
+            &index_bits[log_max_height - log_batch_max_height..],
+            &batch_opening.opened_values,
+            &batch_opening.opening_proof,
+        );
+
+        for (((mat_opening, mat_dims), mat_points), mat_at_z) in batch_opening
+            .opened_values
+            .iter()
+            .zip(lde_dims.iter())
+            .zip(batch_points)
+            .zip(batch_at_z)
+        {
+            let log_height = log2_strict_usize(mat_dims.height);
+            let rev_reduced_index = exp_reverse_bits_len(
+                builder,
+                C::F::two_adic_generator(log_height),
+                &index_bits[log_max_height - log_height..],
+            );
+            let x: Felt<C::F> = builder.eval(rev_reduced_index * C::F::generator());
+
+            for (z, ps_at_z) in mat_points.iter().zip(mat_at_z) {
+                // The quotients share their denominator, so the numerators are reduced first.
+                let mut alpha_pow = *alpha_pows
+                    .entry(log_height)
+                    .or_insert_with(|| builder.eval(C::EF::one()));
+                let mut numerator: Ext<C::F, C::EF> = builder.eval(C::EF::zero());
+                for (p_at_x, p_at_z) in mat_opening.iter().zip(ps_at_z) {
+                    numerator =
+                        builder.eval(numerator + alpha_pow * (*p_at_x - SymbolicExt::Val(*p_at_z)));
+                    alpha_pow = builder.eval(alpha_pow * alpha);
+                }
+                alpha_pows.insert(log_height, alpha_pow);
+
+                let quotient: Ext<C::F, C::EF> =
+                    builder.eval(SymbolicExt::Val(numerator) / (x - SymbolicExt::Val(*z)));
+                let reduced_opening = match reduced_openings.get(&log_height) {
+                    Some(reduced_opening) => builder.eval(*reduced_opening + quotient),
+                    None => quotient,
+                };
+                reduced_openings.insert(log_height, reduced_opening);
+            }
+        }
+    }
+
+    reduced_openings
+}
+
+/// Folds the reduced openings of a query through the commit phases, returning the final folded
+/// evaluation.
+fn verify_query<C: Config>(
+    builder: &mut Builder<C>,
+    commit_phase_commits: &[DigestVariable<C>],
+    index_bits: &[Felt<C::F>],
+    query: &QueryVariable<C>,
+    betas: &[Ext<C::F, C::EF>],
+    reduced_openings: &BTreeMap<usize, Ext<C::F, C::EF>>,
+) -> Ext<C::F, C::EF>
+where
+    C::F: TwoAdicField,
+{
+    let log_max_height = index_bits.len();
+    let mut folded_eval: Ext<C::F, C::EF> = builder.eval(C::EF::zero());
+    let mut x = exp_reverse_bits_len(
+        builder,
+        C::F::two_adic_generator(log_max_height),
+        index_bits,
+    );
+
+    for (i, (((commit, step), beta), folded_coefficients)) in commit_phase_commits
+        .iter()
+        .zip(query.commit_phase_openings.iter())
+        .zip(betas)
+        .zip(query.folded_evals.iter())
+        .enumerate()
+    {
+        let log_folded_height = log_max_height - 1 - i;
+        if let Some(reduced_opening) = reduced_openings.get(&(log_folded_height + 1)) {
+            folded_eval = builder.eval(folded_eval + *reduced_opening);
+        }
+
+        // The commit phase hashes the coefficients of the evaluations, so check the folded
+        // evaluation against its given coefficients.
+        let folded_ext = ext_from_coefficients(builder, folded_coefficients);
+        builder.assert_ext_eq(folded_eval, folded_ext);
+
+        // The folded evaluation is on the left of the pair if the index is even.
+        let bit = index_bits[i];
+        let evals_0: [Felt<C::F>; 4] = core::array::from_fn(|j| {
+            builder.eval(
+                (SymbolicFelt::Val(step.sibling_value[j]) - folded_coefficients[j]) * bit
+                    + folded_coefficients[j],
+            )
+        });
+        let evals_1: [Felt<C::F>; 4] = core::array::from_fn(|j| {
+            builder.eval(
+                SymbolicFelt::Val(folded_coefficients[j]) + step.sibling_value[j] - evals_0[j],
+            )
+        });
+        let leaf = evals_0.iter().chain(evals_1.iter()).copied().collect();
+        let dims = [Dimensions {
+            width: 2 * C::EF::D,
+            height: 1 << log_folded_height,
+        }];
+        verify_batch(
+            builder,
+            commit,
+            &dims,
+            &index_bits[i + 1..],
+            &[leaf],
+            &step.opening_proof,
+        );
+
+        // Interpolate the pair at `beta`, where the points of the pair are `x_0` and `-x_0`.
+        let e0 = ext_from_coefficients(builder, &evals_0);
+        let e1 = ext_from_coefficients(builder, &evals_1);
+        let x0: Felt<C::F> =
+            builder.eval((SymbolicFelt::Const(C::F::one()) - bit * C::F::two()) * x);
+        let inv: Felt<C::F> = builder.eval(SymbolicFelt::Const(C::F::one()) / (x0 * C::F::two()));
+        folded_eval = builder.eval(e0 + inv * (x0 - SymbolicExt::Val(*beta)) * (e1 - e0));
+
+        x = builder.eval(x * x);
+    }
+
+    folded_eval
+}
+
+/// Computes `generator^rev(index)`, where `rev` reverses the little-endian `bits` of `index`.
+pub fn exp_reverse_bits_len<C: Config>(
+    builder: &mut Builder<C>,
+    generator: C::F,
+    bits: &[Felt<C::F>],
+) -> Felt<C::F> {
+    let mut result: Felt<C::F> = builder.eval(C::F::one());
+    let mut power = generator;
+    for bit in bits.iter().rev() {
+        result =
+            builder.eval((SymbolicFelt::Val(*bit) * (power - C::F::one()) + C::F::one()) * result);
+        power = power.square();
+    }
+    result
+}
+
+/// Builds an extension element from its coefficients.
+pub fn ext_from_coefficients<C: Config>(
+    builder: &mut Builder<C>,
+    coefficients: &[Felt<C::F>; 4],
+) -> Ext<C::F, C::EF> {
+    let mut sum: SymbolicExt<C::F, C::EF> = SymbolicExt::Const(C::EF::zero());
+    for (i, coefficient) in coefficients.iter().enumerate() {
+        let mut monomial = vec![C::F::zero(); C::EF::D];
+        monomial[i] = C::F::one();
+        sum = sum + *coefficient * SymbolicExt::Const(C::EF::from_base_slice(&monomial));
+    }
+    builder.eval(sum)
+}
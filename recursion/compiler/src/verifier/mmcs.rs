@@ -0,0 +1,112 @@
+use p3_field::AbstractField;
+use p3_matrix::Dimensions;
+use sp1_recursion_core::poseidon2::WIDTH;
+
+use super::{permute, DigestVariable, DIGEST_SIZE};
+use crate::ir::{Builder, Config, Felt, SymbolicFelt};
+
+/// Hashes `values` with the `PaddingFreeSponge<Perm, 16, 8, 8>` of `BabyBearPoseidon2`.
+pub fn hash_slices<C: Config>(
+    builder: &mut Builder<C>,
+    values: &[&[Felt<C::F>]],
+) -> DigestVariable<C> {
+    let mut state: [Felt<C::F>; WIDTH] = core::array::from_fn(|_| builder.eval(C::F::zero()));
+    let values = values
+        .iter()
+        .flat_map(|slice| slice.iter())
+        .collect::<Vec<_>>();
+    // The sponge overwrites the rate with every chunk of the input.
+    for chunk in values.chunks(DIGEST_SIZE) {
+        for (x, value) in state.iter_mut().zip(chunk) {
+            *x = **value;
+        }
+        state = permute(builder, state);
+    }
+    core::array::from_fn(|i| state[i])
+}
+
+/// Compresses two digests with the `TruncatedPermutation<Perm, 2, 8, 16>` of
+/// `BabyBearPoseidon2`.
+pub fn compress<C: Config>(
+    builder: &mut Builder<C>,
+    left: &DigestVariable<C>,
+    right: &DigestVariable<C>,
+) -> DigestVariable<C> {
+    let state = core::array::from_fn(|i| {
+        if i < DIGEST_SIZE {
+            left[i]
+        } else {
+            right[i - DIGEST_SIZE]
+        }
+    });
+    let output = permute(builder, state);
+    core::array::from_fn(|i| output[i])
+}
+
+/// Verifies a batch opening of a `FieldMerkleTreeMmcs` commitment.
+///
+/// The index is given by its little-endian bits, and `dimensions` are the dimensions of the
+/// committed matrices, in commitment order.
+pub fn verify_batch<C: Config>(
+    builder: &mut Builder<C>,
+    commit: &DigestVariable<C>,
+    dimensions: &[Dimensions],
+    index_bits: &[Felt<C::F>],
+    opened_values: &[Vec<Felt<C::F>>],
+    proof: &[DigestVariable<C>],
+) {
+    assert_eq!(dimensions.len(), opened_values.len());
+    assert_eq!(index_bits.len(), proof.len());
+
+    // Matrices are hashed from the tallest to the smallest, keeping the commitment order among
+    // matrices of the same height.
+    let mut heights_tallest_first = (0..dimensions.len()).collect::<Vec<_>>();
+    heights_tallest_first.sort_by_key(|&i| core::cmp::Reverse(dimensions[i].height));
+    let mut heights_tallest_first = heights_tallest_first.into_iter().peekable();
+
+    let mut curr_height_padded = dimensions[*heights_tallest_first.peek().unwrap()]
+        .height
+        .next_power_of_two();
+    let mut openings = Vec::new();
+    while let Some(&i) = heights_tallest_first.peek() {
+        if dimensions[i].height.next_power_of_two() != curr_height_padded {
+            break;
+        }
+        openings.push(opened_values[i].as_slice());
+        heights_tallest_first.next();
+    }
+    let mut root = hash_slices(builder, &openings);
+
+    for (bit, sibling) in index_bits.iter().zip(proof) {
+        // The current node is on the right if the bit is set.
+        let left: DigestVariable<C> = core::array::from_fn(|j| {
+            builder.eval((SymbolicFelt::Val(sibling[j]) - root[j]) * *bit + root[j])
+        });
+        let right: DigestVariable<C> = core::array::from_fn(|j| {
+            builder.eval(SymbolicFelt::Val(root[j]) + sibling[j] - left[j])
+        });
+        root = compress(builder, &left, &right);
+        curr_height_padded >>= 1;
+
+        let next_height = heights_tallest_first
+            .peek()
+            .map(|&i| dimensions[i].height)
+            .filter(|height| height.next_power_of_two() == curr_height_padded);
+        if let Some(next_height) = next_height {
+            let mut openings = Vec::new();
+            while let Some(&i) = heights_tallest_first.peek() {
+                if dimensions[i].height != next_height {
+                    break;
+                }
+                openings.push(opened_values[i].as_slice());
+                heights_tallest_first.next();
+            }
+            let next_height_openings_digest = hash_slices(builder, &openings);
+            root = compress(builder, &root, &next_height_openings_digest);
+        }
+    }
+
+    for (x, y) in root.iter().zip(commit.iter()) {
+        builder.assert_felt_eq(*x, *y);
+    }
+}
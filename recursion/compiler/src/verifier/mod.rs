@@ -0,0 +1,29 @@
+//! A verifier for the shard proofs of `BabyBearPoseidon2`, written in the DSL.
+
+mod challenger;
+mod constraints;
+mod fri;
+mod mmcs;
+mod shard;
+mod types;
+mod witness;
+
+pub use challenger::*;
+pub use constraints::*;
+pub use fri::*;
+pub use mmcs::*;
+pub use shard::*;
+pub use types::*;
+pub use witness::*;
+
+use crate::ir::{Config, Felt};
+
+/// The number of felts in a Poseidon2 digest.
+pub const DIGEST_SIZE: usize = 8;
+
+/// The FRI parameters of `BabyBearPoseidon2`.
+pub const LOG_BLOWUP: usize = 1;
+pub const NUM_QUERIES: usize = 100;
+pub const PROOF_OF_WORK_BITS: usize = 16;
+
+pub type DigestVariable<C> = [Felt<<C as Config>::F>; DIGEST_SIZE];
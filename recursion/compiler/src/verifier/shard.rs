@@ -0,0 +1,616 @@
+use alloc::collections::VecDeque;
+
+use p3_air::{Air, BaseAir};
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_field::{AbstractExtensionField, AbstractField, Field, TwoAdicField};
+use p3_matrix::Dimensions;
+use p3_util::reverse_slice_index_bits;
+use serde::{Deserialize, Serialize};
+use sp1_core::air::MachineAir;
+use sp1_core::lookup::InteractionKind;
+use sp1_core::stark::{
+    AirOpenedValues, Chip, Proof, ShardCommitment, ShardProof, SymbolicConstraintBuilder,
+    VerifyingKey,
+};
+use sp1_core::utils::baby_bear_poseidon2::{Challenge, Challenger, Val};
+use sp1_core::utils::{BabyBearPoseidon2, StarkUtils};
+use sp1_recursion_core::air::Block;
+
+use super::{fold_constraints, hint_digest, verify_two_adic_pcs, write_digest, write_pcs_proof};
+use super::{Commitment, DigestVariable, DuplexChallengerVariable, PcsWitness};
+use super::{SelectorsVariable, TwoAdicPcsProofVariable};
+use crate::ir::{Builder, Config, Ext, Felt, SymbolicExt};
+
+/// The values of a chip opened at the out-of-domain point and its shift, in the DSL.
+#[derive(Debug, Clone)]
+pub struct ChipOpenedValuesVariable<C: Config> {
+    pub preprocessed: AirOpenedValues<Ext<C::F, C::EF>>,
+    pub main: AirOpenedValues<Ext<C::F, C::EF>>,
+    /// The flattened coefficients of the permutation trace.
+    pub permutation: AirOpenedValues<Ext<C::F, C::EF>>,
+    /// The flattened coefficients of the quotient chunks.
+    pub quotient: Vec<Ext<C::F, C::EF>>,
+    pub cumulative_sum: Ext<C::F, C::EF>,
+    pub log_degree: usize,
+}
+
+/// A `ShardProof` of `BabyBearPoseidon2`, in the DSL.
+#[derive(Debug, Clone)]
+pub struct ShardProofVariable<C: Config> {
+    pub commitment: ShardCommitment<DigestVariable<C>>,
+    pub opened_values: Vec<ChipOpenedValuesVariable<C>>,
+    pub opening_proof: TwoAdicPcsProofVariable<C>,
+    pub chip_ids: Vec<String>,
+}
+
+/// The shape of a shard proof: the chips it proves and the log degrees of their traces.
+///
+/// The shape determines the number of values of the proof, so a verifier program reads the proofs
+/// of a single shape from the witness stream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardShape {
+    pub chip_ids: Vec<String>,
+    pub log_degrees: Vec<usize>,
+}
+
+impl ShardShape {
+    /// The shape of `proof`.
+    pub fn of(proof: &ShardProof<BabyBearPoseidon2>) -> Self {
+        Self {
+            chip_ids: proof.chip_ids.clone(),
+            log_degrees: proof
+                .opened_values
+                .chips
+                .iter()
+                .map(|values| values.log_degree)
+                .collect(),
+        }
+    }
+
+    /// The chips of the shard, or `None` if the shape does not name chips of `chips` in their
+    /// order, each with a valid log degree.
+    pub fn chips<'a, F: TwoAdicField, A: MachineAir<F>>(
+        &self,
+        chips: &'a [Chip<F, A>],
+    ) -> Option<Vec<&'a Chip<F, A>>> {
+        let shard_chips = chips
+            .iter()
+            .filter(|chip| self.chip_ids.contains(&chip.name()))
+            .collect::<Vec<_>>();
+        let names = shard_chips
+            .iter()
+            .map(|chip| chip.name())
+            .collect::<Vec<_>>();
+        let valid = names == self.chip_ids
+            && self.log_degrees.len() == names.len()
+            && self
+                .log_degrees
+                .iter()
+                .all(|log_degree| *log_degree <= F::TWO_ADICITY);
+        valid.then_some(shard_chips)
+    }
+
+    /// The name and the log degree of each chip with a preprocessed trace, as in
+    /// `VerifyingKey::chip_information`, given the chips of the shard.
+    pub fn preprocessed_chips<F: Field, A: MachineAir<F>>(
+        &self,
+        chips: &[&Chip<F, A>],
+    ) -> Vec<(String, usize)> {
+        chips
+            .iter()
+            .zip(self.log_degrees.iter())
+            .filter(|(chip, _)| chip.preprocessed_width() > 0)
+            .map(|(chip, log_degree)| (chip.name(), *log_degree))
+            .collect()
+    }
+}
+
+impl<C: Config<F = Val, EF = Challenge>> ShardProofVariable<C> {
+    /// Reads a shard proof of the given shape from the witness stream, along with the witness of
+    /// its opening proof, as written by [`write_shard_proof`].
+    ///
+    /// `chips` are the chips of the shard, as given by [`ShardShape::chips`].
+    pub fn read<A: MachineAir<Val>>(
+        builder: &mut Builder<C>,
+        chips: &[&Chip<Val, A>],
+        shape: &ShardShape,
+    ) -> Self {
+        assert_eq!(chips.len(), shape.log_degrees.len(), "invalid proof shape");
+
+        let commitment = ShardCommitment {
+            main_commit: hint_digest(builder),
+            permutation_commit: hint_digest(builder),
+            quotient_commit: hint_digest(builder),
+        };
+        let opened_values = chips
+            .iter()
+            .zip(shape.log_degrees.iter())
+            .map(|(chip, log_degree)| ChipOpenedValuesVariable {
+                preprocessed: hint_air_opened_values(builder, chip.preprocessed_width()),
+                main: hint_air_opened_values(builder, chip.width()),
+                permutation: hint_air_opened_values(
+                    builder,
+                    chip.num_interactions() * Challenge::D,
+                ),
+                quotient: hint_exts(builder, Challenge::D << chip.log_quotient_degree()),
+                cumulative_sum: builder.hint(),
+                log_degree: *log_degree,
+            })
+            .collect();
+        let dims = shard_dims(chips, &shape.log_degrees, Challenge::D);
+
+        Self {
+            commitment,
+            opened_values,
+            opening_proof: TwoAdicPcsProofVariable::read(builder, &dims),
+            chip_ids: shape.chip_ids.clone(),
+        }
+    }
+}
+
+/// Appends a shard proof and the witness of its opening proof to the witness stream, in the order
+/// in which [`ShardProofVariable::read`] reads them.
+pub fn write_shard_proof(
+    stream: &mut VecDeque<Block<Val>>,
+    proof: &ShardProof<BabyBearPoseidon2>,
+    witness: &PcsWitness,
+) {
+    let ShardCommitment {
+        main_commit,
+        permutation_commit,
+        quotient_commit,
+    } = &proof.commitment;
+    write_digest(stream, main_commit.clone());
+    write_digest(stream, permutation_commit.clone());
+    write_digest(stream, quotient_commit.clone());
+
+    for values in proof.opened_values.chips.iter() {
+        for opened in [&values.preprocessed, &values.main, &values.permutation] {
+            write_exts(stream, &opened.local);
+            write_exts(stream, &opened.next);
+        }
+        write_exts(stream, &values.quotient);
+        write_exts(stream, &[values.cumulative_sum]);
+    }
+
+    write_pcs_proof(stream, &proof.opening_proof, witness);
+}
+
+impl PcsWitness {
+    /// Replays the verification of a shard proof natively up to its opening proof, and generates
+    /// the witness of the opening proof.
+    ///
+    /// `preprocessed_commit` is the commitment of the verifying key to the preprocessed traces.
+    /// The challenger must have observed the main commitments of all the shards of the proof.
+    pub fn for_shard<A: MachineAir<Val>>(
+        chips: &[&Chip<Val, A>],
+        preprocessed_commit: Option<&Commitment>,
+        proof: &ShardProof<BabyBearPoseidon2>,
+        challenger: &mut Challenger,
+    ) -> Self {
+        let ShardCommitment {
+            main_commit,
+            permutation_commit,
+            quotient_commit,
+        } = &proof.commitment;
+        let log_degrees = proof
+            .opened_values
+            .chips
+            .iter()
+            .map(|values| values.log_degree)
+            .collect::<Vec<_>>();
+
+        for _ in 0..2 {
+            let _: Challenge = challenger.sample_ext_element();
+        }
+        challenger.observe(permutation_commit.clone());
+        let _: Challenge = challenger.sample_ext_element();
+        challenger.observe(quotient_commit.clone());
+        let zeta: Challenge = challenger.sample_ext_element();
+
+        let trace_points = log_degrees
+            .iter()
+            .map(|log_degree| vec![zeta, zeta * Val::two_adic_generator(*log_degree)])
+            .collect::<Vec<_>>();
+        let preprocessed_points = chips
+            .iter()
+            .zip(trace_points.iter())
+            .filter(|(chip, _)| chip.preprocessed_width() > 0)
+            .map(|(_, points)| points.clone())
+            .collect::<Vec<_>>();
+        let quotient_points = chips
+            .iter()
+            .map(|chip| vec![zeta.exp_power_of_2(chip.log_quotient_degree())])
+            .collect::<Vec<_>>();
+
+        let mut batches: Vec<(Commitment, &[Vec<Challenge>])> = vec![];
+        if let Some(preprocessed_commit) = preprocessed_commit {
+            batches.push((preprocessed_commit.clone(), &preprocessed_points));
+        }
+        batches.push((main_commit.clone(), &trace_points));
+        batches.push((permutation_commit.clone(), &trace_points));
+        batches.push((quotient_commit.clone(), &quotient_points));
+
+        Self::generate(
+            &batches,
+            &shard_dims(chips, &log_degrees, Challenge::D),
+            &proof.opened_values.clone().into_values(),
+            &proof.opening_proof,
+            challenger,
+        )
+    }
+}
+
+/// Reads the values of a matrix of `width` columns opened at a point and its shift from the
+/// witness stream.
+fn hint_air_opened_values<C: Config>(
+    builder: &mut Builder<C>,
+    width: usize,
+) -> AirOpenedValues<Ext<C::F, C::EF>> {
+    AirOpenedValues {
+        local: hint_exts(builder, width),
+        next: hint_exts(builder, width),
+    }
+}
+
+fn hint_exts<C: Config>(builder: &mut Builder<C>, len: usize) -> Vec<Ext<C::F, C::EF>> {
+    (0..len).map(|_| builder.hint()).collect()
+}
+
+fn write_exts(stream: &mut VecDeque<Block<Val>>, values: &[Challenge]) {
+    stream.extend(
+        values
+            .iter()
+            .map(|value| Block::from(value.as_base_slice())),
+    );
+}
+
+/// Reads the shard proofs of a machine proof with the given shapes from the witness stream, as
+/// written by [`write_proof`].
+///
+/// Panics if a shape is not one of the machine of `chips`.
+pub fn read_proof<C, A>(
+    builder: &mut Builder<C>,
+    chips: &[Chip<Val, A>],
+    shapes: &[ShardShape],
+) -> Vec<ShardProofVariable<C>>
+where
+    C: Config<F = Val, EF = Challenge>,
+    A: MachineAir<Val>,
+{
+    shapes
+        .iter()
+        .map(|shape| {
+            let shard_chips = shape.chips(chips).expect("invalid proof shape");
+            ShardProofVariable::read(builder, &shard_chips, shape)
+        })
+        .collect()
+}
+
+/// Appends the shard proofs of `proof` to the witness stream, along with the witnesses generated
+/// by replaying their verification natively against the verifying key `vk`.
+pub fn write_proof<A: MachineAir<Val>>(
+    stream: &mut VecDeque<Block<Val>>,
+    chips: &[Chip<Val, A>],
+    vk: &VerifyingKey<BabyBearPoseidon2>,
+    proof: &Proof<BabyBearPoseidon2>,
+) {
+    let mut challenger = BabyBearPoseidon2::new().challenger();
+    vk.observe_into(&mut challenger);
+    challenger.observe_slice(&proof.public_values);
+    for shard_proof in proof.shard_proofs.iter() {
+        challenger.observe(shard_proof.commitment.main_commit.clone());
+    }
+
+    for shard_proof in proof.shard_proofs.iter() {
+        let shard_chips = chips
+            .iter()
+            .filter(|chip| shard_proof.chip_ids.contains(&chip.name()))
+            .collect::<Vec<_>>();
+        let witness = PcsWitness::for_shard(
+            &shard_chips,
+            vk.commit.as_ref(),
+            shard_proof,
+            &mut challenger.clone(),
+        );
+        write_shard_proof(stream, shard_proof, &witness);
+    }
+}
+
+/// Verifies all the shard proofs of a machine proof, as `MachineStark::verify`.
+///
+/// `preprocessed_commit` is the commitment of the verifying key to the preprocessed traces, and
+/// the cumulative sums of the shards must balance the `public_values` of the proof.
+pub fn verify_proof<C, A>(
+    builder: &mut Builder<C>,
+    chips: &[Chip<C::F, A>],
+    preprocessed_commit: Option<&DigestVariable<C>>,
+    public_values: &[Felt<C::F>],
+    proofs: &[ShardProofVariable<C>],
+) where
+    C::F: TwoAdicField,
+    A: MachineAir<C::F> + Air<SymbolicConstraintBuilder<C::F>>,
+{
+    let mut challenger = DuplexChallengerVariable::new(builder);
+    if let Some(commit) = preprocessed_commit {
+        challenger.observe_commitment(builder, commit);
+    }
+    challenger.observe_slice(builder, public_values);
+    for proof in proofs.iter() {
+        challenger.observe_commitment(builder, &proof.commitment.main_commit);
+    }
+
+    let mut cumulative_sum = SymbolicExt::Const(C::EF::zero());
+    for proof in proofs.iter() {
+        let shard_chips = chips
+            .iter()
+            .filter(|chip| proof.chip_ids.contains(&chip.name()))
+            .collect::<Vec<_>>();
+        verify_shard(
+            builder,
+            &shard_chips,
+            preprocessed_commit,
+            &mut challenger.clone(),
+            proof,
+        );
+        for values in proof.opened_values.iter() {
+            cumulative_sum = cumulative_sum + values.cumulative_sum;
+        }
+    }
+    let expected = public_values_sum(builder, public_values, &challenger);
+    builder.assert_ext_eq(cumulative_sum, expected);
+}
+
+/// Computes the sum the cumulative sums of a proof must add up to, given its public values, as
+/// `sp1_core::stark::public_values_sum`.
+///
+/// The challenger must have observed the main commitments of all the shards of the proof.
+pub fn public_values_sum<C: Config>(
+    builder: &mut Builder<C>,
+    public_values: &[Felt<C::F>],
+    challenger: &DuplexChallengerVariable<C>,
+) -> Ext<C::F, C::EF> {
+    let mut challenger = challenger.clone();
+    let alpha = challenger.sample_ext(builder);
+    let beta = challenger.sample_ext(builder);
+
+    let mut rlc: Ext<C::F, C::EF> = builder.eval(C::EF::one());
+    for _ in 0..InteractionKind::PublicValues as usize + 1 {
+        rlc = builder.eval(rlc * alpha);
+    }
+    let mut sum: Ext<C::F, C::EF> = builder.eval(C::EF::zero());
+    for (i, value) in public_values.iter().enumerate() {
+        let denominator = SymbolicExt::Val(rlc)
+            + C::EF::from_canonical_usize(i)
+            + *value * SymbolicExt::Val(beta);
+        sum = builder.eval(SymbolicExt::Val(sum) + SymbolicExt::Const(C::EF::one()) / denominator);
+    }
+    sum
+}
+
+/// Verifies a shard proof, as `Verifier::verify_shard`.
+///
+/// `preprocessed_commit` is the commitment of the verifying key to the preprocessed traces, which
+/// must be given if and only if some chips of the shard have one. The challenger must have
+/// observed the main commitments of all the shards of the proof.
+pub fn verify_shard<C, A>(
+    builder: &mut Builder<C>,
+    chips: &[&Chip<C::F, A>],
+    preprocessed_commit: Option<&DigestVariable<C>>,
+    challenger: &mut DuplexChallengerVariable<C>,
+    proof: &ShardProofVariable<C>,
+) where
+    C::F: TwoAdicField,
+    A: MachineAir<C::F> + Air<SymbolicConstraintBuilder<C::F>>,
+{
+    let ShardProofVariable {
+        commitment,
+        opened_values,
+        opening_proof,
+        ..
+    } = proof;
+    assert_eq!(chips.len(), opened_values.len(), "invalid proof shape");
+    assert_eq!(
+        preprocessed_commit.is_some(),
+        chips.iter().any(|chip| chip.preprocessed_width() > 0),
+        "the chips with a preprocessed trace do not match the verifying key"
+    );
+
+    let log_degrees = opened_values
+        .iter()
+        .map(|values| values.log_degree)
+        .collect::<Vec<_>>();
+    let dims = shard_dims(chips, &log_degrees, C::EF::D);
+
+    let permutation_challenges = (0..2)
+        .map(|_| challenger.sample_ext(builder))
+        .collect::<Vec<_>>();
+    challenger.observe_commitment(builder, &commitment.permutation_commit);
+    let alpha = challenger.sample_ext(builder);
+    challenger.observe_commitment(builder, &commitment.quotient_commit);
+    let zeta = challenger.sample_ext(builder);
+
+    // Verify the opening proof.
+    let trace_points = log_degrees
+        .iter()
+        .map(|log_degree| {
+            let g = C::EF::from_base(C::F::two_adic_generator(*log_degree));
+            vec![zeta, builder.eval(zeta * g)]
+        })
+        .collect::<Vec<_>>();
+    let quotient_points = chips
+        .iter()
+        .map(|chip| vec![exp_power_of_2(builder, zeta, chip.log_quotient_degree())])
+        .collect::<Vec<_>>();
+
+    let to_values = |values: &AirOpenedValues<Ext<C::F, C::EF>>| {
+        vec![values.local.clone(), values.next.clone()]
+    };
+    let mut commits_and_points = vec![];
+    let mut values = vec![];
+    if let Some(preprocessed_commit) = preprocessed_commit {
+        let (points, preprocessed_values): (Vec<_>, Vec<_>) = chips
+            .iter()
+            .zip(trace_points.iter())
+            .zip(opened_values.iter())
+            .filter(|((chip, _), _)| chip.preprocessed_width() > 0)
+            .map(|((_, points), values)| (points.clone(), to_values(&values.preprocessed)))
+            .unzip();
+        commits_and_points.push((*preprocessed_commit, points));
+        values.push(preprocessed_values);
+    }
+    commits_and_points.extend([
+        (commitment.main_commit, trace_points.clone()),
+        (commitment.permutation_commit, trace_points),
+        (commitment.quotient_commit, quotient_points),
+    ]);
+    values.extend([
+        opened_values
+            .iter()
+            .map(|values| to_values(&values.main))
+            .collect::<Vec<_>>(),
+        opened_values
+            .iter()
+            .map(|values| to_values(&values.permutation))
+            .collect(),
+        opened_values
+            .iter()
+            .map(|values| vec![values.quotient.clone()])
+            .collect(),
+    ]);
+    verify_two_adic_pcs(
+        builder,
+        &commits_and_points,
+        &dims,
+        &values,
+        opening_proof,
+        challenger,
+    );
+
+    // Verify the constraint evaluations.
+    for (chip, values) in chips.iter().zip(opened_values.iter()) {
+        verify_constraints(builder, chip, values, zeta, alpha, &permutation_challenges);
+    }
+}
+
+/// Checks that the folded constraints of `chip` at `zeta` match the quotient recomposed from its
+/// opened chunks.
+///
+/// The constraints are recorded from the chip's AIR with a `SymbolicConstraintBuilder` and replayed
+/// over the opened values, so any `MachineAir` can be verified.
+pub fn verify_constraints<C, A>(
+    builder: &mut Builder<C>,
+    chip: &Chip<C::F, A>,
+    opening: &ChipOpenedValuesVariable<C>,
+    zeta: Ext<C::F, C::EF>,
+    alpha: Ext<C::F, C::EF>,
+    permutation_challenges: &[Ext<C::F, C::EF>],
+) where
+    C::F: TwoAdicField,
+    A: Air<SymbolicConstraintBuilder<C::F>>,
+{
+    let g_inv = C::EF::from_base(C::F::two_adic_generator(opening.log_degree).inverse());
+    let zeta_pow = exp_power_of_2(builder, zeta, opening.log_degree);
+    let z_h: Ext<C::F, C::EF> = builder.eval(zeta_pow - C::EF::one());
+    let is_first_row = builder.eval(z_h / (zeta - C::EF::one()));
+    let is_last_row = builder.eval(z_h / (zeta - g_inv));
+    let is_transition = builder.eval(zeta - g_inv);
+
+    // Recompose the quotient from its chunks.
+    let mut quotient_parts = unflatten(builder, &opening.quotient);
+    reverse_slice_index_bits(&mut quotient_parts);
+    let mut quotient: Ext<C::F, C::EF> = builder.eval(C::EF::zero());
+    let mut weight: Ext<C::F, C::EF> = builder.eval(C::EF::one());
+    for part in quotient_parts {
+        quotient = builder.eval(quotient + weight * part);
+        weight = builder.eval(weight * zeta);
+    }
+
+    let selectors = SelectorsVariable {
+        is_first_row,
+        is_last_row,
+        is_transition,
+    };
+    let perm = AirOpenedValues {
+        local: unflatten(builder, &opening.permutation.local),
+        next: unflatten(builder, &opening.permutation.next),
+    };
+    let folded_constraints = fold_constraints(
+        builder,
+        &chip.symbolic_constraints(),
+        &opening.preprocessed,
+        &opening.main,
+        &perm,
+        permutation_challenges,
+        opening.cumulative_sum,
+        &selectors,
+        alpha,
+    );
+    builder.assert_ext_eq(folded_constraints, z_h * quotient);
+}
+
+/// The dimensions of the committed traces of the chips of a shard, in the order of the batches: the
+/// preprocessed traces if some chips have one, then the main, permutation and quotient traces.
+fn shard_dims<F: Field, A: MachineAir<F>>(
+    chips: &[&Chip<F, A>],
+    log_degrees: &[usize],
+    extension_degree: usize,
+) -> Vec<Vec<Dimensions>> {
+    let mut preprocessed_dims = Vec::new();
+    let mut dims = vec![Vec::new(), Vec::new(), Vec::new()];
+    for (chip, log_degree) in chips.iter().zip(log_degrees) {
+        let height = 1 << log_degree;
+        if chip.preprocessed_width() > 0 {
+            preprocessed_dims.push(Dimensions {
+                width: chip.preprocessed_width(),
+                height,
+            });
+        }
+        dims[0].push(Dimensions {
+            width: chip.width(),
+            height,
+        });
+        dims[1].push(Dimensions {
+            width: chip.num_interactions() * extension_degree,
+            height,
+        });
+        dims[2].push(Dimensions {
+            width: extension_degree << chip.log_quotient_degree(),
+            height,
+        });
+    }
+    if !preprocessed_dims.is_empty() {
+        dims.insert(0, preprocessed_dims);
+    }
+    dims
+}
+
+/// Reconstructs extension elements from their flattened coefficients.
+fn unflatten<C: Config>(
+    builder: &mut Builder<C>,
+    values: &[Ext<C::F, C::EF>],
+) -> Vec<Ext<C::F, C::EF>> {
+    values
+        .chunks_exact(C::EF::D)
+        .map(|chunk| {
+            let mut sum = SymbolicExt::Const(C::EF::zero());
+            for (i, value) in chunk.iter().enumerate() {
+                sum = sum + SymbolicExt::Val(*value) * C::EF::monomial(i);
+            }
+            builder.eval(sum)
+        })
+        .collect()
+}
+
+/// Computes `x^(2^power_log)` by repeated squaring.
+fn exp_power_of_2<C: Config>(
+    builder: &mut Builder<C>,
+    x: Ext<C::F, C::EF>,
+    power_log: usize,
+) -> Ext<C::F, C::EF> {
+    let mut result = x;
+    for _ in 0..power_log {
+        result = builder.eval(result * result);
+    }
+    result
+}
@@ -0,0 +1,258 @@
+use alloc::collections::VecDeque;
+
+use p3_commit::Mmcs;
+use p3_field::AbstractExtensionField;
+use p3_fri::{TwoAdicFriPcsConfig, TwoAdicFriPcsProof};
+use p3_matrix::Dimensions;
+use p3_util::log2_strict_usize;
+use sp1_core::utils::baby_bear_poseidon2::{
+    Challenge, ChallengeMmcs, Challenger, Dft, Val, ValMmcs,
+};
+use sp1_recursion_core::air::Block;
+
+use super::{DigestVariable, PcsWitness, DIGEST_SIZE, LOG_BLOWUP, NUM_QUERIES};
+use crate::ir::{Builder, Config, Ext, Felt};
+
+/// The commitment of a batch of matrices.
+pub type Commitment = <ValMmcs as Mmcs<Val>>::Commitment;
+
+/// The opening proof of the `TwoAdicFriPcs` of `BabyBearPoseidon2`.
+pub type PcsProof = TwoAdicFriPcsProof<
+    TwoAdicFriPcsConfig<Val, Challenge, Challenger, Dft, ValMmcs, ChallengeMmcs>,
+>;
+
+/// The openings of a batch of matrices at a queried row.
+#[derive(Debug, Clone)]
+pub struct BatchOpeningVariable<C: Config> {
+    pub opened_values: Vec<Vec<Felt<C::F>>>,
+    pub opening_proof: Vec<DigestVariable<C>>,
+}
+
+/// The opening of a commit phase of FRI at a queried index.
+#[derive(Debug, Clone)]
+pub struct CommitPhaseStepVariable<C: Config> {
+    /// The coefficients of the sibling evaluation.
+    pub sibling_value: [Felt<C::F>; 4],
+    pub opening_proof: Vec<DigestVariable<C>>,
+}
+
+/// A FRI query, along with the witnesses the verifier needs to check it.
+#[derive(Debug, Clone)]
+pub struct QueryVariable<C: Config> {
+    /// For each committed batch, the openings of the batch at the queried index.
+    pub input_openings: Vec<BatchOpeningVariable<C>>,
+    pub commit_phase_openings: Vec<CommitPhaseStepVariable<C>>,
+    /// For each commit phase, the coefficients of the folded evaluation at the queried index.
+    pub folded_evals: Vec<[Felt<C::F>; 4]>,
+}
+
+/// An opening proof of the `TwoAdicFriPcs` of `BabyBearPoseidon2`, in the DSL.
+#[derive(Debug, Clone)]
+pub struct TwoAdicPcsProofVariable<C: Config> {
+    pub commit_phase_commits: Vec<DigestVariable<C>>,
+    pub queries: Vec<QueryVariable<C>>,
+    pub final_poly: Ext<C::F, C::EF>,
+    pub pow_witness: Felt<C::F>,
+}
+
+impl<C: Config<F = Val, EF = Challenge>> TwoAdicPcsProofVariable<C> {
+    /// Embeds `proof` in the program, along with the witnesses generated for it.
+    pub fn new(builder: &mut Builder<C>, proof: &PcsProof, witness: &PcsWitness) -> Self {
+        let fri_proof = &proof.fri_proof;
+        let queries = fri_proof
+            .query_proofs
+            .iter()
+            .zip(proof.query_openings.iter())
+            .zip(witness.folded_evals.iter())
+            .map(|((query_proof, query_openings), folded_evals)| {
+                let input_openings = query_openings
+                    .iter()
+                    .map(|opening| BatchOpeningVariable {
+                        opened_values: opening
+                            .opened_values
+                            .iter()
+                            .map(|values| felts(builder, values))
+                            .collect(),
+                        opening_proof: opening
+                            .opening_proof
+                            .iter()
+                            .map(|digest| digest_variable(builder, digest.clone()))
+                            .collect(),
+                    })
+                    .collect();
+                let commit_phase_openings = query_proof
+                    .commit_phase_openings
+                    .iter()
+                    .map(|step| CommitPhaseStepVariable {
+                        sibling_value: ext_coefficients(builder, step.sibling_value),
+                        opening_proof: step
+                            .opening_proof
+                            .iter()
+                            .map(|digest| digest_variable(builder, digest.clone()))
+                            .collect(),
+                    })
+                    .collect();
+                QueryVariable {
+                    input_openings,
+                    commit_phase_openings,
+                    folded_evals: folded_evals
+                        .iter()
+                        .map(|eval| ext_coefficients(builder, *eval))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Self {
+            commit_phase_commits: fri_proof
+                .commit_phase_commits
+                .iter()
+                .map(|commit| digest_variable(builder, commit.clone()))
+                .collect(),
+            queries,
+            final_poly: builder.eval(fri_proof.final_poly),
+            pow_witness: builder.eval(fri_proof.pow_witness),
+        }
+    }
+}
+
+impl<C: Config<F = Val, EF = Challenge>> TwoAdicPcsProofVariable<C> {
+    /// Reads an opening proof of traces with the dimensions `dims` from the witness stream, along
+    /// with its witness, as written by [`write_pcs_proof`].
+    ///
+    /// `dims` are the dimensions of the committed traces, before the low-degree extension, which
+    /// determine the number of values of the proof.
+    pub fn read(builder: &mut Builder<C>, dims: &[Vec<Dimensions>]) -> Self {
+        let log_lde_height = |dims: &[Dimensions]| {
+            dims.iter()
+                .map(|dims| log2_strict_usize(dims.height) + LOG_BLOWUP)
+                .max()
+                .expect("a batch has at least one matrix")
+        };
+        let log_max_height = dims
+            .iter()
+            .map(|batch_dims| log_lde_height(batch_dims))
+            .max()
+            .expect("at least one batch is opened");
+        let num_commit_phases = log_max_height - LOG_BLOWUP;
+
+        let commit_phase_commits = (0..num_commit_phases)
+            .map(|_| hint_digest(builder))
+            .collect();
+        let queries = (0..NUM_QUERIES)
+            .map(|_| {
+                let input_openings = dims
+                    .iter()
+                    .map(|batch_dims| BatchOpeningVariable {
+                        opened_values: batch_dims
+                            .iter()
+                            .map(|dims| (0..dims.width).map(|_| builder.hint()).collect())
+                            .collect(),
+                        opening_proof: (0..log_lde_height(batch_dims))
+                            .map(|_| hint_digest(builder))
+                            .collect(),
+                    })
+                    .collect();
+                let commit_phase_openings = (0..num_commit_phases)
+                    .map(|i| CommitPhaseStepVariable {
+                        sibling_value: core::array::from_fn(|_| builder.hint()),
+                        opening_proof: (0..log_max_height - 1 - i)
+                            .map(|_| hint_digest(builder))
+                            .collect(),
+                    })
+                    .collect();
+                QueryVariable {
+                    input_openings,
+                    commit_phase_openings,
+                    folded_evals: (0..num_commit_phases)
+                        .map(|_| core::array::from_fn(|_| builder.hint()))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Self {
+            commit_phase_commits,
+            queries,
+            final_poly: builder.hint(),
+            pow_witness: builder.hint(),
+        }
+    }
+}
+
+/// Appends `proof` and its witness to the witness stream, in the order in which
+/// [`TwoAdicPcsProofVariable::read`] reads them.
+pub fn write_pcs_proof(stream: &mut VecDeque<Block<Val>>, proof: &PcsProof, witness: &PcsWitness) {
+    let fri_proof = &proof.fri_proof;
+    for commit in fri_proof.commit_phase_commits.iter() {
+        write_digest(stream, commit.clone());
+    }
+    for ((query_proof, query_openings), folded_evals) in fri_proof
+        .query_proofs
+        .iter()
+        .zip(proof.query_openings.iter())
+        .zip(witness.folded_evals.iter())
+    {
+        for opening in query_openings.iter() {
+            for values in opening.opened_values.iter() {
+                stream.extend(values.iter().map(|value| Block::from(*value)));
+            }
+            for digest in opening.opening_proof.iter() {
+                write_digest(stream, digest.clone());
+            }
+        }
+        for step in query_proof.commit_phase_openings.iter() {
+            write_coefficients(stream, step.sibling_value);
+            for digest in step.opening_proof.iter() {
+                write_digest(stream, digest.clone());
+            }
+        }
+        for eval in folded_evals.iter() {
+            write_coefficients(stream, *eval);
+        }
+    }
+    stream.push_back(Block::from(fri_proof.final_poly.as_base_slice()));
+    stream.push_back(Block::from(fri_proof.pow_witness));
+}
+
+/// Reads a digest from the witness stream.
+pub fn hint_digest<C: Config>(builder: &mut Builder<C>) -> DigestVariable<C> {
+    core::array::from_fn(|_| builder.hint())
+}
+
+/// Appends a digest to the witness stream, as read by [`hint_digest`].
+pub fn write_digest(stream: &mut VecDeque<Block<Val>>, digest: impl Into<[Val; DIGEST_SIZE]>) {
+    let digest: [Val; DIGEST_SIZE] = digest.into();
+    stream.extend(digest.map(Block::from));
+}
+
+/// Appends the coefficients of an extension element to the witness stream, as separate felts.
+fn write_coefficients(stream: &mut VecDeque<Block<Val>>, value: Challenge) {
+    let coefficients: &[Val] = value.as_base_slice();
+    stream.extend(
+        coefficients
+            .iter()
+            .map(|coefficient| Block::from(*coefficient)),
+    );
+}
+
+/// Embeds a digest in the program.
+pub fn digest_variable<C: Config<F = Val>>(
+    builder: &mut Builder<C>,
+    digest: impl Into<[Val; DIGEST_SIZE]>,
+) -> DigestVariable<C> {
+    let digest: [Val; DIGEST_SIZE] = digest.into();
+    digest.map(|value| builder.eval(value))
+}
+
+fn felts<C: Config<F = Val>>(builder: &mut Builder<C>, values: &[Val]) -> Vec<Felt<Val>> {
+    values.iter().map(|value| builder.eval(*value)).collect()
+}
+
+fn ext_coefficients<C: Config<F = Val, EF = Challenge>>(
+    builder: &mut Builder<C>,
+    value: Challenge,
+) -> [Felt<Val>; 4] {
+    let coefficients: &[Val] = value.as_base_slice();
+    core::array::from_fn(|i| builder.eval(coefficients[i]))
+}
@@ -0,0 +1,121 @@
+use p3_challenger::{CanObserve, CanSample, FieldChallenger};
+use p3_field::{AbstractField, Field, PrimeField32, TwoAdicField};
+use p3_matrix::Dimensions;
+use p3_util::{log2_strict_usize, reverse_bits_len};
+use sp1_core::utils::baby_bear_poseidon2::{Challenge, Challenger, Val};
+
+use super::{Commitment, PcsProof, LOG_BLOWUP, NUM_QUERIES};
+
+/// The values the verifier program cannot compute by itself, which it checks against the proof
+/// instead.
+#[derive(Debug, Clone)]
+pub struct PcsWitness {
+    /// The samples the query indices are taken from.
+    pub query_samples: Vec<Val>,
+    /// For each query, the folded evaluation at the queried index of every commit phase.
+    pub folded_evals: Vec<Vec<Challenge>>,
+}
+
+impl PcsWitness {
+    /// Replays the verification of `proof` natively, starting from a challenger in the same state
+    /// as the challenger of the verifier program.
+    pub fn generate(
+        commits_and_points: &[(Commitment, &[Vec<Challenge>])],
+        dims: &[Vec<Dimensions>],
+        values: &[Vec<Vec<Vec<Challenge>>>],
+        proof: &PcsProof,
+        challenger: &mut Challenger,
+    ) -> Self {
+        let fri_proof = &proof.fri_proof;
+
+        let alpha: Challenge = challenger.sample_ext_element();
+        let betas = fri_proof
+            .commit_phase_commits
+            .iter()
+            .map(|commit| {
+                challenger.observe(commit.clone());
+                challenger.sample_ext_element()
+            })
+            .collect::<Vec<Challenge>>();
+        challenger.observe(fri_proof.pow_witness);
+        // The proof of work is checked by the program itself.
+        let _pow_sample: Val = challenger.sample();
+        let query_samples: Vec<Val> = (0..NUM_QUERIES).map(|_| challenger.sample()).collect();
+
+        let log_max_height = fri_proof.commit_phase_commits.len() + LOG_BLOWUP;
+        let folded_evals = fri_proof
+            .query_proofs
+            .iter()
+            .zip(proof.query_openings.iter())
+            .zip(query_samples.iter())
+            .map(|((query_proof, query_openings), sample)| {
+                let mut index = sample.as_canonical_u32() as usize & ((1 << log_max_height) - 1);
+
+                // Reduce the openings of every height to a single evaluation.
+                let mut reduced_openings = [Challenge::zero(); 32];
+                let mut alpha_pows = [Challenge::one(); 32];
+                for (((batch_opening, batch_dims), (_, batch_points)), batch_at_z) in query_openings
+                    .iter()
+                    .zip(dims)
+                    .zip(commits_and_points)
+                    .zip(values)
+                {
+                    for (((mat_opening, mat_dims), mat_points), mat_at_z) in batch_opening
+                        .opened_values
+                        .iter()
+                        .zip(batch_dims)
+                        .zip(batch_points.iter())
+                        .zip(batch_at_z)
+                    {
+                        let log_height = log2_strict_usize(mat_dims.height) + LOG_BLOWUP;
+                        let rev_reduced_index =
+                            reverse_bits_len(index >> (log_max_height - log_height), log_height);
+                        let x = Val::generator()
+                            * Val::two_adic_generator(log_height).exp_u64(rev_reduced_index as u64);
+                        for (&z, ps_at_z) in mat_points.iter().zip(mat_at_z) {
+                            for (&p_at_x, &p_at_z) in mat_opening.iter().zip(ps_at_z) {
+                                let quotient = (-p_at_z + p_at_x) / (-z + x);
+                                reduced_openings[log_height] += alpha_pows[log_height] * quotient;
+                                alpha_pows[log_height] *= alpha;
+                            }
+                        }
+                    }
+                }
+
+                // Fold the evaluations, recording the folded evaluation of every commit phase.
+                let mut folded_eval = Challenge::zero();
+                let mut folded_evals = Vec::new();
+                let mut x =
+                    Val::two_adic_generator(log_max_height)
+                        .exp_u64(reverse_bits_len(index, log_max_height) as u64);
+                for (i, (step, beta)) in query_proof
+                    .commit_phase_openings
+                    .iter()
+                    .zip(betas.iter())
+                    .enumerate()
+                {
+                    let log_folded_height = log_max_height - 1 - i;
+                    folded_eval += reduced_openings[log_folded_height + 1];
+                    folded_evals.push(folded_eval);
+
+                    let index_sibling = index ^ 1;
+                    let mut evals = [folded_eval; 2];
+                    evals[index_sibling % 2] = step.sibling_value;
+                    let mut xs = [x; 2];
+                    xs[index_sibling % 2] *= Val::two_adic_generator(1);
+                    folded_eval = evals[0]
+                        + (*beta - xs[0]) * (evals[1] - evals[0]) * (xs[1] - xs[0]).inverse();
+
+                    index >>= 1;
+                    x = x.square();
+                }
+                folded_evals
+            })
+            .collect();
+
+        Self {
+            query_samples,
+            folded_evals,
+        }
+    }
+}
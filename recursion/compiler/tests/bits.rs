@@ -0,0 +1,33 @@
+use p3_field::{AbstractField, PrimeField32};
+use sp1_core::stark::StarkGenericConfig;
+use sp1_core::utils::BabyBearPoseidon2;
+use sp1_recursion_compiler::asm::VmBuilder;
+use sp1_recursion_compiler::prelude::*;
+use sp1_recursion_core::num2bits::NUM_BITS;
+use sp1_recursion_core::runtime::Runtime;
+
+#[test]
+fn test_compiler_num2bits() {
+    type SC = BabyBearPoseidon2;
+    type F = <SC as StarkGenericConfig>::Val;
+    type EF = <SC as StarkGenericConfig>::Challenge;
+    let mut builder = VmBuilder::<F, EF>::default();
+
+    for value in [F::zero(), F::from_canonical_u32(0x1234_5678), F::neg_one()] {
+        let num: Felt<_> = builder.eval(value);
+        let bits = builder.num2bits_f(num);
+        assert_eq!(bits.len(), NUM_BITS);
+        for (i, bit) in bits.iter().enumerate() {
+            let expected = F::from_canonical_u32((value.as_canonical_u32() >> i) & 1);
+            builder.assert_felt_eq(*bit, expected);
+        }
+
+        let recomposed = builder.bits2num_f(&bits);
+        builder.assert_felt_eq(recomposed, num);
+    }
+
+    let program = builder.compile();
+
+    let mut runtime = Runtime::<F, EF>::new(&program);
+    runtime.run();
+}
@@ -0,0 +1,168 @@
+use p3_field::{AbstractExtensionField, AbstractField};
+use sp1_core::stark::{LocalProver, StarkGenericConfig};
+use sp1_core::utils::{BabyBearPoseidon2, StarkUtils};
+use sp1_recursion_compiler::asm::VmBuilder;
+use sp1_recursion_compiler::prelude::*;
+use sp1_recursion_core::runtime::Runtime;
+use sp1_recursion_core::stark::RecursionAir;
+
+type SC = BabyBearPoseidon2;
+type F = <SC as StarkGenericConfig>::Val;
+type EF = <SC as StarkGenericConfig>::Challenge;
+
+#[test]
+fn test_compiler_functions() {
+    let mut builder = VmBuilder::<F, EF>::default();
+
+    // Computes `(a + b, a * b)`.
+    let sum_product = builder.function("sum_product", |builder, (a, b): (Felt<_>, Felt<_>)| {
+        let sum: Felt<_> = builder.eval(a + b);
+        let product: Felt<_> = builder.eval(a * b);
+        (sum, product)
+    });
+
+    let mul = builder.function(
+        "mul",
+        |builder, (a, b): (Ext<_, _>, Ext<_, _>)| -> Ext<_, _> { builder.eval(a * b) },
+    );
+
+    // Computes `x^n` with a loop, calling another function.
+    let power = builder.function("power", |builder, (x, n): (Ext<_, _>, Var<_>)| {
+        let result: Ext<_, _> = builder.eval(EF::one());
+        builder.range(0, n).for_each(|_, builder| {
+            let product = builder.call(&mul, (result, x));
+            builder.assign(result, product);
+        });
+        result
+    });
+
+    // Fills an allocated array with `value`, so that the heap pointer is passed back and forth.
+    let fill = builder.function("fill", |builder, (value, len): (Felt<_>, Var<_>)| {
+        let array: Vector<_, Felt<_>> = builder.vec(len);
+        builder.range(0, len).for_each(|i, builder| {
+            builder.store(array.ptr, i, value);
+        });
+        array.ptr
+    });
+
+    let a: Felt<_> = builder.eval(F::from_canonical_u32(3));
+    let b: Felt<_> = builder.eval(F::from_canonical_u32(5));
+    let (sum, product) = builder.call(&sum_product, (a, b));
+    builder.assert_felt_eq(sum, F::from_canonical_u32(8));
+    builder.assert_felt_eq(product, F::from_canonical_u32(15));
+    // The arguments are left untouched by the call.
+    builder.assert_felt_eq(a, F::from_canonical_u32(3));
+    builder.assert_felt_eq(b, F::from_canonical_u32(5));
+
+    let (sum, product) = builder.call(&sum_product, (sum, product));
+    builder.assert_felt_eq(sum, F::from_canonical_u32(23));
+    builder.assert_felt_eq(product, F::from_canonical_u32(120));
+
+    let x_value = EF::from_base_slice(&[F::one(), F::two(), F::zero(), F::one()]);
+    let x: Ext<_, _> = builder.eval(x_value);
+    for n in 1..4 {
+        let n_var: Var<_> = builder.eval(F::from_canonical_u32(n));
+        let result = builder.call(&power, (x, n_var));
+        builder.assert_ext_eq(result, x_value.exp_u64(n as u64));
+    }
+
+    let len: Var<_> = builder.eval(F::from_canonical_u32(4));
+    let first = builder.call(&fill, (a, len));
+    let second = builder.call(&fill, (b, len));
+    let other: Vector<_, Felt<_>> = builder.vec(len);
+    builder.store(other.ptr, 0, product);
+    for i in 0..4 {
+        let value: Felt<_> = builder.uninit();
+        builder.load(value, first, i);
+        builder.assert_felt_eq(value, a);
+        builder.load(value, second, i);
+        builder.assert_felt_eq(value, b);
+    }
+    // The allocations of the calls are not overwritten by the caller.
+    let value: Felt<_> = builder.uninit();
+    builder.load(value, first, 3);
+    builder.assert_felt_eq(value, a);
+
+    let code = builder.compile_to_asm();
+    println!("{}", code);
+
+    let program = code.machine_code();
+    let mut runtime = Runtime::<F, EF>::new(&program);
+    runtime.run();
+}
+
+#[test]
+fn test_compiler_function_after_variables() {
+    let mut builder = VmBuilder::<F, EF>::default();
+
+    // The body of a function defined after variables of the caller does not overwrite them.
+    let a: Felt<_> = builder.eval(F::from_canonical_u32(3));
+    let b: Felt<_> = builder.eval(F::from_canonical_u32(5));
+    let double = builder.function("double", |builder, x: Felt<_>| -> Felt<_> {
+        let y: Felt<_> = builder.eval(F::from_canonical_u32(7));
+        builder.eval(x + x + y - y)
+    });
+    let result = builder.call(&double, b);
+    builder.assert_felt_eq(result, F::from_canonical_u32(10));
+    builder.assert_felt_eq(a, F::from_canonical_u32(3));
+    builder.assert_felt_eq(b, F::from_canonical_u32(5));
+
+    let program = builder.compile_to_asm().machine_code();
+    let mut runtime = Runtime::<F, EF>::new(&program);
+    runtime.run();
+}
+
+#[test]
+fn test_compiler_functions_prove() {
+    let mut builder = VmBuilder::<F, EF>::default();
+
+    // A function calling another one, so that the proof covers nested calls and returns.
+    let double = builder.function("double", |builder, x: Felt<_>| -> Felt<_> {
+        builder.eval(x + x)
+    });
+    let quadruple = builder.function("quadruple", |builder, x: Felt<_>| -> Felt<_> {
+        let y = builder.call(&double, x);
+        builder.call(&double, y)
+    });
+
+    let a: Felt<_> = builder.eval(F::from_canonical_u32(3));
+    let b = builder.call(&quadruple, a);
+    let c = builder.call(&double, b);
+    builder.assert_felt_eq(c, F::from_canonical_u32(24));
+    builder.assert_felt_eq(a, F::from_canonical_u32(3));
+    builder.commit_public_value(c);
+    let program = builder.compile();
+
+    let mut runtime = Runtime::<F, EF>::new(&program);
+    runtime.run();
+
+    let machine = RecursionAir::machine(SC::new());
+    let (pk, vk) = machine.setup(&program);
+    let mut challenger = machine.config().challenger();
+    let proof = machine.prove::<LocalProver<_, _>>(&pk, runtime.record, &mut challenger);
+    let mut challenger = machine.config().challenger();
+    machine
+        .verify(&vk, &proof, &mut challenger)
+        .expect("the proof of the calls should verify");
+    assert_eq!(proof.public_values, vec![F::from_canonical_u32(24)]);
+}
+
+#[test]
+#[should_panic(expected = "uses the Felt 0 of its caller")]
+fn test_compiler_function_captures_variable() {
+    let mut builder = VmBuilder::<F, EF>::default();
+
+    let a: Felt<_> = builder.eval(F::from_canonical_u32(3));
+    builder.function("add_a", |builder, x: Felt<_>| -> Felt<_> {
+        builder.eval(x + a)
+    });
+}
+
+#[test]
+#[should_panic(expected = "uses the Var 0 of its caller")]
+fn test_compiler_function_returns_variable() {
+    let mut builder = VmBuilder::<F, EF>::default();
+
+    let n: Var<_> = builder.eval(F::from_canonical_u32(3));
+    builder.function("get_n", |_, _: ()| n);
+}
@@ -0,0 +1,47 @@
+use p3_field::{AbstractExtensionField, AbstractField};
+use sp1_core::stark::StarkGenericConfig;
+use sp1_core::utils::BabyBearPoseidon2;
+use sp1_recursion_compiler::asm::VmBuilder;
+use sp1_recursion_compiler::prelude::*;
+use sp1_recursion_core::runtime::Runtime;
+
+#[test]
+fn test_compiler_hint_vec() {
+    type SC = BabyBearPoseidon2;
+    type F = <SC as StarkGenericConfig>::Val;
+    type EF = <SC as StarkGenericConfig>::Challenge;
+
+    let felts = (0..5).map(F::from_canonical_u32).collect::<Vec<_>>();
+    let exts = (0..3)
+        .map(|i| EF::from_base_slice(&[F::from_canonical_u32(i), F::one(), F::two(), F::zero()]))
+        .collect::<Vec<_>>();
+
+    // The hinted values are only supplied to the runtime, after the program is compiled.
+    let mut builder = VmBuilder::<F, EF>::default();
+    let felt_vec = builder.hint_vec::<Felt<_>>();
+    builder.hint_vec::<Felt<_>>();
+    let ext_vec = builder.hint_vec::<Ext<_, _>>();
+
+    for (i, value) in felts.iter().enumerate() {
+        let hinted = builder.get(&felt_vec, i);
+        builder.assert_felt_eq(hinted, *value);
+    }
+    for (i, value) in exts.iter().enumerate() {
+        let hinted = builder.get(&ext_vec, i);
+        builder.assert_ext_eq(hinted, *value);
+    }
+    let hinted: Felt<_> = builder.hint();
+    builder.assert_felt_eq(hinted, F::from_canonical_u32(7));
+
+    let program = builder.compile();
+
+    let mut runtime = Runtime::<F, EF>::new(&program);
+    runtime.write_felts(&felts);
+    runtime.write_felts(&[]);
+    runtime.write_exts(&exts);
+    runtime
+        .witness_stream
+        .push_back(F::from_canonical_u32(7).into());
+    runtime.run();
+    assert!(runtime.witness_stream.is_empty());
+}
@@ -0,0 +1,115 @@
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_commit::{Pcs, UnivariatePcs};
+use p3_field::{AbstractExtensionField, TwoAdicField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::{Dimensions, Matrix};
+use rand::{thread_rng, Rng};
+use sp1_core::stark::StarkGenericConfig;
+use sp1_core::utils::{BabyBearPoseidon2, StarkUtils};
+use sp1_recursion_compiler::asm::VmBuilder;
+use sp1_recursion_compiler::prelude::*;
+use sp1_recursion_compiler::verifier::{
+    digest_variable, verify_two_adic_pcs, DuplexChallengerVariable, PcsWitness,
+    TwoAdicPcsProofVariable,
+};
+use sp1_recursion_core::runtime::Runtime;
+
+#[test]
+fn test_verify_two_adic_pcs() {
+    type SC = BabyBearPoseidon2;
+    type F = <SC as StarkGenericConfig>::Val;
+    type EF = <SC as StarkGenericConfig>::Challenge;
+    let config = SC::new();
+    let mut rng = thread_rng();
+
+    // Commit to a batch of random traces and open them at a random point and its shift.
+    let log_degrees = [3, 4];
+    let traces = log_degrees
+        .iter()
+        .zip([3, 5])
+        .map(|(log_degree, width)| {
+            let values = (0..width << log_degree).map(|_| rng.gen::<F>()).collect();
+            RowMajorMatrix::new(values, width)
+        })
+        .collect::<Vec<_>>();
+    let dims: Vec<Vec<Dimensions>> = vec![traces.iter().map(|trace| trace.dimensions()).collect()];
+    let (commit, data) = config.pcs().commit_batches(traces);
+
+    let mut challenger = config.challenger();
+    challenger.observe(commit.clone());
+    let zeta: EF = challenger.sample_ext_element();
+    let points = log_degrees
+        .iter()
+        .map(|log_degree| vec![zeta, zeta * F::two_adic_generator(*log_degree)])
+        .collect::<Vec<_>>();
+    let (values, proof) = config
+        .pcs()
+        .open_multi_batches(&[(&data, points.as_slice())], &mut challenger);
+
+    // Verify the proof natively, and replay the verification to generate the witness.
+    let mut challenger = config.challenger();
+    challenger.observe(commit.clone());
+    let _: EF = challenger.sample_ext_element();
+    let mut witness_challenger = challenger.clone();
+    let commits_and_points = [(commit.clone(), points.as_slice())];
+    config
+        .pcs()
+        .verify_multi_batches(
+            &commits_and_points,
+            &dims,
+            values.clone(),
+            &proof,
+            &mut challenger,
+        )
+        .expect("the proof should verify natively");
+    let witness = PcsWitness::generate(
+        &commits_and_points,
+        &dims,
+        &values,
+        &proof,
+        &mut witness_challenger,
+    );
+
+    // Verify the proof in the VM.
+    let mut builder = VmBuilder::<F, EF>::default();
+    let mut challenger = DuplexChallengerVariable::new(&mut builder);
+    let commit = digest_variable(&mut builder, commit);
+    challenger.observe_commitment(&mut builder, &commit);
+    let zeta_var = challenger.sample_ext(&mut builder);
+    builder.assert_ext_eq(zeta_var, zeta);
+
+    let points = log_degrees
+        .iter()
+        .map(|log_degree| {
+            let shift = EF::from_base(F::two_adic_generator(*log_degree));
+            vec![zeta_var, builder.eval(zeta_var * shift)]
+        })
+        .collect::<Vec<_>>();
+    let values = values
+        .iter()
+        .map(|batch| {
+            batch
+                .iter()
+                .map(|mat| {
+                    mat.iter()
+                        .map(|point| point.iter().map(|value| builder.eval(*value)).collect())
+                        .collect()
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<Vec<Vec<Ext<_, _>>>>>>();
+    let proof = TwoAdicPcsProofVariable::new(&mut builder, &proof, &witness);
+    verify_two_adic_pcs(
+        &mut builder,
+        &[(commit, points)],
+        &dims,
+        &values,
+        &proof,
+        &mut challenger,
+    );
+
+    let program = builder.compile();
+
+    let mut runtime = Runtime::<F, EF>::new(&program);
+    runtime.run();
+}
@@ -0,0 +1,499 @@
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sp1_core::stark::StarkGenericConfig;
+use sp1_core::utils::BabyBearPoseidon2;
+use sp1_recursion_compiler::asm::{AsmConfig, VmBuilder};
+use sp1_recursion_compiler::gnark::{GnarkBackend, GnarkError};
+use sp1_recursion_compiler::prelude::*;
+use sp1_recursion_core::air::Block;
+use sp1_recursion_core::poseidon2::{permute, WIDTH};
+use sp1_recursion_core::runtime::{Program, Runtime};
+
+type SC = BabyBearPoseidon2;
+type F = <SC as StarkGenericConfig>::Val;
+type EF = <SC as StarkGenericConfig>::Challenge;
+
+const NUM_PROGRAMS: u64 = 16;
+const NUM_INSTRUCTIONS: usize = 256;
+
+/// The variables of a program along with their expected values.
+type Pools = (Vec<(Var<F>, F)>, Vec<(Felt<F>, F)>, Vec<(Ext<F, EF>, EF)>);
+
+/// A random DSL program, along with the expected values of its variables computed directly with
+/// the field operations of the instructions.
+///
+/// The initial values are read from the witness stream, so that the optimizer cannot fold them.
+struct RandomProgram {
+    builder: VmBuilder<F, EF>,
+    inputs: Vec<Block<F>>,
+    vars: Vec<(Var<F>, F)>,
+    felts: Vec<(Felt<F>, F)>,
+    exts: Vec<(Ext<F, EF>, EF)>,
+}
+
+impl RandomProgram {
+    fn new(rng: &mut StdRng) -> Self {
+        let mut program = Self {
+            builder: VmBuilder::default(),
+            inputs: vec![],
+            vars: vec![],
+            felts: vec![],
+            exts: vec![],
+        };
+        for _ in 0..4 {
+            let value = rng.gen::<F>();
+            let var = program.builder.hint();
+            program.inputs.push(value.into());
+            program.vars.push((var, value));
+
+            let value = rng.gen::<F>();
+            let felt = program.builder.hint();
+            program.inputs.push(value.into());
+            program.felts.push((felt, value));
+
+            let value = rng.gen::<EF>();
+            let ext = program.builder.hint();
+            program.inputs.push(value.as_base_slice().into());
+            program.exts.push((ext, value));
+        }
+        program
+    }
+
+    fn var(&self, rng: &mut StdRng) -> (Var<F>, F) {
+        self.vars[rng.gen_range(0..self.vars.len())]
+    }
+
+    fn felt(&self, rng: &mut StdRng) -> (Felt<F>, F) {
+        self.felts[rng.gen_range(0..self.felts.len())]
+    }
+
+    fn ext(&self, rng: &mut StdRng) -> (Ext<F, EF>, EF) {
+        self.exts[rng.gen_range(0..self.exts.len())]
+    }
+
+    /// Either overwrites a random variable of the pool or allocates a new one.
+    fn var_dst(&mut self, rng: &mut StdRng) -> (Var<F>, Option<usize>) {
+        if rng.gen_bool(0.25) {
+            let index = rng.gen_range(0..self.vars.len());
+            (self.vars[index].0, Some(index))
+        } else {
+            (self.builder.uninit(), None)
+        }
+    }
+
+    fn felt_dst(&mut self, rng: &mut StdRng) -> (Felt<F>, Option<usize>) {
+        if rng.gen_bool(0.25) {
+            let index = rng.gen_range(0..self.felts.len());
+            (self.felts[index].0, Some(index))
+        } else {
+            (self.builder.uninit(), None)
+        }
+    }
+
+    fn ext_dst(&mut self, rng: &mut StdRng) -> (Ext<F, EF>, Option<usize>) {
+        if rng.gen_bool(0.25) {
+            let index = rng.gen_range(0..self.exts.len());
+            (self.exts[index].0, Some(index))
+        } else {
+            (self.builder.uninit(), None)
+        }
+    }
+
+    fn push_var_instruction(&mut self, rng: &mut StdRng) {
+        let (lhs, lhs_value) = self.var(rng);
+        let (rhs, rhs_value) = self.var(rng);
+        let imm = rng.gen::<F>();
+        let (dst, index) = self.var_dst(rng);
+        let (instruction, value) = match rng.gen_range(0..9) {
+            0 => (DslIR::AddV(dst, lhs, rhs), lhs_value + rhs_value),
+            1 => (DslIR::AddVI(dst, lhs, imm), lhs_value + imm),
+            2 => (DslIR::SubV(dst, lhs, rhs), lhs_value - rhs_value),
+            3 => (DslIR::SubVI(dst, lhs, imm), lhs_value - imm),
+            4 => (DslIR::SubVIN(dst, imm, rhs), imm - rhs_value),
+            5 => (DslIR::MulV(dst, lhs, rhs), lhs_value * rhs_value),
+            6 => (DslIR::MulVI(dst, lhs, imm), lhs_value * imm),
+            7 => (DslIR::NegV(dst, lhs), -lhs_value),
+            _ if lhs_value.is_zero() => return,
+            _ => (DslIR::InvV(dst, lhs), lhs_value.inverse()),
+        };
+        self.builder.push(instruction);
+        match index {
+            Some(index) => self.vars[index].1 = value,
+            None => self.vars.push((dst, value)),
+        }
+    }
+
+    fn push_felt_instruction(&mut self, rng: &mut StdRng) {
+        let (lhs, lhs_value) = self.felt(rng);
+        let (rhs, rhs_value) = self.felt(rng);
+        let imm = rng.gen::<F>();
+        let (dst, index) = self.felt_dst(rng);
+        let (instruction, value) = match rng.gen_range(0..12) {
+            0 => (DslIR::AddF(dst, lhs, rhs), lhs_value + rhs_value),
+            1 => (DslIR::AddFI(dst, lhs, imm), lhs_value + imm),
+            2 => (DslIR::SubF(dst, lhs, rhs), lhs_value - rhs_value),
+            3 => (DslIR::SubFI(dst, lhs, imm), lhs_value - imm),
+            4 => (DslIR::SubFIN(dst, imm, rhs), imm - rhs_value),
+            5 => (DslIR::MulF(dst, lhs, rhs), lhs_value * rhs_value),
+            6 => (DslIR::MulFI(dst, lhs, imm), lhs_value * imm),
+            7 => (DslIR::NegF(dst, lhs), -lhs_value),
+            _ if rhs_value.is_zero() || imm.is_zero() => return,
+            8 => (DslIR::DivF(dst, lhs, rhs), lhs_value / rhs_value),
+            9 => (DslIR::DivFI(dst, lhs, imm), lhs_value / imm),
+            10 => (DslIR::DivFIN(dst, imm, rhs), imm / rhs_value),
+            _ => (DslIR::InvF(dst, rhs), rhs_value.inverse()),
+        };
+        self.builder.push(instruction);
+        match index {
+            Some(index) => self.felts[index].1 = value,
+            None => self.felts.push((dst, value)),
+        }
+    }
+
+    fn push_ext_instruction(&mut self, rng: &mut StdRng) {
+        let (lhs, lhs_value) = self.ext(rng);
+        let (rhs, rhs_value) = self.ext(rng);
+        let (felt, felt_value) = self.felt(rng);
+        let imm = rng.gen::<EF>();
+        let felt_imm = rng.gen::<F>();
+        let (dst, index) = self.ext_dst(rng);
+        let (instruction, value) = match rng.gen_range(0..23) {
+            0 => (DslIR::AddE(dst, lhs, rhs), lhs_value + rhs_value),
+            1 => (DslIR::AddEI(dst, lhs, imm), lhs_value + imm),
+            2 => (DslIR::AddEF(dst, lhs, felt), lhs_value + felt_value),
+            3 => (DslIR::AddEFI(dst, lhs, felt_imm), lhs_value + felt_imm),
+            4 => (
+                DslIR::AddEFFI(dst, felt, imm),
+                EF::from_base(felt_value) + imm,
+            ),
+            5 => (DslIR::SubE(dst, lhs, rhs), lhs_value - rhs_value),
+            6 => (DslIR::SubEI(dst, lhs, imm), lhs_value - imm),
+            7 => (DslIR::SubEIN(dst, imm, rhs), imm - rhs_value),
+            8 => (DslIR::SubEF(dst, lhs, felt), lhs_value - felt_value),
+            9 => (DslIR::SubEFI(dst, lhs, felt_imm), lhs_value - felt_imm),
+            10 => (
+                DslIR::SubEFIN(dst, felt_imm, rhs),
+                EF::from_base(felt_imm) - rhs_value,
+            ),
+            11 => (DslIR::MulE(dst, lhs, rhs), lhs_value * rhs_value),
+            12 => (DslIR::MulEI(dst, lhs, imm), lhs_value * imm),
+            13 => (DslIR::MulEF(dst, lhs, felt), lhs_value * felt_value),
+            14 => (DslIR::MulEFI(dst, lhs, felt_imm), lhs_value * felt_imm),
+            15 => (DslIR::NegE(dst, lhs), -lhs_value),
+            _ if rhs_value.is_zero()
+                || felt_value.is_zero()
+                || imm.is_zero()
+                || felt_imm.is_zero() =>
+            {
+                return
+            }
+            16 => (DslIR::DivE(dst, lhs, rhs), lhs_value / rhs_value),
+            17 => (DslIR::DivEI(dst, lhs, imm), lhs_value / imm),
+            18 => (DslIR::DivEIN(dst, imm, rhs), imm / rhs_value),
+            19 => (DslIR::DivEF(dst, lhs, felt), lhs_value / felt_value),
+            20 => (
+                DslIR::DivEFI(dst, lhs, felt_imm),
+                lhs_value / EF::from_base(felt_imm),
+            ),
+            21 => (
+                DslIR::DivEFIN(dst, felt_imm, rhs),
+                EF::from_base(felt_imm) / rhs_value,
+            ),
+            _ => (DslIR::InvE(dst, rhs), rhs_value.inverse()),
+        };
+        self.builder.push(instruction);
+        match index {
+            Some(index) => self.exts[index].1 = value,
+            None => self.exts.push((dst, value)),
+        }
+    }
+
+    fn push_arithmetic_instruction(&mut self, rng: &mut StdRng) {
+        match rng.gen_range(0..3) {
+            0 => self.push_var_instruction(rng),
+            1 => self.push_felt_instruction(rng),
+            _ => self.push_ext_instruction(rng),
+        }
+    }
+
+    /// Records random arithmetic instructions for the body of a block, returning them along with
+    /// the pools of variables after the block, and restoring the pools from before it. A block
+    /// which is not taken also asserts a false equality, which fails if the block runs.
+    fn record_block(
+        &mut self,
+        rng: &mut StdRng,
+        taken: bool,
+    ) -> (Vec<DslIR<AsmConfig<F, EF>>>, Pools) {
+        let outer = std::mem::take(&mut self.builder.operations);
+        let pools = (self.vars.clone(), self.felts.clone(), self.exts.clone());
+        for _ in 0..rng.gen_range(1..8) {
+            self.push_arithmetic_instruction(rng);
+        }
+        if !taken {
+            let (var, value) = self.var(rng);
+            self.builder.push(DslIR::AssertEqVI(var, value + F::one()));
+        }
+        let operations = std::mem::replace(&mut self.builder.operations, outer);
+        let (vars, felts, exts) = pools;
+        let block_pools = (
+            std::mem::replace(&mut self.vars, vars),
+            std::mem::replace(&mut self.felts, felts),
+            std::mem::replace(&mut self.exts, exts),
+        );
+        (operations, block_pools)
+    }
+
+    /// Branches on a random comparison of variables, keeping the values computed by the branch
+    /// which is taken.
+    fn push_conditional(&mut self, rng: &mut StdRng) {
+        let (lhs, lhs_value) = self.var(rng);
+        // Comparing a variable with itself makes sure that both branches are taken.
+        let (rhs, rhs_value) = if rng.gen_bool(0.5) {
+            (lhs, lhs_value)
+        } else {
+            self.var(rng)
+        };
+        let imm = if rng.gen_bool(0.5) {
+            lhs_value
+        } else {
+            rng.gen::<F>()
+        };
+        let kind = rng.gen_range(0..4);
+        let is_eq = match kind {
+            0 => lhs_value == rhs_value,
+            1 => lhs_value != rhs_value,
+            2 => lhs_value == imm,
+            _ => lhs_value != imm,
+        };
+
+        let (then_branch, then_pools) = self.record_block(rng, is_eq);
+        let (else_branch, else_pools) = self.record_block(rng, !is_eq);
+        let instruction = match kind {
+            0 => DslIR::IfEq(lhs, rhs, then_branch, else_branch),
+            1 => DslIR::IfNe(lhs, rhs, then_branch, else_branch),
+            2 => DslIR::IfEqI(lhs, imm, then_branch, else_branch),
+            _ => DslIR::IfNeI(lhs, imm, then_branch, else_branch),
+        };
+        self.builder.push(instruction);
+        (self.vars, self.felts, self.exts) = if is_eq { then_pools } else { else_pools };
+    }
+
+    /// Runs random instructions in a single iteration of a loop with constant bounds, by
+    /// branching on the loop counter.
+    fn push_loop(&mut self, rng: &mut StdRng) {
+        let end = rng.gen_range(1..8);
+        let iteration = F::from_canonical_usize(rng.gen_range(0..end));
+        let (body, pools) = self.record_block(rng, true);
+        let counter: Var<F> = self.builder.uninit();
+        self.builder.push(DslIR::For(
+            Usize::Const(0),
+            Usize::Const(end),
+            counter,
+            vec![DslIR::IfEqI(counter, iteration, body, vec![])],
+        ));
+        (self.vars, self.felts, self.exts) = pools;
+    }
+
+    /// Sums the counter of a loop with constant bounds.
+    fn push_const_loop(&mut self, rng: &mut StdRng) {
+        let end = rng.gen_range(1..8);
+        let sum: Var<F> = self.builder.eval(F::zero());
+        self.builder.range(0, end).for_each(|i, builder| {
+            builder.assign(sum, sum + i);
+        });
+        self.vars
+            .push((sum, F::from_canonical_usize(end * (end - 1) / 2)));
+    }
+
+    /// Copies random felts through memory, with a loop with variable bounds.
+    fn push_memory_copy(&mut self, rng: &mut StdRng) {
+        let len = rng.gen_range(1..8);
+        let values = (0..len).map(|_| self.felt(rng)).collect::<Vec<_>>();
+        let src = self.builder.vec::<Felt<F>, _>(len).ptr;
+        let dst = self.builder.vec::<Felt<F>, _>(len).ptr;
+        for (i, (felt, _)) in values.iter().enumerate() {
+            self.builder.store(src, i, *felt);
+        }
+        let end: Var<F> = self.builder.eval(F::from_canonical_usize(len));
+        self.builder.range(0, end).for_each(|i, builder| {
+            let value: Felt<F> = builder.uninit();
+            builder.load(value, src, i);
+            builder.store(dst, i, value);
+        });
+        for (i, (_, value)) in values.into_iter().enumerate() {
+            let felt: Felt<F> = self.builder.uninit();
+            self.builder.load(felt, dst, i);
+            self.felts.push((felt, value));
+        }
+    }
+
+    /// Asserts that every variable holds its expected value.
+    fn assert_values(&mut self) {
+        for (var, value) in self.vars.iter() {
+            self.builder.push(DslIR::AssertEqVI(*var, *value));
+        }
+        for (felt, value) in self.felts.iter() {
+            self.builder.push(DslIR::AssertEqFI(*felt, *value));
+        }
+        for (ext, value) in self.exts.iter() {
+            self.builder.push(DslIR::AssertEqEI(*ext, *value));
+        }
+    }
+}
+
+/// Generates a random program. Memory instructions are only generated with `with_memory`, as the
+/// gnark backend requires constant memory offsets.
+fn random_program(seed: u64, with_memory: bool) -> RandomProgram {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut program = RandomProgram::new(&mut rng);
+    for _ in 0..NUM_INSTRUCTIONS {
+        match rng.gen_range(0..22) {
+            0..=4 => program.push_var_instruction(&mut rng),
+            5..=9 => program.push_felt_instruction(&mut rng),
+            10..=17 => program.push_ext_instruction(&mut rng),
+            18 => program.push_const_loop(&mut rng),
+            19 => program.push_conditional(&mut rng),
+            20 => program.push_loop(&mut rng),
+            _ if with_memory => program.push_memory_copy(&mut rng),
+            _ => {}
+        }
+    }
+    program.assert_values();
+    program
+}
+
+/// Checks that the emitted Go is well-formed: its brackets are balanced, and every line of the
+/// circuit is a declaration, an assignment, an assertion, a call or a block delimiter.
+fn assert_well_formed(circuit: &str) {
+    let mut open = vec![];
+    for c in circuit.chars() {
+        match c {
+            '(' | '[' | '{' => open.push(c),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                assert_eq!(
+                    open.pop(),
+                    Some(expected),
+                    "unbalanced brackets in the circuit"
+                );
+            }
+            _ => {}
+        }
+    }
+    assert!(open.is_empty(), "unclosed brackets in the circuit");
+
+    let start = circuit.find("fieldChip := babybear.NewChip(api)").unwrap();
+    let end = circuit.find("\treturn nil").unwrap();
+    for line in circuit[start..end].lines().skip(1).map(str::trim) {
+        let is_statement = line.is_empty()
+            || line.starts_with("//")
+            || line.starts_with("var ")
+            || line.starts_with("return ")
+            || line == "{"
+            || line == "}"
+            || ((line.starts_with("for ") || line.starts_with("if ")) && line.ends_with(" {"))
+            || line.starts_with("api.Assert")
+            || line.starts_with("fieldChip.Assert")
+            || line.starts_with("fn_")
+            || is_assignment(line);
+        assert!(is_statement, "unexpected line in the circuit: {line}");
+    }
+}
+
+/// Whether `line` assigns an expression to variables, slice elements or new variables.
+fn is_assignment(line: &str) -> bool {
+    let Some((dst, expr)) = line.split_once(" = ").or_else(|| line.split_once(" := ")) else {
+        return false;
+    };
+    let is_operand = |operand: &str| {
+        operand.starts_with(|c: char| c.is_ascii_alphabetic())
+            && operand
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_.,()[] ".contains(c))
+    };
+    !expr.is_empty() && is_operand(dst)
+}
+
+fn run(program: &Program<F>, inputs: &[Block<F>]) {
+    let mut runtime = Runtime::<F, EF>::new(program);
+    runtime.witness_stream.extend(inputs.iter().copied());
+    runtime.run();
+}
+
+#[test]
+fn test_compiler_random_programs() {
+    for seed in 0..NUM_PROGRAMS {
+        let program = random_program(seed, true);
+
+        // The program runs both as built and optimized.
+        let mut compiler = AsmCompiler::new();
+        compiler.build(program.builder.operations.clone());
+        run(&compiler.compile(), &program.inputs);
+
+        let mut builder = program.builder;
+        let report = builder.optimize();
+        assert!(report.after <= report.before);
+        run(&builder.compile(), &program.inputs);
+    }
+}
+
+#[test]
+fn test_compiler_random_programs_gnark() {
+    for seed in 0..NUM_PROGRAMS {
+        let program = random_program(seed, false);
+        let mut backend = GnarkBackend::<AsmConfig<F, EF>>::default();
+        let circuit = backend.compile(program.builder.operations.clone()).unwrap();
+        assert!(circuit.contains("fieldChip.AssertEqExtension"));
+        assert_well_formed(&circuit);
+
+        // The same program also runs on the recursion runtime.
+        run(&program.builder.compile(), &program.inputs);
+    }
+}
+
+#[test]
+fn test_compiler_gnark_variable_loop_bounds() {
+    // The bound of the loop is only known when the program runs.
+    let mut builder = VmBuilder::<F, EF>::default();
+    let end: Var<F> = builder.hint();
+    let sum: Var<F> = builder.eval(F::zero());
+    builder.range(0, end).for_each(|i, builder| {
+        builder.assign(sum, sum + i);
+    });
+    builder.assert_var_eq(sum, F::from_canonical_usize(6));
+
+    let mut backend = GnarkBackend::<AsmConfig<F, EF>>::default();
+    assert_eq!(
+        backend.compile(builder.operations.clone()),
+        Err(GnarkError::VariableLoopBounds)
+    );
+    run(&builder.compile(), &[F::from_canonical_usize(4).into()]);
+}
+
+#[test]
+fn test_compiler_gnark_poseidon2() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let input: [F; WIDTH] = core::array::from_fn(|_| rng.gen());
+    let expected = permute(input);
+
+    let mut builder = VmBuilder::<F, EF>::default();
+    let state = (0..WIDTH).map(|_| builder.hint()).collect::<Vec<Felt<F>>>();
+    let output = builder.poseidon2_permute(&Slice::Fixed(state));
+    for (i, value) in expected.iter().enumerate() {
+        let felt: Felt<F> = builder.get(&output, i);
+        builder.assert_felt_eq(felt, *value);
+    }
+
+    let mut backend = GnarkBackend::<AsmConfig<F, EF>>::default();
+    let circuit = backend.compile(builder.operations.clone()).unwrap();
+    assert!(circuit.contains("poseidon2RoundConstants := [][16]int{"));
+    assert!(circuit.contains("fieldChip.Poseidon2Permute(input, poseidon2RoundConstants"));
+    assert_well_formed(&circuit);
+    let inputs = input.iter().map(|&value| value.into()).collect::<Vec<_>>();
+    run(&builder.compile(), &inputs);
+}
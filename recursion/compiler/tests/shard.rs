@@ -0,0 +1,140 @@
+use p3_field::AbstractField;
+use sp1_core::runtime::{Instruction, Opcode, Program as CoreProgram, Runtime as CoreRuntime};
+use sp1_core::stark::{LocalProver, Proof, RiscvAir, StarkGenericConfig};
+use sp1_core::utils::{BabyBearPoseidon2, ProgressMonitor, ProverOptions, StarkUtils};
+use sp1_recursion_compiler::asm::VmBuilder;
+use sp1_recursion_compiler::prelude::*;
+use sp1_recursion_compiler::verifier::{
+    digest_variable, read_proof, verify_proof, write_proof, ShardShape,
+};
+use sp1_recursion_core::runtime::Runtime;
+use sp1_recursion_core::stark::RecursionAir;
+
+type SC = BabyBearPoseidon2;
+type F = <SC as StarkGenericConfig>::Val;
+type EF = <SC as StarkGenericConfig>::Challenge;
+
+#[test]
+fn test_verify_recursion_proof() {
+    // Prove a small fibonacci program with the recursion machine, which commits its result.
+    let mut builder = VmBuilder::<F, EF>::default();
+    let a: Felt<_> = builder.eval(F::zero());
+    let b: Felt<_> = builder.eval(F::one());
+    for _ in 0..10 {
+        let c: Felt<_> = builder.eval(a + b);
+        builder.assign(a, b);
+        builder.assign(b, c);
+    }
+    builder.assert_felt_eq(b, F::from_canonical_u32(89));
+    builder.commit_public_value(b);
+    let program = builder.compile();
+
+    let mut runtime = Runtime::<F, EF>::new(&program);
+    runtime.run();
+
+    let machine = RecursionAir::machine(SC::new());
+    let (pk, vk) = machine.setup(&program);
+    let mut challenger = machine.config().challenger();
+    let proof = machine.prove::<LocalProver<_, _>>(&pk, runtime.record, &mut challenger);
+    let mut challenger = machine.config().challenger();
+    machine
+        .verify(&vk, &proof, &mut challenger)
+        .expect("the proof should verify natively");
+    assert_eq!(proof.public_values, vec![F::from_canonical_u32(89)]);
+
+    // Verify the proof in the VM, against the verifying key of the program.
+    let shapes = proof
+        .shard_proofs
+        .iter()
+        .map(ShardShape::of)
+        .collect::<Vec<_>>();
+    let mut builder = VmBuilder::<F, EF>::default();
+    let preprocessed_commit = vk
+        .commit
+        .clone()
+        .map(|commit| digest_variable(&mut builder, commit));
+    let result: Felt<_> = builder.eval(F::from_canonical_u32(89));
+    let public_values = vec![result];
+    let shard_proofs = read_proof(&mut builder, machine.chips(), &shapes);
+    verify_proof(
+        &mut builder,
+        machine.chips(),
+        preprocessed_commit.as_ref(),
+        &public_values,
+        &shard_proofs,
+    );
+    let program = builder.compile();
+
+    let mut runtime = Runtime::<F, EF>::new(&program);
+    write_proof(&mut runtime.witness_stream, machine.chips(), &vk, &proof);
+    runtime.run();
+    assert!(runtime.witness_stream.is_empty());
+}
+
+/// Proves a program of additions with the core machine, in shards of 16 cycles.
+fn prove_core() -> Proof<SC> {
+    let instructions = (0..40)
+        .map(|_| Instruction::new(Opcode::ADD, 29, 29, 1, false, true))
+        .collect();
+    let program = CoreProgram::new(instructions, 0, 0);
+    let options = ProverOptions::default().with_shard_size(1 << 4);
+    let mut runtime = CoreRuntime::with_options(program, options.runtime_options());
+    runtime.run();
+
+    let machine = RiscvAir::machine(SC::new());
+    let (pk, vk) = machine.setup(runtime.program.as_ref());
+    let proof = machine
+        .prove_with_options(
+            &LocalProver::with_options(options.clone()),
+            &pk,
+            runtime.record,
+            &mut machine.config().challenger(),
+            &options.sharding_config(),
+            &ProgressMonitor::new(),
+        )
+        .unwrap();
+    machine
+        .verify(&vk, &proof, &mut machine.config().challenger())
+        .expect("the proof should verify natively");
+    proof
+}
+
+/// Verifies the shard proofs of a core proof in the VM.
+fn verify_core_proof(proof: &Proof<SC>) {
+    let machine = RiscvAir::machine(SC::new());
+    // The core machine has no preprocessed traces, so the verifying key does not depend on the
+    // program.
+    let (_, vk) = machine.setup(&CoreProgram::new(vec![], 0, 0));
+    assert!(vk.commit.is_none());
+
+    let shapes = proof
+        .shard_proofs
+        .iter()
+        .map(ShardShape::of)
+        .collect::<Vec<_>>();
+    let mut builder = VmBuilder::<F, EF>::default();
+    let shard_proofs = read_proof(&mut builder, machine.chips(), &shapes);
+    verify_proof(&mut builder, machine.chips(), None, &[], &shard_proofs);
+    let program = builder.compile();
+
+    let mut runtime = Runtime::<F, EF>::new(&program);
+    write_proof(&mut runtime.witness_stream, machine.chips(), &vk, proof);
+    runtime.run();
+    assert!(runtime.witness_stream.is_empty());
+}
+
+#[test]
+fn test_verify_core_proof() {
+    let proof = prove_core();
+    assert!(proof.shard_proofs.len() > 1);
+    verify_core_proof(&proof);
+}
+
+#[test]
+#[should_panic(expected = "TRAP instruction encountered")]
+fn test_verify_tampered_core_proof() {
+    let mut proof = prove_core();
+    let values = &mut proof.shard_proofs[1].opened_values.chips[0];
+    values.main.local[0] += EF::one();
+    verify_core_proof(&proof);
+}
@@ -46,6 +46,7 @@ impl<F: PrimeField32> MachineAir<F> for CpuChip<F> {
         input: &ExecutionRecord<F>,
         _: &mut ExecutionRecord<F>,
     ) -> RowMajorMatrix<F> {
+        let mut commit_index = F::zero();
         let rows = input
             .cpu_events
             .iter()
@@ -80,8 +81,25 @@ impl<F: PrimeField32> MachineAir<F> for CpuChip<F> {
                     Opcode::BNE => {
                         cols.is_bne = F::one();
                     }
+                    Opcode::JAL => {
+                        cols.is_jal = F::one();
+                    }
+                    Opcode::JALR => {
+                        cols.is_jalr = F::one();
+                    }
+                    Opcode::POSEIDON2_PERM => {
+                        cols.is_poseidon2 = F::one();
+                    }
+                    Opcode::NUM2BITS => {
+                        cols.is_num2bits = F::one();
+                    }
+                    Opcode::COMMIT => {
+                        cols.is_commit = F::one();
+                    }
                     _ => {}
                 };
+                cols.commit_index = commit_index;
+                commit_index += cols.is_commit;
 
                 if let Some(record) = &event.a_record {
                     cols.a.populate(record);
@@ -125,8 +143,10 @@ impl<F: PrimeField32> MachineAir<F> for CpuChip<F> {
         let mut trace =
             RowMajorMatrix::new(rows.into_iter().flatten().collect::<Vec<_>>(), NUM_CPU_COLS);
 
-        // Pad the trace to a power of two.
+        // Pad the trace to a power of two, and to the minimum height of the program.
         pad_to_power_of_two::<NUM_CPU_COLS, F>(&mut trace.values);
+        let height = input.program.trace_height(&self.name(), trace.height());
+        trace.values.resize(height * NUM_CPU_COLS, F::zero());
 
         for i in input.cpu_events.len()..trace.height() {
             trace.values[i * NUM_CPU_COLS + CPU_COL_MAP.clk] =
@@ -135,10 +155,15 @@ impl<F: PrimeField32> MachineAir<F> for CpuChip<F> {
                 F::from_canonical_u32(1);
             trace.values[i * NUM_CPU_COLS + CPU_COL_MAP.instruction.imm_c] =
                 F::from_canonical_u32(1);
+            trace.values[i * NUM_CPU_COLS + CPU_COL_MAP.commit_index] = commit_index;
         }
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord<F>) -> usize {
+        input.cpu_events.len()
+    }
+
     fn included(&self, _: &Self::Record) -> bool {
         true
     }
@@ -165,15 +190,79 @@ where
             .when(local.is_real)
             .assert_eq(local.clk + AB::F::from_canonical_u32(4), next.clk);
 
-        // Increment pc by 1 every cycle unless it is a branch instruction that is satisfied.
+        // Increment pc by 1 every cycle unless it is a branch instruction that is satisfied or a
+        // jump.
         builder
             .when_transition()
-            .when(next.is_real * (AB::Expr::one() - (local.is_beq + local.is_bne)))
+            .when(
+                next.is_real
+                    * (AB::Expr::one()
+                        - (local.is_beq + local.is_bne + local.is_jal + local.is_jalr)),
+            )
             .assert_eq(local.pc + AB::F::one(), next.pc);
         builder
             .when(local.beq + local.bne)
             .assert_eq(next.pc, local.pc + local.c.value.0[0]);
 
+        // Jumps: JAL jumps by the immediate b, and JALR to the address read in b. Both store the
+        // return address `pc + 1` in a(fp) and move fp by c, which is an immediate for JAL and is
+        // read from c(fp) for JALR.
+        builder.assert_bool(local.is_jal);
+        builder.when(local.is_jal).assert_eq(
+            local.instruction.opcode,
+            AB::F::from_canonical_u32(Opcode::JAL as u32),
+        );
+        builder.assert_bool(local.is_jalr);
+        builder.when(local.is_jalr).assert_eq(
+            local.instruction.opcode,
+            AB::F::from_canonical_u32(Opcode::JALR as u32),
+        );
+        builder
+            .when_transition()
+            .when(next.is_real * local.is_jal)
+            .assert_eq(next.pc, local.pc + local.b.value.0[0]);
+        builder
+            .when_transition()
+            .when(next.is_real * local.is_jalr)
+            .assert_eq(next.pc, local.b.value.0[0]);
+        builder
+            .when(local.is_jal)
+            .assert_one(local.instruction.imm_b);
+        builder
+            .when(local.is_jal)
+            .assert_one(local.instruction.imm_c);
+        builder
+            .when(local.is_jalr)
+            .assert_zero(local.instruction.imm_b);
+        builder
+            .when(local.is_jalr)
+            .assert_zero(local.instruction.imm_c);
+        builder
+            .when(local.is_jalr)
+            .assert_eq(local.b.addr, local.fp + local.instruction.op_b.0[0]);
+        builder
+            .when(local.is_jalr)
+            .assert_eq(local.c.addr, local.fp + local.instruction.op_c.0[0]);
+
+        let is_jump = local.is_jal + local.is_jalr;
+        builder
+            .when(is_jump.clone())
+            .assert_eq(local.a.addr, local.fp + local.instruction.op_a);
+        builder
+            .when(is_jump.clone())
+            .assert_eq(local.a.value.0[0], local.pc + AB::F::one());
+        for i in 1..4 {
+            builder
+                .when(is_jump.clone())
+                .assert_zero(local.a.value.0[i]);
+        }
+
+        // The frame pointer only moves on jumps.
+        builder
+            .when_transition()
+            .when(next.is_real)
+            .assert_eq(next.fp, local.fp + is_jump * local.c.value.0[0]);
+
         // Connect immediates.
         builder
             .when(local.instruction.imm_b)
@@ -327,7 +416,56 @@ where
             InteractionKind::Memory,
         ));
 
-        let mut prog_interaction_vals: Vec<AB::Expr> = vec![local.instruction.opcode.into()];
+        // Send the permutation to the Poseidon2 chip, with the destination and source pointers.
+        builder.assert_bool(local.is_poseidon2);
+        builder.when(local.is_poseidon2).assert_eq(
+            local.instruction.opcode,
+            AB::F::from_canonical_u32(Opcode::POSEIDON2_PERM as u32),
+        );
+        builder.send(AirInteraction::new(
+            vec![
+                local.clk.into(),
+                local.a.value.0[0].into(),
+                local.b.value.0[0].into(),
+            ],
+            local.is_poseidon2.into(),
+            InteractionKind::Poseidon2,
+        ));
+
+        // Send the decomposition to the NUM2BITS chip, with the destination pointer and the value.
+        builder.assert_bool(local.is_num2bits);
+        builder.when(local.is_num2bits).assert_eq(
+            local.instruction.opcode,
+            AB::F::from_canonical_u32(Opcode::NUM2BITS as u32),
+        );
+        builder.send(AirInteraction::new(
+            vec![
+                local.clk.into(),
+                local.a.value.0[0].into(),
+                local.b.value.0[0].into(),
+            ],
+            local.is_num2bits.into(),
+            InteractionKind::Num2Bits,
+        ));
+
+        // Expose the committed values as public values, at consecutive indices.
+        builder.assert_bool(local.is_commit);
+        builder.when(local.is_commit).assert_eq(
+            local.instruction.opcode,
+            AB::F::from_canonical_u32(Opcode::COMMIT as u32),
+        );
+        builder.when_first_row().assert_zero(local.commit_index);
+        builder
+            .when_transition()
+            .assert_eq(local.commit_index + local.is_commit, next.commit_index);
+        builder.send(AirInteraction::new(
+            vec![local.commit_index.into(), local.a.value.0[0].into()],
+            local.is_commit.into(),
+            InteractionKind::PublicValues,
+        ));
+
+        let mut prog_interaction_vals: Vec<AB::Expr> =
+            vec![local.pc.into(), local.instruction.opcode.into()];
         prog_interaction_vals.push(local.instruction.op_a.into());
         prog_interaction_vals.extend_from_slice(&local.instruction.op_b.map(|x| x.into()).0);
         prog_interaction_vals.extend_from_slice(&local.instruction.op_c.map(|x| x.into()).0);
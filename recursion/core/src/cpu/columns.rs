@@ -20,6 +20,14 @@ pub struct CpuCols<T> {
     pub is_mul: T,
     pub is_beq: T,
     pub is_bne: T,
+    pub is_jal: T,
+    pub is_jalr: T,
+    pub is_poseidon2: T,
+    pub is_num2bits: T,
+    pub is_commit: T,
+
+    /// The index of the next public value, i.e. the number of values committed before this row.
+    pub commit_index: T,
 
     pub beq: T,
     pub bne: T,
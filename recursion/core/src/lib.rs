@@ -1,6 +1,8 @@
 pub mod air;
 pub mod cpu;
 pub mod memory;
+pub mod num2bits;
+pub mod poseidon2;
 pub mod program;
 pub mod runtime;
 pub mod stark;
@@ -8,14 +10,21 @@ pub mod stark;
 #[cfg(test)]
 pub mod tests {
     use crate::air::Block;
-    use crate::runtime::{Instruction, Opcode, Program, Runtime};
+    use crate::num2bits::NUM_BITS;
+    use crate::poseidon2::WIDTH;
+    use crate::runtime::{Instruction, Opcode, Program, Runtime, STACK_SIZE};
     use crate::stark::RecursionAir;
 
     use p3_baby_bear::BabyBear;
     use p3_field::extension::BinomialExtensionField;
     use p3_field::{AbstractField, PrimeField32};
+    use p3_poseidon2::DiffusionMatrixBabybear;
+    use p3_symmetric::Permutation;
+    use sp1_core::air::MachineAir;
     use sp1_core::lookup::{debug_interactions_with_all_chips, InteractionKind};
     use sp1_core::stark::{LocalProver, StarkGenericConfig};
+    use sp1_core::utils::baby_bear_poseidon2::Perm;
+    use sp1_core::utils::poseidon2_instance::RC_16_30;
     use sp1_core::utils::BabyBearPoseidon2;
     use sp1_core::utils::StarkUtils;
     use std::time::Instant;
@@ -117,6 +126,145 @@ pub mod tests {
                     false,
                 ),
             ],
+            ..Default::default()
+        }
+    }
+
+    pub fn poseidon2_program<F: PrimeField32>(input: [F; WIDTH]) -> Program<F> {
+        // .main
+        //   imm 0(fp) (STACK_SIZE + 1024) <-- src = fp + 1024
+        //   imm 1(fp) (STACK_SIZE + 2048) <-- dst = fp + 2048
+        //   imm (1024 + i)(fp) input[i] <-- src[i] = input[i]
+        //   poseidon2_perm 1(fp) 0(fp) <-- dst = permute(src)
+        let zero = [F::zero(); 4];
+        let imm = |value: F| [value, F::zero(), F::zero(), F::zero()];
+        let mut instructions = vec![
+            Instruction::new(
+                Opcode::SW,
+                F::zero(),
+                imm(F::from_canonical_usize(STACK_SIZE + 1024)),
+                zero,
+                true,
+                false,
+                true,
+                false,
+            ),
+            Instruction::new(
+                Opcode::SW,
+                F::one(),
+                imm(F::from_canonical_usize(STACK_SIZE + 2048)),
+                zero,
+                true,
+                false,
+                true,
+                false,
+            ),
+        ];
+        for (i, value) in input.into_iter().enumerate() {
+            instructions.push(Instruction::new(
+                Opcode::SW,
+                F::from_canonical_usize(1024 + i),
+                imm(value),
+                zero,
+                true,
+                false,
+                true,
+                false,
+            ));
+        }
+        instructions.push(Instruction::new(
+            Opcode::POSEIDON2_PERM,
+            F::one(),
+            zero,
+            zero,
+            false,
+            false,
+            true,
+            false,
+        ));
+        Program::<F> {
+            instructions,
+            ..Default::default()
+        }
+    }
+
+    pub fn num2bits_program<F: PrimeField32>(value: F) -> Program<F> {
+        // .main
+        //   imm 0(fp) (STACK_SIZE + 1024) <-- dst = fp + 1024
+        //   imm 1(fp) value
+        //   num2bits 0(fp) 1(fp) <-- dst = bits(value)
+        let zero = [F::zero(); 4];
+        let imm = |value: F| [value, F::zero(), F::zero(), F::zero()];
+        let instructions = vec![
+            Instruction::new(
+                Opcode::SW,
+                F::zero(),
+                imm(F::from_canonical_usize(STACK_SIZE + 1024)),
+                zero,
+                true,
+                false,
+                true,
+                false,
+            ),
+            Instruction::new(
+                Opcode::SW,
+                F::one(),
+                imm(value),
+                zero,
+                true,
+                false,
+                true,
+                false,
+            ),
+            Instruction::new(
+                Opcode::NUM2BITS,
+                F::zero(),
+                imm(F::one()),
+                zero,
+                false,
+                false,
+                true,
+                false,
+            ),
+        ];
+        Program::<F> {
+            instructions,
+            ..Default::default()
+        }
+    }
+
+    pub fn commit_program<F: PrimeField32>(values: &[F]) -> Program<F> {
+        // .main
+        //   imm i(fp) values[i]
+        //   commit i(fp) <-- expose values[i] as the i-th public value
+        let zero = [F::zero(); 4];
+        let imm = |value: F| [value, F::zero(), F::zero(), F::zero()];
+        let mut instructions = vec![];
+        for (i, value) in values.iter().enumerate() {
+            instructions.push(Instruction::new(
+                Opcode::SW,
+                F::from_canonical_usize(i),
+                imm(*value),
+                zero,
+                true,
+                false,
+                true,
+                false,
+            ));
+            instructions.push(Instruction::new(
+                Opcode::COMMIT,
+                F::from_canonical_usize(i),
+                zero,
+                zero,
+                true,
+                false,
+                true,
+                false,
+            ));
+        }
+        Program::<F> {
+            instructions,
+            ..Default::default()
         }
     }
 
@@ -126,7 +274,7 @@ pub mod tests {
         let mut runtime = Runtime::<F, EF>::new(&program);
         runtime.run();
         assert_eq!(
-            runtime.memory[1024 + 1].value,
+            runtime.memory[STACK_SIZE + 1].value,
             Block::from(BabyBear::from_canonical_u32(144))
         );
     }
@@ -162,4 +310,158 @@ pub mod tests {
         machine.verify(&vk, &proof, &mut challenger).unwrap();
         println!("proving duration = {}", duration);
     }
+
+    #[test]
+    fn test_min_trace_heights_prove() {
+        type SC = BabyBearPoseidon2;
+        type F = <SC as StarkGenericConfig>::Val;
+        let config = SC::new();
+        let machine = RecursionAir::machine(config);
+
+        // Pad every trace to 2^7 rows, including those of the chips without events.
+        let mut program = fibonacci_program::<F>();
+        program.min_trace_log_heights = machine
+            .chips()
+            .iter()
+            .map(|chip| (chip.name(), 7))
+            .collect();
+
+        let mut runtime = Runtime::<F, EF>::new(&program);
+        runtime.run();
+
+        let (pk, vk) = machine.setup(&program);
+        let mut challenger = machine.config().challenger();
+        let proof = machine.prove::<LocalProver<_, _>>(&pk, runtime.record, &mut challenger);
+        let shard_proof = &proof.shard_proofs[0];
+        assert_eq!(shard_proof.chip_ids.len(), machine.chips().len());
+        for values in shard_proof.opened_values.chips.iter() {
+            assert_eq!(values.log_degree, 7);
+        }
+
+        let mut challenger = machine.config().challenger();
+        machine.verify(&vk, &proof, &mut challenger).unwrap();
+    }
+
+    #[test]
+    fn test_poseidon2_execute() {
+        let input = core::array::from_fn(|i| F::from_canonical_usize(i));
+        let program = poseidon2_program::<F>(input);
+        let mut runtime = Runtime::<F, EF>::new(&program);
+        runtime.run();
+
+        let perm = Perm::new(8, 22, RC_16_30.to_vec(), DiffusionMatrixBabybear);
+        let expected = perm.permute(input);
+        for (i, value) in expected.into_iter().enumerate() {
+            assert_eq!(
+                runtime.memory[STACK_SIZE + 2048 + i].value,
+                Block::from(value)
+            );
+        }
+    }
+
+    #[test]
+    fn test_poseidon2_prove() {
+        sp1_core::utils::setup_logger();
+
+        type SC = BabyBearPoseidon2;
+        type F = <SC as StarkGenericConfig>::Val;
+        let input = core::array::from_fn(|i| F::from_canonical_usize(i * i + 7));
+        let program = poseidon2_program::<F>(input);
+
+        let mut runtime = Runtime::<F, EF>::new(&program);
+        runtime.run();
+
+        let config = SC::new();
+        let machine = RecursionAir::machine(config);
+        let (pk, vk) = machine.setup(&program);
+        let mut challenger = machine.config().challenger();
+
+        debug_interactions_with_all_chips::<BabyBearPoseidon2, RecursionAir<BabyBear>>(
+            machine.chips(),
+            &runtime.record,
+            vec![InteractionKind::Memory, InteractionKind::Poseidon2],
+        );
+
+        let proof = machine.prove::<LocalProver<_, _>>(&pk, runtime.record, &mut challenger);
+
+        let mut challenger = machine.config().challenger();
+        machine.verify(&vk, &proof, &mut challenger).unwrap();
+    }
+
+    #[test]
+    fn test_num2bits_execute() {
+        let value = F::from_canonical_u32(0x7654_3210);
+        let program = num2bits_program::<F>(value);
+        let mut runtime = Runtime::<F, EF>::new(&program);
+        runtime.run();
+
+        for i in 0..NUM_BITS {
+            let bit = F::from_canonical_u32((0x7654_3210 >> i) & 1);
+            assert_eq!(
+                runtime.memory[STACK_SIZE + 1024 + i].value,
+                Block::from(bit)
+            );
+        }
+    }
+
+    #[test]
+    fn test_num2bits_prove() {
+        sp1_core::utils::setup_logger();
+
+        type SC = BabyBearPoseidon2;
+        type F = <SC as StarkGenericConfig>::Val;
+        let program = num2bits_program::<F>(F::neg_one());
+
+        let mut runtime = Runtime::<F, EF>::new(&program);
+        runtime.run();
+
+        let config = SC::new();
+        let machine = RecursionAir::machine(config);
+        let (pk, vk) = machine.setup(&program);
+        let mut challenger = machine.config().challenger();
+
+        debug_interactions_with_all_chips::<BabyBearPoseidon2, RecursionAir<BabyBear>>(
+            machine.chips(),
+            &runtime.record,
+            vec![InteractionKind::Memory, InteractionKind::Num2Bits],
+        );
+
+        let proof = machine.prove::<LocalProver<_, _>>(&pk, runtime.record, &mut challenger);
+
+        let mut challenger = machine.config().challenger();
+        machine.verify(&vk, &proof, &mut challenger).unwrap();
+    }
+
+    #[test]
+    fn test_commit_prove() {
+        sp1_core::utils::setup_logger();
+
+        type SC = BabyBearPoseidon2;
+        type F = <SC as StarkGenericConfig>::Val;
+        let values = [7, 0, 123456].map(F::from_canonical_u32);
+        let program = commit_program::<F>(&values);
+
+        let mut runtime = Runtime::<F, EF>::new(&program);
+        runtime.run();
+        assert_eq!(runtime.record.public_values, values);
+
+        let config = SC::new();
+        let machine = RecursionAir::machine(config);
+        let (pk, vk) = machine.setup(&program);
+        let mut challenger = machine.config().challenger();
+        let mut proof = machine.prove::<LocalProver<_, _>>(&pk, runtime.record, &mut challenger);
+        assert_eq!(proof.public_values, values);
+
+        let mut challenger = machine.config().challenger();
+        machine.verify(&vk, &proof, &mut challenger).unwrap();
+
+        // The proof does not verify with other public values, or against another program.
+        proof.public_values[1] = F::one();
+        let mut challenger = machine.config().challenger();
+        assert!(machine.verify(&vk, &proof, &mut challenger).is_err());
+        proof.public_values[1] = F::zero();
+        let (_, other_vk) = machine.setup(&commit_program::<F>(&[F::one(); 3]));
+        let mut challenger = machine.config().challenger();
+        assert!(machine.verify(&other_vk, &proof, &mut challenger).is_err());
+    }
 }
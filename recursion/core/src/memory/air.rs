@@ -2,7 +2,7 @@ use core::mem::size_of;
 use p3_air::{Air, BaseAir};
 use p3_field::PrimeField32;
 use p3_matrix::dense::RowMajorMatrix;
-use p3_matrix::MatrixRowSlices;
+use p3_matrix::{Matrix, MatrixRowSlices};
 use sp1_core::air::{AirInteraction, SP1AirBuilder};
 use sp1_core::lookup::InteractionKind;
 use sp1_core::{air::MachineAir, utils::pad_to_power_of_two};
@@ -78,11 +78,31 @@ impl<F: PrimeField32> MachineAir<F> for MemoryGlobalChip {
         );
 
         pad_to_power_of_two::<NUM_MEMORY_INIT_COLS, F>(&mut trace.values);
+        let height = input
+            .program
+            .trace_height(&MachineAir::<F>::name(self), trace.height());
+        trace
+            .values
+            .resize(height * NUM_MEMORY_INIT_COLS, F::zero());
 
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord<F>) -> usize {
+        match self.kind {
+            MemoryChipKind::Init => input.first_memory_record.len(),
+            MemoryChipKind::Finalize => input.last_memory_record.len(),
+            _ => unreachable!(),
+        }
+    }
+
     fn included(&self, shard: &Self::Record) -> bool {
+        if shard
+            .program
+            .has_min_trace_height(&MachineAir::<F>::name(self))
+        {
+            return true;
+        }
         match self.kind {
             MemoryChipKind::Init => !shard.first_memory_record.is_empty(),
             MemoryChipKind::Finalize => !shard.last_memory_record.is_empty(),
@@ -2,6 +2,8 @@ mod air;
 mod columns;
 
 use crate::air::Block;
+use sp1_core::air::{AirInteraction, SP1AirBuilder};
+use sp1_core::lookup::InteractionKind;
 use sp1_derive::AlignedBorrow;
 
 #[derive(Debug, Clone)]
@@ -44,3 +46,35 @@ pub enum MemoryChipKind {
 pub struct MemoryGlobalChip {
     pub kind: MemoryChipKind,
 }
+
+/// Consumes the previous value of a memory access and emits its new value.
+pub(crate) fn eval_memory_access<AB: SP1AirBuilder>(
+    builder: &mut AB,
+    access: &MemoryReadWriteCols<AB::Var>,
+    multiplicity: AB::Var,
+) {
+    builder.receive(AirInteraction::new(
+        vec![
+            access.addr.into(),
+            access.prev_timestamp.into(),
+            access.prev_value.0[0].into(),
+            access.prev_value.0[1].into(),
+            access.prev_value.0[2].into(),
+            access.prev_value.0[3].into(),
+        ],
+        multiplicity.into(),
+        InteractionKind::Memory,
+    ));
+    builder.send(AirInteraction::new(
+        vec![
+            access.addr.into(),
+            access.timestamp.into(),
+            access.value.0[0].into(),
+            access.value.0[1].into(),
+            access.value.0[2].into(),
+            access.value.0[3].into(),
+        ],
+        multiplicity.into(),
+        InteractionKind::Memory,
+    ));
+}
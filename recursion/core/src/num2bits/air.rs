@@ -0,0 +1,78 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::AbstractField;
+use p3_matrix::MatrixRowSlices;
+use sp1_core::air::{AirInteraction, SP1AirBuilder};
+use sp1_core::lookup::InteractionKind;
+
+use super::columns::{Num2BitsCols, NUM_NUM2BITS_COLS};
+use super::{Num2BitsChip, NUM_BITS};
+use crate::memory::eval_memory_access;
+
+/// The number of low bits of a canonical element whose top four bits are all set, since the
+/// BabyBear modulus is `15 * 2^27 + 1`.
+pub(crate) const NUM_LOW_BITS: usize = 27;
+
+impl<F> BaseAir<F> for Num2BitsChip {
+    fn width(&self) -> usize {
+        NUM_NUM2BITS_COLS
+    }
+}
+
+impl<AB> Air<AB> for Num2BitsChip
+where
+    AB: SP1AirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &Num2BitsCols<AB::Var> = main.row_slice(0).borrow();
+
+        builder.assert_bool(local.is_real);
+
+        // Receive the instruction from the CPU.
+        builder.receive(AirInteraction::new(
+            vec![local.clk.into(), local.dst.into(), local.value.into()],
+            local.is_real.into(),
+            InteractionKind::Num2Bits,
+        ));
+
+        // Write the bits to `dst` at `clk + 1`.
+        let mut reconstructed = AB::Expr::zero();
+        for (i, access) in local.bits.iter().enumerate() {
+            let bit = access.value.0[0];
+            builder.assert_bool(bit);
+            for limb in access.value.0.iter().skip(1) {
+                builder.assert_zero(*limb);
+            }
+            builder
+                .when(local.is_real)
+                .assert_eq(access.addr, local.dst + AB::F::from_canonical_usize(i));
+            builder
+                .when(local.is_real)
+                .assert_eq(access.timestamp, local.clk + AB::F::one());
+            eval_memory_access(builder, access, local.is_real);
+
+            reconstructed += bit * AB::F::from_canonical_u32(1 << i);
+        }
+        builder.assert_eq(reconstructed, local.value);
+
+        // The bits must be the canonical decomposition: if the top four bits are set, the value is
+        // at least `15 * 2^27`, so the low bits must be zero.
+        let high_bits = &local.bits[NUM_LOW_BITS..];
+        builder.assert_eq(
+            local.high_bits_products[0],
+            high_bits[0].value.0[0] * high_bits[1].value.0[0],
+        );
+        for i in 1..local.high_bits_products.len() {
+            builder.assert_eq(
+                local.high_bits_products[i],
+                local.high_bits_products[i - 1] * high_bits[i + 1].value.0[0],
+            );
+        }
+        let all_high_bits = local.high_bits_products[local.high_bits_products.len() - 1];
+        for access in local.bits[..NUM_LOW_BITS].iter() {
+            builder.when(all_high_bits).assert_zero(access.value.0[0]);
+        }
+    }
+}
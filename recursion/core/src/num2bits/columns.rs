@@ -0,0 +1,28 @@
+use core::mem::size_of;
+
+use sp1_derive::AlignedBorrow;
+
+use crate::memory::MemoryReadWriteCols;
+
+use super::NUM_BITS;
+
+pub const NUM_NUM2BITS_COLS: usize = size_of::<Num2BitsCols<u8>>();
+
+/// The column layout for the chip.
+#[derive(AlignedBorrow, Default, Debug, Clone)]
+#[repr(C)]
+pub struct Num2BitsCols<T> {
+    pub clk: T,
+    pub dst: T,
+    pub value: T,
+
+    /// Writes the bits to `dst`.
+    pub bits: [MemoryReadWriteCols<T>; NUM_BITS],
+
+    /// The running products of the top four bits, starting with `bits[27] * bits[28]`.
+    ///
+    /// A decomposition is canonical if the low bits are zero whenever the top four bits are set.
+    pub high_bits_products: [T; 3],
+
+    pub is_real: T,
+}
@@ -0,0 +1,22 @@
+mod air;
+pub mod columns;
+mod trace;
+
+use crate::memory::MemoryRecord;
+
+/// The number of bits of a canonical BabyBear element.
+pub const NUM_BITS: usize = 31;
+
+/// A `NUM2BITS` instruction, which writes the little-endian bits of `value` to `dst`.
+#[derive(Debug, Clone)]
+pub struct Num2BitsEvent<F> {
+    pub clk: F,
+    pub dst: F,
+    pub value: F,
+    pub bit_records: [MemoryRecord<F>; NUM_BITS],
+}
+
+/// A chip that decomposes field elements into their canonical little-endian bits, one row per
+/// decomposition.
+#[derive(Default)]
+pub struct Num2BitsChip;
@@ -0,0 +1,73 @@
+use std::borrow::BorrowMut;
+
+use p3_field::PrimeField32;
+use p3_matrix::dense::RowMajorMatrix;
+use sp1_core::air::MachineAir;
+use sp1_core::utils::pad_rows;
+
+use super::air::NUM_LOW_BITS;
+use super::columns::{Num2BitsCols, NUM_NUM2BITS_COLS};
+use super::Num2BitsChip;
+use crate::runtime::ExecutionRecord;
+
+impl<F: PrimeField32> MachineAir<F> for Num2BitsChip {
+    type Record = ExecutionRecord<F>;
+
+    fn name(&self) -> String {
+        "Num2Bits".to_string()
+    }
+
+    fn generate_trace(
+        &self,
+        input: &ExecutionRecord<F>,
+        _: &mut ExecutionRecord<F>,
+    ) -> RowMajorMatrix<F> {
+        let mut rows = input
+            .num2bits_events
+            .iter()
+            .map(|event| {
+                let mut row = [F::zero(); NUM_NUM2BITS_COLS];
+                let cols: &mut Num2BitsCols<F> = row.as_mut_slice().borrow_mut();
+                cols.clk = event.clk;
+                cols.dst = event.dst;
+                cols.value = event.value;
+                for (access, record) in cols.bits.iter_mut().zip(event.bit_records.iter()) {
+                    access.populate(record);
+                }
+
+                let high_bits = &event.bit_records[NUM_LOW_BITS..];
+                let mut product = high_bits[0].value.0[0];
+                for (i, record) in high_bits[1..].iter().enumerate() {
+                    product *= record.value.0[0];
+                    cols.high_bits_products[i] = product;
+                }
+
+                cols.is_real = F::one();
+                row
+            })
+            .collect::<Vec<_>>();
+
+        // The decomposition of zero satisfies the constraints, so the padding rows are all zero.
+        pad_rows(&mut rows, || [F::zero(); NUM_NUM2BITS_COLS]);
+        let height = input
+            .program
+            .trace_height(&MachineAir::<F>::name(self), rows.len());
+        rows.resize(height, [F::zero(); NUM_NUM2BITS_COLS]);
+
+        RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_NUM2BITS_COLS,
+        )
+    }
+
+    fn num_rows(&self, input: &ExecutionRecord<F>) -> usize {
+        input.num2bits_events.len()
+    }
+
+    fn included(&self, shard: &Self::Record) -> bool {
+        !shard.num2bits_events.is_empty()
+            || shard
+                .program
+                .has_min_trace_height(&MachineAir::<F>::name(self))
+    }
+}
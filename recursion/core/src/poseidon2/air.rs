@@ -0,0 +1,102 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::AbstractField;
+use p3_matrix::MatrixRowSlices;
+use sp1_core::air::{AirInteraction, SP1AirBuilder};
+use sp1_core::lookup::InteractionKind;
+
+use super::columns::{Poseidon2Cols, NUM_POSEIDON2_COLS};
+use super::{external_linear_layer, full_round_index, internal_linear_layer, is_full_round};
+use super::{round_constant, Poseidon2Chip, NUM_ROUNDS, PARTIAL_ROUNDS_START, WIDTH};
+use crate::air::BlockBuilder;
+use crate::memory::eval_memory_access;
+
+impl<F> BaseAir<F> for Poseidon2Chip {
+    fn width(&self) -> usize {
+        NUM_POSEIDON2_COLS
+    }
+}
+
+impl<AB> Air<AB> for Poseidon2Chip
+where
+    AB: SP1AirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &Poseidon2Cols<AB::Var> = main.row_slice(0).borrow();
+
+        builder.assert_bool(local.is_real);
+
+        // Receive the instruction from the CPU.
+        builder.receive(AirInteraction::new(
+            vec![local.clk.into(), local.dst.into(), local.src.into()],
+            local.is_real.into(),
+            InteractionKind::Poseidon2,
+        ));
+
+        // Read the input state at `clk` and write the output state at `clk + 1`.
+        for (i, (input, output)) in local.input.iter().zip(local.output.iter()).enumerate() {
+            let offset = AB::F::from_canonical_usize(i);
+            builder
+                .when(local.is_real)
+                .assert_eq(input.addr, local.src + offset);
+            builder
+                .when(local.is_real)
+                .assert_eq(input.timestamp, local.clk);
+            builder.assert_block_eq::<AB::Var, AB::Var>(input.value, input.prev_value);
+            eval_memory_access(builder, input, local.is_real);
+
+            builder
+                .when(local.is_real)
+                .assert_eq(output.addr, local.dst + offset);
+            builder
+                .when(local.is_real)
+                .assert_eq(output.timestamp, local.clk + AB::F::one());
+            for limb in output.value.0.iter().skip(1) {
+                builder.assert_zero(*limb);
+            }
+            eval_memory_access(builder, output, local.is_real);
+        }
+
+        // Apply the initial external linear layer.
+        let mut state: [AB::Expr; WIDTH] =
+            core::array::from_fn(|i| local.input[i].value.0[0].into());
+        external_linear_layer(&mut state);
+
+        for round in 0..NUM_ROUNDS {
+            let round_state = local.round_states[round];
+            for (col, expr) in round_state.iter().zip(state.iter()) {
+                builder.assert_eq(*col, expr.clone());
+            }
+
+            if is_full_round(round) {
+                let full_round = full_round_index(round);
+                for (i, x_state) in state.iter_mut().enumerate() {
+                    let x = round_state[i] + round_constant::<AB::F>(round, i);
+                    let cube = local.full_sbox_cube[full_round][i];
+                    let out = local.full_sbox_out[full_round][i];
+                    builder.assert_eq(cube, x.clone() * x.clone() * x.clone());
+                    builder.assert_eq(out, cube * cube * x);
+                    *x_state = out.into();
+                }
+                external_linear_layer(&mut state);
+            } else {
+                let partial_round = round - PARTIAL_ROUNDS_START;
+                let x = round_state[0] + round_constant::<AB::F>(round, 0);
+                let cube = local.partial_sbox_cube[partial_round];
+                let out = local.partial_sbox_out[partial_round];
+                builder.assert_eq(cube, x.clone() * x.clone() * x.clone());
+                builder.assert_eq(out, cube * cube * x);
+                state = core::array::from_fn(|i| round_state[i].into());
+                state[0] = out.into();
+                internal_linear_layer(&mut state);
+            }
+        }
+
+        // The output state is written to memory.
+        for (output, expr) in local.output.iter().zip(state) {
+            builder.assert_eq(output.value.0[0], expr);
+        }
+    }
+}
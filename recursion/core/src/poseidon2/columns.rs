@@ -0,0 +1,37 @@
+use core::mem::size_of;
+
+use sp1_derive::AlignedBorrow;
+
+use crate::memory::MemoryReadWriteCols;
+
+use super::{NUM_FULL_ROUNDS, NUM_PARTIAL_ROUNDS, NUM_ROUNDS, WIDTH};
+
+pub const NUM_POSEIDON2_COLS: usize = size_of::<Poseidon2Cols<u8>>();
+
+/// The column layout for the chip.
+#[derive(AlignedBorrow, Default, Debug, Clone)]
+#[repr(C)]
+pub struct Poseidon2Cols<T> {
+    pub clk: T,
+    pub dst: T,
+    pub src: T,
+
+    /// Reads the input state from `src`.
+    pub input: [MemoryReadWriteCols<T>; WIDTH],
+
+    /// Writes the output state to `dst`.
+    pub output: [MemoryReadWriteCols<T>; WIDTH],
+
+    /// The state at the start of every round, after the initial external linear layer.
+    pub round_states: [[T; WIDTH]; NUM_ROUNDS],
+
+    /// The S-boxes of the full rounds, as `x^3` and `x^7`.
+    pub full_sbox_cube: [[T; WIDTH]; NUM_FULL_ROUNDS],
+    pub full_sbox_out: [[T; WIDTH]; NUM_FULL_ROUNDS],
+
+    /// The S-boxes of the partial rounds, as `x^3` and `x^7`.
+    pub partial_sbox_cube: [T; NUM_PARTIAL_ROUNDS],
+    pub partial_sbox_out: [T; NUM_PARTIAL_ROUNDS],
+
+    pub is_real: T,
+}
@@ -0,0 +1,179 @@
+mod air;
+pub mod columns;
+mod trace;
+
+use std::sync::OnceLock;
+
+use p3_baby_bear::BabyBear;
+use p3_field::{AbstractField, PrimeField32};
+use p3_poseidon2::DiffusionMatrixBabybear;
+use p3_symmetric::Permutation;
+use sp1_core::utils::poseidon2_instance::RC_16_30;
+
+use crate::memory::MemoryRecord;
+
+/// The width of the permutation state.
+pub const WIDTH: usize = 16;
+
+/// The number of full rounds, half of which are applied before the partial rounds.
+pub const NUM_FULL_ROUNDS: usize = 8;
+
+/// The number of partial rounds.
+pub const NUM_PARTIAL_ROUNDS: usize = 22;
+
+/// The total number of rounds.
+pub const NUM_ROUNDS: usize = NUM_FULL_ROUNDS + NUM_PARTIAL_ROUNDS;
+
+/// The round index at which the partial rounds start.
+pub const PARTIAL_ROUNDS_START: usize = NUM_FULL_ROUNDS / 2;
+
+/// The round index at which the partial rounds end.
+pub const PARTIAL_ROUNDS_END: usize = PARTIAL_ROUNDS_START + NUM_PARTIAL_ROUNDS;
+
+/// A `POSEIDON2_PERM` instruction, which reads a state from `src` and writes its permutation to
+/// `dst`.
+#[derive(Debug, Clone)]
+pub struct Poseidon2Event<F> {
+    pub clk: F,
+    pub dst: F,
+    pub src: F,
+    pub input: [F; WIDTH],
+    pub output: [F; WIDTH],
+    pub input_records: [MemoryRecord<F>; WIDTH],
+    pub output_records: [MemoryRecord<F>; WIDTH],
+}
+
+/// A chip that implements the Poseidon2 permutation used by `BabyBearPoseidon2`, one row per
+/// permutation.
+#[derive(Default)]
+pub struct Poseidon2Chip;
+
+/// Whether `round` is a full round.
+pub(crate) fn is_full_round(round: usize) -> bool {
+    !(PARTIAL_ROUNDS_START..PARTIAL_ROUNDS_END).contains(&round)
+}
+
+/// The index of the full round `round` among the full rounds.
+pub(crate) fn full_round_index(round: usize) -> usize {
+    if round < PARTIAL_ROUNDS_START {
+        round
+    } else {
+        round - NUM_PARTIAL_ROUNDS
+    }
+}
+
+/// The round constant of `round` for the state element `i`.
+pub(crate) fn round_constant<T: AbstractField>(round: usize, i: usize) -> T {
+    T::from_canonical_u32(RC_16_30[round][i].as_canonical_u32())
+}
+
+/// The diagonal of the internal linear layer, minus the identity.
+///
+/// The internal linear layer is `x -> diag * x + sum(x)`, so the diagonal is read off the images
+/// of the unit vectors under `DiffusionMatrixBabybear`.
+pub fn internal_diagonal() -> &'static [u32; WIDTH] {
+    static DIAGONAL: OnceLock<[u32; WIDTH]> = OnceLock::new();
+    DIAGONAL.get_or_init(|| {
+        core::array::from_fn(|i| {
+            let mut unit = [BabyBear::zero(); WIDTH];
+            unit[i] = BabyBear::one();
+            (DiffusionMatrixBabybear.permute(unit)[i] - BabyBear::one()).as_canonical_u32()
+        })
+    })
+}
+
+/// The S-box `x -> x^7`, returning `(x^3, x^7)`.
+pub(crate) fn sbox<T: AbstractField>(x: T) -> (T, T) {
+    let cube = x.clone() * x.clone() * x.clone();
+    let out = cube.clone() * cube.clone() * x;
+    (cube, out)
+}
+
+/// The external linear layer: the 4x4 MDS matrix applied to each chunk of four elements,
+/// followed by adding the sum of the elements in the same position of every chunk.
+pub(crate) fn external_linear_layer<T: AbstractField>(state: &mut [T; WIDTH]) {
+    for chunk in state.chunks_exact_mut(4) {
+        let t0 = chunk[0].clone() + chunk[1].clone();
+        let t1 = chunk[2].clone() + chunk[3].clone();
+        let t2 = chunk[1].clone() + chunk[1].clone() + t1.clone();
+        let t3 = chunk[3].clone() + chunk[3].clone() + t0.clone();
+        let t4 = t1 * T::from_canonical_u32(4) + t3.clone();
+        let t5 = t0 * T::from_canonical_u32(4) + t2.clone();
+        chunk[0] = t3 + t5.clone();
+        chunk[1] = t5;
+        chunk[2] = t2 + t4.clone();
+        chunk[3] = t4;
+    }
+
+    let sums: [T; 4] = core::array::from_fn(|k| {
+        (0..WIDTH)
+            .step_by(4)
+            .map(|j| state[j + k].clone())
+            .sum::<T>()
+    });
+    for (i, x) in state.iter_mut().enumerate() {
+        *x += sums[i % 4].clone();
+    }
+}
+
+/// The internal linear layer used by the partial rounds.
+pub(crate) fn internal_linear_layer<T: AbstractField>(state: &mut [T; WIDTH]) {
+    let sum = state.iter().cloned().sum::<T>();
+    for (x, diag) in state.iter_mut().zip(internal_diagonal()) {
+        *x = x.clone() * T::from_canonical_u32(*diag) + sum.clone();
+    }
+}
+
+/// Applies round `round` to `state`, returning the `(x^3, x^7)` pair of every S-box in the round.
+pub(crate) fn apply_round<F: PrimeField32>(round: usize, state: &mut [F; WIDTH]) -> Vec<(F, F)> {
+    if is_full_round(round) {
+        let sboxes = (0..WIDTH)
+            .map(|i| sbox(state[i] + round_constant::<F>(round, i)))
+            .collect::<Vec<_>>();
+        for (x, (_, out)) in state.iter_mut().zip(sboxes.iter()) {
+            *x = *out;
+        }
+        external_linear_layer(state);
+        sboxes
+    } else {
+        let (cube, out) = sbox(state[0] + round_constant::<F>(round, 0));
+        state[0] = out;
+        internal_linear_layer(state);
+        vec![(cube, out)]
+    }
+}
+
+/// The Poseidon2 permutation of `BabyBearPoseidon2`.
+pub fn permute<F: PrimeField32>(input: [F; WIDTH]) -> [F; WIDTH] {
+    let mut state = input;
+    external_linear_layer(&mut state);
+    for round in 0..NUM_ROUNDS {
+        apply_round(round, &mut state);
+    }
+    state
+}
+
+#[cfg(test)]
+pub mod poseidon2_tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::AbstractField;
+    use p3_poseidon2::DiffusionMatrixBabybear;
+    use p3_symmetric::Permutation;
+    use sp1_core::utils::baby_bear_poseidon2::Perm;
+    use sp1_core::utils::poseidon2_instance::RC_16_30;
+
+    use super::{permute, WIDTH};
+
+    #[test]
+    fn test_permute_matches_plonky3() {
+        let perm = Perm::new(8, 22, RC_16_30.to_vec(), DiffusionMatrixBabybear);
+        let inputs = [
+            [BabyBear::zero(); WIDTH],
+            core::array::from_fn(|i| BabyBear::from_canonical_usize(i)),
+            core::array::from_fn(|i| BabyBear::from_wrapped_u32(0x7800_0000 + i as u32 * 77)),
+        ];
+        for input in inputs {
+            assert_eq!(permute(input), perm.permute(input));
+        }
+    }
+}
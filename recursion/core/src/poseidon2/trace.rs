@@ -0,0 +1,99 @@
+use std::borrow::BorrowMut;
+
+use p3_field::PrimeField32;
+use p3_matrix::dense::RowMajorMatrix;
+use sp1_core::air::MachineAir;
+use sp1_core::utils::pad_rows;
+
+use super::columns::{Poseidon2Cols, NUM_POSEIDON2_COLS};
+use super::{apply_round, external_linear_layer, full_round_index, is_full_round};
+use super::{Poseidon2Chip, NUM_ROUNDS, PARTIAL_ROUNDS_START, WIDTH};
+use crate::air::Block;
+use crate::runtime::ExecutionRecord;
+
+impl<F: PrimeField32> MachineAir<F> for Poseidon2Chip {
+    type Record = ExecutionRecord<F>;
+
+    fn name(&self) -> String {
+        "Poseidon2".to_string()
+    }
+
+    fn generate_trace(
+        &self,
+        input: &ExecutionRecord<F>,
+        _: &mut ExecutionRecord<F>,
+    ) -> RowMajorMatrix<F> {
+        let mut rows = input
+            .poseidon2_events
+            .iter()
+            .map(|event| {
+                let mut row = [F::zero(); NUM_POSEIDON2_COLS];
+                let cols: &mut Poseidon2Cols<F> = row.as_mut_slice().borrow_mut();
+                cols.clk = event.clk;
+                cols.dst = event.dst;
+                cols.src = event.src;
+                for (access, record) in cols.input.iter_mut().zip(event.input_records.iter()) {
+                    access.populate(record);
+                }
+                for (access, record) in cols.output.iter_mut().zip(event.output_records.iter()) {
+                    access.populate(record);
+                }
+                populate_rounds(cols, event.input);
+                cols.is_real = F::one();
+                row
+            })
+            .collect::<Vec<_>>();
+
+        // Pad the trace with permutations of the zero state, so that the round constraints hold
+        // on every row.
+        let mut padding_row = [F::zero(); NUM_POSEIDON2_COLS];
+        let cols: &mut Poseidon2Cols<F> = padding_row.as_mut_slice().borrow_mut();
+        let output = populate_rounds(cols, [F::zero(); WIDTH]);
+        for (access, value) in cols.output.iter_mut().zip(output) {
+            access.value = Block::from(value);
+        }
+        pad_rows(&mut rows, || padding_row);
+        let height = input
+            .program
+            .trace_height(&MachineAir::<F>::name(self), rows.len());
+        rows.resize(height, padding_row);
+
+        RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_POSEIDON2_COLS,
+        )
+    }
+
+    fn num_rows(&self, input: &ExecutionRecord<F>) -> usize {
+        input.poseidon2_events.len()
+    }
+
+    fn included(&self, shard: &Self::Record) -> bool {
+        !shard.poseidon2_events.is_empty()
+            || shard
+                .program
+                .has_min_trace_height(&MachineAir::<F>::name(self))
+    }
+}
+
+/// Fills in the round columns of the permutation of `input`, returning the output state.
+fn populate_rounds<F: PrimeField32>(cols: &mut Poseidon2Cols<F>, input: [F; WIDTH]) -> [F; WIDTH] {
+    let mut state = input;
+    external_linear_layer(&mut state);
+    for round in 0..NUM_ROUNDS {
+        cols.round_states[round] = state;
+        let sboxes = apply_round(round, &mut state);
+        if is_full_round(round) {
+            let full_round = full_round_index(round);
+            for (i, (cube, out)) in sboxes.into_iter().enumerate() {
+                cols.full_sbox_cube[full_round][i] = cube;
+                cols.full_sbox_out[full_round][i] = out;
+            }
+        } else {
+            let (cube, out) = sboxes[0];
+            cols.partial_sbox_cube[round - PARTIAL_ROUNDS_START] = cube;
+            cols.partial_sbox_out[round - PARTIAL_ROUNDS_START] = out;
+        }
+    }
+    state
+}
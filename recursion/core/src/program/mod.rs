@@ -1,10 +1,13 @@
-use crate::{cpu::columns::InstructionCols, runtime::ExecutionRecord};
+use crate::{
+    cpu::columns::InstructionCols,
+    runtime::{ExecutionRecord, Program},
+};
 use core::mem::size_of;
 use hashbrown::HashMap;
-use p3_air::{Air, BaseAir};
+use p3_air::{Air, BaseAir, PairBuilder};
 use p3_field::PrimeField32;
 use p3_matrix::dense::RowMajorMatrix;
-use p3_matrix::MatrixRowSlices;
+use p3_matrix::{Matrix, MatrixRowSlices};
 use sp1_core::lookup::InteractionKind;
 use sp1_core::{
     air::{AirInteraction, MachineAir, SP1AirBuilder},
@@ -14,16 +17,24 @@ use sp1_derive::AlignedBorrow;
 use std::borrow::Borrow;
 use std::borrow::BorrowMut;
 
-pub const NUM_PROGRAM_COLS: usize = size_of::<ProgramCols<u8>>();
+pub const NUM_PROGRAM_PREPROCESSED_COLS: usize = size_of::<ProgramPreprocessedCols<u8>>();
+pub const NUM_PROGRAM_MULT_COLS: usize = size_of::<ProgramMultiplicityCols<u8>>();
 
 #[derive(Default)]
 pub struct ProgramChip;
 
+/// The preprocessed columns of the program table, which the verifying key commits to.
 #[derive(AlignedBorrow, Clone, Copy, Debug, Default)]
 #[repr(C)]
-pub struct ProgramCols<T> {
+pub struct ProgramPreprocessedCols<T> {
     pub pc: T,
     pub instruction: InstructionCols<T>,
+}
+
+/// The main columns of the program table: the number of times each instruction is executed.
+#[derive(AlignedBorrow, Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct ProgramMultiplicityCols<T> {
     pub multiplicity: T,
 }
 
@@ -34,6 +45,45 @@ impl<F: PrimeField32> MachineAir<F> for ProgramChip {
         "Program".to_string()
     }
 
+    fn preprocessed_width(&self) -> usize {
+        NUM_PROGRAM_PREPROCESSED_COLS
+    }
+
+    fn generate_preprocessed_trace(&self, program: &Program<F>) -> Option<RowMajorMatrix<F>> {
+        let rows = program
+            .instructions
+            .iter()
+            .enumerate()
+            .map(|(i, instruction)| {
+                let mut row = [F::zero(); NUM_PROGRAM_PREPROCESSED_COLS];
+                let cols: &mut ProgramPreprocessedCols<F> = row.as_mut_slice().borrow_mut();
+                cols.pc = F::from_canonical_usize(i);
+                cols.instruction.opcode = F::from_canonical_u32(instruction.opcode as u32);
+                cols.instruction.op_a = instruction.op_a;
+                cols.instruction.op_b = instruction.op_b;
+                cols.instruction.op_c = instruction.op_c;
+                cols.instruction.imm_b = F::from_bool(instruction.imm_b);
+                cols.instruction.imm_c = F::from_bool(instruction.imm_c);
+                row
+            })
+            .collect::<Vec<_>>();
+
+        // Convert the trace to a row major matrix.
+        let mut trace = RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_PROGRAM_PREPROCESSED_COLS,
+        );
+
+        // Pad the trace to a power of two, and to the minimum height of the program.
+        pad_to_power_of_two::<NUM_PROGRAM_PREPROCESSED_COLS, F>(&mut trace.values);
+        let height = program.trace_height(&MachineAir::<F>::name(self), trace.height());
+        trace
+            .values
+            .resize(height * NUM_PROGRAM_PREPROCESSED_COLS, F::zero());
+
+        Some(trace)
+    }
+
     fn generate_trace(
         &self,
         input: &ExecutionRecord<F>,
@@ -47,25 +97,13 @@ impl<F: PrimeField32> MachineAir<F> for ProgramChip {
                 .and_modify(|count| *count += 1)
                 .or_insert(1);
         });
-        let rows = input
-            .program
-            .instructions
-            .clone()
-            .into_iter()
-            .enumerate()
-            .map(|(i, instruction)| {
-                let pc = F::from_canonical_u32(i as u32);
-                let mut row = [F::zero(); NUM_PROGRAM_COLS];
-                let cols: &mut ProgramCols<F> = row.as_mut_slice().borrow_mut();
-                cols.pc = pc;
-                cols.instruction.opcode = F::from_canonical_u32(instruction.opcode as u32);
-                cols.instruction.op_a = instruction.op_a;
-                cols.instruction.op_b = instruction.op_b;
-                cols.instruction.op_c = instruction.op_c;
-                cols.instruction.imm_b = F::from_bool(instruction.imm_b);
-                cols.instruction.imm_c = F::from_bool(instruction.imm_c);
+        let rows = (0..input.program.instructions.len())
+            .map(|i| {
+                let pc = F::from_canonical_usize(i);
+                let mut row = [F::zero(); NUM_PROGRAM_MULT_COLS];
+                let cols: &mut ProgramMultiplicityCols<F> = row.as_mut_slice().borrow_mut();
                 cols.multiplicity =
-                    F::from_canonical_usize(*instruction_counts.get(&cols.pc).unwrap_or(&0));
+                    F::from_canonical_usize(*instruction_counts.get(&pc).unwrap_or(&0));
                 row
             })
             .collect::<Vec<_>>();
@@ -73,15 +111,25 @@ impl<F: PrimeField32> MachineAir<F> for ProgramChip {
         // Convert the trace to a row major matrix.
         let mut trace = RowMajorMatrix::new(
             rows.into_iter().flatten().collect::<Vec<_>>(),
-            NUM_PROGRAM_COLS,
+            NUM_PROGRAM_MULT_COLS,
         );
 
-        // Pad the trace to a power of two.
-        pad_to_power_of_two::<NUM_PROGRAM_COLS, F>(&mut trace.values);
+        // Pad the trace as the preprocessed trace.
+        pad_to_power_of_two::<NUM_PROGRAM_MULT_COLS, F>(&mut trace.values);
+        let height = input
+            .program
+            .trace_height(&MachineAir::<F>::name(self), trace.height());
+        trace
+            .values
+            .resize(height * NUM_PROGRAM_MULT_COLS, F::zero());
 
         trace
     }
 
+    fn num_rows(&self, input: &ExecutionRecord<F>) -> usize {
+        input.program.instructions.len()
+    }
+
     fn included(&self, _: &Self::Record) -> bool {
         true
     }
@@ -89,33 +137,36 @@ impl<F: PrimeField32> MachineAir<F> for ProgramChip {
 
 impl<F> BaseAir<F> for ProgramChip {
     fn width(&self) -> usize {
-        NUM_PROGRAM_COLS
+        NUM_PROGRAM_MULT_COLS
     }
 }
 
 impl<AB> Air<AB> for ProgramChip
 where
-    AB: SP1AirBuilder,
+    AB: SP1AirBuilder + PairBuilder,
 {
     fn eval(&self, builder: &mut AB) {
+        let preprocessed = builder.preprocessed();
+        let prep_local: &ProgramPreprocessedCols<AB::Var> = preprocessed.row_slice(0).borrow();
         let main = builder.main();
-        let local: &ProgramCols<AB::Var> = main.row_slice(0).borrow();
+        let mult_local: &ProgramMultiplicityCols<AB::Var> = main.row_slice(0).borrow();
 
         // Dummy constraint of degree 3.
         builder.assert_eq(
-            local.pc * local.pc * local.pc,
-            local.pc * local.pc * local.pc,
+            prep_local.pc * prep_local.pc * prep_local.pc,
+            prep_local.pc * prep_local.pc * prep_local.pc,
         );
 
-        let mut interaction_vals: Vec<AB::Expr> = vec![local.instruction.opcode.into()];
-        interaction_vals.push(local.instruction.op_a.into());
-        interaction_vals.extend_from_slice(&local.instruction.op_b.map(|x| x.into()).0);
-        interaction_vals.extend_from_slice(&local.instruction.op_c.map(|x| x.into()).0);
-        interaction_vals.push(local.instruction.imm_b.into());
-        interaction_vals.push(local.instruction.imm_c.into());
+        let mut interaction_vals: Vec<AB::Expr> =
+            vec![prep_local.pc.into(), prep_local.instruction.opcode.into()];
+        interaction_vals.push(prep_local.instruction.op_a.into());
+        interaction_vals.extend_from_slice(&prep_local.instruction.op_b.map(|x| x.into()).0);
+        interaction_vals.extend_from_slice(&prep_local.instruction.op_c.map(|x| x.into()).0);
+        interaction_vals.push(prep_local.instruction.imm_b.into());
+        interaction_vals.push(prep_local.instruction.imm_c.into());
         builder.receive(AirInteraction::new(
             interaction_vals,
-            local.multiplicity.into(),
+            mult_local.multiplicity.into(),
             InteractionKind::Program,
         ));
     }
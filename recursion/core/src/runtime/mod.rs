@@ -3,7 +3,7 @@ mod opcode;
 mod program;
 mod record;
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{collections::VecDeque, marker::PhantomData, sync::Arc};
 
 pub use instruction::*;
 pub use opcode::*;
@@ -13,12 +13,17 @@ pub use record::*;
 use crate::air::Block;
 use crate::cpu::CpuEvent;
 use crate::memory::MemoryRecord;
+use crate::num2bits::{Num2BitsEvent, NUM_BITS};
+use crate::poseidon2::{permute, Poseidon2Event, WIDTH};
 
 use p3_field::{ExtensionField, PrimeField32};
 use sp1_core::runtime::MemoryAccessPosition;
 
-pub(crate) const STACK_SIZE: usize = 1024;
-pub(crate) const MEMORY_SIZE: usize = 1024 * 1024;
+pub(crate) const STACK_SIZE: usize = 1 << 20;
+pub(crate) const MEMORY_SIZE: usize = 1 << 21;
+
+/// The first address of the heap, which starts right above the stack frame of the main program.
+pub const HEAP_START_ADDRESS: usize = STACK_SIZE + 4;
 
 pub const D: usize = 4;
 
@@ -57,6 +62,9 @@ pub struct Runtime<F: PrimeField32, EF: ExtensionField<F>> {
     /// The access record for this cycle.
     pub access: CpuRecord<F>,
 
+    /// The values supplied by the host, read in order by `HINT` instructions.
+    pub witness_stream: VecDeque<Block<F>>,
+
     _marker: PhantomData<EF>,
 }
 
@@ -74,10 +82,32 @@ impl<F: PrimeField32, EF: ExtensionField<F>> Runtime<F, EF> {
             memory: vec![MemoryEntry::default(); MEMORY_SIZE],
             record,
             access: CpuRecord::default(),
+            witness_stream: VecDeque::new(),
             _marker: PhantomData,
         }
     }
 
+    /// Appends a vector of field elements to the witness stream, as its length followed by its
+    /// elements, so that the program can read it with `Builder::hint_vec`.
+    pub fn write_felts(&mut self, values: &[F]) {
+        self.witness_stream
+            .push_back(Block::from(F::from_canonical_usize(values.len())));
+        self.witness_stream
+            .extend(values.iter().map(|value| Block::from(*value)));
+    }
+
+    /// Appends a vector of extension elements to the witness stream, as its length followed by its
+    /// elements, so that the program can read it with `Builder::hint_vec`.
+    pub fn write_exts(&mut self, values: &[EF]) {
+        self.witness_stream
+            .push_back(Block::from(F::from_canonical_usize(values.len())));
+        self.witness_stream.extend(
+            values
+                .iter()
+                .map(|value| Block::from(value.as_base_slice())),
+        );
+    }
+
     fn mr(&mut self, addr: F, position: MemoryAccessPosition) -> Block<F> {
         let addr_usize = addr.as_canonical_u32() as usize;
         let entry = self.memory[addr.as_canonical_u32() as usize].clone();
@@ -123,6 +153,34 @@ impl<F: PrimeField32, EF: ExtensionField<F>> Runtime<F, EF> {
         };
     }
 
+    /// Read a memory cell at an explicit timestamp, for accesses made by a chip other than the CPU.
+    fn mr_at(&mut self, addr: F, timestamp: F) -> MemoryRecord<F> {
+        let entry = &mut self.memory[addr.as_canonical_u32() as usize];
+        let record = MemoryRecord {
+            addr,
+            value: entry.value,
+            timestamp,
+            prev_value: entry.value,
+            prev_timestamp: entry.timestamp,
+        };
+        entry.timestamp = timestamp;
+        record
+    }
+
+    /// Write a memory cell at an explicit timestamp, for accesses made by a chip other than the CPU.
+    fn mw_at(&mut self, addr: F, value: Block<F>, timestamp: F) -> MemoryRecord<F> {
+        let entry = &mut self.memory[addr.as_canonical_u32() as usize];
+        let record = MemoryRecord {
+            addr,
+            value,
+            timestamp,
+            prev_value: entry.value,
+            prev_timestamp: entry.timestamp,
+        };
+        *entry = MemoryEntry { value, timestamp };
+        record
+    }
+
     fn timestamp(&self, position: &MemoryAccessPosition) -> F {
         self.clk + F::from_canonical_u32(*position as u32)
     }
@@ -273,6 +331,28 @@ impl<F: PrimeField32, EF: ExtensionField<F>> Runtime<F, EF> {
                     self.mw(a_ptr, a_val, MemoryAccessPosition::A);
                     (a, b, c) = (a_val, b_val, Block::default());
                 }
+                Opcode::LOAD => {
+                    // The pointer is read from b(fp) and the value at `ptr + offset` is copied
+                    // into a(fp).
+                    let b_val = self.mr(self.fp + instruction.op_b[0], MemoryAccessPosition::B);
+                    let c_val = self.mr(b_val.0[0] + instruction.op_c[0], MemoryAccessPosition::C);
+                    let a_val = c_val;
+                    self.mw(self.fp + instruction.op_a, a_val, MemoryAccessPosition::A);
+                    (a, b, c) = (a_val, b_val, c_val);
+                }
+                Opcode::STORE => {
+                    // The pointer is read from a(fp) and the value in b(fp) is copied to
+                    // `ptr + offset`.
+                    let c_val = self.mr(self.fp + instruction.op_a, MemoryAccessPosition::C);
+                    let b_val = self.mr(self.fp + instruction.op_b[0], MemoryAccessPosition::B);
+                    let a_val = b_val;
+                    self.mw(
+                        c_val.0[0] + instruction.op_c[0],
+                        a_val,
+                        MemoryAccessPosition::A,
+                    );
+                    (a, b, c) = (a_val, b_val, c_val);
+                }
                 Opcode::BEQ => {
                     let (a_val, b_val, c_offset) = self.branch_rr(&instruction);
                     (a, b, c) = (a_val, b_val, Block::from(c_offset));
@@ -304,26 +384,102 @@ impl<F: PrimeField32, EF: ExtensionField<F>> Runtime<F, EF> {
                 Opcode::JAL => {
                     let imm = instruction.op_b[0];
                     let a_ptr = instruction.op_a + self.fp;
-                    self.mw(a_ptr, Block::from(self.pc), MemoryAccessPosition::A);
+                    // Store the return address, so that a function call can return with JALR.
+                    self.mw(
+                        a_ptr,
+                        Block::from(self.pc + F::one()),
+                        MemoryAccessPosition::A,
+                    );
                     next_pc = self.pc + imm;
                     self.fp += instruction.op_c[0];
                     (a, b, c) = (Block::from(a_ptr), Block::default(), Block::default());
                 }
                 Opcode::JALR => {
-                    let imm = instruction.op_c;
                     let b_ptr = instruction.op_b[0] + self.fp;
+                    let c_ptr = instruction.op_c[0] + self.fp;
                     let a_ptr = instruction.op_a + self.fp;
                     let b_val = self.mr(b_ptr, MemoryAccessPosition::B);
-                    let c_val = imm;
+                    let c_val = self.mr(c_ptr, MemoryAccessPosition::C);
                     let a_val = Block::from(self.pc + F::one());
                     self.mw(a_ptr, a_val, MemoryAccessPosition::A);
                     next_pc = b_val.0[0];
-                    self.fp = c_val[0];
+                    self.fp += c_val[0];
                     (a, b, c) = (a_val, b_val, c_val);
                 }
                 Opcode::TRAP => {
                     panic!("TRAP instruction encountered")
                 }
+                Opcode::POSEIDON2_PERM => {
+                    // The operands hold the destination and source pointers. The Poseidon2 chip
+                    // reads the state at `clk` and writes the permuted state at `clk + 1`.
+                    let b_val = self.mr(self.fp + instruction.op_b[0], MemoryAccessPosition::B);
+                    let a_val = self.mr(self.fp + instruction.op_a, MemoryAccessPosition::A);
+                    let (dst, src) = (a_val.0[0], b_val.0[0]);
+                    let clk = self.clk;
+
+                    let input_records: [MemoryRecord<F>; WIDTH] =
+                        core::array::from_fn(|i| self.mr_at(src + F::from_canonical_usize(i), clk));
+                    let input = core::array::from_fn(|i| input_records[i].value.0[0]);
+                    let output = permute(input);
+                    let output_records: [MemoryRecord<F>; WIDTH] = core::array::from_fn(|i| {
+                        self.mw_at(
+                            dst + F::from_canonical_usize(i),
+                            Block::from(output[i]),
+                            clk + F::one(),
+                        )
+                    });
+
+                    self.record.poseidon2_events.push(Poseidon2Event {
+                        clk,
+                        dst,
+                        src,
+                        input,
+                        output,
+                        input_records,
+                        output_records,
+                    });
+                    (a, b, c) = (a_val, b_val, instruction.op_c);
+                }
+                Opcode::NUM2BITS => {
+                    // The operands hold the destination pointer and the value. The NUM2BITS chip
+                    // writes the little-endian bits of the value at `clk + 1`.
+                    let b_val = self.mr(self.fp + instruction.op_b[0], MemoryAccessPosition::B);
+                    let a_val = self.mr(self.fp + instruction.op_a, MemoryAccessPosition::A);
+                    let (dst, value) = (a_val.0[0], b_val.0[0]);
+                    let clk = self.clk;
+
+                    let value_u32 = value.as_canonical_u32();
+                    let bit_records: [MemoryRecord<F>; NUM_BITS] = core::array::from_fn(|i| {
+                        self.mw_at(
+                            dst + F::from_canonical_usize(i),
+                            Block::from(F::from_canonical_u32((value_u32 >> i) & 1)),
+                            clk + F::one(),
+                        )
+                    });
+
+                    self.record.num2bits_events.push(Num2BitsEvent {
+                        clk,
+                        dst,
+                        value,
+                        bit_records,
+                    });
+                    (a, b, c) = (a_val, b_val, instruction.op_c);
+                }
+                Opcode::HINT => {
+                    // The value is supplied by the host and is not constrained by the CPU.
+                    let a_val = self
+                        .witness_stream
+                        .pop_front()
+                        .expect("the witness stream is empty");
+                    self.mw(self.fp + instruction.op_a, a_val, MemoryAccessPosition::A);
+                    (a, b, c) = (a_val, instruction.op_b, instruction.op_c);
+                }
+                Opcode::COMMIT => {
+                    // The value is exposed to the verifier as the next public value.
+                    let a_val = self.mr(self.fp + instruction.op_a, MemoryAccessPosition::A);
+                    self.record.public_values.push(a_val.0[0]);
+                    (a, b, c) = (a_val, instruction.op_b, instruction.op_c);
+                }
             };
 
             let event = CpuEvent {
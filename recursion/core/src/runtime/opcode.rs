@@ -16,6 +16,8 @@ pub enum Opcode {
     // Memory instructions.
     LW = 4,
     SW = 5,
+    LOAD = 18,
+    STORE = 19,
 
     // Branch instructions.
     BEQ = 6,
@@ -29,4 +31,16 @@ pub enum Opcode {
 
     // System instructions.
     TRAP = 10,
+
+    // Hash instructions.
+    POSEIDON2_PERM = 17,
+
+    // Hint instructions.
+    HINT = 20,
+
+    // Bit instructions.
+    NUM2BITS = 21,
+
+    // Public value instructions.
+    COMMIT = 22,
 }
@@ -1,7 +1,33 @@
+use std::collections::BTreeMap;
+
 use super::Instruction;
 
 #[derive(Debug, Clone, Default)]
 pub struct Program<F> {
     /// The instructions of the program.
     pub instructions: Vec<Instruction<F>>,
+    /// The log2 of the minimum height of the trace of each chip, by chip name.
+    ///
+    /// The chips named here are included in the proof even if they have no events, so that the
+    /// proofs of all the programs with the same minimum heights, which fit in them, have the
+    /// same shape.
+    pub min_trace_log_heights: BTreeMap<String, usize>,
+}
+
+impl<F> Program<F> {
+    /// The height of the trace of the chip named `chip`, given its height once padded to a power
+    /// of two.
+    pub fn trace_height(&self, chip: &str, padded_height: usize) -> usize {
+        self.min_trace_log_heights
+            .get(chip)
+            .map_or(padded_height, |log_height| {
+                padded_height.max(1 << log_height)
+            })
+    }
+
+    /// Whether the chip named `chip` is included in the proof of the program regardless of its
+    /// events.
+    pub fn has_min_trace_height(&self, chip: &str) -> bool {
+        self.min_trace_log_heights.contains_key(chip)
+    }
 }
@@ -1,27 +1,35 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
-use hashbrown::HashMap;
-use p3_field::PrimeField32;
+use p3_field::{AbstractField, PrimeField32};
 use sp1_core::stark::MachineRecord;
 
 use super::Program;
 use crate::air::Block;
 use crate::cpu::CpuEvent;
+use crate::num2bits::Num2BitsEvent;
+use crate::poseidon2::Poseidon2Event;
 
 #[derive(Default, Debug, Clone)]
 pub struct ExecutionRecord<F: Default> {
     pub program: Arc<Program<F>>,
     pub cpu_events: Vec<CpuEvent<F>>,
+    pub poseidon2_events: Vec<Poseidon2Event<F>>,
+    pub num2bits_events: Vec<Num2BitsEvent<F>>,
 
     // (address)
     pub first_memory_record: Vec<F>,
 
     // (address, last_timestamp, last_value)
     pub last_memory_record: Vec<(F, F, Block<F>)>,
+
+    /// The values committed by `COMMIT` instructions, in order.
+    pub public_values: Vec<F>,
 }
 
 impl<F: PrimeField32> MachineRecord for ExecutionRecord<F> {
     type Config = ();
+    type Program = Program<F>;
 
     fn index(&self) -> u32 {
         0
@@ -29,8 +37,8 @@ impl<F: PrimeField32> MachineRecord for ExecutionRecord<F> {
 
     fn set_index(&mut self, _: u32) {}
 
-    fn stats(&self) -> HashMap<String, usize> {
-        HashMap::new()
+    fn stats(&self) -> BTreeMap<String, usize> {
+        BTreeMap::new()
     }
 
     fn append(&mut self, _: &mut Self) {}
@@ -38,4 +46,11 @@ impl<F: PrimeField32> MachineRecord for ExecutionRecord<F> {
     fn shard(self, _: &Self::Config) -> Vec<Self> {
         vec![self]
     }
+
+    fn public_values<F2: AbstractField>(&self) -> Vec<F2> {
+        self.public_values
+            .iter()
+            .map(|value| F2::from_canonical_u32(value.as_canonical_u32()))
+            .collect()
+    }
 }
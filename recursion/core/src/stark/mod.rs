@@ -1,6 +1,8 @@
 use crate::{
     cpu::CpuChip,
     memory::{MemoryChipKind, MemoryGlobalChip},
+    num2bits::Num2BitsChip,
+    poseidon2::Poseidon2Chip,
     program::ProgramChip,
 };
 use p3_field::PrimeField32;
@@ -15,6 +17,8 @@ pub enum RecursionAir<F: PrimeField32> {
     Cpu(CpuChip<F>),
     MemoryInit(MemoryGlobalChip),
     MemoryFinalize(MemoryGlobalChip),
+    Poseidon2(Poseidon2Chip),
+    Num2Bits(Num2BitsChip),
 }
 
 #[allow(dead_code)]
@@ -41,6 +45,10 @@ impl<F: PrimeField32> RecursionAir<F> {
             kind: MemoryChipKind::Finalize,
         };
         chips.push(RecursionAir::MemoryFinalize(memory_finalize));
+        let poseidon2 = Poseidon2Chip;
+        chips.push(RecursionAir::Poseidon2(poseidon2));
+        let num2bits = Num2BitsChip;
+        chips.push(RecursionAir::Num2Bits(num2bits));
         chips
     }
 }
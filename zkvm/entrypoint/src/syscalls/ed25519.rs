@@ -47,3 +47,24 @@ pub extern "C" fn syscall_ed_decompress(point: &mut [u8; 64]) {
     #[cfg(not(target_os = "zkvm"))]
     unreachable!()
 }
+
+/// Computes `a * G + b * P` for the Edwards point `P` and the generator `G`.
+///
+/// The scalars are given as 16 little-endian words, `a` followed by `b`. The result is stored in
+/// `p`.
+#[allow(unused_variables)]
+#[no_mangle]
+pub extern "C" fn syscall_ed_double_scalar_mul(p: *mut u32, scalars: *const u32) {
+    #[cfg(target_os = "zkvm")]
+    unsafe {
+        asm!(
+            "ecall",
+            in("t0") crate::syscalls::ED_DOUBLE_SCALAR_MUL,
+            in("a0") p,
+            in("a1") scalars
+        );
+    }
+
+    #[cfg(not(target_os = "zkvm"))]
+    unreachable!()
+}
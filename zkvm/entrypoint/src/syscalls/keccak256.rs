@@ -0,0 +1,23 @@
+#[cfg(target_os = "zkvm")]
+use core::arch::asm;
+
+/// Hashes `len` bytes starting at `input` with Keccak-256 and writes the digest to `output`.
+///
+/// Both pointers must be word aligned.
+#[allow(unused_variables)]
+#[no_mangle]
+pub extern "C" fn syscall_keccak256(input: *const u32, len: usize, output: *mut u32) {
+    #[cfg(target_os = "zkvm")]
+    unsafe {
+        asm!(
+            "ecall",
+            in("t0") crate::syscalls::KECCAK256,
+            in("a0") input,
+            in("a1") len,
+            in("a2") output
+        );
+    }
+
+    #[cfg(not(target_os = "zkvm"))]
+    unreachable!()
+}
@@ -1,22 +1,32 @@
 mod blake3_compress;
+mod blake3_hash;
 mod ed25519;
 mod halt;
 mod io;
+mod keccak256;
 mod keccak_permute;
 mod memory;
 mod secp256k1;
+mod sha512_compress;
+mod sha512_extend;
 mod sha_compress;
+mod sha_compress_blocks;
 mod sha_extend;
 mod sys;
 mod unconstrained;
 
+pub use blake3_hash::*;
 pub use ed25519::*;
 pub use halt::*;
 pub use io::*;
+pub use keccak256::*;
 pub use keccak_permute::*;
 pub use memory::*;
 pub use secp256k1::*;
+pub use sha512_compress::*;
+pub use sha512_extend::*;
 pub use sha_compress::*;
+pub use sha_compress_blocks::*;
 pub use sha_extend::*;
 pub use sys::*;
 pub use unconstrained::*;
@@ -60,5 +70,23 @@ pub const EXIT_UNCONSTRAINED: u32 = 111;
 /// Executes `BLAKE3_COMPRESS_INNER`.
 pub const BLAKE3_COMPRESS_INNER: u32 = 112;
 
+/// Executes `SHA_COMPRESS_BLOCKS`.
+pub const SHA_COMPRESS_BLOCKS: u32 = 113;
+
+/// Executes `SHA512_EXTEND`.
+pub const SHA512_EXTEND: u32 = 114;
+
+/// Executes `SHA512_COMPRESS`.
+pub const SHA512_COMPRESS: u32 = 115;
+
+/// Executes `KECCAK256`.
+pub const KECCAK256: u32 = 116;
+
+/// Executes `BLAKE3_HASH`.
+pub const BLAKE3_HASH: u32 = 117;
+
+/// Executes `ED_DOUBLE_SCALAR_MUL`.
+pub const ED_DOUBLE_SCALAR_MUL: u32 = 118;
+
 /// Writes to a file descriptor. Currently only used for `STDOUT/STDERR`.
 pub const WRITE: u32 = 999;
@@ -0,0 +1,20 @@
+#[cfg(target_os = "zkvm")]
+use core::arch::asm;
+
+/// Extends the 16 message words at the start of `w` into the full 80-word SHA-512 message
+/// schedule.
+#[allow(unused_variables)]
+#[no_mangle]
+pub extern "C" fn syscall_sha512_extend(w: *mut u64) {
+    #[cfg(target_os = "zkvm")]
+    unsafe {
+        asm!(
+            "ecall",
+            in("t0") crate::syscalls::SHA512_EXTEND,
+            in("a0") w
+        );
+    }
+
+    #[cfg(not(target_os = "zkvm"))]
+    unreachable!()
+}
@@ -0,0 +1,28 @@
+#[cfg(target_os = "zkvm")]
+use core::arch::asm;
+
+/// Compresses `num_blocks` consecutive 64-byte message blocks into the SHA-256 `state`.
+///
+/// The blocks are read as raw message bytes, so no endianness conversion is needed by the caller.
+/// Both pointers must be word aligned.
+#[allow(unused_variables)]
+#[no_mangle]
+pub extern "C" fn syscall_sha256_compress_blocks(
+    state: *mut u32,
+    blocks: *const u32,
+    num_blocks: usize,
+) {
+    #[cfg(target_os = "zkvm")]
+    unsafe {
+        asm!(
+            "ecall",
+            in("t0") crate::syscalls::SHA_COMPRESS_BLOCKS,
+            in("a0") state,
+            in("a1") blocks,
+            in("a2") num_blocks
+        );
+    }
+
+    #[cfg(not(target_os = "zkvm"))]
+    unreachable!()
+}
@@ -0,0 +1,144 @@
+#![allow(unused_unsafe)]
+
+/// The number of bytes in a block.
+#[allow(dead_code)]
+const BLOCK_LEN: usize = 64;
+
+/// The number of bytes in a chunk.
+#[allow(dead_code)]
+const CHUNK_LEN: usize = 1024;
+
+#[allow(dead_code)]
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A, 0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
+];
+
+#[allow(dead_code)]
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+#[allow(dead_code)]
+const CHUNK_START: u32 = 1 << 0;
+#[allow(dead_code)]
+const CHUNK_END: u32 = 1 << 1;
+#[allow(dead_code)]
+const PARENT: u32 = 1 << 2;
+#[allow(dead_code)]
+const ROOT: u32 = 1 << 3;
+
+/// Computes the BLAKE3 digest of `input`.
+///
+/// Inside the VM, the whole message tree is hashed with a single call to the `BLAKE3_HASH`
+/// precompile.
+pub fn blake3(input: &[u8]) -> [u8; 32] {
+    cfg_if::cfg_if! {
+        if #[cfg(all(target_os = "zkvm", target_vendor = "succinct"))] {
+            // The precompile reads the input as words, so copy it into an aligned buffer.
+            let mut words = vec![0u32; input.len().div_ceil(4)];
+            for (word, chunk) in words.iter_mut().zip(input.chunks(4)) {
+                let mut bytes = [0u8; 4];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                *word = u32::from_le_bytes(bytes);
+            }
+            let mut digest_words = [0u32; 8];
+            unsafe {
+                crate::syscall_blake3_hash(words.as_ptr(), input.len(), digest_words.as_mut_ptr());
+            }
+            to_bytes(&digest_words)
+        } else {
+            let num_chunks = input.len().div_ceil(CHUNK_LEN).max(1);
+            to_bytes(&node(input, 0, num_chunks, true))
+        }
+    }
+}
+
+/// Serializes the digest words as little-endian bytes.
+fn to_bytes(digest_words: &[u32; 8]) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(digest_words.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+/// The chaining value of the node covering `len` chunks starting at chunk `start`, used outside
+/// of the VM.
+#[allow(dead_code)]
+fn node(input: &[u8], start: usize, len: usize, is_root: bool) -> [u32; 8] {
+    let root = if is_root { ROOT } else { 0 };
+    if len == 1 {
+        let chunk = &input[(start * CHUNK_LEN).min(input.len())..]
+            [..input.len().saturating_sub(start * CHUNK_LEN).min(CHUNK_LEN)];
+        let num_blocks = chunk.len().div_ceil(BLOCK_LEN).max(1);
+        let mut cv = IV;
+        for i in 0..num_blocks {
+            let block = &chunk[(i * BLOCK_LEN).min(chunk.len())..]
+                [..chunk.len().saturating_sub(i * BLOCK_LEN).min(BLOCK_LEN)];
+            let mut flags = if i == 0 { CHUNK_START } else { 0 };
+            if i == num_blocks - 1 {
+                flags |= CHUNK_END | root;
+            }
+            cv = compress(&cv, &words(block), start as u32, block.len() as u32, flags);
+        }
+        cv
+    } else {
+        let left_len = 1 << (usize::BITS - 1 - (len - 1).leading_zeros());
+        let left = node(input, start, left_len, false);
+        let right = node(input, start + left_len, len - left_len, false);
+        let mut msg = [0u32; 16];
+        msg[..8].copy_from_slice(&left);
+        msg[8..].copy_from_slice(&right);
+        compress(&IV, &msg, 0, BLOCK_LEN as u32, PARENT | root)
+    }
+}
+
+/// Reads a block as little-endian words, padded with zeros.
+#[allow(dead_code)]
+fn words(block: &[u8]) -> [u32; 16] {
+    let mut msg = [0u32; 16];
+    for (word, chunk) in msg.iter_mut().zip(block.chunks(4)) {
+        let mut bytes = [0u8; 4];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        *word = u32::from_le_bytes(bytes);
+    }
+    msg
+}
+
+/// The BLAKE3 compression function truncated to the chaining value, used outside of the VM.
+#[allow(dead_code)]
+fn compress(cv: &[u32; 8], msg: &[u32; 16], counter: u32, block_len: u32, flags: u32) -> [u32; 8] {
+    let mut state = [0u32; 16];
+    state[..8].copy_from_slice(cv);
+    state[8..12].copy_from_slice(&IV[..4]);
+    state[12] = counter;
+    state[14] = block_len;
+    state[15] = flags;
+
+    let mut msg = *msg;
+    for round in 0..7 {
+        g(&mut state, 0, 4, 8, 12, msg[0], msg[1]);
+        g(&mut state, 1, 5, 9, 13, msg[2], msg[3]);
+        g(&mut state, 2, 6, 10, 14, msg[4], msg[5]);
+        g(&mut state, 3, 7, 11, 15, msg[6], msg[7]);
+        g(&mut state, 0, 5, 10, 15, msg[8], msg[9]);
+        g(&mut state, 1, 6, 11, 12, msg[10], msg[11]);
+        g(&mut state, 2, 7, 8, 13, msg[12], msg[13]);
+        g(&mut state, 3, 4, 9, 14, msg[14], msg[15]);
+        if round < 6 {
+            msg = core::array::from_fn(|i| msg[MSG_PERMUTATION[i]]);
+        }
+    }
+
+    core::array::from_fn(|i| state[i] ^ state[i + 8])
+}
+
+#[allow(dead_code)]
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, mx: u32, my: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(mx);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(my);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
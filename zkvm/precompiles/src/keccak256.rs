@@ -0,0 +1,130 @@
+#![allow(unused_unsafe)]
+
+/// The rate of Keccak-256 in bytes.
+#[allow(dead_code)]
+const RATE: usize = 136;
+
+/// The Keccak-f[1600] round constants.
+#[allow(dead_code)]
+const RC: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+#[allow(dead_code)]
+const RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+#[allow(dead_code)]
+const PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// Computes the Keccak-256 digest of `input`.
+///
+/// Inside the VM, the whole input is absorbed, padded and squeezed with a single call to the
+/// `KECCAK256` precompile.
+pub fn keccak256(input: &[u8]) -> [u8; 32] {
+    cfg_if::cfg_if! {
+        if #[cfg(all(target_os = "zkvm", target_vendor = "succinct"))] {
+            // The precompile reads the input as words, so copy it into an aligned buffer.
+            let mut words = vec![0u32; input.len().div_ceil(4)];
+            for (word, chunk) in words.iter_mut().zip(input.chunks(4)) {
+                let mut bytes = [0u8; 4];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                *word = u32::from_le_bytes(bytes);
+            }
+            let mut digest_words = [0u32; 8];
+            unsafe {
+                crate::syscall_keccak256(words.as_ptr(), input.len(), digest_words.as_mut_ptr());
+            }
+
+            let mut digest = [0u8; 32];
+            for (chunk, word) in digest.chunks_exact_mut(4).zip(digest_words.iter()) {
+                chunk.copy_from_slice(&word.to_le_bytes());
+            }
+            digest
+        } else {
+            let mut padded = input.to_vec();
+            padded.push(0x01);
+            padded.resize(padded.len().next_multiple_of(RATE), 0);
+            *padded.last_mut().unwrap() |= 0x80;
+
+            let mut state = [0u64; 25];
+            for block in padded.chunks_exact(RATE) {
+                for (lane, chunk) in state.iter_mut().zip(block.chunks_exact(8)) {
+                    *lane ^= u64::from_le_bytes(chunk.try_into().unwrap());
+                }
+                keccakf(&mut state);
+            }
+
+            let mut digest = [0u8; 32];
+            for (chunk, lane) in digest.chunks_exact_mut(8).zip(state.iter()) {
+                chunk.copy_from_slice(&lane.to_le_bytes());
+            }
+            digest
+        }
+    }
+}
+
+/// The Keccak-f[1600] permutation, used outside of the VM.
+#[allow(dead_code)]
+fn keccakf(state: &mut [u64; 25]) {
+    for rc in RC.iter() {
+        let mut array = [0u64; 5];
+
+        // Theta
+        for x in 0..5 {
+            for y in (0..25).step_by(5) {
+                array[x] ^= state[x + y];
+            }
+        }
+        for x in 0..5 {
+            for y in (0..25).step_by(5) {
+                state[y + x] ^= array[(x + 4) % 5] ^ array[(x + 1) % 5].rotate_left(1);
+            }
+        }
+
+        // Rho and pi
+        let mut last = state[1];
+        for x in 0..24 {
+            let tmp = state[PI[x]];
+            state[PI[x]] = last.rotate_left(RHO[x]);
+            last = tmp;
+        }
+
+        // Chi
+        for y in (0..25).step_by(5) {
+            array.copy_from_slice(&state[y..y + 5]);
+            for x in 0..5 {
+                state[y + x] = array[x] ^ ((!array[(x + 1) % 5]) & array[(x + 2) % 5]);
+            }
+        }
+
+        // Iota
+        state[0] ^= rc;
+    }
+}
@@ -1,5 +1,8 @@
+pub mod blake3;
 pub mod io;
+pub mod keccak256;
 pub mod secp256k1;
+pub mod sha256;
 pub mod unconstrained;
 
 extern "C" {
@@ -8,13 +11,19 @@ extern "C" {
     pub fn syscall_read(fd: u32, read_buf: *mut u8, nbytes: usize);
     pub fn syscall_sha256_extend(w: *mut u32);
     pub fn syscall_sha256_compress(w: *mut u32, state: *mut u32);
+    pub fn syscall_sha256_compress_blocks(state: *mut u32, blocks: *const u32, num_blocks: usize);
+    pub fn syscall_sha512_extend(w: *mut u64);
+    pub fn syscall_sha512_compress(w: *const u64, state: *mut u64);
     pub fn syscall_ed_add(p: *mut u32, q: *mut u32);
     pub fn syscall_ed_decompress(point: &mut [u8; 64]);
+    pub fn syscall_ed_double_scalar_mul(p: *mut u32, scalars: *const u32);
     pub fn syscall_secp256k1_add(p: *mut u32, q: *const u32);
     pub fn syscall_secp256k1_double(p: *mut u32);
     pub fn syscall_secp256k1_decompress(point: &mut [u8; 64], is_odd: bool);
     pub fn syscall_keccak_permute(state: *mut u64);
+    pub fn syscall_keccak256(input: *const u32, len: usize, output: *mut u32);
     pub fn syscall_blake3_compress_inner(p: *mut u32, q: *const u32);
+    pub fn syscall_blake3_hash(input: *const u32, len: usize, output: *mut u32);
     pub fn syscall_enter_unconstrained() -> bool;
     pub fn syscall_exit_unconstrained();
     pub fn sys_alloc_aligned(bytes: usize, align: usize) -> *mut u8;
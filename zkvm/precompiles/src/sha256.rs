@@ -0,0 +1,202 @@
+#![allow(unused_unsafe)]
+
+/// The number of bytes in a SHA-256 message block.
+const BLOCK_LEN: usize = 64;
+
+/// The initial SHA-256 chaining value.
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Bytes aligned to a word, so that they can be passed to the precompile as message words.
+#[derive(Clone, Copy, Debug)]
+#[repr(C, align(4))]
+struct Aligned<const N: usize>([u8; N]);
+
+/// Computes the SHA-256 digest of `input`.
+///
+/// Inside the VM, the full blocks of `input` are compressed in place with a single call to the
+/// `SHA_COMPRESS_BLOCKS` precompile, and only the padded tail is copied.
+pub fn sha256(input: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// An incremental SHA-256 hasher.
+#[derive(Clone, Debug)]
+pub struct Sha256 {
+    state: [u32; 8],
+    buffer: Aligned<BLOCK_LEN>,
+    buffer_len: usize,
+    total_len: u64,
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sha256 {
+    pub const fn new() -> Self {
+        Self {
+            state: IV,
+            buffer: Aligned([0; BLOCK_LEN]),
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    /// Absorbs `input` into the hasher.
+    pub fn update(&mut self, mut input: &[u8]) {
+        self.total_len += input.len() as u64;
+
+        // Fill up the pending block first.
+        if self.buffer_len > 0 {
+            let take = (BLOCK_LEN - self.buffer_len).min(input.len());
+            self.buffer.0[self.buffer_len..self.buffer_len + take].copy_from_slice(&input[..take]);
+            self.buffer_len += take;
+            input = &input[take..];
+            if self.buffer_len < BLOCK_LEN {
+                return;
+            }
+            compress_blocks(&mut self.state, &self.buffer.0);
+            self.buffer_len = 0;
+        }
+
+        // Compress all of the full blocks at once.
+        let full_len = input.len() - input.len() % BLOCK_LEN;
+        if full_len > 0 {
+            compress_blocks(&mut self.state, &input[..full_len]);
+        }
+
+        let rest = &input[full_len..];
+        self.buffer.0[..rest.len()].copy_from_slice(rest);
+        self.buffer_len = rest.len();
+    }
+
+    /// Pads the message and returns the digest.
+    pub fn finalize(mut self) -> [u8; 32] {
+        let bit_len = self.total_len.wrapping_mul(8);
+
+        let mut tail = Aligned([0u8; 2 * BLOCK_LEN]);
+        tail.0[..self.buffer_len].copy_from_slice(&self.buffer.0[..self.buffer_len]);
+        tail.0[self.buffer_len] = 0x80;
+        let tail_len = if self.buffer_len + 9 <= BLOCK_LEN {
+            BLOCK_LEN
+        } else {
+            2 * BLOCK_LEN
+        };
+        tail.0[tail_len - 8..tail_len].copy_from_slice(&bit_len.to_be_bytes());
+        compress_blocks(&mut self.state, &tail.0[..tail_len]);
+
+        let mut digest = [0u8; 32];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+}
+
+/// Compresses the message blocks in `blocks` into `state`. The length of `blocks` must be a
+/// multiple of the block length.
+fn compress_blocks(state: &mut [u32; 8], blocks: &[u8]) {
+    debug_assert_eq!(blocks.len() % BLOCK_LEN, 0);
+    cfg_if::cfg_if! {
+        if #[cfg(all(target_os = "zkvm", target_vendor = "succinct"))] {
+            // The precompile reads the message as words laid out in memory, so a word-aligned
+            // message is passed as it is. An unaligned one is copied a block at a time.
+            if blocks.as_ptr() as usize % 4 == 0 {
+                unsafe {
+                    crate::syscall_sha256_compress_blocks(
+                        state.as_mut_ptr(),
+                        blocks.as_ptr() as *const u32,
+                        blocks.len() / BLOCK_LEN,
+                    );
+                }
+            } else {
+                let mut block = Aligned([0u8; BLOCK_LEN]);
+                for chunk in blocks.chunks_exact(BLOCK_LEN) {
+                    block.0.copy_from_slice(chunk);
+                    unsafe {
+                        crate::syscall_sha256_compress_blocks(
+                            state.as_mut_ptr(),
+                            block.0.as_ptr() as *const u32,
+                            1,
+                        );
+                    }
+                }
+            }
+        } else {
+            use sha2::digest::generic_array::GenericArray;
+            let blocks = blocks
+                .chunks_exact(BLOCK_LEN)
+                .map(GenericArray::clone_from_slice)
+                .collect::<Vec<_>>();
+            sha2::compress256(state, &blocks);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::Digest;
+
+    use super::{sha256, Sha256};
+
+    /// Message lengths around the padding boundaries: the padding and the length fit in the last
+    /// block up to 55 bytes, and take an extra block from 56 bytes on.
+    const LENGTHS: [usize; 13] = [0, 1, 54, 55, 56, 57, 63, 64, 65, 119, 120, 127, 128];
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + 3) as u8).collect()
+    }
+
+    #[test]
+    fn test_sha256() {
+        for len in LENGTHS {
+            let input = message(len);
+            assert_eq!(
+                sha256(&input).as_slice(),
+                sha2::Sha256::digest(&input).as_slice(),
+                "length {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_sha256_incremental() {
+        for len in LENGTHS {
+            let input = message(len);
+            let expected = sha2::Sha256::digest(&input);
+            for split in [0, 1, len / 2, len.saturating_sub(1), len] {
+                let mut hasher = Sha256::new();
+                hasher.update(&input[..split]);
+                hasher.update(&input[split..]);
+                assert_eq!(
+                    hasher.finalize().as_slice(),
+                    expected.as_slice(),
+                    "length {} split at {}",
+                    len,
+                    split
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sha256_unaligned() {
+        for len in LENGTHS {
+            let bytes = message(len + 1);
+            let input = &bytes[1..];
+            assert_eq!(
+                sha256(input).as_slice(),
+                sha2::Sha256::digest(input).as_slice(),
+                "length {}",
+                len
+            );
+        }
+    }
+}